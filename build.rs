@@ -0,0 +1,9 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the bundled protoc so building doesn't need one installed
+    if std::env::var_os("PROTOC").is_none() {
+        // SAFETY: build scripts are single-threaded
+        unsafe { std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?) };
+    }
+    tonic_prost_build::compile_protos("proto/pm3.proto")?;
+    Ok(())
+}
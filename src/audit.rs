@@ -0,0 +1,325 @@
+use crate::log;
+use crate::namespace::DEFAULT_NAMESPACE;
+use crate::paths::Paths;
+use crate::protocol::{Request, Response};
+use crate::sys::{self, PeerCredentials};
+use serde::{Deserialize, Serialize};
+use std::io;
+use tokio::io::AsyncWriteExt;
+
+/// One line of `audit.log`: who asked the daemon to do what, and how it went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// RFC 3339 timestamp in UTC.
+    pub time: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// Address of a token-authenticated TCP client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    pub command: String,
+    pub outcome: String,
+}
+
+impl AuditEntry {
+    pub fn new(peer: Option<PeerCredentials>, command: String, outcome: String) -> Self {
+        Self {
+            time: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            uid: peer.map(|p| p.uid),
+            user: peer.and_then(|p| sys::user_name(p.uid)),
+            pid: peer.and_then(|p| p.pid),
+            remote: None,
+            command,
+            outcome,
+        }
+    }
+}
+
+/// A short command line for requests that change daemon state. Read-only
+/// requests (list, info, log, ...) return `None` and are not audited, so the
+/// TUI's polling doesn't drown out real operations.
+pub fn describe(request: &Request) -> Option<String> {
+    let (verb, mut words) = match request {
+        Request::Start {
+            names,
+            env,
+            force,
+            namespace,
+            ..
+        } => {
+            let mut words = targets(names, &None);
+            push_opt(&mut words, "--env", env);
+            push_flag(&mut words, "--force", *force);
+            push_namespace(&mut words, namespace);
+            ("start", words)
+        }
+        Request::Stop {
+            names,
+            except,
+            namespace,
+            no_dependents,
+        } => {
+            let mut words = targets(names, except);
+            push_namespace(&mut words, namespace);
+            push_flag(&mut words, "--no-dependents", *no_dependents);
+            ("stop", words)
+        }
+        Request::Restart {
+            names,
+            except,
+            namespace,
+            stagger_ms,
+        } => {
+            let mut words = targets(names, except);
+            push_namespace(&mut words, namespace);
+            if let Some(ms) = stagger_ms {
+                words.push(format!("--stagger {ms}ms"));
+            }
+            ("restart", words)
+        }
+        Request::Reload {
+            names,
+            except,
+            namespace,
+            ..
+        } => {
+            let mut words = targets(names, except);
+            push_namespace(&mut words, namespace);
+            ("reload", words)
+        }
+        Request::Kill => ("kill", Vec::new()),
+        Request::Upgrade => ("daemon upgrade", Vec::new()),
+        Request::Signal { name, signal } => ("signal", vec![name.clone(), signal.clone()]),
+        Request::Save { tag } => {
+            let words = match tag {
+                Some(tag) => vec![format!("--tag {tag}")],
+                None => Vec::new(),
+            };
+            ("save", words)
+        }
+        Request::Resurrect { from, .. } => {
+            let words = match from {
+                Some(from) => vec![format!("--from {from}")],
+                None => Vec::new(),
+            };
+            ("resurrect", words)
+        }
+        Request::Flush {
+            names,
+            all_files,
+            namespace,
+        } => {
+            let mut words = targets(names, &None);
+            push_namespace(&mut words, namespace);
+            push_flag(&mut words, "--all-files", *all_files);
+            ("flush", words)
+        }
+        Request::ReopenLogs { names, namespace } => {
+            let mut words = targets(names, &None);
+            push_namespace(&mut words, namespace);
+            ("reopen-logs", words)
+        }
+        Request::Prune { logs, namespace } => {
+            let mut words = Vec::new();
+            push_namespace(&mut words, namespace);
+            push_flag(&mut words, "--logs", *logs);
+            ("prune", words)
+        }
+        Request::Suspend { name } => ("suspend", vec![name.clone()]),
+        Request::Continue { name } => ("continue", vec![name.clone()]),
+        Request::Pause { names, namespace } => {
+            let mut words = targets(names, &None);
+            push_namespace(&mut words, namespace);
+            ("pause", words)
+        }
+        Request::Resume { names, namespace } => {
+            let mut words = targets(names, &None);
+            push_namespace(&mut words, namespace);
+            ("resume", words)
+        }
+        Request::Run {
+            name,
+            env,
+            namespace,
+            ..
+        } => {
+            let mut words = vec![name.clone()];
+            push_opt(&mut words, "--env", env);
+            push_namespace(&mut words, namespace);
+            ("run", words)
+        }
+        Request::List { .. }
+        | Request::Info { .. }
+        | Request::DaemonInfo
+        | Request::Wait { .. }
+        | Request::Log { .. }
+        | Request::Events { .. }
+        | Request::Stats { .. }
+        | Request::Report { .. }
+        | Request::Tree { .. }
+        | Request::History { .. }
+        | Request::Export { .. }
+        | Request::Snapshots
+        | Request::Session => return None,
+    };
+    words.insert(0, verb.to_string());
+    Some(words.join(" "))
+}
+
+fn targets(names: &Option<Vec<String>>, except: &Option<Vec<String>>) -> Vec<String> {
+    let mut words = match names {
+        Some(names) if !names.is_empty() => vec![names.join(", ")],
+        _ => vec!["all".to_string()],
+    };
+    if let Some(except) = except.as_ref().filter(|e| !e.is_empty()) {
+        words.push(format!("except {}", except.join(", ")));
+    }
+    words
+}
+
+fn push_opt(words: &mut Vec<String>, flag: &str, value: &Option<String>) {
+    if let Some(value) = value {
+        words.push(format!("{flag} {value}"));
+    }
+}
+
+/// The CLI always sends its namespace, so only a non-default one is worth noting.
+fn push_namespace(words: &mut Vec<String>, namespace: &Option<String>) {
+    if namespace
+        .as_deref()
+        .is_some_and(|ns| ns != DEFAULT_NAMESPACE)
+    {
+        push_opt(words, "--namespace", namespace);
+    }
+}
+
+fn push_flag(words: &mut Vec<String>, flag: &str, set: bool) {
+    if set {
+        words.push(flag.to_string());
+    }
+}
+
+/// Summarises the final response sent back for an audited request.
+pub fn outcome(response: &Response) -> String {
+    match response {
+        Response::Error { message } => format!("error: {message}"),
+        Response::TaskFinished {
+            status, exit_code, ..
+        } => match exit_code {
+            Some(code) => format!("{status} (exit {code})"),
+            None => status.to_string(),
+        },
+        _ => "ok".to_string(),
+    }
+}
+
+/// Appends an entry to `audit.log`. The file is only ever appended to.
+pub async fn record(paths: &Paths, entry: &AuditEntry) -> io::Result<()> {
+    let mut line = serde_json::to_string(entry).map_err(io::Error::other)?;
+    line.push('\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(paths.audit_log())
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    // tokio hands writes to a blocking thread; flush so the line is on disk
+    // before the response goes out
+    file.flush().await
+}
+
+/// The last `n` entries of `audit.log`, oldest first. Lines that don't parse
+/// are skipped rather than failing the whole read.
+pub fn read_recent(paths: &Paths, n: usize) -> io::Result<Vec<AuditEntry>> {
+    let path = paths.audit_log();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(log::tail_file(&path, n)?
+        .iter()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ProcessStatus;
+
+    #[test]
+    fn test_describe_stop() {
+        let request = Request::Stop {
+            names: Some(vec!["web".into(), "api".into()]),
+            except: None,
+            namespace: None,
+            no_dependents: true,
+        };
+        assert_eq!(
+            describe(&request).as_deref(),
+            Some("stop web, api --no-dependents")
+        );
+    }
+
+    #[test]
+    fn test_describe_restart_all_except() {
+        let request = Request::Restart {
+            names: None,
+            except: Some(vec!["db".into()]),
+            namespace: Some("staging".into()),
+            stagger_ms: Some(500),
+        };
+        assert_eq!(
+            describe(&request).as_deref(),
+            Some("restart all except db --namespace staging --stagger 500ms")
+        );
+    }
+
+    #[test]
+    fn test_read_only_requests_not_described() {
+        assert_eq!(describe(&Request::List { filter: None }), None);
+        assert_eq!(describe(&Request::DaemonInfo), None);
+        assert_eq!(describe(&Request::Info { name: "web".into() }), None);
+        assert_eq!(describe(&Request::Kill).as_deref(), Some("kill"));
+    }
+
+    #[test]
+    fn test_outcome() {
+        assert_eq!(outcome(&Response::Success { message: None }), "ok");
+        assert_eq!(
+            outcome(&Response::Error {
+                message: "process not found: x".into()
+            }),
+            "error: process not found: x"
+        );
+        assert_eq!(
+            outcome(&Response::TaskFinished {
+                name: "migrate".into(),
+                status: ProcessStatus::Errored,
+                exit_code: Some(2),
+            }),
+            "errored (exit 2)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_and_read_recent() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().to_path_buf());
+        assert!(read_recent(&paths, 10).unwrap().is_empty());
+
+        for command in ["start all", "stop web", "restart api"] {
+            let entry = AuditEntry::new(None, command.into(), "ok".into());
+            record(&paths, &entry).await.unwrap();
+        }
+
+        let entries = read_recent(&paths, 2).unwrap();
+        let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["stop web", "restart api"]);
+        assert_eq!(entries[0].uid, None);
+        assert!(entries[0].time.ends_with('Z'));
+    }
+}
@@ -0,0 +1,477 @@
+use crate::events::{self, Snapshot, Snapshots};
+use crate::paths::Paths;
+use crate::process::ProcessTable;
+use crate::protocol::{AvailabilityReport, ProcessStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{RwLock, watch};
+
+/// How often the process table is checked for processes going up or down.
+const TRACK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Entries older than this are dropped when the daemon starts, keeping the
+/// last state of each process before it so later windows still start right.
+const RETENTION_MS: i64 = 90 * 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mark {
+    /// Running.
+    Up,
+    /// Meant to be running but isn't: crashed, waiting to be restarted, or
+    /// unhealthy.
+    Down,
+    /// Not meant to be running: stopped, finished, suspended or deleted.
+    Stopped,
+    /// Exited on its own with a failure.
+    Crash,
+    /// Came back with a new pid, automatically or on request.
+    Restart,
+}
+
+impl Mark {
+    fn is_state(self) -> bool {
+        matches!(self, Mark::Up | Mark::Down | Mark::Stopped)
+    }
+}
+
+/// One line of `availability.jsonl`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unix timestamp in milliseconds.
+    pub time: i64,
+    pub name: String,
+    pub mark: Mark,
+}
+
+/// Whether a process is up, down or stopped. A process that is starting
+/// keeps its previous state, so one coming back from a crash is down until
+/// it is online again.
+pub fn state(snapshot: &Snapshot, previous: Option<Mark>) -> Mark {
+    match snapshot.status {
+        ProcessStatus::Online if snapshot.pid.is_some() => Mark::Up,
+        // Waiting out the backoff before an automatic restart
+        ProcessStatus::Online
+        | ProcessStatus::Unhealthy
+        | ProcessStatus::Errored
+        | ProcessStatus::Flapping => Mark::Down,
+        ProcessStatus::Starting => match previous {
+            Some(Mark::Up) => Mark::Up,
+            Some(Mark::Down) => Mark::Down,
+            _ => Mark::Stopped,
+        },
+        // A failed task is a crash, but tasks aren't meant to stay running
+        ProcessStatus::Stopped
+        | ProcessStatus::Succeeded
+        | ProcessStatus::Failed
+        | ProcessStatus::Suspended => Mark::Stopped,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tracked {
+    snapshot: Snapshot,
+    state: Mark,
+    /// The last pid the process ran with since it was last stopped, so a
+    /// restart is noticed across a gap without one.
+    last_pid: Option<u32>,
+}
+
+/// Turns successive snapshots of the process table into entries.
+#[derive(Debug, Default)]
+pub struct Tracker {
+    tracked: HashMap<String, Tracked>,
+}
+
+impl Tracker {
+    /// The entries that turn the last snapshots into `snapshots`.
+    pub fn update(&mut self, snapshots: &Snapshots, time: i64) -> Vec<Entry> {
+        let entry = |name: &str, mark| Entry {
+            time,
+            name: name.to_string(),
+            mark,
+        };
+        let mut entries = Vec::new();
+        self.tracked.retain(|name, tracked| {
+            let kept = snapshots.contains_key(name);
+            if !kept && tracked.state != Mark::Stopped {
+                entries.push(entry(name, Mark::Stopped));
+            }
+            kept
+        });
+        for (name, snapshot) in snapshots {
+            let previous = self.tracked.get(name).copied();
+            if let Some(previous) = previous {
+                for _ in previous.snapshot.crashes..snapshot.crashes {
+                    entries.push(entry(name, Mark::Crash));
+                }
+                if let (Some(old), Some(new)) = (previous.last_pid, snapshot.pid)
+                    && old != new
+                {
+                    entries.push(entry(name, Mark::Restart));
+                }
+            }
+            let state = state(snapshot, previous.map(|p| p.state));
+            if previous.map(|p| p.state) != Some(state) {
+                entries.push(entry(name, state));
+            }
+            let last_pid = match state {
+                Mark::Stopped => None,
+                _ => snapshot.pid.or(previous.and_then(|p| p.last_pid)),
+            };
+            self.tracked.insert(
+                name.clone(),
+                Tracked {
+                    snapshot: *snapshot,
+                    state,
+                    last_pid,
+                },
+            );
+        }
+        entries
+    }
+}
+
+/// Appends `entries` to `availability.jsonl`.
+pub async fn record(paths: &Paths, entries: &[Entry]) -> io::Result<()> {
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry).map_err(io::Error::other)?);
+        content.push('\n');
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(paths.availability_log())
+        .await?;
+    file.write_all(content.as_bytes()).await?;
+    file.flush().await
+}
+
+/// Records every process still in the table as stopped, for when the daemon
+/// shuts down and takes them with it.
+pub async fn record_shutdown(paths: &Paths, processes: &Arc<RwLock<ProcessTable>>) {
+    let time = chrono::Utc::now().timestamp_millis();
+    let entries: Vec<Entry> = processes
+        .read()
+        .await
+        .keys()
+        .map(|name| Entry {
+            time,
+            name: name.clone(),
+            mark: Mark::Stopped,
+        })
+        .collect();
+    if let Err(e) = record(paths, &entries).await {
+        tracing::warn!("failed to record availability: {e}");
+    }
+}
+
+/// Every entry of `availability.jsonl`, oldest first. Lines that don't parse
+/// are skipped rather than failing the whole read.
+pub async fn read(paths: &Paths) -> io::Result<Vec<Entry>> {
+    match tokio::fs::read_to_string(paths.availability_log()).await {
+        Ok(content) => Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// `entries` without those before `cutoff`, except the last state of each
+/// process before it. Processes that were last seen stopped before the
+/// cutoff are forgotten.
+pub fn compact(entries: Vec<Entry>, cutoff: i64) -> Vec<Entry> {
+    let mut last_state: BTreeMap<String, Entry> = BTreeMap::new();
+    let mut kept = Vec::new();
+    for entry in entries {
+        if entry.time >= cutoff {
+            kept.push(entry);
+        } else if entry.mark.is_state() {
+            last_state.insert(entry.name.clone(), entry);
+        }
+    }
+    let mut compacted: Vec<Entry> = last_state
+        .into_values()
+        .filter(|entry| {
+            entry.mark != Mark::Stopped || kept.iter().any(|kept| kept.name == entry.name)
+        })
+        .collect();
+    compacted.sort_by_key(|entry| entry.time);
+    compacted.extend(kept);
+    compacted
+}
+
+/// Drops expired entries from `availability.jsonl`, writing a temporary
+/// file first so a crash midway doesn't lose what was there.
+async fn compact_log(paths: &Paths, now: i64) -> io::Result<()> {
+    let entries = read(paths).await?;
+    let total = entries.len();
+    let compacted = compact(entries, now - RETENTION_MS);
+    if compacted.len() == total {
+        return Ok(());
+    }
+    let mut content = String::new();
+    for entry in &compacted {
+        content.push_str(&serde_json::to_string(entry).map_err(io::Error::other)?);
+        content.push('\n');
+    }
+    let path = paths.availability_log();
+    let tmp = path.with_extension("jsonl.tmp");
+    tokio::fs::write(&tmp, content).await?;
+    tokio::fs::rename(&tmp, &path).await
+}
+
+/// Records every process going up, down or stopped, crashing and
+/// restarting, until the daemon shuts down.
+pub fn spawn_tracker(
+    processes: Arc<RwLock<ProcessTable>>,
+    paths: Paths,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let now = chrono::Utc::now().timestamp_millis();
+        if let Err(e) = compact_log(&paths, now).await {
+            tracing::warn!("failed to compact availability log: {e}");
+        }
+
+        let mut tracker = Tracker::default();
+        // Whether the last write failed, so failures are logged once
+        let mut failing = false;
+        loop {
+            let snapshots = events::snapshot(&*processes.read().await);
+            let entries = tracker.update(&snapshots, chrono::Utc::now().timestamp_millis());
+            if !entries.is_empty() {
+                match record(&paths, &entries).await {
+                    Ok(()) => failing = false,
+                    Err(e) if !failing => {
+                        tracing::warn!("failed to record availability: {e}");
+                        failing = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(TRACK_INTERVAL) => {}
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// How available a process was between `start` and `now`, from its entries
+/// in time order. Entries before `start` only set the state it starts in.
+pub fn report(name: &str, entries: &[&Entry], start: i64, now: i64) -> AvailabilityReport {
+    let mut state = None;
+    let mut since = start;
+    let mut down_since = None;
+    let (mut up, mut down) = (0i64, 0i64);
+    let mut recoveries = Vec::new();
+    let (mut crashes, mut restarts, mut last_crash) = (0, 0, None);
+    let mut elapse = |state: Option<Mark>, from: i64, to: i64| match state {
+        Some(Mark::Up) => up += (to - from).max(0),
+        Some(Mark::Down) => down += (to - from).max(0),
+        _ => {}
+    };
+
+    for entry in entries.iter().take_while(|entry| entry.time <= now) {
+        let in_window = entry.time >= start;
+        match entry.mark {
+            Mark::Crash if in_window => {
+                crashes += 1;
+                last_crash = Some(entry.time);
+            }
+            Mark::Restart if in_window => restarts += 1,
+            Mark::Crash | Mark::Restart => {}
+            mark => {
+                let time = entry.time.max(start);
+                elapse(state, since, time);
+                match mark {
+                    Mark::Down if state != Some(Mark::Down) => down_since = Some(entry.time),
+                    Mark::Up => {
+                        if let Some(down_at) = down_since.take()
+                            && in_window
+                        {
+                            recoveries.push(entry.time - down_at);
+                        }
+                    }
+                    Mark::Stopped => down_since = None,
+                    _ => {}
+                }
+                state = Some(mark);
+                since = time;
+            }
+        }
+    }
+    elapse(state, since, now);
+
+    AvailabilityReport {
+        name: name.to_string(),
+        window_secs: ((now - start) / 1000) as u64,
+        uptime_secs: up as f64 / 1000.0,
+        downtime_secs: down as f64 / 1000.0,
+        availability: (up + down > 0).then(|| up as f64 * 100.0 / (up + down) as f64),
+        crashes,
+        restarts,
+        mttr_secs: (!recoveries.is_empty())
+            .then(|| recoveries.iter().sum::<i64>() as f64 / recoveries.len() as f64 / 1000.0),
+        last_crash: last_crash.map(|ms| ms / 1000),
+    }
+}
+
+/// A report per process in `names` and window, in that order.
+pub fn reports(
+    entries: &[Entry],
+    names: &[String],
+    windows: &[Duration],
+    now: i64,
+) -> Vec<AvailabilityReport> {
+    let mut by_name: HashMap<&str, Vec<&Entry>> = HashMap::new();
+    for entry in entries {
+        by_name.entry(&entry.name).or_default().push(entry);
+    }
+    let mut reports = Vec::new();
+    for name in names {
+        let entries = by_name.get(name.as_str()).map_or(&[][..], Vec::as_slice);
+        for window in windows {
+            let start = now - window.as_millis() as i64;
+            reports.push(report(name, entries, start, now));
+        }
+    }
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+
+    fn snap(status: ProcessStatus, pid: Option<u32>, crashes: u32) -> Snapshot {
+        Snapshot {
+            status,
+            pid,
+            restarts: 0,
+            exit_code: None,
+            memory_restarts: 0,
+            memory_breaches: 0,
+            memory_leak: None,
+            crashes,
+            gave_up: false,
+            logs_suppressed: 0,
+        }
+    }
+
+    fn entry(time: i64, mark: Mark) -> Entry {
+        Entry {
+            time,
+            name: "web".to_string(),
+            mark,
+        }
+    }
+
+    fn marks(entries: &[Entry]) -> Vec<Mark> {
+        entries.iter().map(|e| e.mark).collect()
+    }
+
+    #[test]
+    fn test_tracker_crash_and_recovery() {
+        let mut tracker = Tracker::default();
+        let at =
+            |status, pid, crashes| Snapshots::from([("web".into(), snap(status, pid, crashes))]);
+
+        let entries = tracker.update(&at(ProcessStatus::Starting, Some(1), 0), 0);
+        assert_eq!(marks(&entries), vec![Mark::Stopped]);
+        let entries = tracker.update(&at(ProcessStatus::Online, Some(1), 0), 1);
+        assert_eq!(marks(&entries), vec![Mark::Up]);
+
+        // Crashed and waiting to be restarted
+        let entries = tracker.update(&at(ProcessStatus::Online, None, 1), 2);
+        assert_eq!(marks(&entries), vec![Mark::Crash, Mark::Down]);
+        let entries = tracker.update(&at(ProcessStatus::Starting, Some(2), 1), 3);
+        assert_eq!(marks(&entries), vec![Mark::Restart]);
+        let entries = tracker.update(&at(ProcessStatus::Online, Some(2), 1), 4);
+        assert_eq!(marks(&entries), vec![Mark::Up]);
+
+        // A crash and restart between two looks still count
+        let entries = tracker.update(&at(ProcessStatus::Online, Some(3), 2), 5);
+        assert_eq!(marks(&entries), vec![Mark::Crash, Mark::Restart]);
+
+        let entries = tracker.update(&Snapshots::new(), 6);
+        assert_eq!(marks(&entries), vec![Mark::Stopped]);
+        assert_eq!(entries[0].time, 6);
+    }
+
+    #[test]
+    fn test_report_availability_and_mttr() {
+        let entries = [
+            entry(0, Mark::Up),
+            entry(10 * HOUR, Mark::Crash),
+            entry(10 * HOUR, Mark::Down),
+            entry(10 * HOUR + 2000, Mark::Restart),
+            entry(10 * HOUR + 4000, Mark::Up),
+            entry(20 * HOUR, Mark::Stopped),
+            entry(22 * HOUR, Mark::Up),
+        ];
+        let refs: Vec<&Entry> = entries.iter().collect();
+        let report = report("web", &refs, 0, 24 * HOUR);
+        assert_eq!(report.window_secs, 24 * 60 * 60);
+        assert_eq!(report.downtime_secs, 4.0);
+        assert_eq!(report.uptime_secs, (22 * HOUR / 1000 - 4) as f64);
+        assert_eq!(report.crashes, 1);
+        assert_eq!(report.restarts, 1);
+        assert_eq!(report.mttr_secs, Some(4.0));
+        assert_eq!(report.last_crash, Some(10 * 60 * 60));
+        let availability = report.availability.unwrap();
+        assert!(availability > 99.99 && availability < 100.0);
+    }
+
+    #[test]
+    fn test_report_window_starts_in_earlier_state() {
+        let entries = [
+            entry(0, Mark::Crash),
+            entry(0, Mark::Down),
+            entry(3 * HOUR, Mark::Up),
+        ];
+        let refs: Vec<&Entry> = entries.iter().collect();
+        let report = report("web", &refs, 2 * HOUR, 4 * HOUR);
+        assert_eq!(report.downtime_secs, 3600.0);
+        assert_eq!(report.uptime_secs, 3600.0);
+        assert_eq!(report.availability, Some(50.0));
+        // The crash was before the window, but the outage ended in it
+        assert_eq!(report.crashes, 0);
+        assert_eq!(report.mttr_secs, Some(3.0 * 3600.0));
+
+        let report = super::report("web", &[], 0, HOUR);
+        assert_eq!(report.availability, None);
+        assert_eq!(report.mttr_secs, None);
+    }
+
+    #[test]
+    fn test_compact_keeps_last_state() {
+        let mut gone = entry(1, Mark::Stopped);
+        gone.name = "old".to_string();
+        let entries = vec![
+            entry(0, Mark::Up),
+            gone,
+            entry(2, Mark::Crash),
+            entry(2, Mark::Down),
+            entry(10, Mark::Up),
+        ];
+        assert_eq!(
+            compact(entries, 5),
+            vec![entry(2, Mark::Down), entry(10, Mark::Up)]
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use crate::client;
+use crate::config::ProcessConfig;
+use crate::log;
+use crate::paths::Paths;
+use crate::pid;
+use crate::protocol::{Request, Response};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use serde_json::Value;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Lines kept from the end of the daemon and audit logs.
+const DAEMON_LOG_LINES: usize = 2000;
+
+/// What env values are replaced with when redacting.
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone)]
+pub struct BundleOptions {
+    /// Replace env values in configs and process details.
+    pub redact: bool,
+    /// Lines kept from the end of each process log.
+    pub log_lines: usize,
+    /// Windows of the included availability report.
+    pub windows: Vec<Duration>,
+}
+
+/// Describes the bundle, so whoever reads it knows where it came from and
+/// what couldn't be collected.
+#[derive(Debug, Serialize)]
+struct Manifest {
+    pm3_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    created: String,
+    data_dir: String,
+    daemon_running: bool,
+    redacted: bool,
+    /// Parts that couldn't be collected, and why.
+    errors: Vec<String>,
+}
+
+/// Writes a gzipped tarball of everything useful for diagnosing a problem to
+/// `out`: the tail of the daemon, audit and process logs, the dump file,
+/// settings and the project's `pm3.toml`, and, when the daemon is running,
+/// the process list, each process's details and an availability report.
+/// Returns the paths inside the archive.
+pub fn create(paths: &Paths, out: &Path, options: &BundleOptions) -> io::Result<Vec<String>> {
+    let now = chrono::Utc::now();
+    let root = format!("pm3-diagnostics-{}", now.format("%Y%m%dT%H%M%SZ"));
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut errors = Vec::new();
+
+    let tail = |path: &Path, n: usize| -> io::Result<Vec<u8>> {
+        let mut content = log::tail_file(path, n)?.join("\n");
+        content.push('\n');
+        Ok(content.into_bytes())
+    };
+    for (name, path) in [
+        ("daemon.log", paths.daemon_log()),
+        ("audit.log", paths.audit_log()),
+    ] {
+        if path.exists() {
+            match tail(&path, DAEMON_LOG_LINES) {
+                Ok(content) => files.push((name.to_string(), content)),
+                Err(e) => errors.push(format!("{name}: {e}")),
+            }
+        }
+    }
+    for log_file in log_files(&paths.log_dir()) {
+        let Ok(relative) = log_file.strip_prefix(paths.data_dir()) else {
+            continue;
+        };
+        let name = relative.to_string_lossy().replace('\\', "/");
+        match tail(&log_file, options.log_lines) {
+            Ok(content) => files.push((name, content)),
+            Err(e) => errors.push(format!("{name}: {e}")),
+        }
+    }
+
+    if let Some(dump) = read_optional(&paths.dump_file(), &mut errors) {
+        let dump = match serde_json::from_slice::<Value>(&dump) {
+            Ok(mut value) if options.redact => {
+                redact_json(&mut value);
+                to_json(&value)
+            }
+            Ok(_) => dump,
+            // Unparseable dumps are exactly what someone may need to see,
+            // but can't be redacted
+            Err(_) if options.redact => {
+                errors.push("dump.json: not valid JSON, left out to redact it".to_string());
+                Vec::new()
+            }
+            Err(_) => dump,
+        };
+        if !dump.is_empty() {
+            files.push(("dump.json".to_string(), dump));
+        }
+    }
+    if let Some(settings) = read_optional(&paths.settings_file(), &mut errors) {
+        files.push(("settings.toml".to_string(), settings));
+    }
+    let project = std::env::current_dir()
+        .map(|dir| dir.join("pm3.toml"))
+        .ok()
+        .and_then(|path| read_optional(&path, &mut errors));
+    if let Some(project) = project {
+        let project = if options.redact {
+            match String::from_utf8_lossy(&project).parse::<toml::Table>() {
+                Ok(mut table) => {
+                    redact_toml(&mut table);
+                    toml::to_string(&table).unwrap_or_default().into_bytes()
+                }
+                Err(_) => {
+                    errors.push("pm3.toml: not valid TOML, left out to redact it".to_string());
+                    Vec::new()
+                }
+            }
+        } else {
+            project
+        };
+        if !project.is_empty() {
+            files.push(("pm3.toml".to_string(), project));
+        }
+    }
+
+    let daemon_running = pid::is_daemon_running_sync(paths)?;
+    if daemon_running {
+        daemon_state(paths, options, &mut files, &mut errors);
+    }
+
+    let manifest = Manifest {
+        pm3_version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        created: log::format_timestamp(now),
+        data_dir: paths.data_dir().display().to_string(),
+        daemon_running,
+        redacted: options.redact,
+        errors,
+    };
+    files.insert(0, ("manifest.json".to_string(), to_json(&manifest)));
+
+    let mut archive = tar::Builder::new(GzEncoder::new(
+        std::fs::File::create(out)?,
+        Compression::default(),
+    ));
+    let mut names = Vec::new();
+    for (name, content) in files {
+        let path = format!("{root}/{name}");
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(now.timestamp().max(0) as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, &path, content.as_slice())?;
+        names.push(path);
+    }
+    archive.into_inner()?.finish()?;
+    Ok(names)
+}
+
+/// What the running daemon reports about its processes.
+fn daemon_state(
+    paths: &Paths,
+    options: &BundleOptions,
+    files: &mut Vec<(String, Vec<u8>)>,
+    errors: &mut Vec<String>,
+) {
+    let mut request = |name: &str, request: &Request| match client::send_request(paths, request) {
+        Ok(Response::Error { message }) => {
+            errors.push(format!("{name}: {message}"));
+            None
+        }
+        Ok(response) => Some(response),
+        Err(e) => {
+            errors.push(format!("{name}: {e}"));
+            None
+        }
+    };
+
+    if let Some(Response::DaemonInfo { info }) = request("daemon.json", &Request::DaemonInfo) {
+        files.push(("daemon.json".to_string(), to_json(&info)));
+    }
+    let Some(Response::ProcessList { processes, .. }) =
+        request("list.json", &Request::List { filter: None })
+    else {
+        return;
+    };
+    files.push(("list.json".to_string(), to_json(&processes)));
+    for process in &processes {
+        let file = format!("info/{}.json", process.name);
+        let info = Request::Info {
+            name: process.name.clone(),
+        };
+        if let Some(Response::ProcessDetail { info }) = request(&file, &info) {
+            let mut value = serde_json::to_value(&info).unwrap_or_default();
+            if options.redact {
+                redact_json(&mut value);
+            }
+            files.push((file, to_json(&value)));
+        }
+    }
+    let report = Request::Report {
+        name: None,
+        windows_secs: options.windows.iter().map(|d| d.as_secs()).collect(),
+    };
+    if let Some(Response::Report { reports }) = request("report.json", &report) {
+        files.push(("report.json".to_string(), to_json(&reports)));
+    }
+}
+
+fn to_json(value: &impl Serialize) -> Vec<u8> {
+    let mut json = serde_json::to_vec_pretty(value).unwrap_or_default();
+    json.push(b'\n');
+    json
+}
+
+/// The file's content, or `None` when it doesn't exist.
+fn read_optional(path: &Path, errors: &mut Vec<String>) -> Option<Vec<u8>> {
+    match std::fs::read(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            errors.push(format!("{}: {e}", path.display()));
+            None
+        }
+    }
+}
+
+/// Current process logs under `dir`, including namespace subdirectories.
+/// Rotated logs are left out; the tail of the current one is what matters.
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(log_files(&path));
+        } else if path.extension().is_some_and(|ext| ext == "log") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Replaces the values of every env table in a dump or process detail: those
+/// of each process `config`, and the resolved `env` of a process detail.
+pub fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match (key.as_str(), value) {
+                    ("config", value) => redact_config(value),
+                    ("env", Value::Object(env)) => redact_values(env.values_mut()),
+                    (_, value) => redact_json(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// Redacts a process config through [`ProcessConfig::env_tables_mut`], so
+/// every table it holds is covered; one that doesn't parse is redacted by key.
+fn redact_config(value: &mut Value) {
+    if let Ok(mut config) = serde_json::from_value::<ProcessConfig>(value.clone()) {
+        for env in config.env_tables_mut() {
+            env.values_mut()
+                .for_each(|value| *value = REDACTED.to_string());
+        }
+        if let Ok(redacted) = serde_json::to_value(&config) {
+            *value = redacted;
+            return;
+        }
+    }
+    let Value::Object(map) = value else {
+        return;
+    };
+    for (key, value) in map.iter_mut() {
+        match (key.as_str(), value) {
+            ("env", Value::Object(env)) => redact_values(env.values_mut()),
+            ("instance_env" | "environments", Value::Object(tables)) => {
+                for env in tables.values_mut() {
+                    if let Value::Object(env) = env {
+                        redact_values(env.values_mut());
+                    }
+                }
+            }
+            (_, value) => redact_json(value),
+        }
+    }
+}
+
+fn redact_values<'a>(values: impl Iterator<Item = &'a mut Value>) {
+    for value in values {
+        *value = Value::String(REDACTED.to_string());
+    }
+}
+
+/// Replaces the values of every `env`, `env_<environment>` and
+/// `instance_env.<index>` table in a `pm3.toml`.
+pub fn redact_toml(table: &mut toml::Table) {
+    for (key, value) in table.iter_mut() {
+        let is_env = key == "env" || (key.starts_with("env_") && key != "env_file");
+        match value {
+            toml::Value::Table(env) if is_env => redact_toml_values(env),
+            toml::Value::Table(tables) if key == "instance_env" => {
+                for (_, env) in tables.iter_mut() {
+                    if let toml::Value::Table(env) = env {
+                        redact_toml_values(env);
+                    }
+                }
+            }
+            toml::Value::Table(nested) => redact_toml(nested),
+            _ => {}
+        }
+    }
+}
+
+fn redact_toml_values(env: &mut toml::Table) {
+    for (_, value) in env.iter_mut() {
+        *value = toml::Value::String(REDACTED.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_redact_json() {
+        let mut dump = serde_json::json!([{
+            "name": "web",
+            "config": {
+                "command": "node server.js",
+                "env": { "API_KEY": "s3cret" },
+                "environments": { "production": { "DB_URL": "postgres://prod" } },
+                "instances": 2,
+                "instance_env": { "1": { "REPLICA_TOKEN": "t0k3n" } }
+            }
+        }]);
+        redact_json(&mut dump);
+        let config = &dump[0]["config"];
+        assert_eq!(config["env"]["API_KEY"], REDACTED);
+        assert_eq!(config["environments"]["production"]["DB_URL"], REDACTED);
+        assert_eq!(config["instance_env"]["1"]["REPLICA_TOKEN"], REDACTED);
+        assert_eq!(config["command"], "node server.js");
+    }
+
+    #[test]
+    fn test_redact_toml() {
+        let mut table: toml::Table = r#"
+[web]
+command = "node server.js"
+env_file = ".env"
+env = { API_KEY = "s3cret" }
+
+[web.env_production]
+DB_URL = "postgres://prod"
+
+[web.instance_env.1]
+REPLICA_TOKEN = "t0k3n"
+"#
+        .parse()
+        .unwrap();
+        redact_toml(&mut table);
+        let web = table["web"].as_table().unwrap();
+        assert_eq!(web["env"]["API_KEY"].as_str(), Some(REDACTED));
+        assert_eq!(web["env_production"]["DB_URL"].as_str(), Some(REDACTED));
+        assert_eq!(
+            web["instance_env"]["1"]["REPLICA_TOKEN"].as_str(),
+            Some(REDACTED)
+        );
+        assert_eq!(web["env_file"].as_str(), Some(".env"));
+        assert_eq!(web["command"].as_str(), Some("node server.js"));
+    }
+
+    #[test]
+    fn test_create_bundle_without_daemon() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().join("data"));
+        std::fs::create_dir_all(paths.process_log_dir("shop/web")).unwrap();
+        std::fs::write(paths.stdout_log("shop/web"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(paths.rotated_stdout_log("shop/web", 1), "old\n").unwrap();
+        std::fs::write(paths.daemon_log(), "daemon started\n").unwrap();
+        std::fs::write(
+            paths.dump_file(),
+            r#"[{"name":"web","config":{"command":"x","env":{"TOKEN":"abc"}}}]"#,
+        )
+        .unwrap();
+
+        let out = dir.path().join("bundle.tar.gz");
+        let options = BundleOptions {
+            redact: true,
+            log_lines: 2,
+            windows: vec![Duration::from_secs(86_400)],
+        };
+        let names = create(&paths, &out, &options).unwrap();
+        let root = names[0].split('/').next().unwrap().to_string();
+        assert!(names[0].ends_with("/manifest.json"));
+
+        let mut contents = std::collections::HashMap::new();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(
+            std::fs::File::open(&out).unwrap(),
+        ));
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            contents.insert(
+                name.trim_start_matches(&format!("{root}/")).to_string(),
+                content,
+            );
+        }
+        assert_eq!(contents["logs/shop/web-out.log"], "two\nthree\n");
+        assert!(!contents.contains_key("logs/shop/web-out.log.1"));
+        assert_eq!(contents["daemon.log"], "daemon started\n");
+        assert!(contents["dump.json"].contains(REDACTED));
+        assert!(!contents["dump.json"].contains("abc"));
+        let manifest: Value = serde_json::from_str(&contents["manifest.json"]).unwrap();
+        assert_eq!(manifest["daemon_running"], false);
+        assert_eq!(manifest["redacted"], true);
+    }
+}
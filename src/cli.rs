@@ -1,14 +1,64 @@
+use crate::protocol::ProcessStatus;
 use clap::{Parser, Subcommand};
 
 #[derive(Debug, Parser)]
 #[command(name = "pm3", about = "A process manager", version)]
 pub struct Cli {
+    /// Old spelling of `pm3 daemon start --foreground`, still used by boot
+    /// services installed by earlier versions
     #[arg(long, hide = true)]
     pub daemon: bool,
 
+    #[arg(long, hide = true, requires = "daemon", value_name = "ADDR")]
+    pub listen: Option<std::net::SocketAddr>,
+
+    #[arg(long, hide = true, requires = "daemon", value_name = "PATH")]
+    pub auth_token_file: Option<std::path::PathBuf>,
+
+    #[arg(long, hide = true, requires = "daemon")]
+    pub resurrect: bool,
+
+    /// State handed over by the daemon this one replaces in `pm3 daemon upgrade`
+    #[arg(long, hide = true, value_name = "PATH")]
+    pub handover: Option<std::path::PathBuf>,
+
+    /// Print the handover format this binary reads, for `pm3 daemon upgrade`
+    #[arg(long, hide = true)]
+    pub handover_version: bool,
+
+    /// Bridge stdin/stdout to the daemon socket (used by --host over SSH)
+    #[arg(long, hide = true, conflicts_with = "daemon")]
+    pub proxy: bool,
+
     #[arg(long, global = true)]
     pub json: bool,
 
+    /// Manage the daemon on another machine over SSH, e.g. user@server
+    #[arg(long, global = true, value_name = "HOST")]
+    pub host: Option<String>,
+
+    /// Namespace to operate in (defaults to the namespace of ./pm3.toml, then "default")
+    #[arg(long, global = true)]
+    pub namespace: Option<String>,
+
+    /// Fail instead of starting the daemon when it isn't running (also
+    /// PM3_NO_AUTOSTART)
+    #[arg(long, global = true, conflicts_with = "autostart")]
+    pub no_autostart: bool,
+
+    /// Start the daemon when it isn't running, even with PM3_NO_AUTOSTART set
+    #[arg(long, global = true)]
+    pub autostart: bool,
+
+    /// Talk to a separate, fully isolated daemon with its own data dir
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Make commands without names, like list, stop, pause, flush and log,
+    /// act on every namespace
+    #[arg(long, global = true, conflicts_with = "namespace")]
+    pub all_namespaces: bool,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -22,14 +72,75 @@ pub enum Command {
         env: Option<String>,
         #[arg(short, long)]
         wait: bool,
+        /// Start even if conflict_check finds a matching instance
+        #[arg(long)]
+        force: bool,
+        /// Treat names as regular expressions
+        #[arg(long)]
+        regex: bool,
+    },
+    /// Run a one-shot task, stream its output, and exit with its exit code
+    Run {
+        name: String,
+        #[arg(long)]
+        env: Option<String>,
     },
     /// Stop running processes
-    Stop { names: Vec<String> },
+    Stop {
+        names: Vec<String>,
+        /// Skip these processes, groups or patterns
+        #[arg(long, num_args = 1.., value_delimiter = ',')]
+        except: Vec<String>,
+        /// Treat names as regular expressions
+        #[arg(long)]
+        regex: bool,
+        /// Leave processes that depend on the stopped ones running
+        #[arg(long)]
+        no_dependents: bool,
+    },
     /// Restart running processes
-    Restart { names: Vec<String> },
+    Restart {
+        names: Vec<String>,
+        /// Skip these processes, groups or patterns
+        #[arg(long, num_args = 1.., value_delimiter = ',')]
+        except: Vec<String>,
+        /// Treat names as regular expressions
+        #[arg(long)]
+        regex: bool,
+        /// Wait this long between respawning each process (e.g. 5s, 500ms)
+        #[arg(long, value_parser = crate::config::parse_duration)]
+        stagger: Option<std::time::Duration>,
+    },
+    /// Freeze a process (and its children) with SIGSTOP
+    Suspend { name: String },
+    /// Thaw a suspended process with SIGCONT
+    Continue { name: String },
+    /// Pause auto-restart, watch, cron, memory and health monitoring without stopping
+    Pause { names: Vec<String> },
+    /// Resume monitoring for paused processes
+    Resume { names: Vec<String> },
     /// List all managed processes
     #[command(visible_alias = "view")]
-    List,
+    List {
+        /// Only show processes with this status (repeatable or comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        status: Vec<ProcessStatus>,
+        /// Only show processes in this group (repeatable or comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        group: Vec<String>,
+        /// Only show processes with this tag (repeatable or comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        tag: Vec<String>,
+        /// Sort by a column (numeric columns sort largest first)
+        #[arg(long)]
+        sort: Option<ListColumn>,
+        /// Columns to show, in order (e.g. name,status,mem,cpu)
+        #[arg(long, value_delimiter = ',')]
+        columns: Vec<ListColumn>,
+        /// Show one row of totals per group instead of one per process
+        #[arg(long, value_name = "FIELD")]
+        group_by: Option<ListGroupBy>,
+    },
     /// Open interactive TUI
     Tui,
     /// Initialize a new pm3.toml configuration file
@@ -37,21 +148,172 @@ pub enum Command {
     /// Stop all processes and shut down the daemon
     Kill,
     /// Reload process configuration
-    Reload { names: Vec<String> },
+    Reload {
+        names: Vec<String>,
+        /// Skip these processes, groups or patterns
+        #[arg(long, num_args = 1.., value_delimiter = ',')]
+        except: Vec<String>,
+        /// Treat names as regular expressions
+        #[arg(long)]
+        regex: bool,
+    },
+    /// Block until processes reach a status (default: online)
+    Wait {
+        #[arg(required = true)]
+        names: Vec<String>,
+        /// Give up after this long (e.g. 30s, 2m)
+        #[arg(long, value_parser = crate::config::parse_duration, default_value = "60s")]
+        timeout: std::time::Duration,
+        /// Status to wait for
+        #[arg(long, default_value = "online")]
+        status: ProcessStatus,
+    },
     /// Show detailed info about a process
-    Info { name: String },
+    Info {
+        name: String,
+        /// Keep status, CPU, memory and uptime updating every second
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Print a process's status; the exit code is 0 online, 1 stopped,
+    /// 2 errored, 3 not found
+    Status { name: String },
     /// Send a signal to a process
     Signal { name: String, signal: String },
     /// Save current process list for resurrection
-    Save,
+    Save {
+        /// Name the snapshot taken alongside the dump, replacing any with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Do nothing when the daemon isn't running instead of starting one
+        #[arg(long)]
+        if_running: bool,
+    },
     /// Restore previously saved processes
-    Resurrect,
+    Resurrect {
+        /// Roll back to a snapshot (tag or id) instead of the dump file
+        #[arg(long, value_name = "SNAPSHOT")]
+        from: Option<String>,
+    },
+    /// List the kept snapshots of saved state
+    Snapshots,
+    /// Write the running processes' configs out as a pm3.toml
+    Export {
+        /// File to write; printed to stdout when omitted
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<std::path::PathBuf>,
+    },
     /// Clear log files for processes
-    Flush { names: Vec<String> },
+    Flush {
+        /// Process names, groups, tags or patterns; all processes when empty
+        names: Vec<String>,
+        /// Also remove log files left behind by processes that were deleted
+        #[arg(long, conflicts_with = "names")]
+        all_files: bool,
+    },
+    /// Reopen log files once an external tool like logrotate moved them
+    ReopenLogs { names: Vec<String> },
+    /// Remove stopped, errored and finished processes, and log files of
+    /// processes that no longer exist
+    Prune {
+        /// Also remove the log files of the processes pruned
+        #[arg(long)]
+        logs: bool,
+    },
+    /// List the default daemon and every profile's daemon
+    Daemons,
+    /// Check the daemon responds and show its pid, version and uptime
+    Ping,
+    /// Start, stop or check on the daemon itself
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonCommand,
+    },
+    /// Print the dependency graph of the processes in pm3.toml
+    Graph {
+        /// Output format: ascii (start levels) or dot (Graphviz)
+        #[arg(long, default_value = "ascii")]
+        format: GraphFormat,
+    },
+    /// View the daemon's own log
+    DaemonLogs {
+        #[arg(long, default_value_t = 15)]
+        lines: usize,
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Copy a process's output to its logs, run by the daemon
+    #[command(hide = true)]
+    LogRelay {
+        #[arg(long, value_name = "PATH")]
+        stdout_log: std::path::PathBuf,
+        #[arg(long, value_name = "PATH")]
+        stderr_log: std::path::PathBuf,
+        /// The instance whose lines these are, in a log merged with others
+        #[arg(long, value_name = "NAME")]
+        log_instance: Option<String>,
+        #[arg(long)]
+        log_date_format: Option<String>,
+        #[arg(long, value_name = "BYTES", default_value_t = crate::log::DEFAULT_MAX_LINE_LEN)]
+        log_max_line_len: usize,
+        #[arg(long, value_parser = crate::log::RateLimit::parse)]
+        log_rate_limit: Option<crate::log::RateLimit>,
+        /// Drop output while this file exists
+        #[arg(long, value_name = "PATH")]
+        log_quota_flag: Option<std::path::PathBuf>,
+    },
+    /// Show a process's recorded CPU, memory and restarts over time
+    Stats {
+        name: String,
+        /// How far back to look (e.g. 30m, 1h, 1d)
+        #[arg(long, value_parser = crate::config::parse_duration, default_value = "1h")]
+        since: std::time::Duration,
+    },
+    /// Show the processes a process spawned, with their CPU and memory
+    Tree {
+        /// Process, group or pattern; every running process when omitted
+        name: Option<String>,
+    },
+    /// Show availability, MTTR and crash frequency over trailing windows
+    Report {
+        /// Only this process; every process when omitted
+        name: Option<String>,
+        /// Windows to report over (repeatable or comma-separated, e.g. 1d,7d,30d)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_parser = crate::config::parse_duration,
+            default_value = "1d,7d,30d"
+        )]
+        window: Vec<std::time::Duration>,
+        /// Write a diagnostics archive (logs, dump, configs, process details) to this path instead
+        #[arg(long, value_name = "PATH", conflicts_with = "name")]
+        bundle: Option<std::path::PathBuf>,
+        /// Replace env values in the bundled configs and process details
+        #[arg(long, requires = "bundle")]
+        redact: bool,
+        /// Lines kept from the end of each process log in the bundle
+        #[arg(long, default_value_t = 200, requires = "bundle")]
+        log_lines: usize,
+    },
+    /// Show recent state-changing requests from the audit log
+    History {
+        #[arg(long, default_value_t = 20)]
+        lines: usize,
+    },
     /// Generate a system service file for boot auto-start
-    Startup,
+    Startup {
+        /// Service manager to install into: launchd, systemd, openrc or sysv
+        /// (detected when omitted)
+        #[arg(long)]
+        platform: Option<crate::startup::Platform>,
+    },
     /// Remove the generated system service file
-    Unstartup,
+    Unstartup {
+        /// Service manager to remove the service from (detected when omitted)
+        #[arg(long)]
+        platform: Option<crate::startup::Platform>,
+    },
     /// View process logs
     Log {
         name: Option<String>,
@@ -59,13 +321,204 @@ pub enum Command {
         lines: usize,
         #[arg(short, long)]
         follow: bool,
+        /// Only lines logged since then: a duration ago like "15m", or a
+        /// date and time like "2024-05-01 12:00" (needs log_date_format)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only lines logged until then, in the same forms as --since
+        #[arg(long, conflicts_with = "follow")]
+        until: Option<String>,
+        /// Only show stderr
+        #[arg(long, conflicts_with = "out")]
+        err: bool,
+        /// Only show stdout
+        #[arg(long)]
+        out: bool,
     },
+    /// Stream process events (added, removed, status changes, restarts)
+    Events { names: Vec<String> },
+}
+
+/// A column of the `pm3 list` table. Anything that isn't a built-in column
+/// name refers to a custom metric scraped from the process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListColumn {
+    Name,
+    Group,
+    Pid,
+    Status,
+    Cpu,
+    Mem,
+    Uptime,
+    Restarts,
+    Tags,
+    Fds,
+    Threads,
+    Sockets,
+    Read,
+    Write,
+    Health,
+    Metric(String),
+}
+
+impl ListColumn {
+    pub const DEFAULT: [ListColumn; 8] = [
+        ListColumn::Name,
+        ListColumn::Group,
+        ListColumn::Pid,
+        ListColumn::Status,
+        ListColumn::Cpu,
+        ListColumn::Mem,
+        ListColumn::Uptime,
+        ListColumn::Restarts,
+    ];
+}
+
+impl std::fmt::Display for ListColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListColumn::Name => write!(f, "name"),
+            ListColumn::Group => write!(f, "group"),
+            ListColumn::Pid => write!(f, "pid"),
+            ListColumn::Status => write!(f, "status"),
+            ListColumn::Cpu => write!(f, "cpu"),
+            ListColumn::Mem => write!(f, "mem"),
+            ListColumn::Uptime => write!(f, "uptime"),
+            ListColumn::Restarts => write!(f, "restarts"),
+            ListColumn::Tags => write!(f, "tags"),
+            ListColumn::Fds => write!(f, "fds"),
+            ListColumn::Threads => write!(f, "threads"),
+            ListColumn::Sockets => write!(f, "sockets"),
+            ListColumn::Read => write!(f, "read"),
+            ListColumn::Write => write!(f, "write"),
+            ListColumn::Health => write!(f, "health"),
+            ListColumn::Metric(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl std::str::FromStr for ListColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(ListColumn::Name),
+            "group" => Ok(ListColumn::Group),
+            "pid" => Ok(ListColumn::Pid),
+            "status" => Ok(ListColumn::Status),
+            "cpu" => Ok(ListColumn::Cpu),
+            "mem" | "memory" => Ok(ListColumn::Mem),
+            "uptime" => Ok(ListColumn::Uptime),
+            "restarts" => Ok(ListColumn::Restarts),
+            "tags" => Ok(ListColumn::Tags),
+            "fds" => Ok(ListColumn::Fds),
+            "threads" => Ok(ListColumn::Threads),
+            "sockets" => Ok(ListColumn::Sockets),
+            "read" => Ok(ListColumn::Read),
+            "write" => Ok(ListColumn::Write),
+            "health" => Ok(ListColumn::Health),
+            metric
+                if !metric.is_empty()
+                    && metric
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+                    && !metric.starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                Ok(ListColumn::Metric(metric.to_string()))
+            }
+            other => Err(format!("unknown column '{other}'")),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DaemonCommand {
+    /// Start the daemon in the background, or in this terminal with --foreground
+    Start {
+        /// Run in this process instead of detaching, e.g. under a supervisor
+        /// or while debugging
+        #[arg(long)]
+        foreground: bool,
+        /// Also accept remote clients on this TCP address
+        #[arg(long, value_name = "ADDR")]
+        listen: Option<std::net::SocketAddr>,
+        /// The file holding the token remote clients must send
+        #[arg(long, value_name = "PATH")]
+        auth_token_file: Option<std::path::PathBuf>,
+        /// Restore the processes saved by `pm3 save` on startup and log a
+        /// missing dump file (used by the `pm3 startup` service)
+        #[arg(long)]
+        resurrect: bool,
+    },
+    /// Stop every process and shut the daemon down
+    Stop,
+    /// Show whether the daemon is running, with its pid, version and uptime
+    Status,
+    /// Restart the daemon on the pm3 binary now installed, keeping every
+    /// process running
+    Upgrade,
+}
+
+/// What `pm3 list --group-by` sums processes by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListGroupBy {
+    Group,
+}
+
+impl std::str::FromStr for ListGroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "group" => Ok(ListGroupBy::Group),
+            other => Err(format!("unknown field '{other}' (expected group)")),
+        }
+    }
+}
+
+/// Output format of `pm3 graph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Ascii,
+    Dot,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(GraphFormat::Ascii),
+            "dot" => Ok(GraphFormat::Dot),
+            other => Err(format!(
+                "unknown graph format '{other}' (expected ascii or dot)"
+            )),
+        }
+    }
 }
 
 impl Command {
     pub fn optional_names(names: Vec<String>) -> Option<Vec<String>> {
         if names.is_empty() { None } else { Some(names) }
     }
+
+    /// Like `optional_names`, wrapping each name as a `/regex/` when `regex` is set.
+    pub fn selected_names(names: Vec<String>, regex: bool) -> Option<Vec<String>> {
+        if regex {
+            Self::optional_names(crate::select::as_regex_names(names))
+        } else {
+            Self::optional_names(names)
+        }
+    }
+
+    /// Like `optional_names`, but also treats a lone `all` as every process.
+    pub fn optional_names_or_all(names: Vec<String>) -> Option<Vec<String>> {
+        if names == ["all"] {
+            None
+        } else {
+            Self::optional_names(names)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -78,7 +531,9 @@ mod tests {
     fn test_start_no_args() {
         let cli = Cli::try_parse_from(["pm3", "start"]).unwrap();
         match cli.command.unwrap() {
-            Command::Start { names, env, wait } => {
+            Command::Start {
+                names, env, wait, ..
+            } => {
                 assert!(names.is_empty());
                 assert!(env.is_none());
                 assert!(!wait);
@@ -91,7 +546,7 @@ mod tests {
     fn test_stop_no_args() {
         let cli = Cli::try_parse_from(["pm3", "stop"]).unwrap();
         match cli.command.unwrap() {
-            Command::Stop { names } => assert!(names.is_empty()),
+            Command::Stop { names, .. } => assert!(names.is_empty()),
             _ => panic!("expected Stop"),
         }
     }
@@ -100,15 +555,301 @@ mod tests {
     fn test_restart_no_args() {
         let cli = Cli::try_parse_from(["pm3", "restart"]).unwrap();
         match cli.command.unwrap() {
-            Command::Restart { names } => assert!(names.is_empty()),
+            Command::Restart { names, .. } => assert!(names.is_empty()),
+            _ => panic!("expected Restart"),
+        }
+    }
+
+    #[test]
+    fn test_restart_with_except() {
+        let cli = Cli::try_parse_from(["pm3", "restart", "--except", "db", "cache"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Restart { names, except, .. } => {
+                assert!(names.is_empty());
+                assert_eq!(except, vec!["db", "cache"]);
+            }
             _ => panic!("expected Restart"),
         }
+
+        let cli = Cli::try_parse_from(["pm3", "stop", "web", "--except=web:0,web:1"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Stop { names, except, .. } => {
+                assert_eq!(names, vec!["web"]);
+                assert_eq!(except, vec!["web:0", "web:1"]);
+            }
+            _ => panic!("expected Stop"),
+        }
+    }
+
+    #[test]
+    fn test_restart_stagger() {
+        let cli = Cli::try_parse_from(["pm3", "restart", "web", "--stagger", "5s"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Restart { names, stagger, .. } => {
+                assert_eq!(names, vec!["web"]);
+                assert_eq!(stagger, Some(std::time::Duration::from_secs(5)));
+            }
+            _ => panic!("expected Restart"),
+        }
+    }
+
+    #[test]
+    fn test_daemon_logs() {
+        let cli = Cli::try_parse_from(["pm3", "daemon-logs", "-f", "--lines", "50"]).unwrap();
+        match cli.command.unwrap() {
+            Command::DaemonLogs { lines, follow } => {
+                assert_eq!(lines, 50);
+                assert!(follow);
+            }
+            _ => panic!("expected DaemonLogs"),
+        }
+    }
+
+    #[test]
+    fn test_log_relay() {
+        let cli = Cli::try_parse_from([
+            "pm3",
+            "log-relay",
+            "--stdout-log",
+            "/logs/web-out.log",
+            "--stderr-log",
+            "/logs/web-err.log",
+            "--log-instance",
+            "web:0",
+            "--log-rate-limit",
+            "100/1000ms",
+        ])
+        .unwrap();
+        match cli.command.unwrap() {
+            Command::LogRelay {
+                stdout_log,
+                stderr_log,
+                log_instance,
+                log_date_format,
+                log_max_line_len,
+                log_rate_limit,
+                log_quota_flag,
+            } => {
+                assert_eq!(stdout_log, std::path::Path::new("/logs/web-out.log"));
+                assert_eq!(stderr_log, std::path::Path::new("/logs/web-err.log"));
+                assert_eq!(log_instance.as_deref(), Some("web:0"));
+                assert_eq!(log_date_format, None);
+                assert_eq!(log_max_line_len, crate::log::DEFAULT_MAX_LINE_LEN);
+                assert_eq!(
+                    log_rate_limit,
+                    Some(crate::log::RateLimit {
+                        lines: 100,
+                        per: std::time::Duration::from_secs(1),
+                    })
+                );
+                assert_eq!(log_quota_flag, None);
+            }
+            _ => panic!("expected LogRelay"),
+        }
+    }
+
+    #[test]
+    fn test_history_lines() {
+        let cli = Cli::try_parse_from(["pm3", "history"]).unwrap();
+        match cli.command.unwrap() {
+            Command::History { lines } => assert_eq!(lines, 20),
+            _ => panic!("expected History"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "history", "--lines", "5"]).unwrap();
+        match cli.command.unwrap() {
+            Command::History { lines } => assert_eq!(lines, 5),
+            _ => panic!("expected History"),
+        }
+    }
+
+    #[test]
+    fn test_stats_since() {
+        let cli = Cli::try_parse_from(["pm3", "stats", "web"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Stats { name, since } => {
+                assert_eq!(name, "web");
+                assert_eq!(since, std::time::Duration::from_secs(3600));
+            }
+            _ => panic!("expected Stats"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "stats", "web", "--since", "30m"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Stats { since, .. } => {
+                assert_eq!(since, std::time::Duration::from_secs(1800))
+            }
+            _ => panic!("expected Stats"),
+        }
+    }
+
+    #[test]
+    fn test_report_windows() {
+        let cli = Cli::try_parse_from(["pm3", "report"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Report { name, window, .. } => {
+                assert_eq!(name, None);
+                assert_eq!(
+                    window,
+                    vec![
+                        std::time::Duration::from_secs(86_400),
+                        std::time::Duration::from_secs(7 * 86_400),
+                        std::time::Duration::from_secs(30 * 86_400),
+                    ]
+                );
+            }
+            _ => panic!("expected Report"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "report", "web", "--window", "12h,1d"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Report { name, window, .. } => {
+                assert_eq!(name.as_deref(), Some("web"));
+                assert_eq!(
+                    window,
+                    vec![
+                        std::time::Duration::from_secs(12 * 3600),
+                        std::time::Duration::from_secs(86_400),
+                    ]
+                );
+            }
+            _ => panic!("expected Report"),
+        }
+
+        let cli =
+            Cli::try_parse_from(["pm3", "report", "--bundle", "out.tar.gz", "--redact"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Report {
+                bundle,
+                redact,
+                log_lines,
+                ..
+            } => {
+                assert_eq!(bundle, Some(std::path::PathBuf::from("out.tar.gz")));
+                assert!(redact);
+                assert_eq!(log_lines, 200);
+            }
+            _ => panic!("expected Report"),
+        }
+        assert!(Cli::try_parse_from(["pm3", "report", "--redact"]).is_err());
+        assert!(Cli::try_parse_from(["pm3", "report", "web", "--bundle", "out.tar.gz"]).is_err());
+    }
+
+    #[test]
+    fn test_graph_format() {
+        let cli = Cli::try_parse_from(["pm3", "graph"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Graph { format } => assert_eq!(format, GraphFormat::Ascii),
+            _ => panic!("expected Graph"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "graph", "--format", "dot"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Graph { format } => assert_eq!(format, GraphFormat::Dot),
+            _ => panic!("expected Graph"),
+        }
+
+        assert!(Cli::try_parse_from(["pm3", "graph", "--format", "svg"]).is_err());
+    }
+
+    #[test]
+    fn test_list_filters() {
+        let cli = Cli::try_parse_from([
+            "pm3",
+            "list",
+            "--status",
+            "errored,failed",
+            "--group",
+            "backend",
+        ])
+        .unwrap();
+        match cli.command.unwrap() {
+            Command::List {
+                status, group, tag, ..
+            } => {
+                assert_eq!(status, vec![ProcessStatus::Errored, ProcessStatus::Failed]);
+                assert_eq!(group, vec!["backend"]);
+                assert!(tag.is_empty());
+            }
+            _ => panic!("expected List"),
+        }
+        assert!(Cli::try_parse_from(["pm3", "list", "--status", "bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_list_sort_and_columns() {
+        let cli = Cli::try_parse_from([
+            "pm3",
+            "list",
+            "--sort",
+            "mem",
+            "--columns",
+            "name,status,memory,fds,queue_depth",
+        ])
+        .unwrap();
+        match cli.command.unwrap() {
+            Command::List { sort, columns, .. } => {
+                assert_eq!(sort, Some(ListColumn::Mem));
+                assert_eq!(
+                    columns,
+                    vec![
+                        ListColumn::Name,
+                        ListColumn::Status,
+                        ListColumn::Mem,
+                        ListColumn::Fds,
+                        ListColumn::Metric("queue_depth".to_string()),
+                    ]
+                );
+            }
+            _ => panic!("expected List"),
+        }
+        assert!(Cli::try_parse_from(["pm3", "list", "--columns", "name,bad-col"]).is_err());
+    }
+
+    #[test]
+    fn test_list_group_by() {
+        let cli = Cli::try_parse_from(["pm3", "list", "--group-by", "group"]).unwrap();
+        match cli.command.unwrap() {
+            Command::List { group_by, .. } => assert_eq!(group_by, Some(ListGroupBy::Group)),
+            _ => panic!("expected List"),
+        }
+        assert!(Cli::try_parse_from(["pm3", "list", "--group-by", "color"]).is_err());
+    }
+
+    #[test]
+    fn test_namespace_flags() {
+        let cli = Cli::try_parse_from(["pm3", "list", "--namespace", "shop"]).unwrap();
+        assert_eq!(cli.namespace.as_deref(), Some("shop"));
+        assert!(!cli.all_namespaces);
+
+        let cli = Cli::try_parse_from(["pm3", "--all-namespaces", "stop"]).unwrap();
+        assert!(cli.all_namespaces);
+
+        assert!(
+            Cli::try_parse_from(["pm3", "list", "--namespace", "shop", "--all-namespaces"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_stop_with_regex() {
+        let cli = Cli::try_parse_from(["pm3", "stop", "--regex", "^web-\\d+$"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Stop { names, regex, .. } => {
+                assert!(regex);
+                assert_eq!(
+                    Command::selected_names(names, regex),
+                    Some(vec!["/^web-\\d+$/".to_string()])
+                );
+            }
+            _ => panic!("expected Stop"),
+        }
     }
 
     #[test]
     fn test_list() {
         let cli = Cli::try_parse_from(["pm3", "list"]).unwrap();
-        assert!(matches!(cli.command.unwrap(), Command::List));
+        assert!(matches!(cli.command.unwrap(), Command::List { .. }));
     }
 
     #[test]
@@ -132,13 +873,47 @@ mod tests {
     #[test]
     fn test_startup() {
         let cli = Cli::try_parse_from(["pm3", "startup"]).unwrap();
-        assert!(matches!(cli.command.unwrap(), Command::Startup));
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Startup { platform: None }
+        ));
+    }
+
+    #[test]
+    fn test_startup_platform() {
+        let cli = Cli::try_parse_from(["pm3", "startup", "--platform", "openrc"]).unwrap();
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Startup {
+                platform: Some(crate::startup::Platform::Openrc)
+            }
+        ));
+        assert!(Cli::try_parse_from(["pm3", "startup", "--platform", "upstart"]).is_err());
     }
 
     #[test]
     fn test_unstartup() {
-        let cli = Cli::try_parse_from(["pm3", "unstartup"]).unwrap();
-        assert!(matches!(cli.command.unwrap(), Command::Unstartup));
+        let cli = Cli::try_parse_from(["pm3", "unstartup", "--platform", "sysv"]).unwrap();
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Unstartup {
+                platform: Some(crate::startup::Platform::Sysv)
+            }
+        ));
+    }
+
+    #[test]
+    fn test_profile_and_daemons() {
+        let cli = Cli::try_parse_from(["pm3", "--profile", "work", "start"]).unwrap();
+        assert_eq!(cli.profile.as_deref(), Some("work"));
+        let cli = Cli::try_parse_from(["pm3", "daemons"]).unwrap();
+        assert!(matches!(cli.command.unwrap(), Command::Daemons));
+    }
+
+    #[test]
+    fn test_ping() {
+        let cli = Cli::try_parse_from(["pm3", "ping"]).unwrap();
+        assert!(matches!(cli.command.unwrap(), Command::Ping));
     }
 
     // Names handling
@@ -165,7 +940,9 @@ mod tests {
     fn test_start_with_env() {
         let cli = Cli::try_parse_from(["pm3", "start", "--env", "production"]).unwrap();
         match cli.command.unwrap() {
-            Command::Start { names, env, wait } => {
+            Command::Start {
+                names, env, wait, ..
+            } => {
                 assert!(names.is_empty());
                 assert_eq!(env.as_deref(), Some("production"));
                 assert!(!wait);
@@ -178,7 +955,9 @@ mod tests {
     fn test_start_with_name_and_env() {
         let cli = Cli::try_parse_from(["pm3", "start", "web", "--env", "staging"]).unwrap();
         match cli.command.unwrap() {
-            Command::Start { names, env, wait } => {
+            Command::Start {
+                names, env, wait, ..
+            } => {
                 assert_eq!(names, vec!["web"]);
                 assert_eq!(env.as_deref(), Some("staging"));
                 assert!(!wait);
@@ -191,7 +970,9 @@ mod tests {
     fn test_start_with_wait() {
         let cli = Cli::try_parse_from(["pm3", "start", "--wait"]).unwrap();
         match cli.command.unwrap() {
-            Command::Start { names, env, wait } => {
+            Command::Start {
+                names, env, wait, ..
+            } => {
                 assert!(names.is_empty());
                 assert!(env.is_none());
                 assert!(wait);
@@ -200,6 +981,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_start_with_force() {
+        let cli = Cli::try_parse_from(["pm3", "start", "web", "--force"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Start { names, force, .. } => {
+                assert_eq!(names, vec!["web"]);
+                assert!(force);
+            }
+            _ => panic!("expected Start"),
+        }
+    }
+
     #[test]
     fn test_start_with_short_wait() {
         let cli = Cli::try_parse_from(["pm3", "start", "-w"]).unwrap();
@@ -213,17 +1006,34 @@ mod tests {
 
     // Remaining subcommands
 
+    #[test]
+    fn test_run() {
+        let cli = Cli::try_parse_from(["pm3", "run", "migrate", "--env", "prod"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Run { name, env } => {
+                assert_eq!(name, "migrate");
+                assert_eq!(env.as_deref(), Some("prod"));
+            }
+            _ => panic!("expected Run"),
+        }
+    }
+
+    #[test]
+    fn test_run_missing_name() {
+        assert!(Cli::try_parse_from(["pm3", "run"]).is_err());
+    }
+
     #[test]
     fn test_reload() {
         let cli = Cli::try_parse_from(["pm3", "reload"]).unwrap();
         match cli.command.unwrap() {
-            Command::Reload { names } => assert!(names.is_empty()),
+            Command::Reload { names, .. } => assert!(names.is_empty()),
             _ => panic!("expected Reload"),
         }
 
         let cli = Cli::try_parse_from(["pm3", "reload", "web"]).unwrap();
         match cli.command.unwrap() {
-            Command::Reload { names } => assert_eq!(names, vec!["web"]),
+            Command::Reload { names, .. } => assert_eq!(names, vec!["web"]),
             _ => panic!("expected Reload"),
         }
     }
@@ -232,11 +1042,69 @@ mod tests {
     fn test_info() {
         let cli = Cli::try_parse_from(["pm3", "info", "web"]).unwrap();
         match cli.command.unwrap() {
-            Command::Info { name } => assert_eq!(name, "web"),
+            Command::Info { name, watch } => {
+                assert_eq!(name, "web");
+                assert!(!watch);
+            }
+            _ => panic!("expected Info"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "info", "web", "--watch"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Info { watch, .. } => assert!(watch),
             _ => panic!("expected Info"),
         }
     }
 
+    #[test]
+    fn test_wait() {
+        let cli = Cli::try_parse_from(["pm3", "wait", "web"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Wait {
+                names,
+                timeout,
+                status,
+            } => {
+                assert_eq!(names, vec!["web"]);
+                assert_eq!(timeout, std::time::Duration::from_secs(60));
+                assert_eq!(status, ProcessStatus::Online);
+            }
+            _ => panic!("expected Wait"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "pm3",
+            "wait",
+            "backend",
+            "--timeout",
+            "2m",
+            "--status",
+            "stopped",
+        ])
+        .unwrap();
+        match cli.command.unwrap() {
+            Command::Wait {
+                timeout, status, ..
+            } => {
+                assert_eq!(timeout, std::time::Duration::from_secs(120));
+                assert_eq!(status, ProcessStatus::Stopped);
+            }
+            _ => panic!("expected Wait"),
+        }
+
+        assert!(Cli::try_parse_from(["pm3", "wait"]).is_err());
+        assert!(Cli::try_parse_from(["pm3", "wait", "web", "--timeout", "soon"]).is_err());
+    }
+
+    #[test]
+    fn test_status() {
+        let cli = Cli::try_parse_from(["pm3", "status", "web"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Status { name } => assert_eq!(name, "web"),
+            _ => panic!("expected Status"),
+        }
+    }
+
     #[test]
     fn test_signal() {
         let cli = Cli::try_parse_from(["pm3", "signal", "web", "SIGHUP"]).unwrap();
@@ -252,28 +1120,169 @@ mod tests {
     #[test]
     fn test_save() {
         let cli = Cli::try_parse_from(["pm3", "save"]).unwrap();
-        assert!(matches!(cli.command.unwrap(), Command::Save));
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Save {
+                tag: None,
+                if_running: false
+            }
+        ));
+
+        let cli =
+            Cli::try_parse_from(["pm3", "save", "--tag", "pre-deploy", "--if-running"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Save { tag, if_running } => {
+                assert_eq!(tag.as_deref(), Some("pre-deploy"));
+                assert!(if_running);
+            }
+            _ => panic!("expected Save"),
+        }
     }
 
     #[test]
     fn test_resurrect() {
         let cli = Cli::try_parse_from(["pm3", "resurrect"]).unwrap();
-        assert!(matches!(cli.command.unwrap(), Command::Resurrect));
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Resurrect { from: None }
+        ));
+
+        let cli = Cli::try_parse_from(["pm3", "resurrect", "--from", "pre-deploy"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Resurrect { from } => assert_eq!(from.as_deref(), Some("pre-deploy")),
+            _ => panic!("expected Resurrect"),
+        }
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let cli = Cli::try_parse_from(["pm3", "snapshots"]).unwrap();
+        assert!(matches!(cli.command.unwrap(), Command::Snapshots));
+    }
+
+    #[test]
+    fn test_export() {
+        let cli = Cli::try_parse_from(["pm3", "export"]).unwrap();
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Export { output: None }
+        ));
+
+        let cli = Cli::try_parse_from(["pm3", "export", "-o", "pm3.toml"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Export { output } => {
+                assert_eq!(output, Some(std::path::PathBuf::from("pm3.toml")))
+            }
+            _ => panic!("expected Export"),
+        }
+    }
+
+    #[test]
+    fn test_suspend_and_continue() {
+        let cli = Cli::try_parse_from(["pm3", "suspend", "web"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Suspend { name } => assert_eq!(name, "web"),
+            _ => panic!("expected Suspend"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "continue", "web"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Continue { name } => assert_eq!(name, "web"),
+            _ => panic!("expected Continue"),
+        }
+        assert!(Cli::try_parse_from(["pm3", "suspend"]).is_err());
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let cli = Cli::try_parse_from(["pm3", "pause", "web"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Pause { names } => assert_eq!(names, vec!["web"]),
+            _ => panic!("expected Pause"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "resume"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Resume { names } => assert!(names.is_empty()),
+            _ => panic!("expected Resume"),
+        }
+    }
+
+    #[test]
+    fn test_optional_names_or_all() {
+        assert_eq!(
+            Command::optional_names_or_all(vec!["all".to_string()]),
+            None
+        );
+        assert_eq!(Command::optional_names_or_all(vec![]), None);
+        assert_eq!(
+            Command::optional_names_or_all(vec!["web".to_string()]),
+            Some(vec!["web".to_string()])
+        );
     }
 
     #[test]
     fn test_flush() {
         let cli = Cli::try_parse_from(["pm3", "flush"]).unwrap();
         match cli.command.unwrap() {
-            Command::Flush { names } => assert!(names.is_empty()),
+            Command::Flush { names, all_files } => {
+                assert!(names.is_empty());
+                assert!(!all_files);
+            }
             _ => panic!("expected Flush"),
         }
 
         let cli = Cli::try_parse_from(["pm3", "flush", "web"]).unwrap();
         match cli.command.unwrap() {
-            Command::Flush { names } => assert_eq!(names, vec!["web"]),
+            Command::Flush { names, .. } => assert_eq!(names, vec!["web"]),
+            _ => panic!("expected Flush"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "flush", "--all-files"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Flush { all_files, .. } => assert!(all_files),
             _ => panic!("expected Flush"),
         }
+        assert!(Cli::try_parse_from(["pm3", "flush", "web", "--all-files"]).is_err());
+    }
+
+    #[test]
+    fn test_reopen_logs() {
+        let cli = Cli::try_parse_from(["pm3", "reopen-logs", "web"]).unwrap();
+        match cli.command.unwrap() {
+            Command::ReopenLogs { names } => assert_eq!(names, vec!["web"]),
+            _ => panic!("expected ReopenLogs"),
+        }
+    }
+
+    #[test]
+    fn test_tree() {
+        let cli = Cli::try_parse_from(["pm3", "tree"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Tree { name } => assert_eq!(name, None),
+            _ => panic!("expected Tree"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "tree", "web"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Tree { name } => assert_eq!(name.as_deref(), Some("web")),
+            _ => panic!("expected Tree"),
+        }
+    }
+
+    #[test]
+    fn test_prune() {
+        let cli = Cli::try_parse_from(["pm3", "prune"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Prune { logs } => assert!(!logs),
+            _ => panic!("expected Prune"),
+        }
+
+        let cli = Cli::try_parse_from(["pm3", "prune", "--logs"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Prune { logs } => assert!(logs),
+            _ => panic!("expected Prune"),
+        }
     }
 
     #[test]
@@ -284,6 +1293,7 @@ mod tests {
                 name,
                 lines,
                 follow,
+                ..
             } => {
                 assert!(name.is_none());
                 assert_eq!(lines, 15);
@@ -301,6 +1311,7 @@ mod tests {
                 name,
                 lines,
                 follow,
+                ..
             } => {
                 assert_eq!(name.as_deref(), Some("web"));
                 assert_eq!(lines, 50);
@@ -310,10 +1321,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_log_time_range() {
+        let cli = Cli::try_parse_from([
+            "pm3",
+            "log",
+            "web",
+            "--since",
+            "15m",
+            "--until",
+            "2024-05-01 12:00",
+        ])
+        .unwrap();
+        match cli.command.unwrap() {
+            Command::Log { since, until, .. } => {
+                assert_eq!(since.as_deref(), Some("15m"));
+                assert_eq!(until.as_deref(), Some("2024-05-01 12:00"));
+            }
+            _ => panic!("expected Log"),
+        }
+        assert!(Cli::try_parse_from(["pm3", "log", "--until", "1h", "-f"]).is_err());
+    }
+
+    #[test]
+    fn test_log_stream_selection() {
+        let cli = Cli::try_parse_from(["pm3", "log", "web", "--err"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Log { err, out, .. } => assert!(err && !out),
+            _ => panic!("expected Log"),
+        }
+        assert!(Cli::try_parse_from(["pm3", "log", "--err", "--out"]).is_err());
+    }
+
+    #[test]
+    fn test_events_names() {
+        let cli = Cli::try_parse_from(["pm3", "events", "web", "worker"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Events { names } => assert_eq!(names, vec!["web", "worker"]),
+            _ => panic!("expected Events"),
+        }
+    }
+
     #[test]
     fn test_list_view_alias() {
         let cli = Cli::try_parse_from(["pm3", "view"]).unwrap();
-        assert!(matches!(cli.command.unwrap(), Command::List));
+        assert!(matches!(cli.command.unwrap(), Command::List { .. }));
     }
 
     // Error cases
@@ -333,15 +1385,171 @@ mod tests {
         assert!(Cli::try_parse_from(["pm3", "signal"]).is_err());
     }
 
-    // Daemon flag
+    // Daemon flag and subcommand
 
     #[test]
     fn test_daemon_flag() {
         let cli = Cli::try_parse_from(["pm3", "--daemon"]).unwrap();
         assert!(cli.daemon);
+        assert!(!cli.resurrect);
         assert!(cli.command.is_none());
     }
 
+    #[test]
+    fn test_daemon_resurrect_flag() {
+        let cli = Cli::try_parse_from(["pm3", "--daemon", "--resurrect"]).unwrap();
+        assert!(cli.resurrect);
+        assert!(Cli::try_parse_from(["pm3", "--resurrect"]).is_err());
+    }
+
+    #[test]
+    fn test_daemon_listen_flags() {
+        let cli = Cli::try_parse_from([
+            "pm3",
+            "--daemon",
+            "--listen",
+            "0.0.0.0:7878",
+            "--auth-token-file",
+            "/etc/pm3/token",
+        ])
+        .unwrap();
+        assert_eq!(cli.listen, Some("0.0.0.0:7878".parse().unwrap()));
+        assert_eq!(
+            cli.auth_token_file.as_deref(),
+            Some(std::path::Path::new("/etc/pm3/token"))
+        );
+
+        assert!(Cli::try_parse_from(["pm3", "--listen", "0.0.0.0:7878"]).is_err());
+        assert!(Cli::try_parse_from(["pm3", "--daemon", "--listen", "nope"]).is_err());
+    }
+
+    #[test]
+    fn test_daemon_start() {
+        let cli = Cli::try_parse_from(["pm3", "daemon", "start"]).unwrap();
+        match cli.command.unwrap() {
+            Command::Daemon {
+                action:
+                    DaemonCommand::Start {
+                        foreground,
+                        listen,
+                        resurrect,
+                        ..
+                    },
+            } => {
+                assert!(!foreground);
+                assert_eq!(listen, None);
+                assert!(!resurrect);
+            }
+            other => panic!("expected daemon start, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_daemon_start_flags() {
+        let cli = Cli::try_parse_from([
+            "pm3",
+            "daemon",
+            "start",
+            "--foreground",
+            "--resurrect",
+            "--listen",
+            "0.0.0.0:7878",
+            "--auth-token-file",
+            "/etc/pm3/token",
+        ])
+        .unwrap();
+        match cli.command.unwrap() {
+            Command::Daemon {
+                action:
+                    DaemonCommand::Start {
+                        foreground,
+                        listen,
+                        auth_token_file,
+                        resurrect,
+                    },
+            } => {
+                assert!(foreground);
+                assert!(resurrect);
+                assert_eq!(listen, Some("0.0.0.0:7878".parse().unwrap()));
+                assert_eq!(
+                    auth_token_file.as_deref(),
+                    Some(std::path::Path::new("/etc/pm3/token"))
+                );
+            }
+            other => panic!("expected daemon start, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_daemon_stop_and_status() {
+        let cli = Cli::try_parse_from(["pm3", "daemon", "stop"]).unwrap();
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Daemon {
+                action: DaemonCommand::Stop
+            }
+        ));
+        let cli = Cli::try_parse_from(["pm3", "daemon", "status"]).unwrap();
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Daemon {
+                action: DaemonCommand::Status
+            }
+        ));
+        assert!(Cli::try_parse_from(["pm3", "daemon"]).is_err());
+    }
+
+    #[test]
+    fn test_daemon_upgrade_and_handover() {
+        let cli = Cli::try_parse_from(["pm3", "daemon", "upgrade"]).unwrap();
+        assert!(matches!(
+            cli.command.unwrap(),
+            Command::Daemon {
+                action: DaemonCommand::Upgrade
+            }
+        ));
+        // How an upgrading daemon execs its replacement
+        let cli = Cli::try_parse_from([
+            "pm3",
+            "--handover",
+            "/tmp/handover.json",
+            "daemon",
+            "start",
+            "--foreground",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.handover,
+            Some(std::path::PathBuf::from("/tmp/handover.json"))
+        );
+        assert!(
+            Cli::try_parse_from(["pm3", "--handover-version"])
+                .unwrap()
+                .handover_version
+        );
+    }
+
+    #[test]
+    fn test_autostart_flags() {
+        let cli = Cli::try_parse_from(["pm3", "list", "--no-autostart"]).unwrap();
+        assert!(cli.no_autostart);
+        assert!(!cli.autostart);
+        let cli = Cli::try_parse_from(["pm3", "--autostart", "start"]).unwrap();
+        assert!(cli.autostart);
+        assert!(Cli::try_parse_from(["pm3", "list", "--autostart", "--no-autostart"]).is_err());
+    }
+
+    #[test]
+    fn test_host_flag() {
+        let cli = Cli::try_parse_from(["pm3", "list", "--host", "deploy@web1"]).unwrap();
+        assert_eq!(cli.host.as_deref(), Some("deploy@web1"));
+        assert!(matches!(cli.command, Some(Command::List { .. })));
+
+        let cli = Cli::try_parse_from(["pm3", "--proxy", "--profile", "work"]).unwrap();
+        assert!(cli.proxy);
+        assert_eq!(cli.profile.as_deref(), Some("work"));
+    }
+
     #[test]
     fn test_no_args_no_command() {
         let cli = Cli::try_parse_from(["pm3"]).unwrap();
@@ -1,25 +1,316 @@
+use crate::config::ProcessConfig;
 use crate::paths::Paths;
 use crate::pid;
-use crate::protocol::{self, Request, Response};
+use crate::protocol::{
+    self, Auth, AvailabilityReport, DaemonInfo, ProcessDetail, ProcessInfo, Request, Response,
+    StatsSample,
+};
 use crate::sys;
+use crate::tls;
 use color_eyre::eyre::{Context, bail};
-use std::io::{BufRead, BufReader, Write};
-use std::time::Duration;
+use rustls::{ClientConfig, ClientConnection, StreamOwned};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, mpsc};
+use std::time::{Duration, Instant};
+
+/// `host:port` of a remote daemon to talk to over TCP instead of the local socket.
+pub const REMOTE_ENV: &str = "PM3_REMOTE";
+/// The auth token for `PM3_REMOTE`.
+pub const AUTH_TOKEN_ENV: &str = "PM3_AUTH_TOKEN";
+/// A file holding the auth token, used when `PM3_AUTH_TOKEN` is unset.
+pub const AUTH_TOKEN_FILE_ENV: &str = "PM3_AUTH_TOKEN_FILE";
+/// CA certificates (PEM) to verify the remote daemon with; setting it enables TLS.
+pub const REMOTE_CA_ENV: &str = "PM3_REMOTE_CA";
+/// Client certificate (PEM) to present to a daemon that requires one.
+pub const REMOTE_CERT_ENV: &str = "PM3_REMOTE_CERT";
+/// Private key (PEM) for `PM3_REMOTE_CERT`.
+pub const REMOTE_KEY_ENV: &str = "PM3_REMOTE_KEY";
+
+/// The ssh client to run for `--host`, default `ssh`.
+pub const SSH_ENV: &str = "PM3_SSH";
+/// Path of the pm3 binary on `--host` machines, default `pm3`.
+pub const SSH_BIN_ENV: &str = "PM3_SSH_BIN";
+
+/// Set to make commands fail instead of starting a local daemon when none
+/// is running. `0` and `false` leave autostart on.
+pub const NO_AUTOSTART_ENV: &str = "PM3_NO_AUTOSTART";
+
+static AUTOSTART: OnceLock<bool> = OnceLock::new();
+
+/// Decides, for this process and regardless of `PM3_NO_AUTOSTART`, whether
+/// requests may start a local daemon when none is running.
+pub fn set_autostart(autostart: bool) {
+    let _ = AUTOSTART.set(autostart);
+}
+
+fn autostart_enabled() -> bool {
+    match AUTOSTART.get() {
+        Some(autostart) => *autostart,
+        None => env_var(NO_AUTOSTART_ENV).is_none_or(|v| matches!(v.as_str(), "0" | "false")),
+    }
+}
+
+/// A daemon on another machine reached by running `pm3 --proxy` over SSH.
+#[derive(Debug, Clone)]
+struct SshTarget {
+    host: String,
+    profile: Option<String>,
+}
+
+static SSH_TARGET: OnceLock<SshTarget> = OnceLock::new();
+
+/// Sends every request from this process through `ssh <host>` to the daemon
+/// there (in `profile`, if given) instead of the local one.
+pub fn use_ssh_host(host: String, profile: Option<String>) {
+    let _ = SSH_TARGET.set(SshTarget { host, profile });
+}
+
+/// Where requests go: `--host` wins over `PM3_REMOTE`, which wins over the
+/// local daemon.
+enum Target {
+    Local,
+    Tcp(Remote),
+    Ssh(&'static SshTarget),
+}
+
+impl Target {
+    fn resolve() -> color_eyre::Result<Self> {
+        if let Some(ssh) = SSH_TARGET.get() {
+            return Ok(Target::Ssh(ssh));
+        }
+        Ok(match remote_from_env()? {
+            Some(remote) => Target::Tcp(remote),
+            None => Target::Local,
+        })
+    }
+}
+
+/// A remote daemon picked via `PM3_REMOTE`.
+struct Remote {
+    addr: String,
+    token: Option<String>,
+    tls: Option<Arc<ClientConfig>>,
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn remote_from_env() -> color_eyre::Result<Option<Remote>> {
+    let Some(addr) = env_var(REMOTE_ENV) else {
+        return Ok(None);
+    };
+    let token = match (env_var(AUTH_TOKEN_ENV), env_var(AUTH_TOKEN_FILE_ENV)) {
+        (Some(token), _) => Some(token),
+        (None, Some(path)) => Some(
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read auth token from {path}"))?
+                .trim()
+                .to_string(),
+        ),
+        (None, None) => None,
+    };
+    let identity = match (env_var(REMOTE_CERT_ENV), env_var(REMOTE_KEY_ENV)) {
+        (Some(cert), Some(key)) => Some((cert, key)),
+        (None, None) => None,
+        _ => bail!("{REMOTE_CERT_ENV} and {REMOTE_KEY_ENV} must be set together"),
+    };
+    let tls = match env_var(REMOTE_CA_ENV) {
+        Some(ca) => {
+            let identity = identity
+                .as_ref()
+                .map(|(cert, key)| (Path::new(cert), Path::new(key)));
+            Some(tls::client_config(Path::new(&ca), identity)?)
+        }
+        None if identity.is_some() => {
+            bail!("{REMOTE_CERT_ENV} needs {REMOTE_CA_ENV} to verify the daemon")
+        }
+        None => None,
+    };
+    if token.is_none() && identity.is_none() {
+        bail!(
+            "{REMOTE_ENV} is set but neither {AUTH_TOKEN_ENV}, {AUTH_TOKEN_FILE_ENV} nor {REMOTE_CERT_ENV} is"
+        );
+    }
+    Ok(Some(Remote { addr, token, tls }))
+}
+
+/// A connection to the daemon, over the local socket, TCP, TLS or SSH.
+enum Connection {
+    Local(sys::SyncIpcStream),
+    Remote(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+    Ssh(SshConnection),
+}
+
+/// The stdin/stdout of an `ssh <host> pm3 --proxy` child.
+struct SshConnection {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: ChildStdout,
+}
+
+impl SshConnection {
+    fn spawn(target: &SshTarget) -> color_eyre::Result<Self> {
+        let ssh = std::env::var(SSH_ENV).unwrap_or_else(|_| "ssh".to_string());
+        let bin = std::env::var(SSH_BIN_ENV).unwrap_or_else(|_| "pm3".to_string());
+        // ssh hands the remote command to a shell as one string, so each
+        // word is quoted; `--` keeps a host starting with `-` from being
+        // read as an option.
+        let mut remote = vec![bin.as_str(), "--proxy"];
+        if let Some(profile) = &target.profile {
+            remote.extend(["--profile", profile.as_str()]);
+        }
+        let mut child = std::process::Command::new(&ssh)
+            .arg("-T")
+            .arg("--")
+            .arg(&target.host)
+            .arg(shell_words::join(remote))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to run {ssh}"))?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().expect("stdout is piped");
+        Ok(Self {
+            child,
+            stdin,
+            stdout,
+        })
+    }
+}
+
+impl Drop for SshConnection {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Connection {
+    /// Bounds blocking reads so a reader thread can notice it should stop.
+    /// Reads over SSH can't time out and block until the next line.
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Connection::Local(stream) => stream.set_read_timeout(timeout),
+            Connection::Remote(stream) => stream.set_read_timeout(timeout),
+            Connection::Tls(stream) => stream.sock.set_read_timeout(timeout),
+            Connection::Ssh(_) => Ok(()),
+        }
+    }
+
+    fn shutdown_write(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Local(stream) => stream.shutdown(std::net::Shutdown::Write),
+            Connection::Remote(stream) => stream.shutdown(std::net::Shutdown::Write),
+            Connection::Tls(stream) => {
+                stream.conn.send_close_notify();
+                stream.flush()
+            }
+            Connection::Ssh(ssh) => {
+                // Closing the pipe is the proxy's cue to half-close the socket
+                ssh.stdin.take();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Local(stream) => stream.read(buf),
+            Connection::Remote(stream) => stream.read(buf),
+            Connection::Tls(stream) => stream.read(buf),
+            Connection::Ssh(ssh) => ssh.stdout.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Local(stream) => stream.write(buf),
+            Connection::Remote(stream) => stream.write(buf),
+            Connection::Tls(stream) => stream.write(buf),
+            Connection::Ssh(ssh) => match &mut ssh.stdin {
+                Some(stdin) => stdin.write(buf),
+                None => Err(io::ErrorKind::BrokenPipe.into()),
+            },
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Local(stream) => stream.flush(),
+            Connection::Remote(stream) => stream.flush(),
+            Connection::Tls(stream) => stream.flush(),
+            Connection::Ssh(ssh) => match &mut ssh.stdin {
+                Some(stdin) => stdin.flush(),
+                None => Ok(()),
+            },
+        }
+    }
+}
 
 pub fn send_request(paths: &Paths, request: &Request) -> color_eyre::Result<Response> {
-    ensure_daemon_running(paths)?;
-    let mut stream = connect_with_retry(paths, 10, Duration::from_millis(200))?;
+    let target = Target::resolve()?;
+    if let Target::Local = target {
+        ensure_daemon_running(paths)?;
+    }
+    exchange(paths, &target, request)
+}
 
-    let encoded = protocol::encode_request(request)?;
-    stream.write_all(&encoded)?;
-    stream.shutdown(std::net::Shutdown::Write)?;
+/// Asks a running daemon to identify itself, timing the round trip. Unlike
+/// other requests this never spawns a local daemon.
+pub fn ping(paths: &Paths) -> color_eyre::Result<(Response, Duration)> {
+    let target = Target::resolve()?;
+    if let Target::Local = target
+        && !pid::is_daemon_running_sync(paths)?
+    {
+        bail!(
+            "daemon is not running (data dir: {})",
+            paths.data_dir().display()
+        );
+    }
+    let started = Instant::now();
+    let response = exchange(paths, &target, &Request::DaemonInfo)?;
+    Ok((response, started.elapsed()))
+}
 
-    let mut reader = BufReader::new(stream);
+fn exchange(paths: &Paths, target: &Target, request: &Request) -> color_eyre::Result<Response> {
+    let mut reader = send(paths, target, request)?;
     let mut line = String::new();
     reader.read_line(&mut line)?;
+    if line.is_empty() {
+        match target {
+            Target::Ssh(_) => bail!("no response from the daemon over ssh"),
+            // Daemons from before protocol versioning hang up on requests they can't parse
+            _ => bail!(
+                "daemon closed the connection without responding; it may be older than this CLI, restart it with `pm3 kill`"
+            ),
+        }
+    }
 
-    let response = protocol::decode_response(&line)?;
-    Ok(response)
+    decode_response(&line)
+}
+
+/// Decodes a response, explaining failures caused by a daemon that speaks a
+/// different protocol version.
+fn decode_response(line: &str) -> color_eyre::Result<Response> {
+    protocol::decode_response(line).or_else(|e| {
+        match protocol::message_version(line)
+            .and_then(|version| protocol::version_mismatch("daemon", version))
+        {
+            Some(message) => bail!(message),
+            None => Err(e.into()),
+        }
+    })
 }
 
 pub fn send_request_streaming<F>(
@@ -30,58 +321,540 @@ pub fn send_request_streaming<F>(
 where
     F: FnMut(&Response),
 {
-    ensure_daemon_running(paths)?;
-    let mut stream = connect_with_retry(paths, 10, Duration::from_millis(200))?;
-
-    let encoded = protocol::encode_request(request)?;
-    stream.write_all(&encoded)?;
-    stream.shutdown(std::net::Shutdown::Write)?;
-
-    let reader = BufReader::new(stream);
+    let target = Target::resolve()?;
+    if let Target::Local = target {
+        ensure_daemon_running(paths)?;
+    }
+    let reader = send(paths, &target, request)?;
     for line_result in reader.lines() {
         let line = line_result?;
         if line.is_empty() {
             continue;
         }
-        let response = protocol::decode_response(&line)?;
+        let response = decode_response(&line)?;
         on_response(&response);
     }
 
     Ok(())
 }
 
-fn ensure_daemon_running(paths: &Paths) -> color_eyre::Result<()> {
-    if pid::is_daemon_running_sync(paths)? {
-        return Ok(());
+/// How often a `BackgroundStream` reader checks whether it was dropped.
+const BACKGROUND_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A streaming request, such as a followed log, read on its own thread so a
+/// synchronous UI can pick up responses without blocking. Dropping it ends
+/// the stream.
+pub struct BackgroundStream {
+    responses: mpsc::Receiver<Response>,
+    stop: Arc<AtomicBool>,
+}
+
+impl BackgroundStream {
+    pub fn spawn(paths: &Paths, request: &Request) -> color_eyre::Result<Self> {
+        let target = Target::resolve()?;
+        if let Target::Local = target {
+            ensure_daemon_running(paths)?;
+        }
+        let mut reader = send(paths, &target, request)?;
+        reader
+            .get_ref()
+            .set_read_timeout(Some(BACKGROUND_POLL_INTERVAL))?;
+        let (tx, responses) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            while !stopped.load(Ordering::Relaxed) {
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        let line = String::from_utf8_lossy(&buf).into_owned();
+                        buf.clear();
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let response = decode_response(&line).unwrap_or_else(|e| Response::Error {
+                            message: e.to_string(),
+                        });
+                        if tx.send(response).is_err() {
+                            break;
+                        }
+                    }
+                    // A partial line stays in `buf` until the rest arrives
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) => {}
+                    Err(e) => {
+                        let _ = tx.send(Response::Error {
+                            message: e.to_string(),
+                        });
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Self { responses, stop })
     }
 
-    spawn_daemon()?;
+    /// The responses received since the last call, without waiting.
+    pub fn drain(&self) -> Vec<Response> {
+        self.responses.try_iter().collect()
+    }
+}
 
-    // Wait for IPC endpoint to appear
-    for _ in 0..50 {
-        if sys::ipc_exists(paths) {
-            return Ok(());
+impl Drop for BackgroundStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Connects, sends `request` (after the auth line for a remote daemon) and
+/// returns the connection for reading responses.
+fn send(
+    paths: &Paths,
+    target: &Target,
+    request: &Request,
+) -> color_eyre::Result<BufReader<Connection>> {
+    let mut conn = connect(paths, target)?;
+    let encoded = protocol::encode_request(request)?;
+    conn.write_all(&encoded)?;
+    conn.shutdown_write()?;
+
+    Ok(BufReader::new(conn))
+}
+
+/// Opens a connection, authenticating first if the daemon is remote.
+fn connect(paths: &Paths, target: &Target) -> color_eyre::Result<Connection> {
+    Ok(match target {
+        Target::Tcp(remote) => {
+            let stream = TcpStream::connect(&remote.addr).with_context(|| {
+                format!("failed to connect to remote daemon at {}", remote.addr)
+            })?;
+            let mut conn = match &remote.tls {
+                Some(config) => {
+                    let tls =
+                        ClientConnection::new(config.clone(), tls::server_name(&remote.addr)?)?;
+                    Connection::Tls(Box::new(StreamOwned::new(tls, stream)))
+                }
+                None => Connection::Remote(stream),
+            };
+            if let Some(token) = &remote.token {
+                let auth = Auth {
+                    token: token.clone(),
+                };
+                conn.write_all(&protocol::encode_auth(&auth)?)?;
+            }
+            conn
+        }
+        Target::Ssh(ssh) => Connection::Ssh(SshConnection::spawn(ssh)?),
+        Target::Local => {
+            Connection::Local(connect_with_retry(paths, 10, Duration::from_millis(200))?)
+        }
+    })
+}
+
+/// A connection kept open for many requests, so pollers like the TUI don't
+/// reconnect every time. Requests are sent one at a time; the daemon would
+/// also serve them concurrently if they were interleaved.
+pub struct Session {
+    conn: BufReader<Connection>,
+    next_id: u64,
+}
+
+impl Session {
+    pub fn open(paths: &Paths) -> color_eyre::Result<Self> {
+        let target = Target::resolve()?;
+        if let Target::Local = target {
+            ensure_daemon_running(paths)?;
+        }
+        let mut conn = connect(paths, &target)?;
+        conn.write_all(&protocol::encode_request(&Request::Session)?)?;
+        let mut conn = BufReader::new(conn);
+        let mut line = String::new();
+        conn.read_line(&mut line)?;
+        if line.is_empty() {
+            bail!("daemon closed the connection instead of starting a session");
+        }
+        match decode_response(&line)? {
+            Response::Success { .. } => Ok(Self { conn, next_id: 1 }),
+            Response::Error { message } => bail!(message),
+            other => bail!("unexpected response to session request: {other:?}"),
         }
-        std::thread::sleep(Duration::from_millis(100));
     }
 
-    bail!("timed out waiting for daemon to start");
+    /// Sends `request` and returns its last response, skipping any progress
+    /// updates before it.
+    pub fn request(&mut self, request: &Request) -> color_eyre::Result<Response> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let conn = self.conn.get_mut();
+        conn.write_all(&protocol::encode_tagged_request(id, request)?)?;
+        conn.flush()?;
+
+        let mut last = None;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.conn.read_line(&mut line)? == 0 {
+                bail!("daemon closed the session");
+            }
+            let tagged = protocol::decode_tagged_response(&line)?;
+            if tagged.id != id {
+                // Pushes from the daemon
+                continue;
+            }
+            match tagged.message {
+                Response::Done => break,
+                response => last = Some(response),
+            }
+        }
+        last.ok_or_else(|| color_eyre::eyre::eyre!("daemon sent no response"))
+    }
+}
+
+/// An async client for other Rust programs that want to control a daemon
+/// without running the CLI. Each call opens its own connection, so the
+/// client is cheap to clone and share between tasks. Unlike the CLI it
+/// never starts a daemon.
+#[derive(Clone)]
+pub struct DaemonClient {
+    endpoint: Endpoint,
+}
+
+#[derive(Clone)]
+enum Endpoint {
+    Local(Paths),
+    Tcp { addr: String, token: Option<String> },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("failed to reach the daemon: {0}")]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Protocol(#[from] protocol::ProtocolError),
+    /// The daemon answered with an error.
+    #[error("{0}")]
+    Daemon(String),
+    #[error("{0}")]
+    VersionMismatch(String),
+    #[error("unexpected response from the daemon: {0:?}")]
+    Unexpected(Box<Response>),
+    #[error("daemon closed the connection without responding")]
+    NoResponse,
+}
+
+trait AsyncConnection: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send {}
+
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send> AsyncConnection for T {}
+
+/// The responses to a streaming request such as `tail_logs` or `events`.
+pub struct ResponseStream {
+    lines: tokio::io::Lines<tokio::io::BufReader<Box<dyn AsyncConnection>>>,
+}
+
+impl ResponseStream {
+    /// The next response, or `None` once the daemon is done.
+    pub async fn next(&mut self) -> Result<Option<Response>, ClientError> {
+        loop {
+            let Some(line) = self.lines.next_line().await? else {
+                return Ok(None);
+            };
+            if line.is_empty() {
+                continue;
+            }
+            return decode_async_response(&line).map(Some);
+        }
+    }
+}
+
+fn decode_async_response(line: &str) -> Result<Response, ClientError> {
+    protocol::decode_response(line).map_err(|e| {
+        match protocol::message_version(line)
+            .and_then(|version| protocol::version_mismatch("daemon", version))
+        {
+            Some(message) => ClientError::VersionMismatch(message),
+            None => e.into(),
+        }
+    })
+}
+
+impl DaemonClient {
+    /// A client for the daemon that owns `paths`' data dir.
+    pub fn new(paths: Paths) -> Self {
+        Self {
+            endpoint: Endpoint::Local(paths),
+        }
+    }
+
+    /// A client for a daemon's remote listener at `addr` (`host:port`),
+    /// authenticating with `token` if one is given. TLS is not supported.
+    pub fn remote(addr: impl Into<String>, token: Option<String>) -> Self {
+        Self {
+            endpoint: Endpoint::Tcp {
+                addr: addr.into(),
+                token,
+            },
+        }
+    }
+
+    /// Sends any request and returns every response to it.
+    pub async fn stream(&self, request: &Request) -> Result<ResponseStream, ClientError> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut conn: Box<dyn AsyncConnection> = match &self.endpoint {
+            Endpoint::Local(paths) => Box::new(sys::ipc_connect_async(paths).await?),
+            Endpoint::Tcp { addr, token } => {
+                let mut stream = tokio::net::TcpStream::connect(addr).await?;
+                if let Some(token) = token {
+                    let auth = Auth {
+                        token: token.clone(),
+                    };
+                    stream.write_all(&protocol::encode_auth(&auth)?).await?;
+                }
+                Box::new(stream)
+            }
+        };
+        conn.write_all(&protocol::encode_request(request)?).await?;
+        conn.shutdown().await?;
+        Ok(ResponseStream {
+            lines: tokio::io::AsyncBufReadExt::lines(tokio::io::BufReader::new(conn)),
+        })
+    }
+
+    /// Sends a request and returns its final response. An error response
+    /// becomes `ClientError::Daemon`.
+    pub async fn request(&self, request: &Request) -> Result<Response, ClientError> {
+        let mut responses = self.stream(request).await?;
+        let mut last = None;
+        while let Some(response) = responses.next().await? {
+            last = Some(response);
+        }
+        match last {
+            Some(Response::Error { message }) => Err(ClientError::Daemon(message)),
+            Some(response) => Ok(response),
+            None => Err(ClientError::NoResponse),
+        }
+    }
+
+    /// Sends a request that answers with `Success`, returning its message.
+    async fn command(&self, request: &Request) -> Result<Option<String>, ClientError> {
+        match self.request(request).await? {
+            Response::Success { message } => Ok(message),
+            other => Err(ClientError::Unexpected(Box::new(other))),
+        }
+    }
+
+    /// Starts `names` (every process when `None`) from `configs`, which is
+    /// what `config::load_config` returns for a pm3.toml.
+    pub async fn start(
+        &self,
+        configs: HashMap<String, ProcessConfig>,
+        names: Option<Vec<String>>,
+    ) -> Result<Option<String>, ClientError> {
+        self.command(&Request::Start {
+            configs,
+            names,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        })
+        .await
+    }
+
+    /// Stops `names`, or every process when `None`.
+    pub async fn stop(&self, names: Option<Vec<String>>) -> Result<Option<String>, ClientError> {
+        self.command(&Request::Stop {
+            names,
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        })
+        .await
+    }
+
+    /// Restarts `names`, or every process when `None`.
+    pub async fn restart(&self, names: Option<Vec<String>>) -> Result<Option<String>, ClientError> {
+        self.command(&Request::Restart {
+            names,
+            except: None,
+            namespace: None,
+            stagger_ms: None,
+        })
+        .await
+    }
+
+    pub async fn list(&self) -> Result<Vec<ProcessInfo>, ClientError> {
+        match self.request(&Request::List { filter: None }).await? {
+            Response::ProcessList { processes, .. } => Ok(processes),
+            other => Err(ClientError::Unexpected(Box::new(other))),
+        }
+    }
+
+    pub async fn info(&self, name: &str) -> Result<ProcessDetail, ClientError> {
+        let request = Request::Info {
+            name: name.to_string(),
+        };
+        match self.request(&request).await? {
+            Response::ProcessDetail { info } => Ok(*info),
+            other => Err(ClientError::Unexpected(Box::new(other))),
+        }
+    }
+
+    pub async fn daemon_info(&self) -> Result<DaemonInfo, ClientError> {
+        match self.request(&Request::DaemonInfo).await? {
+            Response::DaemonInfo { info } => Ok(info),
+            other => Err(ClientError::Unexpected(Box::new(other))),
+        }
+    }
+
+    /// The recorded resource samples of `name`, oldest first, from the last
+    /// `since` or all that are kept.
+    pub async fn stats(
+        &self,
+        name: &str,
+        since: Option<std::time::Duration>,
+    ) -> Result<Vec<StatsSample>, ClientError> {
+        let request = Request::Stats {
+            name: name.to_string(),
+            since_secs: since.map(|d| d.as_secs()),
+        };
+        match self.request(&request).await? {
+            Response::Stats { samples, .. } => Ok(samples),
+            other => Err(ClientError::Unexpected(Box::new(other))),
+        }
+    }
+
+    /// Availability of `name`, or of every process, over each of `windows`.
+    pub async fn report(
+        &self,
+        name: Option<&str>,
+        windows: &[std::time::Duration],
+    ) -> Result<Vec<AvailabilityReport>, ClientError> {
+        let request = Request::Report {
+            name: name.map(str::to_string),
+            windows_secs: windows.iter().map(|d| d.as_secs()).collect(),
+        };
+        match self.request(&request).await? {
+            Response::Report { reports } => Ok(reports),
+            other => Err(ClientError::Unexpected(Box::new(other))),
+        }
+    }
+
+    /// The last `lines` log lines of `name` (every process when `None`) as
+    /// `LogLine` responses, followed by new lines as they are written if
+    /// `follow` is set.
+    pub async fn tail_logs(
+        &self,
+        name: Option<&str>,
+        lines: usize,
+        follow: bool,
+    ) -> Result<ResponseStream, ClientError> {
+        self.stream(&Request::Log {
+            name: name.map(str::to_string),
+            lines,
+            follow,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        })
+        .await
+    }
+
+    /// Process events as `Event` responses, for `names` or every process.
+    pub async fn events(&self, names: Option<Vec<String>>) -> Result<ResponseStream, ClientError> {
+        self.stream(&Request::Events { names }).await
+    }
 }
 
-fn spawn_daemon() -> color_eyre::Result<()> {
+/// Bridges stdin/stdout to the local daemon's socket, starting the daemon if
+/// needed. This is what `--host` runs on the far side of SSH.
+pub fn proxy_stdio(paths: &Paths) -> color_eyre::Result<()> {
+    ensure_daemon_running(paths)?;
+    let mut stream = connect_with_retry(paths, 10, Duration::from_millis(200))?;
+    let mut upstream = stream.try_clone()?;
+    let forward = std::thread::spawn(move || -> io::Result<()> {
+        io::copy(&mut io::stdin().lock(), &mut upstream)?;
+        upstream.shutdown(std::net::Shutdown::Write)
+    });
+
+    let mut stdout = io::stdout().lock();
+    io::copy(&mut stream, &mut stdout)?;
+    stdout.flush()?;
+    // The daemon has answered; a forwarder still blocked on stdin can be abandoned
+    if forward.is_finished() {
+        forward
+            .join()
+            .map_err(|_| color_eyre::eyre::eyre!("stdin forwarder panicked"))??;
+    }
+    Ok(())
+}
+
+fn ensure_daemon_running(paths: &Paths) -> color_eyre::Result<()> {
+    if pid::is_daemon_running_sync(paths)? {
+        return Ok(());
+    }
+    if !autostart_enabled() {
+        bail!(
+            "daemon is not running (data dir: {}) and autostart is off; start it with `pm3 daemon start`",
+            paths.data_dir().display()
+        );
+    }
+    start_daemon(paths, &[])
+}
+
+/// Starts a daemon for `paths` in the background, as `pm3 daemon start
+/// --foreground` plus `args`, and waits until it accepts connections. The
+/// daemon gets its own session, so closing the terminal doesn't stop it, and
+/// its stdout and stderr go to `daemon.log`.
+pub fn start_daemon(paths: &Paths, args: &[std::ffi::OsString]) -> color_eyre::Result<()> {
     let exe = std::env::current_exe().context("failed to get current executable path")?;
+    std::fs::create_dir_all(paths.data_dir())
+        .with_context(|| format!("failed to create {}", paths.data_dir().display()))?;
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(paths.daemon_log())
+        .with_context(|| format!("failed to open {}", paths.daemon_log().display()))?;
 
     let mut cmd = std::process::Command::new(exe);
-    cmd.arg("--daemon")
-        .stdin(std::process::Stdio::null())
-        .stdout(std::process::Stdio::null())
-        .stderr(std::process::Stdio::null());
+    // Point the daemon at the same data dir, so profiles get their own daemon
+    cmd.args(["daemon", "start", "--foreground"])
+        .args(args)
+        .env("PM3_DATA_DIR", paths.data_dir())
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
 
     sys::configure_daemon_cmd(&mut cmd);
 
-    cmd.spawn().context("failed to spawn daemon")?;
+    let mut child = cmd.spawn().context("failed to spawn daemon")?;
 
-    Ok(())
+    // Wait for IPC endpoint to appear
+    for _ in 0..50 {
+        if sys::ipc_exists(paths) {
+            return Ok(());
+        }
+        if let Some(status) = child.try_wait()? {
+            // Another client may have started the daemon first
+            if pid::is_daemon_running_sync(paths)? {
+                return Ok(());
+            }
+            bail!(
+                "daemon exited during startup ({status}); see {}",
+                paths.daemon_log().display()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    bail!("timed out waiting for daemon to start");
 }
 
 fn connect_with_retry(
@@ -1,6 +1,6 @@
 use crate::env_file;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -11,6 +11,57 @@ pub enum RestartPolicy {
     Never,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessKind {
+    Service,
+    Task,
+}
+
+/// How a process reports that it is ready, moving it from Starting to Online.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadySignal {
+    /// The process writes a line to the file named by `PM3_READY_PATH`.
+    Ipc,
+    /// `readiness_check` passes.
+    Http,
+    /// `ready_delay` milliseconds pass without the process exiting.
+    Delay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartBackoff {
+    Fixed,
+    Linear,
+    Exponential,
+}
+
+/// What happens when a running process keeps failing its `health_check`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnhealthyAction {
+    /// Restart it, counting toward `max_restarts` like a crash.
+    Restart,
+    /// Stop it and leave it stopped.
+    Stop,
+    /// Only mark it unhealthy, and online again once checks pass.
+    None,
+}
+
+/// What happens when `memory_leak_window` finds memory growing toward
+/// `max_memory`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeakAction {
+    /// Only report it, as a `memory_leak` event.
+    Event,
+    /// Report it, and have `cron_restart` restart the process only while
+    /// memory is leaking, so it is restarted off-peak before it hits the limit.
+    CronRestart,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum EnvFile {
@@ -32,6 +83,9 @@ impl EnvFile {
 pub enum Watch {
     Enabled(bool),
     Path(String),
+    /// Globs like `src/**/*.rs`, relative to `cwd`: only changes to matching
+    /// files restart the process.
+    Globs(Vec<String>),
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -40,60 +94,206 @@ pub struct ProcessConfig {
     pub cwd: Option<String>,
     pub env: Option<HashMap<String, String>>,
     pub env_file: Option<EnvFile>,
+    /// Variables set to the trimmed output of a command run before each
+    /// spawn, e.g. `{ DB_PASSWORD = "vault kv get -field=pw db" }`.
+    pub env_cmd: Option<HashMap<String, String>>,
     pub readiness_check: Option<String>,
     pub readiness_timeout: Option<u64>,
+    pub ready_signal: Option<ReadySignal>,
+    pub ready_delay: Option<u64>,
     pub health_check: Option<String>,
+    /// Keep checking `health_check` while the process runs, and act on it
+    /// failing `unhealthy_threshold` times in a row.
+    pub on_unhealthy: Option<UnhealthyAction>,
+    pub unhealthy_threshold: Option<u32>,
     pub kill_timeout: Option<u64>,
     pub kill_signal: Option<String>,
+    pub drain_signal: Option<String>,
+    pub drain_timeout: Option<u64>,
+    pub port: Option<u16>,
+    pub increment_port: Option<bool>,
     pub max_restarts: Option<u32>,
     pub max_memory: Option<String>,
+    /// How often memory is checked against `max_memory`, e.g. `"2s"`.
+    pub memory_check_interval: Option<String>,
+    /// What happens when memory goes over `max_memory`: `restart` (the
+    /// default), `stop`, `signal:<SIGNAL>` or `event-only`.
+    pub memory_action: Option<String>,
+    /// How far back memory use is looked at to spot it growing toward
+    /// `max_memory`, e.g. `"30m"`.
+    pub memory_leak_window: Option<String>,
+    pub memory_leak_action: Option<LeakAction>,
     pub min_uptime: Option<u64>,
     pub stop_exit_codes: Option<Vec<i32>>,
     pub watch: Option<Watch>,
     pub watch_ignore: Option<Vec<String>>,
+    /// Only changes to files with these extensions restart the process.
+    pub watch_extensions: Option<Vec<String>>,
+    /// Skip paths the project's `.gitignore` and `.ignore` files exclude.
+    pub watch_use_gitignore: Option<bool>,
+    /// How long to collect file changes before restarting, e.g. `"2s"`.
+    pub watch_debounce: Option<String>,
+    /// How long to wait after the debounce before restarting, for tools that
+    /// keep writing, e.g. `"1s"`.
+    pub watch_delay: Option<String>,
+    /// Scan for changes at this interval, e.g. `"2s"`, instead of relying on
+    /// filesystem notifications, which NFS and some bind mounts don't send.
+    pub watch_poll: Option<String>,
+    /// Restart the process when one of its `env_file`s changes.
+    pub watch_env_files: Option<bool>,
+    /// A command to run on file changes before restarting, e.g. a build.
+    pub watch_command: Option<String>,
+    /// Whether file changes restart the process after `watch_command`
+    /// (default true).
+    pub watch_restart: Option<bool>,
     pub depends_on: Option<Vec<String>>,
+    /// The `depends_on` entries marked `required = false`.
+    pub optional_deps: Option<Vec<String>>,
+    /// Whether stopping this process also stops its dependents (default true).
+    pub stop_dependents: Option<bool>,
     pub restart: Option<RestartPolicy>,
     pub group: Option<String>,
+    pub tags: Option<Vec<String>>,
     pub pre_start: Option<String>,
     pub post_stop: Option<String>,
     pub cron_restart: Option<String>,
+    pub cron_start: Option<String>,
+    pub cron_stop: Option<String>,
+    pub cron_timezone: Option<String>,
     pub log_date_format: Option<String>,
+    /// Writes stdout and stderr to one `<name>.log`, each line tagged with
+    /// its stream.
+    pub log_combine: Option<bool>,
+    /// Has cluster instances share one `<name>.log`, each line tagged with
+    /// its instance and stream.
+    pub merge_logs: Option<bool>,
+    /// Where stdout is logged instead of `<name>-out.log`; relative paths
+    /// are resolved against `cwd`.
+    pub stdout_log: Option<String>,
+    /// Where stderr is logged instead of `<name>-err.log`.
+    pub stderr_log: Option<String>,
+    /// Bytes of a line that are logged; the rest is left out.
+    pub log_max_line_len: Option<usize>,
+    /// Most lines logged per period, e.g. `"1000/s"`; the rest are dropped
+    /// and counted in a marker line.
+    pub log_rate_limit: Option<String>,
     pub instances: Option<u32>,
+    /// Extra env for individual cluster instances, keyed by instance index.
+    pub instance_env: Option<BTreeMap<u32, HashMap<String, String>>>,
+    pub flap_threshold: Option<u32>,
+    pub flap_window: Option<u64>,
+    pub flap_cooldown: Option<u64>,
+    pub conflict_check: Option<String>,
+    pub restart_delay: Option<u64>,
+    pub restart_backoff: Option<RestartBackoff>,
+    pub max_restart_delay: Option<u64>,
+    pub max_restarts_window: Option<String>,
+    /// Milliseconds to wait before respawning this process during a bulk restart.
+    pub restart_stagger: Option<u64>,
+    pub kind: Option<ProcessKind>,
+    pub metrics_endpoint: Option<String>,
+    pub metrics: Option<Vec<String>>,
+    #[serde(default)]
     pub environments: HashMap<String, HashMap<String, String>>,
+    /// Other fields set by `[name.environments.<environment>]`, as written.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub environment_overrides: HashMap<String, toml::Table>,
 }
 
 impl ProcessConfig {
-    /// Merge a named environment into `env`. Returns true if applied.
-    pub fn apply_environment(&mut self, env_name: &str) -> bool {
-        let Some(env_vars) = self.environments.get(env_name) else {
-            return false;
-        };
-        let base = self.env.get_or_insert_with(HashMap::new);
-        for (k, v) in env_vars {
-            base.insert(k.clone(), v.clone());
+    /// Whether `name` is this process's group or one of its tags.
+    pub fn in_group_or_tag(&self, name: &str) -> bool {
+        self.group.as_deref() == Some(name) || self.tags.iter().flatten().any(|t| t == name)
+    }
+
+    /// Whether the process stays Starting until a readiness signal or
+    /// startup probe passes.
+    pub fn has_startup_probe(&self) -> bool {
+        self.readiness_check.is_some()
+            || self.health_check.is_some()
+            || matches!(
+                self.ready_signal,
+                Some(ReadySignal::Ipc | ReadySignal::Delay)
+            )
+    }
+
+    /// One-shot tasks are expected to exit and are never restarted.
+    pub fn is_task(&self) -> bool {
+        self.kind == Some(ProcessKind::Task)
+    }
+
+    /// Apply a named environment: its overrides replace the fields they set
+    /// and its variables are merged into `env`. Returns true if applied.
+    pub fn apply_environment(&mut self, name: &str, env_name: &str) -> Result<bool, ConfigError> {
+        let overrides = self.environment_overrides.get(env_name);
+        if overrides.is_none() && !self.environments.contains_key(env_name) {
+            return Ok(false);
+        }
+        if let Some(overrides) = overrides {
+            let mut base = self.clone();
+            base.environments.clear();
+            base.environment_overrides.clear();
+            let mut process =
+                process_table(base).map_err(|e| ConfigError::TomlParse(e.to_string()))?;
+            process.extend(overrides.clone());
+            let mut merged = parse_process(name, process.into(), None)?;
+            merged.environments = std::mem::take(&mut self.environments);
+            merged.environment_overrides = std::mem::take(&mut self.environment_overrides);
+            *self = merged;
         }
-        true
+        if let Some(env_vars) = self.environments.get(env_name) {
+            let base = self.env.get_or_insert_with(HashMap::new);
+            for (k, v) in env_vars {
+                base.insert(k.clone(), v.clone());
+            }
+        }
+        Ok(true)
     }
 
-    /// Load env file variables, resolving relative paths against `cwd` when set.
-    pub fn load_env_files(&self) -> Result<HashMap<String, String>, env_file::EnvFileError> {
-        let mut env_file_vars = HashMap::new();
+    /// Every table of env vars: `env`, each `instance_env` and each
+    /// environment's variables.
+    pub fn env_tables_mut(&mut self) -> impl Iterator<Item = &mut HashMap<String, String>> {
+        self.env
+            .iter_mut()
+            .chain(
+                self.instance_env
+                    .iter_mut()
+                    .flat_map(|tables| tables.values_mut()),
+            )
+            .chain(self.environments.values_mut())
+    }
+
+    /// The `env_file` paths, with relative ones resolved against `cwd` when set.
+    pub fn env_file_paths(&self) -> Vec<PathBuf> {
         let Some(env_file) = &self.env_file else {
-            return Ok(env_file_vars);
+            return Vec::new();
         };
+        env_file
+            .paths()
+            .into_iter()
+            .map(|file_path| self.resolve_path(file_path))
+            .collect()
+    }
 
-        for file_path in env_file.paths() {
-            let path = Path::new(file_path);
-            let resolved: PathBuf = if path.is_relative() {
-                if let Some(ref cwd) = self.cwd {
-                    PathBuf::from(cwd).join(path)
-                } else {
-                    path.to_path_buf()
-                }
-            } else {
-                path.to_path_buf()
-            };
+    /// How many bytes of a line are logged.
+    pub fn max_log_line_len(&self) -> usize {
+        self.log_max_line_len
+            .unwrap_or(crate::log::DEFAULT_MAX_LINE_LEN)
+    }
+
+    /// `path`, resolved against `cwd` when it is relative and `cwd` is set.
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        match &self.cwd {
+            Some(cwd) if path.is_relative() => PathBuf::from(cwd).join(path),
+            _ => path.to_path_buf(),
+        }
+    }
 
+    /// Load env file variables, resolving relative paths against `cwd` when set.
+    pub fn load_env_files(&self) -> Result<HashMap<String, String>, env_file::EnvFileError> {
+        let mut env_file_vars = HashMap::new();
+        for resolved in self.env_file_paths() {
             let vars = env_file::load_env_file(&resolved)?;
             env_file_vars.extend(vars);
         }
@@ -108,25 +308,87 @@ struct RawProcessConfig {
     cwd: Option<String>,
     env: Option<HashMap<String, String>>,
     env_file: Option<EnvFile>,
+    env_cmd: Option<HashMap<String, String>>,
     readiness_check: Option<String>,
+    #[serde(default, deserialize_with = "millis")]
     readiness_timeout: Option<u64>,
+    ready_signal: Option<ReadySignal>,
+    #[serde(default, deserialize_with = "millis")]
+    ready_delay: Option<u64>,
     health_check: Option<String>,
+    on_unhealthy: Option<UnhealthyAction>,
+    unhealthy_threshold: Option<u32>,
+    #[serde(default, deserialize_with = "millis")]
     kill_timeout: Option<u64>,
     kill_signal: Option<String>,
+    drain_signal: Option<String>,
+    #[serde(default, deserialize_with = "millis")]
+    drain_timeout: Option<u64>,
+    port: Option<u16>,
+    increment_port: Option<bool>,
     max_restarts: Option<u32>,
     max_memory: Option<String>,
+    #[serde(default, deserialize_with = "duration_text")]
+    memory_check_interval: Option<String>,
+    memory_action: Option<String>,
+    #[serde(default, deserialize_with = "duration_text")]
+    memory_leak_window: Option<String>,
+    memory_leak_action: Option<LeakAction>,
+    #[serde(default, deserialize_with = "millis")]
     min_uptime: Option<u64>,
     stop_exit_codes: Option<Vec<i32>>,
     watch: Option<Watch>,
     watch_ignore: Option<Vec<String>>,
-    depends_on: Option<Vec<String>>,
+    watch_extensions: Option<Vec<String>>,
+    watch_use_gitignore: Option<bool>,
+    #[serde(default, deserialize_with = "duration_text")]
+    watch_debounce: Option<String>,
+    #[serde(default, deserialize_with = "duration_text")]
+    watch_delay: Option<String>,
+    #[serde(default, deserialize_with = "duration_text")]
+    watch_poll: Option<String>,
+    watch_env_files: Option<bool>,
+    watch_command: Option<String>,
+    watch_restart: Option<bool>,
+    depends_on: Option<Vec<Dependency>>,
+    stop_dependents: Option<bool>,
     restart: Option<RestartPolicy>,
     group: Option<String>,
+    tags: Option<Vec<String>>,
     pre_start: Option<String>,
     post_stop: Option<String>,
     cron_restart: Option<String>,
+    cron_start: Option<String>,
+    cron_stop: Option<String>,
+    cron_timezone: Option<String>,
     log_date_format: Option<String>,
+    log_combine: Option<bool>,
+    merge_logs: Option<bool>,
+    stdout_log: Option<String>,
+    stderr_log: Option<String>,
+    log_max_line_len: Option<usize>,
+    log_rate_limit: Option<String>,
     instances: Option<u32>,
+    instance_env: Option<HashMap<String, HashMap<String, String>>>,
+    flap_threshold: Option<u32>,
+    #[serde(default, deserialize_with = "millis")]
+    flap_window: Option<u64>,
+    #[serde(default, deserialize_with = "millis")]
+    flap_cooldown: Option<u64>,
+    conflict_check: Option<String>,
+    #[serde(default, deserialize_with = "millis")]
+    restart_delay: Option<u64>,
+    restart_backoff: Option<RestartBackoff>,
+    #[serde(default, deserialize_with = "millis")]
+    max_restart_delay: Option<u64>,
+    #[serde(default, deserialize_with = "duration_text")]
+    max_restarts_window: Option<String>,
+    #[serde(default, deserialize_with = "millis")]
+    restart_stagger: Option<u64>,
+    kind: Option<ProcessKind>,
+    metrics_endpoint: Option<String>,
+    metrics: Option<Vec<String>>,
+    environments: Option<toml::Table>,
     #[serde(flatten)]
     extra: HashMap<String, toml::Value>,
 }
@@ -145,16 +407,178 @@ pub enum ConfigError {
     IoError(String),
 }
 
+/// Parses a human-friendly duration such as `"500ms"`, `"30s"`, `"5m"`,
+/// `"2h"`, or `"1d"`. A bare number is interpreted as seconds.
+pub fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+    let suffix_start = s.find(|c: char| c.is_alphabetic()).unwrap_or(s.len());
+    let (num_part, suffix) = s.split_at(suffix_start);
+    let value: u64 = num_part
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration: {s:?}"))?;
+
+    let ms = match suffix.trim() {
+        "ms" => value,
+        "" | "s" => value.saturating_mul(1000),
+        "m" => value.saturating_mul(60 * 1000),
+        "h" => value.saturating_mul(60 * 60 * 1000),
+        "d" => value.saturating_mul(24 * 60 * 60 * 1000),
+        other => return Err(format!("unknown duration unit {other:?} in {s:?}")),
+    };
+    if ms == 0 {
+        return Err("duration must be greater than 0".to_string());
+    }
+    Ok(std::time::Duration::from_millis(ms))
+}
+
+/// A duration as written in a process config: an integer of milliseconds, or
+/// a string with a unit like `"30s"`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawDuration {
+    Millis(u64),
+    Text(String),
+}
+
+/// Reads a field kept in milliseconds, which may also be written as a string
+/// like `"5s"`.
+fn millis<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    match Option::<RawDuration>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(RawDuration::Millis(ms)) => Ok(Some(ms)),
+        Some(RawDuration::Text(text)) => parse_duration(&text)
+            .map(|d| Some(d.as_millis() as u64))
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Reads a field kept as a duration string, which may also be written as an
+/// integer of milliseconds.
+fn duration_text<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(
+        Option::<RawDuration>::deserialize(deserializer)?.map(|duration| match duration {
+            RawDuration::Millis(ms) => format!("{ms}ms"),
+            RawDuration::Text(text) => text,
+        }),
+    )
+}
+
+/// A `depends_on` entry: a bare name, or `{ name = "cache", required = false }`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Dependency {
+    Name(String),
+    Detailed {
+        name: String,
+        #[serde(default = "required_by_default")]
+        required: bool,
+    },
+}
+
+fn required_by_default() -> bool {
+    true
+}
+
+/// Splits `depends_on` into every dependency name (which all order startup)
+/// and the optional ones.
+fn split_dependencies(deps: Option<Vec<Dependency>>) -> (Option<Vec<String>>, Option<Vec<String>>) {
+    let Some(deps) = deps else {
+        return (None, None);
+    };
+    let mut names = Vec::new();
+    let mut optional = Vec::new();
+    for dep in deps {
+        match dep {
+            Dependency::Name(name) => names.push(name),
+            Dependency::Detailed { name, required } => {
+                if !required {
+                    optional.push(name.clone());
+                }
+                names.push(name);
+            }
+        }
+    }
+    (Some(names), (!optional.is_empty()).then_some(optional))
+}
+
+/// Validates `[name.instance_env.N]` tables: each key must be the index of
+/// one of the process's cluster instances.
+fn parse_instance_env(
+    name: &str,
+    tables: HashMap<String, HashMap<String, String>>,
+    instances: Option<u32>,
+) -> Result<BTreeMap<u32, HashMap<String, String>>, ConfigError> {
+    let count = instances.unwrap_or(1);
+    if count < 2 {
+        return Err(ConfigError::TomlParse(format!(
+            "instance_env requires instances > 1 in process '{name}'"
+        )));
+    }
+    tables
+        .into_iter()
+        .map(|(key, env)| match key.parse::<u32>() {
+            Ok(index) if index < count => Ok((index, env)),
+            _ => Err(ConfigError::TomlParse(format!(
+                "invalid instance_env key '{key}' in process '{name}': expected an instance index from 0 to {}",
+                count - 1
+            ))),
+        })
+        .collect()
+}
+
+const GLOBAL_CRON_TIMEZONE_KEY: &str = "cron_timezone";
+const NAMESPACE_KEY: &str = "namespace";
+
+/// Reads the top-level `namespace = "..."` from a config file, if set.
+/// The namespace of the config at `path`: its top-level `namespace` key, else
+/// one derived from the directory it is in.
+pub fn load_namespace(path: &std::path::Path) -> Result<Option<String>, ConfigError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::IoError(format!("{}: {}", path.display(), e)))?;
+    let table: toml::Table =
+        toml::from_str(&content).map_err(|e| ConfigError::TomlParse(e.to_string()))?;
+    if let Some(toml::Value::String(namespace)) = table.get(NAMESPACE_KEY) {
+        return Ok(Some(namespace.clone()));
+    }
+    let path = std::fs::canonicalize(path)
+        .map_err(|e| ConfigError::IoError(format!("{}: {}", path.display(), e)))?;
+    Ok(path.parent().and_then(crate::namespace::from_dir))
+}
+
 pub fn load_config(path: &std::path::Path) -> Result<HashMap<String, ProcessConfig>, ConfigError> {
     let content = std::fs::read_to_string(path)
         .map_err(|e| ConfigError::IoError(format!("{}: {}", path.display(), e)))?;
     parse_config(&content)
 }
 
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
 pub fn parse_config(content: &str) -> Result<HashMap<String, ProcessConfig>, ConfigError> {
-    let table: HashMap<String, toml::Value> =
+    let mut table: HashMap<String, toml::Value> =
         toml::from_str(content).map_err(|e| ConfigError::TomlParse(e.to_string()))?;
 
+    // A top-level `cron_timezone = "..."` is the default for every process.
+    let default_cron_timezone = match table.remove(GLOBAL_CRON_TIMEZONE_KEY) {
+        Some(toml::Value::String(tz)) => Some(tz),
+        Some(other) => {
+            table.insert(GLOBAL_CRON_TIMEZONE_KEY.to_string(), other);
+            None
+        }
+        None => None,
+    };
+    // A top-level `namespace = "..."` is read by the client (`load_namespace`).
+    if matches!(table.get(NAMESPACE_KEY), Some(toml::Value::String(_))) {
+        table.remove(NAMESPACE_KEY);
+    }
+
     if table.is_empty() {
         return Err(ConfigError::Empty);
     }
@@ -166,70 +590,454 @@ pub fn parse_config(content: &str) -> Result<HashMap<String, ProcessConfig>, Con
             return Err(ConfigError::InvalidProcessName(name));
         }
 
-        let raw: RawProcessConfig = value
-            .try_into()
-            .map_err(|e: toml::de::Error| ConfigError::TomlParse(e.to_string()))?;
+        let config = parse_process(&name, value, default_cron_timezone.as_ref())?;
+        configs.insert(name, config);
+    }
+
+    Ok(configs)
+}
+
+/// Parses and validates one `[name]` table of a pm3.toml.
+fn parse_process(
+    name: &str,
+    value: toml::Value,
+    default_cron_timezone: Option<&String>,
+) -> Result<ProcessConfig, ConfigError> {
+    let mut raw: RawProcessConfig = value
+        .try_into()
+        .map_err(|e: toml::de::Error| ConfigError::TomlParse(e.to_string()))?;
+
+    // A bare `ready_delay` is a warm-up period: ready once it passes
+    if raw.ready_delay.is_some() && raw.ready_signal.is_none() {
+        raw.ready_signal = Some(ReadySignal::Delay);
+    }
 
-        if raw.readiness_timeout.is_some() && raw.readiness_check.is_none() {
+    if raw.readiness_timeout.is_some()
+        && raw.readiness_check.is_none()
+        && raw.ready_signal != Some(ReadySignal::Ipc)
+    {
+        return Err(ConfigError::TomlParse(format!(
+            "readiness_timeout requires readiness_check or ready_signal = \"ipc\" in process '{name}'"
+        )));
+    }
+    match raw.ready_signal {
+        Some(ReadySignal::Http) if raw.readiness_check.is_none() => {
             return Err(ConfigError::TomlParse(format!(
-                "readiness_timeout requires readiness_check in process '{name}'"
+                "ready_signal = \"http\" requires readiness_check in process '{name}'"
             )));
         }
-        if matches!(raw.readiness_timeout, Some(0)) {
+        Some(ReadySignal::Ipc | ReadySignal::Delay) if raw.readiness_check.is_some() => {
             return Err(ConfigError::TomlParse(format!(
-                "readiness_timeout must be greater than 0 in process '{name}'"
+                "readiness_check can only be used with ready_signal = \"http\" in process '{name}'"
             )));
         }
+        _ => {}
+    }
+    if (raw.ready_signal == Some(ReadySignal::Delay)) != raw.ready_delay.is_some() {
+        return Err(ConfigError::TomlParse(format!(
+            "ready_signal = \"delay\" and ready_delay must be set together in process '{name}'"
+        )));
+    }
+    if matches!(raw.readiness_timeout, Some(0)) {
+        return Err(ConfigError::TomlParse(format!(
+            "readiness_timeout must be greater than 0 in process '{name}'"
+        )));
+    }
 
-        let mut environments: HashMap<String, HashMap<String, String>> = HashMap::new();
-
-        for (key, val) in &raw.extra {
-            if let Some(env_name) = key.strip_prefix("env_") {
-                let env_map: HashMap<String, String> = val
-                    .clone()
-                    .try_into()
-                    .map_err(|e: toml::de::Error| ConfigError::TomlParse(e.to_string()))?;
-                environments.insert(env_name.to_string(), env_map);
-            } else {
-                return Err(ConfigError::UnknownField {
-                    process: name.clone(),
-                    field: key.clone(),
-                });
-            }
+    if let Some((key, _)) = raw
+        .env_cmd
+        .iter()
+        .flatten()
+        .find(|(_, command)| command.trim().is_empty())
+    {
+        return Err(ConfigError::TomlParse(format!(
+            "env_cmd.{key} must not be empty in process '{name}'"
+        )));
+    }
+
+    if raw.on_unhealthy.is_some() && raw.health_check.is_none() {
+        return Err(ConfigError::TomlParse(format!(
+            "on_unhealthy requires health_check in process '{name}'"
+        )));
+    }
+    if raw.unhealthy_threshold.is_some() && raw.on_unhealthy.is_none() {
+        return Err(ConfigError::TomlParse(format!(
+            "unhealthy_threshold requires on_unhealthy in process '{name}'"
+        )));
+    }
+    if raw.unhealthy_threshold == Some(0) {
+        return Err(ConfigError::TomlParse(format!(
+            "unhealthy_threshold must be greater than 0 in process '{name}'"
+        )));
+    }
+
+    if raw.metrics.is_some() != raw.metrics_endpoint.is_some() {
+        return Err(ConfigError::TomlParse(format!(
+            "metrics and metrics_endpoint must be set together in process '{name}'"
+        )));
+    }
+    if let Some(tz) = raw.cron_timezone.as_ref().or(default_cron_timezone)
+        && tz.trim().parse::<chrono_tz::Tz>().is_err()
+    {
+        return Err(ConfigError::TomlParse(format!(
+            "invalid cron_timezone in process '{name}': unknown timezone: {tz:?}"
+        )));
+    }
+    if let Some(tag) = raw.tags.iter().flatten().find(|t| !is_valid_tag(t)) {
+        return Err(ConfigError::TomlParse(format!(
+            "invalid tag '{tag}' in process '{name}': tags may only contain letters, digits, '-', '_' and '.'"
+        )));
+    }
+    if let Some(port) = raw.port {
+        if raw.env.as_ref().is_some_and(|env| env.contains_key("PORT")) {
+            return Err(ConfigError::TomlParse(format!(
+                "port and env PORT cannot both be set in process '{name}'"
+            )));
+        }
+        let last = u32::from(port) + raw.instances.unwrap_or(1).saturating_sub(1);
+        if raw.increment_port == Some(true) && last > u32::from(u16::MAX) {
+            return Err(ConfigError::TomlParse(format!(
+                "port {port} + instances exceeds 65535 in process '{name}'"
+            )));
+        }
+    } else if raw.increment_port.is_some() {
+        return Err(ConfigError::TomlParse(format!(
+            "increment_port requires port in process '{name}'"
+        )));
+    }
+    match &raw.watch {
+        Some(Watch::Globs(globs)) if globs.is_empty() => {
+            return Err(ConfigError::TomlParse(format!(
+                "watch needs at least one glob in process '{name}'"
+            )));
+        }
+        Some(Watch::Globs(globs)) if globs.iter().any(|g| g.trim().is_empty()) => {
+            return Err(ConfigError::TomlParse(format!(
+                "watch globs cannot be empty in process '{name}'"
+            )));
+        }
+        None | Some(Watch::Enabled(false)) if raw.watch_extensions.is_some() => {
+            return Err(ConfigError::TomlParse(format!(
+                "watch_extensions requires watch in process '{name}'"
+            )));
+        }
+        None | Some(Watch::Enabled(false)) if raw.watch_use_gitignore.is_some() => {
+            return Err(ConfigError::TomlParse(format!(
+                "watch_use_gitignore requires watch in process '{name}'"
+            )));
+        }
+        None | Some(Watch::Enabled(false))
+            if raw.watch_debounce.is_some() || raw.watch_delay.is_some() =>
+        {
+            return Err(ConfigError::TomlParse(format!(
+                "watch_debounce and watch_delay require watch in process '{name}'"
+            )));
         }
+        None | Some(Watch::Enabled(false)) if raw.watch_poll.is_some() => {
+            return Err(ConfigError::TomlParse(format!(
+                "watch_poll requires watch in process '{name}'"
+            )));
+        }
+        _ => {}
+    }
+    if raw.merge_logs.is_some() && raw.instances.unwrap_or(1) < 2 {
+        return Err(ConfigError::TomlParse(format!(
+            "merge_logs requires instances > 1 in process '{name}'"
+        )));
+    }
+    let custom_logs = [&raw.stdout_log, &raw.stderr_log];
+    if custom_logs
+        .iter()
+        .any(|log| log.as_ref().is_some_and(|l| l.trim().is_empty()))
+    {
+        return Err(ConfigError::TomlParse(format!(
+            "stdout_log and stderr_log cannot be empty in process '{name}'"
+        )));
+    }
+    if custom_logs.iter().any(|log| log.is_some())
+        && (raw.log_combine.is_some() || raw.merge_logs.is_some())
+    {
+        return Err(ConfigError::TomlParse(format!(
+            "stdout_log and stderr_log cannot be used with log_combine or merge_logs in process '{name}'"
+        )));
+    }
+    if raw.log_max_line_len == Some(0) {
+        return Err(ConfigError::TomlParse(format!(
+            "log_max_line_len must be greater than 0 in process '{name}'"
+        )));
+    }
+    if let Some(limit) = &raw.log_rate_limit {
+        crate::log::RateLimit::parse(limit)
+            .map_err(|e| ConfigError::TomlParse(format!("{e} in process '{name}'")))?;
+    }
+    if raw.watch_env_files.is_some() && raw.env_file.is_none() {
+        return Err(ConfigError::TomlParse(format!(
+            "watch_env_files requires env_file in process '{name}'"
+        )));
+    }
+    let watching = !matches!(raw.watch, None | Some(Watch::Enabled(false)))
+        || raw.watch_env_files == Some(true);
+    if !watching && (raw.watch_command.is_some() || raw.watch_restart.is_some()) {
+        return Err(ConfigError::TomlParse(format!(
+            "watch_command and watch_restart require watch or watch_env_files in process '{name}'"
+        )));
+    }
+    if raw.watch_restart == Some(false) && raw.watch_command.is_none() {
+        return Err(ConfigError::TomlParse(format!(
+            "watch_restart = false requires watch_command in process '{name}'"
+        )));
+    }
+    if let Some(extensions) = &raw.watch_extensions
+        && (extensions.is_empty()
+            || extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').is_empty()))
+    {
+        return Err(ConfigError::TomlParse(format!(
+            "watch_extensions needs at least one non-empty extension in process '{name}'"
+        )));
+    }
+    if raw.drain_timeout.is_some() && raw.drain_signal.is_none() {
+        return Err(ConfigError::TomlParse(format!(
+            "drain_timeout requires drain_signal in process '{name}'"
+        )));
+    }
+    if raw.max_memory.is_none()
+        && (raw.memory_check_interval.is_some() || raw.memory_action.is_some())
+    {
+        return Err(ConfigError::TomlParse(format!(
+            "memory_check_interval and memory_action require max_memory in process '{name}'"
+        )));
+    }
+    if raw.memory_leak_window.is_some() && raw.max_memory.is_none() {
+        return Err(ConfigError::TomlParse(format!(
+            "memory_leak_window requires max_memory in process '{name}'"
+        )));
+    }
+    if raw.memory_leak_action.is_some() && raw.memory_leak_window.is_none() {
+        return Err(ConfigError::TomlParse(format!(
+            "memory_leak_action requires memory_leak_window in process '{name}'"
+        )));
+    }
+    if raw.memory_leak_action == Some(LeakAction::CronRestart) && raw.cron_restart.is_none() {
+        return Err(ConfigError::TomlParse(format!(
+            "memory_leak_action = \"cron_restart\" requires cron_restart in process '{name}'"
+        )));
+    }
+    if let Some(action) = &raw.memory_action {
+        crate::memory::MemoryAction::parse(action)
+            .map_err(|e| ConfigError::TomlParse(format!("{e} in process '{name}'")))?;
+    }
+    for (field, value) in [
+        ("max_restarts_window", &raw.max_restarts_window),
+        ("memory_check_interval", &raw.memory_check_interval),
+        ("memory_leak_window", &raw.memory_leak_window),
+        ("watch_debounce", &raw.watch_debounce),
+        ("watch_delay", &raw.watch_delay),
+        ("watch_poll", &raw.watch_poll),
+    ] {
+        if let Some(value) = value
+            && let Err(e) = parse_duration(value)
+        {
+            return Err(ConfigError::TomlParse(format!(
+                "invalid {field} in process '{name}': {e}"
+            )));
+        }
+    }
 
-        configs.insert(
-            name,
-            ProcessConfig {
-                command: raw.command,
-                cwd: raw.cwd,
-                env: raw.env,
-                env_file: raw.env_file,
-                readiness_check: raw.readiness_check,
-                readiness_timeout: raw.readiness_timeout,
-                health_check: raw.health_check,
-                kill_timeout: raw.kill_timeout,
-                kill_signal: raw.kill_signal,
-                max_restarts: raw.max_restarts,
-                max_memory: raw.max_memory,
-                min_uptime: raw.min_uptime,
-                stop_exit_codes: raw.stop_exit_codes,
-                watch: raw.watch,
-                watch_ignore: raw.watch_ignore,
-                depends_on: raw.depends_on,
-                restart: raw.restart,
-                group: raw.group,
-                pre_start: raw.pre_start,
-                post_stop: raw.post_stop,
-                cron_restart: raw.cron_restart,
-                log_date_format: raw.log_date_format,
-                instances: raw.instances,
-                environments,
-            },
-        );
+    let (depends_on, optional_deps) = split_dependencies(raw.depends_on.take());
+    let instance_env = match raw.instance_env.take() {
+        Some(tables) => Some(parse_instance_env(name, tables, raw.instances)?),
+        None => None,
+    };
+
+    let mut environments: HashMap<String, HashMap<String, String>> = HashMap::new();
+
+    for (key, val) in &raw.extra {
+        if let Some(env_name) = key.strip_prefix("env_") {
+            let env_map: HashMap<String, String> = val
+                .clone()
+                .try_into()
+                .map_err(|e: toml::de::Error| ConfigError::TomlParse(e.to_string()))?;
+            environments.insert(env_name.to_string(), env_map);
+        } else {
+            return Err(ConfigError::UnknownField {
+                process: name.to_string(),
+                field: key.clone(),
+            });
+        }
     }
 
-    Ok(configs)
+    let mut environment_overrides = HashMap::new();
+    for (env_name, value) in raw.environments.take().unwrap_or_default() {
+        let toml::Value::Table(mut overrides) = value else {
+            return Err(ConfigError::TomlParse(format!(
+                "environments.{env_name} must be a table in process '{name}'"
+            )));
+        };
+        if let Some(key) = overrides.keys().find(|key| {
+            *key == "environments"
+                || key.starts_with("env_") && !matches!(key.as_str(), "env_file" | "env_cmd")
+        }) {
+            return Err(ConfigError::TomlParse(format!(
+                "environments.{env_name} can't set '{key}' in process '{name}'"
+            )));
+        }
+        // `env` merges with the base env, the same as `[name.env_<environment>]`
+        if let Some(env) = overrides.remove("env") {
+            let env: HashMap<String, String> = env
+                .try_into()
+                .map_err(|e: toml::de::Error| ConfigError::TomlParse(e.to_string()))?;
+            environments
+                .entry(env_name.clone())
+                .or_default()
+                .extend(env);
+        }
+        environment_overrides.insert(env_name, overrides);
+    }
+
+    let config = ProcessConfig {
+        command: raw.command,
+        cwd: raw.cwd,
+        env: raw.env,
+        env_file: raw.env_file,
+        env_cmd: raw.env_cmd,
+        readiness_check: raw.readiness_check,
+        readiness_timeout: raw.readiness_timeout,
+        ready_signal: raw.ready_signal,
+        ready_delay: raw.ready_delay,
+        drain_signal: raw.drain_signal,
+        drain_timeout: raw.drain_timeout,
+        port: raw.port,
+        increment_port: raw.increment_port,
+        health_check: raw.health_check,
+        on_unhealthy: raw.on_unhealthy,
+        unhealthy_threshold: raw.unhealthy_threshold,
+        kill_timeout: raw.kill_timeout,
+        kill_signal: raw.kill_signal,
+        max_restarts: raw.max_restarts,
+        max_memory: raw.max_memory,
+        memory_check_interval: raw.memory_check_interval,
+        memory_action: raw.memory_action,
+        memory_leak_window: raw.memory_leak_window,
+        memory_leak_action: raw.memory_leak_action,
+        min_uptime: raw.min_uptime,
+        stop_exit_codes: raw.stop_exit_codes,
+        watch: raw.watch,
+        watch_ignore: raw.watch_ignore,
+        watch_extensions: raw.watch_extensions,
+        watch_use_gitignore: raw.watch_use_gitignore,
+        watch_debounce: raw.watch_debounce,
+        watch_delay: raw.watch_delay,
+        watch_poll: raw.watch_poll,
+        watch_env_files: raw.watch_env_files,
+        watch_command: raw.watch_command,
+        watch_restart: raw.watch_restart,
+        depends_on,
+        optional_deps,
+        stop_dependents: raw.stop_dependents,
+        restart: raw.restart,
+        group: raw.group,
+        tags: raw.tags,
+        pre_start: raw.pre_start,
+        post_stop: raw.post_stop,
+        cron_restart: raw.cron_restart,
+        cron_start: raw.cron_start,
+        cron_stop: raw.cron_stop,
+        cron_timezone: raw.cron_timezone.or_else(|| default_cron_timezone.cloned()),
+        log_date_format: raw.log_date_format,
+        log_combine: raw.log_combine,
+        merge_logs: raw.merge_logs,
+        stdout_log: raw.stdout_log,
+        stderr_log: raw.stderr_log,
+        log_max_line_len: raw.log_max_line_len,
+        log_rate_limit: raw.log_rate_limit,
+        instances: raw.instances,
+        instance_env,
+        flap_threshold: raw.flap_threshold,
+        flap_window: raw.flap_window,
+        flap_cooldown: raw.flap_cooldown,
+        conflict_check: raw.conflict_check,
+        restart_delay: raw.restart_delay,
+        restart_backoff: raw.restart_backoff,
+        max_restart_delay: raw.max_restart_delay,
+        max_restarts_window: raw.max_restarts_window,
+        restart_stagger: raw.restart_stagger,
+        kind: raw.kind,
+        metrics_endpoint: raw.metrics_endpoint,
+        metrics: raw.metrics,
+        environments,
+        environment_overrides,
+    };
+    // Catch a bad override now rather than at `pm3 start --env`
+    for env_name in config.environment_overrides.keys() {
+        config.clone().apply_environment(name, env_name)?;
+    }
+    Ok(config)
+}
+
+/// Renders configs as a pm3.toml that `parse_config` reads back to the same
+/// configs, with a top-level `namespace` unless it is the default one.
+pub fn to_toml(
+    configs: &HashMap<String, ProcessConfig>,
+    namespace: Option<&str>,
+) -> Result<String, toml::ser::Error> {
+    let mut file = toml::Table::new();
+    if let Some(namespace) = namespace.filter(|ns| *ns != crate::namespace::DEFAULT_NAMESPACE) {
+        file.insert(NAMESPACE_KEY.to_string(), namespace.into());
+    }
+
+    for (name, config) in configs {
+        file.insert(name.clone(), process_table(config.clone())?.into());
+    }
+
+    toml::to_string(&file)
+}
+
+/// One process as a `[name]` table of a pm3.toml.
+fn process_table(mut config: ProcessConfig) -> Result<toml::Table, toml::ser::Error> {
+    let depends_on = config.depends_on.take();
+    let optional_deps = config.optional_deps.take().unwrap_or_default();
+    let instance_env = config.instance_env.take();
+    let environments = std::mem::take(&mut config.environments);
+    let environment_overrides = std::mem::take(&mut config.environment_overrides);
+
+    let mut process = toml::Table::try_from(&config)?;
+    process.remove("environments");
+    process.remove("environment_overrides");
+    if let Some(deps) = depends_on {
+        let deps = deps
+            .into_iter()
+            .map(|dep| {
+                if optional_deps.contains(&dep) {
+                    let mut detailed = toml::Table::new();
+                    detailed.insert("name".to_string(), dep.into());
+                    detailed.insert("required".to_string(), false.into());
+                    toml::Value::Table(detailed)
+                } else {
+                    dep.into()
+                }
+            })
+            .collect::<Vec<_>>();
+        process.insert("depends_on".to_string(), deps.into());
+    }
+    if let Some(instance_env) = instance_env {
+        let mut tables = toml::Table::new();
+        for (index, env) in instance_env {
+            tables.insert(index.to_string(), toml::Value::try_from(env)?);
+        }
+        process.insert("instance_env".to_string(), tables.into());
+    }
+    for (env_name, vars) in environments {
+        process.insert(format!("env_{env_name}"), toml::Value::try_from(vars)?);
+    }
+    if !environment_overrides.is_empty() {
+        process.insert(
+            "environments".to_string(),
+            toml::Value::try_from(environment_overrides)?,
+        );
+    }
+    Ok(process)
 }
 
 #[cfg(test)]
@@ -452,75 +1260,582 @@ watch = "./src"
     }
 
     #[test]
-    fn test_restart_policy_variants() {
+    fn test_watch_globs_and_extensions() {
         let input = r#"
-[a]
-command = "a"
-restart = "on_failure"
-
-[b]
-command = "b"
-restart = "always"
-
-[c]
-command = "c"
-restart = "never"
+[web]
+command = "cargo run"
+watch = ["src/**/*.rs", "config/*.toml"]
+watch_extensions = ["rs", ".toml"]
+watch_use_gitignore = true
 "#;
         let configs = parse_config(input).unwrap();
-        assert_eq!(configs["a"].restart, Some(RestartPolicy::OnFailure));
-        assert_eq!(configs["b"].restart, Some(RestartPolicy::Always));
-        assert_eq!(configs["c"].restart, Some(RestartPolicy::Never));
+        assert_eq!(configs["web"].watch_use_gitignore, Some(true));
+        assert_eq!(
+            configs["web"].watch,
+            Some(Watch::Globs(vec![
+                "src/**/*.rs".to_string(),
+                "config/*.toml".to_string()
+            ]))
+        );
+        assert_eq!(
+            configs["web"].watch_extensions,
+            Some(vec!["rs".to_string(), ".toml".to_string()])
+        );
+
+        for bad in [
+            "watch = []",
+            "watch = [\"\"]",
+            "watch_extensions = [\"rs\"]",
+            "watch = false\nwatch_extensions = [\"rs\"]",
+            "watch = true\nwatch_extensions = []",
+            "watch = true\nwatch_extensions = [\".\"]",
+            "watch_use_gitignore = true",
+        ] {
+            let input = format!("[web]\ncommand = \"cargo run\"\n{bad}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
     }
 
     #[test]
-    fn test_env_environment_sections() {
+    fn test_watch_debounce_and_delay() {
         let input = r#"
 [web]
-command = "node server.js"
+command = "npm run dev"
+watch = true
+watch_debounce = "2s"
+watch_delay = "300ms"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].watch_debounce.as_deref(), Some("2s"));
+        assert_eq!(configs["web"].watch_delay.as_deref(), Some("300ms"));
 
-[web.env_production]
-DATABASE_URL = "postgres://prod/db"
-API_KEY = "prod-key"
+        for bad in [
+            "watch = true\nwatch_debounce = \"soon\"",
+            "watch = true\nwatch_delay = \"0s\"",
+            "watch_debounce = \"1s\"",
+            "watch = false\nwatch_delay = \"1s\"",
+        ] {
+            let input = format!("[web]\ncommand = \"npm run dev\"\n{bad}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
+    }
 
-[web.env_staging]
-DATABASE_URL = "postgres://staging/db"
+    #[test]
+    fn test_watch_poll() {
+        let input = r#"
+[web]
+command = "npm run dev"
+watch = "./src"
+watch_poll = "2s"
 "#;
         let configs = parse_config(input).unwrap();
-        let web = &configs["web"];
+        assert_eq!(configs["web"].watch_poll.as_deref(), Some("2s"));
 
-        assert_eq!(web.environments.len(), 2);
-        let prod = web.environments.get("production").unwrap();
-        assert_eq!(prod.get("DATABASE_URL").unwrap(), "postgres://prod/db");
-        assert_eq!(prod.get("API_KEY").unwrap(), "prod-key");
-
-        let staging = web.environments.get("staging").unwrap();
-        assert_eq!(
-            staging.get("DATABASE_URL").unwrap(),
-            "postgres://staging/db"
-        );
+        for bad in [
+            "watch = true\nwatch_poll = \"often\"",
+            "watch_poll = \"2s\"",
+        ] {
+            let input = format!("[web]\ncommand = \"npm run dev\"\n{bad}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
     }
 
     #[test]
-    fn test_readiness_timeout_requires_readiness_check() {
+    fn test_log_combine_and_merge_logs() {
         let input = r#"
 [web]
 command = "node server.js"
-readiness_timeout = 120
+instances = 4
+log_combine = true
+merge_logs = true
 "#;
-        let result = parse_config(input);
-        assert!(matches!(result, Err(ConfigError::TomlParse(_))));
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].log_combine, Some(true));
+        assert_eq!(configs["web"].merge_logs, Some(true));
+
+        // Only instances have logs to merge
+        let input = r#"
+[web]
+command = "node server.js"
+merge_logs = true
+"#;
+        assert!(matches!(
+            parse_config(input),
+            Err(ConfigError::TomlParse(_))
+        ));
     }
 
     #[test]
-    fn test_readiness_timeout_must_be_positive() {
+    fn test_custom_log_paths() {
         let input = r#"
 [web]
 command = "node server.js"
-readiness_check = "tcp://localhost:3000"
-readiness_timeout = 0
+stdout_log = "/var/log/web/out.log"
+stderr_log = "logs/err.log"
 "#;
-        let result = parse_config(input);
-        assert!(matches!(result, Err(ConfigError::TomlParse(_))));
+        let configs = parse_config(input).unwrap();
+        assert_eq!(
+            configs["web"].stdout_log.as_deref(),
+            Some("/var/log/web/out.log")
+        );
+        assert_eq!(configs["web"].stderr_log.as_deref(), Some("logs/err.log"));
+
+        for bad in [
+            "stdout_log = \"\"",
+            "stderr_log = \"err.log\"\nlog_combine = true",
+        ] {
+            let input = format!("[web]\ncommand = \"node server.js\"\n{bad}");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_log_max_line_len() {
+        let input = r#"
+[web]
+command = "node server.js"
+log_max_line_len = 1024
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].log_max_line_len, Some(1024));
+        assert_eq!(configs["web"].max_log_line_len(), 1024);
+
+        let input = r#"
+[web]
+command = "node server.js"
+log_max_line_len = 0
+"#;
+        assert!(matches!(
+            parse_config(input),
+            Err(ConfigError::TomlParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_log_rate_limit() {
+        let input = r#"
+[web]
+command = "node server.js"
+log_rate_limit = "1000/s"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].log_rate_limit.as_deref(), Some("1000/s"));
+
+        for bad in ["1000", "0/s", "many/s", "100/soon"] {
+            let input = format!("[web]\ncommand = \"node server.js\"\nlog_rate_limit = \"{bad}\"");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_memory_check_interval_and_action() {
+        let input = r#"
+[web]
+command = "node server.js"
+max_memory = "512M"
+memory_check_interval = "2s"
+memory_action = "signal:SIGUSR2"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].memory_check_interval.as_deref(), Some("2s"));
+        assert_eq!(
+            configs["web"].memory_action.as_deref(),
+            Some("signal:SIGUSR2")
+        );
+
+        for bad in [
+            "max_memory = \"512M\"\nmemory_check_interval = \"often\"",
+            "max_memory = \"512M\"\nmemory_action = \"reboot\"",
+            "max_memory = \"512M\"\nmemory_action = \"signal:SIGNOPE\"",
+            "memory_action = \"stop\"",
+        ] {
+            let input = format!("[web]\ncommand = \"node server.js\"\n{bad}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_memory_leak_window() {
+        let input = r#"
+[web]
+command = "node server.js"
+max_memory = "512M"
+memory_leak_window = "30m"
+memory_leak_action = "cron_restart"
+cron_restart = "0 4 * * *"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].memory_leak_window.as_deref(), Some("30m"));
+        assert_eq!(
+            configs["web"].memory_leak_action,
+            Some(LeakAction::CronRestart)
+        );
+
+        for bad in [
+            "memory_leak_window = \"30m\"",
+            "max_memory = \"512M\"\nmemory_leak_window = \"soon\"",
+            "max_memory = \"512M\"\nmemory_leak_action = \"event\"",
+            "max_memory = \"512M\"\nmemory_leak_window = \"30m\"\nmemory_leak_action = \"cron_restart\"",
+        ] {
+            let input = format!("[web]\ncommand = \"node server.js\"\n{bad}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_on_unhealthy() {
+        let input = r#"
+[web]
+command = "node server.js"
+health_check = "http://localhost:3000/health"
+on_unhealthy = "restart"
+unhealthy_threshold = 5
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].on_unhealthy, Some(UnhealthyAction::Restart));
+        assert_eq!(configs["web"].unhealthy_threshold, Some(5));
+
+        for bad in [
+            "on_unhealthy = \"stop\"",
+            "health_check = \"tcp://localhost:3000\"\non_unhealthy = \"reboot\"",
+            "health_check = \"tcp://localhost:3000\"\nunhealthy_threshold = 3",
+            "health_check = \"tcp://localhost:3000\"\non_unhealthy = \"none\"\nunhealthy_threshold = 0",
+        ] {
+            let input = format!("[web]\ncommand = \"node server.js\"\n{bad}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_env_cmd() {
+        let configs = parse_config(
+            r#"
+[web]
+command = "node server.js"
+env_cmd = { DB_PASSWORD = "vault kv get -field=pw db" }
+"#,
+        )
+        .unwrap();
+        let env_cmd = configs["web"].env_cmd.as_ref().unwrap();
+        assert_eq!(env_cmd["DB_PASSWORD"], "vault kv get -field=pw db");
+        assert!(configs["web"].environments.is_empty());
+
+        let err = parse_config("[web]\ncommand = \"x\"\nenv_cmd = { A = \" \" }").unwrap_err();
+        assert!(
+            err.to_string().contains("env_cmd.A must not be empty"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_watch_env_files() {
+        let input = r#"
+[web]
+command = "npm run dev"
+env_file = ".env"
+watch_env_files = true
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].watch_env_files, Some(true));
+
+        let input = r#"
+[web]
+command = "npm run dev"
+watch_env_files = true
+"#;
+        assert!(matches!(
+            parse_config(input),
+            Err(ConfigError::TomlParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_watch_command() {
+        let input = r#"
+[api]
+command = "./target/debug/api"
+watch = ["src/**/*.rs"]
+watch_command = "cargo build"
+watch_restart = false
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["api"].watch_command.as_deref(), Some("cargo build"));
+        assert_eq!(configs["api"].watch_restart, Some(false));
+
+        for bad in [
+            "watch_command = \"cargo build\"",
+            "watch = true\nwatch_restart = false",
+        ] {
+            let input = format!("[api]\ncommand = \"./api\"\n{bad}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_restart_policy_variants() {
+        let input = r#"
+[a]
+command = "a"
+restart = "on_failure"
+
+[b]
+command = "b"
+restart = "always"
+
+[c]
+command = "c"
+restart = "never"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["a"].restart, Some(RestartPolicy::OnFailure));
+        assert_eq!(configs["b"].restart, Some(RestartPolicy::Always));
+        assert_eq!(configs["c"].restart, Some(RestartPolicy::Never));
+    }
+
+    #[test]
+    fn test_env_environment_sections() {
+        let input = r#"
+[web]
+command = "node server.js"
+
+[web.env_production]
+DATABASE_URL = "postgres://prod/db"
+API_KEY = "prod-key"
+
+[web.env_staging]
+DATABASE_URL = "postgres://staging/db"
+"#;
+        let configs = parse_config(input).unwrap();
+        let web = &configs["web"];
+
+        assert_eq!(web.environments.len(), 2);
+        let prod = web.environments.get("production").unwrap();
+        assert_eq!(prod.get("DATABASE_URL").unwrap(), "postgres://prod/db");
+        assert_eq!(prod.get("API_KEY").unwrap(), "prod-key");
+
+        let staging = web.environments.get("staging").unwrap();
+        assert_eq!(
+            staging.get("DATABASE_URL").unwrap(),
+            "postgres://staging/db"
+        );
+    }
+
+    #[test]
+    fn test_readiness_timeout_requires_readiness_check() {
+        let input = r#"
+[web]
+command = "node server.js"
+readiness_timeout = 120
+"#;
+        let result = parse_config(input);
+        assert!(matches!(result, Err(ConfigError::TomlParse(_))));
+    }
+
+    #[test]
+    fn test_ready_signal() {
+        let input = r#"
+[web]
+command = "node server.js"
+ready_signal = "ipc"
+readiness_timeout = 60
+
+[worker]
+command = "python worker.py"
+ready_signal = "delay"
+ready_delay = 2000
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].ready_signal, Some(ReadySignal::Ipc));
+        assert!(configs["web"].has_startup_probe());
+        assert_eq!(configs["worker"].ready_delay, Some(2000));
+        assert!(configs["worker"].has_startup_probe());
+
+        let configs =
+            parse_config("[web]\ncommand = \"node server.js\"\nready_delay = 500\n").unwrap();
+        assert_eq!(configs["web"].ready_signal, Some(ReadySignal::Delay));
+
+        for invalid in [
+            "ready_signal = \"http\"",
+            "ready_signal = \"delay\"",
+            "ready_signal = \"ipc\"\nready_delay = 500",
+            "readiness_check = \"tcp://localhost:3000\"\nready_delay = 500",
+            "ready_signal = \"ipc\"\nreadiness_check = \"tcp://localhost:3000\"",
+            "ready_signal = \"fifo\"",
+        ] {
+            let input = format!("[web]\ncommand = \"node server.js\"\n{invalid}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "expected error for: {invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_optional_dependencies() {
+        let input = r#"
+[db]
+command = "postgres"
+
+[cache]
+command = "redis-server"
+
+[web]
+command = "node server.js"
+depends_on = ["db", { name = "cache", required = false }]
+"#;
+        let configs = parse_config(input).unwrap();
+        let web = &configs["web"];
+        assert_eq!(
+            web.depends_on,
+            Some(vec!["db".to_string(), "cache".to_string()])
+        );
+        assert_eq!(web.optional_deps, Some(vec!["cache".to_string()]));
+        assert!(configs["db"].optional_deps.is_none());
+    }
+
+    #[test]
+    fn test_restart_stagger() {
+        let input = r#"
+[web]
+command = "node server.js"
+instances = 4
+restart_stagger = 2000
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].restart_stagger, Some(2000));
+    }
+
+    #[test]
+    fn test_stop_dependents() {
+        let input = r#"
+[db]
+command = "postgres"
+stop_dependents = false
+
+[web]
+command = "node server.js"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["db"].stop_dependents, Some(false));
+        assert_eq!(configs["web"].stop_dependents, None);
+    }
+
+    #[test]
+    fn test_instance_env() {
+        let input = r#"
+[db]
+command = "postgres"
+instances = 3
+
+[db.instance_env.0]
+ROLE = "primary"
+
+[db.instance_env.2]
+ROLE = "replica"
+SHARD = "b"
+"#;
+        let configs = parse_config(input).unwrap();
+        let instance_env = configs["db"].instance_env.as_ref().unwrap();
+        assert_eq!(instance_env[&0]["ROLE"], "primary");
+        assert_eq!(instance_env[&2]["SHARD"], "b");
+        assert!(!instance_env.contains_key(&1));
+
+        for invalid in [
+            "instances = 2\n[web.instance_env.2]\nROLE = \"x\"",
+            "instances = 2\n[web.instance_env.first]\nROLE = \"x\"",
+            "[web.instance_env.0]\nROLE = \"x\"",
+        ] {
+            let input = format!("[web]\ncommand = \"node server.js\"\n{invalid}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "expected error for: {invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_port_options() {
+        let input = r#"
+[web]
+command = "node server.js"
+instances = 4
+port = 3000
+increment_port = true
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].port, Some(3000));
+        assert_eq!(configs["web"].increment_port, Some(true));
+
+        for invalid in [
+            "increment_port = true",
+            "port = 3000\nenv = { PORT = \"4000\" }",
+            "port = 65535\ninstances = 2\nincrement_port = true",
+            "port = 70000",
+        ] {
+            let input = format!("[web]\ncommand = \"node server.js\"\n{invalid}\n");
+            assert!(
+                matches!(parse_config(&input), Err(ConfigError::TomlParse(_))),
+                "expected error for: {invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_drain_options() {
+        let input = r#"
+[web]
+command = "node server.js"
+drain_signal = "SIGUSR2"
+drain_timeout = 10000
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].drain_signal.as_deref(), Some("SIGUSR2"));
+        assert_eq!(configs["web"].drain_timeout, Some(10000));
+
+        let input = "[web]\ncommand = \"node server.js\"\ndrain_timeout = 10000\n";
+        assert!(matches!(
+            parse_config(input),
+            Err(ConfigError::TomlParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_readiness_timeout_must_be_positive() {
+        let input = r#"
+[web]
+command = "node server.js"
+readiness_check = "tcp://localhost:3000"
+readiness_timeout = 0
+"#;
+        let result = parse_config(input);
+        assert!(matches!(result, Err(ConfigError::TomlParse(_))));
     }
 
     fn base_config() -> ProcessConfig {
@@ -529,29 +1844,120 @@ readiness_timeout = 0
             cwd: None,
             env: None,
             env_file: None,
+            env_cmd: None,
             readiness_check: None,
             readiness_timeout: None,
             health_check: None,
+            on_unhealthy: None,
+            unhealthy_threshold: None,
             kill_timeout: None,
             kill_signal: None,
             max_restarts: None,
             max_memory: None,
+            memory_check_interval: None,
+            memory_action: None,
+            memory_leak_window: None,
+            memory_leak_action: None,
             min_uptime: None,
             stop_exit_codes: None,
             watch: None,
             watch_ignore: None,
+            watch_extensions: None,
+            watch_use_gitignore: None,
+            watch_debounce: None,
+            watch_delay: None,
+            watch_poll: None,
+            watch_env_files: None,
+            watch_command: None,
+            watch_restart: None,
             depends_on: None,
             restart: None,
             group: None,
             pre_start: None,
             post_stop: None,
             cron_restart: None,
+            cron_start: None,
+            cron_stop: None,
+            cron_timezone: None,
+            tags: None,
+            ready_signal: None,
+            ready_delay: None,
+            drain_signal: None,
+            drain_timeout: None,
+            port: None,
+            increment_port: None,
+            instance_env: None,
+            optional_deps: None,
+            stop_dependents: None,
+            restart_stagger: None,
             log_date_format: None,
+            log_combine: None,
+            merge_logs: None,
+            stdout_log: None,
+            stderr_log: None,
+            log_max_line_len: None,
+            log_rate_limit: None,
             instances: None,
+            flap_threshold: None,
+            flap_window: None,
+            flap_cooldown: None,
+            conflict_check: None,
+            restart_delay: None,
+            restart_backoff: None,
+            max_restart_delay: None,
+            max_restarts_window: None,
+            kind: None,
+            metrics_endpoint: None,
+            metrics: None,
             environments: HashMap::new(),
+            environment_overrides: HashMap::new(),
         }
     }
 
+    #[test]
+    fn test_to_toml_roundtrips() {
+        let content = r#"
+[web]
+command = "node server.js"
+cwd = "/srv/app"
+env = { NODE_ENV = "development" }
+port = 3000
+increment_port = true
+instances = 2
+depends_on = ["db", { name = "cache", required = false }]
+ready_signal = "ipc"
+readiness_timeout = 30000
+watch = "src"
+tags = ["frontend"]
+
+[web.instance_env.1]
+ROLE = "secondary"
+
+[web.env_production]
+NODE_ENV = "production"
+
+[web.environments.production]
+instances = 4
+max_memory = "1G"
+
+[db]
+command = "postgres"
+restart = "always"
+cron_timezone = "UTC"
+
+[cache]
+command = "redis-server"
+ready_delay = 500
+"#;
+        let configs = parse_config(content).unwrap();
+        let rendered = to_toml(&configs, Some("shop")).unwrap();
+        assert_eq!(parse_config(&rendered).unwrap(), configs);
+        assert!(rendered.starts_with("namespace = \"shop\"\n"), "{rendered}");
+
+        let rendered = to_toml(&configs, Some(crate::namespace::DEFAULT_NAMESPACE)).unwrap();
+        assert!(!rendered.contains("namespace"), "{rendered}");
+    }
+
     #[test]
     fn test_apply_environment_merges() {
         let input = r#"
@@ -565,11 +1971,64 @@ B = "3"
 "#;
         let mut configs = parse_config(input).unwrap();
         let mut web = configs.remove("web").unwrap();
-        assert!(web.apply_environment("prod"));
+        assert!(web.apply_environment("web", "prod").unwrap());
         let env = web.env.as_ref().unwrap();
         assert_eq!(env.get("A").unwrap(), "2");
         assert_eq!(env.get("B").unwrap(), "3");
-        assert!(!web.apply_environment("missing"));
+        assert!(!web.apply_environment("web", "missing").unwrap());
+    }
+
+    #[test]
+    fn test_apply_environment_overrides_fields() {
+        let input = r#"
+[web]
+command = "node server.js"
+instances = 2
+env = { A = "1", B = "1" }
+watch = true
+
+[web.env_production]
+A = "2"
+
+[web.environments.production]
+command = "node dist/server.js"
+instances = 4
+max_memory = "1G"
+watch = false
+env = { B = "2" }
+"#;
+        let mut configs = parse_config(input).unwrap();
+        let mut web = configs.remove("web").unwrap();
+        assert_eq!(web.instances, Some(2));
+        let staged = web.clone();
+
+        assert!(web.apply_environment("web", "production").unwrap());
+        assert_eq!(web.command, "node dist/server.js");
+        assert_eq!(web.instances, Some(4));
+        assert_eq!(web.max_memory.as_deref(), Some("1G"));
+        assert_eq!(web.watch, Some(Watch::Enabled(false)));
+        let env = web.env.as_ref().unwrap();
+        assert_eq!(env.get("A").unwrap(), "2");
+        assert_eq!(env.get("B").unwrap(), "2");
+        assert_eq!(web.environment_overrides, staged.environment_overrides);
+    }
+
+    #[test]
+    fn test_environment_overrides_are_validated() {
+        let invalid = |overrides: &str| {
+            let input = format!(
+                "[web]\ncommand = \"node server.js\"\n\n[web.environments.production]\n{overrides}"
+            );
+            parse_config(&input).unwrap_err().to_string()
+        };
+        assert!(invalid("instances = \"four\"").contains("invalid type"));
+        assert!(invalid("on_unhealthy = \"restart\"").contains("requires health_check"));
+        assert!(invalid("bogus = 1").contains("bogus"));
+        assert!(invalid("env_staging = { A = \"1\" }").contains("can't set 'env_staging'"));
+
+        let err =
+            parse_config("[web]\ncommand = \"x\"\nenvironments = { production = 1 }").unwrap_err();
+        assert!(err.to_string().contains("must be a table"), "{err}");
     }
 
     #[test]
@@ -653,4 +2112,248 @@ command = "node server.js"
         let configs = parse_config(input).unwrap();
         assert!(configs["web"].instances.is_none());
     }
+
+    #[test]
+    fn test_flap_fields_parsed() {
+        let input = r#"
+[web]
+command = "node server.js"
+flap_threshold = 5
+flap_window = 60000
+flap_cooldown = 300000
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].flap_threshold, Some(5));
+        assert_eq!(configs["web"].flap_window, Some(60000));
+        assert_eq!(configs["web"].flap_cooldown, Some(300000));
+    }
+
+    #[test]
+    fn test_restart_backoff_fields_parsed() {
+        let input = r#"
+[web]
+command = "node server.js"
+restart_delay = 500
+restart_backoff = "linear"
+max_restart_delay = 10000
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].restart_delay, Some(500));
+        assert_eq!(configs["web"].restart_backoff, Some(RestartBackoff::Linear));
+        assert_eq!(configs["web"].max_restart_delay, Some(10000));
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        use std::time::Duration;
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86400));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn test_max_restarts_window_parsed() {
+        let input = r#"
+[web]
+command = "node server.js"
+max_restarts = 5
+max_restarts_window = "5m"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["web"].max_restarts_window.as_deref(), Some("5m"));
+    }
+
+    #[test]
+    fn test_max_restarts_window_invalid() {
+        let input = r#"
+[web]
+command = "node server.js"
+max_restarts_window = "soon"
+"#;
+        let err = parse_config(input).unwrap_err();
+        assert!(err.to_string().contains("max_restarts_window"));
+    }
+
+    #[test]
+    fn test_durations_accept_millis_and_units() {
+        let input = r#"
+[web]
+command = "node server.js"
+kill_timeout = "10s"
+restart_delay = 250
+max_restarts_window = 30000
+watch = true
+watch_debounce = "2s"
+watch_delay = 500
+"#;
+        let web = &parse_config(input).unwrap()["web"];
+        assert_eq!(web.kill_timeout, Some(10_000));
+        assert_eq!(web.restart_delay, Some(250));
+        assert_eq!(web.max_restarts_window.as_deref(), Some("30000ms"));
+        assert_eq!(web.watch_debounce.as_deref(), Some("2s"));
+        assert_eq!(web.watch_delay.as_deref(), Some("500ms"));
+
+        let err = parse_config("[web]\ncommand = \"x\"\nkill_timeout = \"soon\"\n").unwrap_err();
+        assert!(err.to_string().contains("soon"), "{err}");
+    }
+
+    #[test]
+    fn test_cron_start_stop_parsed() {
+        let input = r#"
+[dev]
+command = "npm run dev"
+cron_start = "0 8 * * 1-5"
+cron_stop = "0 20 * * *"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["dev"].cron_start.as_deref(), Some("0 8 * * 1-5"));
+        assert_eq!(configs["dev"].cron_stop.as_deref(), Some("0 20 * * *"));
+    }
+
+    #[test]
+    fn test_cron_timezone_global_default_and_override() {
+        let input = r#"
+cron_timezone = "UTC"
+
+[nightly]
+command = "./backup.sh"
+cron_restart = "0 3 * * *"
+
+[report]
+command = "./report.sh"
+cron_restart = "0 9 * * *"
+cron_timezone = "Etc/UTC"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs["nightly"].cron_timezone.as_deref(), Some("UTC"));
+        assert_eq!(configs["report"].cron_timezone.as_deref(), Some("Etc/UTC"));
+    }
+
+    #[test]
+    fn test_cron_timezone_invalid() {
+        let input = r#"
+[web]
+command = "node server.js"
+cron_timezone = "Mars/Olympus_Mons"
+"#;
+        let err = parse_config(input).unwrap_err();
+        assert!(err.to_string().contains("cron_timezone"));
+    }
+
+    #[test]
+    fn test_namespace_key_is_not_a_process() {
+        let input = r#"
+namespace = "shop"
+
+[web]
+command = "node server.js"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs.len(), 1);
+        assert!(configs.contains_key("web"));
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("pm3.toml");
+        std::fs::write(&path, input).unwrap();
+        assert_eq!(load_namespace(&path).unwrap().as_deref(), Some("shop"));
+
+        let project = dir.path().join("My Shop");
+        std::fs::create_dir(&project).unwrap();
+        let path = project.join("pm3.toml");
+        std::fs::write(&path, "[web]\ncommand = \"x\"\n").unwrap();
+        assert_eq!(load_namespace(&path).unwrap().as_deref(), Some("My-Shop"));
+    }
+
+    #[test]
+    fn test_tags_parsed() {
+        let input = r#"
+[payments]
+command = "node payments.js"
+tags = ["payments", "critical"]
+
+[web]
+command = "node server.js"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(
+            configs["payments"].tags,
+            Some(vec!["payments".to_string(), "critical".to_string()])
+        );
+        assert!(configs["web"].tags.is_none());
+    }
+
+    #[test]
+    fn test_tags_invalid() {
+        let input = r#"
+[web]
+command = "node server.js"
+tags = ["web-*"]
+"#;
+        let err = parse_config(input).unwrap_err();
+        assert!(err.to_string().contains("invalid tag 'web-*'"));
+    }
+
+    #[test]
+    fn test_kind_task_parsed() {
+        let input = r#"
+[migrate]
+command = "./migrate.sh"
+kind = "task"
+
+[web]
+command = "node server.js"
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(configs["migrate"].kind, Some(ProcessKind::Task));
+        assert!(configs["migrate"].is_task());
+        assert!(!configs["web"].is_task());
+    }
+
+    #[test]
+    fn test_metrics_fields_parsed() {
+        let input = r#"
+[worker]
+command = "python worker.py"
+metrics_endpoint = "http://localhost:9464/metrics"
+metrics = ["queue_depth"]
+"#;
+        let configs = parse_config(input).unwrap();
+        assert_eq!(
+            configs["worker"].metrics_endpoint.as_deref(),
+            Some("http://localhost:9464/metrics")
+        );
+        assert_eq!(
+            configs["worker"].metrics,
+            Some(vec!["queue_depth".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_metrics_without_endpoint_errors() {
+        let input = r#"
+[worker]
+command = "python worker.py"
+metrics = ["queue_depth"]
+"#;
+        let err = parse_config(input).unwrap_err();
+        assert!(err.to_string().contains("metrics_endpoint"));
+    }
+
+    #[test]
+    fn test_restart_backoff_invalid_value() {
+        let input = r#"
+[web]
+command = "node server.js"
+restart_backoff = "random"
+"#;
+        assert!(parse_config(input).is_err());
+    }
 }
@@ -0,0 +1,150 @@
+use crate::health::{self, HealthCheckTarget};
+use crate::process::{self, ProcessError};
+use std::collections::{HashMap, HashSet};
+use std::process::Stdio;
+use std::time::Duration;
+
+/// How long a `conflict_check` may run before the start is refused.
+pub const CONFLICT_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs a process's `conflict_check` and describes any matching instance
+/// that pm3 does not manage.
+///
+/// A `tcp://host:port` check conflicts when something already accepts
+/// connections on that port. Any other value is run as a command: a
+/// non-zero exit means no conflict. If the command prints PIDs (as `pgrep`
+/// does), PIDs in `managed_pids`, the processes pm3 manages and their
+/// descendants, are ignored.
+pub async fn find_conflict(
+    check: &str,
+    managed_pids: &HashSet<u32>,
+) -> Result<Option<String>, ProcessError> {
+    if check.starts_with("tcp://") {
+        let HealthCheckTarget::Tcp(host, port) = health::parse_health_check(check)? else {
+            unreachable!("tcp:// always parses as a TCP target");
+        };
+        if health::check_tcp(&host, port).await {
+            return Ok(Some(format!("port {port} is already in use")));
+        }
+        return Ok(None);
+    }
+
+    let (program, args) = process::parse_command(check)?;
+    let output = tokio::process::Command::new(&program)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(ProcessError::SpawnFailed)?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(unmanaged_match(&stdout, managed_pids))
+}
+
+/// `pids` and every process they spawned, since the real server of a
+/// command wrapped in `sh -c` or npm is a grandchild of the pid pm3 tracks.
+/// Without `ps`, only `pids` themselves.
+pub async fn with_descendants(pids: HashSet<u32>) -> HashSet<u32> {
+    let Some(entries) = crate::tree::read_process_table().await else {
+        return pids;
+    };
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for entry in entries.iter().filter(|entry| entry.pid != entry.ppid) {
+        children.entry(entry.ppid).or_default().push(entry.pid);
+    }
+    pids.iter()
+        .flat_map(|&pid| crate::sys::process_tree(pid, &children))
+        .collect()
+}
+
+fn unmanaged_match(stdout: &str, managed_pids: &HashSet<u32>) -> Option<String> {
+    let pids: Vec<u32> = stdout
+        .split_whitespace()
+        .filter_map(|word| word.parse().ok())
+        .collect();
+
+    if pids.is_empty() {
+        let detail = stdout.trim();
+        return Some(if detail.is_empty() {
+            "conflict check matched an existing instance".to_string()
+        } else {
+            format!("conflict check matched an existing instance: {detail}")
+        });
+    }
+
+    let own_pid = std::process::id();
+    let unmanaged: Vec<String> = pids
+        .into_iter()
+        .filter(|pid| *pid != own_pid && !managed_pids.contains(pid))
+        .map(|pid| pid.to_string())
+        .collect();
+
+    if unmanaged.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "already running outside pm3 (pid {})",
+            unmanaged.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unmanaged_match_ignores_managed_pids() {
+        let managed = HashSet::from([100, 200]);
+        assert_eq!(unmanaged_match("100\n200\n", &managed), None);
+    }
+
+    #[test]
+    fn test_unmanaged_match_reports_unmanaged_pids() {
+        let managed = HashSet::from([100]);
+        assert_eq!(
+            unmanaged_match("100\n4242\n", &managed),
+            Some("already running outside pm3 (pid 4242)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unmanaged_match_without_pids() {
+        assert_eq!(
+            unmanaged_match("", &HashSet::new()),
+            Some("conflict check matched an existing instance".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_conflict_command_exit_status() {
+        assert!(
+            find_conflict("false", &HashSet::new())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            find_conflict("true", &HashSet::new())
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_conflict_tcp_port_in_use() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let found = find_conflict(&format!("tcp://127.0.0.1:{port}"), &HashSet::new())
+            .await
+            .unwrap();
+        assert_eq!(found, Some(format!("port {port} is already in use")));
+    }
+}
@@ -1,7 +1,10 @@
+use crate::config::LeakAction;
+use crate::manager::Manager;
 use crate::paths::Paths;
 use crate::process::{self, ProcessTable};
-use crate::protocol::ProcessStatus;
-use chrono::Utc;
+use crate::protocol::{ProcessStatus, Response};
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
 use cron::Schedule;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -23,14 +26,158 @@ pub fn parse_cron_expression(expr: &str) -> Result<Schedule, process::ProcessErr
 }
 
 pub fn next_run_duration(schedule: &Schedule) -> Option<std::time::Duration> {
+    next_run_duration_in(schedule, Utc)
+}
+
+/// Like `next_run_duration`, but evaluates the schedule's wall-clock
+/// fields in the given timezone.
+pub fn next_run_duration_in<Tz: TimeZone>(
+    schedule: &Schedule,
+    timezone: Tz,
+) -> Option<std::time::Duration> {
     let now = Utc::now();
-    let next = schedule.upcoming(Utc).next()?;
-    let delta = next - now;
+    let next = schedule.upcoming(timezone).next()?;
+    let delta = next.with_timezone(&Utc) - now;
     delta.to_std().ok()
 }
+
+/// Resolves a process's `cron_timezone`, defaulting to UTC.
+pub fn resolve_timezone(timezone: Option<&str>) -> Result<Tz, process::ProcessError> {
+    match timezone {
+        Some(name) => name.trim().parse().map_err(|_| {
+            process::ProcessError::InvalidCommand(format!("unknown timezone: {name:?}"))
+        }),
+        None => Ok(Tz::UTC),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CronAction {
+    Start,
+    Stop,
+}
+
+impl std::fmt::Display for CronAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CronAction::Start => write!(f, "start"),
+            CronAction::Stop => write!(f, "stop"),
+        }
+    }
+}
+
+/// Picks whichever of the `cron_start` / `cron_stop` schedules fires first.
+pub fn next_scheduled_action(
+    start: Option<&Schedule>,
+    stop: Option<&Schedule>,
+    timezone: Tz,
+) -> Option<(std::time::Duration, CronAction)> {
+    let start = start
+        .and_then(|s| next_run_duration_in(s, timezone))
+        .map(|d| (d, CronAction::Start));
+    let stop = stop
+        .and_then(|s| next_run_duration_in(s, timezone))
+        .map(|d| (d, CronAction::Stop));
+    match (start, stop) {
+        (Some(a), Some(b)) => Some(if b.0 <= a.0 { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Handle to a running `cron_start` / `cron_stop` scheduler. Unlike
+/// `cron_restart`, these outlive the process itself, so they are owned by
+/// the manager rather than tied to the process's monitor shutdown channel.
+pub struct StartStopSchedule {
+    pub cron_start: Option<String>,
+    pub cron_stop: Option<String>,
+    pub cron_timezone: Option<String>,
+    pub shutdown: watch::Sender<bool>,
+}
+
+pub fn spawn_cron_start_stop(
+    name: String,
+    cron_start: Option<String>,
+    cron_stop: Option<String>,
+    timezone: Option<String>,
+    manager: Manager,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let zone = match resolve_timezone(timezone.as_deref()) {
+            Ok(z) => z,
+            Err(e) => {
+                tracing::warn!("invalid cron_timezone for '{}': {}", name, e);
+                return;
+            }
+        };
+        let parse = |field: &str, expr: Option<String>| match expr {
+            Some(expr) => match parse_cron_expression(&expr) {
+                Ok(s) => Ok(Some(s)),
+                Err(e) => {
+                    tracing::warn!("invalid {} for '{}': {}", field, name, e);
+                    Err(())
+                }
+            },
+            None => Ok(None),
+        };
+        let Ok(start) = parse("cron_start", cron_start) else {
+            return;
+        };
+        let Ok(stop) = parse("cron_stop", cron_stop) else {
+            return;
+        };
+
+        loop {
+            let Some((sleep_dur, action)) =
+                next_scheduled_action(start.as_ref(), stop.as_ref(), zone)
+            else {
+                return;
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep(sleep_dur) => {}
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                    continue;
+                }
+            }
+
+            if *shutdown_rx.borrow() {
+                return;
+            }
+
+            if manager.is_paused(&name).await {
+                tracing::info!("cron {} skipped for '{}': monitoring paused", action, name);
+                continue;
+            }
+
+            tracing::info!("cron {} triggered for '{}'", action, name);
+            let response = match action {
+                CronAction::Start => manager.scheduled_start(&name).await,
+                CronAction::Stop => {
+                    manager
+                        .stop(Some(vec![name.clone()]), None, None, false)
+                        .await
+                }
+            };
+            if let Response::Error { message } = response {
+                tracing::error!(
+                    "failed to {} '{}' on cron schedule: {}",
+                    action,
+                    name,
+                    message
+                );
+            }
+        }
+    });
+}
+
 pub fn spawn_cron_restart(
     name: String,
     cron_expr: String,
+    timezone: Option<String>,
     processes: Arc<RwLock<ProcessTable>>,
     paths: Paths,
     mut shutdown_rx: watch::Receiver<bool>,
@@ -39,14 +186,21 @@ pub fn spawn_cron_restart(
         let schedule = match parse_cron_expression(&cron_expr) {
             Ok(s) => s,
             Err(e) => {
-                eprintln!("invalid cron_restart for '{}': {}", name, e);
+                tracing::warn!("invalid cron_restart for '{}': {}", name, e);
+                return;
+            }
+        };
+        let zone = match resolve_timezone(timezone.as_deref()) {
+            Ok(z) => z,
+            Err(e) => {
+                tracing::warn!("invalid cron_timezone for '{}': {}", name, e);
                 return;
             }
         };
 
         loop {
             // Calculate duration until next run
-            let Some(sleep_dur) = next_run_duration(&schedule) else {
+            let Some(sleep_dur) = next_run_duration_in(&schedule, zone) else {
                 return;
             };
 
@@ -69,6 +223,21 @@ pub fn spawn_cron_restart(
             {
                 let table = processes.read().await;
                 match table.get(&name) {
+                    Some(managed) if managed.paused => {
+                        tracing::info!("cron restart skipped for '{}': monitoring paused", name);
+                        continue;
+                    }
+                    Some(managed) if managed.status == ProcessStatus::Suspended => {
+                        tracing::info!("cron restart skipped for '{}': process suspended", name);
+                        continue;
+                    }
+                    Some(managed)
+                        if managed.config.memory_leak_action == Some(LeakAction::CronRestart)
+                            && managed.memory_leak.is_none() =>
+                    {
+                        tracing::info!("cron restart skipped for '{}': no memory leak", name);
+                        continue;
+                    }
                     Some(managed)
                         if managed.status == ProcessStatus::Online
                             || managed.status == ProcessStatus::Starting =>
@@ -79,7 +248,7 @@ pub fn spawn_cron_restart(
                 }
             }
 
-            eprintln!("cron restart triggered for '{}'", name);
+            tracing::info!("cron restart triggered for '{}'", name);
 
             // Graceful stop
             let (old_config, old_restarts) = {
@@ -97,7 +266,7 @@ pub fn spawn_cron_restart(
 
                 let _ = managed.graceful_stop().await;
                 if let Some(ref hook) = cfg.post_stop {
-                    let _ = process::run_hook(hook, &name, cfg.cwd.as_deref(), &paths).await;
+                    let _ = process::run_hook(hook, &name, &cfg, &paths).await;
                 }
 
                 (cfg, restarts)
@@ -115,7 +284,7 @@ pub fn spawn_cron_restart(
             {
                 Ok(()) => return, // This cron instance terminates; the new one takes over
                 Err(e) => {
-                    eprintln!("failed to restart '{}' on cron schedule: {}", name, e);
+                    tracing::error!("failed to restart '{}' on cron schedule: {}", name, e);
                     let mut table = processes.write().await;
                     if let Some(managed) = table.get_mut(&name) {
                         managed.status = ProcessStatus::Errored;
@@ -178,6 +347,58 @@ mod tests {
         assert!(dur.unwrap().as_secs() <= 60);
     }
 
+    #[test]
+    fn test_next_scheduled_action_picks_earliest() {
+        let every_minute = parse_cron_expression("* * * * *").unwrap();
+        let yearly = parse_cron_expression("0 0 1 1 *").unwrap();
+
+        let utc = Tz::UTC;
+
+        let (_, action) = next_scheduled_action(Some(&yearly), Some(&every_minute), utc).unwrap();
+        assert_eq!(action, CronAction::Stop);
+        let (_, action) = next_scheduled_action(Some(&every_minute), Some(&yearly), utc).unwrap();
+        assert_eq!(action, CronAction::Start);
+        let (_, action) = next_scheduled_action(Some(&yearly), None, utc).unwrap();
+        assert_eq!(action, CronAction::Start);
+        assert!(next_scheduled_action(None, None, utc).is_none());
+    }
+
+    #[test]
+    fn test_next_run_duration_in_timezone() {
+        // Midnight one hour east of UTC comes one hour before UTC midnight.
+        let schedule = parse_cron_expression("0 0 * * *").unwrap();
+        let utc = next_run_duration(&schedule).unwrap().as_secs() as i64;
+        let east = chrono::FixedOffset::east_opt(3600).unwrap();
+        let shifted = next_run_duration_in(&schedule, east).unwrap().as_secs() as i64;
+        let diff = (utc - shifted).rem_euclid(86_400);
+        assert!((3599..=3601).contains(&diff), "diff was {diff}");
+    }
+
+    #[test]
+    fn test_resolve_timezone_defaults_to_utc() {
+        let zone = resolve_timezone(None).unwrap();
+        let now = Utc::now().naive_utc();
+        assert_eq!(
+            chrono::Offset::fix(&zone.offset_from_utc_datetime(&now)).local_minus_utc(),
+            0
+        );
+        assert!(resolve_timezone(Some("Not/AZone")).is_err());
+    }
+
+    #[test]
+    fn test_resolve_timezone_follows_daylight_saving() {
+        let zone = resolve_timezone(Some("Europe/Berlin")).unwrap();
+        let offset = |month| {
+            let date = chrono::NaiveDate::from_ymd_opt(2025, month, 1)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap();
+            chrono::Offset::fix(&zone.offset_from_utc_datetime(&date)).local_minus_utc()
+        };
+        assert_eq!(offset(1), 3600);
+        assert_eq!(offset(7), 7200);
+    }
+
     #[test]
     fn test_next_run_duration_every_5_min() {
         let schedule = parse_cron_expression("*/5 * * * *").unwrap();
@@ -1,39 +1,385 @@
-use crate::manager::Manager;
+use crate::audit::{self, AuditEntry};
+use crate::availability;
+use crate::client::DaemonClient;
+use crate::events;
+use crate::grpc;
+use crate::idle;
+use crate::log;
+use crate::manager::{LogQuery, Manager, Progress};
 use crate::memory;
+use crate::notifier;
 use crate::paths::Paths;
 use crate::pid;
-use crate::protocol::{self, Request};
+use crate::protocol::{self, Request, Response};
+use crate::quota;
+use crate::settings::{self, DaemonSettings, GrpcSettings, RemoteSettings};
+use crate::statsd;
 use crate::sys;
+use crate::telemetry;
+use crate::timeseries;
+use crate::tls;
+use crate::upgrade::{self, Handover};
+use crate::web;
 use color_eyre::eyre::bail;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use tokio::fs;
-use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
-use tokio::sync::watch;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio_rustls::TlsAcceptor;
+
+/// Longest auth line accepted from a not-yet-authenticated remote client.
+const MAX_AUTH_LINE: u64 = 4096;
+
+/// How much of a rejected remote client's request is read before hanging up.
+const MAX_REJECTED_INPUT: u64 = 1024 * 1024;
+
+/// How long a remote client has to finish the TLS handshake and send its
+/// auth line, so silent connections don't pile up.
+const AUTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Who is on the other end of a connection.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Client {
+    /// A local socket client, with its peer credentials where available.
+    Local(Option<sys::PeerCredentials>),
+    /// A TCP client that presented the auth token or a trusted client certificate.
+    Remote(SocketAddr),
+}
+
+/// The optional TCP listener for remote clients.
+struct RemoteListener {
+    listener: TcpListener,
+    /// Token clients must send first; `None` when client certificates suffice.
+    token: Option<String>,
+    tls: Option<TlsAcceptor>,
+}
 
 pub async fn run(paths: Paths) -> color_eyre::Result<()> {
-    fs::create_dir_all(paths.data_dir()).await?;
+    run_with_remote(paths, RemoteSettings::default(), false, None).await
+}
+
+/// A daemon embedded in the current tokio runtime, for integration tests
+/// and programs that manage processes without a separate `pm3` binary.
+///
+/// ```no_run
+/// # async fn example() -> color_eyre::Result<()> {
+/// let dir = tempfile::tempdir()?;
+/// let paths = pm3::paths::Paths::with_base(dir.path().to_path_buf());
+/// let daemon = pm3::daemon::Daemon::builder().paths(paths).spawn().await?;
+/// assert!(daemon.client().list().await?.is_empty());
+/// daemon.shutdown().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Daemon;
+
+impl Daemon {
+    pub fn builder() -> DaemonBuilder {
+        DaemonBuilder::default()
+    }
+}
+
+#[derive(Default)]
+pub struct DaemonBuilder {
+    paths: Option<Paths>,
+    remote: RemoteSettings,
+    resurrect: bool,
+}
+
+impl DaemonBuilder {
+    /// The data dir to run in. Defaults to the CLI's, `Paths::new()`.
+    pub fn paths(mut self, paths: Paths) -> Self {
+        self.paths = Some(paths);
+        self
+    }
+
+    /// Remote listener settings, overriding `[remote]` in `settings.toml`.
+    pub fn remote(mut self, remote: RemoteSettings) -> Self {
+        self.remote = remote;
+        self
+    }
+
+    /// Whether restoring the saved processes on startup is required, like
+    /// `pm3 daemon start --resurrect`, so a missing dump file is logged.
+    pub fn resurrect(mut self, resurrect: bool) -> Self {
+        self.resurrect = resurrect;
+        self
+    }
+
+    /// Starts the daemon as a task on the current runtime and returns once it
+    /// is listening, or with the error it failed to start with.
+    pub async fn spawn(self) -> color_eyre::Result<DaemonHandle> {
+        let paths = match self.paths {
+            Some(paths) => paths,
+            None => Paths::new()?,
+        };
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let task = tokio::spawn(serve(
+            paths.clone(),
+            self.remote,
+            self.resurrect,
+            None,
+            Some(ready_tx),
+        ));
+        let handle = DaemonHandle { paths, task };
+        if ready_rx.await.is_err() {
+            // The daemon gave up before listening; its result says why
+            handle.await?;
+            bail!("daemon exited while starting");
+        }
+        Ok(handle)
+    }
+}
+
+/// A running embedded daemon. Awaiting the handle waits for the daemon to
+/// exit; dropping it leaves the daemon running until the runtime shuts down.
+pub struct DaemonHandle {
+    paths: Paths,
+    task: tokio::task::JoinHandle<color_eyre::Result<()>>,
+}
+
+impl DaemonHandle {
+    pub fn paths(&self) -> &Paths {
+        &self.paths
+    }
+
+    /// A client connected to this daemon.
+    pub fn client(&self) -> DaemonClient {
+        DaemonClient::new(self.paths.clone())
+    }
+
+    /// Stops every process, like `pm3 kill`, and waits for the daemon to exit.
+    pub async fn shutdown(self) -> color_eyre::Result<()> {
+        self.client().request(&Request::Kill).await?;
+        self.await
+    }
+}
+
+impl std::future::IntoFuture for DaemonHandle {
+    type Output = color_eyre::Result<()>;
+    type IntoFuture = std::pin::Pin<Box<dyn std::future::Future<Output = Self::Output> + Send>>;
 
-    if pid::is_daemon_running(&paths).await? {
-        bail!("daemon is already running");
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(async move { self.task.await? })
+    }
+}
+
+/// Runs the daemon with `remote`'s fields (from `--listen` and
+/// `--auth-token-file`) taking precedence over the `[remote]` settings, and
+/// with `resurrect` (`--resurrect`) requiring the saved processes be restored.
+/// With `handover` (`--handover`) it takes over the socket and processes of
+/// the daemon `pm3 daemon upgrade` replaced with it instead.
+pub async fn run_with_remote(
+    paths: Paths,
+    remote: RemoteSettings,
+    resurrect: bool,
+    handover: Option<PathBuf>,
+) -> color_eyre::Result<()> {
+    serve(paths, remote, resurrect, handover, None).await
+}
+
+/// Runs the daemon, reporting on `ready` once it accepts connections.
+async fn serve(
+    paths: Paths,
+    remote: RemoteSettings,
+    resurrect: bool,
+    handover: Option<PathBuf>,
+    ready: Option<oneshot::Sender<()>>,
+) -> color_eyre::Result<()> {
+    fs::create_dir_all(paths.data_dir()).await?;
+    let mut settings = settings::load(&paths)?;
+    init_logging(&paths, settings.daemon.log_level)?;
+    if remote.listen.is_some() {
+        settings.remote.listen = remote.listen;
+    }
+    if remote.auth_token_file.is_some() {
+        settings.remote.auth_token_file = remote.auth_token_file;
     }
 
+    // An upgraded daemon keeps the pid, so the pid file names this process
+    let handover = match handover {
+        Some(path) => match Handover::take(&path) {
+            Ok(handover) => Some(handover),
+            Err(e) => {
+                tracing::error!("upgrade: {e}; starting without the handed-over processes");
+                None
+            }
+        },
+        None if pid::is_daemon_running(&paths).await? => bail!("daemon is already running"),
+        None => None,
+    };
+
     pid::write_pid_file(&paths).await?;
 
-    let listener = sys::ipc_bind(&paths).await?;
+    let listener = match &handover {
+        // SAFETY: the handover was just taken from the daemon this one replaced
+        #[cfg(unix)]
+        Some(handover) => unsafe { upgrade::listener(handover)? },
+        _ => sys::ipc_bind(&paths, settings.daemon.socket_mode).await?,
+    };
+    let remote = match bind_remote(&settings.remote).await {
+        Ok(remote) => remote,
+        Err(e) => {
+            tracing::error!("remote listener: {e}");
+            sys::ipc_cleanup(&paths).await;
+            pid::remove_pid_file(&paths).await;
+            return Err(e);
+        }
+    };
+    let grpc = match bind_grpc(&settings.grpc).await {
+        Ok(grpc) => grpc,
+        Err(e) => {
+            tracing::error!("grpc listener: {e}");
+            sys::ipc_cleanup(&paths).await;
+            pid::remove_pid_file(&paths).await;
+            return Err(e);
+        }
+    };
+
+    let web = match bind_web(&settings.daemon).await {
+        Ok(web) => web,
+        Err(e) => {
+            tracing::error!("web dashboard: {e}");
+            sys::ipc_cleanup(&paths).await;
+            pid::remove_pid_file(&paths).await;
+            return Err(e);
+        }
+    };
 
     let (shutdown_tx, mut shutdown_rx) = watch::channel(false);
-    let manager = Manager::new(paths.clone());
+    let mut manager = Manager::new(paths.clone(), settings);
+    // Embedded daemons run inside another program, which isn't theirs to exec
+    // and has no `pm3 log-relay` to run
+    if ready.is_none() {
+        match std::env::current_exe() {
+            Ok(exe) => {
+                crate::log::enable_relay(exe.clone());
+                manager.enable_upgrade(exe, handover.as_ref());
+            }
+            Err(e) => tracing::warn!("daemon upgrade and log relays unavailable: {e}"),
+        }
+    }
 
-    manager.auto_restore().await;
+    // Restore in the background so clients can connect (and query
+    // restore progress) while saved processes come back up.
+    let restorer = manager.clone();
+    tokio::spawn(async move {
+        match handover {
+            Some(handover) => restorer.adopt_handover(handover.processes).await,
+            None => restorer.auto_restore(resurrect).await,
+        }
+    });
 
+    manager.spawn_autosaver(shutdown_tx.subscribe());
+    idle::spawn_idle_watcher(
+        manager.settings().daemon.idle_timeout().unwrap_or_default(),
+        manager.activity(),
+        manager.processes(),
+        shutdown_tx.clone(),
+    );
     memory::spawn_stats_collector(
         manager.processes(),
         manager.stats_cache(),
         shutdown_tx.subscribe(),
     );
+    timeseries::spawn_sampler(
+        manager.settings().history.clone(),
+        manager.history(),
+        manager.processes(),
+        manager.stats_cache(),
+        manager.paths().clone(),
+        shutdown_tx.subscribe(),
+    );
+    availability::spawn_tracker(
+        manager.processes(),
+        manager.paths().clone(),
+        shutdown_tx.subscribe(),
+    );
+    events::spawn_event_watcher(
+        manager.processes(),
+        manager.events(),
+        shutdown_tx.subscribe(),
+    );
+    quota::spawn_quota_watcher(
+        manager.settings().daemon.disk_quota().unwrap_or_default(),
+        manager.paths().clone(),
+        shutdown_tx.subscribe(),
+    );
+    // What logrotate's postrotate scripts send, like `pm3 reopen-logs`
+    sys::on_reopen_signal({
+        let manager = manager.clone();
+        move || {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.reopen_logs(None, None).await });
+        }
+    })?;
+    statsd::spawn_statsd_reporter(
+        manager.settings().metrics.clone(),
+        manager.processes(),
+        manager.stats_cache(),
+        manager.events().subscribe(),
+        shutdown_tx.subscribe(),
+    );
+    telemetry::spawn_telemetry_exporter(
+        manager.settings().telemetry.clone(),
+        manager.processes(),
+        manager.stats_cache(),
+        manager.events().subscribe(),
+        shutdown_tx.subscribe(),
+    );
+    notifier::spawn_notifiers(
+        &manager.settings().notify,
+        &manager.events(),
+        &shutdown_tx.subscribe(),
+    );
+    let grpc_server = grpc.map(|(listener, token)| {
+        grpc::spawn_server(listener, token, manager.clone(), shutdown_tx.clone())
+    });
+    let web_server = web.map(|(listener, token)| {
+        web::spawn_server(listener, token, manager.clone(), shutdown_tx.clone())
+    });
+
+    tracing::info!(
+        "daemon started (pid {}, version {})",
+        std::process::id(),
+        env!("CARGO_PKG_VERSION")
+    );
+    if let Some(ready) = ready {
+        let _ = ready.send(());
+    }
 
-    let result = run_accept_loop(&listener, &shutdown_tx, &mut shutdown_rx, &manager).await;
+    let result = loop {
+        match run_accept_loop(
+            &listener,
+            remote.as_ref(),
+            &shutdown_tx,
+            &mut shutdown_rx,
+            &manager,
+        )
+        .await
+        {
+            Ok(LoopExit::Upgrade) => {
+                #[cfg(unix)]
+                {
+                    let err = upgrade::exec(&manager, &listener).await;
+                    tracing::error!("upgrade failed, carrying on as before: {err}");
+                }
+            }
+            Ok(LoopExit::Shutdown) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+
+    tracing::info!("daemon shutting down");
+
+    for server in [grpc_server, web_server].into_iter().flatten() {
+        server.abort();
+    }
 
     manager.shutdown_all().await;
+    availability::record_shutdown(&paths, &manager.processes()).await;
 
     sys::ipc_cleanup(&paths).await;
     pid::remove_pid_file(&paths).await;
@@ -41,12 +387,139 @@ pub async fn run(paths: Paths) -> color_eyre::Result<()> {
     result
 }
 
+/// Sends daemon diagnostics to `daemon.log` in the data dir. The subscriber
+/// is process-wide, so only the first daemon in a process installs it.
+/// `daemon.log`, which `pm3 reopen-logs` and SIGUSR1 reopen once a tool
+/// like logrotate moved it aside.
+struct DaemonLog {
+    path: PathBuf,
+    file: std::sync::Mutex<std::fs::File>,
+}
+
+static DAEMON_LOG: std::sync::OnceLock<std::sync::Arc<DaemonLog>> = std::sync::OnceLock::new();
+
+impl DaemonLog {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            file: std::sync::Mutex::new(file),
+        })
+    }
+}
+
+impl std::io::Write for &DaemonLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.lock().unwrap().flush()
+    }
+}
+
+/// Closes `daemon.log` and opens it again at its path. Does nothing in a
+/// process that isn't logging to one.
+pub fn reopen_daemon_log() -> std::io::Result<()> {
+    let Some(log) = DAEMON_LOG.get() else {
+        return Ok(());
+    };
+    let reopened = DaemonLog::open(log.path.clone())?;
+    *log.file.lock().unwrap() = reopened.file.into_inner().unwrap();
+    Ok(())
+}
+
+fn init_logging(paths: &Paths, level: settings::LogLevel) -> color_eyre::Result<()> {
+    let log = std::sync::Arc::new(DaemonLog::open(paths.daemon_log())?);
+    // The first daemon in a process keeps the log, as tracing does
+    let _ = DAEMON_LOG.set(log.clone());
+    let _ = tracing_subscriber::fmt()
+        .with_writer(log)
+        .with_ansi(false)
+        .with_target(false)
+        .with_max_level(tracing::Level::from(level))
+        .try_init();
+    Ok(())
+}
+
+async fn bind_remote(remote: &RemoteSettings) -> color_eyre::Result<Option<RemoteListener>> {
+    let Some(addr) = remote.listen else {
+        return Ok(None);
+    };
+    remote.validate()?;
+    let token = match remote.auth_token_file {
+        Some(_) => Some(remote.load_token()?),
+        None => None,
+    };
+    let tls = match (&remote.tls_cert, &remote.tls_key) {
+        (Some(cert), Some(key)) => {
+            let config = tls::server_config(cert, key, remote.tls_client_ca.as_deref())?;
+            Some(TlsAcceptor::from(config))
+        }
+        _ => None,
+    };
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(
+        "accepting remote clients on {}{}",
+        listener.local_addr()?,
+        if tls.is_some() { " (tls)" } else { "" }
+    );
+    Ok(Some(RemoteListener {
+        listener,
+        token,
+        tls,
+    }))
+}
+
+async fn bind_grpc(grpc: &GrpcSettings) -> color_eyre::Result<Option<(TcpListener, String)>> {
+    let Some(addr) = grpc.listen else {
+        return Ok(None);
+    };
+    grpc.validate()?;
+    let token = grpc.load_token()?;
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("serving grpc on {}", listener.local_addr()?);
+    Ok(Some((listener, token)))
+}
+
+async fn bind_web(daemon: &DaemonSettings) -> color_eyre::Result<Option<(TcpListener, String)>> {
+    if !daemon.web_ui {
+        return Ok(None);
+    }
+    let token = daemon.load_web_token()?;
+    let listener = TcpListener::bind(daemon.web_listen).await?;
+    tracing::info!("serving web dashboard on http://{}", listener.local_addr()?);
+    Ok(Some((listener, token)))
+}
+
+/// Waits for the next remote client, or forever when remote access is off.
+async fn accept_remote(
+    remote: Option<&RemoteListener>,
+) -> std::io::Result<(TcpStream, SocketAddr)> {
+    match remote {
+        Some(remote) => remote.listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Why the accept loop stopped.
+enum LoopExit {
+    Shutdown,
+    /// `pm3 daemon upgrade` asked the daemon to re-exec.
+    Upgrade,
+}
+
 async fn run_accept_loop(
     listener: &sys::IpcListener,
+    remote: Option<&RemoteListener>,
     shutdown_tx: &watch::Sender<bool>,
     shutdown_rx: &mut watch::Receiver<bool>,
     manager: &Manager,
-) -> color_eyre::Result<()> {
+) -> color_eyre::Result<LoopExit> {
+    let upgrader = manager.upgrader();
     loop {
         tokio::select! {
             accept_result = sys::ipc_accept(listener) => {
@@ -55,7 +528,48 @@ async fn run_accept_loop(
                 let mgr = manager.clone();
                 tokio::spawn(async move {
                     if let Err(e) = handle_connection(stream, &tx, &mgr).await {
-                        eprintln!("connection error: {e}");
+                        tracing::error!("connection error: {e}");
+                    }
+                });
+            }
+            accept_result = accept_remote(remote) => {
+                // A failed remote accept shouldn't take the local socket down with it
+                let (stream, addr) = match accept_result {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!("remote accept failed: {e}");
+                        continue;
+                    }
+                };
+                let token = remote.and_then(|r| r.token.clone());
+                let tls = remote.and_then(|r| r.tls.clone());
+                let tx = shutdown_tx.clone();
+                let mgr = manager.clone();
+                tokio::spawn(async move {
+                    let result = match tls {
+                        Some(acceptor) => {
+                            let handshake = acceptor.accept(stream);
+                            let stream = match tokio::time::timeout(AUTH_TIMEOUT, handshake).await {
+                                Ok(Ok(stream)) => stream,
+                                Ok(Err(e)) => {
+                                    tracing::warn!("tls handshake with {addr} failed: {e}");
+                                    return;
+                                }
+                                Err(_) => {
+                                    tracing::warn!("tls handshake with {addr} timed out");
+                                    return;
+                                }
+                            };
+                            handle_remote_connection(stream, addr, token.as_deref(), &tx, &mgr)
+                                .await
+                        }
+                        None => {
+                            handle_remote_connection(stream, addr, token.as_deref(), &tx, &mgr)
+                                .await
+                        }
+                    };
+                    if let Err(e) = result {
+                        tracing::error!("remote connection error ({addr}): {e}");
                     }
                 });
             }
@@ -67,10 +581,13 @@ async fn run_accept_loop(
             _ = sys::signal_shutdown() => {
                 break;
             }
+            _ = upgrader.requested() => {
+                return Ok(LoopExit::Upgrade);
+            }
         }
     }
 
-    Ok(())
+    Ok(LoopExit::Shutdown)
 }
 
 async fn handle_connection(
@@ -78,13 +595,79 @@ async fn handle_connection(
     shutdown_tx: &watch::Sender<bool>,
     manager: &Manager,
 ) -> color_eyre::Result<()> {
+    let peer = sys::peer_credentials(&stream);
     let (reader, writer) = tokio::io::split(stream);
-    handle_connection_inner(reader, writer, shutdown_tx, manager).await
+    handle_connection_inner(reader, writer, Client::Local(peer), shutdown_tx, manager).await
+}
+
+/// Checks the auth line a remote client sends first, if a token is required,
+/// then serves its request like a local one.
+async fn handle_remote_connection(
+    stream: impl AsyncRead + AsyncWrite,
+    addr: SocketAddr,
+    token: Option<&str>,
+    shutdown_tx: &watch::Sender<bool>,
+    manager: &Manager,
+) -> color_eyre::Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let Some(token) = token else {
+        return handle_connection_inner(reader, writer, Client::Remote(addr), shutdown_tx, manager)
+            .await;
+    };
+    let mut line = String::new();
+    let read = tokio::time::timeout(
+        AUTH_TIMEOUT,
+        (&mut reader).take(MAX_AUTH_LINE).read_line(&mut line),
+    )
+    .await;
+    let Ok(read) = read else {
+        tracing::warn!("dropped remote client {addr}: no auth line within {AUTH_TIMEOUT:?}");
+        return Ok(());
+    };
+    read?;
+
+    let authenticated =
+        protocol::decode_auth(&line).is_ok_and(|auth| tokens_match(&auth.token, token));
+    if !authenticated {
+        tracing::warn!("rejected remote client {addr}: bad or missing auth token");
+        // Read out the request the client already sent: closing with unread
+        // input resets the connection and the client would never see the error.
+        let mut rest = reader.take(MAX_REJECTED_INPUT);
+        let mut sink = tokio::io::sink();
+        // A client that never finishes sending is only waited on so long
+        if let Ok(copied) =
+            tokio::time::timeout(AUTH_TIMEOUT, tokio::io::copy(&mut rest, &mut sink)).await
+        {
+            copied?;
+        }
+        let response = protocol::Response::Error {
+            message: "authentication failed".to_string(),
+        };
+        writer
+            .write_all(&protocol::encode_response(&response)?)
+            .await?;
+        writer.shutdown().await?;
+        return Ok(());
+    }
+
+    handle_connection_inner(reader, writer, Client::Remote(addr), shutdown_tx, manager).await
+}
+
+/// Compares tokens without bailing out at the first differing byte.
+pub(crate) fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
 }
 
 async fn handle_connection_inner(
     reader: impl AsyncRead + Unpin,
     mut writer: impl AsyncWrite + Unpin,
+    client: Client,
     shutdown_tx: &watch::Sender<bool>,
     manager: &Manager,
 ) -> color_eyre::Result<()> {
@@ -96,25 +679,353 @@ async fn handle_connection_inner(
         return Ok(());
     }
 
-    let request = protocol::decode_request(&line)?;
+    match decode_request(&line) {
+        Ok(Request::Session) => {
+            return run_session(buf_reader, writer, client, shutdown_tx, manager).await;
+        }
+        Ok(request) => serve_request(request, &mut writer, client, shutdown_tx, manager).await?,
+        Err(response) => {
+            writer
+                .write_all(&protocol::encode_response(&response)?)
+                .await?;
+        }
+    }
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+/// Decodes a request line, or the error to answer it with. Answering rather
+/// than dropping the connection lets the client say why.
+fn decode_request(line: &str) -> Result<Request, protocol::Response> {
+    protocol::decode_request(line).map_err(|e| {
+        let message = protocol::message_version(line)
+            .and_then(|version| protocol::version_mismatch("CLI", version))
+            .unwrap_or_else(|| format!("invalid request: {e}"));
+        tracing::warn!("{message}");
+        protocol::Response::Error { message }
+    })
+}
+
+/// Refuses local clients outside `allowed_uids`/`allowed_gids`. Remote
+/// clients already proved themselves with the token.
+fn rejection(client: Client, manager: &Manager) -> Option<protocol::Response> {
+    let Client::Local(peer) = client else {
+        return None;
+    };
+    if manager
+        .settings()
+        .daemon
+        .authorizes(peer, sys::current_uid())
+    {
+        return None;
+    }
+    let uid = peer.map_or_else(|| "unknown".to_string(), |p| p.uid.to_string());
+    tracing::warn!("rejected request from uid {uid}: not in allowed_uids/allowed_gids");
+    Some(protocol::Response::Error {
+        message: format!("permission denied: uid {uid} may not control this daemon"),
+    })
+}
+
+/// Writes every response to `request`; the caller closes the writer.
+async fn serve_request(
+    request: Request,
+    writer: &mut (impl AsyncWrite + Unpin),
+    client: Client,
+    shutdown_tx: &watch::Sender<bool>,
+    manager: &Manager,
+) -> color_eyre::Result<()> {
+    let _activity = manager.activity().begin();
+    let command = audit::describe(&request);
+
+    if let Some(response) = rejection(client, manager) {
+        record_audit(manager, client, command, audit::outcome(&response)).await;
+        writer
+            .write_all(&protocol::encode_response(&response)?)
+            .await?;
+        return Ok(());
+    }
 
     if let Request::Log {
-        ref name,
+        name,
         lines,
         follow,
+        since,
+        until,
+        stream,
+        namespace,
     } = request
     {
-        manager
-            .stream_logs(name.clone(), lines, follow, &mut writer)
+        let range = match log::TimeRange::parse(since.as_deref(), until.as_deref()) {
+            Ok(range) => range,
+            Err(message) => {
+                writer
+                    .write_all(&protocol::encode_response(&Response::Error { message })?)
+                    .await?;
+                return Ok(());
+            }
+        };
+        let query = LogQuery {
+            name,
+            namespace,
+            lines,
+            follow,
+            range,
+            only: stream,
+        };
+        manager.stream_logs(query, writer).await?;
+        return Ok(());
+    }
+
+    if let Request::Events { names } = request {
+        manager.stream_events(names, writer).await?;
+        return Ok(());
+    }
+
+    if let Request::Run {
+        configs,
+        name,
+        env,
+        path,
+        namespace,
+    } = request
+    {
+        let finished = manager
+            .run_task(configs, name, env, path, namespace, writer)
             .await?;
-        writer.shutdown().await?;
+        let outcome = finished
+            .as_ref()
+            .map(audit::outcome)
+            .unwrap_or_else(|| "client disconnected".to_string());
+        record_audit(manager, client, command, outcome).await;
         return Ok(());
     }
 
-    let response = manager.dispatch(request, shutdown_tx).await;
-    let encoded = protocol::encode_response(&response)?;
-    writer.write_all(&encoded).await?;
-    writer.shutdown().await?;
+    if let Request::Start {
+        wait: true,
+        progress: true,
+        ..
+    } = request
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let starter = manager.clone();
+        let start = tokio::spawn(async move {
+            starter
+                .start_request(request, Progress::streaming(tx))
+                .await
+        });
+        // The channel closes once start returns, so this drains every update
+        while let Some(update) = rx.recv().await {
+            writer
+                .write_all(&protocol::encode_response(&update)?)
+                .await?;
+        }
+        let response = start.await?;
+        record_audit(manager, client, command, audit::outcome(&response)).await;
+        writer
+            .write_all(&protocol::encode_response(&response)?)
+            .await?;
+        return Ok(());
+    }
 
+    // Shutting down on kill waits for the answer, or the daemon could exit
+    // before the client hears back
+    let kill = matches!(request, Request::Kill);
+    let deferred_shutdown = watch::channel(false).0;
+    let dispatch_tx = if kill {
+        &deferred_shutdown
+    } else {
+        shutdown_tx
+    };
+    let response = manager.dispatch(request, dispatch_tx).await;
+    record_audit(manager, client, command, audit::outcome(&response)).await;
+    let written = writer
+        .write_all(&protocol::encode_response(&response)?)
+        .await;
+    if kill {
+        let _ = writer.flush().await;
+        let _ = shutdown_tx.send(true);
+    }
+    written?;
     Ok(())
 }
+
+/// Serves a session: after acknowledging it, each line is a request tagged
+/// with an id. Requests run concurrently and their responses are interleaved
+/// on the one connection, each tagged with the request's id and followed by
+/// `Done`. The session ends when the client closes its side and every
+/// request has finished.
+async fn run_session(
+    reader: BufReader<impl AsyncRead + Unpin>,
+    mut writer: impl AsyncWrite + Unpin,
+    client: Client,
+    shutdown_tx: &watch::Sender<bool>,
+    manager: &Manager,
+) -> color_eyre::Result<()> {
+    if let Some(response) = rejection(client, manager) {
+        writer
+            .write_all(&protocol::encode_response(&response)?)
+            .await?;
+        writer.shutdown().await?;
+        return Ok(());
+    }
+    // An open session counts as activity even between requests
+    let _activity = manager.activity().begin();
+    writer
+        .write_all(&protocol::encode_response(&protocol::Response::Success {
+            message: Some("session started".to_string()),
+        })?)
+        .await?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<u8>>();
+    let read_requests = async move {
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (id, request) = match protocol::decode_tagged_request(&line) {
+                Ok(tagged) if tagged.message == Request::Session => (
+                    tagged.id,
+                    Err(protocol::Response::Error {
+                        message: "a session can't be opened inside another".to_string(),
+                    }),
+                ),
+                Ok(tagged) => (tagged.id, Ok(tagged.message)),
+                Err(_) => (
+                    protocol::message_id(&line).unwrap_or(protocol::PUSH_ID),
+                    decode_request(&line).and(Err(protocol::Response::Error {
+                        message: "session requests need a numeric \"id\"".to_string(),
+                    })),
+                ),
+            };
+            tokio::spawn(serve_tagged(
+                id,
+                request,
+                client,
+                shutdown_tx.clone(),
+                manager.clone(),
+                tx.clone(),
+            ));
+        }
+        Ok::<_, color_eyre::Report>(())
+    };
+    let write_responses = async move {
+        // Ends once the reader and every request task have dropped their senders
+        while let Some(bytes) = rx.recv().await {
+            writer.write_all(&bytes).await?;
+        }
+        writer.shutdown().await?;
+        Ok::<_, color_eyre::Report>(())
+    };
+
+    let (read, write) = tokio::join!(read_requests, write_responses);
+    read.and(write)
+}
+
+/// Tags each response to one session request with `id` on its way to the
+/// session writer, then marks the end with `Done`.
+async fn serve_tagged(
+    id: u64,
+    request: Result<Request, protocol::Response>,
+    client: Client,
+    shutdown_tx: watch::Sender<bool>,
+    manager: Manager,
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+) {
+    let tag = |response: &protocol::Response| protocol::encode_tagged_response(id, response);
+    match request {
+        Ok(request) => {
+            let mut responses = spawn_request(request, client, shutdown_tx, manager);
+            while let Some(response) = responses.recv().await {
+                let Ok(bytes) = tag(&response) else {
+                    continue;
+                };
+                // Once the session is gone, dropping `responses` stops the request
+                if tx.send(bytes).is_err() {
+                    return;
+                }
+            }
+        }
+        Err(response) => {
+            if let Ok(bytes) = tag(&response) {
+                let _ = tx.send(bytes);
+            }
+        }
+    }
+    if let Ok(bytes) = tag(&protocol::Response::Done) {
+        let _ = tx.send(bytes);
+    }
+}
+
+/// How many responses a request may run ahead of a slow consumer.
+const RESPONSE_BUFFER: usize = 64;
+
+/// Runs `request` through `serve_request` on an in-memory pipe and hands
+/// back its responses as they are written. Dropping the receiver stops a
+/// streaming request.
+pub(crate) fn spawn_request(
+    request: Request,
+    client: Client,
+    shutdown_tx: watch::Sender<bool>,
+    manager: Manager,
+) -> mpsc::Receiver<protocol::Response> {
+    let (tx, rx) = mpsc::channel(RESPONSE_BUFFER);
+    tokio::spawn(async move {
+        let (mut near, far) = tokio::io::duplex(64 * 1024);
+        let serve = async {
+            let result = serve_request(request, &mut near, client, &shutdown_tx, &manager).await;
+            let _ = near.shutdown().await;
+            result
+        };
+        let forward = async {
+            let mut lines = BufReader::new(far).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let Ok(response) = protocol::decode_response(&line) else {
+                    continue;
+                };
+                if tx.send(response).await.is_err() {
+                    break;
+                }
+            }
+        };
+        let (result, ()) = tokio::join!(serve, forward);
+        if let Err(e) = result {
+            tracing::warn!("request failed: {e}");
+        }
+    });
+    rx
+}
+
+/// Runs a request that answers once, returning the last response it sent.
+pub(crate) async fn run_request(
+    request: Request,
+    client: Client,
+    shutdown_tx: watch::Sender<bool>,
+    manager: Manager,
+) -> Option<protocol::Response> {
+    let mut responses = spawn_request(request, client, shutdown_tx, manager);
+    let mut last = None;
+    while let Some(response) = responses.recv().await {
+        last = Some(response);
+    }
+    last
+}
+
+/// Appends a state-changing request to `audit.log`; read-only requests have
+/// no `command` and are skipped.
+async fn record_audit(manager: &Manager, client: Client, command: Option<String>, outcome: String) {
+    let Some(command) = command else {
+        return;
+    };
+    let entry = match client {
+        Client::Local(peer) => AuditEntry::new(peer, command, outcome),
+        Client::Remote(addr) => AuditEntry {
+            remote: Some(addr.to_string()),
+            ..AuditEntry::new(None, command, outcome)
+        },
+    };
+    if let Err(e) = audit::record(manager.paths(), &entry).await {
+        tracing::warn!("failed to write audit log: {e}");
+    }
+}
@@ -149,6 +149,89 @@ fn find_cycle(configs: &HashMap<String, ProcessConfig>) -> Vec<String> {
     vec!["unknown cycle".to_string()]
 }
 
+/// Processes that are only ever optional dependencies: something depends on
+/// them, and every dependent marks them optional. Their failure to start is
+/// a warning rather than an error.
+pub fn optional_only(configs: &HashMap<String, ProcessConfig>) -> HashSet<String> {
+    let mut required: HashSet<&str> = HashSet::new();
+    let mut optional: HashSet<&str> = HashSet::new();
+    for config in configs.values() {
+        let optional_deps = config.optional_deps.as_deref().unwrap_or_default();
+        for dep in config.depends_on.iter().flatten() {
+            if optional_deps.contains(dep) {
+                optional.insert(dep);
+            } else {
+                required.insert(dep);
+            }
+        }
+    }
+    optional
+        .difference(&required)
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Renders the dependency graph as Graphviz DOT. Edges point from a process to
+/// what it depends on; optional dependencies are dashed.
+pub fn render_dot(configs: &HashMap<String, ProcessConfig>) -> Result<String, DepsError> {
+    validate_deps(configs)?;
+    let mut names: Vec<&String> = configs.keys().collect();
+    names.sort();
+
+    let mut out = String::from("digraph pm3 {\n    rankdir=LR;\n");
+    for name in &names {
+        out.push_str(&format!("    \"{name}\";\n"));
+    }
+    for name in &names {
+        let config = &configs[*name];
+        let optional_deps = config.optional_deps.as_deref().unwrap_or_default();
+        for dep in config.depends_on.iter().flatten() {
+            if optional_deps.contains(dep) {
+                out.push_str(&format!("    \"{name}\" -> \"{dep}\" [style=dashed];\n"));
+            } else {
+                out.push_str(&format!("    \"{name}\" -> \"{dep}\";\n"));
+            }
+        }
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// Renders the dependency graph as text, one block per start level, listing
+/// each process with what it depends on.
+pub fn render_ascii(configs: &HashMap<String, ProcessConfig>) -> Result<String, DepsError> {
+    validate_deps(configs)?;
+    let levels = topological_levels(configs)?;
+
+    let mut out = String::new();
+    for (idx, level) in levels.iter().enumerate() {
+        out.push_str(&format!("level {idx}\n"));
+        for name in level {
+            let config = &configs[name];
+            let optional_deps = config.optional_deps.as_deref().unwrap_or_default();
+            let mut deps: Vec<String> = config
+                .depends_on
+                .iter()
+                .flatten()
+                .map(|dep| {
+                    if optional_deps.contains(dep) {
+                        format!("{dep} (optional)")
+                    } else {
+                        dep.clone()
+                    }
+                })
+                .collect();
+            deps.sort();
+            if deps.is_empty() {
+                out.push_str(&format!("  {name}\n"));
+            } else {
+                out.push_str(&format!("  {name} -> {}\n", deps.join(", ")));
+            }
+        }
+    }
+    Ok(out)
+}
+
 /// Flat reverse of topological levels: dependents come before their dependencies.
 pub fn reverse_stop_order(
     configs: &HashMap<String, ProcessConfig>,
@@ -159,6 +242,25 @@ pub fn reverse_stop_order(
     Ok(order)
 }
 
+/// Orders `names` for stopping without pulling in anything else: dependents
+/// come before their dependencies, considering only edges among `names`.
+pub fn stop_order_within(
+    names: &[String],
+    configs: &HashMap<String, ProcessConfig>,
+) -> Result<Vec<String>, DepsError> {
+    let subset: HashMap<String, ProcessConfig> = names
+        .iter()
+        .filter_map(|name| {
+            let mut config = configs.get(name)?.clone();
+            if let Some(deps) = &mut config.depends_on {
+                deps.retain(|dep| names.contains(dep));
+            }
+            Some((name.clone(), config))
+        })
+        .collect();
+    reverse_stop_order(&subset)
+}
+
 /// Given a set of requested names, expand to include all transitive dependencies.
 /// Returns names in topological order (dependencies first).
 pub fn expand_deps(
@@ -267,26 +369,73 @@ mod tests {
             cwd: None,
             env: None,
             env_file: None,
+            env_cmd: None,
             readiness_check: None,
             readiness_timeout: None,
             health_check: None,
+            on_unhealthy: None,
+            unhealthy_threshold: None,
             kill_timeout: None,
             kill_signal: None,
             max_restarts: None,
             max_memory: None,
+            memory_check_interval: None,
+            memory_action: None,
+            memory_leak_window: None,
+            memory_leak_action: None,
             min_uptime: None,
             stop_exit_codes: None,
             watch: None,
             watch_ignore: None,
+            watch_extensions: None,
+            watch_use_gitignore: None,
+            watch_debounce: None,
+            watch_delay: None,
+            watch_poll: None,
+            watch_env_files: None,
+            watch_command: None,
+            watch_restart: None,
             depends_on: deps.map(|v| v.into_iter().map(|s| s.to_string()).collect()),
             restart: None,
             group: None,
             pre_start: None,
             post_stop: None,
             cron_restart: None,
+            cron_start: None,
+            cron_stop: None,
+            cron_timezone: None,
+            tags: None,
+            ready_signal: None,
+            ready_delay: None,
+            drain_signal: None,
+            drain_timeout: None,
+            port: None,
+            increment_port: None,
+            instance_env: None,
+            optional_deps: None,
+            stop_dependents: None,
+            restart_stagger: None,
             log_date_format: None,
+            log_combine: None,
+            merge_logs: None,
+            stdout_log: None,
+            stderr_log: None,
+            log_max_line_len: None,
+            log_rate_limit: None,
             instances: None,
+            flap_threshold: None,
+            flap_window: None,
+            flap_cooldown: None,
+            conflict_check: None,
+            restart_delay: None,
+            restart_backoff: None,
+            max_restart_delay: None,
+            max_restarts_window: None,
+            kind: None,
+            metrics_endpoint: None,
+            metrics: None,
             environments: HashMap::new(),
+            environment_overrides: HashMap::new(),
         }
     }
 
@@ -409,6 +558,68 @@ mod tests {
         assert!(cache_idx < web_idx);
     }
 
+    #[test]
+    fn test_render_ascii_groups_by_level() {
+        let mut configs = HashMap::new();
+        configs.insert("db".to_string(), cfg(None));
+        configs.insert("cache".to_string(), cfg(None));
+        let mut web = cfg(Some(vec!["db", "cache"]));
+        web.optional_deps = Some(vec!["cache".to_string()]);
+        configs.insert("web".to_string(), web);
+
+        let out = render_ascii(&configs).unwrap();
+        assert_eq!(
+            out,
+            "level 0\n  cache\n  db\nlevel 1\n  web -> cache (optional), db\n"
+        );
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let mut configs = HashMap::new();
+        configs.insert("db".to_string(), cfg(None));
+        configs.insert("cache".to_string(), cfg(None));
+        let mut web = cfg(Some(vec!["db", "cache"]));
+        web.optional_deps = Some(vec!["cache".to_string()]);
+        configs.insert("web".to_string(), web);
+
+        let out = render_dot(&configs).unwrap();
+        assert!(out.starts_with("digraph pm3 {\n"));
+        assert!(out.contains("    \"web\" -> \"db\";\n"));
+        assert!(out.contains("    \"web\" -> \"cache\" [style=dashed];\n"));
+        assert!(out.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_render_rejects_missing_dep() {
+        let mut configs = HashMap::new();
+        configs.insert("web".to_string(), cfg(Some(vec!["db"])));
+        assert!(matches!(
+            render_ascii(&configs),
+            Err(DepsError::Missing { .. })
+        ));
+        assert!(matches!(
+            render_dot(&configs),
+            Err(DepsError::Missing { .. })
+        ));
+    }
+
+    #[test]
+    fn test_optional_only() {
+        let mut configs = HashMap::new();
+        configs.insert("db".to_string(), cfg(None));
+        configs.insert("cache".to_string(), cfg(None));
+        configs.insert("search".to_string(), cfg(None));
+        let mut web = cfg(Some(vec!["db", "cache", "search"]));
+        web.optional_deps = Some(vec!["cache".to_string(), "search".to_string()]);
+        configs.insert("web".to_string(), web);
+        // worker needs search, so it is no longer optional-only
+        configs.insert("worker".to_string(), cfg(Some(vec!["search"])));
+
+        let optional = optional_only(&configs);
+        assert_eq!(optional, HashSet::from(["cache".to_string()]));
+    }
+
     #[test]
     fn test_expand_dependents() {
         let mut configs = HashMap::new();
@@ -424,4 +635,19 @@ mod tests {
         assert!(web_idx < db_idx);
         assert!(worker_idx < db_idx);
     }
+
+    #[test]
+    fn test_stop_order_within_ignores_outside_deps() {
+        let mut configs = HashMap::new();
+        configs.insert("db".to_string(), cfg(None));
+        configs.insert("cache".to_string(), cfg(None));
+        configs.insert("web".to_string(), cfg(Some(vec!["db", "cache"])));
+        configs.insert("worker".to_string(), cfg(Some(vec!["db"])));
+
+        let order = stop_order_within(&["db".to_string(), "web".to_string()], &configs).unwrap();
+        assert_eq!(order, vec!["web", "db"]);
+
+        let order = stop_order_within(&["db".to_string()], &configs).unwrap();
+        assert_eq!(order, vec!["db"]);
+    }
 }
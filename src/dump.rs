@@ -0,0 +1,251 @@
+//! The dump file `pm3 save` writes and `pm3 resurrect` reads, also used for
+//! snapshots. It carries a format version so a newer pm3 can upgrade dumps
+//! written by an older one, and an older pm3 can refuse newer dumps clearly
+//! instead of failing to parse them.
+
+use crate::config::ProcessConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+
+/// Version of the dump format written by this pm3. Bump it, and add a step
+/// to `migrate`, whenever an older pm3 would misread dumps in the new format.
+/// Version 0 is the bare array of entries written before versioning.
+pub const DUMP_VERSION: u32 = 1;
+
+/// One saved process.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DumpEntry {
+    pub name: String,
+    pub config: ProcessConfig,
+    #[serde(default)]
+    pub pid: Option<u32>,
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+/// Writes a dump or snapshot file that only the daemon's user can read, since
+/// it holds the processes' env values. The file is created with that mode, so
+/// it is never readable by others, even briefly; a leftover file at `path` is
+/// removed first rather than reused with its old permissions.
+pub async fn write_private(path: &Path, json: &str) -> io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    file.write_all(json.as_bytes()).await?;
+    file.flush().await
+}
+
+/// Written in place of a value hidden by `env_mask`, unless `mask_dump` is off.
+pub const MASKED: &str = "[masked]";
+
+/// Hides the values of the env vars `masks` picks, in every env table of
+/// `config`.
+pub fn mask_env(config: &mut ProcessConfig, masks: impl Fn(&str) -> bool) {
+    for env in config.env_tables_mut() {
+        for (key, value) in env.iter_mut() {
+            if masks(key) {
+                *value = MASKED.to_string();
+            }
+        }
+    }
+}
+
+/// Drops the env vars `mask_env` hid, so a restored process doesn't run
+/// with the placeholder. Returns their names, sorted.
+pub fn drop_masked_env(config: &mut ProcessConfig) -> Vec<String> {
+    let mut dropped = Vec::new();
+    for env in config.env_tables_mut() {
+        env.retain(|key, value| {
+            if value == MASKED {
+                dropped.push(key.clone());
+            }
+            value != MASKED
+        });
+    }
+    dropped.sort();
+    dropped.dedup();
+    dropped
+}
+
+#[derive(Serialize)]
+struct DumpFile<'a> {
+    version: u32,
+    processes: &'a [DumpEntry],
+}
+
+/// Serializes `entries` in the current format.
+pub fn to_json(entries: &[DumpEntry]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&DumpFile {
+        version: DUMP_VERSION,
+        processes: entries,
+    })
+}
+
+/// Parses a dump written in any format up to `DUMP_VERSION`, returning its
+/// entries and the version it was written in. Fields a dump lacks take their
+/// defaults and fields this pm3 doesn't know are ignored.
+pub fn parse(data: &str) -> Result<(Vec<DumpEntry>, u32), String> {
+    let value: Value =
+        serde_json::from_str(data).map_err(|e| format!("failed to parse dump file: {e}"))?;
+    let (version, mut processes) = match value {
+        Value::Array(_) => (0, value),
+        Value::Object(mut file) => {
+            let version = file
+                .get("version")
+                .and_then(Value::as_u64)
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or("failed to parse dump file: missing format version")?;
+            let processes = file.remove("processes").unwrap_or(Value::Null);
+            (version, processes)
+        }
+        _ => return Err("failed to parse dump file: expected an object".to_string()),
+    };
+    if version > DUMP_VERSION {
+        return Err(format!(
+            "dump file is format version {version}, but this pm3 reads up to version \
+             {DUMP_VERSION}; upgrade pm3 to restore it"
+        ));
+    }
+    for from in version..DUMP_VERSION {
+        migrate(from, &mut processes);
+    }
+
+    let Value::Array(processes) = processes else {
+        return Err("failed to parse dump file: expected a list of processes".to_string());
+    };
+    let entries = processes
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let name = entry["name"].as_str().map(str::to_string);
+            serde_json::from_value(entry).map_err(|e| match name {
+                Some(name) => format!("failed to parse dump entry '{name}': {e}"),
+                None => format!("failed to parse dump entry {index}: {e}"),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((entries, version))
+}
+
+/// Upgrades the process entries of a version `from` dump to version
+/// `from + 1`.
+fn migrate(from: u32, _processes: &mut Value) {
+    match from {
+        // Version 1 only wrapped the entries with the format version
+        0 => {}
+        _ => unreachable!("no migration from dump version {from}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> DumpEntry {
+        let configs =
+            crate::config::parse_config(&format!("[{name}]\ncommand = \"sleep 1\"\n")).unwrap();
+        DumpEntry {
+            name: name.to_string(),
+            config: configs[name].clone(),
+            pid: Some(42),
+            restarts: 3,
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let entries = vec![entry("web"), entry("db")];
+        let json = to_json(&entries).unwrap();
+        assert_eq!(parse(&json).unwrap(), (entries, DUMP_VERSION));
+    }
+
+    #[test]
+    fn test_mask_env_and_drop_masked() {
+        let mut config = crate::config::parse_config(
+            r#"
+[web]
+command = "sleep 1"
+env = { PORT = "3000", API_KEY = "abc" }
+
+[web.env_production]
+API_KEY = "prod"
+"#,
+        )
+        .unwrap()
+        .remove("web")
+        .unwrap();
+        mask_env(&mut config, |key| key.ends_with("_KEY"));
+        assert_eq!(config.env.as_ref().unwrap()["API_KEY"], MASKED);
+        assert_eq!(config.env.as_ref().unwrap()["PORT"], "3000");
+        assert_eq!(config.environments["production"]["API_KEY"], MASKED);
+
+        assert_eq!(drop_masked_env(&mut config), vec!["API_KEY"]);
+        let env = config.env.as_ref().unwrap();
+        assert_eq!(env.len(), 1);
+        assert_eq!(env["PORT"], "3000");
+        assert!(config.environments["production"].is_empty());
+    }
+
+    #[test]
+    fn test_parse_unversioned_dump() {
+        let json =
+            r#"[{"name":"web","config":{"command":"sleep 1","cwd":null},"pid":7,"restarts":1}]"#;
+        let (entries, version) = parse(json).unwrap();
+        assert_eq!(version, 0);
+        assert_eq!(entries[0].name, "web");
+        assert_eq!(entries[0].pid, Some(7));
+    }
+
+    #[test]
+    fn test_parse_tolerates_missing_and_unknown_fields() {
+        let json = r#"{
+            "version": 1,
+            "processes": [
+                {"name": "web", "config": {"command": "sleep 1", "added_later": true}, "note": "x"}
+            ]
+        }"#;
+        let (entries, _) = parse(json).unwrap();
+        assert_eq!(entries[0].config.command, "sleep 1");
+        assert_eq!(entries[0].pid, None);
+        assert_eq!(entries[0].restarts, 0);
+        assert!(entries[0].config.environments.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_newer_dumps() {
+        let json = format!(r#"{{"version":{},"processes":[]}}"#, DUMP_VERSION + 1);
+        let err = parse(&json).unwrap_err();
+        assert!(err.contains("upgrade pm3"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_names_bad_entries() {
+        let json = r#"{"version":1,"processes":[{"name":"web","config":{}}]}"#;
+        let err = parse(json).unwrap_err();
+        assert!(err.starts_with("failed to parse dump entry 'web'"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_private_replaces_readable_file() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dump.json.tmp");
+        std::fs::write(&path, "stale").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&path, "{}").await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::log;
+use crate::process::{self, ProcessTable};
+use crate::protocol::ProcessStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, broadcast, watch};
+
+/// How often the process table is compared against the last snapshot.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How many events a slow subscriber may fall behind before missing some.
+pub const EVENT_BUFFER: usize = 256;
+
+/// Something that happened to a process, as streamed by `pm3 events`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessEvent {
+    pub name: String,
+    pub kind: EventKind,
+    /// The process status after the event.
+    pub status: ProcessStatus,
+    /// RFC 3339 timestamp in UTC.
+    pub timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// The process was started for the first time or re-added.
+    Added,
+    /// The process was deleted from the daemon.
+    Removed,
+    /// The status changed, e.g. online to errored.
+    StatusChanged,
+    /// The process came back with a new pid, automatically or on request.
+    Restarted,
+    /// The process crashed with its `max_restarts` used up and was left down.
+    RestartLimit,
+    /// The process went over `max_memory`, and its `memory_action` was taken.
+    MemoryLimit,
+    /// Lines of output were dropped under `log_rate_limit` or the disk quota.
+    LogsSuppressed,
+    /// Memory is growing toward `max_memory`, found by `memory_leak_window`.
+    MemoryLeak,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            EventKind::Added => "added",
+            EventKind::Removed => "removed",
+            EventKind::StatusChanged => "status_changed",
+            EventKind::Restarted => "restarted",
+            EventKind::RestartLimit => "restart_limit",
+            EventKind::MemoryLimit => "memory_limit",
+            EventKind::LogsSuppressed => "logs_suppressed",
+            EventKind::MemoryLeak => "memory_leak",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// The parts of a process whose changes are reported as events.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snapshot {
+    pub status: ProcessStatus,
+    pub pid: Option<u32>,
+    pub restarts: u32,
+    pub exit_code: Option<i32>,
+    pub memory_restarts: u32,
+    pub memory_breaches: u32,
+    pub memory_leak: Option<crate::memory::LeakTrend>,
+    pub crashes: u32,
+    /// Errored with no restarts left.
+    pub gave_up: bool,
+    /// Lines of output dropped since the process was spawned.
+    pub logs_suppressed: u64,
+}
+
+pub type Snapshots = BTreeMap<String, Snapshot>;
+
+pub fn snapshot(table: &ProcessTable) -> Snapshots {
+    table
+        .iter()
+        .map(|(name, managed)| {
+            let max_restarts = managed
+                .config
+                .max_restarts
+                .unwrap_or(process::DEFAULT_MAX_RESTARTS);
+            (
+                name.clone(),
+                Snapshot {
+                    status: managed.status,
+                    pid: managed.pid,
+                    restarts: managed.restarts,
+                    exit_code: managed.exit_code,
+                    memory_restarts: managed.memory_restarts,
+                    memory_breaches: managed.memory_breaches,
+                    memory_leak: managed.memory_leak,
+                    crashes: managed.crashes,
+                    gave_up: managed.status == ProcessStatus::Errored
+                        && managed.restarts >= max_restarts,
+                    logs_suppressed: managed.log_control.suppressed(),
+                },
+            )
+        })
+        .collect()
+}
+
+/// The events that turn `before` into `after`, in name order.
+pub fn diff(before: &Snapshots, after: &Snapshots, timestamp: &str) -> Vec<ProcessEvent> {
+    let event = |name: &str, kind, status, detail| ProcessEvent {
+        name: name.to_string(),
+        kind,
+        status,
+        timestamp: timestamp.to_string(),
+        detail,
+    };
+    let mut events = Vec::new();
+    for (name, old) in before {
+        if !after.contains_key(name) {
+            events.push(event(name, EventKind::Removed, old.status, None));
+        }
+    }
+    for (name, new) in after {
+        let Some(old) = before.get(name) else {
+            events.push(event(name, EventKind::Added, new.status, None));
+            continue;
+        };
+        if new.memory_breaches > old.memory_breaches {
+            let detail = if new.memory_restarts > old.memory_restarts {
+                format!("memory restart #{}", new.memory_restarts)
+            } else {
+                format!("over max_memory #{}", new.memory_breaches)
+            };
+            events.push(event(
+                name,
+                EventKind::MemoryLimit,
+                new.status,
+                Some(detail),
+            ));
+        }
+        if let (None, Some(trend)) = (old.memory_leak, new.memory_leak) {
+            events.push(event(
+                name,
+                EventKind::MemoryLeak,
+                new.status,
+                Some(trend.to_string()),
+            ));
+        }
+        let respawned = old.pid.is_some() && new.pid.is_some() && old.pid != new.pid;
+        if new.restarts > old.restarts || respawned {
+            let detail = (new.restarts > 0).then(|| format!("restart #{}", new.restarts));
+            events.push(event(name, EventKind::Restarted, new.status, detail));
+        }
+        if new.status != old.status {
+            let mut detail = format!("{} -> {}", old.status, new.status);
+            if new.pid.is_none()
+                && let Some(code) = new.exit_code
+            {
+                detail.push_str(&format!(" (exit code {code})"));
+            }
+            events.push(event(
+                name,
+                EventKind::StatusChanged,
+                new.status,
+                Some(detail),
+            ));
+        }
+        if new.gave_up && !old.gave_up {
+            let detail = format!("gave up after {} restarts", new.restarts);
+            events.push(event(
+                name,
+                EventKind::RestartLimit,
+                new.status,
+                Some(detail),
+            ));
+        }
+        // The count starts over when the process is spawned again
+        let suppressed = match new.logs_suppressed.checked_sub(old.logs_suppressed) {
+            Some(more) => more,
+            None => new.logs_suppressed,
+        };
+        if suppressed > 0 {
+            let detail = format!("{suppressed} log lines suppressed");
+            events.push(event(
+                name,
+                EventKind::LogsSuppressed,
+                new.status,
+                Some(detail),
+            ));
+        }
+    }
+    events
+}
+
+/// Watches the process table and broadcasts an event for every change, until
+/// the daemon shuts down.
+pub fn spawn_event_watcher(
+    processes: Arc<RwLock<ProcessTable>>,
+    events: broadcast::Sender<ProcessEvent>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let mut last = snapshot(&*processes.read().await);
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(EVENT_POLL_INTERVAL) => {}
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                }
+            }
+
+            let current = snapshot(&*processes.read().await);
+            if current != last {
+                let timestamp = log::format_timestamp(chrono::Utc::now());
+                for event in diff(&last, &current, &timestamp) {
+                    // No subscribers is fine; the event is simply dropped
+                    let _ = events.send(event);
+                }
+                last = current;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snap(status: ProcessStatus, pid: Option<u32>, restarts: u32) -> Snapshot {
+        Snapshot {
+            status,
+            pid,
+            restarts,
+            exit_code: None,
+            memory_restarts: 0,
+            memory_breaches: 0,
+            memory_leak: None,
+            crashes: 0,
+            gave_up: false,
+            logs_suppressed: 0,
+        }
+    }
+
+    fn kinds(events: &[ProcessEvent]) -> Vec<(&str, EventKind)> {
+        events.iter().map(|e| (e.name.as_str(), e.kind)).collect()
+    }
+
+    #[test]
+    fn test_diff_added_and_removed() {
+        let before = Snapshots::from([("old".into(), snap(ProcessStatus::Online, Some(1), 0))]);
+        let after = Snapshots::from([("new".into(), snap(ProcessStatus::Starting, Some(2), 0))]);
+        let events = diff(&before, &after, "t");
+        assert_eq!(
+            kinds(&events),
+            vec![("old", EventKind::Removed), ("new", EventKind::Added)]
+        );
+        assert_eq!(events[1].status, ProcessStatus::Starting);
+        assert_eq!(events[1].timestamp, "t");
+    }
+
+    #[test]
+    fn test_diff_status_change_with_exit_code() {
+        let before = Snapshots::from([("web".into(), snap(ProcessStatus::Online, Some(1), 0))]);
+        let mut errored = snap(ProcessStatus::Errored, None, 0);
+        errored.exit_code = Some(3);
+        let after = Snapshots::from([("web".into(), errored)]);
+        let events = diff(&before, &after, "t");
+        assert_eq!(kinds(&events), vec![("web", EventKind::StatusChanged)]);
+        assert_eq!(
+            events[0].detail.as_deref(),
+            Some("online -> errored (exit code 3)")
+        );
+    }
+
+    #[test]
+    fn test_diff_restart() {
+        let before = Snapshots::from([("web".into(), snap(ProcessStatus::Online, Some(1), 0))]);
+        let after = Snapshots::from([("web".into(), snap(ProcessStatus::Online, Some(2), 1))]);
+        let events = diff(&before, &after, "t");
+        assert_eq!(kinds(&events), vec![("web", EventKind::Restarted)]);
+        assert_eq!(events[0].detail.as_deref(), Some("restart #1"));
+
+        assert!(diff(&after, &after, "t").is_empty());
+    }
+
+    #[test]
+    fn test_diff_limits() {
+        let before = Snapshots::from([("web".into(), snap(ProcessStatus::Online, Some(1), 2))]);
+        let mut killed = snap(ProcessStatus::Stopped, None, 2);
+        killed.memory_restarts = 1;
+        killed.memory_breaches = 1;
+        let events = diff(&before, &Snapshots::from([("web".into(), killed)]), "t");
+        assert_eq!(
+            kinds(&events),
+            vec![
+                ("web", EventKind::MemoryLimit),
+                ("web", EventKind::StatusChanged)
+            ]
+        );
+        assert_eq!(events[0].detail.as_deref(), Some("memory restart #1"));
+
+        let mut signalled = snap(ProcessStatus::Online, Some(1), 2);
+        signalled.memory_breaches = 3;
+        let events = diff(&before, &Snapshots::from([("web".into(), signalled)]), "t");
+        assert_eq!(kinds(&events), vec![("web", EventKind::MemoryLimit)]);
+        assert_eq!(events[0].detail.as_deref(), Some("over max_memory #3"));
+
+        let mut leaking = snap(ProcessStatus::Online, Some(1), 2);
+        leaking.memory_leak = Some(crate::memory::LeakTrend {
+            bytes_per_sec: 1024.0 * 1024.0 / 60.0,
+            secs_to_limit: 1800,
+        });
+        let after = Snapshots::from([("web".into(), leaking)]);
+        let events = diff(&before, &after, "t");
+        assert_eq!(kinds(&events), vec![("web", EventKind::MemoryLeak)]);
+        assert_eq!(
+            events[0].detail.as_deref(),
+            Some("memory growing 1.0M/min, reaching max_memory in about 30m")
+        );
+        assert!(diff(&after, &after, "t").is_empty());
+
+        let mut errored = snap(ProcessStatus::Errored, None, 2);
+        errored.gave_up = true;
+        let events = diff(&before, &Snapshots::from([("web".into(), errored)]), "t");
+        assert_eq!(
+            kinds(&events),
+            vec![
+                ("web", EventKind::StatusChanged),
+                ("web", EventKind::RestartLimit)
+            ]
+        );
+        assert_eq!(
+            events[1].detail.as_deref(),
+            Some("gave up after 2 restarts")
+        );
+    }
+
+    #[test]
+    fn test_diff_logs_suppressed() {
+        let before = Snapshots::from([("web".into(), snap(ProcessStatus::Online, Some(1), 0))]);
+        let mut dropping = snap(ProcessStatus::Online, Some(1), 0);
+        dropping.logs_suppressed = 120;
+        let after = Snapshots::from([("web".into(), dropping)]);
+        let events = diff(&before, &after, "t");
+        assert_eq!(kinds(&events), vec![("web", EventKind::LogsSuppressed)]);
+        assert_eq!(
+            events[0].detail.as_deref(),
+            Some("120 log lines suppressed")
+        );
+        assert!(diff(&after, &after, "t").is_empty());
+
+        // A respawned process counts from zero again
+        let mut respawned = snap(ProcessStatus::Online, Some(1), 0);
+        respawned.logs_suppressed = 5;
+        let events = diff(&after, &Snapshots::from([("web".into(), respawned)]), "t");
+        assert_eq!(events[0].detail.as_deref(), Some("5 log lines suppressed"));
+    }
+}
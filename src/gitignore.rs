@@ -0,0 +1,234 @@
+//! `watch_use_gitignore`: the file watcher skips paths the project's
+//! `.gitignore` and `.ignore` files exclude, read from the repository root
+//! down to each changed path like git does.
+
+use crate::select::glob_match;
+use crate::watch::path_glob_match;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// The ignore files read in each directory; later ones take precedence.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// One line of an ignore file.
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    pattern: String,
+    /// `!pattern`: re-includes what an earlier rule excluded.
+    negated: bool,
+    /// `pattern/`: only matches directories.
+    dir_only: bool,
+    /// A pattern with a `/` other than a trailing one is relative to the
+    /// ignore file's directory; others match a name at any depth.
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        // `\#` and `\!` start patterns with a literal `#` or `!`
+        let line = line.strip_prefix('\\').unwrap_or(line);
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self {
+            pattern: line.trim_start_matches('/').to_string(),
+            negated,
+            dir_only,
+            anchored: line.contains('/'),
+        })
+    }
+
+    /// Whether the rule matches `names`, a path relative to the ignore file's
+    /// directory.
+    fn matches(&self, names: &[&str], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            path_glob_match(&self.pattern, &names.iter().collect::<PathBuf>())
+        } else {
+            names
+                .last()
+                .is_some_and(|name| glob_match(&self.pattern, name))
+        }
+    }
+}
+
+/// The ignore rules of a project, read from each directory as paths under it
+/// are checked.
+#[derive(Debug)]
+pub struct GitIgnore {
+    /// The repository root, the nearest directory up from the watched one
+    /// with a `.git`, or the watched directory outside a repository.
+    root: PathBuf,
+    rules: HashMap<PathBuf, Vec<Rule>>,
+}
+
+impl GitIgnore {
+    pub fn new(dir: &Path) -> Self {
+        let root = dir
+            .ancestors()
+            .find(|d| d.join(".git").exists())
+            .unwrap_or(dir)
+            .to_path_buf();
+        Self {
+            root,
+            rules: HashMap::new(),
+        }
+    }
+
+    /// Whether `path` is ignored, itself or through a directory it is in,
+    /// since git doesn't look inside ignored directories.
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let names: Vec<&str> = relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        if names.first() == Some(&".git") {
+            return true;
+        }
+
+        for end in 1..=names.len() {
+            let candidate_is_dir = end < names.len() || is_dir;
+            let mut ignored = false;
+            let mut dir = self.root.clone();
+            for start in 0..end {
+                if start > 0 {
+                    dir.push(names[start - 1]);
+                }
+                for rule in self.rules_in(&dir) {
+                    if rule.matches(&names[start..end], candidate_is_dir) {
+                        ignored = !rule.negated;
+                    }
+                }
+            }
+            if ignored {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Drops what was read from `dir`, after one of its ignore files changed.
+    pub fn forget(&mut self, dir: &Path) {
+        self.rules.remove(dir);
+    }
+
+    fn rules_in(&mut self, dir: &Path) -> &[Rule] {
+        self.rules.entry(dir.to_path_buf()).or_insert_with(|| {
+            IGNORE_FILES
+                .iter()
+                .filter_map(|file| std::fs::read_to_string(dir.join(file)).ok())
+                .flat_map(|content| content.lines().filter_map(Rule::parse).collect::<Vec<_>>())
+                .collect()
+        })
+    }
+
+    /// Whether `path` is one of the files rules are read from.
+    pub fn is_ignore_file(path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| IGNORE_FILES.iter().any(|file| name == *file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!(Rule::parse("# comment"), None);
+        assert_eq!(Rule::parse("   "), None);
+        let rule = Rule::parse("!/build/").unwrap();
+        assert_eq!(rule.pattern, "build");
+        assert!(rule.negated && rule.dir_only && rule.anchored);
+        let rule = Rule::parse("\\#notes.txt").unwrap();
+        assert_eq!(rule.pattern, "#notes.txt");
+        assert!(!rule.negated && !rule.dir_only && !rule.anchored);
+    }
+
+    #[test]
+    fn test_names_match_at_any_depth() {
+        let dir = project(&[(".gitignore", "node_modules/\n*.log\n")]);
+        let mut ignore = GitIgnore::new(dir.path());
+        let root = dir.path();
+        assert!(ignore.is_ignored(&root.join("node_modules/react/index.js"), false));
+        assert!(ignore.is_ignored(&root.join("web/node_modules/x.js"), false));
+        assert!(ignore.is_ignored(&root.join("logs/app.log"), false));
+        assert!(!ignore.is_ignored(&root.join("src/app.js"), false));
+        assert!(ignore.is_ignored(&root.join(".git/HEAD"), false));
+    }
+
+    #[test]
+    fn test_anchored_and_negated_rules() {
+        let dir = project(&[(
+            ".gitignore",
+            "/dist\nconfig/*.local.toml\n*.env\n!example.env\n",
+        )]);
+        let mut ignore = GitIgnore::new(dir.path());
+        let root = dir.path();
+        assert!(ignore.is_ignored(&root.join("dist/app.js"), false));
+        assert!(!ignore.is_ignored(&root.join("web/dist/app.js"), false));
+        assert!(ignore.is_ignored(&root.join("config/dev.local.toml"), false));
+        assert!(!ignore.is_ignored(&root.join("config/app.toml"), false));
+        assert!(ignore.is_ignored(&root.join("prod.env"), false));
+        assert!(!ignore.is_ignored(&root.join("example.env"), false));
+    }
+
+    #[test]
+    fn test_nested_and_dot_ignore_files() {
+        let dir = project(&[
+            (".gitignore", "*.tmp\n"),
+            ("web/.gitignore", "!keep.tmp\n"),
+            ("web/.ignore", "fixtures/\n"),
+        ]);
+        let root = dir.path();
+        // Watching a subdirectory still reads the rules from the repository root
+        let mut ignore = GitIgnore::new(&root.join("web"));
+        assert!(ignore.is_ignored(&root.join("web/a.tmp"), false));
+        assert!(!ignore.is_ignored(&root.join("web/keep.tmp"), false));
+        assert!(ignore.is_ignored(&root.join("web/fixtures/data.json"), false));
+        assert!(!ignore.is_ignored(&root.join("web/src/main.js"), false));
+    }
+
+    #[test]
+    fn test_forget_rereads_changed_rules() {
+        let dir = project(&[(".gitignore", "*.tmp\n")]);
+        let root = dir.path();
+        let mut ignore = GitIgnore::new(root);
+        assert!(ignore.is_ignored(&root.join("a.tmp"), false));
+        std::fs::write(root.join(".gitignore"), "").unwrap();
+        assert!(ignore.is_ignored(&root.join("a.tmp"), false));
+        ignore.forget(root);
+        assert!(!ignore.is_ignored(&root.join("a.tmp"), false));
+    }
+}
@@ -0,0 +1,486 @@
+use crate::config;
+use crate::daemon::{self, Client};
+use crate::events::{EventKind, ProcessEvent};
+use crate::log::LogStream;
+use crate::manager::Manager;
+use crate::protocol::{self, ListFilter, ProcessStatus, Request, Response};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Status, metadata::MetadataMap};
+
+/// Types and client generated from `proto/pm3.proto`.
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("pm3.v1");
+}
+
+use proto::process_manager_server::{ProcessManager, ProcessManagerServer};
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// Serves gRPC on `listener` until the task is aborted. Every call must carry
+/// `authorization: Bearer <token>` metadata.
+pub fn spawn_server(
+    listener: TcpListener,
+    token: String,
+    manager: Manager,
+    shutdown_tx: watch::Sender<bool>,
+) -> tokio::task::JoinHandle<()> {
+    let fallback_addr = listener
+        .local_addr()
+        .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0)));
+    let service = GrpcService {
+        manager,
+        shutdown_tx,
+        fallback_addr,
+    };
+    let server = ProcessManagerServer::with_interceptor(service, move |request| {
+        authenticate(request, &token)
+    });
+    tokio::spawn(async move {
+        let incoming = tonic::transport::server::TcpIncoming::from(listener);
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(server)
+            .serve_with_incoming(incoming)
+            .await
+        {
+            tracing::error!("grpc server: {e}");
+        }
+    })
+}
+
+fn authenticate(request: tonic::Request<()>, token: &str) -> Result<tonic::Request<()>, Status> {
+    if bearer_token(request.metadata()).is_some_and(|given| daemon::tokens_match(given, token)) {
+        Ok(request)
+    } else {
+        tracing::warn!(
+            "rejected grpc client {}: bad or missing auth token",
+            request
+                .remote_addr()
+                .map_or_else(|| "unknown".to_string(), |addr| addr.to_string())
+        );
+        Err(Status::unauthenticated("authentication failed"))
+    }
+}
+
+fn bearer_token(metadata: &MetadataMap) -> Option<&str> {
+    metadata
+        .get("authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+struct GrpcService {
+    manager: Manager,
+    shutdown_tx: watch::Sender<bool>,
+    /// Recorded in the audit log when a call's peer address is unknown.
+    fallback_addr: SocketAddr,
+}
+
+impl GrpcService {
+    fn client<T>(&self, call: &tonic::Request<T>) -> Client {
+        Client::Remote(call.remote_addr().unwrap_or(self.fallback_addr))
+    }
+
+    /// The final response to a request that answers once. Calls are served
+    /// like a remote client's, so they are audited the same way.
+    async fn call<T>(
+        &self,
+        call: &tonic::Request<T>,
+        request: Request,
+    ) -> Result<Response, Status> {
+        let response = daemon::run_request(
+            request,
+            self.client(call),
+            self.shutdown_tx.clone(),
+            self.manager.clone(),
+        )
+        .await;
+        match response {
+            Some(Response::Error { message }) => Err(error_status(message)),
+            Some(response) => Ok(response),
+            None => Err(Status::internal("daemon sent no response")),
+        }
+    }
+
+    async fn command<T>(
+        &self,
+        call: tonic::Request<T>,
+        request: Request,
+    ) -> Result<tonic::Response<proto::CommandReply>, Status> {
+        match self.call(&call, request).await? {
+            Response::Success { message } => {
+                Ok(tonic::Response::new(proto::CommandReply { message }))
+            }
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    /// Converts each streamed response with `convert`, ending the stream on
+    /// the first error.
+    fn stream<T, U: Send + 'static>(
+        &self,
+        call: &tonic::Request<T>,
+        request: Request,
+        convert: fn(Response) -> Option<U>,
+    ) -> ResponseStream<U> {
+        let responses = ReceiverStream::new(daemon::spawn_request(
+            request,
+            self.client(call),
+            self.shutdown_tx.clone(),
+            self.manager.clone(),
+        ));
+        Box::pin(responses.filter_map(move |response| match response {
+            Response::Error { message } => Some(Err(error_status(message))),
+            response => convert(response).map(Ok),
+        }))
+    }
+}
+
+/// Errors from the daemon are mostly about the state of processes, e.g.
+/// naming one that doesn't exist.
+fn error_status(message: String) -> Status {
+    if message.contains("not found") {
+        Status::not_found(message)
+    } else {
+        Status::failed_precondition(message)
+    }
+}
+
+fn unexpected(response: &Response) -> Status {
+    Status::internal(format!("unexpected response from daemon: {response:?}"))
+}
+
+fn non_empty(values: Vec<String>) -> Option<Vec<String>> {
+    (!values.is_empty()).then_some(values)
+}
+
+#[tonic::async_trait]
+impl ProcessManager for GrpcService {
+    type TailLogsStream = ResponseStream<proto::LogLine>;
+    type EventsStream = ResponseStream<proto::Event>;
+
+    async fn list(
+        &self,
+        request: tonic::Request<proto::ListRequest>,
+    ) -> Result<tonic::Response<proto::ListReply>, Status> {
+        let filter = request.get_ref();
+        let filter = ListFilter {
+            status: filter.status().filter_map(status_from_proto).collect(),
+            group: filter.group.clone(),
+            tag: filter.tag.clone(),
+            namespace: filter.namespace.clone(),
+        };
+        let filter = (!filter.is_empty()).then_some(filter);
+        match self.call(&request, Request::List { filter }).await? {
+            Response::ProcessList { processes, .. } => Ok(tonic::Response::new(proto::ListReply {
+                processes: processes.into_iter().map(process_info_to_proto).collect(),
+            })),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    async fn info(
+        &self,
+        request: tonic::Request<proto::InfoRequest>,
+    ) -> Result<tonic::Response<proto::ProcessDetail>, Status> {
+        let name = request.get_ref().name.clone();
+        match self.call(&request, Request::Info { name }).await? {
+            Response::ProcessDetail { info } => {
+                Ok(tonic::Response::new(process_detail_to_proto(*info)))
+            }
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    async fn start(
+        &self,
+        request: tonic::Request<proto::StartRequest>,
+    ) -> Result<tonic::Response<proto::CommandReply>, Status> {
+        let start = request.get_ref().clone();
+        let configs = config::parse_config(&start.config_toml)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let start = Request::Start {
+            configs,
+            names: non_empty(start.names),
+            env: start.env,
+            wait: start.wait,
+            path: None,
+            force: start.force,
+            namespace: start.namespace,
+            progress: false,
+        };
+        self.command(request, start).await
+    }
+
+    async fn stop(
+        &self,
+        request: tonic::Request<proto::StopRequest>,
+    ) -> Result<tonic::Response<proto::CommandReply>, Status> {
+        let stop = request.get_ref().clone();
+        let stop = Request::Stop {
+            names: non_empty(stop.names),
+            except: non_empty(stop.except),
+            namespace: stop.namespace,
+            no_dependents: stop.no_dependents,
+        };
+        self.command(request, stop).await
+    }
+
+    async fn restart(
+        &self,
+        request: tonic::Request<proto::RestartRequest>,
+    ) -> Result<tonic::Response<proto::CommandReply>, Status> {
+        let restart = request.get_ref().clone();
+        let restart = Request::Restart {
+            names: non_empty(restart.names),
+            except: non_empty(restart.except),
+            namespace: restart.namespace,
+            stagger_ms: restart.stagger_ms,
+        };
+        self.command(request, restart).await
+    }
+
+    async fn reload(
+        &self,
+        request: tonic::Request<proto::ReloadRequest>,
+    ) -> Result<tonic::Response<proto::CommandReply>, Status> {
+        let reload = request.get_ref().clone();
+        let reload = Request::Reload {
+            names: non_empty(reload.names),
+            except: non_empty(reload.except),
+            namespace: reload.namespace,
+            path: None,
+        };
+        self.command(request, reload).await
+    }
+
+    async fn signal(
+        &self,
+        request: tonic::Request<proto::SignalRequest>,
+    ) -> Result<tonic::Response<proto::CommandReply>, Status> {
+        let proto::SignalRequest { name, signal } = request.get_ref().clone();
+        self.command(request, Request::Signal { name, signal })
+            .await
+    }
+
+    async fn save(
+        &self,
+        request: tonic::Request<proto::SaveRequest>,
+    ) -> Result<tonic::Response<proto::CommandReply>, Status> {
+        self.command(request, Request::Save { tag: None }).await
+    }
+
+    async fn daemon_info(
+        &self,
+        request: tonic::Request<proto::DaemonInfoRequest>,
+    ) -> Result<tonic::Response<proto::DaemonInfoReply>, Status> {
+        match self.call(&request, Request::DaemonInfo).await? {
+            Response::DaemonInfo { info } => Ok(tonic::Response::new(proto::DaemonInfoReply {
+                pid: info.pid,
+                version: info.version,
+                uptime: info.uptime,
+                processes: info.processes as u64,
+                data_dir: info.data_dir,
+            })),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    async fn tail_logs(
+        &self,
+        request: tonic::Request<proto::TailLogsRequest>,
+    ) -> Result<tonic::Response<Self::TailLogsStream>, Status> {
+        let tail = request.get_ref();
+        let log = Request::Log {
+            name: tail.name.clone(),
+            lines: tail.lines as usize,
+            follow: tail.follow,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        };
+        Ok(tonic::Response::new(self.stream(
+            &request,
+            log,
+            |response| match response {
+                Response::LogLine {
+                    name,
+                    stream,
+                    timestamp,
+                    line,
+                } => Some(proto::LogLine {
+                    name,
+                    stream: log_stream_to_proto(stream) as i32,
+                    timestamp,
+                    line,
+                }),
+                _ => None,
+            },
+        )))
+    }
+
+    async fn events(
+        &self,
+        request: tonic::Request<proto::EventsRequest>,
+    ) -> Result<tonic::Response<Self::EventsStream>, Status> {
+        let names = non_empty(request.get_ref().names.clone());
+        Ok(tonic::Response::new(self.stream(
+            &request,
+            Request::Events { names },
+            |response| match response {
+                Response::Event(event) => Some(event_to_proto(event)),
+                _ => None,
+            },
+        )))
+    }
+}
+
+fn status_to_proto(status: ProcessStatus) -> proto::ProcessStatus {
+    match status {
+        ProcessStatus::Starting => proto::ProcessStatus::Starting,
+        ProcessStatus::Online => proto::ProcessStatus::Online,
+        ProcessStatus::Unhealthy => proto::ProcessStatus::Unhealthy,
+        ProcessStatus::Stopped => proto::ProcessStatus::Stopped,
+        ProcessStatus::Errored => proto::ProcessStatus::Errored,
+        ProcessStatus::Flapping => proto::ProcessStatus::Flapping,
+        ProcessStatus::Succeeded => proto::ProcessStatus::Succeeded,
+        ProcessStatus::Failed => proto::ProcessStatus::Failed,
+        ProcessStatus::Suspended => proto::ProcessStatus::Suspended,
+    }
+}
+
+fn status_from_proto(status: proto::ProcessStatus) -> Option<ProcessStatus> {
+    Some(match status {
+        proto::ProcessStatus::Unspecified => return None,
+        proto::ProcessStatus::Starting => ProcessStatus::Starting,
+        proto::ProcessStatus::Online => ProcessStatus::Online,
+        proto::ProcessStatus::Unhealthy => ProcessStatus::Unhealthy,
+        proto::ProcessStatus::Stopped => ProcessStatus::Stopped,
+        proto::ProcessStatus::Errored => ProcessStatus::Errored,
+        proto::ProcessStatus::Flapping => ProcessStatus::Flapping,
+        proto::ProcessStatus::Succeeded => ProcessStatus::Succeeded,
+        proto::ProcessStatus::Failed => ProcessStatus::Failed,
+        proto::ProcessStatus::Suspended => ProcessStatus::Suspended,
+    })
+}
+
+fn log_stream_to_proto(stream: Option<LogStream>) -> proto::LogStream {
+    match stream {
+        Some(LogStream::Stdout) => proto::LogStream::Stdout,
+        Some(LogStream::Stderr) => proto::LogStream::Stderr,
+        None => proto::LogStream::Unspecified,
+    }
+}
+
+fn process_info_to_proto(info: protocol::ProcessInfo) -> proto::ProcessInfo {
+    proto::ProcessInfo {
+        name: info.name,
+        pid: info.pid,
+        status: status_to_proto(info.status) as i32,
+        uptime: info.uptime,
+        restarts: info.restarts,
+        cpu_percent: info.cpu_percent,
+        memory_bytes: info.memory_bytes,
+        group: info.group,
+        tags: info.tags,
+        metrics: info.metrics.into_iter().collect(),
+        paused: info.paused,
+    }
+}
+
+fn process_detail_to_proto(detail: protocol::ProcessDetail) -> proto::ProcessDetail {
+    proto::ProcessDetail {
+        process: Some(proto::ProcessInfo {
+            name: detail.name,
+            pid: detail.pid,
+            status: status_to_proto(detail.status) as i32,
+            uptime: detail.uptime,
+            restarts: detail.restarts,
+            cpu_percent: detail.cpu_percent,
+            memory_bytes: detail.memory_bytes,
+            group: detail.group,
+            tags: detail.tags,
+            metrics: detail.metrics.into_iter().collect(),
+            paused: detail.paused,
+        }),
+        command: detail.command,
+        cwd: detail.cwd,
+        env: detail.env.unwrap_or_default(),
+        exit_code: detail.exit_code,
+        stdout_log: detail.stdout_log,
+        stderr_log: detail.stderr_log,
+        depends_on: detail.depends_on.unwrap_or_default(),
+    }
+}
+
+fn event_to_proto(event: ProcessEvent) -> proto::Event {
+    let kind = match event.kind {
+        EventKind::Added => proto::EventKind::Added,
+        EventKind::Removed => proto::EventKind::Removed,
+        EventKind::StatusChanged => proto::EventKind::StatusChanged,
+        EventKind::Restarted => proto::EventKind::Restarted,
+        EventKind::RestartLimit => proto::EventKind::RestartLimit,
+        EventKind::MemoryLimit => proto::EventKind::MemoryLimit,
+        EventKind::LogsSuppressed => proto::EventKind::LogsSuppressed,
+        EventKind::MemoryLeak => proto::EventKind::MemoryLeak,
+    };
+    proto::Event {
+        name: event.name,
+        kind: kind as i32,
+        status: status_to_proto(event.status) as i32,
+        timestamp: event.timestamp,
+        detail: event.detail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_roundtrips_through_proto() {
+        for status in [
+            ProcessStatus::Starting,
+            ProcessStatus::Online,
+            ProcessStatus::Unhealthy,
+            ProcessStatus::Stopped,
+            ProcessStatus::Errored,
+            ProcessStatus::Flapping,
+            ProcessStatus::Succeeded,
+            ProcessStatus::Failed,
+            ProcessStatus::Suspended,
+        ] {
+            assert_eq!(status_from_proto(status_to_proto(status)), Some(status));
+        }
+        assert_eq!(status_from_proto(proto::ProcessStatus::Unspecified), None);
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(bearer_token(&metadata), None);
+        metadata.insert("authorization", "Bearer s3cret".parse().unwrap());
+        assert_eq!(bearer_token(&metadata), Some("s3cret"));
+        metadata.insert("authorization", "s3cret".parse().unwrap());
+        assert_eq!(bearer_token(&metadata), None);
+    }
+
+    #[test]
+    fn test_error_status_codes() {
+        assert_eq!(
+            error_status("process not found: web".into()).code(),
+            tonic::Code::NotFound
+        );
+        assert_eq!(
+            error_status("web is already running".into()).code(),
+            tonic::Code::FailedPrecondition
+        );
+    }
+}
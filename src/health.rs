@@ -1,5 +1,8 @@
-use crate::process::{ProcessError, ProcessTable};
-use crate::protocol::ProcessStatus;
+use crate::config::{ProcessConfig, ReadySignal, UnhealthyAction};
+use crate::paths::Paths;
+use crate::process::{self, ProcessError, ProcessTable};
+use crate::protocol::{HealthReport, ProcessStatus};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::TcpStream;
@@ -8,11 +11,36 @@ use tokio::sync::{RwLock, watch};
 pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 pub const HEALTH_CHECK_TIMEOUT_SECS: u64 = 30;
 pub const HEALTH_CHECK_ATTEMPT_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often a running process's `health_check` is probed under `on_unhealthy`.
+pub const LIVENESS_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+pub const DEFAULT_UNHEALTHY_THRESHOLD: u32 = 3;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum HealthCheckTarget {
     Http(String),
     Tcp(String, u16),
+    /// Passes once the process has written to its `PM3_READY_PATH` file.
+    ReadyFile(PathBuf),
+}
+
+/// How a starting process shows it is ready, per its `ready_signal`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Readiness {
+    Check(String),
+    ReadyFile(PathBuf),
+    Delay(Duration),
+}
+
+impl Readiness {
+    pub fn for_process(name: &str, config: &ProcessConfig, paths: &Paths) -> Option<Self> {
+        match config.ready_signal {
+            Some(ReadySignal::Ipc) => Some(Self::ReadyFile(paths.ready_file(name))),
+            Some(ReadySignal::Delay) => Some(Self::Delay(Duration::from_millis(
+                config.ready_delay.unwrap_or_default(),
+            ))),
+            _ => config.readiness_check.clone().map(Self::Check),
+        }
+    }
 }
 
 pub fn parse_health_check(url: &str) -> Result<HealthCheckTarget, ProcessError> {
@@ -45,36 +73,134 @@ pub fn parse_health_check(url: &str) -> Result<HealthCheckTarget, ProcessError>
     }
 }
 
-async fn check_http(client: &reqwest::Client, url: &str) -> bool {
+/// The result of one probe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckOutcome {
+    pub passed: bool,
+    pub http_status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl CheckOutcome {
+    fn failed(error: impl Into<String>) -> Self {
+        Self {
+            passed: false,
+            http_status: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// The latest `health_check` probe of a process, kept for `pm3 info`.
+#[derive(Debug, Clone)]
+pub struct HealthRecord {
+    pub outcome: CheckOutcome,
+    pub at: tokio::time::Instant,
+    pub latency: Duration,
+    /// Probes failed in a row, 0 when this one passed.
+    pub failures: u32,
+}
+
+impl HealthRecord {
+    pub fn report(&self) -> HealthReport {
+        HealthReport {
+            passed: self.outcome.passed,
+            checked_ago: self.at.elapsed().as_secs(),
+            latency_ms: self.latency.as_millis() as u64,
+            http_status: self.outcome.http_status,
+            error: self.outcome.error.clone(),
+            failures: self.failures,
+        }
+    }
+}
+
+async fn check_http(client: &reqwest::Client, url: &str) -> CheckOutcome {
     match client.get(url).send().await {
-        Ok(resp) => resp.status().is_success(),
-        Err(_) => false,
+        Ok(resp) => CheckOutcome {
+            passed: resp.status().is_success(),
+            http_status: Some(resp.status().as_u16()),
+            error: None,
+        },
+        Err(e) if e.is_timeout() => CheckOutcome::failed("timed out"),
+        Err(e) if e.is_connect() => CheckOutcome::failed("connection failed"),
+        Err(e) => CheckOutcome::failed(e.to_string()),
     }
 }
 
-async fn check_tcp(host: &str, port: u16) -> bool {
+async fn connect_tcp(host: &str, port: u16) -> CheckOutcome {
     let addr = if host.contains(':') {
         format!("[{host}]:{port}")
     } else {
         format!("{host}:{port}")
     };
-    tokio::time::timeout(HEALTH_CHECK_ATTEMPT_TIMEOUT, TcpStream::connect(&addr))
-        .await
-        .map(|r| r.is_ok())
-        .unwrap_or(false)
+    match tokio::time::timeout(HEALTH_CHECK_ATTEMPT_TIMEOUT, TcpStream::connect(&addr)).await {
+        Ok(Ok(_)) => CheckOutcome {
+            passed: true,
+            http_status: None,
+            error: None,
+        },
+        Ok(Err(e)) => CheckOutcome::failed(e.to_string()),
+        Err(_) => CheckOutcome::failed("timed out"),
+    }
+}
+
+pub(crate) async fn check_tcp(host: &str, port: u16) -> bool {
+    connect_tcp(host, port).await.passed
 }
 
-async fn check_target(client: &reqwest::Client, target: &HealthCheckTarget) -> bool {
+async fn check_target(client: &reqwest::Client, target: &HealthCheckTarget) -> CheckOutcome {
     match target {
         HealthCheckTarget::Http(url) => check_http(client, url).await,
-        HealthCheckTarget::Tcp(host, port) => check_tcp(host, *port).await,
+        HealthCheckTarget::Tcp(host, port) => connect_tcp(host, *port).await,
+        HealthCheckTarget::ReadyFile(path) => {
+            let ready = tokio::fs::metadata(path)
+                .await
+                .is_ok_and(|meta| meta.len() > 0);
+            CheckOutcome {
+                passed: ready,
+                http_status: None,
+                error: (!ready).then(|| "not ready yet".to_string()),
+            }
+        }
     }
 }
 
+/// Probes `target`, keeping the result as the latest health check of the
+/// process running as `pid`. Returns the result and how many probes have
+/// failed in a row, or `None` once `name` is gone or runs another process.
+async fn probe_health(
+    name: &str,
+    pid: Option<u32>,
+    target: &HealthCheckTarget,
+    client: &reqwest::Client,
+    processes: &Arc<RwLock<ProcessTable>>,
+) -> Option<(bool, u32)> {
+    let at = tokio::time::Instant::now();
+    let outcome = check_target(client, target).await;
+    let passed = outcome.passed;
+    let mut table = processes.write().await;
+    // A reload promotion or restart may have put another process under the
+    // name while this probe ran; its record isn't this one's to write
+    let managed = table.get_mut(name).filter(|m| m.pid == pid)?;
+    let failures = match (&managed.health, passed) {
+        (_, true) => 0,
+        (Some(last), false) => last.failures + 1,
+        (None, false) => 1,
+    };
+    managed.health = Some(HealthRecord {
+        outcome,
+        at,
+        latency: at.elapsed(),
+        failures,
+    });
+    Some((passed, failures))
+}
+
 async fn set_unhealthy_if_starting(name: &str, processes: &Arc<RwLock<ProcessTable>>) {
     let mut table = processes.write().await;
     if let Some(managed) = table.get_mut(name)
         && managed.status == ProcessStatus::Starting
+        && !managed.paused
     {
         managed.status = ProcessStatus::Unhealthy;
     }
@@ -97,29 +223,46 @@ enum WaitOutcome {
 
 async fn wait_for_check_pass(
     name: &str,
+    check_kind: &str,
     target: &HealthCheckTarget,
     timeout_secs: u64,
     processes: &Arc<RwLock<ProcessTable>>,
     client: &reqwest::Client,
     shutdown_rx: &mut watch::Receiver<bool>,
 ) -> WaitOutcome {
-    for _ in 0..timeout_secs {
+    let mut attempts = 0;
+    while attempts < timeout_secs {
         // Check shutdown signal
         if *shutdown_rx.borrow() {
             return WaitOutcome::Aborted;
         }
 
-        // Check if process is still in Starting state
-        {
+        // Check if process is still in Starting state; paused time doesn't count
+        let (paused, pid) = {
             let table = processes.read().await;
             match table.get(name) {
-                Some(managed) if managed.status == ProcessStatus::Starting => {}
+                Some(managed) if managed.status == ProcessStatus::Starting => {
+                    (managed.paused, managed.pid)
+                }
+                // Frozen processes can't answer; resume checking after continue
+                Some(managed) if managed.status == ProcessStatus::Suspended => (true, managed.pid),
                 _ => return WaitOutcome::Aborted,
             }
-        }
+        };
 
-        if check_target(client, target).await {
-            return WaitOutcome::Passed;
+        if !paused {
+            attempts += 1;
+            let passed = if check_kind == "health" {
+                match probe_health(name, pid, target, client, processes).await {
+                    Some((passed, _)) => passed,
+                    None => return WaitOutcome::Aborted,
+                }
+            } else {
+                check_target(client, target).await.passed
+            };
+            if passed {
+                return WaitOutcome::Passed;
+            }
         }
 
         // Wait before next attempt, also listening for shutdown
@@ -138,7 +281,7 @@ async fn wait_for_check_pass(
 
 pub fn spawn_startup_checker(
     name: String,
-    readiness_check: Option<String>,
+    readiness: Option<Readiness>,
     readiness_timeout_secs: Option<u64>,
     health_check: Option<String>,
     processes: Arc<RwLock<ProcessTable>>,
@@ -146,28 +289,37 @@ pub fn spawn_startup_checker(
 ) {
     tokio::spawn(async move {
         let mut parsed_checks: Vec<(&str, HealthCheckTarget, u64)> = Vec::new();
-
-        if let Some(readiness_check) = readiness_check {
-            let target = match parse_health_check(&readiness_check) {
-                Ok(t) => t,
-                Err(e) => {
-                    eprintln!("invalid readiness check for '{name}': {e}");
-                    set_unhealthy_if_starting(&name, &processes).await;
-                    return;
-                }
-            };
-            parsed_checks.push((
-                "readiness",
-                target,
-                readiness_timeout_secs.unwrap_or(HEALTH_CHECK_TIMEOUT_SECS),
-            ));
+        let readiness_timeout_secs = readiness_timeout_secs.unwrap_or(HEALTH_CHECK_TIMEOUT_SECS);
+        let mut ready_delay = None;
+
+        match readiness {
+            Some(Readiness::Check(readiness_check)) => {
+                let target = match parse_health_check(&readiness_check) {
+                    Ok(t) => t,
+                    Err(e) => {
+                        tracing::warn!("invalid readiness check for '{name}': {e}");
+                        set_unhealthy_if_starting(&name, &processes).await;
+                        return;
+                    }
+                };
+                parsed_checks.push(("readiness", target, readiness_timeout_secs));
+            }
+            Some(Readiness::ReadyFile(path)) => {
+                parsed_checks.push((
+                    "readiness",
+                    HealthCheckTarget::ReadyFile(path),
+                    readiness_timeout_secs,
+                ));
+            }
+            Some(Readiness::Delay(delay)) => ready_delay = Some(delay),
+            None => {}
         }
 
         if let Some(health_check) = health_check {
             let target = match parse_health_check(&health_check) {
                 Ok(t) => t,
                 Err(e) => {
-                    eprintln!("invalid health check for '{name}': {e}");
+                    tracing::warn!("invalid health check for '{name}': {e}");
                     set_unhealthy_if_starting(&name, &processes).await;
                     return;
                 }
@@ -175,17 +327,24 @@ pub fn spawn_startup_checker(
             parsed_checks.push(("health", target, HEALTH_CHECK_TIMEOUT_SECS));
         }
 
-        if parsed_checks.is_empty() {
+        if parsed_checks.is_empty() && ready_delay.is_none() {
             return;
         }
 
+        if let Some(delay) = ready_delay {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown_rx.changed() => return,
+            }
+        }
+
         let client = match reqwest::Client::builder()
             .timeout(HEALTH_CHECK_ATTEMPT_TIMEOUT)
             .build()
         {
             Ok(c) => c,
             Err(e) => {
-                eprintln!("failed to build HTTP client for '{name}': {e}");
+                tracing::error!("failed to build HTTP client for '{name}': {e}");
                 set_unhealthy_if_starting(&name, &processes).await;
                 return;
             }
@@ -194,6 +353,7 @@ pub fn spawn_startup_checker(
         for (check_kind, target, timeout_secs) in &parsed_checks {
             match wait_for_check_pass(
                 &name,
+                check_kind,
                 target,
                 *timeout_secs,
                 &processes,
@@ -204,7 +364,9 @@ pub fn spawn_startup_checker(
             {
                 WaitOutcome::Passed => {}
                 WaitOutcome::TimedOut => {
-                    eprintln!("{check_kind} check timed out for '{name}' after {timeout_secs}s");
+                    tracing::warn!(
+                        "{check_kind} check timed out for '{name}' after {timeout_secs}s"
+                    );
                     set_unhealthy_if_starting(&name, &processes).await;
                     return;
                 }
@@ -225,6 +387,150 @@ pub fn spawn_health_checker(
     spawn_startup_checker(name, None, None, Some(health_check), processes, shutdown_rx);
 }
 
+/// With `on_unhealthy`, keeps probing the `health_check` of a process once it
+/// is up. After `unhealthy_threshold` failures in a row the process is
+/// marked unhealthy and stopped, or restarted like a crashed one, after its
+/// restart delay and within `max_restarts`; it is marked online again
+/// when a check passes. A process whose startup check timed out is handled
+/// the same way.
+pub fn spawn_liveness_checker(
+    name: String,
+    config: ProcessConfig,
+    processes: Arc<RwLock<ProcessTable>>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let (Some(action), Some(health_check)) = (config.on_unhealthy, config.health_check.clone())
+    else {
+        return;
+    };
+    tokio::spawn(async move {
+        let target = match parse_health_check(&health_check) {
+            Ok(t) => t,
+            Err(e) => {
+                tracing::warn!("invalid health check for '{name}': {e}");
+                return;
+            }
+        };
+        let client = match reqwest::Client::builder()
+            .timeout(HEALTH_CHECK_ATTEMPT_TIMEOUT)
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!("failed to build HTTP client for '{name}': {e}");
+                return;
+            }
+        };
+        let threshold = config
+            .unhealthy_threshold
+            .unwrap_or(DEFAULT_UNHEALTHY_THRESHOLD);
+        // The process this checker was spawned for; a restart or reload
+        // promotion spawns a new checker along with the new process
+        let mut watched = None;
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(LIVENESS_CHECK_INTERVAL) => {}
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                }
+            }
+            if *shutdown_rx.borrow() {
+                return;
+            }
+
+            // Starting processes are the startup checker's to judge
+            let pid = {
+                let table = processes.read().await;
+                match table.get(&name) {
+                    Some(managed) if managed.paused => continue,
+                    Some(managed)
+                        if matches!(
+                            managed.status,
+                            ProcessStatus::Starting | ProcessStatus::Suspended
+                        ) =>
+                    {
+                        continue;
+                    }
+                    Some(managed)
+                        if matches!(
+                            managed.status,
+                            ProcessStatus::Online | ProcessStatus::Unhealthy
+                        ) =>
+                    {
+                        managed.pid
+                    }
+                    _ => return,
+                }
+            };
+            if *watched.get_or_insert(pid) != pid {
+                return;
+            }
+
+            let Some((passed, failures)) =
+                probe_health(&name, pid, &target, &client, &processes).await
+            else {
+                return;
+            };
+            if passed {
+                let mut table = processes.write().await;
+                if let Some(managed) = table.get_mut(&name)
+                    && managed.status == ProcessStatus::Unhealthy
+                {
+                    tracing::info!("'{name}' is healthy again");
+                    managed.status = ProcessStatus::Online;
+                }
+                continue;
+            }
+            if failures < threshold {
+                continue;
+            }
+
+            let raw_pid = {
+                let mut table = processes.write().await;
+                let Some(managed) = table.get_mut(&name).filter(|m| m.pid == pid) else {
+                    return;
+                };
+                if managed.status == ProcessStatus::Online {
+                    tracing::warn!("'{name}' failed {failures} health checks in a row");
+                    managed.status = ProcessStatus::Unhealthy;
+                }
+                if action == UnhealthyAction::None {
+                    continue;
+                }
+
+                if action == UnhealthyAction::Stop {
+                    // Signal the process monitor not to auto-restart
+                    if let Some(ref tx) = managed.monitor_shutdown {
+                        let _ = tx.send(true);
+                    }
+                } else {
+                    // The process monitor restarts it once it exits, as it
+                    // would a crash, within the same restart budget
+                    managed.unhealthy_restart = true;
+                    managed.crashes += 1;
+                }
+                managed.pid
+            };
+
+            tracing::warn!(
+                "'{name}' is unhealthy, {}",
+                if action == UnhealthyAction::Stop {
+                    "stopping"
+                } else {
+                    "restarting"
+                }
+            );
+            if let Some(raw_pid) = raw_pid {
+                process::terminate(raw_pid, &config).await;
+            }
+            return;
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,169 @@
+//! `[daemon] idle_timeout`: a daemon with nothing to manage and no clients
+//! exits on its own, so it doesn't stay resident on a dev machine. The next
+//! pm3 command starts it again.
+
+use crate::config::ProcessConfig;
+use crate::process::ProcessTable;
+use crate::protocol::ProcessStatus;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, watch};
+
+/// How often the daemon checks whether it has been idle long enough.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Client requests in flight, and when the last one finished.
+#[derive(Debug)]
+pub struct Activity {
+    in_flight: AtomicUsize,
+    last: Mutex<Instant>,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            last: Mutex::new(Instant::now()),
+        }
+    }
+}
+
+impl Activity {
+    /// Marks a request or session as in flight until the guard is dropped.
+    pub fn begin(self: &Arc<Self>) -> ActivityGuard {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        ActivityGuard(Arc::clone(self))
+    }
+
+    /// How long since the last request finished, or `None` while one is
+    /// still being served.
+    pub fn idle_for(&self) -> Option<Duration> {
+        if self.in_flight.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        Some(self.last.lock().unwrap().elapsed())
+    }
+}
+
+/// Holds a request's place in [`Activity`].
+pub struct ActivityGuard(Arc<Activity>);
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        *self.0.last.lock().unwrap() = Instant::now();
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether a process needs the daemon: it is running, or on its way up or
+/// back up, or a `cron_start` schedule will start it later.
+fn keeps_daemon_up(status: ProcessStatus, config: &ProcessConfig) -> bool {
+    let finished = matches!(
+        status,
+        ProcessStatus::Stopped
+            | ProcessStatus::Errored
+            | ProcessStatus::Succeeded
+            | ProcessStatus::Failed
+    );
+    !finished || config.cron_start.is_some()
+}
+
+/// With a `timeout`, shuts the daemon down once no process needs it and no
+/// client has made a request for that long.
+pub fn spawn_idle_watcher(
+    timeout: Option<Duration>,
+    activity: Arc<Activity>,
+    processes: Arc<RwLock<ProcessTable>>,
+    shutdown_tx: watch::Sender<bool>,
+) {
+    let Some(timeout) = timeout else {
+        return;
+    };
+    let mut shutdown_rx = shutdown_tx.subscribe();
+    tokio::spawn(async move {
+        // Idle time only counts once the process table is empty of work, so
+        // a daemon whose last process just stopped gets the full timeout
+        let mut idle_since: Option<Instant> = None;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                }
+            }
+
+            let busy = processes
+                .read()
+                .await
+                .values()
+                .any(|p| keeps_daemon_up(p.status, &p.config));
+            if busy {
+                idle_since = None;
+                continue;
+            }
+            let since = *idle_since.get_or_insert_with(Instant::now);
+            let Some(quiet) = activity.idle_for() else {
+                continue;
+            };
+            if quiet.min(since.elapsed()) >= timeout {
+                tracing::info!(
+                    "no running processes or client requests for {timeout:?}, shutting down (idle_timeout)"
+                );
+                let _ = shutdown_tx.send(true);
+                return;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> ProcessConfig {
+        crate::config::parse_config(&format!("[p]\ncommand = \"sleep 1\"\n{toml}"))
+            .unwrap()
+            .remove("p")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_running_processes_keep_daemon_up() {
+        let plain = config("");
+        for status in [
+            ProcessStatus::Starting,
+            ProcessStatus::Online,
+            ProcessStatus::Unhealthy,
+            ProcessStatus::Flapping,
+            ProcessStatus::Suspended,
+        ] {
+            assert!(keeps_daemon_up(status, &plain), "{status}");
+        }
+        for status in [
+            ProcessStatus::Stopped,
+            ProcessStatus::Errored,
+            ProcessStatus::Succeeded,
+            ProcessStatus::Failed,
+        ] {
+            assert!(!keeps_daemon_up(status, &plain), "{status}");
+        }
+    }
+
+    #[test]
+    fn test_cron_start_keeps_daemon_up() {
+        let scheduled = config("cron_start = \"0 9 * * *\"\n");
+        assert!(keeps_daemon_up(ProcessStatus::Stopped, &scheduled));
+    }
+
+    #[test]
+    fn test_activity_tracks_requests_in_flight() {
+        let activity = Arc::new(Activity::default());
+        let guard = activity.begin();
+        assert_eq!(activity.idle_for(), None);
+        drop(guard);
+        assert!(activity.idle_for().unwrap() < Duration::from_secs(1));
+    }
+}
@@ -1,20 +1,44 @@
+pub mod audit;
+pub mod availability;
+pub mod bundle;
 pub mod cli;
 pub mod client;
 pub mod config;
+pub mod conflict;
 pub mod cron;
 pub mod daemon;
 pub mod deps;
+pub mod dump;
 pub mod env_file;
+pub mod events;
+pub mod gitignore;
+pub mod grpc;
 pub mod health;
+pub mod idle;
 pub mod init;
 pub mod log;
 pub mod manager;
 pub mod memory;
+pub mod metrics;
+pub mod namespace;
+pub mod notifier;
 pub mod paths;
 pub mod pid;
 pub mod process;
+pub mod profile;
 pub mod protocol;
+pub mod quota;
+pub mod select;
+pub mod settings;
+pub mod snapshot;
 pub mod startup;
+pub mod statsd;
 pub mod sys;
+pub mod telemetry;
+pub mod timeseries;
+pub mod tls;
+pub mod tree;
 pub mod tui;
+pub mod upgrade;
 pub mod watch;
+pub mod web;
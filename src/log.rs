@@ -1,8 +1,13 @@
+use crate::config::ProcessConfig;
+use crate::paths::Paths;
 use serde::{Deserialize, Serialize};
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader as TokioBufReader};
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
 
 /// 10 MB rotation threshold
 pub const LOG_ROTATION_SIZE: u64 = 10 * 1024 * 1024;
@@ -10,16 +15,353 @@ pub const LOG_ROTATION_SIZE: u64 = 10 * 1024 * 1024;
 /// Keep up to 3 rotated files (.1, .2, .3)
 pub const LOG_ROTATION_KEEP: u32 = 3;
 
+/// How long a logged line may be, in bytes, unless `log_max_line_len` says
+/// otherwise. The rest of a longer line is left out.
+pub const DEFAULT_MAX_LINE_LEN: usize = 64 * 1024;
+
+/// The fds a log relay reads a process's stdout and stderr from.
+pub const RELAY_STDOUT_FD: i32 = 3;
+pub const RELAY_STDERR_FD: i32 = 4;
+
+/// How often lines dropped for the disk quota are reported, absent a
+/// `log_rate_limit` whose period says otherwise.
+const SUPPRESSED_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a copier checks whether the data dir is over the disk quota.
+const QUOTA_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the daemon checks the logs a relay writes for new lines, when
+/// file notifications can't tell it.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the daemon checks the logs a relay writes anyway while file
+/// notifications tell it of new lines, in case one was missed or the process
+/// is gone.
+const FOLLOW_IDLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How many lines a relay holds for the daemon before it drops some.
+const RELAY_FEED_CAPACITY: usize = 1024;
+
+/// The pm3 binary to run log relays with, set by daemons that have one.
+static RELAY_EXE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Has processes spawned from now on write their output through a
+/// `pm3 log-relay` process run from `exe`.
+pub fn enable_relay(exe: PathBuf) {
+    let _ = RELAY_EXE.set(exe);
+}
+
+pub fn relay_exe() -> Option<&'static Path> {
+    RELAY_EXE.get().map(PathBuf::as_path)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LogStream {
     Stdout,
     Stderr,
 }
 
+/// A line a relay sends the daemon on its stdout, as one JSON object a
+/// line.
+#[derive(Debug, Serialize, Deserialize)]
+struct RelayedLine {
+    stream: LogStream,
+    line: String,
+    /// When the line was read, in milliseconds since the epoch.
+    time: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct LogEntry {
     pub stream: LogStream,
     pub line: String,
+    /// When the line was read from the process.
+    pub time: chrono::DateTime<chrono::Utc>,
+}
+
+/// Where a process's output is logged: `<name>-out.log` and `<name>-err.log`,
+/// or with `log_combine` one `<name>.log` whose lines are tagged with their
+/// stream, like `[err] `. With `merge_logs` a cluster's instances share the
+/// cluster's `<name>.log`, their lines tagged like `[web:0 err] `.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFiles {
+    pub stdout: PathBuf,
+    pub stderr: PathBuf,
+    /// The instance whose lines these are, in a log shared with the others.
+    pub instance: Option<String>,
+}
+
+impl LogFiles {
+    pub fn new(paths: &Paths, name: &str, config: &ProcessConfig) -> Self {
+        if config.stdout_log.is_some() || config.stderr_log.is_some() {
+            // Cluster instances each get their own, like `app-out-0.log`
+            let index = name.rsplit_once(':').map(|(_, index)| index);
+            let custom = |path: &Option<String>, default: PathBuf| match path {
+                Some(path) => with_instance(config.resolve_path(path), index),
+                None => default,
+            };
+            return Self {
+                stdout: custom(&config.stdout_log, paths.stdout_log(name)),
+                stderr: custom(&config.stderr_log, paths.stderr_log(name)),
+                instance: None,
+            };
+        }
+        let cluster = name
+            .rsplit_once(':')
+            .filter(|_| config.merge_logs == Some(true));
+        if let Some((cluster, _)) = cluster {
+            let path = paths.combined_log(cluster);
+            return Self {
+                stdout: path.clone(),
+                stderr: path,
+                instance: Some(name.to_string()),
+            };
+        }
+        if config.log_combine == Some(true) || config.merge_logs == Some(true) {
+            let path = paths.combined_log(name);
+            return Self {
+                stdout: path.clone(),
+                stderr: path,
+                instance: None,
+            };
+        }
+        Self {
+            stdout: paths.stdout_log(name),
+            stderr: paths.stderr_log(name),
+            instance: None,
+        }
+    }
+
+    /// Creates the directories the files are in.
+    pub async fn create_dirs(&self) -> io::Result<()> {
+        for path in [&self.stdout, &self.stderr] {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_combined(&self) -> bool {
+        self.stdout == self.stderr
+    }
+
+    pub fn path(&self, stream: &LogStream) -> &Path {
+        match stream {
+            LogStream::Stdout => &self.stdout,
+            LogStream::Stderr => &self.stderr,
+        }
+    }
+
+    /// What a copier writes before each line of `stream`, in a combined log.
+    pub fn tag(&self, stream: &LogStream) -> Option<String> {
+        if !self.is_combined() {
+            return None;
+        }
+        let stream = match stream {
+            LogStream::Stdout => "out",
+            LogStream::Stderr => "err",
+        };
+        Some(match &self.instance {
+            Some(instance) => format!("[{instance} {stream}] "),
+            None => format!("[{stream}] "),
+        })
+    }
+
+    /// The stream a line of a combined log is from and the line without its
+    /// tag, or `None` if another instance wrote it. Untagged lines, like the
+    /// output of hooks, count as stdout.
+    pub fn untag<'a>(&self, line: &'a str) -> Option<(LogStream, &'a str)> {
+        match split_tag(line) {
+            None => Some((LogStream::Stdout, line)),
+            Some((Some(instance), ..)) if self.instance.as_deref() != Some(instance) => None,
+            Some((_, stream, rest)) => Some((stream, rest)),
+        }
+    }
+
+    /// The current length of each file, to read what is appended from.
+    pub fn offsets(&self) -> [u64; 2] {
+        [&self.stdout, &self.stderr].map(|path| std::fs::metadata(path).map_or(0, |m| m.len()))
+    }
+
+    /// Reads the lines appended since `offsets` like `read_new_lines`, with
+    /// the stream each is from.
+    pub fn read_new_lines(
+        &self,
+        offsets: &mut [u64; 2],
+        include_partial: bool,
+    ) -> io::Result<Vec<(LogStream, String)>> {
+        if self.is_combined() {
+            let lines = read_new_lines(&self.stdout, &mut offsets[0], include_partial)?;
+            return Ok(lines
+                .iter()
+                .filter_map(|line| self.untag(line))
+                .map(|(stream, line)| (stream, line.to_string()))
+                .collect());
+        }
+        let mut lines = Vec::new();
+        for (stream, offset) in [LogStream::Stdout, LogStream::Stderr]
+            .into_iter()
+            .zip(offsets.iter_mut())
+        {
+            let new = read_new_lines(self.path(&stream), offset, include_partial)?;
+            lines.extend(new.into_iter().map(|line| (stream.clone(), line)));
+        }
+        Ok(lines)
+    }
+}
+
+/// `log_rate_limit`: at most `lines` lines of a stream are logged per `per`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub lines: u32,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    /// Parses `<lines>/<period>`, like `"1000/s"` or `"100/10s"`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = |reason: &str| format!("invalid log_rate_limit '{s}': {reason}");
+        let (lines, per) = s
+            .split_once('/')
+            .ok_or_else(|| invalid("expected <lines>/<period>, like \"1000/s\""))?;
+        let lines = lines
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&lines| lines > 0)
+            .ok_or_else(|| invalid("the line count must be a positive number"))?;
+        // A bare unit is one of it, so "1000/s" is 1000 lines per second
+        let per = per.trim();
+        if per.is_empty() {
+            return Err(invalid("missing the period after '/'"));
+        }
+        let per = match per.starts_with(|c: char| c.is_ascii_digit()) {
+            true => crate::config::parse_duration(per),
+            false => crate::config::parse_duration(&format!("1{per}")),
+        }
+        .map_err(|e| invalid(&e))?;
+        Ok(Self { lines, per })
+    }
+}
+
+impl std::fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}ms", self.lines, self.per.as_millis())
+    }
+}
+
+/// What a copier leaves out of a process's output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLimits {
+    /// Bytes of a line that are logged.
+    pub max_line_len: usize,
+    pub rate_limit: Option<RateLimit>,
+    /// Output is dropped while this file exists, see [`crate::quota`].
+    pub quota_flag: Option<PathBuf>,
+}
+
+impl Default for LogLimits {
+    fn default() -> Self {
+        Self {
+            max_line_len: DEFAULT_MAX_LINE_LEN,
+            rate_limit: None,
+            quota_flag: None,
+        }
+    }
+}
+
+impl LogLimits {
+    pub fn new(paths: &Paths, config: &ProcessConfig) -> Self {
+        Self {
+            max_line_len: config.max_log_line_len(),
+            rate_limit: config
+                .log_rate_limit
+                .as_deref()
+                .and_then(|limit| RateLimit::parse(limit).ok()),
+            quota_flag: Some(paths.over_quota_file()),
+        }
+    }
+}
+
+/// Shared by a process's log copiers and the daemon: how many lines the
+/// copiers dropped, and requests for them to reopen the log files.
+#[derive(Debug, Clone)]
+pub struct LogControl {
+    pub suppressed: Arc<AtomicU64>,
+    reopen: Arc<watch::Sender<()>>,
+}
+
+impl Default for LogControl {
+    fn default() -> Self {
+        Self {
+            suppressed: Arc::default(),
+            reopen: Arc::new(watch::channel(()).0),
+        }
+    }
+}
+
+impl LogControl {
+    /// Lines dropped under `log_rate_limit` or the disk quota so far.
+    pub fn suppressed(&self) -> u64 {
+        self.suppressed.load(Ordering::Relaxed)
+    }
+
+    /// Has the copiers close the log files and open them again at their
+    /// paths, once a tool like logrotate has moved them aside.
+    pub fn reopen(&self) {
+        self.reopen.send_replace(());
+    }
+}
+
+/// The line a copier logs in place of the `count` lines it dropped.
+fn suppressed_marker(count: u64, reason: &str) -> String {
+    format!("[pm3: {count} lines suppressed ({reason})]")
+}
+
+/// How many lines a line logged by [`suppressed_marker`] stands for.
+pub fn parse_suppressed_marker(line: &str) -> Option<u64> {
+    let (count, _reason) = line
+        .strip_prefix("[pm3: ")?
+        .strip_suffix(")]")?
+        .split_once(" lines suppressed (")?;
+    count.parse().ok()
+}
+
+/// `path` with the index of a cluster instance added to its file name,
+/// before the extension.
+fn with_instance(path: PathBuf, index: Option<&str>) -> PathBuf {
+    let Some(index) = index else {
+        return path;
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{stem}-{index}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{index}"),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Splits the `[<instance> <stream>] ` tag off a line of a combined log.
+fn split_tag(line: &str) -> Option<(Option<&str>, LogStream, &str)> {
+    let (tag, rest) = line.strip_prefix('[')?.split_once("] ")?;
+    let (instance, stream) = match tag.rsplit_once(' ') {
+        Some((instance, stream)) => (Some(instance), stream),
+        None => (None, tag),
+    };
+    let stream = match stream {
+        "out" => LogStream::Stdout,
+        "err" => LogStream::Stderr,
+        _ => return None,
+    };
+    Some((instance, stream, rest))
+}
+
+/// The timestamp format of streamed log lines and events: RFC 3339 in UTC
+/// with milliseconds.
+pub fn format_timestamp(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
 }
 
 pub fn tail_file(path: &Path, n: usize) -> io::Result<Vec<String>> {
@@ -67,8 +409,10 @@ pub fn tail_file(path: &Path, n: usize) -> io::Result<Vec<String>> {
 
     // Read from start_offset to end.
     file.seek(io::SeekFrom::Start(start_offset))?;
-    let mut tail = String::new();
-    file.read_to_string(&mut tail)?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    // Hooks write to the logs directly, so anything may be in them
+    let tail = String::from_utf8_lossy(&tail);
 
     let mut lines: Vec<String> = tail.lines().map(String::from).collect();
     // If the file ends with \n, .lines() won't produce a trailing empty string,
@@ -77,6 +421,210 @@ pub fn tail_file(path: &Path, n: usize) -> io::Result<Vec<String>> {
     Ok(lines)
 }
 
+/// Like `tail_file`, but when `path` has fewer than `n` lines, continues into
+/// its rotations, newest first, so the lines span rotation boundaries.
+pub fn tail_rotated(path: &Path, n: usize) -> io::Result<Vec<String>> {
+    let mut lines = tail_file(path, n)?;
+    for i in 1..=LOG_ROTATION_KEEP {
+        if lines.len() >= n {
+            break;
+        }
+        let mut older = tail_file(&rotated_path(path, i), n - lines.len())?;
+        older.append(&mut lines);
+        lines = older;
+    }
+    Ok(lines)
+}
+
+/// The time in the prefix `log_date_format` gave a log line, if it has one.
+/// Formats without a date give times on an arbitrary day, which still order
+/// the lines of one day.
+pub fn line_time(line: &str, log_date_format: &str) -> Option<chrono::NaiveDateTime> {
+    // Lines of a combined log start with their stream
+    let line = split_tag(line).map_or(line, |(_, _, rest)| rest);
+    let parts = log_date_format.matches(" | ").count() + 1;
+    let prefix_len = line.match_indices(" | ").nth(parts - 1)?.0;
+    let prefix = &line[..prefix_len];
+    chrono::NaiveDateTime::parse_from_str(prefix, log_date_format)
+        .ok()
+        .or_else(|| {
+            chrono::NaiveTime::parse_from_str(prefix, log_date_format)
+                .ok()
+                .map(|time| chrono::NaiveDate::MIN.and_time(time))
+        })
+}
+
+/// The `--since`/`--until` window of `pm3 log`, in local time like the
+/// `log_date_format` timestamps it is compared with.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeRange {
+    pub since: Option<chrono::NaiveDateTime>,
+    pub until: Option<chrono::NaiveDateTime>,
+}
+
+impl TimeRange {
+    /// Parses each bound as a duration before now, like `"15m"`, or a local
+    /// date and time, like `"2024-05-01 12:00"`.
+    pub fn parse(since: Option<&str>, until: Option<&str>) -> Result<Self, String> {
+        let now = chrono::Local::now().naive_local();
+        let bound = |value: Option<&str>| value.map(|v| parse_log_time(v, now)).transpose();
+        Ok(Self {
+            since: bound(since)?,
+            until: bound(until)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Keeps the lines whose `log_date_format` timestamps fall in the range.
+    /// Lines without one go with the line before them, and are dropped at
+    /// the start, where that line isn't known.
+    pub fn filter(&self, lines: Vec<String>, log_date_format: &str) -> Vec<String> {
+        let today = chrono::Local::now().date_naive();
+        let mut inside = false;
+        lines
+            .into_iter()
+            .filter(|line| {
+                if let Some(mut time) = line_time(line, log_date_format) {
+                    // Formats without a date are taken to be from today
+                    if time.date() == chrono::NaiveDate::MIN {
+                        time = today.and_time(time.time());
+                    }
+                    inside = self.since.is_none_or(|since| time >= since)
+                        && self.until.is_none_or(|until| time <= until);
+                }
+                inside
+            })
+            .collect()
+    }
+}
+
+fn parse_log_time(
+    value: &str,
+    now: chrono::NaiveDateTime,
+) -> Result<chrono::NaiveDateTime, String> {
+    let value = value.trim();
+    if let Ok(ago) = crate::config::parse_duration(value) {
+        return chrono::Duration::from_std(ago)
+            .ok()
+            .and_then(|ago| now.checked_sub_signed(ago))
+            .ok_or_else(|| format!("duration too long: {value:?}"));
+    }
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&chrono::Local).naive_local());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(time) = chrono::NaiveDateTime::parse_from_str(value, format) {
+            return Ok(time);
+        }
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN));
+    }
+    for format in ["%H:%M:%S", "%H:%M"] {
+        if let Ok(time) = chrono::NaiveTime::parse_from_str(value, format) {
+            return Ok(now.date().and_time(time));
+        }
+    }
+    Err(format!(
+        "invalid time {value:?}: expected a duration like \"15m\" or a time like \"2024-05-01 12:00\""
+    ))
+}
+
+/// Merges a process's stdout and stderr lines in the order they were written,
+/// going by their `log_date_format` timestamps. Lines without one, like the
+/// rest of a multi-line message, stay after the line before them. Without a
+/// format there is nothing to go by, so stdout comes first.
+pub fn interleave(
+    stdout: Vec<String>,
+    stderr: Vec<String>,
+    log_date_format: Option<&str>,
+) -> Vec<(LogStream, String)> {
+    let Some(format) = log_date_format else {
+        return stdout
+            .into_iter()
+            .map(|line| (LogStream::Stdout, line))
+            .chain(stderr.into_iter().map(|line| (LogStream::Stderr, line)))
+            .collect();
+    };
+    let timed = |lines: Vec<String>| {
+        let mut last = None;
+        lines
+            .into_iter()
+            .map(|line| {
+                last = line_time(&line, format).or(last);
+                (last, line)
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut stdout = timed(stdout).into_iter().peekable();
+    let mut stderr = timed(stderr).into_iter().peekable();
+    let mut merged = Vec::with_capacity(stdout.len() + stderr.len());
+    loop {
+        let take_stdout = match (stdout.peek(), stderr.peek()) {
+            (Some((out_time, _)), Some((err_time, _))) => out_time <= err_time,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        let (stream, (_, line)) = if take_stdout {
+            (LogStream::Stdout, stdout.next().unwrap())
+        } else {
+            (LogStream::Stderr, stderr.next().unwrap())
+        };
+        merged.push((stream, line));
+    }
+    merged
+}
+
+/// Reads lines appended to `path` since `offset` and advances `offset`.
+///
+/// A trailing line without a newline is left for the next call unless
+/// `include_partial` is set. If the file shrank (e.g. after rotation),
+/// reading restarts from the beginning.
+pub fn read_new_lines(
+    path: &Path,
+    offset: &mut u64,
+    include_partial: bool,
+) -> io::Result<Vec<String>> {
+    use std::io::{Read, Seek};
+
+    let mut file = match std::fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let len = file.metadata()?.len();
+    if len < *offset {
+        *offset = 0;
+    }
+    if len == *offset {
+        return Ok(Vec::new());
+    }
+
+    file.seek(io::SeekFrom::Start(*offset))?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+
+    let complete = if include_partial {
+        buf.len()
+    } else {
+        match buf.iter().rposition(|&b| b == b'\n') {
+            Some(i) => i + 1,
+            None => return Ok(Vec::new()),
+        }
+    };
+    *offset += complete as u64;
+
+    Ok(String::from_utf8_lossy(&buf[..complete])
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
 pub async fn rotate_log(path: &Path, max_rotations: u32) -> io::Result<()> {
     // Delete the oldest rotated file if it exists
     let oldest = rotated_path(path, max_rotations);
@@ -101,99 +649,945 @@ pub async fn rotate_log(path: &Path, max_rotations: u32) -> io::Result<()> {
     Ok(())
 }
 
-fn rotated_path(path: &Path, n: u32) -> std::path::PathBuf {
+pub fn rotated_path(path: &Path, n: u32) -> std::path::PathBuf {
     let mut p = path.as_os_str().to_owned();
     p.push(format!(".{n}"));
     p.into()
 }
 
+/// Log files under `dir`, namespace subdirectories included, that belong to
+/// none of `kept`, like those of processes that were deleted. Rotated files
+/// go with the log they were rotated from.
+pub fn orphaned_logs(dir: &Path, kept: &[LogFiles]) -> Vec<PathBuf> {
+    let kept: Vec<&Path> = kept
+        .iter()
+        .flat_map(|logs| [logs.stdout.as_path(), logs.stderr.as_path()])
+        .collect();
+    let mut orphans = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let current = match path.extension().and_then(|ext| ext.to_str()) {
+                Some(n) if n.parse::<u32>().is_ok() => path.with_extension(""),
+                _ => path.clone(),
+            };
+            if current.extension().is_some_and(|ext| ext == "log")
+                && !kept.iter().any(|kept| *kept == current)
+            {
+                orphans.push(path);
+            }
+        }
+    }
+    orphans.sort();
+    orphans
+}
+
 pub fn spawn_log_copier(
-    name: String,
     stream: LogStream,
     reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
-    log_path: std::path::PathBuf,
+    files: &LogFiles,
     log_date_format: Option<String>,
+    limits: &LogLimits,
+    control: &LogControl,
     broadcaster: broadcast::Sender<LogEntry>,
 ) {
+    let files = files.clone();
+    let limits = limits.clone();
+    let control = control.clone();
     tokio::spawn(async move {
-        if let Err(e) =
-            run_log_copier(name, stream, reader, log_path, log_date_format, broadcaster).await
-        {
-            eprintln!("log copier error: {e}");
+        let copied = run_log_copier(
+            stream,
+            reader,
+            &files,
+            log_date_format,
+            &limits,
+            &control,
+            broadcaster,
+        );
+        if let Err(e) = copied.await {
+            tracing::error!("log copier error: {e}");
         }
     });
 }
 
-async fn run_log_copier(
-    _name: String,
+/// Reads lines of a process's output, keeping at most `max_len` bytes of
+/// each. The rest of a long line is skipped as it arrives, so it is never
+/// held in memory whole. A read that is cancelled picks up where it left
+/// off on the next one.
+struct LineReader<R> {
+    reader: TokioBufReader<R>,
+    max_len: usize,
+    buf: Vec<u8>,
+    read: usize,
+    dropped: usize,
+}
+
+impl<R: tokio::io::AsyncRead + Unpin> LineReader<R> {
+    fn new(reader: R, max_len: usize) -> Self {
+        Self {
+            reader: TokioBufReader::new(reader),
+            max_len,
+            buf: Vec::new(),
+            read: 0,
+            dropped: 0,
+        }
+    }
+
+    /// The next line without its newline and whether it ended in one, or
+    /// `None` at EOF.
+    async fn next(&mut self) -> io::Result<Option<(String, bool)>> {
+        loop {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                return Ok((self.read > 0).then(|| self.take(false)));
+            }
+            let (chunk, ended) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (&available[..i], true),
+                None => (available, false),
+            };
+            let kept = chunk.len().min(self.max_len.saturating_sub(self.buf.len()));
+            self.buf.extend_from_slice(&chunk[..kept]);
+            self.dropped += chunk.len() - kept;
+            let consumed = chunk.len() + usize::from(ended);
+            self.reader.consume(consumed);
+            self.read += consumed;
+            if ended {
+                return Ok(Some(self.take(true)));
+            }
+        }
+    }
+
+    fn take(&mut self, ended: bool) -> (String, bool) {
+        // Binary output is kept as far as it goes, invalid bytes replaced
+        let mut line = String::from_utf8_lossy(&self.buf).into_owned();
+        if self.dropped > 0 {
+            line.push_str(&format!("… [pm3: {} bytes truncated]", self.dropped));
+        }
+        self.buf.clear();
+        self.read = 0;
+        self.dropped = 0;
+        (line, ended)
+    }
+}
+
+/// Decides which lines a copier drops under `log_rate_limit` or the disk
+/// quota, and when to log how many it dropped: once the window they were
+/// dropped in is over.
+struct Limiter<'a> {
+    limits: &'a LogLimits,
+    window: Duration,
+    window_start: tokio::time::Instant,
+    logged: u32,
+    dropped: u64,
+    reason: &'static str,
+    over_quota: bool,
+    quota_checked: Option<tokio::time::Instant>,
+    suppressed: Arc<AtomicU64>,
+}
+
+impl<'a> Limiter<'a> {
+    fn new(limits: &'a LogLimits, suppressed: Arc<AtomicU64>) -> Self {
+        Self {
+            limits,
+            window: limits
+                .rate_limit
+                .map_or(SUPPRESSED_REPORT_INTERVAL, |limit| limit.per),
+            window_start: tokio::time::Instant::now(),
+            logged: 0,
+            dropped: 0,
+            reason: "",
+            over_quota: false,
+            quota_checked: None,
+            suppressed,
+        }
+    }
+
+    /// When the dropped lines are due to be reported, if any were dropped.
+    fn report_at(&self) -> Option<tokio::time::Instant> {
+        (self.dropped > 0).then(|| self.window_start + self.window)
+    }
+
+    /// The marker for the lines dropped so far, once it is due or when
+    /// `now` is set, starting a new window.
+    fn take_report(&mut self, now: bool) -> Option<String> {
+        let due = self
+            .report_at()
+            .is_some_and(|at| now || tokio::time::Instant::now() >= at);
+        if !due {
+            return None;
+        }
+        let marker = suppressed_marker(self.dropped, self.reason);
+        self.suppressed.fetch_add(self.dropped, Ordering::Relaxed);
+        self.dropped = 0;
+        self.window_start = tokio::time::Instant::now();
+        self.logged = 0;
+        Some(marker)
+    }
+
+    /// Whether the next line is logged, counting it as dropped if not.
+    async fn admit(&mut self) -> bool {
+        let now = tokio::time::Instant::now();
+        if now >= self.window_start + self.window {
+            self.window_start = now;
+            self.logged = 0;
+        }
+        let reason = if self.over_quota().await {
+            "disk quota"
+        } else if let Some(limit) = self.limits.rate_limit
+            && self.logged >= limit.lines
+        {
+            "log_rate_limit"
+        } else {
+            self.logged += 1;
+            return true;
+        };
+        self.dropped += 1;
+        self.reason = reason;
+        false
+    }
+
+    async fn over_quota(&mut self) -> bool {
+        let Some(flag) = &self.limits.quota_flag else {
+            return false;
+        };
+        if self
+            .quota_checked
+            .is_none_or(|at| at.elapsed() >= QUOTA_CHECK_INTERVAL)
+        {
+            self.over_quota = tokio::fs::try_exists(flag).await.unwrap_or(false);
+            self.quota_checked = Some(tokio::time::Instant::now());
+        }
+        self.over_quota
+    }
+}
+
+/// Appends lines of a stream to its log, rotating it as it grows, and
+/// broadcasts them to followers.
+struct LogWriter<'a> {
     stream: LogStream,
-    reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
-    log_path: std::path::PathBuf,
+    path: &'a Path,
+    tag: Option<String>,
     log_date_format: Option<String>,
+    file: tokio::fs::File,
+    byte_count: u64,
     broadcaster: broadcast::Sender<LogEntry>,
-) -> io::Result<()> {
-    let mut buf_reader = TokioBufReader::new(reader);
-    let mut file = tokio::fs::OpenOptions::new()
+}
+
+async fn open_log(path: &Path) -> io::Result<tokio::fs::File> {
+    tokio::fs::OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&log_path)
-        .await?;
+        .open(path)
+        .await
+}
 
-    let mut byte_count: u64 = {
-        let meta = tokio::fs::metadata(&log_path).await?;
-        meta.len()
-    };
+impl<'a> LogWriter<'a> {
+    async fn open(
+        stream: LogStream,
+        files: &'a LogFiles,
+        log_date_format: Option<String>,
+        broadcaster: broadcast::Sender<LogEntry>,
+    ) -> io::Result<Self> {
+        let path = files.path(&stream);
+        let file = open_log(path).await?;
+        let byte_count = tokio::fs::metadata(path).await?.len();
+        Ok(Self {
+            tag: files.tag(&stream),
+            stream,
+            path,
+            log_date_format,
+            file,
+            byte_count,
+            broadcaster,
+        })
+    }
 
-    let mut line = String::new();
-    loop {
-        line.clear();
-        let n = buf_reader.read_line(&mut line).await?;
-        if n == 0 {
-            break; // EOF — child exited
-        }
+    /// Closes the log and opens it again at its path, which a tool like
+    /// logrotate may have moved it away from.
+    async fn reopen(&mut self) -> io::Result<()> {
+        self.file.flush().await?;
+        self.file = open_log(self.path).await?;
+        self.byte_count = self.file.metadata().await?.len();
+        Ok(())
+    }
 
-        let formatted = if let Some(ref fmt) = log_date_format {
+    async fn write(&mut self, line: &str, ended: bool) -> io::Result<()> {
+        let mut formatted = self.tag.clone().unwrap_or_default();
+        if let Some(ref fmt) = self.log_date_format {
             let ts = chrono::Local::now().format(fmt);
-            format!("{ts} | {line}")
-        } else {
-            line.clone()
-        };
+            formatted.push_str(&format!("{ts} | "));
+        }
+        formatted.push_str(line);
+        if ended {
+            formatted.push('\n');
+        }
+
+        // Other copiers write to a combined log too, and may have rotated
+        // it: go by its size on disk, and move on to the new file
+        if self.tag.is_some() {
+            let on_disk = tokio::fs::metadata(self.path).await.ok();
+            if !is_same_file(&self.file.metadata().await?, on_disk.as_ref()) {
+                self.file = open_log(self.path).await?;
+            }
+            self.byte_count = on_disk.map_or(0, |meta| meta.len());
+        }
 
         // Check rotation before writing
         let line_bytes = formatted.as_bytes();
-        if byte_count + line_bytes.len() as u64 > LOG_ROTATION_SIZE {
+        if self.byte_count + line_bytes.len() as u64 > LOG_ROTATION_SIZE {
             // Flush and close current file, rotate, reopen
-            file.flush().await?;
-            drop(file);
-            rotate_log(&log_path, LOG_ROTATION_KEEP).await?;
-            file = tokio::fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&log_path)
-                .await?;
-            byte_count = 0;
+            self.file.flush().await?;
+            rotate_log(self.path, LOG_ROTATION_KEEP).await?;
+            self.file = open_log(self.path).await?;
+            self.byte_count = 0;
         }
 
-        file.write_all(line_bytes).await?;
-        file.flush().await?;
-        byte_count += line_bytes.len() as u64;
+        self.file.write_all(line_bytes).await?;
+        self.file.flush().await?;
+        self.byte_count += line_bytes.len() as u64;
 
         // Broadcast to any follow subscribers (ignore if no receivers)
-        let _ = broadcaster.send(LogEntry {
-            stream: stream.clone(),
+        let _ = self.broadcaster.send(LogEntry {
+            stream: self.stream.clone(),
             line: line.trim_end().to_string(),
+            time: chrono::Utc::now(),
         });
+        Ok(())
+    }
+}
+
+async fn run_log_copier(
+    stream: LogStream,
+    reader: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    files: &LogFiles,
+    log_date_format: Option<String>,
+    limits: &LogLimits,
+    control: &LogControl,
+    broadcaster: broadcast::Sender<LogEntry>,
+) -> io::Result<()> {
+    let mut writer = LogWriter::open(stream, files, log_date_format, broadcaster).await?;
+    let mut lines = LineReader::new(reader, limits.max_line_len);
+    let mut limiter = Limiter::new(limits, control.suppressed.clone());
+    let mut reopen = control.reopen.subscribe();
+
+    loop {
+        let report_at = limiter.report_at();
+        let next = tokio::select! {
+            next = lines.next() => Some(next?),
+            // While lines are being dropped, wake up to report them even if
+            // the process goes quiet
+            _ = tokio::time::sleep_until(report_at.unwrap_or_else(tokio::time::Instant::now)),
+                if report_at.is_some() => None,
+            Ok(()) = reopen.changed() => {
+                writer.reopen().await?;
+                continue;
+            }
+        };
+        if let Some(marker) = limiter.take_report(false) {
+            writer.write(&marker, true).await?;
+        }
+        let Some(next) = next else {
+            continue; // Only the report was due
+        };
+        let Some((line, ended)) = next else {
+            break; // EOF — child exited
+        };
+        if limiter.admit().await {
+            writer.write(&line, ended).await?;
+        }
+    }
+    if let Some(marker) = limiter.take_report(true) {
+        writer.write(&marker, true).await?;
     }
 
-    file.flush().await?;
+    writer.file.flush().await?;
     Ok(())
 }
 
+/// Whether an open log is still the one at its path, which is gone or
+/// another file once the log was rotated.
+fn is_same_file(open: &std::fs::Metadata, on_disk: Option<&std::fs::Metadata>) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        on_disk.is_some_and(|on_disk| (open.dev(), open.ino()) == (on_disk.dev(), on_disk.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        on_disk.is_some_and(|on_disk| open.len() <= on_disk.len())
+    }
+}
+
+/// Starts `pm3 log-relay`, which copies a process's output from `stdout`
+/// and `stderr` to its `logs` itself. Unlike a copier in the daemon, it
+/// keeps the output flowing to the logs while the daemon is restarted.
+#[cfg(unix)]
+pub fn spawn_relay(
+    exe: &Path,
+    stdout: std::os::fd::OwnedFd,
+    stderr: std::os::fd::OwnedFd,
+    logs: &LogFiles,
+    log_date_format: Option<&str>,
+    limits: &LogLimits,
+) -> io::Result<tokio::process::Child> {
+    let mut cmd = tokio::process::Command::new(exe);
+    cmd.arg("log-relay")
+        .arg("--stdout-log")
+        .arg(&logs.stdout)
+        .arg("--stderr-log")
+        .arg(&logs.stderr);
+    if let Some(instance) = &logs.instance {
+        cmd.arg("--log-instance").arg(instance);
+    }
+    if let Some(format) = log_date_format {
+        cmd.arg("--log-date-format").arg(format);
+    }
+    cmd.arg("--log-max-line-len")
+        .arg(limits.max_line_len.to_string());
+    if let Some(rate_limit) = limits.rate_limit {
+        cmd.arg("--log-rate-limit").arg(rate_limit.to_string());
+    }
+    if let Some(flag) = &limits.quota_flag {
+        cmd.arg("--log-quota-flag").arg(flag);
+    }
+    cmd.stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped());
+    // Out of the daemon's process group, so Ctrl-C on a foreground daemon
+    // leaves it to see the process's last words
+    cmd.process_group(0);
+    crate::sys::pass_fds(
+        &mut cmd,
+        [(stdout, RELAY_STDOUT_FD), (stderr, RELAY_STDERR_FD)],
+    );
+    cmd.spawn()
+}
+
+/// Runs `pm3 log-relay`: copies the output passed in on `RELAY_STDOUT_FD`
+/// and `RELAY_STDERR_FD` to the logs until the process closes it, and sends
+/// the lines to the daemon on stdout for as long as it reads them.
+#[cfg(unix)]
+pub async fn run_relay(
+    logs: LogFiles,
+    log_date_format: Option<String>,
+    limits: LogLimits,
+) -> io::Result<()> {
+    // SAFETY: the daemon starts relays with these fds open, and nothing else
+    // in this process owns them
+    let [stdout, stderr] = [RELAY_STDOUT_FD, RELAY_STDERR_FD]
+        .map(|fd| unsafe { crate::sys::adopt_fd(fd) }.and_then(crate::sys::fd_reader));
+    let (tx, rx) = broadcast::channel(RELAY_FEED_CAPACITY);
+    let feed = tokio::spawn(send_relayed_lines(rx, tokio::io::stdout()));
+    let control = LogControl::default();
+    crate::sys::on_reopen_signal({
+        let control = control.clone();
+        move || control.reopen()
+    })?;
+    let (stdout, stderr) = tokio::join!(
+        run_log_copier(
+            LogStream::Stdout,
+            stdout?,
+            &logs,
+            log_date_format.clone(),
+            &limits,
+            &control,
+            tx.clone(),
+        ),
+        run_log_copier(
+            LogStream::Stderr,
+            stderr?,
+            &logs,
+            log_date_format,
+            &limits,
+            &control,
+            tx,
+        ),
+    );
+    // A daemon that stopped reading without closing the feed can't hold the
+    // relay up for long
+    let _ = tokio::time::timeout(Duration::from_secs(5), feed).await;
+    stdout.and(stderr)
+}
+
+/// Writes the lines broadcast on `rx` to `feed` until the copiers are done
+/// or the daemon stops reading. Lines the daemon falls too far behind on are
+/// left out of the feed, but not the logs.
+async fn send_relayed_lines(
+    mut rx: broadcast::Receiver<LogEntry>,
+    mut feed: impl tokio::io::AsyncWrite + Unpin,
+) {
+    loop {
+        let entry = match rx.recv().await {
+            Ok(entry) => entry,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        let relayed = RelayedLine {
+            stream: entry.stream,
+            line: entry.line,
+            time: entry.time.timestamp_millis(),
+        };
+        let Ok(mut json) = serde_json::to_string(&relayed) else {
+            continue;
+        };
+        json.push('\n');
+        if feed.write_all(json.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Broadcasts the lines a relay sends on its stdout, `feed`, for `pm3 log
+/// --follow`, until the relay is done or the process gets a new broadcaster
+/// on restart, then waits for `relay_exited`. The lines the relay reports
+/// dropping are added to `suppressed`.
+pub fn spawn_relay_feed(
+    feed: impl tokio::io::AsyncRead + Unpin + Send + 'static,
+    broadcaster: broadcast::WeakSender<LogEntry>,
+    suppressed: Arc<AtomicU64>,
+    relay_exited: impl std::future::Future<Output = ()> + Send + 'static,
+) {
+    tokio::spawn(async move {
+        let mut lines = TokioBufReader::new(feed).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Some(broadcaster) = broadcaster.upgrade() else {
+                break;
+            };
+            let Ok(relayed) = serde_json::from_str::<RelayedLine>(&line) else {
+                continue;
+            };
+            if let Some(count) = parse_suppressed_marker(&relayed.line) {
+                suppressed.fetch_add(count, Ordering::Relaxed);
+            }
+            let time = chrono::DateTime::from_timestamp_millis(relayed.time)
+                .unwrap_or_else(chrono::Utc::now);
+            let _ = broadcaster.send(LogEntry {
+                stream: relayed.stream,
+                line: relayed.line,
+                time,
+            });
+        }
+        // Closing the feed tells the relay to stop sending
+        drop(lines);
+        relay_exited.await;
+    });
+}
+
+/// Broadcasts the lines a relay appends to a process's `logs`, for `pm3 log
+/// --follow`, until `relay_exited` resolves or the process gets a new
+/// broadcaster on restart. For relays this daemon has no feed from, as it
+/// didn't start them. Lines are sent without the timestamp the relay
+/// prefixed them with, as a copier in the daemon sends them. The lines the
+/// relay reports dropping are added to `suppressed`.
+pub fn spawn_log_follower(
+    logs: LogFiles,
+    log_date_format: Option<String>,
+    broadcaster: broadcast::WeakSender<LogEntry>,
+    suppressed: Arc<AtomicU64>,
+    relay_exited: impl std::future::Future<Output = ()> + Send + 'static,
+) {
+    // The prefix is the formatted time and " | ", which the format may
+    // contain too
+    let prefix_parts = log_date_format.map(|format| format.matches(" | ").count() + 1);
+    let mut offsets = logs.offsets();
+    let changed = Arc::new(tokio::sync::Notify::new());
+    let watcher = watch_logs(&logs, changed.clone());
+    let interval = match watcher {
+        Some(_) => FOLLOW_IDLE_INTERVAL,
+        None => FOLLOW_INTERVAL,
+    };
+    tokio::spawn(async move {
+        let _watcher = watcher;
+        let mut relay_exited = std::pin::pin!(relay_exited);
+        let mut exited = false;
+        loop {
+            tokio::select! {
+                _ = changed.notified() => {}
+                _ = tokio::time::sleep(interval) => {}
+                _ = &mut relay_exited => exited = true,
+            }
+            let Some(broadcaster) = broadcaster.upgrade() else {
+                return;
+            };
+            // Read even with nobody following, to count dropped lines
+            if let Ok(lines) = logs.read_new_lines(&mut offsets, exited) {
+                let time = chrono::Utc::now();
+                for (stream, mut line) in lines {
+                    if let Some(parts) = prefix_parts
+                        && let Some(unprefixed) = line.splitn(parts + 1, " | ").nth(parts)
+                    {
+                        line = unprefixed.to_string();
+                    }
+                    if let Some(count) = parse_suppressed_marker(&line) {
+                        suppressed.fetch_add(count, Ordering::Relaxed);
+                    }
+                    let _ = broadcaster.send(LogEntry { stream, line, time });
+                }
+            }
+            if exited {
+                return;
+            }
+        }
+    });
+}
+
+/// Notifies `changed` when either of `logs` changes, or `None` if the
+/// platform's file notifications can't be set up.
+fn watch_logs(
+    logs: &LogFiles,
+    changed: Arc<tokio::sync::Notify>,
+) -> Option<notify::RecommendedWatcher> {
+    use notify::Watcher;
+    let files = [&logs.stdout, &logs.stderr];
+    let names: Vec<std::ffi::OsString> = files
+        .iter()
+        .filter_map(|path| path.file_name())
+        .map(Into::into)
+        .collect();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        use notify::EventKind;
+        // Not the opens and reads of the follower itself
+        let written = |event: &notify::Event| {
+            matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            )
+        };
+        let ours = |path: &PathBuf| {
+            path.file_name()
+                .is_some_and(|name| names.iter().any(|n| n == name))
+        };
+        if event.is_ok_and(|event| written(&event) && event.paths.iter().any(ours)) {
+            changed.notify_one();
+        }
+    })
+    .ok()?;
+    // The directories, as a log is replaced when it's rotated
+    for file in files {
+        let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty())?;
+        watcher
+            .watch(dir, notify::RecursiveMode::NonRecursive)
+            .ok()?;
+    }
+    Some(watcher)
+}
+
+/// Resolves once a relay inherited from the daemon that upgraded to this one
+/// exits, reaping it.
+pub async fn wait_for_relay(pid: u32) {
+    use crate::sys::Reaped;
+    loop {
+        match crate::sys::reap(pid) {
+            Reaped::Running => {}
+            Reaped::NotChild if crate::sys::is_pid_alive(pid) => {}
+            _ => return,
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::Write;
 
+    #[test]
+    fn test_read_new_lines_advances_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("task.log");
+        std::fs::write(&path, "one\ntwo\npar").unwrap();
+
+        let mut offset = 0;
+        let lines = read_new_lines(&path, &mut offset, false).unwrap();
+        assert_eq!(lines, vec!["one", "two"]);
+        assert_eq!(offset, 8);
+
+        assert!(
+            read_new_lines(&path, &mut offset, false)
+                .unwrap()
+                .is_empty()
+        );
+        let lines = read_new_lines(&path, &mut offset, true).unwrap();
+        assert_eq!(lines, vec!["par"]);
+
+        // Truncated file is read from the start again
+        std::fs::write(&path, "new\n").unwrap();
+        let lines = read_new_lines(&path, &mut offset, false).unwrap();
+        assert_eq!(lines, vec!["new"]);
+    }
+
+    #[tokio::test]
+    async fn test_log_follower_broadcasts_appended_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let stdout_log = dir.path().join("web-out.log");
+        let stderr_log = dir.path().join("web-err.log");
+        std::fs::write(&stdout_log, "12:00 | before\n").unwrap();
+        let (tx, mut rx) = broadcast::channel(16);
+        let (exited_tx, exited_rx) = tokio::sync::oneshot::channel::<()>();
+        spawn_log_follower(
+            LogFiles {
+                stdout: stdout_log.clone(),
+                stderr: stderr_log,
+                instance: None,
+            },
+            Some("%H:%M".to_string()),
+            tx.downgrade(),
+            Arc::default(),
+            async move {
+                let _ = exited_rx.await;
+            },
+        );
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&stdout_log)
+            .unwrap();
+        write!(file, "12:01 | a | b\n12:02 | last").unwrap();
+        let entry = rx.recv().await.unwrap();
+        assert_eq!(entry.stream, LogStream::Stdout);
+        assert_eq!(entry.line, "a | b");
+
+        // The unterminated last line is sent once the relay exits
+        exited_tx.send(()).unwrap();
+        assert_eq!(rx.recv().await.unwrap().line, "last");
+    }
+
+    #[test]
+    fn test_log_files_for_config() {
+        let paths = Paths::with_base(PathBuf::from("/tmp/pm3-test"));
+        let config = |extra: &str| {
+            let toml = format!("[web]\ncommand = \"node app.js\"\ninstances = 2\n{extra}");
+            crate::config::parse_config(&toml)
+                .unwrap()
+                .remove("web")
+                .unwrap()
+        };
+
+        let logs = LogFiles::new(&paths, "web:0", &config(""));
+        assert!(logs.stdout.ends_with("logs/web:0-out.log"));
+        assert!(logs.stderr.ends_with("logs/web:0-err.log"));
+        assert!(!logs.is_combined());
+        assert_eq!(logs.tag(&LogStream::Stdout), None);
+
+        let logs = LogFiles::new(&paths, "web:0", &config("log_combine = true"));
+        assert!(logs.stdout.ends_with("logs/web:0.log"));
+        assert!(logs.is_combined());
+        assert_eq!(logs.tag(&LogStream::Stderr).as_deref(), Some("[err] "));
+
+        let logs = LogFiles::new(&paths, "web:1", &config("merge_logs = true"));
+        assert!(logs.stdout.ends_with("logs/web.log"));
+        assert!(logs.is_combined());
+        assert_eq!(
+            logs.tag(&LogStream::Stdout).as_deref(),
+            Some("[web:1 out] ")
+        );
+
+        let logs = LogFiles::new(
+            &paths,
+            "web:1",
+            &config("cwd = \"/srv/web\"\nstdout_log = \"logs/web.log\""),
+        );
+        assert_eq!(logs.stdout, PathBuf::from("/srv/web/logs/web-1.log"));
+        assert!(logs.stderr.ends_with("logs/web:1-err.log"));
+
+        // The same file for both is a combined log
+        let logs = LogFiles::new(
+            &paths,
+            "web",
+            &config("stdout_log = \"/var/log/web\"\nstderr_log = \"/var/log/web\""),
+        );
+        assert_eq!(logs.stdout, PathBuf::from("/var/log/web"));
+        assert!(logs.is_combined());
+    }
+
+    #[test]
+    fn test_untag_combined_lines() {
+        let logs = LogFiles {
+            stdout: PathBuf::from("web.log"),
+            stderr: PathBuf::from("web.log"),
+            instance: Some("web:1".to_string()),
+        };
+        assert_eq!(
+            logs.untag("[web:1 err] 12:00 | oops"),
+            Some((LogStream::Stderr, "12:00 | oops"))
+        );
+        assert_eq!(logs.untag("[web:0 out] hello"), None);
+        assert_eq!(
+            logs.untag("[out] hello"),
+            Some((LogStream::Stdout, "hello"))
+        );
+        // Hook output isn't tagged, nor is a line that only looks like it
+        assert_eq!(
+            logs.untag("migrating"),
+            Some((LogStream::Stdout, "migrating"))
+        );
+        assert_eq!(
+            logs.untag("[INFO] ready"),
+            Some((LogStream::Stdout, "[INFO] ready"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_combined_copiers_tag_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("web.log");
+        let logs = LogFiles {
+            stdout: path.clone(),
+            stderr: path.clone(),
+            instance: None,
+        };
+        let (tx, _rx) = broadcast::channel(16);
+        let reader = |data: &'static str| std::io::Cursor::new(data.as_bytes());
+        let (limits, control) = (LogLimits::default(), LogControl::default());
+        let mut offsets = logs.offsets();
+        let (stdout, stderr) = tokio::join!(
+            run_log_copier(
+                LogStream::Stdout,
+                reader("one\ntwo\n"),
+                &logs,
+                None,
+                &limits,
+                &control,
+                tx.clone(),
+            ),
+            run_log_copier(
+                LogStream::Stderr,
+                reader("oops\n"),
+                &logs,
+                None,
+                &limits,
+                &control,
+                tx,
+            ),
+        );
+        stdout.unwrap();
+        stderr.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let mut written: Vec<&str> = content.lines().collect();
+        written.sort();
+        assert_eq!(written, vec!["[err] oops", "[out] one", "[out] two"]);
+
+        let mut lines = logs.read_new_lines(&mut offsets, false).unwrap();
+        lines.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            lines,
+            vec![
+                (LogStream::Stdout, "one".to_string()),
+                (LogStream::Stderr, "oops".to_string()),
+                (LogStream::Stdout, "two".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_time() {
+        let format = "%Y-%m-%d %H:%M:%S";
+        assert_eq!(
+            line_time("2024-05-01 12:00:03 | ready", format),
+            chrono::NaiveDate::from_ymd_opt(2024, 5, 1).and_then(|d| d.and_hms_opt(12, 0, 3))
+        );
+        // The message may contain the separator too
+        assert!(line_time("2024-05-01 12:00:03 | a | b", format).is_some());
+        assert!(line_time("12:00:03 | ready", "%H:%M:%S").is_some());
+        assert!(line_time("  at main.rs:3", format).is_none());
+        // Lines of a combined log start with their stream
+        assert!(line_time("[err] 2024-05-01 12:00:03 | oops", format).is_some());
+        // Formats containing the separator
+        assert!(line_time("2024-05-01 | 12:00:03 | ready", "%Y-%m-%d | %H:%M:%S").is_some());
+    }
+
+    #[test]
+    fn test_parse_log_time() {
+        let now = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(12, 30, 0)
+            .unwrap();
+        let at = |h, m| now.date().and_hms_opt(h, m, 0).unwrap();
+        assert_eq!(parse_log_time("15m", now), Ok(at(12, 15)));
+        assert_eq!(parse_log_time("2024-05-01 12:00", now), Ok(at(12, 0)));
+        assert_eq!(parse_log_time("2024-05-01", now), Ok(at(0, 0)));
+        assert_eq!(parse_log_time("09:45", now), Ok(at(9, 45)));
+        assert!(parse_log_time("yesterday", now).is_err());
+    }
+
+    #[test]
+    fn test_time_range_filter() {
+        let day = chrono::NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let range = TimeRange {
+            since: day.and_hms_opt(10, 0, 2),
+            until: day.and_hms_opt(10, 0, 4),
+        };
+        let lines = [
+            "2024-05-01 10:00:01 | before",
+            "  continued",
+            "2024-05-01 10:00:03 | inside",
+            "  continued",
+            "2024-05-01 10:00:05 | after",
+        ]
+        .map(String::from)
+        .to_vec();
+        assert_eq!(
+            range.filter(lines, "%Y-%m-%d %H:%M:%S"),
+            vec!["2024-05-01 10:00:03 | inside", "  continued"]
+        );
+    }
+
+    #[test]
+    fn test_interleave_by_timestamp() {
+        let lines = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let stdout = lines(&["10:00:01 | start", "10:00:04 | done"]);
+        let stderr = lines(&["10:00:02 | warn", "  detail", "10:00:05 | oops"]);
+
+        let merged = interleave(stdout.clone(), stderr.clone(), Some("%H:%M:%S"));
+        let order: Vec<&str> = merged.iter().map(|(_, line)| line.as_str()).collect();
+        assert_eq!(
+            order,
+            vec![
+                "10:00:01 | start",
+                "10:00:02 | warn",
+                "  detail",
+                "10:00:04 | done",
+                "10:00:05 | oops"
+            ]
+        );
+        assert_eq!(merged[1].0, LogStream::Stderr);
+
+        // Without timestamps, stdout then stderr
+        let merged = interleave(stdout, stderr, None);
+        assert_eq!(
+            merged[2],
+            (LogStream::Stderr, "10:00:02 | warn".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tail_rotated_continues_into_rotations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(rotated_path(&path, 2), "a\nb\n").unwrap();
+        std::fs::write(rotated_path(&path, 1), "c\nd\n").unwrap();
+        std::fs::write(&path, "e\n").unwrap();
+
+        assert_eq!(tail_rotated(&path, 1).unwrap(), vec!["e"]);
+        assert_eq!(tail_rotated(&path, 4).unwrap(), vec!["b", "c", "d", "e"]);
+        assert_eq!(
+            tail_rotated(&path, 100).unwrap(),
+            vec!["a", "b", "c", "d", "e"]
+        );
+
+        // A freshly rotated log has no current file yet
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(tail_rotated(&path, 3).unwrap(), vec!["b", "c", "d"]);
+    }
+
     #[test]
     fn test_tail_file_empty() {
         let dir = tempfile::tempdir().unwrap();
@@ -320,6 +1714,14 @@ mod tests {
         // r3 was deleted
     }
 
+    fn separate_logs(stdout: &Path) -> LogFiles {
+        LogFiles {
+            stdout: stdout.to_path_buf(),
+            stderr: stdout.with_extension("err"),
+            instance: None,
+        }
+    }
+
     /// Helper: pipe `lines` through `run_log_copier` with the given format,
     /// return the resulting log file contents.
     async fn run_copier_with_format(fmt: Option<&str>, lines: &[&str]) -> String {
@@ -337,11 +1739,12 @@ mod tests {
         let reader = tokio::io::BufReader::new(std::io::Cursor::new(input.into_bytes()));
 
         run_log_copier(
-            "test".to_string(),
             LogStream::Stdout,
             reader,
-            log_path.clone(),
+            &separate_logs(&log_path),
             fmt.map(|s| s.to_string()),
+            &LogLimits::default(),
+            &LogControl::default(),
             tx,
         )
         .await
@@ -408,11 +1811,12 @@ mod tests {
         let (tx, _rx) = broadcast::channel(16);
         let reader = tokio::io::BufReader::new(std::io::Cursor::new(data));
         run_log_copier(
-            "test".into(),
             LogStream::Stdout,
             reader,
-            log_path.clone(),
+            &separate_logs(&log_path),
             None,
+            &LogLimits::default(),
+            &LogControl::default(),
             tx,
         )
         .await
@@ -500,4 +1904,319 @@ mod tests {
             );
         }
     }
+
+    async fn run_copier_with_max_len(data: &[u8], max_line_len: usize) -> (String, Vec<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("test.log");
+        let (tx, mut rx) = broadcast::channel(16);
+        let reader = std::io::Cursor::new(data.to_vec());
+        run_log_copier(
+            LogStream::Stdout,
+            reader,
+            &separate_logs(&log_path),
+            None,
+            &LogLimits {
+                max_line_len,
+                ..LogLimits::default()
+            },
+            &LogControl::default(),
+            tx,
+        )
+        .await
+        .unwrap();
+        let mut sent = Vec::new();
+        while let Ok(entry) = rx.try_recv() {
+            sent.push(entry.line);
+        }
+        (std::fs::read_to_string(&log_path).unwrap(), sent)
+    }
+
+    #[tokio::test]
+    async fn test_long_lines_are_truncated() {
+        let (content, sent) = run_copier_with_max_len(b"0123456789\nshort\nabcdefghij", 4).await;
+        assert_eq!(
+            content,
+            "0123… [pm3: 6 bytes truncated]\nshor… [pm3: 1 bytes truncated]\nabcd… [pm3: 6 bytes truncated]"
+        );
+        assert_eq!(sent[0], "0123… [pm3: 6 bytes truncated]");
+
+        // Lines longer than the reader's buffer are cut all the same
+        let long = "x".repeat(100_000) + "\nnext\n";
+        let (content, _) = run_copier_with_max_len(long.as_bytes(), 10).await;
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines,
+            vec!["xxxxxxxxxx… [pm3: 99990 bytes truncated]", "next"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_binary_output_is_logged_lossily() {
+        let (content, sent) = run_copier_with_max_len(b"ok\n\xff\xfe\x00bin\nafter\n", 64).await;
+        assert_eq!(content, "ok\n\u{fffd}\u{fffd}\0bin\nafter\n");
+        assert_eq!(sent, vec!["ok", "\u{fffd}\u{fffd}\0bin", "after"]);
+    }
+
+    #[test]
+    fn test_tail_file_invalid_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.log");
+        std::fs::write(&path, b"one\n\xffbad\n").unwrap();
+        assert_eq!(tail_file(&path, 2).unwrap(), vec!["one", "\u{fffd}bad"]);
+    }
+
+    #[test]
+    fn test_rate_limit_parse() {
+        let limit = RateLimit::parse("1000/s").unwrap();
+        assert_eq!((limit.lines, limit.per), (1000, Duration::from_secs(1)));
+        let limit = RateLimit::parse("100/10s").unwrap();
+        assert_eq!((limit.lines, limit.per), (100, Duration::from_secs(10)));
+        assert_eq!(RateLimit::parse(&limit.to_string()), Ok(limit));
+        for bad in ["1000", "0/s", "x/s", "10/", "10/soon"] {
+            assert!(RateLimit::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_parse_suppressed_marker() {
+        let marker = suppressed_marker(42, "log_rate_limit");
+        assert_eq!(marker, "[pm3: 42 lines suppressed (log_rate_limit)]");
+        assert_eq!(parse_suppressed_marker(&marker), Some(42));
+        assert_eq!(parse_suppressed_marker("42 lines suppressed"), None);
+    }
+
+    async fn run_copier_with_limits(data: &str, limits: LogLimits) -> (String, u64) {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("test.log");
+        let (tx, _rx) = broadcast::channel(16);
+        let control = LogControl::default();
+        run_log_copier(
+            LogStream::Stdout,
+            std::io::Cursor::new(data.as_bytes().to_vec()),
+            &separate_logs(&log_path),
+            None,
+            &limits,
+            &control,
+            tx,
+        )
+        .await
+        .unwrap();
+        let content = std::fs::read_to_string(&log_path).unwrap();
+        (content, control.suppressed())
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_drops_lines_with_a_marker() {
+        let limits = LogLimits {
+            rate_limit: Some(RateLimit::parse("3/1h").unwrap()),
+            ..LogLimits::default()
+        };
+        let (content, suppressed) = run_copier_with_limits("1\n2\n3\n4\n5\n6\n", limits).await;
+        assert_eq!(
+            content,
+            "1\n2\n3\n[pm3: 3 lines suppressed (log_rate_limit)]\n"
+        );
+        assert_eq!(suppressed, 3);
+    }
+
+    #[tokio::test]
+    async fn test_over_quota_drops_all_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let flag = dir.path().join("over-quota");
+        std::fs::write(&flag, "").unwrap();
+        let limits = LogLimits {
+            quota_flag: Some(flag),
+            ..LogLimits::default()
+        };
+        let (content, suppressed) = run_copier_with_limits("a\nb\n", limits).await;
+        assert_eq!(content, "[pm3: 2 lines suppressed (disk quota)]\n");
+        assert_eq!(suppressed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_suppressed_lines_are_reported_when_the_window_ends() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("test.log");
+        let (tx, _rx) = broadcast::channel(16);
+        let control = LogControl::default();
+        let (mut output, reader) = tokio::io::duplex(1024);
+        let limits = LogLimits {
+            rate_limit: Some(RateLimit::parse("2/50ms").unwrap()),
+            ..LogLimits::default()
+        };
+        let logs = separate_logs(&log_path);
+        let copier = tokio::spawn({
+            let control = control.clone();
+            async move {
+                run_log_copier(
+                    LogStream::Stdout,
+                    reader,
+                    &logs,
+                    None,
+                    &limits,
+                    &control,
+                    tx,
+                )
+                .await
+            }
+        });
+
+        output.write_all(b"1\n2\n3\n4\n").await.unwrap();
+        // The process goes quiet, and the marker is written all the same
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            std::fs::read_to_string(&log_path).unwrap(),
+            "1\n2\n[pm3: 2 lines suppressed (log_rate_limit)]\n"
+        );
+        assert_eq!(control.suppressed(), 2);
+
+        output.write_all(b"5\n").await.unwrap();
+        drop(output);
+        copier.await.unwrap().unwrap();
+        assert!(
+            std::fs::read_to_string(&log_path)
+                .unwrap()
+                .ends_with("]\n5\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_relay_feed_broadcasts_sent_lines() {
+        let (relay_tx, relay_rx) = broadcast::channel(16);
+        let (feed_in, feed_out) = tokio::io::duplex(1024);
+        let sender = tokio::spawn(send_relayed_lines(relay_rx, feed_in));
+        let (tx, mut rx) = broadcast::channel(16);
+        let suppressed = Arc::new(AtomicU64::new(0));
+        let (exited_tx, exited_rx) = tokio::sync::oneshot::channel::<()>();
+        spawn_relay_feed(feed_out, tx.downgrade(), suppressed.clone(), async move {
+            let _ = exited_tx.send(());
+        });
+
+        let time = chrono::DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+        for line in ["a | b", "[pm3: 7 lines suppressed (log_rate_limit)]"] {
+            relay_tx
+                .send(LogEntry {
+                    stream: LogStream::Stderr,
+                    line: line.to_string(),
+                    time,
+                })
+                .unwrap();
+        }
+        let entry = rx.recv().await.unwrap();
+        assert_eq!(entry.stream, LogStream::Stderr);
+        assert_eq!(entry.line, "a | b");
+        assert_eq!(entry.time, time);
+        rx.recv().await.unwrap();
+        assert_eq!(suppressed.load(Ordering::Relaxed), 7);
+
+        // The relay is waited for once it's done sending
+        drop(relay_tx);
+        sender.await.unwrap();
+        exited_rx.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_log_follower_counts_relayed_markers() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = separate_logs(&dir.path().join("web-out.log"));
+        std::fs::write(&logs.stdout, "").unwrap();
+        let (tx, _rx) = broadcast::channel(16);
+        let suppressed = Arc::new(AtomicU64::new(0));
+        let (exited_tx, exited_rx) = tokio::sync::oneshot::channel::<()>();
+        spawn_log_follower(
+            logs.clone(),
+            None,
+            tx.downgrade(),
+            suppressed.clone(),
+            async move {
+                let _ = exited_rx.await;
+            },
+        );
+
+        std::fs::write(
+            &logs.stdout,
+            "line\n[pm3: 7 lines suppressed (log_rate_limit)]\n",
+        )
+        .unwrap();
+        exited_tx.send(()).unwrap();
+        for _ in 0..50 {
+            if suppressed.load(Ordering::Relaxed) > 0 {
+                break;
+            }
+            tokio::time::sleep(FOLLOW_INTERVAL).await;
+        }
+        assert_eq!(suppressed.load(Ordering::Relaxed), 7);
+    }
+
+    #[tokio::test]
+    async fn test_copier_reopens_moved_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("test.log");
+        let moved = dir.path().join("test.log.1");
+        let (tx, _rx) = broadcast::channel(16);
+        let control = LogControl::default();
+        let (mut output, reader) = tokio::io::duplex(1024);
+        let logs = separate_logs(&log_path);
+        let copier = tokio::spawn({
+            let control = control.clone();
+            async move {
+                let limits = LogLimits::default();
+                run_log_copier(
+                    LogStream::Stdout,
+                    reader,
+                    &logs,
+                    None,
+                    &limits,
+                    &control,
+                    tx,
+                )
+                .await
+            }
+        });
+
+        output.write_all(b"before\n").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // What logrotate does before running `pm3 reopen-logs`
+        std::fs::rename(&log_path, &moved).unwrap();
+        control.reopen();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        output.write_all(b"after\n").await.unwrap();
+        drop(output);
+        copier.await.unwrap().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&moved).unwrap(), "before\n");
+        assert_eq!(std::fs::read_to_string(&log_path).unwrap(), "after\n");
+    }
+
+    #[test]
+    fn test_orphaned_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().to_path_buf());
+        std::fs::create_dir_all(paths.process_log_dir("shop/api")).unwrap();
+        for name in [
+            "web-out.log",
+            "web-out.log.1",
+            "web-err.log",
+            "gone-out.log",
+            "gone-err.log.2",
+            "shop/api-out.log",
+            "notes.txt",
+        ] {
+            std::fs::write(paths.log_dir().join(name), "").unwrap();
+        }
+        let config = crate::config::parse_config("[web]\ncommand = \"x\"")
+            .unwrap()
+            .remove("web")
+            .unwrap();
+        let kept = [LogFiles::new(&paths, "web", &config)];
+
+        assert_eq!(
+            orphaned_logs(&paths.log_dir(), &kept),
+            vec![
+                paths.log_dir().join("gone-err.log.2"),
+                paths.log_dir().join("gone-out.log"),
+                paths.log_dir().join("shop/api-out.log"),
+            ]
+        );
+    }
 }
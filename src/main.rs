@@ -1,60 +1,360 @@
 use clap::{CommandFactory, Parser};
 use comfy_table::{Attribute, Cell, Color, Table, presets::UTF8_FULL_CONDENSED};
 use owo_colors::OwoColorize;
-use pm3::cli::{Cli, Command};
-use pm3::protocol::{ProcessStatus, Request, Response};
+use pm3::cli::{Cli, Command, DaemonCommand, GraphFormat, ListColumn, ListGroupBy};
+use pm3::log::LogStream;
+use pm3::metrics::format_metric;
+use pm3::namespace::DEFAULT_NAMESPACE;
+use pm3::protocol::{
+    AvailabilityReport, ListFilter, ProcessInfo, ProcessStatus, ProcessTree, Request, Response,
+    RestoreProgress, SnapshotInfo, StatsSample, TreeNode,
+};
+use std::cmp::Ordering;
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
     let cli = Cli::parse();
 
-    if cli.daemon {
+    if cli.handover_version {
+        // Asked by a daemon about to upgrade to this binary
+        println!(
+            "{} {}",
+            pm3::upgrade::HANDOVER_VERSION,
+            env!("CARGO_PKG_VERSION")
+        );
+    } else if cli.daemon {
+        // Boot services installed by older versions still run `pm3 --daemon`
         let paths = pm3::paths::Paths::new()?;
-        pm3::daemon::run(paths).await?;
+        let remote = pm3::settings::RemoteSettings {
+            listen: cli.listen,
+            auth_token_file: cli.auth_token_file,
+            ..Default::default()
+        };
+        pm3::daemon::run_with_remote(paths, remote, cli.resurrect, cli.handover).await?;
+    } else if cli.proxy {
+        let base_paths = pm3::paths::Paths::new()?;
+        let paths = pm3::profile::paths_for(&base_paths, cli.profile.as_deref())
+            .map_err(|e| color_eyre::eyre::eyre!(e))?;
+        pm3::client::proxy_stdio(&paths)?;
     } else if let Some(command) = cli.command {
+        if let Command::LogRelay {
+            stdout_log,
+            stderr_log,
+            log_instance,
+            log_date_format,
+            log_max_line_len,
+            log_rate_limit,
+            log_quota_flag,
+        } = command
+        {
+            let logs = pm3::log::LogFiles {
+                stdout: stdout_log,
+                stderr: stderr_log,
+                instance: log_instance,
+            };
+            let limits = pm3::log::LogLimits {
+                max_line_len: log_max_line_len,
+                rate_limit: log_rate_limit,
+                quota_flag: log_quota_flag,
+            };
+            #[cfg(unix)]
+            pm3::log::run_relay(logs, log_date_format, limits).await?;
+            #[cfg(not(unix))]
+            {
+                let _ = (logs, log_date_format, limits);
+                color_eyre::eyre::bail!("log relays are only used on unix");
+            }
+            #[cfg(unix)]
+            return Ok(());
+        }
+        if let Some(host) = cli.host.clone() {
+            pm3::client::use_ssh_host(host, cli.profile.clone());
+        }
+        if cli.no_autostart || cli.autostart {
+            pm3::client::set_autostart(cli.autostart);
+        }
         if matches!(command, Command::Init) {
             let cwd = std::env::current_dir()?;
             pm3::init::run(&cwd)?;
             return Ok(());
         }
-        if matches!(command, Command::Startup) {
-            pm3::startup::install()?;
+        if let Command::Startup { platform } = command {
+            pm3::startup::install(platform)?;
             return Ok(());
         }
-        if matches!(command, Command::Unstartup) {
-            pm3::startup::uninstall()?;
+        if let Command::Unstartup { platform } = command {
+            pm3::startup::uninstall(platform)?;
             return Ok(());
         }
-        let paths = pm3::paths::Paths::new()?;
+        if let Command::Graph { format } = command {
+            let config_path = std::env::current_dir()?.join("pm3.toml");
+            let configs = pm3::config::load_config(&config_path)
+                .map_err(|e| color_eyre::eyre::eyre!("{e}"))?;
+            let configs = pm3::manager::expand_instances(configs);
+            let graph = match format {
+                GraphFormat::Ascii => pm3::deps::render_ascii(&configs),
+                GraphFormat::Dot => pm3::deps::render_dot(&configs),
+            }
+            .map_err(|e| color_eyre::eyre::eyre!("{e}"))?;
+            print!("{graph}");
+            return Ok(());
+        }
+        let base_paths = pm3::paths::Paths::new()?;
+        if matches!(command, Command::Daemons) {
+            let daemons = pm3::profile::list_daemons(&base_paths);
+            if cli.json {
+                println!("{}", serde_json::to_string(&daemons)?);
+            } else {
+                print_daemons(&daemons);
+            }
+            return Ok(());
+        }
+        let paths = pm3::profile::paths_for(&base_paths, cli.profile.as_deref())
+            .map_err(|e| color_eyre::eyre::eyre!(e))?;
+        if let Command::Daemon { action } = command {
+            let remote = cli.host.is_some();
+            return run_daemon_command(action, &paths, remote, cli.handover, cli.json).await;
+        }
         if matches!(command, Command::Tui) {
             pm3::tui::run(&paths)?;
             return Ok(());
         }
-        let request = command_to_request(command)?;
+        // Shutdown hooks save with --if-running, which mustn't start an empty
+        // daemon and overwrite the dump with it
+        if matches!(
+            command,
+            Command::Save {
+                if_running: true,
+                ..
+            }
+        ) && cli.host.is_none()
+            && !pm3::pid::is_daemon_running_sync(&paths)?
+        {
+            println!("{}", "daemon is not running; nothing saved".yellow());
+            return Ok(());
+        }
+        if let Command::DaemonLogs { lines, follow } = command {
+            print_daemon_log(&paths, lines, follow).await?;
+            return Ok(());
+        }
+        if let Command::Report {
+            window,
+            bundle: Some(out),
+            redact,
+            log_lines,
+            ..
+        } = &command
+        {
+            let options = pm3::bundle::BundleOptions {
+                redact: *redact,
+                log_lines: *log_lines,
+                windows: window.clone(),
+            };
+            let files = pm3::bundle::create(&paths, out, &options)?;
+            println!(
+                "{} {} files to {}",
+                "bundled".green(),
+                files.len(),
+                out.display()
+            );
+            return Ok(());
+        }
+        if matches!(command, Command::Ping) {
+            let (response, latency) = pm3::client::ping(&paths)?;
+            if cli.json {
+                let mut value = serde_json::to_value(&response)?;
+                value["latency_ms"] = serde_json::json!(latency.as_secs_f64() * 1000.0);
+                println!("{value}");
+            } else {
+                if let Response::DaemonInfo { info } = &response {
+                    println!(
+                        "{} pid {} in {:.2}ms",
+                        "pong from".green(),
+                        info.pid,
+                        latency.as_secs_f64() * 1000.0
+                    );
+                }
+                print_response(&response);
+                if let Response::DaemonInfo { info } = &response
+                    && info.version != env!("CARGO_PKG_VERSION")
+                {
+                    println!(
+                        "{}",
+                        format!(
+                            "daemon runs pm3 {} but this CLI is {}; run `pm3 daemon upgrade` to upgrade it",
+                            info.version,
+                            env!("CARGO_PKG_VERSION")
+                        )
+                        .yellow()
+                    );
+                }
+            }
+            return Ok(());
+        }
+        if let Command::Info { name, watch: true } = &command {
+            return watch_info(&paths, name, cli.json).await;
+        }
+        let list_view = match &command {
+            Command::List {
+                sort,
+                columns,
+                group_by,
+                ..
+            } => Some((sort.clone(), columns.clone(), *group_by)),
+            _ => None,
+        };
+        let namespace = if cli.all_namespaces {
+            None
+        } else {
+            Some(current_namespace(cli.namespace)?)
+        };
+        let status_check = matches!(command, Command::Status { .. });
+        let export_output = match &command {
+            Command::Export { output } => Some(output.clone()),
+            _ => None,
+        };
+        let request = command_to_request(command, namespace.clone())?;
 
-        if matches!(request, Request::Log { .. }) {
-            // Log uses streaming — read multiple responses until EOF
+        if let Some(output) = export_output {
+            let response = pm3::client::send_request(&paths, &request)?;
+            let configs = match response {
+                Response::Configs { configs } if !cli.json => configs,
+                other => {
+                    if cli.json {
+                        print_response_json(&other);
+                    } else {
+                        print_response(&other);
+                    }
+                    return Ok(());
+                }
+            };
+            if configs.is_empty() {
+                color_eyre::eyre::bail!("no processes to export");
+            }
+            let content = pm3::config::to_toml(&configs, namespace.as_deref())?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content)?;
+                    println!(
+                        "{} {} process(es) to {}",
+                        "exported".green(),
+                        configs.len(),
+                        path.display()
+                    );
+                }
+                None => print!("{content}"),
+            }
+            return Ok(());
+        }
+
+        if status_check {
+            let response = pm3::client::send_request(&paths, &request)?;
+            if cli.json {
+                print_response_json(&response);
+            } else {
+                match &response {
+                    Response::ProcessDetail { info } => println!("{}", info.status),
+                    Response::Error { message } => eprintln!("{message}"),
+                    other => print_response(other),
+                }
+            }
+            std::process::exit(status_exit_code(&response));
+        }
+
+        if let Request::Run { name: ref task, .. } = request {
+            let mut code = 1;
+            pm3::client::send_request_streaming(&paths, &request, |resp| {
+                if let Response::TaskFinished {
+                    status, exit_code, ..
+                } = resp
+                {
+                    code = match status {
+                        ProcessStatus::Succeeded => 0,
+                        _ => exit_code.filter(|c| *c != 0).unwrap_or(1),
+                    };
+                }
+                if cli.json {
+                    print_stream_json(resp, Some(task));
+                } else {
+                    print_response(resp);
+                }
+            })?;
+            std::process::exit(code);
+        }
+
+        if matches!(request, Request::Log { .. } | Request::Events { .. }) {
+            // Log and events stream — read multiple responses until EOF
             if cli.json {
+                let name = match &request {
+                    Request::Log { name, .. } => name.clone(),
+                    _ => None,
+                };
                 pm3::client::send_request_streaming(&paths, &request, |resp| {
-                    print_response_json(resp);
+                    print_stream_json(resp, name.as_deref());
                 })?;
             } else {
-                pm3::client::send_request_streaming(&paths, &request, |resp| {
-                    print_response(resp);
+                // Red stderr tells the streams apart, which --err and --out
+                // make moot
+                let combined = matches!(request, Request::Log { stream: None, .. });
+                pm3::client::send_request_streaming(&paths, &request, |resp| match resp {
+                    Response::LogLine { name, line, .. } if !combined => {
+                        print_log_line(name.as_deref(), line, false)
+                    }
+                    _ => print_response(resp),
                 })?;
             }
         } else {
-            let response = pm3::client::send_request(&paths, &request)?;
+            let mut response = if matches!(request, Request::Start { progress: true, .. }) {
+                send_with_progress(&paths, &request, cli.json)?
+            } else {
+                pm3::client::send_request(&paths, &request)?
+            };
+            if let (Some((Some(sort), _, _)), Response::ProcessList { processes, .. }) =
+                (&list_view, &mut response)
+            {
+                sort_processes(processes, sort);
+            }
             if cli.json {
-                print_response_json(&response);
+                match &response {
+                    // the entries alone, as history printed before the daemon answered it
+                    Response::History { entries } => {
+                        println!("{}", serde_json::to_string(entries)?)
+                    }
+                    _ => print_response_json(&response),
+                }
             } else {
-                print_response(&response);
+                if let Response::ProcessList {
+                    restoring: Some(progress),
+                    ..
+                } = &response
+                {
+                    print_restore_progress(progress);
+                }
+                match (&list_view, &response) {
+                    (
+                        Some((_, _, Some(ListGroupBy::Group))),
+                        Response::ProcessList { processes, .. },
+                    ) => print_group_summaries(processes),
+                    (Some((_, columns, None)), Response::ProcessList { processes, .. }) => {
+                        print_process_list(processes, columns)
+                    }
+                    _ => print_response(&response),
+                }
                 if should_auto_list(&request) {
-                    let list_resp = pm3::client::send_request(&paths, &Request::List)?;
+                    let filter = namespace.map(|namespace| ListFilter {
+                        namespace: Some(namespace),
+                        ..Default::default()
+                    });
+                    let list_resp = pm3::client::send_request(&paths, &Request::List { filter })?;
                     print_response(&list_resp);
                 }
             }
+            // Deploy scripts gate on `pm3 wait`, so a failed wait fails the command
+            if matches!(request, Request::Wait { .. }) && matches!(response, Response::Error { .. })
+            {
+                std::process::exit(1);
+            }
         }
     } else {
         Cli::command().print_help()?;
@@ -63,6 +363,183 @@ async fn main() -> color_eyre::Result<()> {
     Ok(())
 }
 
+/// Runs `pm3 daemon <action>`; `handover` is set when an upgrading daemon
+/// execs this one.
+async fn run_daemon_command(
+    action: DaemonCommand,
+    paths: &pm3::paths::Paths,
+    remote: bool,
+    handover: Option<std::path::PathBuf>,
+    json: bool,
+) -> color_eyre::Result<()> {
+    match action {
+        DaemonCommand::Start {
+            foreground,
+            listen,
+            auth_token_file,
+            resurrect,
+        } => {
+            if remote {
+                color_eyre::eyre::bail!(
+                    "pm3 daemon start only starts a local daemon; run it on the remote host"
+                );
+            }
+            if foreground {
+                let remote = pm3::settings::RemoteSettings {
+                    listen,
+                    auth_token_file,
+                    ..Default::default()
+                };
+                return pm3::daemon::run_with_remote(paths.clone(), remote, resurrect, handover)
+                    .await;
+            }
+            if pm3::pid::is_daemon_running_sync(paths)? {
+                let pid = pm3::pid::read_pid_file(paths).await.unwrap_or_default();
+                println!(
+                    "{}",
+                    format!("daemon is already running (pid {pid})").yellow()
+                );
+                return Ok(());
+            }
+            let mut args: Vec<std::ffi::OsString> = Vec::new();
+            if let Some(listen) = listen {
+                args.extend(["--listen".into(), listen.to_string().into()]);
+            }
+            if let Some(path) = auth_token_file {
+                args.extend(["--auth-token-file".into(), path.into_os_string()]);
+            }
+            if resurrect {
+                args.push("--resurrect".into());
+            }
+            pm3::client::start_daemon(paths, &args)?;
+            let pid = pm3::pid::read_pid_file(paths).await.unwrap_or_default();
+            println!("{} (pid {pid})", "daemon started".green());
+        }
+        DaemonCommand::Stop => {
+            if !remote && !pm3::pid::is_daemon_running_sync(paths)? {
+                println!("{}", "daemon is not running".yellow());
+                return Ok(());
+            }
+            let response = pm3::client::send_request(paths, &Request::Kill)?;
+            if let Response::Error { message } = response {
+                color_eyre::eyre::bail!(message);
+            }
+            if !remote {
+                // Kill answers before the daemon has stopped its processes
+                while pm3::pid::is_daemon_running_sync(paths)? {
+                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                }
+            }
+            println!("{}", "daemon stopped".green());
+        }
+        DaemonCommand::Status => {
+            if !remote && !pm3::pid::is_daemon_running_sync(paths)? {
+                println!(
+                    "{}",
+                    format!(
+                        "daemon is not running (data dir: {})",
+                        paths.data_dir().display()
+                    )
+                    .yellow()
+                );
+                std::process::exit(3);
+            }
+            let (response, _) = pm3::client::ping(paths)?;
+            if json {
+                print_response_json(&response);
+            } else {
+                if let Response::DaemonInfo { info } = &response {
+                    println!("{} (pid {})", "daemon is running".green(), info.pid);
+                }
+                print_response(&response);
+            }
+        }
+        DaemonCommand::Upgrade => {
+            if !remote && !pm3::pid::is_daemon_running_sync(paths)? {
+                color_eyre::eyre::bail!("daemon is not running; start it with `pm3 daemon start`");
+            }
+            let upgrades = match pm3::client::ping(paths)? {
+                (Response::DaemonInfo { info }, _) => info.upgrades,
+                _ => 0,
+            };
+            let response = pm3::client::send_request(paths, &Request::Upgrade)?;
+            if let Response::Error { message } = response {
+                color_eyre::eyre::bail!(message);
+            }
+            if !json {
+                print_response(&response);
+            }
+            // The daemon answers before it execs, then comes back on the same socket
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(15);
+            loop {
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                if let Ok((Response::DaemonInfo { info }, _)) = pm3::client::ping(paths)
+                    && info.upgrades > upgrades
+                {
+                    if json {
+                        print_response_json(&Response::DaemonInfo { info });
+                    } else {
+                        println!(
+                            "{} to pm3 {} (pid {})",
+                            "daemon upgraded".green(),
+                            info.version,
+                            info.pid
+                        );
+                    }
+                    break;
+                }
+                if std::time::Instant::now() >= deadline {
+                    color_eyre::eyre::bail!(
+                        "daemon did not come back from the upgrade; see `pm3 daemon-logs`"
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sends a streaming request, printing `Progress` updates as they arrive,
+/// and returns the final response.
+fn send_with_progress(
+    paths: &pm3::paths::Paths,
+    request: &Request,
+    json: bool,
+) -> color_eyre::Result<Response> {
+    let mut last = None;
+    pm3::client::send_request_streaming(paths, request, |resp| match resp {
+        Response::Progress { .. } if json => print_response_json(resp),
+        Response::Progress { .. } => print_response(resp),
+        _ => last = Some(resp.clone()),
+    })?;
+    last.ok_or_else(|| color_eyre::eyre::eyre!("daemon closed the connection without a response"))
+}
+
+/// Prints the last `lines` of daemon.log, then with `follow` keeps printing
+/// what the daemon appends. Reads the file directly, so it works even when
+/// the daemon has exited.
+async fn print_daemon_log(
+    paths: &pm3::paths::Paths,
+    lines: usize,
+    follow: bool,
+) -> color_eyre::Result<()> {
+    let path = paths.daemon_log();
+    for line in pm3::log::tail_file(&path, lines)? {
+        println!("{line}");
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    let mut offset = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    loop {
+        for line in pm3::log::read_new_lines(&path, &mut offset, false)? {
+            println!("{line}");
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    }
+}
+
 fn should_auto_list(request: &Request) -> bool {
     matches!(
         request,
@@ -77,57 +554,318 @@ fn current_path() -> Option<String> {
     std::env::var("PATH").ok()
 }
 
-fn command_to_request(command: Command) -> color_eyre::Result<Request> {
+/// The namespace commands act in: `--namespace`, else the one of ./pm3.toml
+/// (its `namespace` key or its directory), else the default namespace.
+fn current_namespace(flag: Option<String>) -> color_eyre::Result<String> {
+    let namespace = match flag {
+        Some(namespace) => namespace,
+        None => {
+            let config_path = std::env::current_dir()?.join("pm3.toml");
+            pm3::config::load_namespace(&config_path)
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string())
+        }
+    };
+    pm3::namespace::validate(&namespace).map_err(|e| color_eyre::eyre::eyre!(e))?;
+    Ok(namespace)
+}
+
+/// `name` in `namespace`, unless it is already qualified or a `/regex/`.
+fn qualify(namespace: Option<&str>, name: String) -> String {
+    match namespace {
+        Some(namespace) if !name.contains('/') => pm3::namespace::qualify(namespace, &name),
+        _ => name,
+    }
+}
+
+fn qualify_all(namespace: Option<&str>, names: Option<Vec<String>>) -> Option<Vec<String>> {
+    names.map(|names| {
+        names
+            .into_iter()
+            .map(|name| qualify(namespace, name))
+            .collect()
+    })
+}
+
+/// `namespace` is `None` when acting on every namespace (`--all-namespaces`).
+fn command_to_request(command: Command, namespace: Option<String>) -> color_eyre::Result<Request> {
     match command {
-        Command::Start { names, env, wait } => {
+        Command::Start {
+            names,
+            env,
+            wait,
+            force,
+            regex,
+        } => {
             let config_path = std::env::current_dir()?.join("pm3.toml");
             let configs = pm3::config::load_config(&config_path)
                 .map_err(|e| color_eyre::eyre::eyre!("{e}"))?;
             Ok(Request::Start {
                 configs,
-                names: Command::optional_names(names),
+                names: Command::selected_names(names, regex),
                 env,
                 wait,
                 path: current_path(),
+                force,
+                namespace,
+                progress: wait,
             })
         }
-        Command::Stop { names } => Ok(Request::Stop {
-            names: Command::optional_names(names),
+        Command::Run { name, env } => {
+            let config_path = std::env::current_dir()?.join("pm3.toml");
+            let configs = pm3::config::load_config(&config_path)
+                .map_err(|e| color_eyre::eyre::eyre!("{e}"))?;
+            Ok(Request::Run {
+                configs,
+                name,
+                env,
+                path: current_path(),
+                namespace,
+            })
+        }
+        Command::Stop {
+            names,
+            except,
+            regex,
+            no_dependents,
+        } => Ok(Request::Stop {
+            names: Command::selected_names(names, regex),
+            except: Command::selected_names(except, regex),
+            namespace,
+            no_dependents,
+        }),
+        Command::Restart {
+            names,
+            except,
+            regex,
+            stagger,
+        } => Ok(Request::Restart {
+            names: Command::selected_names(names, regex),
+            except: Command::selected_names(except, regex),
+            namespace,
+            stagger_ms: stagger.map(|d| d.as_millis() as u64),
+        }),
+        Command::Suspend { name } => Ok(Request::Suspend {
+            name: qualify(namespace.as_deref(), name),
         }),
-        Command::Restart { names } => Ok(Request::Restart {
-            names: Command::optional_names(names),
+        Command::Continue { name } => Ok(Request::Continue {
+            name: qualify(namespace.as_deref(), name),
         }),
-        Command::List => Ok(Request::List),
+        Command::Pause { names } => Ok(Request::Pause {
+            names: qualify_all(namespace.as_deref(), Command::optional_names_or_all(names)),
+            namespace,
+        }),
+        Command::Resume { names } => Ok(Request::Resume {
+            names: qualify_all(namespace.as_deref(), Command::optional_names_or_all(names)),
+            namespace,
+        }),
+        Command::List {
+            status, group, tag, ..
+        } => {
+            let filter = ListFilter {
+                status,
+                group,
+                tag,
+                namespace,
+            };
+            Ok(Request::List {
+                filter: (!filter.is_empty()).then_some(filter),
+            })
+        }
         Command::Kill => Ok(Request::Kill),
-        Command::Reload { names } => Ok(Request::Reload {
-            names: Command::optional_names(names),
+        Command::Reload {
+            names,
+            except,
+            regex,
+        } => Ok(Request::Reload {
+            names: Command::selected_names(names, regex),
+            except: Command::selected_names(except, regex),
             path: current_path(),
+            namespace,
         }),
         Command::Tui => unreachable!("tui is handled directly in main"),
         Command::Init => unreachable!("init is handled directly in main"),
-        Command::Startup => unreachable!("startup is handled directly in main"),
-        Command::Unstartup => unreachable!("unstartup is handled directly in main"),
-        Command::Info { name } => Ok(Request::Info { name }),
-        Command::Signal { name, signal } => Ok(Request::Signal { name, signal }),
-        Command::Save => Ok(Request::Save),
-        Command::Resurrect => Ok(Request::Resurrect {
+        Command::Startup { .. } => unreachable!("startup is handled directly in main"),
+        Command::Unstartup { .. } => unreachable!("unstartup is handled directly in main"),
+        Command::Daemons => unreachable!("daemons is handled directly in main"),
+        Command::Ping => unreachable!("ping is handled directly in main"),
+        Command::Graph { .. } => unreachable!("graph is handled directly in main"),
+        Command::DaemonLogs { .. } => unreachable!("daemon-logs is handled directly in main"),
+        Command::Daemon { .. } => unreachable!("daemon is handled directly in main"),
+        Command::LogRelay { .. } => unreachable!("log-relay is handled directly in main"),
+        Command::Info { name, .. } => Ok(Request::Info {
+            name: qualify(namespace.as_deref(), name),
+        }),
+        Command::Wait {
+            names,
+            timeout,
+            status,
+        } => Ok(Request::Wait {
+            names,
+            status,
+            timeout_ms: timeout.as_millis() as u64,
+            namespace,
+        }),
+        Command::Stats { name, since } => Ok(Request::Stats {
+            name: qualify(namespace.as_deref(), name),
+            since_secs: Some(since.as_secs()),
+        }),
+        Command::Report { name, window, .. } => Ok(Request::Report {
+            name: name.map(|name| qualify(namespace.as_deref(), name)),
+            windows_secs: window.iter().map(|d| d.as_secs()).collect(),
+        }),
+        Command::Status { name } => Ok(Request::Info {
+            name: qualify(namespace.as_deref(), name),
+        }),
+        Command::Signal { name, signal } => Ok(Request::Signal {
+            name: qualify(namespace.as_deref(), name),
+            signal,
+        }),
+        Command::Save { tag, .. } => Ok(Request::Save { tag }),
+        Command::Resurrect { from } => Ok(Request::Resurrect {
             path: current_path(),
+            from,
         }),
-        Command::Flush { names } => Ok(Request::Flush {
-            names: Command::optional_names(names),
+        Command::Snapshots => Ok(Request::Snapshots),
+        Command::Tree { name } => Ok(Request::Tree {
+            name: name.map(|name| qualify(namespace.as_deref(), name)),
         }),
+        Command::History { lines } => Ok(Request::History { lines }),
+        Command::Export { .. } => match namespace {
+            Some(namespace) => Ok(Request::Export {
+                namespace: Some(namespace),
+            }),
+            None => {
+                color_eyre::eyre::bail!("export writes a single namespace; drop --all-namespaces")
+            }
+        },
+        Command::Flush { names, all_files } => Ok(Request::Flush {
+            names: qualify_all(namespace.as_deref(), Command::optional_names(names)),
+            all_files,
+            namespace,
+        }),
+        Command::ReopenLogs { names } => Ok(Request::ReopenLogs {
+            names: qualify_all(namespace.as_deref(), Command::optional_names(names)),
+            namespace,
+        }),
+        Command::Prune { logs } => Ok(Request::Prune { logs, namespace }),
         Command::Log {
             name,
             lines,
             follow,
+            since,
+            until,
+            err,
+            out,
         } => Ok(Request::Log {
-            name,
+            name: name.map(|name| qualify(namespace.as_deref(), name)),
             lines,
             follow,
+            since,
+            until,
+            stream: match (err, out) {
+                (true, _) => Some(LogStream::Stderr),
+                (_, true) => Some(LogStream::Stdout),
+                _ => None,
+            },
+            namespace,
+        }),
+        Command::Events { names } => Ok(Request::Events {
+            names: qualify_all(namespace.as_deref(), (!names.is_empty()).then_some(names)),
         }),
     }
 }
 
+/// Prints a streamed response as one NDJSON line, naming the process on log
+/// lines even when the daemon left it implied by the request.
+fn print_stream_json(response: &Response, name: Option<&str>) {
+    match response {
+        Response::LogLine {
+            name: None,
+            stream,
+            timestamp,
+            line,
+        } if name.is_some() => print_response_json(&Response::LogLine {
+            name: name.map(str::to_string),
+            stream: stream.clone(),
+            timestamp: timestamp.clone(),
+            line: line.clone(),
+        }),
+        _ => print_response_json(response),
+    }
+}
+
+fn colored_status(status: ProcessStatus) -> String {
+    let status_str = status.to_string();
+    match status {
+        ProcessStatus::Online => status_str.green().to_string(),
+        ProcessStatus::Starting => status_str.yellow().to_string(),
+        ProcessStatus::Unhealthy => status_str.magenta().to_string(),
+        ProcessStatus::Stopped => status_str,
+        ProcessStatus::Errored => status_str.red().to_string(),
+        ProcessStatus::Flapping => status_str.bright_red().to_string(),
+        ProcessStatus::Succeeded => status_str.cyan().to_string(),
+        ProcessStatus::Failed => status_str.red().to_string(),
+        ProcessStatus::Suspended => status_str.blue().to_string(),
+    }
+}
+
+/// `pm3 info --watch`: prints the detail once, then keeps one line of
+/// status, CPU, memory, uptime and restarts up to date in place, polling
+/// over a single session until interrupted. With `--json`, or when stdout
+/// isn't a terminal, every poll is printed on a line of its own instead.
+async fn watch_info(paths: &pm3::paths::Paths, name: &str, json: bool) -> color_eyre::Result<()> {
+    use std::io::{IsTerminal, Write};
+
+    let in_place = !json && std::io::stdout().is_terminal();
+    let mut session = pm3::client::Session::open(paths)?;
+    let request = Request::Info {
+        name: name.to_string(),
+    };
+    for poll in 0.. {
+        let response = session.request(&request)?;
+        let Response::ProcessDetail { info } = &response else {
+            if in_place && poll > 0 {
+                println!();
+            }
+            match response {
+                Response::Error { message } => color_eyre::eyre::bail!(message),
+                other => color_eyre::eyre::bail!("unexpected response: {other:?}"),
+            }
+        };
+        if json {
+            print_response_json(&response);
+        } else {
+            if poll == 0 {
+                print_response(&response);
+                println!();
+            }
+            let line = format!(
+                "  {} {}  {} {}  {} {}  {} {}  {} {}",
+                "status:".dimmed(),
+                colored_status(info.status),
+                "cpu:".dimmed(),
+                format_cpu(info.cpu_percent),
+                "memory:".dimmed(),
+                format_memory_bytes(info.memory_bytes),
+                "uptime:".dimmed(),
+                format_uptime(info.uptime),
+                "restarts:".dimmed(),
+                info.restarts,
+            );
+            if in_place {
+                print!("\r\x1b[2K{line}");
+                std::io::stdout().flush()?;
+            } else {
+                println!("{line}");
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    Ok(())
+}
+
 fn print_response_json(response: &Response) {
     let json = serde_json::to_string(response).expect("failed to serialize response");
     println!("{json}");
@@ -140,7 +878,37 @@ fn status_color(status: &ProcessStatus) -> Color {
         ProcessStatus::Unhealthy => Color::Magenta,
         ProcessStatus::Stopped => Color::Reset,
         ProcessStatus::Errored => Color::Red,
+        ProcessStatus::Flapping => Color::DarkYellow,
+        ProcessStatus::Succeeded => Color::Cyan,
+        ProcessStatus::Failed => Color::Red,
+        ProcessStatus::Suspended => Color::Blue,
+    }
+}
+
+/// Prints a log line, prefixed with its process when the request didn't name
+/// one, and in red when `stderr`.
+fn print_log_line(name: Option<&str>, line: &str, stderr: bool) {
+    let line = if stderr {
+        line.red().to_string()
+    } else {
+        line.to_string()
+    };
+    if let Some(name) = name {
+        println!("{} {line}", format!("[{name}]").cyan().bold());
+    } else {
+        println!("{line}");
+    }
+}
+
+fn print_restore_progress(progress: &RestoreProgress) {
+    let mut line = format!(
+        "restoring {}/{} processes",
+        progress.restored, progress.total
+    );
+    if !progress.starting.is_empty() {
+        line.push_str(&format!(" (starting: {})", progress.starting.join(", ")));
     }
+    println!("{}", line.yellow());
 }
 
 fn print_response(response: &Response) {
@@ -155,62 +923,19 @@ fn print_response(response: &Response) {
         Response::Error { message } => {
             eprintln!("{} {}", "error:".red().bold(), message);
         }
-        Response::ProcessList { processes } => {
-            if processes.is_empty() {
-                println!("{}", "no processes running".yellow());
+        Response::ProcessList { processes, .. } => print_process_list(processes, &[]),
+        Response::ProcessDetail { info } => {
+            let colored_status = colored_status(info.status);
+            if info.paused {
+                println!(
+                    "{}: {} {}",
+                    info.name.cyan().bold(),
+                    colored_status,
+                    "(monitoring paused)".yellow()
+                );
             } else {
-                let mut table = Table::new();
-                table.load_preset(UTF8_FULL_CONDENSED);
-                table.set_header(vec![
-                    Cell::new("name").add_attribute(Attribute::Bold),
-                    Cell::new("group").add_attribute(Attribute::Bold),
-                    Cell::new("pid").add_attribute(Attribute::Bold),
-                    Cell::new("status").add_attribute(Attribute::Bold),
-                    Cell::new("cpu").add_attribute(Attribute::Bold),
-                    Cell::new("mem").add_attribute(Attribute::Bold),
-                    Cell::new("uptime").add_attribute(Attribute::Bold),
-                    Cell::new("restarts").add_attribute(Attribute::Bold),
-                ]);
-                for p in processes {
-                    let group = p.group.as_deref().unwrap_or("-");
-                    let pid = p
-                        .pid
-                        .map(|id| id.to_string())
-                        .unwrap_or_else(|| "-".to_string());
-                    let uptime = format_uptime(p.uptime);
-                    let status = p.status.to_string();
-                    let cpu = format_cpu(p.cpu_percent);
-                    let mem = format_memory_bytes(p.memory_bytes);
-                    let restarts = p.restarts.to_string();
-                    let restarts_cell = if p.restarts > 0 {
-                        Cell::new(&restarts).fg(Color::Yellow)
-                    } else {
-                        Cell::new(&restarts)
-                    };
-                    table.add_row(vec![
-                        Cell::new(&p.name).fg(Color::Cyan),
-                        Cell::new(group).fg(Color::Magenta),
-                        Cell::new(&pid),
-                        Cell::new(&status).fg(status_color(&p.status)),
-                        Cell::new(&cpu),
-                        Cell::new(&mem),
-                        Cell::new(&uptime),
-                        restarts_cell,
-                    ]);
-                }
-                println!("{table}");
+                println!("{}: {}", info.name.cyan().bold(), colored_status);
             }
-        }
-        Response::ProcessDetail { info } => {
-            let status_str = info.status.to_string();
-            let colored_status = match info.status {
-                ProcessStatus::Online => status_str.green().to_string(),
-                ProcessStatus::Starting => status_str.yellow().to_string(),
-                ProcessStatus::Unhealthy => status_str.magenta().to_string(),
-                ProcessStatus::Stopped => status_str.to_string(),
-                ProcessStatus::Errored => status_str.red().to_string(),
-            };
-            println!("{}: {}", info.name.cyan().bold(), colored_status);
             println!("  {} {}", "command:".dimmed(), info.command);
             if let Some(pid) = info.pid {
                 println!("  {} {pid}", "pid:".dimmed());
@@ -224,11 +949,37 @@ fn print_response(response: &Response) {
                 "memory:".dimmed(),
                 format_memory_bytes(info.memory_bytes)
             );
+            if let Some(usage) = &info.resources {
+                println!(
+                    "  {} {} ({} sockets)",
+                    "fds:".dimmed(),
+                    usage.fds,
+                    usage.sockets
+                );
+                println!("  {} {}", "threads:".dimmed(), usage.threads);
+                if usage.read_bytes.is_some() || usage.write_bytes.is_some() {
+                    println!(
+                        "  {} {} read, {} written",
+                        "io:".dimmed(),
+                        format_memory_bytes(usage.read_bytes),
+                        format_memory_bytes(usage.write_bytes)
+                    );
+                }
+            }
             println!("  {} {}", "uptime:".dimmed(), format_uptime(info.uptime));
             println!("  {} {}", "restarts:".dimmed(), info.restarts);
             if let Some(group) = &info.group {
                 println!("  {} {group}", "group:".dimmed());
             }
+            if !info.tags.is_empty() {
+                println!("  {} {}", "tags:".dimmed(), info.tags.join(", "));
+            }
+            if !info.metrics.is_empty() {
+                println!("  {}", "metrics:".dimmed());
+                for (k, v) in &info.metrics {
+                    println!("    {k}={}", format_metric(*v));
+                }
+            }
             if let Some(env) = &info.env {
                 println!("  {}", "env:".dimmed());
                 for (k, v) in env {
@@ -250,17 +1001,649 @@ fn print_response(response: &Response) {
             if let Some(health_check) = &info.health_check {
                 println!("  {} {health_check}", "health_check:".dimmed());
             }
+            if let Some(health) = &info.health {
+                let state = if health.passed {
+                    "passing".green().to_string()
+                } else {
+                    format!("failing {} in a row", health.failures)
+                        .red()
+                        .to_string()
+                };
+                println!(
+                    "  {} {state} ({})",
+                    "health:".dimmed(),
+                    health_detail(health)
+                );
+            }
             if let Some(depends_on) = &info.depends_on {
                 println!("  {} {}", "depends_on:".dimmed(), depends_on.join(", "));
             }
         }
-        Response::LogLine { name, line } => {
-            if let Some(name) = name {
-                println!("{} {line}", format!("[{name}]").cyan().bold());
+        Response::TaskFinished {
+            name,
+            status,
+            exit_code,
+        } => {
+            let code = exit_code.map_or_else(|| "signal".to_string(), |c| c.to_string());
+            let summary = format!("task '{name}' {status} (exit code: {code})");
+            match status {
+                ProcessStatus::Succeeded => println!("{}", summary.green()),
+                _ => eprintln!("{}", summary.red()),
+            }
+        }
+        Response::Progress { message } => println!("{}", message.dimmed()),
+        Response::DaemonInfo { info } => {
+            println!("  {} {}", "version:".dimmed(), info.version);
+            println!(
+                "  {} {}",
+                "uptime:".dimmed(),
+                format_uptime(Some(info.uptime))
+            );
+            println!("  {} {}", "processes:".dimmed(), info.processes);
+            println!("  {} {}", "data dir:".dimmed(), info.data_dir);
+            if info.upgrades > 0 {
+                println!("  {} {}", "upgrades:".dimmed(), info.upgrades);
+            }
+        }
+        Response::LogLine {
+            name, stream, line, ..
+        } => print_log_line(name.as_deref(), line, *stream == Some(LogStream::Stderr)),
+        Response::Done => {}
+        Response::Stats {
+            name,
+            interval,
+            samples,
+        } => print_stats(name, *interval, samples),
+        Response::Report { reports } => print_report(reports),
+        Response::Snapshots { snapshots } => print_snapshots(snapshots),
+        Response::Trees { trees } => print_trees(trees),
+        Response::History { entries } => print_history(entries),
+        Response::Configs { configs } => match pm3::config::to_toml(configs, None) {
+            Ok(content) => print!("{content}"),
+            Err(e) => eprintln!("{}", format!("failed to render configs: {e}").red()),
+        },
+        Response::Event(event) => {
+            let mut line = format!(
+                "{} {} {} {}",
+                event.timestamp.dimmed(),
+                event.name.cyan().bold(),
+                event.kind,
+                event.status,
+            );
+            if let Some(detail) = &event.detail {
+                line.push_str(&format!(" {}", detail.dimmed()));
+            }
+            println!("{line}");
+        }
+    }
+}
+
+fn print_daemons(daemons: &[pm3::profile::DaemonEntry]) {
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(
+        ["profile", "status", "pid", "data dir"]
+            .into_iter()
+            .map(|h| Cell::new(h).add_attribute(Attribute::Bold)),
+    );
+    for daemon in daemons {
+        let (status, color) = if daemon.running {
+            ("running", Color::Green)
+        } else {
+            ("stopped", Color::Reset)
+        };
+        let pid = match (daemon.running, daemon.pid) {
+            (true, Some(pid)) => pid.to_string(),
+            _ => "-".to_string(),
+        };
+        table.add_row(vec![
+            Cell::new(&daemon.profile).fg(Color::Cyan),
+            Cell::new(status).fg(color),
+            Cell::new(pid),
+            Cell::new(daemon.data_dir.display()),
+        ]);
+    }
+    println!("{table}");
+}
+
+fn print_history(entries: &[pm3::audit::AuditEntry]) {
+    if entries.is_empty() {
+        println!("{}", "no recorded operations".yellow());
+        return;
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(
+        ["time", "user", "pid", "command", "result"]
+            .into_iter()
+            .map(|h| Cell::new(h).add_attribute(Attribute::Bold)),
+    );
+    for entry in entries {
+        let user = match (&entry.remote, &entry.user, entry.uid) {
+            (Some(addr), _, _) => format!("remote {addr}"),
+            (None, Some(name), _) => name.clone(),
+            (None, None, Some(uid)) => uid.to_string(),
+            (None, None, None) => "-".to_string(),
+        };
+        let pid = entry
+            .pid
+            .map(|pid| pid.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let color = if entry.outcome.starts_with("error") {
+            Color::Red
+        } else {
+            Color::Reset
+        };
+        table.add_row(vec![
+            Cell::new(&entry.time),
+            Cell::new(user).fg(Color::Cyan),
+            Cell::new(pid),
+            Cell::new(&entry.command),
+            Cell::new(&entry.outcome).fg(color),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// How many columns the `pm3 stats` graphs span at most.
+const STATS_GRAPH_WIDTH: usize = 60;
+
+fn print_stats(name: &str, interval: u64, samples: &[StatsSample]) {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        println!("{}", format!("no samples recorded for {name} yet").yellow());
+        return;
+    };
+    let time = |secs: i64| {
+        chrono::DateTime::from_timestamp(secs, 0)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    };
+    println!(
+        "{}: {} samples every {interval}s, {} to {}",
+        name.cyan().bold(),
+        samples.len(),
+        time(first.time),
+        time(last.time)
+    );
+
+    let cpu: Vec<f64> = samples.iter().filter_map(|s| s.cpu_percent).collect();
+    let memory: Vec<f64> = samples
+        .iter()
+        .filter_map(|s| s.memory_bytes.map(|m| m as f64))
+        .collect();
+    let summary = |values: &[f64], format: &dyn Fn(f64) -> String| {
+        if values.is_empty() {
+            return vec!["-".to_string(); 3];
+        }
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let avg = values.iter().sum::<f64>() / values.len() as f64;
+        vec![format(min), format(avg), format(max)]
+    };
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(
+        ["", "min", "avg", "max", "latest"]
+            .into_iter()
+            .map(|h| Cell::new(h).add_attribute(Attribute::Bold)),
+    );
+    let mut cpu_row = vec!["cpu".to_string()];
+    cpu_row.extend(summary(&cpu, &|v| format_cpu(Some(v))));
+    cpu_row.push(format_cpu(last.cpu_percent));
+    let mut memory_row = vec!["memory".to_string()];
+    memory_row.extend(summary(&memory, &|v| format_memory_bytes(Some(v as u64))));
+    memory_row.push(format_memory_bytes(last.memory_bytes));
+    table.add_row(cpu_row);
+    table.add_row(memory_row);
+    println!("{table}");
+
+    let restarts = last.restarts.saturating_sub(first.restarts);
+    let restarts_line = format!("restarts: {} ({restarts} in this period)", last.restarts);
+    if restarts > 0 {
+        println!("{}", restarts_line.yellow());
+    } else {
+        println!("{restarts_line}");
+    }
+
+    let cpu_graph = pm3::timeseries::downsample(samples, STATS_GRAPH_WIDTH, |s| s.cpu_percent);
+    let memory_graph = pm3::timeseries::downsample(samples, STATS_GRAPH_WIDTH, |s| {
+        s.memory_bytes.map(|m| m as f64)
+    });
+    println!("{} {}", "cpu    ".dimmed(), sparkline(&cpu_graph).green());
+    println!("{} {}", "memory ".dimmed(), sparkline(&memory_graph).cyan());
+}
+
+fn print_snapshots(snapshots: &[SnapshotInfo]) {
+    if snapshots.is_empty() {
+        println!("{}", "no snapshots saved".yellow());
+        return;
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(
+        ["id", "tag", "created", "processes"]
+            .into_iter()
+            .map(|h| Cell::new(h).add_attribute(Attribute::Bold)),
+    );
+    for snapshot in snapshots {
+        let created = chrono::DateTime::from_timestamp(snapshot.created, 0)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "-".to_string());
+        table.add_row(vec![
+            Cell::new(&snapshot.id),
+            Cell::new(snapshot.tag.as_deref().unwrap_or("-")).fg(Color::Cyan),
+            Cell::new(created),
+            Cell::new(snapshot.processes),
+        ]);
+    }
+    println!("{table}");
+}
+
+fn print_trees(trees: &[ProcessTree]) {
+    if trees.is_empty() {
+        println!("{}", "no running processes".yellow());
+        return;
+    }
+    for (i, tree) in trees.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", tree.name.cyan().bold());
+        print_tree_node(&tree.root, "", "");
+    }
+}
+
+/// Prints `node` after `lead` and its children below it, each indented by
+/// `indent` and connected with box-drawing lines.
+fn print_tree_node(node: &TreeNode, lead: &str, indent: &str) {
+    println!(
+        "{lead}{} {:>6} {:>7}  {}",
+        node.pid.to_string().bold(),
+        format_cpu(node.cpu_percent),
+        format_memory_bytes(node.memory_bytes),
+        node.command.dimmed(),
+    );
+    for (i, child) in node.children.iter().enumerate() {
+        let last = i + 1 == node.children.len();
+        let (branch, below) = if last {
+            ("└─ ", "   ")
+        } else {
+            ("├─ ", "│  ")
+        };
+        print_tree_node(
+            child,
+            &format!("{indent}{branch}"),
+            &format!("{indent}{below}"),
+        );
+    }
+}
+
+fn print_report(reports: &[AvailabilityReport]) {
+    if reports.is_empty() {
+        println!("{}", "no processes to report on".yellow());
+        return;
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(
+        [
+            "name",
+            "window",
+            "availability",
+            "uptime",
+            "downtime",
+            "crashes",
+            "crashes/day",
+            "restarts",
+            "mttr",
+            "last crash",
+        ]
+        .into_iter()
+        .map(|h| Cell::new(h).add_attribute(Attribute::Bold)),
+    );
+    for report in reports {
+        let (availability, color) = match report.availability {
+            None => ("-".to_string(), Color::Reset),
+            Some(a) if a >= 99.9 => (format!("{a:.3}%"), Color::Green),
+            Some(a) if a >= 99.0 => (format!("{a:.3}%"), Color::Yellow),
+            Some(a) => (format!("{a:.3}%"), Color::Red),
+        };
+        let days = report.window_secs as f64 / 86_400.0;
+        let crashes_per_day = if days > 0.0 {
+            format!("{:.2}", report.crashes as f64 / days)
+        } else {
+            "-".to_string()
+        };
+        let last_crash = report
+            .last_crash
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "-".to_string());
+        table.add_row(vec![
+            Cell::new(&report.name).fg(Color::Cyan),
+            Cell::new(format_window(report.window_secs)),
+            Cell::new(availability).fg(color),
+            Cell::new(format_uptime(Some(report.uptime_secs as u64))),
+            Cell::new(format_uptime(Some(report.downtime_secs as u64))),
+            Cell::new(report.crashes),
+            Cell::new(crashes_per_day),
+            Cell::new(report.restarts),
+            Cell::new(format_uptime(report.mttr_secs.map(|s| s.round() as u64))),
+            Cell::new(last_crash),
+        ]);
+    }
+    println!("{table}");
+}
+
+/// A window length in the largest unit that divides it, like `7d` or `90m`.
+fn format_window(secs: u64) -> String {
+    match secs {
+        0 => "0s".to_string(),
+        s if s % 86_400 == 0 => format!("{}d", s / 86_400),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
+    }
+}
+
+/// Draws values as block characters scaled to the largest, leaving a gap
+/// where there is no value, such as while the process was down.
+fn sparkline(values: &[Option<f64>]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().flatten().copied().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|value| match value {
+            None => ' ',
+            Some(_) if max <= 0.0 => BARS[0],
+            Some(v) => BARS[((v / max) * (BARS.len() - 1) as f64).round() as usize],
+        })
+        .collect()
+}
+
+/// Prints the process table. An empty `columns` means the default eight
+/// columns (plus tags, when any process has them) followed by every custom
+/// metric any process reports.
+fn print_process_list(processes: &[ProcessInfo], columns: &[ListColumn]) {
+    if processes.is_empty() {
+        println!("{}", "no processes running".yellow());
+        return;
+    }
+
+    let columns: Vec<ListColumn> = if columns.is_empty() {
+        let metric_names: std::collections::BTreeSet<&String> =
+            processes.iter().flat_map(|p| p.metrics.keys()).collect();
+        let show_tags = processes.iter().any(|p| !p.tags.is_empty());
+        ListColumn::DEFAULT
+            .into_iter()
+            .flat_map(|c| {
+                let tags = (c == ListColumn::Group && show_tags).then_some(ListColumn::Tags);
+                std::iter::once(c).chain(tags)
+            })
+            .chain(
+                metric_names
+                    .into_iter()
+                    .map(|m| ListColumn::Metric(m.clone())),
+            )
+            .collect()
+    } else {
+        columns.to_vec()
+    };
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(
+        columns
+            .iter()
+            .map(|c| Cell::new(c).add_attribute(Attribute::Bold)),
+    );
+    for p in processes {
+        table.add_row(columns.iter().map(|c| list_cell(c, p)));
+    }
+    println!("{table}");
+    let totals = ListTotals::of(processes.iter());
+    println!(
+        "{}",
+        format!(
+            "{} processes: {} · cpu {} · mem {}",
+            totals.count,
+            totals.statuses(),
+            format_cpu(Some(totals.cpu_percent)),
+            format_memory_bytes(Some(totals.memory_bytes)),
+        )
+        .dimmed()
+    );
+}
+
+/// Sums over some rows of `pm3 list`, for its footer and `--group-by`.
+#[derive(Debug, Default, PartialEq)]
+struct ListTotals {
+    count: usize,
+    /// How many processes are in each status, running ones first.
+    by_status: Vec<(ProcessStatus, usize)>,
+    cpu_percent: f64,
+    memory_bytes: u64,
+}
+
+impl ListTotals {
+    fn of<'a>(processes: impl IntoIterator<Item = &'a ProcessInfo>) -> Self {
+        let mut totals = Self::default();
+        for p in processes {
+            totals.count += 1;
+            match totals.by_status.iter_mut().find(|(s, _)| *s == p.status) {
+                Some((_, n)) => *n += 1,
+                None => totals.by_status.push((p.status, 1)),
+            }
+            totals.cpu_percent += p.cpu_percent.unwrap_or_default();
+            totals.memory_bytes += p.memory_bytes.unwrap_or_default();
+        }
+        totals.by_status.sort_by_key(|(status, _)| *status as u8);
+        totals
+    }
+
+    /// e.g. "3 online, 1 errored"
+    fn statuses(&self) -> String {
+        self.by_status
+            .iter()
+            .map(|(status, n)| format!("{n} {status}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// `pm3 list --group-by group`: one row of totals per group, then processes
+/// outside any group under "-", and the overall totals last.
+fn print_group_summaries(processes: &[ProcessInfo]) {
+    if processes.is_empty() {
+        println!("{}", "no processes running".yellow());
+        return;
+    }
+
+    let mut groups: std::collections::BTreeMap<(bool, Option<&str>), Vec<&ProcessInfo>> =
+        Default::default();
+    for p in processes {
+        let group = p.group.as_deref();
+        groups.entry((group.is_none(), group)).or_default().push(p);
+    }
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    table.set_header(
+        ["group", "processes", "status", "cpu", "mem"]
+            .into_iter()
+            .map(|h| Cell::new(h).add_attribute(Attribute::Bold)),
+    );
+    let rows = groups
+        .iter()
+        .map(|((_, group), members)| {
+            (
+                Cell::new(group.unwrap_or("-")).fg(Color::Magenta),
+                ListTotals::of(members.iter().copied()),
+            )
+        })
+        .chain(std::iter::once((
+            Cell::new("total").add_attribute(Attribute::Bold),
+            ListTotals::of(processes),
+        )));
+    for (label, totals) in rows {
+        table.add_row(vec![
+            label,
+            Cell::new(totals.count),
+            Cell::new(totals.statuses()),
+            Cell::new(format_cpu(Some(totals.cpu_percent))),
+            Cell::new(format_memory_bytes(Some(totals.memory_bytes))),
+        ]);
+    }
+    println!("{table}");
+}
+
+fn list_cell(column: &ListColumn, p: &ProcessInfo) -> Cell {
+    match column {
+        ListColumn::Name => Cell::new(&p.name).fg(Color::Cyan),
+        ListColumn::Group => Cell::new(p.group.as_deref().unwrap_or("-")).fg(Color::Magenta),
+        ListColumn::Pid => Cell::new(
+            p.pid
+                .map(|id| id.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        ),
+        ListColumn::Status => {
+            let status = if p.paused {
+                format!("{} (paused)", p.status)
+            } else {
+                p.status.to_string()
+            };
+            Cell::new(status).fg(status_color(&p.status))
+        }
+        ListColumn::Cpu => Cell::new(format_cpu(p.cpu_percent)),
+        ListColumn::Mem => Cell::new(format_memory_bytes(p.memory_bytes)),
+        ListColumn::Uptime => Cell::new(format_uptime(p.uptime)),
+        ListColumn::Tags => Cell::new(if p.tags.is_empty() {
+            "-".to_string()
+        } else {
+            p.tags.join(",")
+        })
+        .fg(Color::Magenta),
+        ListColumn::Restarts => {
+            let cell = Cell::new(p.restarts);
+            if p.restarts > 0 {
+                cell.fg(Color::Yellow)
             } else {
-                println!("{line}");
+                cell
             }
         }
+        ListColumn::Fds | ListColumn::Threads | ListColumn::Sockets => {
+            Cell::new(resource_count(column, p).map_or_else(|| "-".to_string(), |n| n.to_string()))
+        }
+        ListColumn::Read | ListColumn::Write => {
+            Cell::new(format_memory_bytes(resource_bytes(column, p)))
+        }
+        ListColumn::Health => match &p.health {
+            Some(health) if health.passed => Cell::new("ok").fg(Color::Green),
+            Some(health) => Cell::new(format!("failing ({})", health.failures)).fg(Color::Red),
+            None => Cell::new("-"),
+        },
+        ListColumn::Metric(name) => Cell::new(
+            p.metrics
+                .get(name)
+                .map_or_else(|| "-".to_string(), |v| format_metric(*v)),
+        ),
+    }
+}
+
+/// Sorts by `column`, ties broken by name. Text columns sort ascending and
+/// numeric ones largest first, with processes lacking a value at the end.
+fn sort_processes(processes: &mut [ProcessInfo], column: &ListColumn) {
+    processes.sort_by(|a, b| compare_by(column, a, b).then_with(|| a.name.cmp(&b.name)));
+}
+
+fn compare_by(column: &ListColumn, a: &ProcessInfo, b: &ProcessInfo) -> Ordering {
+    match column {
+        ListColumn::Name => a.name.cmp(&b.name),
+        ListColumn::Group => a.group.cmp(&b.group),
+        ListColumn::Pid => a.pid.cmp(&b.pid),
+        ListColumn::Status => a.status.to_string().cmp(&b.status.to_string()),
+        ListColumn::Tags => a.tags.cmp(&b.tags),
+        ListColumn::Cpu => largest_first(a.cpu_percent, b.cpu_percent),
+        ListColumn::Mem => largest_first(
+            a.memory_bytes.map(|m| m as f64),
+            b.memory_bytes.map(|m| m as f64),
+        ),
+        ListColumn::Uptime => largest_first(a.uptime.map(|u| u as f64), b.uptime.map(|u| u as f64)),
+        ListColumn::Restarts => largest_first(Some(a.restarts as f64), Some(b.restarts as f64)),
+        ListColumn::Fds | ListColumn::Threads | ListColumn::Sockets => {
+            let count = |p: &ProcessInfo| resource_count(column, p).map(f64::from);
+            largest_first(count(a), count(b))
+        }
+        ListColumn::Read | ListColumn::Write => {
+            let bytes = |p: &ProcessInfo| resource_bytes(column, p).map(|b| b as f64);
+            largest_first(bytes(a), bytes(b))
+        }
+        ListColumn::Health => largest_first(
+            a.health.as_ref().map(|h| h.failures as f64),
+            b.health.as_ref().map(|h| h.failures as f64),
+        ),
+        ListColumn::Metric(name) => {
+            largest_first(a.metrics.get(name).copied(), b.metrics.get(name).copied())
+        }
+    }
+}
+
+/// The latest health probe, e.g. "HTTP 503, 12ms, 3s ago".
+fn health_detail(health: &pm3::protocol::HealthReport) -> String {
+    let mut parts = Vec::new();
+    if let Some(status) = health.http_status {
+        parts.push(format!("HTTP {status}"));
+    }
+    if let Some(error) = &health.error {
+        parts.push(error.clone());
+    }
+    parts.push(format!("{}ms", health.latency_ms));
+    parts.push(format!("{}s ago", health.checked_ago));
+    parts.join(", ")
+}
+
+/// The fds, threads or sockets count shown in `column`.
+fn resource_count(column: &ListColumn, p: &ProcessInfo) -> Option<u32> {
+    let usage = p.resources?;
+    match column {
+        ListColumn::Fds => Some(usage.fds),
+        ListColumn::Threads => Some(usage.threads),
+        ListColumn::Sockets => Some(usage.sockets),
+        _ => None,
+    }
+}
+
+/// The bytes read or written shown in `column`.
+fn resource_bytes(column: &ListColumn, p: &ProcessInfo) -> Option<u64> {
+    let usage = p.resources?;
+    match column {
+        ListColumn::Read => usage.read_bytes,
+        ListColumn::Write => usage.write_bytes,
+        _ => None,
+    }
+}
+
+fn largest_first(a: Option<f64>, b: Option<f64>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
     }
 }
 
@@ -281,6 +1664,25 @@ fn format_memory_bytes(bytes: Option<u64>) -> String {
     }
 }
 
+/// Exit code for `pm3 status`: 0 online, 1 stopped, 2 errored, 3 not found.
+/// Transitional states count as stopped and unhealthy ones as errored.
+fn status_exit_code(response: &Response) -> i32 {
+    match response {
+        Response::ProcessDetail { info } => match info.status {
+            ProcessStatus::Online => 0,
+            ProcessStatus::Starting
+            | ProcessStatus::Stopped
+            | ProcessStatus::Succeeded
+            | ProcessStatus::Suspended => 1,
+            ProcessStatus::Errored
+            | ProcessStatus::Failed
+            | ProcessStatus::Flapping
+            | ProcessStatus::Unhealthy => 2,
+        },
+        _ => 3,
+    }
+}
+
 fn format_uptime(seconds: Option<u64>) -> String {
     match seconds {
         None => "-".to_string(),
@@ -295,6 +1697,133 @@ fn format_uptime(seconds: Option<u64>) -> String {
 mod tests {
     use super::*;
 
+    fn info(name: &str, memory_bytes: Option<u64>, restarts: u32) -> ProcessInfo {
+        ProcessInfo {
+            name: name.to_string(),
+            pid: None,
+            status: ProcessStatus::Online,
+            uptime: None,
+            restarts,
+            cpu_percent: None,
+            memory_bytes,
+            resources: None,
+            health: None,
+            group: None,
+            tags: Vec::new(),
+            metrics: Default::default(),
+            paused: false,
+        }
+    }
+
+    fn names(processes: &[ProcessInfo]) -> Vec<&str> {
+        processes.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_processes_by_mem_largest_first() {
+        let mut processes = vec![
+            info("a", Some(10), 0),
+            info("b", None, 0),
+            info("c", Some(30), 0),
+            info("d", Some(30), 0),
+        ];
+        sort_processes(&mut processes, &ListColumn::Mem);
+        assert_eq!(names(&processes), vec!["c", "d", "a", "b"]);
+    }
+
+    #[test]
+    fn test_sort_processes_by_name_and_restarts() {
+        let mut processes = vec![
+            info("web", None, 1),
+            info("api", None, 4),
+            info("db", None, 0),
+        ];
+        sort_processes(&mut processes, &ListColumn::Name);
+        assert_eq!(names(&processes), vec!["api", "db", "web"]);
+        sort_processes(&mut processes, &ListColumn::Restarts);
+        assert_eq!(names(&processes), vec!["api", "web", "db"]);
+    }
+
+    #[test]
+    fn test_sort_processes_by_metric() {
+        let mut a = info("a", None, 0);
+        a.metrics.insert("queue_depth".to_string(), 2.0);
+        let mut b = info("b", None, 0);
+        b.metrics.insert("queue_depth".to_string(), 7.0);
+        let mut processes = vec![info("c", None, 0), a, b];
+        sort_processes(
+            &mut processes,
+            &ListColumn::Metric("queue_depth".to_string()),
+        );
+        assert_eq!(names(&processes), vec!["b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_list_totals() {
+        let mut errored = info("c", None, 0);
+        errored.status = ProcessStatus::Errored;
+        let mut busy = info("b", Some(300), 0);
+        busy.cpu_percent = Some(12.5);
+        let processes = [errored, info("a", Some(100), 0), busy];
+
+        let totals = ListTotals::of(&processes);
+        assert_eq!(totals.count, 3);
+        assert_eq!(totals.cpu_percent, 12.5);
+        assert_eq!(totals.memory_bytes, 400);
+        assert_eq!(totals.statuses(), "2 online, 1 errored");
+    }
+
+    #[test]
+    fn test_sort_processes_by_fds() {
+        let with_fds = |name: &str, fds: u32| {
+            let mut p = info(name, None, 0);
+            p.resources = Some(pm3::protocol::ResourceUsage {
+                fds,
+                ..Default::default()
+            });
+            p
+        };
+        let mut processes = vec![with_fds("a", 12), info("b", None, 0), with_fds("c", 900)];
+        sort_processes(&mut processes, &ListColumn::Fds);
+        assert_eq!(names(&processes), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_sort_processes_by_health_and_detail() {
+        let with_health = |name: &str, failures: u32| {
+            let mut p = info(name, None, 0);
+            p.health = Some(pm3::protocol::HealthReport {
+                passed: failures == 0,
+                checked_ago: 3,
+                latency_ms: 12,
+                http_status: Some(if failures == 0 { 200 } else { 503 }),
+                error: None,
+                failures,
+            });
+            p
+        };
+        let mut processes = vec![with_health("a", 0), info("b", None, 0), with_health("c", 4)];
+        sort_processes(&mut processes, &ListColumn::Health);
+        assert_eq!(names(&processes), vec!["c", "a", "b"]);
+        assert_eq!(
+            health_detail(processes[0].health.as_ref().unwrap()),
+            "HTTP 503, 12ms, 3s ago"
+        );
+
+        let refused = pm3::protocol::HealthReport {
+            passed: false,
+            checked_ago: 1,
+            latency_ms: 0,
+            http_status: None,
+            error: Some("Connection refused (os error 111)".to_string()),
+            failures: 2,
+        };
+        assert_eq!(
+            health_detail(&refused),
+            "Connection refused (os error 111), 0ms, 1s ago"
+        );
+    }
+
     #[test]
     fn test_format_uptime_none() {
         assert_eq!(format_uptime(None), "-");
@@ -327,4 +1856,21 @@ mod tests {
         assert_eq!(format_uptime(Some(90000)), "1d 1h");
         assert_eq!(format_uptime(Some(172800)), "2d 0h");
     }
+
+    #[test]
+    fn test_format_window() {
+        assert_eq!(format_window(7 * 86_400), "7d");
+        assert_eq!(format_window(36 * 3600), "36h");
+        assert_eq!(format_window(90 * 60), "90m");
+        assert_eq!(format_window(45), "45s");
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(
+            sparkline(&[Some(0.0), Some(50.0), None, Some(100.0)]),
+            "▁▅ █"
+        );
+        assert_eq!(sparkline(&[Some(0.0), None]), "▁ ");
+    }
 }
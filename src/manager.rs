@@ -1,38 +1,99 @@
-use crate::config::ProcessConfig;
+use crate::config::{ProcessConfig, ReadySignal};
 use crate::deps;
+use crate::dump::{self as dump_file, DumpEntry};
+use crate::events::{self, ProcessEvent};
 use crate::log;
+use crate::namespace as ns;
 use crate::paths::Paths;
 use crate::process::{self, ProcessTable};
 use crate::protocol::{self, ProcessStatus, Request, Response};
-use crate::{cron, health, memory, watch as file_watch};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::settings::Settings;
+use crate::{
+    audit, availability, conflict, cron, health, idle, memory, metrics, select, snapshot,
+    timeseries, upgrade, watch as file_watch,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
-use tokio::sync::{RwLock, watch};
+use tokio::sync::{RwLock, broadcast, mpsc, watch};
 
 #[derive(Clone)]
 pub struct Manager {
     paths: Paths,
     processes: Arc<RwLock<ProcessTable>>,
     stats_cache: Arc<RwLock<memory::StatsCache>>,
+    history: Arc<RwLock<timeseries::History>>,
+    restore_progress: Arc<RwLock<Option<protocol::RestoreProgress>>>,
+    cron_schedules: Arc<RwLock<HashMap<String, cron::StartStopSchedule>>>,
+    settings: Arc<Settings>,
+    events: broadcast::Sender<ProcessEvent>,
+    activity: Arc<idle::Activity>,
+    upgrader: Arc<upgrade::Upgrader>,
+    log_taps: Arc<LogTaps>,
+    started_at: Instant,
 }
 
 impl Manager {
-    pub fn new(paths: Paths) -> Self {
+    pub fn new(paths: Paths, settings: Settings) -> Self {
         Self {
             paths,
+            history: Arc::new(RwLock::new(timeseries::History::from_settings(
+                &settings.history,
+            ))),
+            settings: Arc::new(settings),
             processes: Arc::new(RwLock::new(HashMap::new())),
             stats_cache: Arc::new(RwLock::new(HashMap::new())),
+            restore_progress: Arc::new(RwLock::new(None)),
+            cron_schedules: Arc::new(RwLock::new(HashMap::new())),
+            events: broadcast::channel(events::EVENT_BUFFER).0,
+            activity: Arc::new(idle::Activity::default()),
+            upgrader: Arc::new(upgrade::Upgrader::default()),
+            log_taps: Arc::default(),
+            started_at: Instant::now(),
         }
     }
 
+    /// Lets `pm3 daemon upgrade` re-exec `exe`, carrying on the uptime and
+    /// upgrade count of the daemon that handed over to this one, if any.
+    pub fn enable_upgrade(
+        &mut self,
+        exe: std::path::PathBuf,
+        handover: Option<&upgrade::Handover>,
+    ) {
+        let upgrades = handover.map_or(0, |h| h.upgrades);
+        if let Some(handover) = handover {
+            let uptime = Duration::from_secs(handover.uptime);
+            self.started_at = Instant::now()
+                .checked_sub(uptime)
+                .unwrap_or(self.started_at);
+        }
+        self.upgrader = Arc::new(upgrade::Upgrader::new(exe, upgrades));
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
     pub fn paths(&self) -> &Paths {
         &self.paths
     }
 
+    /// Client requests in flight, for `[daemon] idle_timeout`.
+    pub fn activity(&self) -> Arc<idle::Activity> {
+        Arc::clone(&self.activity)
+    }
+
+    pub fn upgrader(&self) -> Arc<upgrade::Upgrader> {
+        Arc::clone(&self.upgrader)
+    }
+
+    /// Time since the daemon started, counting from before any upgrades.
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
     pub fn processes(&self) -> Arc<RwLock<ProcessTable>> {
         Arc::clone(&self.processes)
     }
@@ -41,6 +102,15 @@ impl Manager {
         Arc::clone(&self.stats_cache)
     }
 
+    pub fn history(&self) -> Arc<RwLock<timeseries::History>> {
+        Arc::clone(&self.history)
+    }
+
+    /// The sender process events are broadcast on; subscribe to receive them.
+    pub fn events(&self) -> broadcast::Sender<ProcessEvent> {
+        self.events.clone()
+    }
+
     pub async fn shutdown_all(&self) {
         let names: Vec<String> = {
             let table = self.processes.read().await;
@@ -61,27 +131,41 @@ impl Manager {
                     m.config
                         .post_stop
                         .as_ref()
-                        .map(|hook| (hook.clone(), m.config.cwd.clone()))
+                        .map(|hook| (hook.clone(), m.config.clone()))
                 })
             };
-            if let Some((hook, cwd)) = hook_info {
-                let _ = process::run_hook(&hook, name, cwd.as_deref(), &self.paths).await;
+            if let Some((hook, config)) = hook_info {
+                let _ = process::run_hook(&hook, name, &config, &self.paths).await;
             }
         }
     }
 
     pub async fn dispatch(&self, request: Request, shutdown_tx: &watch::Sender<bool>) -> Response {
         match request {
-            Request::Start {
-                configs,
+            request @ Request::Start { .. } => {
+                self.start_request(request, Progress::default()).await
+            }
+            Request::List { filter } => self.list(filter).await,
+            Request::Stop {
                 names,
-                env,
-                wait,
-                path,
-            } => self.start(configs, names, env, wait, path).await,
-            Request::List => self.list().await,
-            Request::Stop { names } => self.stop(names).await,
-            Request::Restart { names } => self.restart(names).await,
+                except,
+                namespace,
+                no_dependents,
+            } => self.stop(names, except, namespace, no_dependents).await,
+            Request::Restart {
+                names,
+                except,
+                namespace,
+                stagger_ms,
+            } => {
+                self.restart(
+                    names,
+                    except,
+                    namespace,
+                    stagger_ms.map(Duration::from_millis),
+                )
+                .await
+            }
             Request::Kill => {
                 let _ = shutdown_tx.send(true);
                 Response::Success {
@@ -90,31 +174,194 @@ impl Manager {
             }
             Request::Info { name } => self.info(name).await,
             Request::Signal { name, signal } => self.signal(name, signal).await,
-            Request::Flush { names } => self.flush(names).await,
+            Request::Flush {
+                names,
+                all_files,
+                namespace,
+            } => self.flush(names, all_files, namespace).await,
+            Request::ReopenLogs { names, namespace } => self.reopen_logs(names, namespace).await,
+            Request::Prune { logs, namespace } => self.prune(logs, namespace).await,
             Request::Log { .. } => Response::Error {
                 message: "unexpected dispatch for log".to_string(),
             },
-            Request::Reload { names, path } => self.reload(names, path).await,
-            Request::Save => self.save().await,
-            Request::Resurrect { path } => self.resurrect(path).await,
+            Request::Run { .. } => Response::Error {
+                message: "unexpected dispatch for run".to_string(),
+            },
+            Request::Events { .. } => Response::Error {
+                message: "unexpected dispatch for events".to_string(),
+            },
+            Request::Session => Response::Error {
+                message: "a session can't be opened inside another".to_string(),
+            },
+            Request::Reload {
+                names,
+                except,
+                namespace,
+                path,
+            } => self.reload(names, except, namespace, path).await,
+            Request::Save { tag } => self.save(tag).await,
+            Request::Resurrect { path, from } => self.resurrect(path, from).await,
+            Request::Snapshots => self.snapshots().await,
+            Request::Export { namespace } => self.export(namespace).await,
+            Request::Suspend { name } => self.set_suspended(name, true).await,
+            Request::Continue { name } => self.set_suspended(name, false).await,
+            Request::Pause { names, namespace } => self.set_paused(names, namespace, true).await,
+            Request::Resume { names, namespace } => self.set_paused(names, namespace, false).await,
+            Request::Upgrade => self.upgrade().await,
+            Request::Wait {
+                names,
+                status,
+                timeout_ms,
+                namespace,
+            } => {
+                self.wait(names, status, Duration::from_millis(timeout_ms), namespace)
+                    .await
+            }
+            Request::DaemonInfo => Response::DaemonInfo {
+                info: protocol::DaemonInfo {
+                    pid: std::process::id(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    uptime: self.uptime().as_secs(),
+                    processes: self.processes.read().await.len(),
+                    data_dir: self.paths.data_dir().display().to_string(),
+                    upgrades: self.upgrader.upgrades(),
+                },
+            },
+            Request::Stats { name, since_secs } => self.stats(name, since_secs).await,
+            Request::Report { name, windows_secs } => self.report(name, windows_secs).await,
+            Request::Tree { name } => self.tree(name).await,
+            Request::History { lines } => match audit::read_recent(&self.paths, lines) {
+                Ok(entries) => Response::History { entries },
+                Err(e) => Response::Error {
+                    message: format!("failed to read audit log: {e}"),
+                },
+            },
         }
     }
 
-    pub async fn list(&self) -> Response {
+    pub async fn list(&self, filter: Option<protocol::ListFilter>) -> Response {
+        let restoring = self.restore_progress.read().await.clone();
         let table = self.processes.read().await;
         let cache = self.stats_cache.read().await;
-        let infos: Vec<_> = table.values().map(|m| m.to_process_info(&cache)).collect();
-        Response::ProcessList { processes: infos }
+        let infos: Vec<_> = table
+            .values()
+            .filter(|m| {
+                filter
+                    .as_ref()
+                    .is_none_or(|f| f.matches(&m.name, m.status, &m.config))
+            })
+            .map(|m| m.to_process_info(&cache))
+            .collect();
+        Response::ProcessList {
+            processes: infos,
+            restoring,
+        }
+    }
+
+    /// Handles a `Request::Start`, reporting `--wait` progress to `progress`.
+    pub async fn start_request(&self, request: Request, progress: Progress) -> Response {
+        let Request::Start {
+            configs,
+            names,
+            env,
+            wait,
+            path,
+            force,
+            namespace,
+            ..
+        } = request
+        else {
+            return Response::Error {
+                message: "unexpected dispatch for start".to_string(),
+            };
+        };
+        let (configs, names) = match namespace {
+            Some(namespace) => match scope_start(configs, names, &namespace) {
+                Ok(scoped) => scoped,
+                Err(message) => return Response::Error { message },
+            },
+            None => (configs, names),
+        };
+        self.start(configs, names, env, wait.then_some(progress), path, force)
+            .await
+    }
+
+    /// Runs the `conflict_check` of each process in `level` that isn't
+    /// already running. They run without holding the process table, since a
+    /// check is a user command that may take a while.
+    async fn check_conflicts(
+        &self,
+        level: &[String],
+        configs: &HashMap<String, ProcessConfig>,
+    ) -> Result<(), String> {
+        let (managed_pids, checks): (HashSet<u32>, Vec<(&String, &String)>) = {
+            let table = self.processes.read().await;
+            let checks = level
+                .iter()
+                .filter(|name| table.get(*name).is_none_or(is_down))
+                .filter_map(|name| Some((name, configs.get(name)?.conflict_check.as_ref()?)))
+                .collect();
+            (table.values().filter_map(|p| p.pid).collect(), checks)
+        };
+        if checks.is_empty() {
+            return Ok(());
+        }
+        let managed_pids = conflict::with_descendants(managed_pids).await;
+        for (name, check) in checks {
+            let found = tokio::time::timeout(
+                conflict::CONFLICT_CHECK_TIMEOUT,
+                conflict::find_conflict(check, &managed_pids),
+            )
+            .await
+            .map_err(|_| {
+                format!(
+                    "conflict check for '{name}' timed out after {}s",
+                    conflict::CONFLICT_CHECK_TIMEOUT.as_secs()
+                )
+            })?;
+            match found {
+                Ok(None) => {}
+                Ok(Some(found)) => {
+                    return Err(format!(
+                        "refusing to start '{name}': {found} (use --force to override)"
+                    ));
+                }
+                Err(e) => return Err(format!("conflict check for '{name}' failed: {e}")),
+            }
+        }
+        Ok(())
     }
 
+    /// Starts processes level by level in dependency order. Every level but
+    /// the last is awaited before the next; with `wait` the last one is too,
+    /// and each level's progress is reported.
     pub async fn start(
         &self,
         configs: HashMap<String, ProcessConfig>,
         names: Option<Vec<String>>,
         env: Option<String>,
-        wait: bool,
+        wait: Option<Progress>,
         path: Option<String>,
+        force: bool,
     ) -> Response {
+        let mut configs = configs;
+        // Applied before expanding, so an environment can change `instances`.
+        let mut in_env = HashSet::new();
+        if let Some(ref env_name) = env {
+            for (name, config) in &mut configs {
+                match config.apply_environment(name, env_name) {
+                    Ok(true) => {
+                        in_env.insert(name.clone());
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        return Response::Error {
+                            message: e.to_string(),
+                        };
+                    }
+                }
+            }
+        }
         let configs = expand_instances(configs);
 
         let mut to_start: Vec<(String, ProcessConfig)> = match names {
@@ -135,12 +382,12 @@ impl Manager {
         };
 
         if let Some(ref env_name) = env {
-            let mut any_applied = false;
-            for (_, config) in &mut to_start {
-                if config.apply_environment(env_name) {
-                    any_applied = true;
-                }
-            }
+            let any_applied = to_start.iter().any(|(name, _)| {
+                let base = name
+                    .rsplit_once(':')
+                    .map_or(name.as_str(), |(base, _)| base);
+                in_env.contains(base)
+            });
             if !any_applied {
                 return Response::Error {
                     message: format!("unknown environment: '{}'", env_name),
@@ -169,50 +416,78 @@ impl Manager {
             }
         };
 
+        let optional = deps::optional_only(&subset_configs);
         let mut started = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
 
         for (level_idx, level) in levels.iter().enumerate() {
             let mut spawned: Vec<SpawnedProcess> = Vec::new();
             let mut level_names: Vec<String> = Vec::new();
+            let mut failure: Option<String> = None;
+
+            if !force && let Err(message) = self.check_conflicts(level, &subset_configs).await {
+                return Response::Error { message };
+            }
+
+            let concurrency = self.settings.daemon.spawn_concurrency;
+            let candidates: Vec<(String, ProcessConfig)> = {
+                let table = self.processes.read().await;
+                level
+                    .iter()
+                    .filter(|name| table.get(*name).is_none_or(is_down))
+                    .map(|name| (name.clone(), subset_configs[name].clone()))
+                    .collect()
+            };
+            let (resolved, unresolved) = resolve_batch(candidates, concurrency).await;
 
             {
                 let mut table = self.processes.write().await;
-                for name in level {
+                let mut batch: SpawnBatch = Vec::new();
+                let mut previous: HashMap<String, (Option<u32>, bool)> = HashMap::new();
+                for (name, config, env_cmd) in resolved {
                     let mut old_restarts = None;
-                    if let Some(existing) = table.get(name) {
+                    let mut paused = false;
+                    // Something else may have started it while its
+                    // `env_cmd` ran
+                    if let Some(existing) = table.get(&name) {
+                        paused = existing.paused;
                         match existing.status {
-                            ProcessStatus::Stopped | ProcessStatus::Errored => {
+                            ProcessStatus::Stopped
+                            | ProcessStatus::Errored
+                            | ProcessStatus::Flapping
+                            | ProcessStatus::Succeeded
+                            | ProcessStatus::Failed => {
                                 old_restarts = Some(existing.restarts);
                             }
                             _ => continue,
                         }
                     }
-                    let config = subset_configs.get(name).unwrap().clone();
-                    match process::spawn_process(name.clone(), config.clone(), &self.paths).await {
+                    previous.insert(name.clone(), (old_restarts, paused));
+                    batch.push((name, config, env_cmd));
+                }
+
+                let results = spawn_batch(batch, concurrency, &self.log_taps, &self.paths).await;
+                for (name, config, result) in unresolved.into_iter().chain(results) {
+                    match result {
                         Ok((mut managed, child)) => {
+                            let (old_restarts, paused) = previous[&name];
                             if let Some(previous) = old_restarts {
                                 managed.restarts = previous;
                             }
-                            let pid = managed.pid;
-                            let shutdown_tx = managed
-                                .monitor_shutdown
-                                .as_ref()
-                                .expect("monitor shutdown sender missing")
-                                .clone();
+                            managed.paused = paused;
+                            spawned.push(SpawnedProcess::new(&name, &managed, child, config));
                             table.insert(name.clone(), managed);
-                            spawned.push(SpawnedProcess {
-                                name: name.clone(),
-                                child,
-                                pid,
-                                config,
-                                shutdown_tx,
-                            });
-                            level_names.push(name.clone());
+                            level_names.push(name);
+                        }
+                        Err(e) if optional.contains(&name) => {
+                            let warning = format!(
+                                "failed to start '{name}': {e}, starting dependents without it"
+                            );
+                            tracing::warn!("{warning}");
+                            warnings.push(warning);
                         }
                         Err(e) => {
-                            return Response::Error {
-                                message: format!("failed to start '{}': {}", name, e),
-                            };
+                            failure.get_or_insert(format!("failed to start '{}': {}", name, e));
                         }
                     }
                 }
@@ -221,16 +496,35 @@ impl Manager {
             for spawned_process in spawned {
                 spawned_process.spawn_monitors(Arc::clone(&self.processes), self.paths.clone());
             }
+            if let Some(message) = failure {
+                return Response::Error { message };
+            }
+
+            for name in &level_names {
+                self.schedule_start_stop(name, &subset_configs[name]).await;
+            }
 
             started.extend(level_names.clone());
 
             let is_last_level = level_idx == levels.len() - 1;
-            let should_wait = !is_last_level || wait;
-            if should_wait
-                && !level_names.is_empty()
-                && let Err(msg) = wait_for_online(&level_names, &self.processes).await
-            {
-                return Response::Error { message: msg };
+            let should_wait = !is_last_level || wait.is_some();
+            if should_wait && !level_names.is_empty() {
+                if let Some(progress) = &wait {
+                    progress.report(format!("starting {}…", level_names.join(", ")));
+                }
+                match wait_for_level(&level_names, &optional, &self.processes).await {
+                    Ok(None) => {}
+                    Ok(Some(warning)) => {
+                        if let Some(progress) = &wait {
+                            progress.report(format!("warning: {warning}"));
+                        }
+                        warnings.push(warning);
+                    }
+                    Err(message) => return Response::Error { message },
+                }
+                if let Some(progress) = &wait {
+                    progress.report(format!("online: {}", level_names.join(", ")));
+                }
             }
         }
 
@@ -245,6 +539,7 @@ impl Manager {
             let table = self.processes.read().await;
             let failures: Vec<&String> = started
                 .iter()
+                .filter(|name| !optional.contains(*name))
                 .filter(|name| {
                     table
                         .get(*name)
@@ -265,20 +560,100 @@ impl Manager {
             }
         }
 
+        let mut message = format!("started: {}", started.join(", "));
+        for warning in &warnings {
+            message.push_str(&format!(" (warning: {warning})"));
+        }
         Response::Success {
-            message: Some(format!("started: {}", started.join(", "))),
+            message: Some(message),
         }
     }
 
-    pub async fn stop(&self, names: Option<Vec<String>>) -> Response {
-        let mut table = self.processes.write().await;
+    /// Starts a process on its `cron_start` schedule, bringing up any stopped
+    /// dependencies first.
+    pub async fn scheduled_start(&self, name: &str) -> Response {
+        let configs: HashMap<String, ProcessConfig> = {
+            let table = self.processes.read().await;
+            table
+                .iter()
+                .map(|(k, v)| (k.clone(), v.config.clone()))
+                .collect()
+        };
 
-        let targets: Vec<String> = match names {
-            Some(ref requested) => match resolve_table_names(requested, &table) {
-                Ok(r) => r,
-                Err(msg) => return Response::Error { message: msg },
+        let names = match deps::expand_deps(&[name.to_string()], &configs) {
+            Ok(names) => names,
+            Err(e) => {
+                return Response::Error {
+                    message: e.to_string(),
+                };
+            }
+        };
+
+        self.start(configs, Some(names), None, None, None, false)
+            .await
+    }
+
+    /// Spawns (or replaces) the `cron_start` / `cron_stop` scheduler for a
+    /// process. A scheduler whose expressions are unchanged is left running.
+    async fn schedule_start_stop(&self, name: &str, config: &ProcessConfig) {
+        let mut schedules = self.cron_schedules.write().await;
+        if let Some(existing) = schedules.get(name)
+            && existing.cron_start == config.cron_start
+            && existing.cron_stop == config.cron_stop
+            && existing.cron_timezone == config.cron_timezone
+        {
+            return;
+        }
+        if let Some(old) = schedules.remove(name) {
+            let _ = old.shutdown.send(true);
+        }
+        if config.cron_start.is_none() && config.cron_stop.is_none() {
+            return;
+        }
+
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        cron::spawn_cron_start_stop(
+            name.to_string(),
+            config.cron_start.clone(),
+            config.cron_stop.clone(),
+            config.cron_timezone.clone(),
+            self.clone(),
+            shutdown_rx,
+        );
+        schedules.insert(
+            name.to_string(),
+            cron::StartStopSchedule {
+                cron_start: config.cron_start.clone(),
+                cron_stop: config.cron_stop.clone(),
+                cron_timezone: config.cron_timezone.clone(),
+                shutdown: shutdown_tx,
             },
-            None => table.keys().cloned().collect(),
+        );
+    }
+
+    /// Stops the targets and, unless `no_dependents` is set or a target has
+    /// `stop_dependents = false`, everything that depends on them.
+    pub async fn stop(
+        &self,
+        names: Option<Vec<String>>,
+        except: Option<Vec<String>>,
+        namespace: Option<String>,
+        no_dependents: bool,
+    ) -> Response {
+        let mut table = self.processes.write().await;
+
+        let targets: Vec<String> = match resolve_targets(
+            names.as_deref(),
+            except.as_deref(),
+            &table,
+            namespace.as_deref(),
+        ) {
+            Ok(r) => r,
+            Err(msg) => return Response::Error { message: msg },
+        };
+        let excluded = match resolve_excluded(except.as_deref(), &table, namespace.as_deref()) {
+            Ok(r) => r,
+            Err(msg) => return Response::Error { message: msg },
         };
 
         let running_configs: HashMap<String, ProcessConfig> = table
@@ -286,14 +661,30 @@ impl Manager {
             .map(|(k, v)| (k.clone(), v.config.clone()))
             .collect();
 
-        let stop_order = match deps::expand_dependents(&targets, &running_configs) {
-            Ok(order) => order,
-            Err(e) => {
-                return Response::Error {
-                    message: e.to_string(),
-                };
-            }
-        };
+        let (cascading, alone): (Vec<String>, Vec<String>) =
+            targets.into_iter().partition(|name| {
+                !no_dependents
+                    && running_configs
+                        .get(name)
+                        .is_none_or(|c| c.stop_dependents != Some(false))
+            });
+        let stop_order =
+            match deps::expand_dependents(&cascading, &running_configs).and_then(|mut names| {
+                for name in alone {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                names.retain(|name| !excluded.contains(name));
+                deps::stop_order_within(&names, &running_configs)
+            }) {
+                Ok(order) => order,
+                Err(e) => {
+                    return Response::Error {
+                        message: e.to_string(),
+                    };
+                }
+            };
 
         let mut stopped = Vec::new();
         for name in &stop_order {
@@ -310,8 +701,7 @@ impl Manager {
                 };
             }
             if let Some(ref hook) = managed.config.post_stop {
-                let _ =
-                    process::run_hook(hook, name, managed.config.cwd.as_deref(), &self.paths).await;
+                let _ = process::run_hook(hook, name, &managed.config, &self.paths).await;
             }
             stopped.push(name.clone());
         }
@@ -321,16 +711,31 @@ impl Manager {
         }
     }
 
-    pub async fn restart(&self, names: Option<Vec<String>>) -> Response {
-        let (targets, restart_configs) = {
+    /// Restarts the targets and their dependents. With `stagger` (or a
+    /// process's `restart_stagger`), respawns are spaced out instead of all
+    /// happening at once.
+    pub async fn restart(
+        &self,
+        names: Option<Vec<String>>,
+        except: Option<Vec<String>>,
+        namespace: Option<String>,
+        stagger: Option<Duration>,
+    ) -> Response {
+        let (targets, excluded, restart_configs) = {
             let table = self.processes.read().await;
 
-            let targets: Vec<String> = match names {
-                Some(ref requested) => match resolve_table_names(requested, &table) {
-                    Ok(r) => r,
-                    Err(msg) => return Response::Error { message: msg },
-                },
-                None => table.keys().cloned().collect(),
+            let targets: Vec<String> = match resolve_targets(
+                names.as_deref(),
+                except.as_deref(),
+                &table,
+                namespace.as_deref(),
+            ) {
+                Ok(r) => r,
+                Err(msg) => return Response::Error { message: msg },
+            };
+            let excluded = match resolve_excluded(except.as_deref(), &table, namespace.as_deref()) {
+                Ok(r) => r,
+                Err(msg) => return Response::Error { message: msg },
             };
 
             let running_configs: HashMap<String, ProcessConfig> = table
@@ -338,11 +743,14 @@ impl Manager {
                 .map(|(k, v)| (k.clone(), v.config.clone()))
                 .collect();
 
-            (targets, running_configs)
+            (targets, excluded, running_configs)
         };
 
         let stop_order = match deps::expand_dependents(&targets, &restart_configs) {
-            Ok(order) => order,
+            Ok(mut order) => {
+                order.retain(|name| !excluded.contains(name));
+                order
+            }
             Err(e) => {
                 return Response::Error {
                     message: e.to_string(),
@@ -350,6 +758,39 @@ impl Manager {
             }
         };
 
+        let subset_configs: HashMap<String, ProcessConfig> = restart_configs
+            .iter()
+            .filter(|(k, _)| stop_order.contains(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let optional = deps::optional_only(&subset_configs);
+
+        // Resolved before anything is stopped, so a slow `env_cmd` neither
+        // holds the process table nor lengthens the downtime.
+        let concurrency = self.settings.daemon.spawn_concurrency;
+        let candidates = subset_configs
+            .iter()
+            .map(|(name, config)| (name.clone(), config.clone()))
+            .collect();
+        let (resolved, unresolved) = resolve_batch(candidates, concurrency).await;
+        let mut env_cmds: HashMap<String, process::EnvCmdValues> = resolved
+            .into_iter()
+            .map(|(name, _, env_cmd)| (name, env_cmd))
+            .collect();
+        let mut warnings: Vec<String> = Vec::new();
+        for (name, _, result) in unresolved {
+            let Err(e) = result else { continue };
+            if !optional.contains(&name) {
+                return Response::Error {
+                    message: format!("failed to restart '{}': {}", name, e),
+                };
+            }
+            let warning =
+                format!("failed to restart '{name}': {e}, starting dependents without it");
+            tracing::warn!("{warning}");
+            warnings.push(warning);
+        }
+
         let mut old_restarts_map: HashMap<String, u32> = HashMap::new();
         {
             let mut table = self.processes.write().await;
@@ -368,19 +809,11 @@ impl Manager {
                     };
                 }
                 if let Some(ref hook) = managed.config.post_stop {
-                    let _ =
-                        process::run_hook(hook, name, managed.config.cwd.as_deref(), &self.paths)
-                            .await;
+                    let _ = process::run_hook(hook, name, &managed.config, &self.paths).await;
                 }
             }
         }
 
-        let subset_configs: HashMap<String, ProcessConfig> = restart_configs
-            .iter()
-            .filter(|(k, _)| stop_order.contains(k))
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
-
         let levels = match deps::topological_levels(&subset_configs) {
             Ok(l) => l,
             Err(e) => {
@@ -390,77 +823,119 @@ impl Manager {
             }
         };
 
-        let mut restarted = Vec::new();
+        let mut restarted: Vec<String> = Vec::new();
 
         for (level_idx, level) in levels.iter().enumerate() {
-            let mut spawned: Vec<SpawnedProcess> = Vec::new();
             let mut level_names: Vec<String> = Vec::new();
 
-            {
-                let mut table = self.processes.write().await;
-                for name in level {
-                    let config = match subset_configs.get(name) {
-                        Some(c) => c.clone(),
-                        None => continue,
-                    };
-                    let old_restarts = old_restarts_map.get(name).copied().unwrap_or(0);
-                    match process::spawn_process(name.clone(), config.clone(), &self.paths).await {
-                        Ok((mut new_managed, child)) => {
-                            new_managed.restarts = old_restarts + 1;
-                            let pid = new_managed.pid;
-                            let shutdown_tx = new_managed
-                                .monitor_shutdown
-                                .as_ref()
-                                .expect("monitor shutdown sender missing")
-                                .clone();
-                            table.insert(name.clone(), new_managed);
-                            spawned.push(SpawnedProcess {
-                                name: name.clone(),
-                                child,
-                                pid,
-                                config,
-                                shutdown_tx,
-                            });
-                            level_names.push(name.clone());
-                        }
-                        Err(e) => {
-                            return Response::Error {
-                                message: format!("failed to restart '{}': {}", name, e),
-                            };
+            let configs: SpawnBatch = level
+                .iter()
+                .filter_map(|name| {
+                    let config = subset_configs.get(name)?.clone();
+                    Some((name.clone(), config, env_cmds.remove(name)?))
+                })
+                .collect();
+            // Staggered processes are respawned one at a time with a pause
+            // before each; the rest of the level is spawned together.
+            let (staggered, together): (Vec<_>, Vec<_>) = configs
+                .into_iter()
+                .partition(|(_, config, _)| stagger.is_some() || config.restart_stagger.is_some());
+            let mut batches: Vec<(Option<Duration>, SpawnBatch)> = Vec::new();
+            if !together.is_empty() {
+                batches.push((None, together));
+            }
+            for (name, config, env_cmd) in staggered {
+                let delay = stagger.or(config.restart_stagger.map(Duration::from_millis));
+                batches.push((delay, vec![(name, config, env_cmd)]));
+            }
+
+            for (delay, batch) in batches {
+                if let Some(delay) = delay
+                    && !(restarted.is_empty() && level_names.is_empty())
+                {
+                    tokio::time::sleep(delay).await;
+                }
+
+                let results = spawn_batch(batch, concurrency, &self.log_taps, &self.paths).await;
+                let mut spawned: Vec<SpawnedProcess> = Vec::new();
+                let mut failure: Option<String> = None;
+                {
+                    let mut table = self.processes.write().await;
+                    for (name, config, result) in results {
+                        match result {
+                            Ok((mut new_managed, child)) => {
+                                let old_restarts =
+                                    old_restarts_map.get(&name).copied().unwrap_or(0);
+                                new_managed.restarts = old_restarts + 1;
+                                new_managed.paused = table.get(&name).is_some_and(|m| m.paused);
+                                spawned.push(SpawnedProcess::new(
+                                    &name,
+                                    &new_managed,
+                                    child,
+                                    config,
+                                ));
+                                table.insert(name.clone(), new_managed);
+                                level_names.push(name);
+                            }
+                            Err(e) if optional.contains(&name) => {
+                                let warning = format!(
+                                    "failed to restart '{name}': {e}, starting dependents without it"
+                                );
+                                tracing::warn!("{warning}");
+                                warnings.push(warning);
+                            }
+                            Err(e) => {
+                                failure
+                                    .get_or_insert(format!("failed to restart '{}': {}", name, e));
+                            }
                         }
                     }
                 }
-            }
-
-            for spawned_process in spawned {
-                spawned_process.spawn_monitors(Arc::clone(&self.processes), self.paths.clone());
+                for spawned_process in spawned {
+                    spawned_process.spawn_monitors(Arc::clone(&self.processes), self.paths.clone());
+                }
+                if let Some(message) = failure {
+                    return Response::Error { message };
+                }
             }
 
             restarted.extend(level_names.clone());
 
             let is_last_level = level_idx == levels.len() - 1;
-            if !is_last_level
-                && !level_names.is_empty()
-                && let Err(msg) = wait_for_online(&level_names, &self.processes).await
-            {
-                return Response::Error { message: msg };
+            if !is_last_level && !level_names.is_empty() {
+                match wait_for_level(&level_names, &optional, &self.processes).await {
+                    Ok(warning) => warnings.extend(warning),
+                    Err(message) => return Response::Error { message },
+                }
             }
         }
 
+        let mut message = format!("restarted: {}", restarted.join(", "));
+        for warning in &warnings {
+            message.push_str(&format!(" (warning: {warning})"));
+        }
         Response::Success {
-            message: Some(format!("restarted: {}", restarted.join(", "))),
+            message: Some(message),
         }
     }
 
-    pub async fn reload(&self, names: Option<Vec<String>>, path: Option<String>) -> Response {
+    pub async fn reload(
+        &self,
+        names: Option<Vec<String>>,
+        except: Option<Vec<String>>,
+        namespace: Option<String>,
+        path: Option<String>,
+    ) -> Response {
         let targets = {
             let table = self.processes.read().await;
-            let targets: Vec<String> = match names {
-                Some(ref requested) => match resolve_table_names(requested, &table) {
-                    Ok(r) => r,
-                    Err(msg) => return Response::Error { message: msg },
-                },
-                None => table.keys().cloned().collect(),
+            let targets: Vec<String> = match resolve_targets(
+                names.as_deref(),
+                except.as_deref(),
+                &table,
+                namespace.as_deref(),
+            ) {
+                Ok(r) => r,
+                Err(msg) => return Response::Error { message: msg },
             };
             targets
         };
@@ -483,9 +958,7 @@ impl Manager {
             let table = self.processes.read().await;
             for name in &targets {
                 if let Some(managed) = table.get(name) {
-                    if managed.config.readiness_check.is_some()
-                        || managed.config.health_check.is_some()
-                    {
+                    if managed.config.has_startup_probe() {
                         with_checks.push((name.clone(), managed.config.clone(), managed.restarts));
                     } else {
                         without_checks.push(name.clone());
@@ -530,7 +1003,7 @@ impl Manager {
 
                     health::spawn_startup_checker(
                         temp_name.clone(),
-                        config.readiness_check.clone(),
+                        health::Readiness::for_process(&temp_name, &config, &self.paths),
                         config.readiness_timeout,
                         config.health_check.clone(),
                         Arc::clone(&self.processes),
@@ -539,23 +1012,52 @@ impl Manager {
 
                     match wait_for_online(std::slice::from_ref(&temp_name), &self.processes).await {
                         Ok(()) => {
+                            // Let the old instance finish in-flight work before it is stopped
+                            let draining = {
+                                let table = self.processes.read().await;
+                                table
+                                    .get(&name)
+                                    .and_then(|old| Some((old.pid?, old.drain()?)))
+                            };
+                            if let Some((old_pid, drain_timeout)) = draining {
+                                let deadline = tokio::time::Instant::now() + drain_timeout;
+                                while is_pid_alive(old_pid)
+                                    && tokio::time::Instant::now() < deadline
+                                {
+                                    tokio::time::sleep(Duration::from_millis(50)).await;
+                                }
+                            }
+
                             let mut table = self.processes.write().await;
 
+                            let mut restart_history = Vec::new();
+                            let mut paused = false;
+                            let mut crashes = 0;
+                            let mut health = None;
                             if let Some(old_managed) = table.get_mut(&name) {
+                                restart_history = std::mem::take(&mut old_managed.restart_history);
+                                paused = old_managed.paused;
+                                crashes = old_managed.crashes;
+                                health = old_managed.health.take();
                                 let _ = old_managed.graceful_stop().await;
                                 if let Some(ref hook) = config.post_stop {
-                                    let _ = process::run_hook(
-                                        hook,
-                                        &name,
-                                        config.cwd.as_deref(),
-                                        &self.paths,
-                                    )
-                                    .await;
+                                    let _ =
+                                        process::run_hook(hook, &name, &config, &self.paths).await;
                                 }
                             }
 
+                            // Promote under the same lock so the final name is never missing
+                            // or reported as Starting; the exit monitor follows the PID.
                             if let Some(mut new_managed) = table.remove(&temp_name) {
                                 new_managed.name = name.clone();
+                                new_managed.restart_history = restart_history;
+                                new_managed.paused = paused;
+                                new_managed.crashes = crashes;
+                                // Keep the latest probe, and its streak, until the
+                                // promoted process's own checker records one
+                                if new_managed.health.is_none() {
+                                    new_managed.health = health;
+                                }
                                 let shutdown_tx = new_managed
                                     .monitor_shutdown
                                     .as_ref()
@@ -565,15 +1067,21 @@ impl Manager {
                                 drop(table);
 
                                 // Attach remaining monitors after swap
-                                if let Some(mm) = max_memory.clone() {
+                                if max_memory.is_some() {
                                     memory::spawn_memory_monitor(
                                         name.clone(),
-                                        mm,
+                                        config.clone(),
                                         Arc::clone(&self.processes),
                                         self.paths.clone(),
                                         shutdown_tx.subscribe(),
                                     );
                                 }
+                                health::spawn_liveness_checker(
+                                    name.clone(),
+                                    config.clone(),
+                                    Arc::clone(&self.processes),
+                                    shutdown_tx.subscribe(),
+                                );
                                 file_watch::spawn_watcher(
                                     name.clone(),
                                     config.clone(),
@@ -581,10 +1089,22 @@ impl Manager {
                                     self.paths.clone(),
                                     shutdown_tx.subscribe(),
                                 );
+                                if let (Some(endpoint), Some(wanted)) =
+                                    (config.metrics_endpoint.clone(), config.metrics.clone())
+                                {
+                                    metrics::spawn_metrics_scraper(
+                                        name.clone(),
+                                        endpoint,
+                                        wanted,
+                                        Arc::clone(&self.processes),
+                                        shutdown_tx.subscribe(),
+                                    );
+                                }
                                 if let Some(cr) = cron_restart.clone() {
                                     cron::spawn_cron_restart(
                                         name.clone(),
                                         cr,
+                                        config.cron_timezone.clone(),
                                         Arc::clone(&self.processes),
                                         self.paths.clone(),
                                         shutdown_tx.subscribe(),
@@ -611,7 +1131,10 @@ impl Manager {
         }
 
         if !without_checks.is_empty() {
-            match self.restart(Some(without_checks.clone())).await {
+            match self
+                .restart(Some(without_checks.clone()), None, None, None)
+                .await
+            {
                 Response::Success { .. } => {
                     reloaded.extend(without_checks);
                 }
@@ -636,55 +1159,404 @@ impl Manager {
         Response::Success { message: Some(msg) }
     }
 
-    pub async fn save(&self) -> Response {
-        let table = self.processes.read().await;
-
-        let entries: Vec<DumpEntry> = table
-            .values()
-            .map(|managed| DumpEntry {
-                name: managed.name.clone(),
-                config: managed.config.clone(),
-                pid: managed.pid,
-                restarts: managed.restarts,
-            })
-            .collect();
-
-        drop(table);
+    pub async fn set_paused(
+        &self,
+        names: Option<Vec<String>>,
+        namespace: Option<String>,
+        paused: bool,
+    ) -> Response {
+        let mut table = self.processes.write().await;
 
-        let json = match serde_json::to_string_pretty(&entries) {
-            Ok(j) => j,
-            Err(e) => {
-                return Response::Error {
-                    message: format!("failed to serialize state: {}", e),
-                };
-            }
+        let targets: Vec<String> = match names {
+            Some(ref requested) => match resolve_table_names(requested, &table, None) {
+                Ok(r) => r,
+                Err(msg) => return Response::Error { message: msg },
+            },
+            None => namespace_keys(&table, namespace.as_deref()),
         };
 
-        if let Err(e) = fs::write(self.paths.dump_file(), json.as_bytes()).await {
+        let mut changed = Vec::new();
+        for name in &targets {
+            if let Some(managed) = table.get_mut(name)
+                && managed.paused != paused
+            {
+                managed.paused = paused;
+                changed.push(name.clone());
+            }
+        }
+
+        let verb = if paused { "paused" } else { "resumed" };
+        if changed.is_empty() {
+            return Response::Success {
+                message: Some(format!("nothing to do: already {verb}")),
+            };
+        }
+        changed.sort();
+        tracing::info!("monitoring {verb} for: {}", changed.join(", "));
+        Response::Success {
+            message: Some(format!("{verb}: {}", changed.join(", "))),
+        }
+    }
+
+    /// Blocks until every named process reaches `status`, for `pm3 wait`.
+    pub async fn wait(
+        &self,
+        names: Vec<String>,
+        status: ProcessStatus,
+        timeout: Duration,
+        namespace: Option<String>,
+    ) -> Response {
+        let targets = {
+            let table = self.processes.read().await;
+            match resolve_table_names(&names, &table, namespace.as_deref()) {
+                Ok(r) => r,
+                Err(message) => return Response::Error { message },
+            }
+        };
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        loop {
+            let pending: Vec<String> = {
+                let table = self.processes.read().await;
+                let mut pending = Vec::new();
+                for name in &targets {
+                    let Some(managed) = table.get(name) else {
+                        return Response::Error {
+                            message: format!("process '{name}' was removed while waiting"),
+                        };
+                    };
+                    match wait_satisfied(managed.status, status) {
+                        Ok(true) => {}
+                        Ok(false) => pending.push(format!("{name}: {}", managed.status)),
+                        Err(()) => {
+                            return Response::Error {
+                                message: format!(
+                                    "'{name}' will not become {status} (status: {})",
+                                    managed.status
+                                ),
+                            };
+                        }
+                    }
+                }
+                pending
+            };
+
+            if pending.is_empty() {
+                return Response::Success {
+                    message: Some(format!("{status}: {}", targets.join(", "))),
+                };
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Response::Error {
+                    message: format!("timeout waiting for {status} ({})", pending.join(", ")),
+                };
+            }
+            tokio::time::sleep(DEP_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Freezes (SIGSTOP) or thaws (SIGCONT) a process and its children.
+    /// Uptime does not advance while a process is suspended. For a cluster or
+    /// group, nothing is signalled unless every member is running.
+    pub async fn set_suspended(&self, name: String, suspend: bool) -> Response {
+        let mut table = self.processes.write().await;
+
+        let targets = match resolve_table_names(std::slice::from_ref(&name), &table, None) {
+            Ok(r) => r,
+            Err(msg) => return Response::Error { message: msg },
+        };
+
+        // Every target is checked before any is signalled, so a group isn't
+        // left half frozen
+        let mut pending = Vec::new();
+        for target in &targets {
+            let Some(managed) = table.get(target) else {
+                continue;
+            };
+            let Some(pid) = managed.pid else {
+                return Response::Error {
+                    message: format!("process '{target}' is not running"),
+                };
+            };
+            if managed.suspended.is_some() != suspend {
+                pending.push((target.clone(), pid));
+            }
+        }
+
+        let signal = |pid: u32, suspend: bool| match suspend {
+            true => crate::sys::suspend_process_tree(pid),
+            false => crate::sys::resume_process_tree(pid),
+        };
+        for (i, (target, pid)) in pending.iter().enumerate() {
+            if let Err(e) = signal(*pid, suspend) {
+                // Undo the ones already signalled
+                for (_, pid) in &pending[..i] {
+                    let _ = signal(*pid, !suspend);
+                }
+                let verb = if suspend { "suspend" } else { "continue" };
+                return Response::Error {
+                    message: format!("failed to {verb} '{target}': {e}"),
+                };
+            }
+        }
+
+        let mut changed = Vec::new();
+        for (target, _) in pending {
+            let Some(managed) = table.get_mut(&target) else {
+                continue;
+            };
+            if suspend {
+                managed.suspended = Some((managed.status, tokio::time::Instant::now()));
+                managed.status = ProcessStatus::Suspended;
+            } else if let Some((previous, since)) = managed.suspended.take() {
+                managed.started_at += since.elapsed();
+                managed.status = previous;
+            }
+            changed.push(target);
+        }
+
+        let verb = if suspend { "suspended" } else { "continued" };
+        if changed.is_empty() {
+            let state = if suspend { "suspended" } else { "running" };
+            return Response::Success {
+                message: Some(format!("nothing to do: already {state}")),
+            };
+        }
+        changed.sort();
+        Response::Success {
+            message: Some(format!("{verb}: {}", changed.join(", "))),
+        }
+    }
+
+    /// Whether monitoring is paused for a process.
+    pub async fn is_paused(&self, name: &str) -> bool {
+        let table = self.processes.read().await;
+        table.get(name).is_some_and(|m| m.paused)
+    }
+
+    /// Writes the dump file and, unless turned off, a snapshot of it,
+    /// optionally tagged.
+    pub async fn save(&self, tag: Option<String>) -> Response {
+        let keep = self.settings.daemon.keep_snapshots;
+        if let Some(tag) = &tag {
+            if keep == 0 {
+                return Response::Error {
+                    message: "snapshots are turned off (keep_snapshots = 0 in settings.toml)"
+                        .to_string(),
+                };
+            }
+            if let Err(message) = snapshot::validate_tag(tag) {
+                return Response::Error { message };
+            }
+        }
+
+        let (json, count) = match self.dump(true).await {
+            Ok(dump) => dump,
+            Err(message) => return Response::Error { message },
+        };
+        if let Err(e) = self.write_dump(&json).await {
             return Response::Error {
                 message: format!("failed to write dump file: {}", e),
             };
         }
+        if keep == 0 {
+            return Response::Success {
+                message: Some(format!("saved {count} process(es) to dump file")),
+            };
+        }
 
-        Response::Success {
-            message: Some(format!("saved {} process(es) to dump file", entries.len())),
+        // Snapshots outlive the processes, so their pids aren't kept to be
+        // adopted later
+        let snapshot = match self.dump(false).await {
+            Ok((json, _)) => snapshot::write(&self.paths, &json, tag.as_deref(), keep).await,
+            Err(message) => Err(std::io::Error::other(message)),
+        };
+        match snapshot {
+            Ok(id) => Response::Success {
+                message: Some(format!(
+                    "saved {count} process(es) to dump file and snapshot {}",
+                    tag.unwrap_or(id)
+                )),
+            },
+            Err(e) => Response::Error {
+                message: format!(
+                    "saved {count} process(es) to dump file, but failed to write snapshot: {e}"
+                ),
+            },
+        }
+    }
+
+    /// The configs of `namespace`'s processes under their local names, with
+    /// cluster instances folded back into one entry and the env the daemon
+    /// injected at start removed.
+    pub async fn export(&self, namespace: Option<String>) -> Response {
+        let namespace = namespace.unwrap_or_else(|| ns::DEFAULT_NAMESPACE.to_string());
+        let table = self.processes.read().await;
+        let configs = table
+            .iter()
+            .filter_map(|(key, managed)| {
+                let name = ns::local_name(&namespace, key)?;
+                let mut config = managed.config.clone();
+                for dep in config
+                    .depends_on
+                    .iter_mut()
+                    .chain(config.optional_deps.iter_mut())
+                    .flatten()
+                {
+                    if let Some(local) = ns::local_name(&namespace, dep) {
+                        *dep = local.to_string();
+                    }
+                }
+                // PATH comes from the shell that started the process
+                if let Some(env) = config.env.as_mut() {
+                    env.remove("PATH");
+                }
+                Some((name.to_string(), config))
+            })
+            .collect();
+        Response::Configs {
+            configs: collapse_instances(configs),
+        }
+    }
+
+    /// The kept snapshots, oldest first.
+    pub async fn snapshots(&self) -> Response {
+        match snapshot::list(&self.paths).await {
+            Ok(snapshots) => Response::Snapshots {
+                snapshots: snapshots.into_iter().map(|(info, _)| info).collect(),
+            },
+            Err(e) => Response::Error {
+                message: format!("failed to read snapshots: {e}"),
+            },
+        }
+    }
+
+    /// The dump file content for the current process table, and how many
+    /// processes it holds. Without `pids`, restoring it always spawns fresh
+    /// processes.
+    async fn dump(&self, pids: bool) -> Result<(String, usize), String> {
+        let table = self.processes.read().await;
+
+        // One-shot tasks are not resurrected
+        let entries: Vec<DumpEntry> = table
+            .values()
+            .filter(|managed| !managed.config.is_task())
+            .map(|managed| {
+                let mut config = managed.config.clone();
+                if self.settings.daemon.mask_dump {
+                    dump_file::mask_env(&mut config, |key| self.settings.daemon.masks_env(key));
+                }
+                DumpEntry {
+                    name: managed.name.clone(),
+                    config,
+                    pid: managed.pid.filter(|_| pids),
+                    restarts: managed.restarts,
+                }
+            })
+            .collect();
+
+        drop(table);
+
+        let json = dump_file::to_json(&entries)
+            .map_err(|e| format!("failed to serialize state: {}", e))?;
+        Ok((json, entries.len()))
+    }
+
+    /// Replaces the dump file, writing a temporary file first so a crash
+    /// midway doesn't lose the saved processes.
+    async fn write_dump(&self, json: &str) -> std::io::Result<()> {
+        let path = self.paths.dump_file();
+        let tmp = path.with_extension("json.tmp");
+        dump_file::write_private(&tmp, json).await?;
+        fs::rename(&tmp, &path).await
+    }
+
+    /// Saves the process table for `[daemon] autosave`, unless the dump
+    /// would be the same as `last`, the content this last wrote.
+    async fn autosave(&self, last: &mut Option<String>) {
+        let json = match self.dump(true).await {
+            Ok((json, _)) => json,
+            Err(message) => {
+                tracing::warn!("autosave: {message}");
+                return;
+            }
+        };
+        if last.as_deref() == Some(json.as_str()) {
+            return;
+        }
+        match self.write_dump(&json).await {
+            Ok(()) => *last = Some(json),
+            Err(e) => tracing::warn!("autosave: failed to write dump file: {e}"),
         }
     }
 
     /// Core restore logic shared by `resurrect` (CLI command) and `auto_restore` (daemon startup).
+    /// Restores the snapshot `from` instead of the dump file when given.
     /// Returns `Ok(restored_names)` on success, `Err(message)` on failure.
-    async fn restore_from_dump(&self, path: Option<String>) -> Result<Vec<String>, String> {
-        let dump_path = self.paths.dump_file();
+    async fn restore_from_dump(
+        &self,
+        path: Option<String>,
+        from: Option<&str>,
+    ) -> Result<Vec<String>, String> {
+        let result = self.restore_levels_from_dump(path, from).await;
+        *self.restore_progress.write().await = None;
+        result
+    }
+
+    /// The processes saved in the dump file, which `pm3 resurrect` brings
+    /// back, or none when nothing was saved.
+    async fn saved_configs(&self) -> Result<Vec<DumpEntry>, String> {
+        let data = match fs::read_to_string(self.paths.dump_file()).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("failed to read dump file: {e}")),
+        };
+        dump_file::parse(&data).map(|(entries, _)| entries)
+    }
+
+    async fn restore_levels_from_dump(
+        &self,
+        path: Option<String>,
+        from: Option<&str>,
+    ) -> Result<Vec<String>, String> {
+        let dump_path = match from {
+            Some(key) => snapshot::find(&self.paths, key)
+                .await
+                .map_err(|e| format!("failed to read snapshots: {e}"))?
+                .ok_or_else(|| format!("snapshot not found: {key}"))?,
+            None => self.paths.dump_file(),
+        };
         if !dump_path.exists() {
             return Err("no dump file found".to_string());
         }
+        // Rolling back to a snapshot replaces processes that aren't running
+        // with the snapshot's config; the dump only fills in missing ones
+        let keeps = |managed: &process::ManagedProcess| from.is_none() || !is_down(managed);
 
         let data = fs::read_to_string(&dump_path)
             .await
             .map_err(|e| format!("failed to read dump file: {}", e))?;
 
-        let mut entries: Vec<DumpEntry> =
-            serde_json::from_str(&data).map_err(|e| format!("failed to parse dump file: {}", e))?;
+        let (mut entries, version) = dump_file::parse(&data)?;
+        if version < dump_file::DUMP_VERSION {
+            tracing::info!(
+                "{} is dump format version {version}; the next save rewrites it as version {}",
+                dump_path.display(),
+                dump_file::DUMP_VERSION
+            );
+        }
+
+        for entry in &mut entries {
+            let dropped = dump_file::drop_masked_env(&mut entry.config);
+            if !dropped.is_empty() {
+                tracing::warn!(
+                    "{}: {} masked in {}, restoring without them; set them with env_file or env_cmd instead",
+                    entry.name,
+                    dropped.join(", "),
+                    dump_path.display()
+                );
+            }
+        }
 
         // Force-insert PATH into restored configs so they use the current CLI PATH
         if let Some(ref p) = path {
@@ -698,7 +1570,7 @@ impl Manager {
             let table = self.processes.read().await;
             entries
                 .iter()
-                .filter(|e| table.contains_key(&e.name))
+                .filter(|e| table.get(&e.name).is_some_and(keeps))
                 .map(|e| e.name.clone())
                 .collect()
         };
@@ -720,21 +1592,44 @@ impl Manager {
         deps::validate_deps(&subset_configs).map_err(|e| e.to_string())?;
 
         let levels = deps::topological_levels(&subset_configs).map_err(|e| e.to_string())?;
+        let optional = deps::optional_only(&subset_configs);
 
         let entry_map: HashMap<String, &DumpEntry> =
             to_restore.iter().map(|e| (e.name.clone(), e)).collect();
 
         let mut restored = Vec::new();
+        *self.restore_progress.write().await = Some(protocol::RestoreProgress {
+            restored: 0,
+            total: to_restore.len(),
+            starting: Vec::new(),
+        });
 
         for (level_idx, level) in levels.iter().enumerate() {
             let mut spawned: Vec<SpawnedProcess> = Vec::new();
             let mut level_names: Vec<String> = Vec::new();
 
+            let candidates = level
+                .iter()
+                .filter_map(|name| entry_map.get(name))
+                .filter(|entry| !entry.pid.is_some_and(is_pid_alive))
+                .map(|entry| (entry.name.clone(), entry.config.clone()))
+                .collect();
+            let concurrency = self.settings.daemon.spawn_concurrency;
+            let (resolved, unresolved) = resolve_batch(candidates, concurrency).await;
+            let mut env_cmds: HashMap<String, process::EnvCmdValues> = resolved
+                .into_iter()
+                .map(|(name, _, env_cmd)| (name, env_cmd))
+                .collect();
+            let mut unresolved: HashMap<String, process::ProcessError> = unresolved
+                .into_iter()
+                .filter_map(|(name, _, result)| Some((name, result.err()?)))
+                .collect();
+
             {
                 let mut table = self.processes.write().await;
 
                 for name in level {
-                    if table.contains_key(name) {
+                    if table.get(name).is_some_and(keeps) {
                         continue;
                     }
 
@@ -746,35 +1641,42 @@ impl Manager {
                     let old_alive = entry.pid.is_some_and(is_pid_alive);
 
                     if old_alive {
-                        let (log_tx, _) = tokio::sync::broadcast::channel(1024);
-                        let (monitor_tx, _) = watch::channel(false);
-
-                        let status = if entry.config.readiness_check.is_some()
-                            || entry.config.health_check.is_some()
-                        {
+                        let status = if entry.config.has_startup_probe() {
                             ProcessStatus::Starting
                         } else {
                             ProcessStatus::Online
                         };
 
-                        let managed = process::ManagedProcess {
-                            name: name.clone(),
-                            config: entry.config.clone(),
-                            pid: entry.pid,
+                        let managed = process::ManagedProcess::adopted(
+                            name.clone(),
+                            entry.config.clone(),
+                            entry.pid,
                             status,
-                            started_at: tokio::time::Instant::now(),
-                            restarts: entry.restarts,
-                            log_broadcaster: log_tx,
-                            monitor_shutdown: Some(monitor_tx),
-                        };
+                            entry.restarts,
+                        );
+                        // The relay of the daemon that spawned it, if any,
+                        // is unknown here and left to run until it is done
+                        follow_relayed_logs(name, &managed, &self.paths, std::future::pending());
 
                         table.insert(name.clone(), managed);
                         level_names.push(name.clone());
                     } else {
                         let config = entry.config.clone();
-                        match process::spawn_process(name.clone(), config.clone(), &self.paths)
-                            .await
-                        {
+                        let result = match (unresolved.remove(name), env_cmds.remove(name)) {
+                            (Some(e), _) => Err(e),
+                            (None, Some(env_cmd)) => {
+                                let (name, config) = (name.clone(), config.clone());
+                                process::spawn_resolved(name, config, env_cmd, None, &self.paths)
+                                    .await
+                            }
+                            // It died after its `env_cmd` was resolved for
+                            // the others
+                            (None, None) => {
+                                process::spawn_process(name.clone(), config.clone(), &self.paths)
+                                    .await
+                            }
+                        };
+                        match result {
                             Ok((mut managed, child)) => {
                                 managed.restarts = entry.restarts;
                                 let pid = managed.pid;
@@ -793,6 +1695,11 @@ impl Manager {
                                 });
                                 level_names.push(name.clone());
                             }
+                            Err(e) if optional.contains(name) => {
+                                tracing::warn!(
+                                    "failed to resurrect '{name}': {e}, starting dependents without it"
+                                );
+                            }
                             Err(e) => {
                                 return Err(format!("failed to resurrect '{}': {}", name, e));
                             }
@@ -820,8 +1727,7 @@ impl Manager {
                         continue;
                     }
 
-                    if entry.config.readiness_check.is_some() || entry.config.health_check.is_some()
-                    {
+                    if entry.config.has_startup_probe() {
                         let startup_rx = managed
                             .monitor_shutdown
                             .as_ref()
@@ -829,14 +1735,14 @@ impl Manager {
                             .subscribe();
                         health::spawn_startup_checker(
                             name.clone(),
-                            entry.config.readiness_check.clone(),
+                            health::Readiness::for_process(name, &entry.config, &self.paths),
                             entry.config.readiness_timeout,
                             entry.config.health_check.clone(),
                             Arc::clone(&self.processes),
                             startup_rx,
                         );
                     }
-                    if let Some(ref mm) = entry.config.max_memory {
+                    if entry.config.max_memory.is_some() {
                         let mm_rx = managed
                             .monitor_shutdown
                             .as_ref()
@@ -844,12 +1750,42 @@ impl Manager {
                             .subscribe();
                         memory::spawn_memory_monitor(
                             name.clone(),
-                            mm.clone(),
+                            entry.config.clone(),
                             Arc::clone(&self.processes),
                             self.paths.clone(),
                             mm_rx,
                         );
                     }
+                    if entry.config.on_unhealthy.is_some() {
+                        let liveness_rx = managed
+                            .monitor_shutdown
+                            .as_ref()
+                            .expect("monitor shutdown sender missing")
+                            .subscribe();
+                        health::spawn_liveness_checker(
+                            name.clone(),
+                            entry.config.clone(),
+                            Arc::clone(&self.processes),
+                            liveness_rx,
+                        );
+                    }
+                    if let (Some(endpoint), Some(wanted)) = (
+                        entry.config.metrics_endpoint.clone(),
+                        entry.config.metrics.clone(),
+                    ) {
+                        let metrics_rx = managed
+                            .monitor_shutdown
+                            .as_ref()
+                            .expect("monitor shutdown sender missing")
+                            .subscribe();
+                        metrics::spawn_metrics_scraper(
+                            name.clone(),
+                            endpoint,
+                            wanted,
+                            Arc::clone(&self.processes),
+                            metrics_rx,
+                        );
+                    }
                     if let Some(ref cr) = entry.config.cron_restart {
                         let cr_rx = managed
                             .monitor_shutdown
@@ -859,6 +1795,7 @@ impl Manager {
                         cron::spawn_cron_restart(
                             name.clone(),
                             cr.clone(),
+                            entry.config.cron_timezone.clone(),
                             Arc::clone(&self.processes),
                             self.paths.clone(),
                             cr_rx,
@@ -882,22 +1819,34 @@ impl Manager {
                 }
             }
 
+            for name in &level_names {
+                if let Some(entry) = entry_map.get(name) {
+                    self.schedule_start_stop(name, &entry.config).await;
+                }
+            }
+
+            if let Some(progress) = self.restore_progress.write().await.as_mut() {
+                progress.starting = level_names.clone();
+            }
+
             restored.extend(level_names.clone());
 
             let is_last_level = level_idx == levels.len() - 1;
-            if !is_last_level
-                && !level_names.is_empty()
-                && let Err(msg) = wait_for_online(&level_names, &self.processes).await
-            {
-                return Err(msg);
+            if !is_last_level && !level_names.is_empty() {
+                wait_for_level(&level_names, &optional, &self.processes).await?;
+            }
+
+            if let Some(progress) = self.restore_progress.write().await.as_mut() {
+                progress.restored = restored.len();
+                progress.starting.clear();
             }
         }
 
         Ok(restored)
     }
 
-    pub async fn resurrect(&self, path: Option<String>) -> Response {
-        match self.restore_from_dump(path).await {
+    pub async fn resurrect(&self, path: Option<String>, from: Option<String>) -> Response {
+        match self.restore_from_dump(path, from.as_deref()).await {
             Ok(restored) if restored.is_empty() => Response::Success {
                 message: Some("all processes already running".to_string()),
             },
@@ -909,58 +1858,284 @@ impl Manager {
     }
 
     /// Auto-restore processes from dump file on daemon startup.
-    /// Silently skips if no dump file exists.
-    pub async fn auto_restore(&self) {
-        match self.restore_from_dump(None).await {
+    /// Silently skips if no dump file exists, unless the restore is
+    /// `required` (`--resurrect`).
+    pub async fn auto_restore(&self, required: bool) {
+        match self.restore_from_dump(None, None).await {
             Ok(restored) if restored.is_empty() => {}
             Ok(restored) => {
-                eprintln!(
+                tracing::info!(
                     "auto-restored {} process(es): {}",
                     restored.len(),
                     restored.join(", ")
                 );
             }
             Err(msg) if msg == "no dump file found" => {
-                // No dump file — silently continue
+                if required {
+                    tracing::warn!(
+                        "--resurrect: no dump file at {}, run `pm3 save` to create one",
+                        self.paths.dump_file().display()
+                    );
+                }
             }
             Err(msg) => {
-                eprintln!("auto-restore failed: {}", msg);
+                tracing::error!("auto-restore failed: {}", msg);
             }
         }
     }
 
-    pub async fn flush(&self, names: Option<Vec<String>>) -> Response {
-        let table = self.processes.read().await;
+    /// Checks the installed binary can take over, then has the daemon re-exec
+    /// it once this response is sent.
+    async fn upgrade(&self) -> Response {
+        if self.restore_progress.read().await.is_some() {
+            return Response::Error {
+                message: "a restore is in progress; upgrade once it finishes".to_string(),
+            };
+        }
+        match self.upgrader.check().await {
+            Ok(version) => {
+                self.upgrader.request();
+                Response::Success {
+                    message: Some(format!(
+                        "upgrading daemon from pm3 {} to pm3 {version}",
+                        env!("CARGO_PKG_VERSION")
+                    )),
+                }
+            }
+            Err(message) => Response::Error { message },
+        }
+    }
 
-        let targets: Vec<String> = match names {
-            Some(ref requested) => {
-                for name in requested {
-                    if !table.contains_key(name) {
-                        return Response::Error {
-                            message: format!("process not found: {name}"),
-                        };
+    /// Takes over the processes an upgrading daemon handed to this one: live
+    /// ones are adopted by pid with their output still copied to the logs,
+    /// ones that should be running but died in between are started again, and
+    /// the rest keep their status.
+    pub async fn adopt_handover(&self, handed_over: Vec<upgrade::HandedOver>) {
+        let mut adopted = Vec::new();
+        let mut respawn = Vec::new();
+        {
+            let mut table = self.processes.write().await;
+            for process in handed_over {
+                // SAFETY: each handed-over process is adopted once, from the
+                // handover of the daemon this one replaced
+                #[cfg(unix)]
+                let output_fds = unsafe { upgrade::output_fds(&process) };
+                let entry = process.entry;
+                let alive = entry.pid.is_some_and(is_pid_alive);
+                if entry.pid.is_some() && !alive {
+                    respawn.push(entry);
+                    continue;
+                }
+
+                let pid = entry.pid.filter(|_| alive);
+                let mut managed = process::ManagedProcess::adopted(
+                    entry.name.clone(),
+                    entry.config.clone(),
+                    pid,
+                    process.status,
+                    entry.restarts,
+                );
+                managed.exit_code = process.exit_code;
+                managed.paused = process.paused;
+                managed.crashes = process.crashes;
+                managed.suspended = process
+                    .suspended
+                    .map(|status| (status, tokio::time::Instant::now()));
+                #[cfg(unix)]
+                if pid.is_some() {
+                    copy_handed_over_output(
+                        &entry.name,
+                        &entry.config,
+                        &output_fds,
+                        &managed,
+                        &self.paths,
+                    );
+                    managed.output_fds = output_fds;
+                }
+                #[cfg(unix)]
+                if let (Some(_), Some(relay)) = (pid, process.log_relay) {
+                    managed.log_relay = Some(relay);
+                    let feed = managed
+                        .output_fds
+                        .relay_feed
+                        .as_ref()
+                        .and_then(|fd| fd.try_clone().ok())
+                        .and_then(|fd| crate::sys::fd_reader(fd).ok());
+                    match feed {
+                        Some(feed) => log::spawn_relay_feed(
+                            feed,
+                            managed.log_broadcaster.downgrade(),
+                            managed.log_control.suppressed.clone(),
+                            log::wait_for_relay(relay),
+                        ),
+                        // Handed over by a daemon that didn't keep it
+                        None => follow_relayed_logs(
+                            &entry.name,
+                            &managed,
+                            &self.paths,
+                            log::wait_for_relay(relay),
+                        ),
+                    }
+                }
+                table.insert(entry.name.clone(), managed);
+                adopted.push((entry, pid));
+            }
+        }
+
+        for (entry, pid) in &adopted {
+            if let Some(pid) = *pid {
+                let shutdown_tx = {
+                    let table = self.processes.read().await;
+                    match table
+                        .get(&entry.name)
+                        .and_then(|m| m.monitor_shutdown.clone())
+                    {
+                        Some(tx) => tx,
+                        None => continue,
+                    }
+                };
+                process::spawn_aux_monitors(
+                    entry.name.clone(),
+                    entry.config.clone(),
+                    Arc::clone(&self.processes),
+                    self.paths.clone(),
+                    shutdown_tx.clone(),
+                );
+                process::spawn_pid_monitor(
+                    entry.name.clone(),
+                    pid,
+                    Arc::clone(&self.processes),
+                    self.paths.clone(),
+                    shutdown_tx.subscribe(),
+                );
+            }
+            self.schedule_start_stop(&entry.name, &entry.config).await;
+        }
+
+        for entry in respawn {
+            tracing::warn!(
+                "'{}' exited during the upgrade, starting it again",
+                entry.name
+            );
+            if let Err(e) = process::spawn_and_attach(
+                entry.name.clone(),
+                entry.config.clone(),
+                entry.restarts,
+                &self.processes,
+                &self.paths,
+            )
+            .await
+            {
+                tracing::error!("failed to restart '{}': {e}", entry.name);
+            }
+            self.schedule_start_stop(&entry.name, &entry.config).await;
+        }
+        tracing::info!("took over {} process(es) after upgrading", adopted.len());
+    }
+
+    /// With `[daemon] autosave`, saves the process table once it has settled
+    /// after each change, until the daemon shuts down.
+    pub fn spawn_autosaver(&self, mut shutdown_rx: watch::Receiver<bool>) {
+        if !self.settings.daemon.autosave {
+            return;
+        }
+        let manager = self.clone();
+        let mut events = self.events.subscribe();
+        tokio::spawn(async move {
+            let mut last = None;
+            loop {
+                // Wait for a change, then for AUTOSAVE_DEBOUNCE without another
+                // one, so a burst of changes is written once
+                let mut changed = false;
+                loop {
+                    let debounce = async {
+                        if changed {
+                            tokio::time::sleep(AUTOSAVE_DEBOUNCE).await
+                        } else {
+                            std::future::pending().await
+                        }
+                    };
+                    tokio::select! {
+                        _ = debounce => break,
+                        event = events.recv() => match event {
+                            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => changed = true,
+                            Err(broadcast::error::RecvError::Closed) => return,
+                        },
+                        _ = shutdown_rx.changed() => {
+                            if *shutdown_rx.borrow() {
+                                return;
+                            }
+                        }
                     }
                 }
-                requested.clone()
+                // Changes made by a restore are saved once it finishes
+                while manager.restore_progress.read().await.is_some() {
+                    tokio::time::sleep(AUTOSAVE_DEBOUNCE).await;
+                }
+                manager.autosave(&mut last).await;
+            }
+        });
+    }
+
+    /// Clears the logs of `names`, which may be groups, tags or patterns, or
+    /// of every process in `namespace`. With `all_files`, log files in the
+    /// log dir that no process in the table or the dump file writes to are
+    /// removed too.
+    pub async fn flush(
+        &self,
+        names: Option<Vec<String>>,
+        all_files: bool,
+        namespace: Option<String>,
+    ) -> Response {
+        let saved = match all_files {
+            true => match self.saved_configs().await {
+                Ok(saved) => saved,
+                Err(message) => return Response::Error { message },
+            },
+            false => Vec::new(),
+        };
+        let table = self.processes.read().await;
+
+        let targets: Vec<String> = match names {
+            Some(ref requested) => match resolve_table_names(requested, &table, None) {
+                Ok(r) => r,
+                Err(msg) => return Response::Error { message: msg },
+            },
+            None => namespace_keys(&table, namespace.as_deref()),
+        };
+        let logs: Vec<log::LogFiles> = targets
+            .iter()
+            .map(|name| log::LogFiles::new(&self.paths, name, &table[name].config))
+            .collect();
+        let orphans = match all_files {
+            true => {
+                let kept: Vec<log::LogFiles> = table
+                    .iter()
+                    .map(|(name, managed)| (name, &managed.config))
+                    .chain(saved.iter().map(|entry| (&entry.name, &entry.config)))
+                    .map(|(name, config)| log::LogFiles::new(&self.paths, name, config))
+                    .collect();
+                log::orphaned_logs(&self.paths.log_dir(), &kept)
             }
-            None => table.keys().cloned().collect(),
+            false => Vec::new(),
         };
 
         drop(table);
 
-        for name in &targets {
-            let stdout_path = self.paths.stdout_log(name);
-            let stderr_path = self.paths.stderr_log(name);
+        for (name, logs) in targets.iter().zip(&logs) {
+            let stdout_path = &logs.stdout;
+            let stderr_path = &logs.stderr;
 
             if stdout_path.exists()
-                && let Err(e) = fs::write(&stdout_path, b"").await
+                && let Err(e) = fs::write(stdout_path, b"").await
             {
                 return Response::Error {
                     message: format!("failed to truncate stdout log for '{}': {}", name, e),
                 };
             }
-            if stderr_path.exists()
-                && let Err(e) = fs::write(&stderr_path, b"").await
+            if !logs.is_combined()
+                && stderr_path.exists()
+                && let Err(e) = fs::write(stderr_path, b"").await
             {
                 return Response::Error {
                     message: format!("failed to truncate stderr log for '{}': {}", name, e),
@@ -968,13 +2143,153 @@ impl Manager {
             }
 
             for i in 1..=log::LOG_ROTATION_KEEP {
-                let _ = fs::remove_file(self.paths.rotated_stdout_log(name, i)).await;
-                let _ = fs::remove_file(self.paths.rotated_stderr_log(name, i)).await;
+                let _ = fs::remove_file(log::rotated_path(stdout_path, i)).await;
+                let _ = fs::remove_file(log::rotated_path(stderr_path, i)).await;
+            }
+        }
+
+        for orphan in &orphans {
+            if let Err(e) = fs::remove_file(orphan).await {
+                return Response::Error {
+                    message: format!("failed to remove {}: {e}", orphan.display()),
+                };
             }
         }
 
+        let mut message = format!("flushed logs: {}", targets.join(", "));
+        if all_files {
+            message.push_str(&format!("; removed {} orphaned log files", orphans.len()));
+        }
+        Response::Success {
+            message: Some(message),
+        }
+    }
+
+    /// Has the copiers of `names`, which may be groups, tags or patterns, or
+    /// of every process in `namespace`, close
+    /// their log files and open them again at their paths, for tools like
+    /// logrotate that move the files aside. Without names, the daemon reopens
+    /// its own log too.
+    pub async fn reopen_logs(
+        &self,
+        names: Option<Vec<String>>,
+        namespace: Option<String>,
+    ) -> Response {
+        let table = self.processes.read().await;
+        let targets: Vec<String> = match &names {
+            Some(requested) => match resolve_table_names(requested, &table, None) {
+                Ok(r) => r,
+                Err(message) => return Response::Error { message },
+            },
+            None => namespace_keys(&table, namespace.as_deref()),
+        };
+        for name in &targets {
+            let managed = &table[name];
+            managed.log_control.reopen();
+            // A relay copies the output in a process of its own
+            if let Some(relay) = managed.log_relay
+                && let Err(e) = crate::sys::signal_reopen(relay)
+            {
+                tracing::warn!("failed to ask the log relay of '{name}' to reopen its logs: {e}");
+            }
+        }
+        drop(table);
+
+        if names.is_none()
+            && let Err(e) = crate::daemon::reopen_daemon_log()
+        {
+            return Response::Error {
+                message: format!("failed to reopen the daemon log: {e}"),
+            };
+        }
+
         Response::Success {
-            message: Some(format!("flushed logs: {}", targets.join(", "))),
+            message: Some(format!("reopened logs: {}", targets.join(", "))),
+        }
+    }
+
+    /// Drops the stopped, errored and finished processes of `namespace`, or
+    /// of every namespace, from the table, along with their log files when
+    /// `logs` is set, and removes log files that no process in the table or
+    /// the dump file wrote to in the first place.
+    pub async fn prune(&self, logs: bool, namespace: Option<String>) -> Response {
+        let saved = match self.saved_configs().await {
+            Ok(saved) => saved,
+            Err(message) => return Response::Error { message },
+        };
+        let mut table = self.processes.write().await;
+        let kept: Vec<log::LogFiles> = table
+            .iter()
+            .map(|(name, managed)| (name, &managed.config))
+            .chain(saved.iter().map(|entry| (&entry.name, &entry.config)))
+            .map(|(name, config)| log::LogFiles::new(&self.paths, name, config))
+            .collect();
+        let mut orphans = log::orphaned_logs(&self.paths.log_dir(), &kept);
+
+        let in_namespace = namespace_keys(&table, namespace.as_deref());
+        let mut pruned: Vec<String> = in_namespace
+            .into_iter()
+            .filter(|name| {
+                matches!(
+                    table[name].status,
+                    ProcessStatus::Stopped
+                        | ProcessStatus::Errored
+                        | ProcessStatus::Succeeded
+                        | ProcessStatus::Failed
+                )
+            })
+            .collect();
+        pruned.sort();
+        for name in &pruned {
+            let Some(managed) = table.remove(name) else {
+                continue;
+            };
+            // Watchers and scrapers outlive a crash; they go with the entry
+            if let Some(ref tx) = managed.monitor_shutdown {
+                let _ = tx.send(true);
+            }
+            if logs {
+                let files = log::LogFiles::new(&self.paths, name, &managed.config);
+                for path in [files.stdout, files.stderr] {
+                    for i in 1..=log::LOG_ROTATION_KEEP {
+                        orphans.push(log::rotated_path(&path, i));
+                    }
+                    orphans.push(path);
+                }
+            }
+        }
+        drop(table);
+
+        {
+            let mut schedules = self.cron_schedules.write().await;
+            for name in &pruned {
+                if let Some(schedule) = schedules.remove(name) {
+                    let _ = schedule.shutdown.send(true);
+                }
+            }
+        }
+
+        orphans.sort();
+        orphans.dedup();
+        let mut removed = 0;
+        for path in &orphans {
+            match fs::remove_file(path).await {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    return Response::Error {
+                        message: format!("failed to remove {}: {e}", path.display()),
+                    };
+                }
+            }
+        }
+
+        let pruned = match pruned.is_empty() {
+            true => "none".to_string(),
+            false => pruned.join(", "),
+        };
+        Response::Success {
+            message: Some(format!("pruned: {pruned}; removed {removed} log files")),
         }
     }
 
@@ -983,7 +2298,12 @@ impl Manager {
         let cache = self.stats_cache.read().await;
         match table.get(&name) {
             Some(managed) => {
-                let detail = managed.to_process_detail(&self.paths, &cache);
+                let mut detail = managed.to_process_detail(&self.paths, &cache);
+                for (key, value) in detail.env.iter_mut().flatten() {
+                    if self.settings.daemon.masks_env(key) {
+                        *value = dump_file::MASKED.to_string();
+                    }
+                }
                 Response::ProcessDetail {
                     info: Box::new(detail),
                 }
@@ -994,6 +2314,112 @@ impl Manager {
         }
     }
 
+    /// The recorded samples of a process, including one that has since been
+    /// deleted, as long as they are within the retention period.
+    pub async fn stats(&self, name: String, since_secs: Option<u64>) -> Response {
+        let settings = &self.settings.history;
+        if !settings.enabled {
+            return Response::Error {
+                message: "stats history is disabled in settings.toml".to_string(),
+            };
+        }
+        let since = since_secs.map(|secs| chrono::Utc::now().timestamp() - secs as i64);
+        let samples = match self.history.read().await.since(&name, since) {
+            Some(samples) => samples,
+            None if self.processes.read().await.contains_key(&name) => Vec::new(),
+            None => {
+                return Response::Error {
+                    message: format!("process not found: {name}"),
+                };
+            }
+        };
+        Response::Stats {
+            name,
+            interval: settings.interval().map_or(10, |d| d.as_secs().max(1)),
+            samples,
+        }
+    }
+
+    /// Availability of `name`, or of every process, over each trailing
+    /// window. A deleted process is still reported on by name.
+    pub async fn report(&self, name: Option<String>, windows_secs: Vec<u64>) -> Response {
+        if windows_secs.is_empty() {
+            return Response::Error {
+                message: "no report windows given".to_string(),
+            };
+        }
+        let entries = match availability::read(&self.paths).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                return Response::Error {
+                    message: format!("failed to read availability log: {e}"),
+                };
+            }
+        };
+        let names = match name {
+            Some(name) => {
+                let known = self.processes.read().await.contains_key(&name)
+                    || entries.iter().any(|entry| entry.name == name);
+                if !known {
+                    return Response::Error {
+                        message: format!("process not found: {name}"),
+                    };
+                }
+                vec![name]
+            }
+            None => {
+                let mut names: Vec<String> = self.processes.read().await.keys().cloned().collect();
+                names.sort();
+                names
+            }
+        };
+        let windows: Vec<Duration> = windows_secs.into_iter().map(Duration::from_secs).collect();
+        let now = chrono::Utc::now().timestamp_millis();
+        Response::Report {
+            reports: availability::reports(&entries, &names, &windows, now),
+        }
+    }
+
+    /// What each running target spawned, read from `ps` in one go.
+    pub async fn tree(&self, name: Option<String>) -> Response {
+        let roots: Vec<(String, u32)> = {
+            let table = self.processes.read().await;
+            let mut targets: Vec<String> = match &name {
+                Some(name) => match resolve_table_names(std::slice::from_ref(name), &table, None) {
+                    Ok(r) => r,
+                    Err(msg) => return Response::Error { message: msg },
+                },
+                None => table.keys().cloned().collect(),
+            };
+            targets.sort();
+            targets
+                .into_iter()
+                .filter_map(|target| {
+                    let pid = table[&target].pid?;
+                    Some((target, pid))
+                })
+                .collect()
+        };
+        if let Some(name) = &name
+            && roots.is_empty()
+        {
+            return Response::Error {
+                message: format!("not running: {name}"),
+            };
+        }
+
+        let entries = crate::tree::read_process_table().await.unwrap_or_default();
+        Response::Trees {
+            trees: roots
+                .into_iter()
+                .map(|(name, pid)| protocol::ProcessTree {
+                    name,
+                    root: crate::tree::build(pid, &entries),
+                })
+                .collect(),
+        }
+    }
+
     pub async fn signal(&self, name: String, signal: String) -> Response {
         let table = self.processes.read().await;
         let managed = match table.get(&name) {
@@ -1036,50 +2462,245 @@ impl Manager {
 
     const MAX_LOG_LINES: usize = 10_000;
 
+    /// Start a one-shot task and stream its output until it exits, finishing
+    /// with a `TaskFinished` response that carries the exit code. Like
+    /// `start`, the task is registered in `namespace`. Returns the final
+    /// response, or `None` if the client went away first.
+    pub async fn run_task(
+        &self,
+        configs: HashMap<String, ProcessConfig>,
+        name: String,
+        env: Option<String>,
+        path: Option<String>,
+        namespace: Option<String>,
+        writer: &mut (impl AsyncWriteExt + Unpin),
+    ) -> color_eyre::Result<Option<Response>> {
+        let task = match configs.get(&name) {
+            None => Err(format!("process not found: {name}")),
+            Some(config) if !config.is_task() => Err(format!(
+                "'{name}' is not a task (set kind = \"task\" to use pm3 run)"
+            )),
+            Some(_) => Ok(()),
+        };
+        let scoped = task.and_then(|()| match namespace {
+            Some(namespace) => scope_start(configs, None, &namespace)
+                .map(|(configs, _)| (configs, ns::qualify(&namespace, &name))),
+            None => Ok((configs, name)),
+        });
+        let (configs, name) = match scoped {
+            Ok(scoped) => scoped,
+            Err(message) => {
+                let response = Response::Error { message };
+                writer
+                    .write_all(&protocol::encode_response(&response)?)
+                    .await?;
+                return Ok(Some(response));
+            }
+        };
+
+        // Tapped before the task is spawned, so not even its first lines are
+        // missed
+        let (log_tx, mut log_rx) = broadcast::channel(process::LOG_BROADCAST_CAPACITY);
+        self.log_taps.lock().unwrap().insert(name.clone(), log_tx);
+        let response = self
+            .start(configs, Some(vec![name.clone()]), env, None, path, false)
+            .await;
+        let untapped = self.log_taps.lock().unwrap().remove(&name).is_some();
+        if let Response::Error { .. } = response {
+            writer
+                .write_all(&protocol::encode_response(&response)?)
+                .await?;
+            return Ok(Some(response));
+        }
+        // Already running, so it wasn't spawned with the tap
+        if untapped && let Some(managed) = self.processes.read().await.get(&name) {
+            log_rx = managed.log_broadcaster.subscribe();
+        }
+
+        let mut exit_check = tokio::time::interval(TASK_EXIT_CHECK_INTERVAL);
+        let mut finished = None;
+        // Once the task is done, the lines still on their way are waited for
+        // until its output goes quiet
+        let quiet = tokio::time::sleep(TASK_OUTPUT_GRACE);
+        tokio::pin!(quiet);
+        let (status, exit_code) = loop {
+            let received = tokio::select! {
+                received = log_rx.recv() => received,
+                _ = exit_check.tick(), if finished.is_none() => {
+                    let table = self.processes.read().await;
+                    finished = table
+                        .get(&name)
+                        .and_then(|m| m.pid.is_none().then_some((m.status, m.exit_code)));
+                    if finished.is_some() {
+                        quiet.as_mut().reset(tokio::time::Instant::now() + TASK_OUTPUT_GRACE);
+                    }
+                    continue;
+                }
+                _ = &mut quiet, if finished.is_some() => break finished.unwrap(),
+            };
+            let resp = match received {
+                Ok(entry) => Response::LogLine {
+                    name: None,
+                    stream: Some(entry.stream),
+                    timestamp: Some(log::format_timestamp(entry.time)),
+                    line: entry.line,
+                },
+                Err(broadcast::error::RecvError::Lagged(dropped)) => Response::LogLine {
+                    name: None,
+                    stream: None,
+                    timestamp: Some(log::format_timestamp(chrono::Utc::now())),
+                    line: format!("[pm3: {dropped} log lines dropped due to lag]"),
+                },
+                // Replaced, e.g. by a restart; the table has the new one
+                Err(broadcast::error::RecvError::Closed) => {
+                    let table = self.processes.read().await;
+                    match table.get(&name) {
+                        Some(managed) => log_rx = managed.log_broadcaster.subscribe(),
+                        None => {
+                            drop(table);
+                            exit_check.tick().await;
+                        }
+                    }
+                    continue;
+                }
+            };
+            if writer
+                .write_all(&protocol::encode_response(&resp)?)
+                .await
+                .is_err()
+            {
+                return Ok(None);
+            }
+            if log_rx.is_empty() {
+                writer.flush().await?;
+            }
+            quiet
+                .as_mut()
+                .reset(tokio::time::Instant::now() + TASK_OUTPUT_GRACE);
+        };
+        writer.flush().await?;
+
+        let resp = Response::TaskFinished {
+            name,
+            status,
+            exit_code,
+        };
+        writer.write_all(&protocol::encode_response(&resp)?).await?;
+        Ok(Some(resp))
+    }
+
     pub async fn stream_logs(
         &self,
-        name: Option<String>,
-        lines: usize,
-        follow: bool,
+        query: LogQuery,
         writer: &mut (impl AsyncWriteExt + Unpin),
     ) -> color_eyre::Result<()> {
+        let LogQuery {
+            name,
+            namespace,
+            lines,
+            follow,
+            range,
+            only,
+        } = query;
         let lines = lines.min(Self::MAX_LOG_LINES);
+        if follow && range.until.is_some() {
+            let message = "--until can't be used with --follow".to_string();
+            let encoded = protocol::encode_response(&Response::Error { message })?;
+            writer.write_all(&encoded).await?;
+            return Ok(());
+        }
         let table = self.processes.read().await;
 
-        let targets: Vec<String> = match name {
-            Some(ref n) => {
-                if !table.contains_key(n) {
-                    let resp = Response::Error {
-                        message: format!("process not found: {n}"),
-                    };
-                    let encoded = protocol::encode_response(&resp)?;
-                    writer.write_all(&encoded).await?;
-                    return Ok(());
-                }
-                vec![n.clone()]
+        let has_timestamps = |n: &str| {
+            table
+                .get(n)
+                .is_some_and(|managed| managed.config.log_date_format.is_some())
+        };
+        // A process name, or else a group, tag or pattern like stop takes
+        let single = name.as_ref().filter(|n| table.contains_key(*n));
+        let targets = match (&name, single) {
+            (_, Some(n)) if !range.is_empty() && !has_timestamps(n) => Err(format!(
+                "--since and --until need log_date_format, which '{n}' doesn't set"
+            )),
+            (_, Some(n)) => Ok(vec![n.clone()]),
+            (Some(n), None) => resolve_table_names(std::slice::from_ref(n), &table, None),
+            (None, None) => Ok(namespace_keys(&table, namespace.as_deref())),
+        };
+        let targets: Vec<String> = match targets {
+            // Without timestamps there is nothing to filter by
+            Ok(targets) => targets
+                .into_iter()
+                .filter(|n| single.is_some() || range.is_empty() || has_timestamps(n))
+                .collect(),
+            Err(message) => {
+                let encoded = protocol::encode_response(&Response::Error { message })?;
+                writer.write_all(&encoded).await?;
+                return Ok(());
             }
-            None => table.keys().cloned().collect(),
         };
 
-        let multi = targets.len() > 1;
+        // A named process is implied by the request; otherwise say whose line it is
+        let multi = single.is_none();
 
-        for target in &targets {
-            let stdout_lines =
-                log::tail_file(&self.paths.stdout_log(target), lines).unwrap_or_default();
-            let stderr_lines =
-                log::tail_file(&self.paths.stderr_log(target), lines).unwrap_or_default();
+        // The files are read and the lines sent without holding the table,
+        // which every writer would otherwise wait behind
+        let sources: Vec<(&String, log::LogFiles, Option<String>)> = targets
+            .iter()
+            .filter_map(|target| {
+                let config = &table.get(target)?.config;
+                let logs = log::LogFiles::new(&self.paths, target, config);
+                Some((target, logs, config.log_date_format.clone()))
+            })
+            .collect();
+        drop(table);
 
-            for line in stdout_lines {
-                let resp = Response::LogLine {
-                    name: if multi { Some(target.clone()) } else { None },
-                    line,
+        for (target, logs, log_date_format) in sources {
+            let log_date_format = log_date_format.as_deref();
+            let history = if logs.is_combined() {
+                // Lines of the other stream or instances may be in between,
+                // so it takes reading further back to find enough
+                let filtered = only.is_some() || !range.is_empty() || logs.instance.is_some();
+                let n = if filtered { Self::MAX_LOG_LINES } else { lines };
+                let all = log::tail_rotated(&logs.stdout, n).unwrap_or_default();
+                let all = match log_date_format {
+                    Some(format) if !range.is_empty() => range.filter(all, format),
+                    _ => all,
                 };
-                let encoded = protocol::encode_response(&resp)?;
-                writer.write_all(&encoded).await?;
-            }
-            for line in stderr_lines {
+                let mut kept: Vec<_> = all
+                    .iter()
+                    .filter_map(|line| logs.untag(line))
+                    .filter(|(stream, _)| only.as_ref().is_none_or(|only| only == stream))
+                    .map(|(stream, line)| (stream, line.to_string()))
+                    .collect();
+                kept.drain(..kept.len().saturating_sub(lines));
+                kept
+            } else {
+                let tail = |stream: log::LogStream| {
+                    let path = logs.path(&stream);
+                    match log_date_format {
+                        // Only the other stream's file is read
+                        _ if only.as_ref().is_some_and(|only| *only != stream) => Vec::new(),
+                        // The last lines inside the range, wherever they are
+                        Some(format) if !range.is_empty() => {
+                            let all =
+                                log::tail_rotated(path, Self::MAX_LOG_LINES).unwrap_or_default();
+                            let mut kept = range.filter(all, format);
+                            kept.drain(..kept.len().saturating_sub(lines));
+                            kept
+                        }
+                        _ => log::tail_rotated(path, lines).unwrap_or_default(),
+                    }
+                };
+                let stdout_lines = tail(log::LogStream::Stdout);
+                let stderr_lines = tail(log::LogStream::Stderr);
+                log::interleave(stdout_lines, stderr_lines, log_date_format)
+            };
+
+            for (stream, line) in history {
                 let resp = Response::LogLine {
                     name: if multi { Some(target.clone()) } else { None },
+                    stream: Some(stream),
+                    timestamp: None,
                     line,
                 };
                 let encoded = protocol::encode_response(&resp)?;
@@ -1091,69 +2712,79 @@ impl Manager {
             return Ok(());
         }
 
-        let mut receivers = Vec::new();
+        // One task per process feeds a shared channel, so lines go out as
+        // soon as they're logged; the tasks stop when the client goes away
+        let (tx, mut merged) = mpsc::channel(256);
+        let mut forwarders = tokio::task::JoinSet::new();
+        let table = self.processes.read().await;
         for target in &targets {
             if let Some(managed) = table.get(target) {
-                let rx = managed.log_broadcaster.subscribe();
-                receivers.push((target.clone(), rx));
+                forwarders.spawn(forward_logs(
+                    target.clone(),
+                    managed.log_broadcaster.subscribe(),
+                    only.clone(),
+                    Arc::clone(&self.processes),
+                    tx.clone(),
+                ));
             }
         }
-
+        drop(tx);
         drop(table);
 
         writer.flush().await?;
 
-        loop {
-            let mut any_received = false;
-            let mut closed_targets = Vec::new();
-
-            for (i, (target, rx)) in receivers.iter_mut().enumerate() {
-                match rx.try_recv() {
-                    Ok(entry) => {
-                        let resp = Response::LogLine {
-                            name: if multi { Some(target.clone()) } else { None },
-                            line: entry.line,
-                        };
-                        let encoded = protocol::encode_response(&resp)?;
-                        if writer.write_all(&encoded).await.is_err() {
-                            return Ok(());
-                        }
-                        any_received = true;
-                    }
-                    Err(tokio::sync::broadcast::error::TryRecvError::Empty) => {}
-                    Err(tokio::sync::broadcast::error::TryRecvError::Lagged(n)) => {
-                        let resp = Response::LogLine {
-                            name: if multi { Some(target.clone()) } else { None },
-                            line: format!("[pm3: {n} log lines dropped due to lag]"),
-                        };
-                        let encoded = protocol::encode_response(&resp)?;
-                        if writer.write_all(&encoded).await.is_err() {
-                            return Ok(());
-                        }
-                        any_received = true;
-                    }
-                    Err(tokio::sync::broadcast::error::TryRecvError::Closed) => {
-                        closed_targets.push(i);
-                    }
-                }
+        while let Some((target, received)) = merged.recv().await {
+            let resp = match received {
+                Ok(entry) => Response::LogLine {
+                    name: if multi { Some(target) } else { None },
+                    stream: Some(entry.stream),
+                    timestamp: Some(log::format_timestamp(entry.time)),
+                    line: entry.line,
+                },
+                Err(dropped) => Response::LogLine {
+                    name: if multi { Some(target) } else { None },
+                    stream: None,
+                    timestamp: Some(log::format_timestamp(chrono::Utc::now())),
+                    line: format!("[pm3: {dropped} log lines dropped due to lag]"),
+                },
+            };
+            let encoded = protocol::encode_response(&resp)?;
+            if writer.write_all(&encoded).await.is_err() {
+                return Ok(());
             }
-
-            // Resubscribe to processes whose broadcasters were closed (process restarted)
-            if !closed_targets.is_empty() {
-                let table = self.processes.read().await;
-                for &i in &closed_targets {
-                    let target = &receivers[i].0;
-                    if let Some(managed) = table.get(target) {
-                        receivers[i].1 = managed.log_broadcaster.subscribe();
-                    }
-                }
+            // Flush once the lines that arrived together are written
+            if merged.is_empty() && writer.flush().await.is_err() {
+                return Ok(());
             }
+        }
+        Ok(())
+    }
 
-            if !any_received {
-                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    /// Streams process events, optionally only those of `names`, until the
+    /// client goes away.
+    pub async fn stream_events(
+        &self,
+        names: Option<Vec<String>>,
+        writer: &mut (impl AsyncWriteExt + Unpin),
+    ) -> color_eyre::Result<()> {
+        let mut rx = self.events.subscribe();
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("events client fell behind, {n} events dropped");
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            };
+            if names
+                .as_ref()
+                .is_some_and(|names| !names.contains(&event.name))
+            {
+                continue;
             }
-
-            if writer.flush().await.is_err() {
+            let encoded = protocol::encode_response(&Response::Event(event))?;
+            if writer.write_all(&encoded).await.is_err() || writer.flush().await.is_err() {
                 return Ok(());
             }
         }
@@ -1169,6 +2800,26 @@ struct SpawnedProcess {
 }
 
 impl SpawnedProcess {
+    fn new(
+        name: &str,
+        managed: &process::ManagedProcess,
+        child: tokio::process::Child,
+        config: ProcessConfig,
+    ) -> Self {
+        let shutdown_tx = managed
+            .monitor_shutdown
+            .as_ref()
+            .expect("monitor shutdown sender missing")
+            .clone();
+        Self {
+            name: name.to_string(),
+            child,
+            pid: managed.pid,
+            config,
+            shutdown_tx,
+        }
+    }
+
     fn spawn_monitors(self, processes: Arc<RwLock<ProcessTable>>, paths: Paths) {
         let shutdown_rx = self.shutdown_tx.subscribe();
         process::spawn_monitor(
@@ -1183,6 +2834,157 @@ impl SpawnedProcess {
     }
 }
 
+type SpawnBatch = Vec<(String, ProcessConfig, process::EnvCmdValues)>;
+type SpawnResult = Result<(process::ManagedProcess, tokio::process::Child), process::ProcessError>;
+
+/// Sends a process's log lines, from `only` that stream if set, to `tx`, or
+/// `Err` with how many were dropped when it fell behind, following the
+/// process across restarts, which give it a new broadcaster.
+async fn forward_logs(
+    target: String,
+    mut rx: broadcast::Receiver<log::LogEntry>,
+    only: Option<log::LogStream>,
+    processes: Arc<RwLock<ProcessTable>>,
+    tx: mpsc::Sender<(String, Result<log::LogEntry, u64>)>,
+) {
+    loop {
+        let received = match rx.recv().await {
+            Ok(entry) if only.as_ref().is_some_and(|only| *only != entry.stream) => continue,
+            Ok(entry) => Ok(entry),
+            Err(broadcast::error::RecvError::Lagged(n)) => Err(n),
+            Err(broadcast::error::RecvError::Closed) => {
+                // Wait for the replacement, which only exists once it's in
+                // the table
+                loop {
+                    if let Some(managed) = processes.read().await.get(&target) {
+                        rx = managed.log_broadcaster.subscribe();
+                        break;
+                    }
+                    if tx.is_closed() {
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                continue;
+            }
+        };
+        if tx.send((target.clone(), received)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Runs the `env_cmd` commands of `configs`, at most `limit` processes at a
+/// time, before the caller takes the process table to spawn them. Those
+/// whose commands fail come back with their error instead.
+async fn resolve_batch(
+    configs: Vec<(String, ProcessConfig)>,
+    limit: usize,
+) -> (SpawnBatch, Vec<(String, ProcessConfig, SpawnResult)>) {
+    let permits = Arc::new(tokio::sync::Semaphore::new(limit.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (idx, (name, config)) in configs.into_iter().enumerate() {
+        let permits = Arc::clone(&permits);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let env_cmd = process::resolve_env_cmd(&config).await;
+            (idx, name, config, env_cmd)
+        });
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    while let Some(joined) = tasks.join_next().await {
+        results.push(joined.expect("env_cmd task panicked"));
+    }
+    results.sort_by_key(|(idx, ..)| *idx);
+    let mut batch = SpawnBatch::new();
+    let mut failed = Vec::new();
+    for (_, name, config, env_cmd) in results {
+        match env_cmd {
+            Ok(env_cmd) => batch.push((name, config, env_cmd)),
+            Err(e) => failed.push((name, config, Err(e))),
+        }
+    }
+    (batch, failed)
+}
+
+/// Log broadcasters waiting for the process of the same name to be spawned,
+/// so whoever made one gets every line it writes.
+type LogTaps = std::sync::Mutex<HashMap<String, broadcast::Sender<log::LogEntry>>>;
+
+/// Spawns a batch of processes concurrently, at most `limit` at a time, each
+/// with the broadcaster tapped for it, if any. Results come back in the
+/// batch's order.
+async fn spawn_batch(
+    batch: SpawnBatch,
+    limit: usize,
+    log_taps: &LogTaps,
+    paths: &Paths,
+) -> Vec<(String, ProcessConfig, SpawnResult)> {
+    let permits = Arc::new(tokio::sync::Semaphore::new(limit.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (idx, (name, config, env_cmd)) in batch.into_iter().enumerate() {
+        let permits = Arc::clone(&permits);
+        let paths = paths.clone();
+        let log_tx = log_taps.lock().unwrap().remove(&name);
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let result =
+                process::spawn_resolved(name.clone(), config.clone(), env_cmd, log_tx, &paths)
+                    .await;
+            (idx, name, config, result)
+        });
+    }
+
+    let mut results = Vec::with_capacity(tasks.len());
+    while let Some(joined) = tasks.join_next().await {
+        results.push(joined.expect("spawn task panicked"));
+    }
+    results.sort_by_key(|(idx, ..)| *idx);
+    results
+        .into_iter()
+        .map(|(_, name, config, result)| (name, config, result))
+        .collect()
+}
+
+type ScopedStart = (HashMap<String, ProcessConfig>, Option<Vec<String>>);
+
+/// Moves a start request into `namespace`: requested names are resolved
+/// against the project's own names, then every process name and
+/// `depends_on` entry is qualified with the namespace.
+fn scope_start(
+    configs: HashMap<String, ProcessConfig>,
+    names: Option<Vec<String>>,
+    namespace: &str,
+) -> Result<ScopedStart, String> {
+    ns::validate(namespace)?;
+    let configs = expand_instances(configs);
+    let names = match names {
+        Some(requested) => Some(
+            resolve_config_names(&requested, &configs)?
+                .iter()
+                .map(|name| ns::qualify(namespace, name))
+                .collect(),
+        ),
+        None => None,
+    };
+    let configs = configs
+        .into_iter()
+        .map(|(name, mut config)| {
+            for dep in config
+                .depends_on
+                .iter_mut()
+                .chain(config.optional_deps.iter_mut())
+                .flatten()
+            {
+                *dep = ns::qualify(namespace, dep);
+            }
+            (ns::qualify(namespace, &name), config)
+        })
+        .collect();
+    Ok((configs, names))
+}
+
 /// Inject a `PATH` value into each config's env map.
 /// Uses `or_insert` so a user-explicit PATH in pm3.toml takes precedence.
 fn inject_path(configs: &mut [(String, ProcessConfig)], path: &str) {
@@ -1197,71 +2999,212 @@ fn resolve_config_names(
     requested: &[String],
     configs: &HashMap<String, ProcessConfig>,
 ) -> Result<Vec<String>, String> {
+    let candidates: Vec<Candidate> = configs
+        .iter()
+        .map(|(key, config)| Candidate {
+            key,
+            local: key,
+            config,
+        })
+        .collect();
+    resolve_names(requested, &candidates, |name| {
+        format!("process, group or tag '{name}' not found in configs")
+    })
+}
+
+/// Resolves the processes a bulk command targets: the requested names (or
+/// every process), minus anything matched by `except`. Exclusions go through
+/// the same name, group, tag and pattern expansion as the targets themselves.
+fn resolve_targets(
+    names: Option<&[String]>,
+    except: Option<&[String]>,
+    table: &ProcessTable,
+    namespace: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let excluded = resolve_excluded(except, table, namespace)?;
+    let mut targets = match names {
+        Some(requested) => resolve_table_names(requested, table, namespace)?,
+        None => namespace_keys(table, namespace),
+    };
+    targets.retain(|name| !excluded.contains(name));
+    Ok(targets)
+}
+
+/// The processes of `namespace`, or of every namespace when it is `None`
+/// (`--all-namespaces`).
+fn namespace_keys(table: &ProcessTable, namespace: Option<&str>) -> Vec<String> {
+    table
+        .keys()
+        .filter(|key| namespace.is_none_or(|ns| ns::local_name(ns, key).is_some()))
+        .cloned()
+        .collect()
+}
+
+/// The processes `--except` names. Callers that add dependents to their
+/// targets drop these again afterwards, so a dependent isn't brought back.
+fn resolve_excluded(
+    except: Option<&[String]>,
+    table: &ProcessTable,
+    namespace: Option<&str>,
+) -> Result<Vec<String>, String> {
+    match except {
+        Some(excluded) => resolve_table_names(excluded, table, namespace),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Resolves names against the process table. With a namespace, only that
+/// namespace's processes are candidates and names are matched against their
+/// unqualified form; without one, names match the full table keys, and a
+/// qualified name (`shop/backend`) is resolved within its namespace so it can
+/// still pick a group or tag.
+fn resolve_table_names(
+    requested: &[String],
+    table: &ProcessTable,
+    namespace: Option<&str>,
+) -> Result<Vec<String>, String> {
+    if namespace.is_some() {
+        return resolve_in(requested, table, namespace);
+    }
     let mut result = Vec::new();
     for name in requested {
-        if configs.contains_key(name) {
-            result.push(name.clone());
-        } else {
-            // Try cluster prefix match: "web" -> "web:0", "web:1", ...
-            let prefix = format!("{}:", name);
-            let cluster_matches: Vec<String> = configs
-                .keys()
-                .filter(|k| k.starts_with(&prefix))
-                .cloned()
-                .collect();
-            if !cluster_matches.is_empty() {
-                result.extend(cluster_matches);
-            } else {
-                let group_matches: Vec<String> = configs
-                    .iter()
-                    .filter(|(_, c)| c.group.as_deref() == Some(name))
-                    .map(|(k, _)| k.clone())
-                    .collect();
-                if group_matches.is_empty() {
-                    return Err(format!("process or group '{}' not found in configs", name));
-                }
-                result.extend(group_matches);
+        let resolved = match name.split_once('/') {
+            Some((ns, local)) if !ns.is_empty() && !local.contains('/') => {
+                resolve_in(&[local.to_string()], table, Some(ns))
+                    .map_err(|_| format!("process, group or tag not found: {name}"))?
             }
-        }
+            _ => resolve_in(std::slice::from_ref(name), table, None)?,
+        };
+        result.extend(resolved);
     }
+    dedup_in_order(&mut result);
     Ok(result)
 }
 
-fn resolve_table_names(requested: &[String], table: &ProcessTable) -> Result<Vec<String>, String> {
+fn resolve_in(
+    requested: &[String],
+    table: &ProcessTable,
+    namespace: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let candidates: Vec<Candidate> = table
+        .iter()
+        .filter_map(|(key, managed)| {
+            let local = match namespace {
+                Some(ns) => ns::local_name(ns, key)?,
+                None => key,
+            };
+            Some(Candidate {
+                key,
+                local,
+                config: &managed.config,
+            })
+        })
+        .collect();
+    resolve_names(requested, &candidates, |name| {
+        format!("process, group or tag not found: {name}")
+    })
+}
+
+struct Candidate<'a> {
+    key: &'a str,
+    local: &'a str,
+    config: &'a ProcessConfig,
+}
+
+/// Matches each requested name against the candidates' local names, trying
+/// in order: a glob or regex pattern, an exact name, a cluster prefix
+/// (`web` -> `web:0`, `web:1`, ...), then a group or tag. Returns the keys
+/// of the matches, each once, in the order they were first matched; names
+/// that match nothing are reported via `not_found`.
+fn resolve_names(
+    requested: &[String],
+    candidates: &[Candidate],
+    not_found: fn(&str) -> String,
+) -> Result<Vec<String>, String> {
     let mut result = Vec::new();
     for name in requested {
-        if table.contains_key(name) {
-            result.push(name.clone());
+        let pattern = select::NamePattern::parse(name)?;
+        if !matches!(pattern, select::NamePattern::Exact(_)) {
+            let mut matched: Vec<String> = candidates
+                .iter()
+                .filter(|c| pattern.matches(c.local))
+                .map(|c| c.key.to_string())
+                .collect();
+            if matched.is_empty() {
+                return Err(format!("no processes match pattern '{name}'"));
+            }
+            matched.sort();
+            result.extend(matched);
+        } else if let Some(c) = candidates.iter().find(|c| c.local == name) {
+            result.push(c.key.to_string());
         } else {
-            // Try cluster prefix match: "web" -> "web:0", "web:1", ...
             let prefix = format!("{}:", name);
-            let cluster_matches: Vec<String> = table
-                .keys()
-                .filter(|k| k.starts_with(&prefix))
-                .cloned()
+            let cluster_matches: Vec<String> = candidates
+                .iter()
+                .filter(|c| c.local.starts_with(&prefix))
+                .map(|c| c.key.to_string())
                 .collect();
             if !cluster_matches.is_empty() {
                 result.extend(cluster_matches);
             } else {
-                let group_matches: Vec<String> = table
+                let group_matches: Vec<String> = candidates
                     .iter()
-                    .filter(|(_, m)| m.config.group.as_deref() == Some(name))
-                    .map(|(k, _)| k.clone())
+                    .filter(|c| c.config.in_group_or_tag(name))
+                    .map(|c| c.key.to_string())
                     .collect();
                 if group_matches.is_empty() {
-                    return Err(format!("process or group not found: {name}"));
+                    return Err(not_found(name));
                 }
                 result.extend(group_matches);
             }
         }
     }
+    dedup_in_order(&mut result);
     Ok(result)
 }
 
+/// Drops repeated keys, keeping the first of each, so a process named by
+/// two overlapping patterns is only acted on once.
+fn dedup_in_order(keys: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    keys.retain(|key| seen.insert(key.clone()));
+}
+
 const DEP_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
 const DEP_POLL_INTERVAL: Duration = Duration::from_millis(200);
 const STARTUP_WAIT_BUFFER_SECS: u64 = 5;
 
+/// How often `pm3 run` checks whether its task has exited.
+const TASK_EXIT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+/// How long `pm3 run` waits for more output of a task that has exited before
+/// it reports the exit.
+const TASK_OUTPUT_GRACE: Duration = Duration::from_millis(100);
+/// How long the process table must go unchanged before it is autosaved.
+const AUTOSAVE_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Waits for one dependency level to come online before the next is
+/// started. Processes in `optional` don't hold their dependents up: if one
+/// doesn't come online, the warning saying so is returned instead.
+async fn wait_for_level(
+    names: &[String],
+    optional: &HashSet<String>,
+    processes: &Arc<RwLock<ProcessTable>>,
+) -> Result<Option<String>, String> {
+    let (optional_names, required_names): (Vec<String>, Vec<String>) = names
+        .iter()
+        .cloned()
+        .partition(|name| optional.contains(name));
+    wait_for_online(&required_names, processes).await?;
+    if !optional_names.is_empty()
+        && let Err(msg) = wait_for_online(&optional_names, processes).await
+    {
+        let warning = format!("{msg}, starting dependents without it");
+        tracing::warn!("{warning}");
+        return Ok(Some(warning));
+    }
+    Ok(None)
+}
+
 async fn wait_for_online(
     names: &[String],
     processes: &Arc<RwLock<ProcessTable>>,
@@ -1276,7 +3219,9 @@ async fn wait_for_online(
             };
 
             let mut startup_timeout_secs = 0_u64;
-            if managed.config.readiness_check.is_some() {
+            if managed.config.readiness_check.is_some()
+                || managed.config.ready_signal == Some(ReadySignal::Ipc)
+            {
                 startup_timeout_secs = startup_timeout_secs.saturating_add(
                     managed
                         .config
@@ -1288,6 +3233,9 @@ async fn wait_for_online(
                 startup_timeout_secs =
                     startup_timeout_secs.saturating_add(health::HEALTH_CHECK_TIMEOUT_SECS);
             }
+            if let Some(delay_ms) = managed.config.ready_delay {
+                startup_timeout_secs = startup_timeout_secs.saturating_add(delay_ms.div_ceil(1000));
+            }
 
             if startup_timeout_secs > 0 {
                 let with_buffer = Duration::from_secs(
@@ -1316,8 +3264,15 @@ async fn wait_for_online(
             for name in names {
                 if let Some(managed) = table.get(name) {
                     match managed.status {
-                        ProcessStatus::Online => {}
-                        ProcessStatus::Stopped | ProcessStatus::Errored => {
+                        // A task dependency is satisfied once it has finished successfully
+                        ProcessStatus::Online if managed.config.is_task() => {
+                            all_online = false;
+                        }
+                        ProcessStatus::Online | ProcessStatus::Succeeded => {}
+                        ProcessStatus::Stopped
+                        | ProcessStatus::Errored
+                        | ProcessStatus::Flapping
+                        | ProcessStatus::Failed => {
                             return Err(format!(
                                 "dependency '{}' failed (status: {})",
                                 name, managed.status
@@ -1326,7 +3281,7 @@ async fn wait_for_online(
                         ProcessStatus::Unhealthy => {
                             return Err(format!("dependency '{}' is unhealthy", name));
                         }
-                        ProcessStatus::Starting => {
+                        ProcessStatus::Starting | ProcessStatus::Suspended => {
                             all_online = false;
                         }
                     }
@@ -1343,18 +3298,124 @@ async fn wait_for_online(
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct DumpEntry {
-    name: String,
-    config: ProcessConfig,
-    pid: Option<u32>,
-    restarts: u32,
+/// Whether a process in `current` satisfies a wait for `target`. `stopped`
+/// means no longer running, however it ended. Waiting for `online` gives up
+/// (`Err`) once a process has crashed out, as dependency waits do.
+fn wait_satisfied(current: ProcessStatus, target: ProcessStatus) -> Result<bool, ()> {
+    match target {
+        ProcessStatus::Online => match current {
+            ProcessStatus::Online => Ok(true),
+            ProcessStatus::Errored | ProcessStatus::Flapping | ProcessStatus::Failed => Err(()),
+            _ => Ok(false),
+        },
+        ProcessStatus::Stopped => Ok(matches!(
+            current,
+            ProcessStatus::Stopped
+                | ProcessStatus::Errored
+                | ProcessStatus::Failed
+                | ProcessStatus::Succeeded
+        )),
+        _ => Ok(current == target),
+    }
+}
+
+/// The processes and lines a `pm3 log` asks for.
+pub struct LogQuery {
+    pub name: Option<String>,
+    /// Where a query without a name looks, or every namespace when unset.
+    pub namespace: Option<String>,
+    pub lines: usize,
+    pub follow: bool,
+    pub range: log::TimeRange,
+    /// Only lines from this stream, or both when unset.
+    pub only: Option<log::LogStream>,
+}
+
+/// Where `start --wait` reports per-level progress. The default discards
+/// it, for clients that only read the final response.
+#[derive(Clone, Default)]
+pub struct Progress(Option<mpsc::UnboundedSender<Response>>);
+
+impl Progress {
+    pub fn streaming(tx: mpsc::UnboundedSender<Response>) -> Self {
+        Self(Some(tx))
+    }
+
+    fn report(&self, message: String) {
+        if let Some(tx) = &self.0 {
+            let _ = tx.send(Response::Progress { message });
+        }
+    }
+}
+
+/// Whether a process has stopped or given up, and can be started again.
+fn is_down(managed: &process::ManagedProcess) -> bool {
+    matches!(
+        managed.status,
+        ProcessStatus::Stopped
+            | ProcessStatus::Errored
+            | ProcessStatus::Flapping
+            | ProcessStatus::Succeeded
+            | ProcessStatus::Failed
+    )
 }
 
 fn is_pid_alive(pid: u32) -> bool {
     crate::sys::is_pid_alive(pid)
 }
 
+/// Resumes copying a handed-over process's output to its logs, reading
+/// copies of the fds so the originals can be handed over again.
+#[cfg(unix)]
+fn copy_handed_over_output(
+    name: &str,
+    config: &ProcessConfig,
+    fds: &process::OutputFds,
+    managed: &process::ManagedProcess,
+    paths: &Paths,
+) {
+    let logs = log::LogFiles::new(paths, name, config);
+    let limits = log::LogLimits::new(paths, config);
+    let streams = [
+        (log::LogStream::Stdout, &fds.stdout),
+        (log::LogStream::Stderr, &fds.stderr),
+    ];
+    for (stream, fd) in streams {
+        let reader = fd
+            .as_ref()
+            .and_then(|fd| fd.try_clone().ok())
+            .and_then(|fd| crate::sys::fd_reader(fd).ok());
+        if let Some(reader) = reader {
+            log::spawn_log_copier(
+                stream,
+                reader,
+                &logs,
+                config.log_date_format.clone(),
+                &limits,
+                &managed.log_control,
+                managed.log_broadcaster.clone(),
+            );
+        }
+    }
+}
+
+/// Broadcasts the lines a log relay appends to an adopted process's logs
+/// until `relay_exited`, as this daemon doesn't read its output itself.
+fn follow_relayed_logs(
+    name: &str,
+    managed: &process::ManagedProcess,
+    paths: &Paths,
+    relay_exited: impl std::future::Future<Output = ()> + Send + 'static,
+) {
+    log::spawn_log_follower(
+        log::LogFiles::new(paths, name, &managed.config),
+        managed.config.log_date_format.clone(),
+        managed.log_broadcaster.downgrade(),
+        managed.log_control.suppressed.clone(),
+        relay_exited,
+    );
+}
+
 /// Expand cluster-mode processes: entries with `instances > 1` are replaced by
 /// N individual entries named `<name>:0` .. `<name>:N-1`.  Dependencies that
 /// reference a clustered name are rewritten to point at the individual instances.
@@ -1368,11 +3429,6 @@ pub fn expand_instances(configs: HashMap<String, ProcessConfig>) -> HashMap<Stri
         }
     }
 
-    // If nothing is clustered, return as-is.
-    if cluster_map.is_empty() {
-        return configs;
-    }
-
     let mut result: HashMap<String, ProcessConfig> = HashMap::new();
 
     for (name, config) in configs {
@@ -1398,6 +3454,8 @@ pub fn expand_instances(configs: HashMap<String, ProcessConfig>) -> HashMap<Stri
             // Non-clustered: just rewrite deps if needed.
             let mut cfg = config;
             cfg.depends_on = rewrite_deps(&cfg.depends_on);
+            cfg.optional_deps = rewrite_deps(&cfg.optional_deps);
+            inject_port(&mut cfg, 0);
             result.insert(name, cfg);
         } else {
             // Clustered: expand into N entries.
@@ -1406,6 +3464,7 @@ pub fn expand_instances(configs: HashMap<String, ProcessConfig>) -> HashMap<Stri
                 let mut cfg = config.clone();
                 cfg.instances = Some(1);
                 cfg.depends_on = rewrite_deps(&cfg.depends_on);
+                cfg.optional_deps = rewrite_deps(&cfg.optional_deps);
 
                 // Auto-set group to logical name if user didn't specify one.
                 if cfg.group.is_none() {
@@ -1416,6 +3475,10 @@ pub fn expand_instances(configs: HashMap<String, ProcessConfig>) -> HashMap<Stri
                 let env = cfg.env.get_or_insert_with(HashMap::new);
                 env.insert("PM3_INSTANCE_ID".to_string(), i.to_string());
                 env.insert("PM3_INSTANCE_COUNT".to_string(), n.to_string());
+                if let Some(overrides) = cfg.instance_env.as_ref().and_then(|e| e.get(&i)) {
+                    env.extend(overrides.clone());
+                }
+                inject_port(&mut cfg, i);
 
                 result.insert(instance_name, cfg);
             }
@@ -1425,6 +3488,106 @@ pub fn expand_instances(configs: HashMap<String, ProcessConfig>) -> HashMap<Stri
     result
 }
 
+/// Reverses `expand_instances`: `<name>:N` entries are folded back into one
+/// `<name>` entry with `instances` set to how many there are, dropping the
+/// env, group and dependency rewrites the expansion added.
+pub fn collapse_instances(
+    configs: HashMap<String, ProcessConfig>,
+) -> HashMap<String, ProcessConfig> {
+    let mut clusters: BTreeMap<String, Vec<(u32, ProcessConfig)>> = BTreeMap::new();
+    let mut result: HashMap<String, ProcessConfig> = HashMap::new();
+    for (name, config) in configs {
+        let instance = name
+            .rsplit_once(':')
+            .and_then(|(base, index)| Some((base, index.parse::<u32>().ok()?)));
+        match instance {
+            Some((base, index)) => clusters
+                .entry(base.to_string())
+                .or_default()
+                .push((index, config)),
+            None => {
+                result.insert(name, config);
+            }
+        }
+    }
+
+    let clustered: HashSet<String> = clusters.keys().cloned().collect();
+    for (name, mut instances) in clusters {
+        instances.sort_by_key(|(index, _)| *index);
+        // A var one instance's instance_env overrides keeps its base value in
+        // the instances that don't override it
+        let mut env = HashMap::new();
+        for (index, config) in &instances {
+            let overrides = config.instance_env.as_ref().and_then(|e| e.get(index));
+            for (key, value) in config.env.iter().flatten() {
+                if overrides.is_none_or(|o| !o.contains_key(key)) {
+                    env.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+        env.remove("PM3_INSTANCE_ID");
+        env.remove("PM3_INSTANCE_COUNT");
+
+        let count = instances.len() as u32;
+        let (_, mut config) = instances.swap_remove(0);
+        config.env = Some(env);
+        config.instances = Some(count);
+        if config.group.as_deref() == Some(name.as_str()) {
+            config.group = None;
+        }
+        result.insert(name, config);
+    }
+
+    let fold_deps = |deps: &mut Option<Vec<String>>| {
+        let Some(deps) = deps else {
+            return;
+        };
+        let mut folded: Vec<String> = Vec::new();
+        for dep in deps.drain(..) {
+            let dep = match dep.rsplit_once(':') {
+                Some((base, _)) if clustered.contains(base) => base.to_string(),
+                _ => dep,
+            };
+            if !folded.contains(&dep) {
+                folded.push(dep);
+            }
+        }
+        *deps = folded;
+    };
+    for config in result.values_mut() {
+        fold_deps(&mut config.depends_on);
+        fold_deps(&mut config.optional_deps);
+        // `port` and an env PORT can't both be set, so PORT came from `inject_port`
+        if config.port.is_some()
+            && let Some(env) = config.env.as_mut()
+        {
+            env.remove("PORT");
+        }
+        if config.env.as_ref().is_some_and(HashMap::is_empty) {
+            config.env = None;
+        }
+    }
+
+    result
+}
+
+/// Sets `PORT` from the `port` option, offset by the instance index when
+/// `increment_port` is on. A `PORT` already set by an earlier expansion wins.
+fn inject_port(cfg: &mut ProcessConfig, index: u32) {
+    let Some(port) = cfg.port else {
+        return;
+    };
+    let port = if cfg.increment_port == Some(true) {
+        u32::from(port) + index
+    } else {
+        u32::from(port)
+    };
+    cfg.env
+        .get_or_insert_with(HashMap::new)
+        .entry("PORT".to_string())
+        .or_insert_with(|| port.to_string());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1436,26 +3599,73 @@ mod tests {
             cwd: None,
             env: None,
             env_file: None,
+            env_cmd: None,
             readiness_check: None,
             readiness_timeout: None,
             health_check: None,
+            on_unhealthy: None,
+            unhealthy_threshold: None,
             kill_timeout: None,
             kill_signal: None,
             max_restarts: None,
             max_memory: None,
+            memory_check_interval: None,
+            memory_action: None,
+            memory_leak_window: None,
+            memory_leak_action: None,
             min_uptime: None,
             stop_exit_codes: None,
             watch: None,
             watch_ignore: None,
+            watch_extensions: None,
+            watch_use_gitignore: None,
+            watch_debounce: None,
+            watch_delay: None,
+            watch_poll: None,
+            watch_env_files: None,
+            watch_command: None,
+            watch_restart: None,
             depends_on: None,
             restart: None,
             group: None,
             pre_start: None,
             post_stop: None,
             cron_restart: None,
+            cron_start: None,
+            cron_stop: None,
+            cron_timezone: None,
+            tags: None,
+            ready_signal: None,
+            ready_delay: None,
+            drain_signal: None,
+            drain_timeout: None,
+            port: None,
+            increment_port: None,
+            instance_env: None,
+            optional_deps: None,
+            stop_dependents: None,
+            restart_stagger: None,
             log_date_format: None,
+            log_combine: None,
+            merge_logs: None,
+            stdout_log: None,
+            stderr_log: None,
+            log_max_line_len: None,
+            log_rate_limit: None,
             instances: None,
+            flap_threshold: None,
+            flap_window: None,
+            flap_cooldown: None,
+            conflict_check: None,
+            restart_delay: None,
+            restart_backoff: None,
+            max_restart_delay: None,
+            max_restarts_window: None,
+            kind: None,
+            metrics_endpoint: None,
+            metrics: None,
             environments: HashMap::new(),
+            environment_overrides: HashMap::new(),
         }
     }
 
@@ -1491,6 +3701,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_expand_instances_assigns_ports() {
+        let mut configs = HashMap::new();
+        let mut web = cfg("node server.js");
+        web.instances = Some(3);
+        web.port = Some(3000);
+        web.increment_port = Some(true);
+        configs.insert("web".to_string(), web);
+        let mut api = cfg("node api.js");
+        api.port = Some(8080);
+        configs.insert("api".to_string(), api);
+
+        // Expanding twice (as namespaced starts do) keeps each instance's port
+        let result = expand_instances(expand_instances(configs));
+        for i in 0..3 {
+            let env = result[&format!("web:{i}")].env.as_ref().unwrap();
+            assert_eq!(env["PORT"], (3000 + i).to_string());
+        }
+        assert_eq!(result["api"].env.as_ref().unwrap()["PORT"], "8080");
+    }
+
+    #[test]
+    fn test_expand_instances_applies_instance_env() {
+        let mut configs = HashMap::new();
+        let mut db = cfg("postgres");
+        db.instances = Some(2);
+        db.env = Some(HashMap::from([("ROLE".to_string(), "replica".to_string())]));
+        db.instance_env = Some(std::collections::BTreeMap::from([(
+            0,
+            HashMap::from([("ROLE".to_string(), "primary".to_string())]),
+        )]));
+        configs.insert("db".to_string(), db);
+
+        let result = expand_instances(configs);
+        assert_eq!(result["db:0"].env.as_ref().unwrap()["ROLE"], "primary");
+        assert_eq!(result["db:1"].env.as_ref().unwrap()["ROLE"], "replica");
+    }
+
     #[test]
     fn test_expand_instances_preserves_custom_group() {
         let mut configs = HashMap::new();
@@ -1565,6 +3813,34 @@ mod tests {
         assert!(result.contains_key("web"));
     }
 
+    #[test]
+    fn test_collapse_instances_reverses_expansion() {
+        let mut configs = HashMap::new();
+        let mut web = cfg("node server.js");
+        web.instances = Some(3);
+        web.port = Some(3000);
+        web.increment_port = Some(true);
+        web.env = Some(HashMap::from([("ROLE".to_string(), "replica".to_string())]));
+        web.instance_env = Some(BTreeMap::from([(
+            0,
+            HashMap::from([("ROLE".to_string(), "primary".to_string())]),
+        )]));
+        configs.insert("web".to_string(), web);
+        let mut api = cfg("node api.js");
+        api.port = Some(8080);
+        api.depends_on = Some(vec!["web".to_string(), "db".to_string()]);
+        api.optional_deps = Some(vec!["web".to_string()]);
+        configs.insert("api".to_string(), api);
+        let mut db = cfg("postgres");
+        db.group = Some("storage".to_string());
+        configs.insert("db".to_string(), db);
+
+        assert_eq!(
+            collapse_instances(expand_instances(configs.clone())),
+            configs
+        );
+    }
+
     #[test]
     fn test_resolve_config_names_cluster_prefix() {
         let mut configs = HashMap::new();
@@ -1600,4 +3876,69 @@ mod tests {
         let result = resolve_config_names(&["backend".to_string()], &configs).unwrap();
         assert_eq!(result, vec!["web".to_string()]);
     }
+
+    #[test]
+    fn test_resolve_config_names_tags() {
+        let mut configs = HashMap::new();
+        let mut payments = cfg("node payments.js");
+        payments.group = Some("backend".to_string());
+        payments.tags = Some(vec!["critical".to_string(), "payments".to_string()]);
+        configs.insert("payments".to_string(), payments);
+        let mut db = cfg("postgres");
+        db.tags = Some(vec!["critical".to_string()]);
+        configs.insert("db".to_string(), db);
+        configs.insert("web".to_string(), cfg("node server.js"));
+
+        let mut result = resolve_config_names(&["critical".to_string()], &configs).unwrap();
+        result.sort();
+        assert_eq!(result, vec!["db".to_string(), "payments".to_string()]);
+
+        let result = resolve_config_names(&["payments".to_string()], &configs).unwrap();
+        assert_eq!(result, vec!["payments".to_string()]);
+    }
+
+    #[test]
+    fn test_resolve_config_names_glob_and_regex() {
+        let mut configs = HashMap::new();
+        configs.insert("web:0".to_string(), cfg("node server.js"));
+        configs.insert("web:1".to_string(), cfg("node server.js"));
+        configs.insert("worker:0".to_string(), cfg("node worker.js"));
+        configs.insert("db".to_string(), cfg("postgres"));
+
+        let result = resolve_config_names(&["w*:0".to_string()], &configs).unwrap();
+        assert_eq!(result, vec!["web:0".to_string(), "worker:0".to_string()]);
+
+        let result = resolve_config_names(&["/^web:/".to_string()], &configs).unwrap();
+        assert_eq!(result, vec!["web:0".to_string(), "web:1".to_string()]);
+
+        let err = resolve_config_names(&["api-*".to_string()], &configs).unwrap_err();
+        assert!(err.contains("api-*"));
+    }
+
+    #[test]
+    fn test_resolve_config_names_overlapping_patterns() {
+        let mut configs = HashMap::new();
+        configs.insert("web:0".to_string(), cfg("node server.js"));
+        configs.insert("web:1".to_string(), cfg("node server.js"));
+        let mut worker = cfg("node worker.js");
+        worker.group = Some("backend".to_string());
+        configs.insert("worker".to_string(), worker);
+
+        let requested = ["worker", "web:*", "web", "backend", "*:1"].map(String::from);
+        let result = resolve_config_names(&requested, &configs).unwrap();
+        assert_eq!(result, vec!["worker", "web:0", "web:1"]);
+    }
+
+    #[test]
+    fn test_wait_satisfied() {
+        use ProcessStatus::*;
+        assert_eq!(wait_satisfied(Online, Online), Ok(true));
+        assert_eq!(wait_satisfied(Starting, Online), Ok(false));
+        assert_eq!(wait_satisfied(Stopped, Online), Ok(false));
+        assert_eq!(wait_satisfied(Errored, Online), Err(()));
+        assert_eq!(wait_satisfied(Errored, Stopped), Ok(true));
+        assert_eq!(wait_satisfied(Succeeded, Stopped), Ok(true));
+        assert_eq!(wait_satisfied(Online, Stopped), Ok(false));
+        assert_eq!(wait_satisfied(Unhealthy, Unhealthy), Ok(true));
+    }
 }
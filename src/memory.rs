@@ -1,7 +1,8 @@
+use crate::config::ProcessConfig;
 use crate::paths::Paths;
 use crate::process::{self, ProcessError, ProcessTable};
-use crate::protocol::ProcessStatus;
-use std::collections::HashMap;
+use crate::protocol::{ProcessStatus, ResourceUsage};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{RwLock, watch};
@@ -45,21 +46,19 @@ pub fn parse_memory_string(s: &str) -> Result<u64, ProcessError> {
 
     Ok((value * multiplier as f64) as u64)
 }
-#[cfg(unix)]
+/// Resident memory of `pid` together with every process it spawned, so a
+/// `sh -c` wrapper counts the memory of the server it started.
+#[cfg(target_os = "linux")]
 pub async fn read_rss_bytes(pid: u32) -> Option<u64> {
-    let output = tokio::process::Command::new("ps")
-        .args(["-o", "rss=", "-p", &pid.to_string()])
-        .output()
+    tokio::task::spawn_blocking(move || proc_memory_tree(&read_proc_table()).total_memory(pid))
         .await
-        .ok()?;
-
-    if !output.status.success() {
-        return None;
-    }
+        .ok()?
+}
 
-    let text = String::from_utf8_lossy(&output.stdout);
-    let kb: u64 = text.trim().parse().ok()?;
-    Some(kb * 1024)
+#[cfg(all(unix, not(target_os = "linux")))]
+pub async fn read_rss_bytes(pid: u32) -> Option<u64> {
+    let entries = crate::tree::read_process_table().await?;
+    MemoryTree::from_ps(&entries).total_memory(pid)
 }
 
 #[cfg(windows)]
@@ -70,41 +69,268 @@ pub async fn read_rss_bytes(_pid: u32) -> Option<u64> {
 pub struct ProcessStats {
     pub cpu_percent: Option<f64>,
     pub memory_bytes: Option<u64>,
+    pub resources: Option<ResourceUsage>,
 }
 
 pub type StatsCache = HashMap<u32, ProcessStats>;
 
-#[cfg(unix)]
-pub async fn read_process_stats(pid: u32) -> Option<(f64, u64)> {
-    let output = tokio::process::Command::new("ps")
-        .args(["-o", "%cpu=,rss=", "-p", &pid.to_string()])
-        .output()
-        .await
-        .ok()?;
+/// The memory of every process and what spawned it, to add up the memory of
+/// a process together with all of its descendants.
+#[derive(Debug, Default)]
+pub struct MemoryTree {
+    memory: HashMap<u32, u64>,
+    children: HashMap<u32, Vec<u32>>,
+}
 
-    if !output.status.success() {
-        return None;
+impl MemoryTree {
+    pub fn from_ps(entries: &[crate::tree::PsEntry]) -> Self {
+        let mut tree = Self::default();
+        for entry in entries {
+            tree.insert(entry.pid, entry.ppid, entry.memory_bytes);
+        }
+        tree
     }
 
-    let text = String::from_utf8_lossy(&output.stdout);
-    let text = text.trim();
-    let mut parts = text.split_whitespace();
-    let cpu: f64 = parts.next()?.parse().ok()?;
-    let rss_kb: u64 = parts.next()?.parse().ok()?;
-    Some((cpu, rss_kb * 1024))
+    pub fn insert(&mut self, pid: u32, ppid: u32, bytes: u64) {
+        self.memory.insert(pid, bytes);
+        if pid != ppid {
+            self.children.entry(ppid).or_default().push(pid);
+        }
+    }
+
+    /// The memory of `pid` and its descendants, or `None` when `pid` isn't
+    /// running.
+    pub fn total_memory(&self, pid: u32) -> Option<u64> {
+        self.memory.get(&pid)?;
+        let tree = crate::sys::process_tree(pid, &self.children);
+        Some(tree.iter().filter_map(|p| self.memory.get(p)).sum())
+    }
 }
 
-#[cfg(windows)]
-pub async fn read_process_stats(_pid: u32) -> Option<(f64, u64)> {
+/// Parent, CPU time, start time and resident memory of a process, as read
+/// from `/proc/<pid>/stat`.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ProcStat {
+    ppid: u32,
+    /// User plus system time, in clock ticks.
+    cpu_ticks: u64,
+    /// Clock ticks after boot the process started at.
+    start_ticks: u64,
+    rss_pages: u64,
+}
+
+#[cfg(target_os = "linux")]
+fn parse_proc_stat(stat: &str) -> Option<ProcStat> {
+    // The command name is parenthesised and may hold spaces or parens itself
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    // Numbered as in proc(5), where the first field after the name is the 3rd
+    let field = |n: usize| fields.get(n - 3)?.parse::<u64>().ok();
+    Some(ProcStat {
+        ppid: u32::try_from(field(4)?).ok()?,
+        cpu_ticks: field(14)? + field(15)?,
+        start_ticks: field(22)?,
+        rss_pages: field(24)?,
+    })
+}
+
+/// The stat of every process on the system, by pid.
+#[cfg(target_os = "linux")]
+fn read_proc_table() -> HashMap<u32, ProcStat> {
+    let Ok(dir) = std::fs::read_dir("/proc") else {
+        return HashMap::new();
+    };
+    dir.flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+            Some((pid, parse_proc_stat(&stat)?))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn proc_memory_tree(table: &HashMap<u32, ProcStat>) -> MemoryTree {
+    let page_size = page_size();
+    let mut tree = MemoryTree::default();
+    for (&pid, stat) in table {
+        tree.insert(pid, stat.ppid, stat.rss_pages * page_size);
+    }
+    tree
+}
+
+#[cfg(target_os = "linux")]
+fn clock_ticks_per_sec() -> f64 {
+    use nix::unistd::{SysconfVar, sysconf};
+    sysconf(SysconfVar::CLK_TCK)
+        .ok()
+        .flatten()
+        .map_or(100.0, |ticks| ticks as f64)
+}
+
+#[cfg(target_os = "linux")]
+fn page_size() -> u64 {
+    use nix::unistd::{SysconfVar, sysconf};
+    sysconf(SysconfVar::PAGE_SIZE)
+        .ok()
+        .flatten()
+        .map_or(4096, |size| size as u64)
+}
+
+/// Samples the CPU and memory of every running process in one pass, for
+/// the stats collector. On Linux it reads `/proc` and keeps each process's
+/// CPU time from the previous pass, so CPU% is its use since then rather
+/// than the average over its whole life that `ps` reports. Elsewhere it
+/// runs `ps` once for all of them. Memory includes the processes each one
+/// spawned.
+#[derive(Debug, Default)]
+pub struct Sampler {
+    /// Start time, CPU time and when it was read, by pid. The start time
+    /// tells a reused pid apart from the process it last belonged to.
+    #[cfg(target_os = "linux")]
+    previous: HashMap<u32, (u64, u64, std::time::Instant)>,
+}
+
+impl Sampler {
+    #[cfg(target_os = "linux")]
+    pub async fn sample(&mut self, pids: Vec<u32>) -> StatsCache {
+        let previous = std::mem::take(&mut self.previous);
+        let (cache, previous) = tokio::task::spawn_blocking(move || sample_proc(&pids, &previous))
+            .await
+            .unwrap_or_default();
+        self.previous = previous;
+        cache
+    }
+
+    #[cfg(all(unix, not(target_os = "linux")))]
+    pub async fn sample(&mut self, pids: Vec<u32>) -> StatsCache {
+        let Some(entries) = crate::tree::read_process_table().await else {
+            return StatsCache::new();
+        };
+        let tree = MemoryTree::from_ps(&entries);
+        entries
+            .iter()
+            .filter(|entry| pids.contains(&entry.pid))
+            .map(|entry| {
+                let stats = ProcessStats {
+                    cpu_percent: Some(entry.cpu_percent),
+                    memory_bytes: tree.total_memory(entry.pid),
+                    resources: None,
+                };
+                (entry.pid, stats)
+            })
+            .collect()
+    }
+
+    #[cfg(windows)]
+    pub async fn sample(&mut self, _pids: Vec<u32>) -> StatsCache {
+        StatsCache::new()
+    }
+}
+
+#[cfg(target_os = "linux")]
+type CpuTimes = HashMap<u32, (u64, u64, std::time::Instant)>;
+
+/// Reads the stats of `pids` from `/proc`, working out CPU% against
+/// `previous` CPU times. Returns the stats and the CPU times to compare the
+/// next pass against. Processes that exited are left out of both.
+#[cfg(target_os = "linux")]
+fn sample_proc(pids: &[u32], previous: &CpuTimes) -> (StatsCache, CpuTimes) {
+    let ticks_per_sec = clock_ticks_per_sec();
+    let uptime = std::fs::read_to_string("/proc/uptime")
+        .ok()
+        .and_then(|s| s.split_whitespace().next()?.parse::<f64>().ok());
+    let now = std::time::Instant::now();
+
+    let table = read_proc_table();
+    let tree = proc_memory_tree(&table);
+
+    let mut cache = StatsCache::new();
+    let mut times = CpuTimes::new();
+    for &pid in pids {
+        let Some(stat) = table.get(&pid) else {
+            continue;
+        };
+        let cpu_secs = |ticks: u64| ticks as f64 / ticks_per_sec;
+        let cpu_percent = match previous.get(&pid) {
+            Some(&(start, ticks, at))
+                if start == stat.start_ticks && stat.cpu_ticks >= ticks && now > at =>
+            {
+                Some(cpu_secs(stat.cpu_ticks - ticks) / (now - at).as_secs_f64() * 100.0)
+            }
+            // Seen for the first time: its average since it started
+            _ => uptime.map(|uptime| {
+                let age = (uptime - cpu_secs(stat.start_ticks)).max(cpu_secs(1));
+                cpu_secs(stat.cpu_ticks) / age * 100.0
+            }),
+        };
+        times.insert(pid, (stat.start_ticks, stat.cpu_ticks, now));
+        cache.insert(
+            pid,
+            ProcessStats {
+                cpu_percent,
+                memory_bytes: tree.total_memory(pid),
+                resources: read_resource_usage(pid),
+            },
+        );
+    }
+    (cache, times)
+}
+
+/// Open fds, threads, sockets and storage IO of `pid`, from `/proc`.
+#[cfg(target_os = "linux")]
+pub fn read_resource_usage(pid: u32) -> Option<ResourceUsage> {
+    let proc = std::path::PathBuf::from(format!("/proc/{pid}"));
+    let status = std::fs::read_to_string(proc.join("status")).ok()?;
+    let threads = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))
+        .and_then(|n| n.trim().parse().ok())?;
+    let mut fds = 0;
+    let mut sockets = 0;
+    for entry in std::fs::read_dir(proc.join("fd")).ok()?.flatten() {
+        fds += 1;
+        if std::fs::read_link(entry.path())
+            .is_ok_and(|target| target.to_string_lossy().starts_with("socket:"))
+        {
+            sockets += 1;
+        }
+    }
+    let (read_bytes, write_bytes) = std::fs::read_to_string(proc.join("io"))
+        .map(|io| parse_proc_io(&io))
+        .unwrap_or_default();
+    Some(ResourceUsage {
+        fds,
+        threads,
+        sockets,
+        read_bytes,
+        write_bytes,
+    })
+}
+
+/// Without `/proc` there is nothing cheap to read these from.
+#[cfg(not(target_os = "linux"))]
+pub fn read_resource_usage(_pid: u32) -> Option<ResourceUsage> {
     None
 }
 
+/// `read_bytes` and `write_bytes` out of `/proc/<pid>/io`.
+fn parse_proc_io(io: &str) -> (Option<u64>, Option<u64>) {
+    let field = |name: &str| {
+        io.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|n| n.trim().parse().ok())
+    };
+    (field("read_bytes"), field("write_bytes"))
+}
+
 pub fn spawn_stats_collector(
     processes: Arc<RwLock<ProcessTable>>,
     stats_cache: Arc<RwLock<StatsCache>>,
     mut shutdown_rx: watch::Receiver<bool>,
 ) {
     tokio::spawn(async move {
+        let mut sampler = Sampler::default();
         loop {
             tokio::select! {
                 _ = tokio::time::sleep(STATS_POLL_INTERVAL) => {}
@@ -120,54 +346,203 @@ pub fn spawn_stats_collector(
             }
 
             // Collect PIDs of running processes
-            let pids: Vec<(String, u32)> = {
+            let pids: Vec<u32> = {
                 let table = processes.read().await;
                 table
-                    .iter()
-                    .filter(|(_, m)| {
-                        matches!(m.status, ProcessStatus::Online | ProcessStatus::Starting)
-                    })
-                    .filter_map(|(name, m)| m.pid.map(|pid| (name.clone(), pid)))
+                    .values()
+                    .filter(|m| matches!(m.status, ProcessStatus::Online | ProcessStatus::Starting))
+                    .filter_map(|m| m.pid)
                     .collect()
             };
 
-            let mut new_cache = HashMap::new();
-            for (_name, pid) in &pids {
-                if let Some((cpu, mem)) = read_process_stats(*pid).await {
-                    new_cache.insert(
-                        *pid,
-                        ProcessStats {
-                            cpu_percent: Some(cpu),
-                            memory_bytes: Some(mem),
-                        },
-                    );
-                }
-            }
-
+            let new_cache = sampler.sample(pids).await;
             *stats_cache.write().await = new_cache;
         }
     });
 }
+/// What the memory monitor does when a process goes over `max_memory`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryAction {
+    /// Kill the process and start it again.
+    Restart,
+    /// Stop the process and leave it stopped.
+    Stop,
+    /// Send the process a signal, e.g. one that makes it dump its heap.
+    Signal(String),
+    /// Only report it, as a `memory_limit` event.
+    EventOnly,
+}
+
+impl MemoryAction {
+    /// Parses a `memory_action`: `restart`, `stop`, `signal:<SIGNAL>` or
+    /// `event-only`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.trim() {
+            "restart" => Ok(Self::Restart),
+            "stop" => Ok(Self::Stop),
+            "event-only" => Ok(Self::EventOnly),
+            other => match other.strip_prefix("signal:") {
+                Some(signal) => match process::parse_signal(signal) {
+                    Ok(_) => Ok(Self::Signal(signal.to_string())),
+                    Err(e) => Err(format!("invalid memory_action '{s}': {e}")),
+                },
+                None => Err(format!(
+                    "invalid memory_action '{s}': expected restart, stop, signal:<SIGNAL> or event-only"
+                )),
+            },
+        }
+    }
+
+    /// What is done about it, for the log.
+    fn describe(&self) -> String {
+        match self {
+            Self::Restart => "restarting".to_string(),
+            Self::Stop => "stopping".to_string(),
+            Self::Signal(signal) => format!("sending {signal}"),
+            Self::EventOnly => "reporting only".to_string(),
+        }
+    }
+}
+
+/// How soon memory must be on course to reach `max_memory` for its growth to
+/// count as a leak.
+const LEAK_HORIZON: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Memory growing steadily toward `max_memory`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeakTrend {
+    /// How fast memory grew over `memory_leak_window`.
+    pub bytes_per_sec: f64,
+    /// When it reaches `max_memory` if it keeps growing at that rate.
+    pub secs_to_limit: u64,
+}
+
+impl std::fmt::Display for LeakTrend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mb_per_min = self.bytes_per_sec * 60.0 / (1024.0 * 1024.0);
+        let mins = self.secs_to_limit.div_ceil(60);
+        let eta = if mins >= 60 {
+            format!("{}h {}m", mins / 60, mins % 60)
+        } else {
+            format!("{mins}m")
+        };
+        write!(
+            f,
+            "memory growing {mb_per_min:.1}M/min, reaching max_memory in about {eta}"
+        )
+    }
+}
+
+/// RSS samples over `memory_leak_window`, to spot memory growing toward
+/// `max_memory` before it gets there.
+#[derive(Debug)]
+pub struct LeakDetector {
+    window: Duration,
+    samples: VecDeque<(tokio::time::Instant, u64)>,
+}
+
+impl LeakDetector {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Adds a sample, returning the trend once samples span the whole
+    /// window and, at the rate memory grew over it, it reaches `max_bytes`
+    /// within [`LEAK_HORIZON`].
+    pub fn record(
+        &mut self,
+        at: tokio::time::Instant,
+        rss: u64,
+        max_bytes: u64,
+    ) -> Option<LeakTrend> {
+        self.samples.push_back((at, rss));
+        // Keep one sample from before the window so it stays covered
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|(time, _)| at.duration_since(*time) >= self.window)
+        {
+            self.samples.pop_front();
+        }
+        let (first, _) = *self.samples.front()?;
+        if self.samples.len() < 3 || at.duration_since(first) < self.window {
+            return None;
+        }
+
+        // Least-squares slope of RSS over time
+        let points: Vec<(f64, f64)> = self
+            .samples
+            .iter()
+            .map(|(time, rss)| (time.duration_since(first).as_secs_f64(), *rss as f64))
+            .collect();
+        let n = points.len() as f64;
+        let mean_t = points.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let mean_rss = points.iter().map(|(_, rss)| rss).sum::<f64>() / n;
+        let covariance: f64 = points
+            .iter()
+            .map(|(t, rss)| (t - mean_t) * (rss - mean_rss))
+            .sum();
+        let variance: f64 = points.iter().map(|(t, _)| (t - mean_t).powi(2)).sum();
+        if variance == 0.0 {
+            return None;
+        }
+        let bytes_per_sec = covariance / variance;
+        if bytes_per_sec <= 0.0 {
+            return None;
+        }
+        let secs_to_limit = (max_bytes.saturating_sub(rss) as f64 / bytes_per_sec) as u64;
+        (secs_to_limit <= LEAK_HORIZON.as_secs()).then_some(LeakTrend {
+            bytes_per_sec,
+            secs_to_limit,
+        })
+    }
+}
+
+/// Checks the process's memory against `max_memory` every
+/// `memory_check_interval` and takes its `memory_action` when it is over.
 pub fn spawn_memory_monitor(
     name: String,
-    max_memory_str: String,
+    config: ProcessConfig,
     processes: Arc<RwLock<ProcessTable>>,
     paths: Paths,
     mut shutdown_rx: watch::Receiver<bool>,
 ) {
     tokio::spawn(async move {
-        let max_bytes = match parse_memory_string(&max_memory_str) {
+        let Some(max_memory) = config.max_memory.as_deref() else {
+            return;
+        };
+        let max_bytes = match parse_memory_string(max_memory) {
             Ok(b) => b,
             Err(e) => {
-                eprintln!("invalid max_memory for '{name}': {e}");
+                tracing::warn!("invalid max_memory for '{name}': {e}");
                 return;
             }
         };
+        let interval = config
+            .memory_check_interval
+            .as_deref()
+            .and_then(|interval| crate::config::parse_duration(interval).ok())
+            .unwrap_or(MEMORY_CHECK_INTERVAL);
+        let action = config
+            .memory_action
+            .as_deref()
+            .and_then(|action| MemoryAction::parse(action).ok())
+            .unwrap_or(MemoryAction::Restart);
+        let mut leaks = config
+            .memory_leak_window
+            .as_deref()
+            .and_then(|window| crate::config::parse_duration(window).ok())
+            .map(LeakDetector::new);
+        // Signals and events are sent once each time memory goes over
+        let mut over = false;
 
         loop {
             // Wait for next check interval, listening for shutdown
             tokio::select! {
-                _ = tokio::time::sleep(MEMORY_CHECK_INTERVAL) => {}
+                _ = tokio::time::sleep(interval) => {}
                 _ = shutdown_rx.changed() => {
                     if *shutdown_rx.borrow() {
                         return;
@@ -184,6 +559,11 @@ pub fn spawn_memory_monitor(
             let pid = {
                 let table = processes.read().await;
                 match table.get(&name) {
+                    Some(managed)
+                        if managed.paused || managed.status == ProcessStatus::Suspended =>
+                    {
+                        continue;
+                    }
                     Some(managed)
                         if managed.status == ProcessStatus::Online
                             || managed.status == ProcessStatus::Starting =>
@@ -201,27 +581,54 @@ pub fn spawn_memory_monitor(
                 continue;
             };
 
+            if let Some(leaks) = &mut leaks {
+                let trend = leaks.record(tokio::time::Instant::now(), rss, max_bytes);
+                let mut table = processes.write().await;
+                if let Some(managed) = table.get_mut(&name)
+                    && managed.memory_leak != trend
+                {
+                    if let (None, Some(trend)) = (managed.memory_leak, trend) {
+                        tracing::warn!("possible memory leak in '{name}': {trend}");
+                    }
+                    managed.memory_leak = trend;
+                }
+            }
+
             if rss <= max_bytes {
+                over = false;
                 continue;
             }
-
-            // Memory limit exceeded — kill and restart
-            eprintln!(
-                "memory limit exceeded for '{}': {} bytes > {} bytes, restarting",
-                name, rss, max_bytes
+            if over {
+                continue;
+            }
+            over = true;
+
+            tracing::warn!(
+                "memory limit exceeded for '{}': {} bytes > {} bytes, {}",
+                name,
+                rss,
+                max_bytes,
+                action.describe()
             );
 
-            // Acquire write lock, signal monitor_shutdown to prevent handle_child_exit from restarting
+            // Acquire write lock; stopping or restarting signals monitor_shutdown
+            // to prevent handle_child_exit from restarting on its own
             let (config, old_restarts, raw_pid) = {
                 let mut table = processes.write().await;
                 let managed = match table.get_mut(&name) {
                     Some(m) => m,
                     None => return,
                 };
+                managed.memory_breaches += 1;
 
-                // Signal the process monitor not to auto-restart
-                if let Some(ref tx) = managed.monitor_shutdown {
-                    let _ = tx.send(true);
+                if matches!(action, MemoryAction::Restart | MemoryAction::Stop) {
+                    // Signal the process monitor not to auto-restart
+                    if let Some(ref tx) = managed.monitor_shutdown {
+                        let _ = tx.send(true);
+                    }
+                }
+                if action == MemoryAction::Restart {
+                    managed.memory_restarts += 1;
                 }
 
                 let config = managed.config.clone();
@@ -230,29 +637,25 @@ pub fn spawn_memory_monitor(
                 (config, restarts, raw_pid)
             };
 
-            // Kill the process
-            if let Some(raw_pid) = raw_pid {
-                let signal_name = config
-                    .kill_signal
-                    .as_deref()
-                    .unwrap_or(process::DEFAULT_KILL_SIGNAL);
-                if let Ok(signal) = process::parse_signal(signal_name) {
-                    let _ = crate::sys::send_signal(raw_pid, signal);
-
-                    // Poll for process exit
-                    let timeout_ms = config
-                        .kill_timeout
-                        .unwrap_or(process::DEFAULT_KILL_TIMEOUT_MS);
-                    let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout_ms);
-                    while crate::sys::is_pid_alive(raw_pid) {
-                        if tokio::time::Instant::now() >= deadline {
-                            let _ = crate::sys::force_kill(raw_pid);
-                            tokio::time::sleep(Duration::from_millis(100)).await;
-                            break;
-                        }
-                        tokio::time::sleep(Duration::from_millis(50)).await;
+            match &action {
+                MemoryAction::Signal(signal) => {
+                    if let Ok(signal) = process::parse_signal(signal) {
+                        let _ = crate::sys::send_signal(pid, signal);
                     }
+                    continue;
                 }
+                MemoryAction::EventOnly => continue,
+                MemoryAction::Restart | MemoryAction::Stop => {}
+            }
+
+            // Kill the process
+            if let Some(raw_pid) = raw_pid {
+                process::terminate(raw_pid, &config).await;
+            }
+
+            // handle_child_exit marks the process Stopped
+            if action == MemoryAction::Stop {
+                return;
             }
 
             // Wait for handle_child_exit to mark process Stopped
@@ -270,7 +673,7 @@ pub fn spawn_memory_monitor(
             {
                 Ok(()) => return, // This monitor instance terminates; the new one takes over
                 Err(e) => {
-                    eprintln!("failed to restart '{name}' after memory limit: {e}");
+                    tracing::error!("failed to restart '{name}' after memory limit: {e}");
                     let mut table = processes.write().await;
                     if let Some(managed) = table.get_mut(&name) {
                         managed.status = ProcessStatus::Errored;
@@ -286,6 +689,60 @@ pub fn spawn_memory_monitor(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_leak_detector() {
+        const MB: u64 = 1024 * 1024;
+        let start = tokio::time::Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // Growing 1M a minute toward a limit 60M away
+        let mut leaks = LeakDetector::new(Duration::from_secs(600));
+        for minute in 0..10 {
+            assert_eq!(
+                leaks.record(at(minute * 60), 100 * MB + minute * MB, 200 * MB),
+                None
+            );
+        }
+        let trend = leaks.record(at(600), 110 * MB, 170 * MB).unwrap();
+        assert!((trend.bytes_per_sec - MB as f64 / 60.0).abs() < 1.0);
+        assert_eq!(trend.secs_to_limit, 3600);
+        assert_eq!(
+            trend.to_string(),
+            "memory growing 1.0M/min, reaching max_memory in about 1h 0m"
+        );
+        // Older samples fall out of the window
+        assert!(leaks.samples.len() <= 11);
+
+        // Flat memory, or growth too slow to matter, isn't a leak
+        let mut flat = LeakDetector::new(Duration::from_secs(600));
+        let mut slow = LeakDetector::new(Duration::from_secs(600));
+        for minute in 0..=10 {
+            assert_eq!(flat.record(at(minute * 60), 100 * MB, 200 * MB), None);
+            assert_eq!(
+                slow.record(at(minute * 60), 100 * MB + minute, 200 * MB),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_proc_io() {
+        let io = "rchar: 12345\nwchar: 678\nsyscr: 10\nsyscw: 5\n\
+                  read_bytes: 4096\nwrite_bytes: 8192\ncancelled_write_bytes: 0\n";
+        assert_eq!(parse_proc_io(io), (Some(4096), Some(8192)));
+        assert_eq!(parse_proc_io(""), (None, None));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_read_resource_usage_of_self() {
+        let _socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let usage = read_resource_usage(std::process::id()).unwrap();
+        assert!(usage.threads >= 1);
+        assert!(usage.sockets >= 1);
+        assert!(usage.fds > usage.sockets);
+    }
+
     #[test]
     fn test_parse_megabytes() {
         assert_eq!(parse_memory_string("200M").unwrap(), 200 * 1024 * 1024);
@@ -362,6 +819,38 @@ mod tests {
         assert!(rss.unwrap() > 0);
     }
 
+    #[test]
+    fn test_memory_tree_counts_descendants() {
+        let mut tree = MemoryTree::default();
+        tree.insert(1, 0, 10);
+        tree.insert(100, 1, 1);
+        tree.insert(101, 100, 200);
+        tree.insert(102, 101, 30);
+        tree.insert(103, 100, 4);
+        tree.insert(200, 1, 5000);
+
+        assert_eq!(tree.total_memory(100), Some(235));
+        assert_eq!(tree.total_memory(102), Some(30));
+        assert_eq!(tree.total_memory(999), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_read_rss_counts_children() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "sleep 5"])
+            .spawn()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let own = read_rss_bytes(std::process::id()).await.unwrap();
+        let wrapper = read_rss_bytes(child.id()).await.unwrap();
+        assert!(wrapper > 0);
+        // The test process's total includes the child it spawned
+        assert!(own > wrapper);
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
     #[cfg(unix)]
     #[tokio::test]
     async fn test_read_rss_nonexistent_pid() {
@@ -369,21 +858,41 @@ mod tests {
         assert!(rss.is_none());
     }
 
-    #[cfg(unix)]
-    #[tokio::test]
-    async fn test_read_process_stats_current_process() {
-        let pid = std::process::id();
-        let stats = read_process_stats(pid).await;
-        assert!(stats.is_some());
-        let (cpu, mem) = stats.unwrap();
-        assert!(cpu >= 0.0);
-        assert!(mem > 0);
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_proc_stat() {
+        let stat = "4242 (my (odd) cmd) S 1 4242 4242 0 -1 4194560 1523 0 0 0 \
+                    250 50 0 0 20 0 3 0 123456 10485760 2560 18446744073709551615";
+        assert_eq!(
+            parse_proc_stat(stat),
+            Some(ProcStat {
+                ppid: 1,
+                cpu_ticks: 300,
+                start_ticks: 123456,
+                rss_pages: 2560,
+            })
+        );
+        assert_eq!(parse_proc_stat("4242 (cmd) S 1"), None);
     }
 
     #[cfg(unix)]
     #[tokio::test]
-    async fn test_read_process_stats_nonexistent_pid() {
-        let stats = read_process_stats(999_999_999).await;
-        assert!(stats.is_none());
+    async fn test_sampler_current_process() {
+        let pid = std::process::id();
+        let mut sampler = Sampler::default();
+        let first = sampler.sample(vec![pid, 999_999_999]).await;
+        let stats = &first[&pid];
+        assert!(stats.cpu_percent.is_some_and(|cpu| cpu >= 0.0));
+        assert!(stats.memory_bytes.is_some_and(|mem| mem > 0));
+        assert!(!first.contains_key(&999_999_999));
+
+        // Burn some CPU so the second pass measures use over the interval
+        let started = std::time::Instant::now();
+        let mut spins = 0u64;
+        while started.elapsed() < Duration::from_millis(200) {
+            spins = std::hint::black_box(spins + 1);
+        }
+        let second = sampler.sample(vec![pid]).await;
+        assert!(second[&pid].cpu_percent.is_some_and(|cpu| cpu > 0.0));
     }
 }
@@ -0,0 +1,156 @@
+use crate::process::ProcessTable;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, watch};
+
+pub const METRICS_SCRAPE_INTERVAL: Duration = Duration::from_secs(5);
+pub const METRICS_SCRAPE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Extracts the requested metrics from a Prometheus text exposition body.
+/// Samples of the same metric with different labels are summed.
+pub fn parse_prometheus(body: &str, wanted: &[String]) -> BTreeMap<String, f64> {
+    let mut values = BTreeMap::new();
+
+    for line in body.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let name_end = line
+            .find(|c: char| c == '{' || c.is_whitespace())
+            .unwrap_or(line.len());
+        let name = &line[..name_end];
+        if !wanted.iter().any(|w| w == name) {
+            continue;
+        }
+
+        let rest = &line[name_end..];
+        let rest = match rest.strip_prefix('{') {
+            Some(labels) => match labels.find('}') {
+                Some(end) => &labels[end + 1..],
+                None => continue,
+            },
+            None => rest,
+        };
+
+        let Some(value) = rest.split_whitespace().next() else {
+            continue;
+        };
+        let Ok(value) = value.parse::<f64>() else {
+            continue;
+        };
+
+        *values.entry(name.to_string()).or_insert(0.0) += value;
+    }
+
+    values
+}
+
+pub fn format_metric(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+pub fn spawn_metrics_scraper(
+    name: String,
+    endpoint: String,
+    wanted: Vec<String>,
+    processes: Arc<RwLock<ProcessTable>>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder()
+            .timeout(METRICS_SCRAPE_TIMEOUT)
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!("failed to build HTTP client for '{name}' metrics: {e}");
+                return;
+            }
+        };
+
+        loop {
+            if *shutdown_rx.borrow() {
+                return;
+            }
+
+            let scraped = match client.get(&endpoint).send().await {
+                Ok(resp) if resp.status().is_success() => resp
+                    .text()
+                    .await
+                    .ok()
+                    .map(|body| parse_prometheus(&body, &wanted)),
+                _ => None,
+            };
+
+            {
+                let mut table = processes.write().await;
+                match table.get_mut(&name) {
+                    Some(managed) => managed.custom_metrics = scraped.unwrap_or_default(),
+                    None => return,
+                }
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(METRICS_SCRAPE_INTERVAL) => {}
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wanted(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_simple_metric() {
+        let body = "# HELP queue_depth Jobs waiting\n# TYPE queue_depth gauge\nqueue_depth 12\n";
+        let values = parse_prometheus(body, &wanted(&["queue_depth"]));
+        assert_eq!(values.get("queue_depth"), Some(&12.0));
+    }
+
+    #[test]
+    fn test_parse_sums_labelled_samples() {
+        let body = "queue_depth{queue=\"a\"} 3\nqueue_depth{queue=\"b\"} 4.5\n";
+        let values = parse_prometheus(body, &wanted(&["queue_depth"]));
+        assert_eq!(values.get("queue_depth"), Some(&7.5));
+    }
+
+    #[test]
+    fn test_parse_ignores_unwanted_and_prefix_matches() {
+        let body = "queue_depth_total 99\nother 1\njobs_done 5 1700000000000\n";
+        let values = parse_prometheus(body, &wanted(&["queue_depth", "jobs_done"]));
+        assert!(!values.contains_key("queue_depth"));
+        assert_eq!(values.get("jobs_done"), Some(&5.0));
+        assert_eq!(values.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_skips_malformed_lines() {
+        let body = "queue_depth{queue=\"a\" 3\nqueue_depth NaNx\nqueue_depth\n";
+        let values = parse_prometheus(body, &wanted(&["queue_depth"]));
+        assert!(values.is_empty());
+    }
+
+    #[test]
+    fn test_format_metric() {
+        assert_eq!(format_metric(12.0), "12");
+        assert_eq!(format_metric(0.5), "0.50");
+        assert_eq!(format_metric(-3.0), "-3");
+    }
+}
@@ -0,0 +1,102 @@
+//! Namespaces let several projects share one daemon without their process
+//! names colliding. A process `web` started in namespace `shop` is tracked as
+//! `shop/web`; the default namespace keeps bare names. Process names can't
+//! contain `/`, so a qualified name always splits unambiguously.
+
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+pub fn validate(namespace: &str) -> Result<(), String> {
+    if namespace.is_empty()
+        || !namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        || namespace.contains("..")
+    {
+        return Err(format!(
+            "invalid namespace '{namespace}': namespaces may only contain letters, digits, '-', '_' and '.'"
+        ));
+    }
+    Ok(())
+}
+
+/// The namespace a `pm3.toml` in `dir` acts in without a `namespace` key:
+/// the directory's name, with characters namespaces can't hold replaced by
+/// `-`.
+pub fn from_dir(dir: &std::path::Path) -> Option<String> {
+    let name: String = dir
+        .file_name()?
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let name = name.replace("..", "-");
+    validate(&name).is_ok().then_some(name)
+}
+
+/// The process table key for `name` in `namespace`.
+pub fn qualify(namespace: &str, name: &str) -> String {
+    if namespace == DEFAULT_NAMESPACE {
+        name.to_string()
+    } else {
+        format!("{namespace}/{name}")
+    }
+}
+
+/// Splits a process table key into its namespace and local name.
+pub fn split(key: &str) -> (&str, &str) {
+    key.split_once('/').unwrap_or((DEFAULT_NAMESPACE, key))
+}
+
+/// The local name of `key` if it belongs to `namespace`.
+pub fn local_name<'a>(namespace: &str, key: &'a str) -> Option<&'a str> {
+    let (ns, name) = split(key);
+    (ns == namespace).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qualify_and_split() {
+        assert_eq!(qualify("shop", "web"), "shop/web");
+        assert_eq!(qualify(DEFAULT_NAMESPACE, "web"), "web");
+        assert_eq!(split("shop/web:1"), ("shop", "web:1"));
+        assert_eq!(split("web"), (DEFAULT_NAMESPACE, "web"));
+    }
+
+    #[test]
+    fn test_local_name() {
+        assert_eq!(local_name("shop", "shop/web"), Some("web"));
+        assert_eq!(local_name("shop", "web"), None);
+        assert_eq!(local_name(DEFAULT_NAMESPACE, "web"), Some("web"));
+        assert_eq!(local_name(DEFAULT_NAMESPACE, "shop/web"), None);
+    }
+
+    #[test]
+    fn test_from_dir() {
+        use std::path::Path;
+        assert_eq!(from_dir(Path::new("/srv/shop")).as_deref(), Some("shop"));
+        assert_eq!(
+            from_dir(Path::new("/home/me/my app")).as_deref(),
+            Some("my-app")
+        );
+        assert_eq!(from_dir(Path::new("/srv/..x")).as_deref(), Some("-x"));
+        assert_eq!(from_dir(Path::new("/")), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("shop").is_ok());
+        assert!(validate("my-app_2.0").is_ok());
+        assert!(validate("").is_err());
+        assert!(validate("a/b").is_err());
+        assert!(validate("..").is_err());
+    }
+}
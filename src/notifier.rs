@@ -0,0 +1,346 @@
+use crate::events::{EventKind, ProcessEvent};
+use crate::protocol::ProcessStatus;
+use crate::settings::{NotifierKind, NotifierSettings, NotifyEvent, SmtpTls};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+use tokio::time::Instant;
+
+pub const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const DEFAULT_TEMPLATE: &str = "[{host}] {name} {event}: {detail}";
+
+pub const DEFAULT_SUBJECT: &str = "[pm3] {name} {event}";
+
+/// The notifier event an event stands for, if any.
+pub fn notify_event(event: &ProcessEvent) -> Option<NotifyEvent> {
+    match event.kind {
+        EventKind::StatusChanged if event.status == ProcessStatus::Errored => {
+            Some(NotifyEvent::Errored)
+        }
+        EventKind::RestartLimit => Some(NotifyEvent::RestartLimit),
+        EventKind::MemoryLimit => Some(NotifyEvent::MemoryLimit),
+        EventKind::MemoryLeak => Some(NotifyEvent::MemoryLeak),
+        _ => None,
+    }
+}
+
+/// Whether `name` is one of `processes`, counting cluster instances like
+/// `web:0` as `web`. An empty list matches every process.
+fn wants_process(processes: &[String], name: &str) -> bool {
+    let base = name.split_once(':').map_or(name, |(base, _)| base);
+    processes.is_empty() || processes.iter().any(|p| p == name || p == base)
+}
+
+/// Replaces each `{key}` in `template` with its value; unknown placeholders
+/// are left as they are.
+pub fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = template.to_string();
+    for (key, value) in vars {
+        out = out.replace(&format!("{{{key}}}"), value);
+    }
+    out
+}
+
+/// Lets through at most one message per process and event every
+/// `interval`, counting the ones held back.
+struct RateLimiter {
+    interval: Duration,
+    sent: HashMap<(String, NotifyEvent), (Instant, u32)>,
+}
+
+impl RateLimiter {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            sent: HashMap::new(),
+        }
+    }
+
+    /// `Some(suppressed)` if a message may be sent now, with the number held
+    /// back since the last one.
+    fn check(&mut self, name: &str, event: NotifyEvent, now: Instant) -> Option<u32> {
+        let key = (name.to_string(), event);
+        match self.sent.get_mut(&key) {
+            Some((last, suppressed)) if now.saturating_duration_since(*last) < self.interval => {
+                *suppressed += 1;
+                None
+            }
+            Some((last, suppressed)) => {
+                *last = now;
+                Some(std::mem::take(suppressed))
+            }
+            None => {
+                self.sent.insert(key, (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+enum Channel {
+    Webhook {
+        client: reqwest::Client,
+        url: String,
+        kind: NotifierKind,
+    },
+    Email {
+        transport: Box<AsyncSmtpTransport<Tokio1Executor>>,
+        from: Mailbox,
+        to: Vec<Mailbox>,
+    },
+}
+
+impl Channel {
+    fn build(settings: &NotifierSettings) -> Result<Self, String> {
+        match settings.kind {
+            NotifierKind::Slack | NotifierKind::Discord => {
+                let client = reqwest::Client::builder()
+                    .timeout(NOTIFY_TIMEOUT)
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                Ok(Channel::Webhook {
+                    client,
+                    url: settings.webhook_url.clone().unwrap_or_default(),
+                    kind: settings.kind,
+                })
+            }
+            NotifierKind::Email => {
+                let host = settings.smtp_host.as_deref().unwrap_or_default();
+                let mut builder = match settings.smtp_tls {
+                    SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+                        .map_err(|e| e.to_string())?,
+                    SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+                        .map_err(|e| e.to_string())?,
+                    SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+                };
+                if let Some(port) = settings.smtp_port {
+                    builder = builder.port(port);
+                }
+                if let Some(username) = &settings.smtp_username {
+                    let password = settings
+                        .load_smtp_password()
+                        .map_err(|e| e.to_string())?
+                        .unwrap_or_default();
+                    builder = builder.credentials(Credentials::new(username.clone(), password));
+                }
+                let parse = |address: &str| {
+                    address
+                        .parse::<Mailbox>()
+                        .map_err(|e| format!("invalid address {address:?}: {e}"))
+                };
+                Ok(Channel::Email {
+                    transport: Box::new(builder.timeout(Some(NOTIFY_TIMEOUT)).build()),
+                    from: parse(settings.from.as_deref().unwrap_or_default())?,
+                    to: settings
+                        .to
+                        .iter()
+                        .map(|address| parse(address))
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+        }
+    }
+
+    async fn send(&self, subject: &str, text: &str) -> Result<(), String> {
+        match self {
+            Channel::Webhook { client, url, kind } => {
+                let body = match kind {
+                    NotifierKind::Discord => serde_json::json!({ "content": text }),
+                    _ => serde_json::json!({ "text": text }),
+                };
+                let response = client
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body.to_string())
+                    .send()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                if !response.status().is_success() {
+                    return Err(format!("HTTP {}", response.status()));
+                }
+                Ok(())
+            }
+            Channel::Email {
+                transport,
+                from,
+                to,
+            } => {
+                let mut message = lettre::Message::builder()
+                    .from(from.clone())
+                    .subject(subject)
+                    .header(ContentType::TEXT_PLAIN);
+                for recipient in to {
+                    message = message.to(recipient.clone());
+                }
+                let message = message.body(text.to_string()).map_err(|e| e.to_string())?;
+                transport.send(message).await.map_err(|e| e.to_string())?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Starts one task per `[[notify]]` table, each sending a message for the
+/// events it opted into until the daemon shuts down. A notifier that can't
+/// be set up, e.g. because of a bad address, is logged and skipped.
+pub fn spawn_notifiers(
+    notifiers: &[NotifierSettings],
+    events: &broadcast::Sender<ProcessEvent>,
+    shutdown_rx: &watch::Receiver<bool>,
+) {
+    let host = hostname::get()
+        .map(|h| h.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    for settings in notifiers {
+        let channel = match Channel::build(settings) {
+            Ok(channel) => channel,
+            Err(e) => {
+                tracing::error!("{} notifier: {e}", settings.kind);
+                continue;
+            }
+        };
+        let limiter = RateLimiter::new(settings.rate_limit().unwrap_or(Duration::from_secs(300)));
+        tokio::spawn(run_notifier(
+            settings.clone(),
+            channel,
+            limiter,
+            host.clone(),
+            events.subscribe(),
+            shutdown_rx.clone(),
+        ));
+    }
+}
+
+async fn run_notifier(
+    settings: NotifierSettings,
+    channel: Channel,
+    mut limiter: RateLimiter,
+    host: String,
+    mut events: broadcast::Receiver<ProcessEvent>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let template = settings.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+    let subject = settings.subject.as_deref().unwrap_or(DEFAULT_SUBJECT);
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    tracing::warn!("{} notifier fell behind, {n} events dropped", settings.kind);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            _ = shutdown_rx.changed() => {
+                if *shutdown_rx.borrow() {
+                    return;
+                }
+                continue;
+            }
+        };
+
+        let Some(kind) = notify_event(&event) else {
+            continue;
+        };
+        if !settings.on.contains(&kind) || !wants_process(&settings.processes, &event.name) {
+            continue;
+        }
+        let Some(suppressed) = limiter.check(&event.name, kind, Instant::now()) else {
+            continue;
+        };
+
+        let kind = kind.to_string();
+        let status = event.status.to_string();
+        let suppressed = suppressed.to_string();
+        let vars = [
+            ("name", event.name.as_str()),
+            ("event", kind.as_str()),
+            ("status", status.as_str()),
+            ("detail", event.detail.as_deref().unwrap_or(status.as_str())),
+            ("timestamp", event.timestamp.as_str()),
+            ("host", host.as_str()),
+            ("suppressed", suppressed.as_str()),
+        ];
+        let text = render(template, &vars);
+        if let Err(e) = channel.send(&render(subject, &vars), &text).await {
+            tracing::warn!(
+                "{} notification about '{}' failed: {e}",
+                settings.kind,
+                event.name
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: EventKind, status: ProcessStatus) -> ProcessEvent {
+        ProcessEvent {
+            name: "web".to_string(),
+            kind,
+            status,
+            timestamp: "t".to_string(),
+            detail: None,
+        }
+    }
+
+    #[test]
+    fn test_notify_event() {
+        let errored = event(EventKind::StatusChanged, ProcessStatus::Errored);
+        assert_eq!(notify_event(&errored), Some(NotifyEvent::Errored));
+        let online = event(EventKind::StatusChanged, ProcessStatus::Online);
+        assert_eq!(notify_event(&online), None);
+        let limit = event(EventKind::RestartLimit, ProcessStatus::Errored);
+        assert_eq!(notify_event(&limit), Some(NotifyEvent::RestartLimit));
+        let memory = event(EventKind::MemoryLimit, ProcessStatus::Stopped);
+        assert_eq!(notify_event(&memory), Some(NotifyEvent::MemoryLimit));
+        let leak = event(EventKind::MemoryLeak, ProcessStatus::Online);
+        assert_eq!(notify_event(&leak), Some(NotifyEvent::MemoryLeak));
+    }
+
+    #[test]
+    fn test_wants_process() {
+        assert!(wants_process(&[], "anything"));
+        let processes = vec!["web".to_string()];
+        assert!(wants_process(&processes, "web"));
+        assert!(wants_process(&processes, "web:2"));
+        assert!(!wants_process(&processes, "worker"));
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(
+                "{name} is {status} {unknown}",
+                &[("name", "web"), ("status", "errored")]
+            ),
+            "web is errored {unknown}"
+        );
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let mut limiter = RateLimiter::new(Duration::from_secs(60));
+        let start = Instant::now();
+        assert_eq!(limiter.check("web", NotifyEvent::Errored, start), Some(0));
+        assert_eq!(limiter.check("web", NotifyEvent::Errored, start), None);
+        assert_eq!(limiter.check("web", NotifyEvent::Errored, start), None);
+        // Other processes and events have their own budget
+        assert_eq!(limiter.check("api", NotifyEvent::Errored, start), Some(0));
+        assert_eq!(
+            limiter.check("web", NotifyEvent::MemoryLimit, start),
+            Some(0)
+        );
+
+        let later = start + Duration::from_secs(61);
+        assert_eq!(limiter.check("web", NotifyEvent::Errored, later), Some(2));
+        assert_eq!(limiter.check("web", NotifyEvent::Errored, later), None);
+    }
+}
@@ -41,6 +41,43 @@ impl Paths {
         self.data_dir.join("dump.json")
     }
 
+    /// The state an upgrading daemon hands to its replacement.
+    pub fn handover_file(&self) -> PathBuf {
+        self.data_dir.join("handover.json")
+    }
+
+    /// Timestamped copies of the saved processes, for `pm3 resurrect --from`.
+    pub fn snapshots_dir(&self) -> PathBuf {
+        self.data_dir.join("snapshots")
+    }
+
+    /// Daemon-wide settings (`[daemon]` and friends).
+    pub fn settings_file(&self) -> PathBuf {
+        self.data_dir.join("settings.toml")
+    }
+
+    /// The daemon's own diagnostic log.
+    pub fn daemon_log(&self) -> PathBuf {
+        self.data_dir.join("daemon.log")
+    }
+
+    /// Append-only record of state-changing client requests.
+    pub fn audit_log(&self) -> PathBuf {
+        self.data_dir.join("audit.log")
+    }
+
+    /// Append-only record of when each process went up, down or stopped,
+    /// for `pm3 report`.
+    pub fn availability_log(&self) -> PathBuf {
+        self.data_dir.join("availability.jsonl")
+    }
+
+    /// Present while the data dir is over `[daemon] disk_quota`. Log
+    /// copiers, in the daemon and in log relays, drop output while it is.
+    pub fn over_quota_file(&self) -> PathBuf {
+        self.data_dir.join("over-quota")
+    }
+
     pub fn port_file(&self) -> PathBuf {
         self.data_dir.join("pm3.port")
     }
@@ -49,6 +86,31 @@ impl Paths {
         self.data_dir.join("logs")
     }
 
+    /// Directory holding a process's logs. Namespaced processes (`shop/web`)
+    /// get a subdirectory per namespace.
+    pub fn process_log_dir(&self, name: &str) -> PathBuf {
+        match name.split_once('/') {
+            Some((namespace, _)) => self.log_dir().join(namespace),
+            None => self.log_dir(),
+        }
+    }
+
+    /// Directory holding the recorded resource samples of each process.
+    pub fn stats_dir(&self) -> PathBuf {
+        self.data_dir.join("stats")
+    }
+
+    /// A process's samples, one JSON object per line. Namespaced processes
+    /// get a subdirectory per namespace, like their logs.
+    pub fn stats_file(&self, name: &str) -> PathBuf {
+        self.stats_dir().join(format!("{name}.jsonl"))
+    }
+
+    /// File a process with `ready_signal = "ipc"` writes to once ready.
+    pub fn ready_file(&self, name: &str) -> PathBuf {
+        self.data_dir.join("ready").join(name)
+    }
+
     pub fn stdout_log(&self, name: &str) -> PathBuf {
         self.data_dir.join("logs").join(format!("{name}-out.log"))
     }
@@ -57,6 +119,12 @@ impl Paths {
         self.data_dir.join("logs").join(format!("{name}-err.log"))
     }
 
+    /// The one log of a process with `log_combine`, or of the instances of a
+    /// cluster with `merge_logs`.
+    pub fn combined_log(&self, name: &str) -> PathBuf {
+        self.data_dir.join("logs").join(format!("{name}.log"))
+    }
+
     pub fn rotated_stdout_log(&self, name: &str, n: u32) -> PathBuf {
         self.data_dir
             .join("logs")
@@ -135,6 +203,25 @@ mod tests {
         assert!(log.ends_with("logs/web-out.log"));
     }
 
+    #[test]
+    fn test_namespaced_logs_use_subdirectory() {
+        let paths = Paths::with_base(PathBuf::from("/tmp/pm3-test"));
+        assert!(
+            paths
+                .stdout_log("shop/web")
+                .ends_with("logs/shop/web-out.log")
+        );
+        assert!(paths.process_log_dir("shop/web").ends_with("logs/shop"));
+        assert_eq!(paths.process_log_dir("web"), paths.log_dir());
+    }
+
+    #[test]
+    fn test_ready_file_under_data_dir() {
+        let paths = Paths::with_base(PathBuf::from("/tmp/pm3-test"));
+        assert!(paths.ready_file("web").ends_with("ready/web"));
+        assert!(paths.ready_file("shop/web").ends_with("ready/shop/web"));
+    }
+
     #[test]
     fn test_stderr_log_includes_name() {
         let paths = Paths::with_base(PathBuf::from("/tmp/pm3-test"));
@@ -142,6 +229,12 @@ mod tests {
         assert!(log.ends_with("logs/web-err.log"));
     }
 
+    #[test]
+    fn test_combined_log_includes_name() {
+        let paths = Paths::with_base(PathBuf::from("/tmp/pm3-test"));
+        assert!(paths.combined_log("web").ends_with("logs/web.log"));
+    }
+
     #[test]
     fn test_rotated_stdout_log_format() {
         let paths = Paths::with_base(PathBuf::from("/tmp/pm3-test"));
@@ -1,9 +1,9 @@
-use crate::config::{ProcessConfig, RestartPolicy};
-use crate::log::{self, LogEntry, LogStream};
+use crate::config::{ProcessConfig, ReadySignal, RestartBackoff, RestartPolicy};
+use crate::log::{self, LogControl, LogEntry, LogFiles, LogLimits, LogStream};
 use crate::paths::Paths;
 use crate::protocol::{ProcessDetail, ProcessInfo, ProcessStatus};
-use crate::{cron, health, memory, watch as file_watch};
-use std::collections::HashMap;
+use crate::{cron, health, memory, metrics, watch as file_watch};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs;
@@ -12,11 +12,18 @@ use tokio::sync::{RwLock, broadcast, watch};
 
 pub const DEFAULT_KILL_TIMEOUT_MS: u64 = 5000;
 pub const DEFAULT_KILL_SIGNAL: &str = "SIGTERM";
+pub const DEFAULT_DRAIN_TIMEOUT_MS: u64 = 5000;
 pub const DEFAULT_MAX_RESTARTS: u32 = 15;
 pub const BACKOFF_BASE_MS: u64 = 100;
 pub const BACKOFF_CAP_MS: u64 = 30_000;
 pub const DEFAULT_MIN_UPTIME_MS: u64 = 1000;
+pub const DEFAULT_FLAP_WINDOW_MS: u64 = 60_000;
+pub const DEFAULT_FLAP_COOLDOWN_MS: u64 = 300_000;
 pub const SPAWN_VERIFY_DELAY_MS: u64 = 50;
+/// How many lines of a process's output followers can fall behind by.
+pub const LOG_BROADCAST_CAPACITY: usize = 1024;
+/// Env var naming the file a `ready_signal = "ipc"` process writes to when ready.
+pub const READY_PATH_ENV: &str = "PM3_READY_PATH";
 
 #[derive(Debug, thiserror::Error)]
 pub enum ProcessError {
@@ -32,6 +39,8 @@ pub enum ProcessError {
     EnvFile(String),
     #[error("hook failed: {0}")]
     HookFailed(String),
+    #[error("env_cmd failed: {0}")]
+    EnvCmd(String),
     #[error("process exited immediately (exit code: {exit_code:?})")]
     ImmediateExit { exit_code: Option<i32> },
 }
@@ -56,24 +65,23 @@ pub fn parse_signal(name: &str) -> Result<crate::sys::Signal, ProcessError> {
 pub async fn run_hook(
     hook: &str,
     name: &str,
-    cwd: Option<&str>,
+    config: &ProcessConfig,
     paths: &Paths,
 ) -> Result<(), ProcessError> {
-    fs::create_dir_all(paths.log_dir()).await?;
-
-    let stdout_file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(paths.stdout_log(name))
-        .map_err(ProcessError::SpawnFailed)?;
-    let stderr_file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(paths.stderr_log(name))
-        .map_err(ProcessError::SpawnFailed)?;
+    let logs = LogFiles::new(paths, name, config);
+    logs.create_dirs().await?;
+    let open = |path: &std::path::Path| {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(ProcessError::SpawnFailed)
+    };
+    let stdout_file = open(&logs.stdout)?;
+    let stderr_file = open(&logs.stderr)?;
 
     let mut cmd = crate::sys::hook_command(hook);
-    if let Some(dir) = cwd {
+    if let Some(dir) = &config.cwd {
         cmd.current_dir(dir);
     }
     cmd.stdin(std::process::Stdio::null());
@@ -84,7 +92,7 @@ pub async fn run_hook(
 
     if !status.success() {
         return Err(ProcessError::HookFailed(format!(
-            "pre_start '{}' exited with code {}",
+            "'{}' exited with code {}",
             hook,
             status.code().unwrap_or(-1)
         )));
@@ -93,6 +101,54 @@ pub async fn run_hook(
     Ok(())
 }
 
+/// How long an `env_cmd` command may run before the spawn fails.
+const ENV_CMD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs each `env_cmd` command, with the process's cwd and `env`, and takes
+/// its stdout, minus the trailing newline, as the variable's value. The
+/// values are only ever handed to the child, never logged or stored.
+async fn run_env_cmds(
+    env_cmd: &HashMap<String, String>,
+    config: &ProcessConfig,
+    env_vars: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, ProcessError> {
+    let mut resolved = HashMap::new();
+    for (key, command) in env_cmd {
+        let mut cmd = crate::sys::hook_command(command);
+        if let Some(dir) = &config.cwd {
+            cmd.current_dir(dir);
+        }
+        cmd.envs(env_vars);
+        cmd.stdin(std::process::Stdio::null());
+        cmd.kill_on_drop(true);
+
+        let failed = |reason: String| ProcessError::EnvCmd(format!("{key}: '{command}' {reason}"));
+        let output = match tokio::time::timeout(ENV_CMD_TIMEOUT, cmd.output()).await {
+            Ok(output) => output.map_err(ProcessError::SpawnFailed)?,
+            Err(_) => {
+                return Err(failed(format!(
+                    "timed out after {}s",
+                    ENV_CMD_TIMEOUT.as_secs()
+                )));
+            }
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let mut reason = format!("exited with code {}", output.status.code().unwrap_or(-1));
+            if let Some(line) = stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+                reason.push_str(&format!(": {}", line.trim()));
+            }
+            return Err(failed(reason));
+        }
+        let value = String::from_utf8(output.stdout)
+            .map_err(|_| failed("printed a value that isn't UTF-8".to_string()))?;
+        let value = value.strip_suffix('\n').unwrap_or(&value);
+        let value = value.strip_suffix('\r').unwrap_or(value);
+        resolved.insert(key.clone(), value.to_string());
+    }
+    Ok(resolved)
+}
+
 pub struct ManagedProcess {
     pub name: String,
     pub config: ProcessConfig,
@@ -100,22 +156,115 @@ pub struct ManagedProcess {
     pub status: ProcessStatus,
     pub started_at: tokio::time::Instant,
     pub restarts: u32,
+    pub restart_history: Vec<tokio::time::Instant>,
+    pub exit_code: Option<i32>,
+    /// Times the process was restarted for exceeding `max_memory`.
+    pub memory_restarts: u32,
+    /// Times the process's memory went over `max_memory`, whatever its
+    /// `memory_action`.
+    pub memory_breaches: u32,
+    /// Set while `memory_leak_window` finds memory growing toward
+    /// `max_memory`.
+    pub memory_leak: Option<memory::LeakTrend>,
+    /// The latest `health_check` probe.
+    pub health: Option<health::HealthRecord>,
+    /// Times the process exited on its own with a failure, whether or not it
+    /// was restarted afterwards.
+    pub crashes: u32,
+    pub custom_metrics: BTreeMap<String, f64>,
+    /// Monitoring is paused: no auto-restart, watch, cron, memory or health
+    /// actions are taken until resumed.
+    pub paused: bool,
+    /// Set while frozen with SIGSTOP: the status to restore on continue and
+    /// when the process was suspended.
+    pub suspended: Option<(ProcessStatus, tokio::time::Instant)>,
+    /// Set when `on_unhealthy = "restart"` terminated the process, so its
+    /// exit is restarted whatever the restart policy, within `max_restarts`.
+    pub unhealthy_restart: bool,
     pub log_broadcaster: broadcast::Sender<LogEntry>,
     pub monitor_shutdown: Option<watch::Sender<bool>>,
+    pub output_fds: OutputFds,
+    /// The `pm3 log-relay` copying the process's output to its logs, when
+    /// the daemon doesn't copy it itself.
+    pub log_relay: Option<u32>,
+    /// Shared with the copiers of the process's output.
+    pub log_control: LogControl,
+}
+
+/// Copies of the read ends of a process's stdout PTY and stderr pipe, kept
+/// so `pm3 daemon upgrade` can pass them to the new daemon, which carries on
+/// copying the process's output to its logs. When a log relay copies the
+/// output instead, only the relay's feed of the lines it copies is kept.
+#[derive(Debug, Default)]
+pub struct OutputFds {
+    #[cfg(unix)]
+    pub stdout: Option<std::os::fd::OwnedFd>,
+    #[cfg(unix)]
+    pub stderr: Option<std::os::fd::OwnedFd>,
+    #[cfg(unix)]
+    pub relay_feed: Option<std::os::fd::OwnedFd>,
 }
 
 impl ManagedProcess {
+    /// An entry for a process this daemon didn't spawn itself, adopted by
+    /// pid, or with none when it isn't running.
+    pub fn adopted(
+        name: String,
+        config: ProcessConfig,
+        pid: Option<u32>,
+        status: ProcessStatus,
+        restarts: u32,
+    ) -> Self {
+        Self {
+            name,
+            config,
+            pid,
+            status,
+            started_at: tokio::time::Instant::now(),
+            restarts,
+            restart_history: Vec::new(),
+            exit_code: None,
+            memory_restarts: 0,
+            memory_breaches: 0,
+            memory_leak: None,
+            health: None,
+            crashes: 0,
+            custom_metrics: BTreeMap::new(),
+            paused: false,
+            suspended: None,
+            unhealthy_restart: false,
+            log_broadcaster: broadcast::channel(1024).0,
+            monitor_shutdown: Some(watch::channel(false).0),
+            output_fds: OutputFds::default(),
+            log_relay: None,
+            log_control: LogControl::default(),
+        }
+    }
+
+    /// Time the process has been running, not counting time spent suspended.
+    pub fn uptime(&self) -> Duration {
+        match self.suspended {
+            Some((_, since)) => since.saturating_duration_since(self.started_at),
+            None => self.started_at.elapsed(),
+        }
+    }
+
     pub fn to_process_info(&self, stats_cache: &memory::StatsCache) -> ProcessInfo {
         let stats = self.pid.and_then(|pid| stats_cache.get(&pid));
         ProcessInfo {
             name: self.name.clone(),
             pid: self.pid,
             status: self.status,
-            uptime: Some(self.started_at.elapsed().as_secs()),
+            uptime: Some(self.uptime().as_secs()),
             restarts: self.restarts,
             cpu_percent: stats.and_then(|s| s.cpu_percent),
             memory_bytes: stats.and_then(|s| s.memory_bytes),
+            resources: stats.and_then(|s| s.resources),
+            health: self.health.as_ref().map(health::HealthRecord::report),
             group: self.config.group.clone(),
+            tags: self.config.tags.clone().unwrap_or_default(),
+            metrics: self.custom_metrics.clone(),
+            paused: self.paused,
         }
     }
 
@@ -125,28 +274,61 @@ impl ManagedProcess {
         stats_cache: &memory::StatsCache,
     ) -> ProcessDetail {
         let stats = self.pid.and_then(|pid| stats_cache.get(&pid));
+        let logs = LogFiles::new(paths, &self.name, &self.config);
         ProcessDetail {
             name: self.name.clone(),
             pid: self.pid,
             status: self.status,
-            uptime: Some(self.started_at.elapsed().as_secs()),
+            uptime: Some(self.uptime().as_secs()),
             restarts: self.restarts,
             cpu_percent: stats.and_then(|s| s.cpu_percent),
             memory_bytes: stats.and_then(|s| s.memory_bytes),
+            resources: stats.and_then(|s| s.resources),
+            health: self.health.as_ref().map(health::HealthRecord::report),
             group: self.config.group.clone(),
+            tags: self.config.tags.clone().unwrap_or_default(),
             command: self.config.command.clone(),
             cwd: self.config.cwd.clone(),
             env: self.config.env.clone(),
             exit_code: None,
-            stdout_log: Some(paths.stdout_log(&self.name).to_string_lossy().into_owned()),
-            stderr_log: Some(paths.stderr_log(&self.name).to_string_lossy().into_owned()),
+            stdout_log: Some(logs.stdout.to_string_lossy().into_owned()),
+            stderr_log: Some(logs.stderr.to_string_lossy().into_owned()),
             readiness_check: self.config.readiness_check.clone(),
             readiness_timeout: self.config.readiness_timeout,
             health_check: self.config.health_check.clone(),
             depends_on: self.config.depends_on.clone(),
+            metrics: self.custom_metrics.clone(),
+            paused: self.paused,
         }
     }
 
+    /// Sends `drain_signal`, if configured, so the process stops taking new
+    /// work before it is stopped. Returns how long to let it finish.
+    pub fn drain(&self) -> Option<Duration> {
+        let signal_name = self.config.drain_signal.as_deref()?;
+        let pid = self.pid?;
+        let signal = match parse_signal(signal_name) {
+            Ok(signal) => signal,
+            Err(e) => {
+                tracing::warn!("invalid drain_signal for '{}': {e}", self.name);
+                return None;
+            }
+        };
+        // Exiting once drained is expected, not a crash to restart
+        if let Some(ref tx) = self.monitor_shutdown {
+            let _ = tx.send(true);
+        }
+        if let Err(e) = crate::sys::send_signal(pid, signal) {
+            tracing::warn!("failed to send {signal_name} to pid {pid}: {e}");
+            return None;
+        }
+        Some(Duration::from_millis(
+            self.config
+                .drain_timeout
+                .unwrap_or(DEFAULT_DRAIN_TIMEOUT_MS),
+        ))
+    }
+
     pub async fn graceful_stop(&mut self) -> Result<(), ProcessError> {
         // Signal the monitor not to auto-restart
         if let Some(ref tx) = self.monitor_shutdown {
@@ -172,13 +354,17 @@ impl ManagedProcess {
         let duration = Duration::from_millis(timeout_ms);
 
         if let Err(e) = crate::sys::send_signal(raw_pid, signal) {
-            eprintln!("failed to send {signal_name} to pid {raw_pid}: {e}");
+            tracing::warn!("failed to send {signal_name} to pid {raw_pid}: {e}");
             if !crate::sys::is_pid_alive(raw_pid) {
                 self.pid = None;
                 self.status = ProcessStatus::Stopped;
                 return Ok(());
             }
         }
+        // A frozen process can't act on the stop signal until it is thawed
+        if self.suspended.take().is_some() {
+            let _ = crate::sys::resume_process_tree(raw_pid);
+        }
 
         // Poll for process exit
         let deadline = tokio::time::Instant::now() + duration;
@@ -199,6 +385,28 @@ impl ManagedProcess {
     }
 }
 
+/// Sends `config`'s kill signal to `pid`, force-killing it if it's still
+/// alive after `kill_timeout`.
+pub async fn terminate(pid: u32, config: &ProcessConfig) {
+    let signal_name = config.kill_signal.as_deref().unwrap_or(DEFAULT_KILL_SIGNAL);
+    let Ok(signal) = parse_signal(signal_name) else {
+        return;
+    };
+    let _ = crate::sys::send_signal(pid, signal);
+
+    // Poll for process exit
+    let timeout_ms = config.kill_timeout.unwrap_or(DEFAULT_KILL_TIMEOUT_MS);
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(timeout_ms);
+    while crate::sys::is_pid_alive(pid) {
+        if tokio::time::Instant::now() >= deadline {
+            let _ = crate::sys::force_kill(pid);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
 pub fn spawn_aux_monitors(
     name: String,
     config: ProcessConfig,
@@ -206,25 +414,31 @@ pub fn spawn_aux_monitors(
     paths: Paths,
     shutdown_tx: watch::Sender<bool>,
 ) {
-    if config.readiness_check.is_some() || config.health_check.is_some() {
+    if config.has_startup_probe() {
         health::spawn_startup_checker(
             name.clone(),
-            config.readiness_check.clone(),
+            health::Readiness::for_process(&name, &config, &paths),
             config.readiness_timeout,
             config.health_check.clone(),
             Arc::clone(&processes),
             shutdown_tx.subscribe(),
         );
     }
-    if let Some(mm) = config.max_memory.clone() {
+    if config.max_memory.is_some() {
         memory::spawn_memory_monitor(
             name.clone(),
-            mm,
+            config.clone(),
             Arc::clone(&processes),
             paths.clone(),
             shutdown_tx.subscribe(),
         );
     }
+    health::spawn_liveness_checker(
+        name.clone(),
+        config.clone(),
+        Arc::clone(&processes),
+        shutdown_tx.subscribe(),
+    );
     // Watcher handles watch disabled internally
     file_watch::spawn_watcher(
         name.clone(),
@@ -233,10 +447,22 @@ pub fn spawn_aux_monitors(
         paths.clone(),
         shutdown_tx.subscribe(),
     );
+    if let (Some(endpoint), Some(wanted)) =
+        (config.metrics_endpoint.clone(), config.metrics.clone())
+    {
+        metrics::spawn_metrics_scraper(
+            name.clone(),
+            endpoint,
+            wanted,
+            Arc::clone(&processes),
+            shutdown_tx.subscribe(),
+        );
+    }
     if let Some(cr) = config.cron_restart.clone() {
         cron::spawn_cron_restart(
             name,
             cr,
+            config.cron_timezone.clone(),
             Arc::clone(&processes),
             paths,
             shutdown_tx.subscribe(),
@@ -246,18 +472,60 @@ pub fn spawn_aux_monitors(
 
 pub type ProcessTable = HashMap<String, ManagedProcess>;
 
+/// The variables a process's `env_cmd` commands set.
+pub type EnvCmdValues = HashMap<String, String>;
+
+/// The variables from a process's `env_file` and `env`, later ones winning.
+fn config_env(config: &ProcessConfig) -> Result<HashMap<String, String>, ProcessError> {
+    let mut env_vars = HashMap::new();
+    if config.env_file.is_some() {
+        let env_file_vars = config
+            .load_env_files()
+            .map_err(|e| ProcessError::EnvFile(e.to_string()))?;
+        env_vars.extend(env_file_vars);
+    }
+    if let Some(ref env) = config.env {
+        env_vars.extend(env.clone());
+    }
+    Ok(env_vars)
+}
+
+/// Runs a process's `env_cmd` commands ahead of its spawn. Callers that
+/// take the process table to spawn resolve these first, since they are user
+/// commands that may take a while.
+pub async fn resolve_env_cmd(config: &ProcessConfig) -> Result<EnvCmdValues, ProcessError> {
+    match config.env_cmd {
+        Some(ref env_cmd) => run_env_cmds(env_cmd, config, &config_env(config)?).await,
+        None => Ok(EnvCmdValues::new()),
+    }
+}
+
 pub async fn spawn_process(
     name: String,
     config: ProcessConfig,
     paths: &Paths,
+) -> Result<(ManagedProcess, Child), ProcessError> {
+    let env_cmd = resolve_env_cmd(&config).await?;
+    spawn_resolved(name, config, env_cmd, None, paths).await
+}
+
+/// Spawns a process whose `env_cmd` values `resolve_env_cmd` has already
+/// produced. Its output is broadcast on `log_tx` when given, so lines can be
+/// received from the first one on, or else on a new channel.
+pub async fn spawn_resolved(
+    name: String,
+    config: ProcessConfig,
+    env_cmd: EnvCmdValues,
+    log_tx: Option<broadcast::Sender<LogEntry>>,
+    paths: &Paths,
 ) -> Result<(ManagedProcess, Child), ProcessError> {
     if let Some(ref hook) = config.pre_start {
-        run_hook(hook, &name, config.cwd.as_deref(), paths).await?;
+        run_hook(hook, &name, &config, paths).await?;
     }
 
     let (program, args) = parse_command(&config.command)?;
 
-    fs::create_dir_all(paths.log_dir()).await?;
+    LogFiles::new(paths, &name, &config).create_dirs().await?;
 
     let mut cmd = Command::new(&program);
     cmd.args(&args);
@@ -266,15 +534,18 @@ pub async fn spawn_process(
         cmd.current_dir(cwd);
     }
 
-    if config.env_file.is_some() {
-        let env_file_vars = config
-            .load_env_files()
-            .map_err(|e| ProcessError::EnvFile(e.to_string()))?;
-        cmd.envs(&env_file_vars);
-    }
+    let mut env_vars = config_env(&config)?;
+    env_vars.extend(env_cmd);
+    cmd.envs(&env_vars);
 
-    if let Some(ref env_vars) = config.env {
-        cmd.envs(env_vars);
+    if config.ready_signal == Some(ReadySignal::Ipc) {
+        let ready_file = paths.ready_file(&name);
+        if let Some(parent) = ready_file.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        // A leftover file from the previous run would report ready too early
+        let _ = fs::remove_file(&ready_file).await;
+        cmd.env(READY_PATH_ENV, &ready_file);
     }
 
     cmd.stdin(std::process::Stdio::null());
@@ -298,62 +569,60 @@ pub async fn spawn_process(
     let mut child = cmd.spawn().map_err(ProcessError::SpawnFailed)?;
     let pid = child.id();
 
-    let (log_tx, _) = broadcast::channel(1024);
+    let log_tx = log_tx.unwrap_or_else(|| broadcast::channel(LOG_BROADCAST_CAPACITY).0);
     let (monitor_tx, _monitor_rx) = watch::channel(false);
 
-    let log_date_format = config.log_date_format.clone();
-
-    // Spawn stdout log copier
+    let log_control = LogControl::default();
     #[cfg(unix)]
-    if let Some(reader) = pty_reader {
-        log::spawn_log_copier(
-            name.clone(),
-            LogStream::Stdout,
-            reader,
-            paths.stdout_log(&name),
-            log_date_format.clone(),
-            log_tx.clone(),
-        );
-    }
+    let (log_relay, output_fds) = match (pty_reader, child.stderr.take()) {
+        (Some(stdout), Some(stderr)) => {
+            copy_output(&name, stdout, stderr, paths, &config, &log_tx, &log_control)
+        }
+        _ => (None, OutputFds::default()),
+    };
     #[cfg(not(unix))]
-    if let Some(stdout) = child.stdout.take() {
-        log::spawn_log_copier(
-            name.clone(),
-            LogStream::Stdout,
-            stdout,
-            paths.stdout_log(&name),
-            log_date_format.clone(),
-            log_tx.clone(),
-        );
-    }
-
-    if let Some(stderr) = child.stderr.take() {
-        log::spawn_log_copier(
-            name.clone(),
-            LogStream::Stderr,
-            stderr,
-            paths.stderr_log(&name),
-            log_date_format,
-            log_tx.clone(),
-        );
-    }
+    let (log_relay, output_fds) = {
+        let log_date_format = config.log_date_format.clone();
+        let logs = LogFiles::new(paths, &name, &config);
+        let limits = LogLimits::new(paths, &config);
+        if let Some(stdout) = child.stdout.take() {
+            log::spawn_log_copier(
+                LogStream::Stdout,
+                stdout,
+                &logs,
+                log_date_format.clone(),
+                &limits,
+                &log_control,
+                log_tx.clone(),
+            );
+        }
+        if let Some(stderr) = child.stderr.take() {
+            log::spawn_log_copier(
+                LogStream::Stderr,
+                stderr,
+                &logs,
+                log_date_format,
+                &limits,
+                &log_control,
+                log_tx.clone(),
+            );
+        }
+        (None, OutputFds::default())
+    };
 
     // Brief delay to let immediately-failing processes exit
     tokio::time::sleep(Duration::from_millis(SPAWN_VERIFY_DELAY_MS)).await;
 
+    let mut exit_code = None;
     let status = match child.try_wait() {
         Ok(Some(exit_status)) => {
             // Process already exited
-            let exit_code = exit_status.code();
-            if exit_code == Some(0) {
-                ProcessStatus::Stopped
-            } else {
-                ProcessStatus::Errored
-            }
+            exit_code = exit_status.code();
+            exited_status(&config, exit_code)
         }
         Ok(None) => {
             // Still running
-            if config.readiness_check.is_some() || config.health_check.is_some() {
+            if config.has_startup_probe() {
                 ProcessStatus::Starting
             } else {
                 ProcessStatus::Online
@@ -361,7 +630,7 @@ pub async fn spawn_process(
         }
         Err(_) => {
             // Cannot query status, assume running
-            if config.readiness_check.is_some() || config.health_check.is_some() {
+            if config.has_startup_probe() {
                 ProcessStatus::Starting
             } else {
                 ProcessStatus::Online
@@ -376,13 +645,113 @@ pub async fn spawn_process(
         status,
         started_at: tokio::time::Instant::now(),
         restarts: 0,
+        restart_history: Vec::new(),
+        exit_code,
+        memory_restarts: 0,
+        memory_breaches: 0,
+        memory_leak: None,
+        health: None,
+        crashes: 0,
+        custom_metrics: BTreeMap::new(),
+        paused: false,
+        suspended: None,
+        unhealthy_restart: false,
         log_broadcaster: log_tx,
         monitor_shutdown: Some(monitor_tx),
+        output_fds,
+        log_relay,
+        log_control,
     };
 
     Ok((managed, child))
 }
 
+/// Copies a process's output to its logs. A daemon that has a pm3 binary
+/// hands it to a `pm3 log-relay`, which outlives the daemon, so the logs keep
+/// up while the daemon restarts and the next one can follow them. The relay
+/// sends the lines back for `pm3 log --follow`. Returns the relay's pid, and
+/// the fds an upgrade passes on.
+#[cfg(unix)]
+fn copy_output(
+    name: &str,
+    stdout: crate::sys::PtyReader,
+    stderr: tokio::process::ChildStderr,
+    paths: &Paths,
+    config: &ProcessConfig,
+    log_tx: &broadcast::Sender<LogEntry>,
+    control: &LogControl,
+) -> (Option<u32>, OutputFds) {
+    use std::os::fd::AsFd;
+
+    let logs = LogFiles::new(paths, name, config);
+    let limits = LogLimits::new(paths, config);
+    let log_date_format = config.log_date_format.clone();
+    if let Some(exe) = log::relay_exe() {
+        let relay = stdout.as_fd().try_clone_to_owned().and_then(|stdout| {
+            let stderr = stderr.as_fd().try_clone_to_owned()?;
+            log::spawn_relay(
+                exe,
+                stdout,
+                stderr,
+                &logs,
+                log_date_format.as_deref(),
+                &limits,
+            )
+        });
+        match relay {
+            Ok(mut relay) => {
+                let pid = relay.id();
+                let Some(feed) = relay.stdout.take() else {
+                    unreachable!("the relay's stdout is piped");
+                };
+                let output_fds = OutputFds {
+                    relay_feed: feed.as_fd().try_clone_to_owned().ok(),
+                    ..OutputFds::default()
+                };
+                log::spawn_relay_feed(
+                    feed,
+                    log_tx.downgrade(),
+                    control.suppressed.clone(),
+                    async move {
+                        let _ = relay.wait().await;
+                    },
+                );
+                return (pid, output_fds);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "failed to start a log relay for '{name}', copying its output in the daemon: {e}"
+                )
+            }
+        }
+    }
+
+    let output_fds = OutputFds {
+        stdout: stdout.as_fd().try_clone_to_owned().ok(),
+        stderr: stderr.as_fd().try_clone_to_owned().ok(),
+        relay_feed: None,
+    };
+    log::spawn_log_copier(
+        LogStream::Stdout,
+        stdout,
+        &logs,
+        log_date_format.clone(),
+        &limits,
+        control,
+        log_tx.clone(),
+    );
+    log::spawn_log_copier(
+        LogStream::Stderr,
+        stderr,
+        &logs,
+        log_date_format,
+        &limits,
+        control,
+        log_tx.clone(),
+    );
+    (None, output_fds)
+}
+
 /// Spawn a process, register it in the table, and attach monitors.
 pub async fn spawn_and_attach(
     name: String,
@@ -404,6 +773,13 @@ pub async fn spawn_and_attach(
 
     {
         let mut table = processes.write().await;
+        if let Some(existing) = table.get_mut(&name) {
+            managed.restart_history = std::mem::take(&mut existing.restart_history);
+            managed.memory_restarts = existing.memory_restarts;
+            managed.memory_breaches = existing.memory_breaches;
+            managed.crashes = existing.crashes;
+            managed.paused = existing.paused;
+        }
         table.insert(name.clone(), managed);
     }
 
@@ -425,12 +801,61 @@ pub async fn spawn_and_attach(
     Ok(())
 }
 
+/// Records an automatic restart at `now`, forgetting those older than
+/// `keep`, the longest window restarts are counted over.
+pub fn record_restart(
+    history: &mut Vec<tokio::time::Instant>,
+    now: tokio::time::Instant,
+    keep: Duration,
+) {
+    history.retain(|t| now.saturating_duration_since(*t) < keep);
+    history.push(now);
+}
+
+/// How many recorded restarts fall within the last `window`.
+pub fn restarts_within(
+    history: &[tokio::time::Instant],
+    now: tokio::time::Instant,
+    window: Duration,
+) -> u32 {
+    history
+        .iter()
+        .filter(|t| now.saturating_duration_since(**t) < window)
+        .count() as u32
+}
+
+/// Whether the process is flapping, i.e. restarted more than `threshold`
+/// times within the last `window`.
+pub fn detect_flapping(
+    history: &[tokio::time::Instant],
+    now: tokio::time::Instant,
+    window: Duration,
+    threshold: u32,
+) -> bool {
+    restarts_within(history, now, window) > threshold
+}
+
+/// Status for a process that exited and will not be restarted.
+pub fn exited_status(config: &ProcessConfig, exit_code: Option<i32>) -> ProcessStatus {
+    match (config.is_task(), exit_code == Some(0)) {
+        (true, true) => ProcessStatus::Succeeded,
+        (true, false) => ProcessStatus::Failed,
+        (false, true) => ProcessStatus::Stopped,
+        (false, false) => ProcessStatus::Errored,
+    }
+}
+
 pub fn evaluate_restart_policy(
     config: &ProcessConfig,
     exit_code: Option<i32>,
     _uptime: Duration,
     restarts: u32,
 ) -> bool {
+    // One-shot tasks are expected to exit
+    if config.is_task() {
+        return false;
+    }
+
     let policy = config.restart.unwrap_or(RestartPolicy::OnFailure);
     let max_restarts = config.max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS);
 
@@ -462,8 +887,39 @@ pub fn evaluate_restart_policy(
 
 /// Compute exponential backoff delay: 100ms * 2^count, capped at 30s
 pub fn compute_backoff(restart_count: u32) -> Duration {
-    let ms = BACKOFF_BASE_MS.saturating_mul(2u64.saturating_pow(restart_count));
-    Duration::from_millis(ms.min(BACKOFF_CAP_MS))
+    compute_restart_delay(
+        RestartBackoff::Exponential,
+        BACKOFF_BASE_MS,
+        BACKOFF_CAP_MS,
+        restart_count,
+    )
+}
+
+pub fn compute_restart_delay(
+    strategy: RestartBackoff,
+    base_ms: u64,
+    cap_ms: u64,
+    restart_count: u32,
+) -> Duration {
+    let ms = match strategy {
+        RestartBackoff::Fixed => base_ms,
+        RestartBackoff::Linear => base_ms.saturating_mul(u64::from(restart_count) + 1),
+        RestartBackoff::Exponential => base_ms.saturating_mul(2u64.saturating_pow(restart_count)),
+    };
+    Duration::from_millis(ms.min(cap_ms))
+}
+
+/// Delay before the next automatic restart, honouring the process's
+/// `restart_delay`, `restart_backoff`, and `max_restart_delay` settings.
+pub fn restart_delay_for(config: &ProcessConfig, restart_count: u32) -> Duration {
+    compute_restart_delay(
+        config
+            .restart_backoff
+            .unwrap_or(RestartBackoff::Exponential),
+        config.restart_delay.unwrap_or(BACKOFF_BASE_MS),
+        config.max_restart_delay.unwrap_or(BACKOFF_CAP_MS),
+        restart_count,
+    )
 }
 
 pub fn spawn_monitor(
@@ -478,14 +934,36 @@ pub fn spawn_monitor(
         // Wait for child to exit (graceful_stop handles killing via PID signals)
         let status = child.wait().await;
         let exit_code = status.ok().and_then(|s| s.code());
+        let name = current_name(name, monitored_pid, &processes).await;
         handle_child_exit(&name, monitored_pid, exit_code, &processes, &paths).await;
     });
 }
 
+/// Resolve the name a monitored child is registered under right now.
+///
+/// A zero-downtime reload spawns the new instance as `__reload_<name>` and
+/// promotes it to `<name>` once healthy, so the monitor follows the PID.
+async fn current_name(
+    name: String,
+    monitored_pid: Option<u32>,
+    processes: &Arc<RwLock<ProcessTable>>,
+) -> String {
+    let table = processes.read().await;
+    if monitored_pid.is_none() || table.get(&name).is_some_and(|m| m.pid == monitored_pid) {
+        return name;
+    }
+    table
+        .iter()
+        .find(|(_, m)| m.pid == monitored_pid)
+        .map(|(k, _)| k.clone())
+        .unwrap_or(name)
+}
+
 /// Monitor a reattached process by polling `is_pid_alive`.
 ///
-/// Used during `restore_from_dump` where we have no `Child` handle.
-/// When the PID dies, `handle_child_exit` is called so restart policies apply.
+/// Used during `restore_from_dump` and after a daemon upgrade, where we have
+/// no `Child` handle. When the PID dies, `handle_child_exit` is called so
+/// restart policies apply.
 pub fn spawn_pid_monitor(
     name: String,
     pid: u32,
@@ -493,23 +971,35 @@ pub fn spawn_pid_monitor(
     paths: Paths,
     mut shutdown_rx: watch::Receiver<bool>,
 ) {
+    use crate::sys::Reaped;
+
     tokio::spawn(async move {
+        let mut stopping = false;
         loop {
+            // Once stopped, a handed-over child is still reaped when it exits,
+            // so whoever is waiting for it to die sees it go
+            let interval = if stopping { 50 } else { 1000 };
             tokio::select! {
-                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
-                _ = shutdown_rx.changed() => {
-                    if *shutdown_rx.borrow() {
-                        return;
-                    }
+                _ = tokio::time::sleep(Duration::from_millis(interval)) => {}
+                changed = shutdown_rx.changed(), if !stopping => {
+                    stopping = changed.is_err() || *shutdown_rx.borrow();
                 }
             }
-            if *shutdown_rx.borrow() {
-                return;
-            }
-            if !crate::sys::is_pid_alive(pid) {
-                handle_child_exit(&name, Some(pid), None, &processes, &paths).await;
-                return;
+            stopping |= *shutdown_rx.borrow();
+
+            let exit_code = match crate::sys::reap(pid) {
+                Reaped::Exited(code) => Some(code),
+                Reaped::Running => None,
+                Reaped::NotChild if stopping => return,
+                Reaped::NotChild => (!crate::sys::is_pid_alive(pid)).then_some(None),
+            };
+            let Some(exit_code) = exit_code else {
+                continue;
+            };
+            if !stopping {
+                handle_child_exit(&name, Some(pid), exit_code, &processes, &paths).await;
             }
+            return;
         }
     });
 }
@@ -522,6 +1012,7 @@ async fn handle_child_exit(
     paths: &Paths,
 ) {
     let (config, uptime, restarts, should_restart);
+    let mut cooldown = None;
 
     {
         let mut table = processes.write().await;
@@ -543,7 +1034,27 @@ async fn handle_child_exit(
             return;
         }
 
-        let uptime_dur = managed.started_at.elapsed();
+        managed.exit_code = exit_code;
+        // Counted as a crash by the health checker already
+        let unhealthy = std::mem::take(&mut managed.unhealthy_restart);
+        if !unhealthy
+            && matches!(
+                exited_status(&managed.config, exit_code),
+                ProcessStatus::Errored | ProcessStatus::Failed
+            )
+        {
+            managed.crashes += 1;
+        }
+
+        // Paused processes are left down for whoever is doing maintenance
+        if managed.paused {
+            managed.status = exited_status(&managed.config, exit_code);
+            managed.pid = None;
+            return;
+        }
+
+        let uptime_dur = managed.uptime();
+        managed.suspended = None;
         let min_uptime_ms = managed.config.min_uptime.unwrap_or(DEFAULT_MIN_UPTIME_MS);
 
         // If uptime >= min_uptime, process was stable — reset restart counter
@@ -554,24 +1065,61 @@ async fn handle_child_exit(
         config = managed.config.clone();
         uptime = uptime_dur;
         restarts = managed.restarts;
-        should_restart = evaluate_restart_policy(&config, exit_code, uptime, restarts);
+
+        // With max_restarts_window, only restarts inside the window count
+        // against max_restarts; `restarts` still counts them all
+        let now = tokio::time::Instant::now();
+        let restart_window = config
+            .max_restarts_window
+            .as_deref()
+            .and_then(|w| crate::config::parse_duration(w).ok());
+        let budget_used = match restart_window {
+            Some(window) => restarts_within(&managed.restart_history, now, window),
+            None => restarts,
+        };
+        should_restart = if unhealthy {
+            budget_used < config.max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS)
+        } else {
+            evaluate_restart_policy(&config, exit_code, uptime, budget_used)
+        };
 
         if !should_restart {
-            if exit_code == Some(0) {
-                managed.status = ProcessStatus::Stopped;
+            managed.status = if unhealthy {
+                tracing::warn!("'{name}' has no restarts left, leaving it errored");
+                ProcessStatus::Errored
             } else {
-                managed.status = ProcessStatus::Errored;
-            }
+                exited_status(&config, exit_code)
+            };
             managed.pid = None;
             return;
         }
 
         // Mark as restarting
         managed.pid = None;
+
+        let flap_window = config
+            .flap_threshold
+            .map(|_| Duration::from_millis(config.flap_window.unwrap_or(DEFAULT_FLAP_WINDOW_MS)));
+        if let Some(keep) = restart_window.max(flap_window) {
+            record_restart(&mut managed.restart_history, now, keep);
+        }
+
+        if let (Some(threshold), Some(window)) = (config.flap_threshold, flap_window)
+            && detect_flapping(&managed.restart_history, now, window, threshold)
+        {
+            let ms = config.flap_cooldown.unwrap_or(DEFAULT_FLAP_COOLDOWN_MS);
+            tracing::warn!(
+                "process '{name}' is flapping ({} restarts within {}ms), pausing restarts for {ms}ms",
+                restarts_within(&managed.restart_history, now, window),
+                window.as_millis()
+            );
+            managed.status = ProcessStatus::Flapping;
+            cooldown = Some(Duration::from_millis(ms));
+        }
     }
 
-    // Compute backoff and sleep outside the lock
-    let backoff = compute_backoff(restarts);
+    // Compute backoff (or the flapping cooldown) and sleep outside the lock
+    let backoff = cooldown.unwrap_or_else(|| restart_delay_for(&config, restarts));
     tokio::time::sleep(backoff).await;
 
     // Re-check shutdown wasn't signaled while we were sleeping
@@ -586,6 +1134,16 @@ async fn handle_child_exit(
             managed.status = ProcessStatus::Stopped;
             return;
         }
+        if managed.paused && managed.pid.is_none() {
+            managed.status = exited_status(&config, exit_code);
+            return;
+        }
+        // A manual start/restart during the cooldown already replaced the process
+        if cooldown.is_some()
+            && (managed.status != ProcessStatus::Flapping || managed.pid.is_some())
+        {
+            return;
+        }
     }
 
     if let Err(e) = spawn_and_attach(
@@ -597,7 +1155,7 @@ async fn handle_child_exit(
     )
     .await
     {
-        eprintln!("failed to restart '{name}': {e}");
+        tracing::error!("failed to restart '{name}': {e}");
         let mut table = processes.write().await;
         if let Some(managed) = table.get_mut(name) {
             managed.status = ProcessStatus::Errored;
@@ -733,26 +1291,73 @@ mod tests {
             cwd: None,
             env: None,
             env_file: None,
+            env_cmd: None,
             readiness_check: None,
             readiness_timeout: None,
             health_check: None,
+            on_unhealthy: None,
+            unhealthy_threshold: None,
             kill_timeout: None,
             kill_signal: None,
             max_restarts: None,
             max_memory: None,
+            memory_check_interval: None,
+            memory_action: None,
+            memory_leak_window: None,
+            memory_leak_action: None,
             min_uptime: None,
             stop_exit_codes: None,
             watch: None,
             watch_ignore: None,
+            watch_extensions: None,
+            watch_use_gitignore: None,
+            watch_debounce: None,
+            watch_delay: None,
+            watch_poll: None,
+            watch_env_files: None,
+            watch_command: None,
+            watch_restart: None,
             depends_on: None,
             restart,
             group: None,
             pre_start: None,
             post_stop: None,
             cron_restart: None,
+            cron_start: None,
+            cron_stop: None,
+            cron_timezone: None,
+            tags: None,
+            ready_signal: None,
+            ready_delay: None,
+            drain_signal: None,
+            drain_timeout: None,
+            port: None,
+            increment_port: None,
+            instance_env: None,
+            optional_deps: None,
+            stop_dependents: None,
+            restart_stagger: None,
             log_date_format: None,
+            log_combine: None,
+            merge_logs: None,
+            stdout_log: None,
+            stderr_log: None,
+            log_max_line_len: None,
+            log_rate_limit: None,
             instances: None,
+            flap_threshold: None,
+            flap_window: None,
+            flap_cooldown: None,
+            conflict_check: None,
+            restart_delay: None,
+            restart_backoff: None,
+            max_restart_delay: None,
+            max_restarts_window: None,
+            kind: None,
+            metrics_endpoint: None,
+            metrics: None,
             environments: HashMap::new(),
+            environment_overrides: HashMap::new(),
         }
     }
 
@@ -904,6 +1509,114 @@ mod tests {
         assert_eq!(compute_backoff(30), Duration::from_millis(BACKOFF_CAP_MS));
     }
 
+    #[test]
+    fn test_restart_delay_strategies() {
+        assert_eq!(
+            compute_restart_delay(RestartBackoff::Fixed, 500, 10_000, 5),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            compute_restart_delay(RestartBackoff::Linear, 500, 10_000, 0),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            compute_restart_delay(RestartBackoff::Linear, 500, 10_000, 3),
+            Duration::from_millis(2000)
+        );
+        assert_eq!(
+            compute_restart_delay(RestartBackoff::Linear, 500, 10_000, 100),
+            Duration::from_millis(10_000)
+        );
+        assert_eq!(
+            compute_restart_delay(RestartBackoff::Exponential, 50, 1000, 3),
+            Duration::from_millis(400)
+        );
+    }
+
+    #[test]
+    fn test_restart_delay_for_uses_config() {
+        let mut config = test_config(Some(RestartPolicy::OnFailure));
+        assert_eq!(restart_delay_for(&config, 2), compute_backoff(2));
+
+        config.restart_delay = Some(250);
+        config.restart_backoff = Some(RestartBackoff::Fixed);
+        config.max_restart_delay = Some(200);
+        assert_eq!(restart_delay_for(&config, 4), Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_record_restart_keeps_only_the_longest_window() {
+        let start = tokio::time::Instant::now();
+        let mut history = vec![start, start + Duration::from_secs(100)];
+        record_restart(
+            &mut history,
+            start + Duration::from_secs(300),
+            Duration::from_secs(250),
+        );
+        assert_eq!(
+            history,
+            vec![
+                start + Duration::from_secs(100),
+                start + Duration::from_secs(300)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_restarts_within_counts_the_window() {
+        let start = tokio::time::Instant::now();
+        let history = vec![
+            start,
+            start + Duration::from_secs(100),
+            start + Duration::from_secs(250),
+        ];
+        let now = start + Duration::from_secs(300);
+        assert_eq!(restarts_within(&history, now, Duration::from_secs(250)), 2);
+        assert_eq!(restarts_within(&history, now, Duration::from_secs(10)), 0);
+        assert_eq!(history.len(), 3);
+    }
+
+    #[test]
+    fn test_detect_flapping_over_threshold() {
+        let start = tokio::time::Instant::now();
+        let window = Duration::from_secs(60);
+        let history = vec![start, start + Duration::from_secs(1)];
+        assert!(!detect_flapping(
+            &history,
+            start + Duration::from_secs(1),
+            window,
+            2
+        ));
+        let history = vec![
+            start,
+            start + Duration::from_secs(1),
+            start + Duration::from_secs(2),
+        ];
+        assert!(detect_flapping(
+            &history,
+            start + Duration::from_secs(2),
+            window,
+            2
+        ));
+    }
+
+    #[test]
+    fn test_detect_flapping_ignores_restarts_outside_window() {
+        let start = tokio::time::Instant::now();
+        let window = Duration::from_secs(10);
+        let history = vec![
+            start,
+            start + Duration::from_secs(1),
+            start + Duration::from_secs(30),
+        ];
+        assert!(!detect_flapping(
+            &history,
+            start + Duration::from_secs(30),
+            window,
+            2
+        ));
+    }
+
     #[test]
     fn test_min_uptime_resets_counter_before_policy_check() {
         let mut config = test_config(Some(RestartPolicy::OnFailure));
@@ -0,0 +1,121 @@
+//! Named profiles run fully isolated daemons: `pm3 --profile work` talks to a
+//! daemon with its own socket, pid file, logs and saved state under
+//! `<data dir>/profiles/work`. No profile means the default daemon.
+
+use crate::paths::Paths;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Where the profiles' data dirs live, inside the default daemon's.
+pub const PROFILES_DIR: &str = "profiles";
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DaemonEntry {
+    pub profile: String,
+    pub data_dir: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub running: bool,
+}
+
+pub fn validate(profile: &str) -> Result<(), String> {
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+    {
+        return Err(format!(
+            "invalid profile '{profile}': profiles may only contain letters, digits, '-' and '_'"
+        ));
+    }
+    Ok(())
+}
+
+/// Paths for `profile`, rooted in the default daemon's data dir.
+pub fn paths_for(base: &Paths, profile: Option<&str>) -> Result<Paths, String> {
+    match profile {
+        None => Ok(base.clone()),
+        Some(DEFAULT_PROFILE) => Ok(base.clone()),
+        Some(name) => {
+            validate(name)?;
+            Ok(Paths::with_base(
+                base.data_dir().join(PROFILES_DIR).join(name),
+            ))
+        }
+    }
+}
+
+/// The default daemon plus every profile that has a data dir, sorted by name.
+pub fn list_daemons(base: &Paths) -> Vec<DaemonEntry> {
+    let mut profiles: Vec<String> = std::fs::read_dir(base.data_dir().join(PROFILES_DIR))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| validate(name).is_ok())
+        .collect();
+    profiles.sort();
+
+    std::iter::once(DEFAULT_PROFILE.to_string())
+        .chain(profiles)
+        .filter_map(|profile| {
+            let paths = paths_for(base, Some(&profile)).ok()?;
+            let pid = std::fs::read_to_string(paths.pid_file())
+                .ok()
+                .and_then(|s| s.trim().parse().ok());
+            let running = pid.is_some_and(|pid| crate::sys::check_pid(pid).unwrap_or(false));
+            Some(DaemonEntry {
+                profile,
+                data_dir: paths.data_dir().to_path_buf(),
+                pid,
+                running,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_for_profile() {
+        let base = Paths::with_base(PathBuf::from("/tmp/pm3-test"));
+        assert_eq!(paths_for(&base, None).unwrap().data_dir(), base.data_dir());
+        assert_eq!(
+            paths_for(&base, Some("default")).unwrap().data_dir(),
+            base.data_dir()
+        );
+        assert!(
+            paths_for(&base, Some("work"))
+                .unwrap()
+                .socket_file()
+                .ends_with("profiles/work/pm3.sock")
+        );
+        assert!(paths_for(&base, Some("../etc")).is_err());
+        assert!(paths_for(&base, Some("")).is_err());
+    }
+
+    #[test]
+    fn test_list_daemons() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = Paths::with_base(dir.path().to_path_buf());
+        let work = paths_for(&base, Some("work")).unwrap();
+        std::fs::create_dir_all(work.data_dir()).unwrap();
+        std::fs::write(work.pid_file(), std::process::id().to_string()).unwrap();
+        std::fs::create_dir_all(base.data_dir().join(PROFILES_DIR).join("home")).unwrap();
+
+        let daemons = list_daemons(&base);
+        let summary: Vec<(&str, bool)> = daemons
+            .iter()
+            .map(|d| (d.profile.as_str(), d.running))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("default", false), ("home", false), ("work", true)]
+        );
+        assert_eq!(daemons[2].pid, Some(std::process::id()));
+    }
+}
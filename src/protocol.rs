@@ -1,11 +1,19 @@
+use crate::audit::AuditEntry;
 use crate::config::ProcessConfig;
+use crate::events::ProcessEvent;
+use crate::log::LogStream;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-fn default_log_lines() -> usize {
+/// How many recent lines a log request replays when it doesn't say.
+pub fn default_log_lines() -> usize {
     15
 }
 
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Request {
@@ -19,21 +27,49 @@ pub enum Request {
         wait: bool,
         #[serde(default, skip_serializing_if = "Option::is_none")]
         path: Option<String>,
+        #[serde(default)]
+        force: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+        /// Stream `Progress` responses while waiting, before the final one.
+        #[serde(default, skip_serializing_if = "is_false")]
+        progress: bool,
     },
     Stop {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         names: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        except: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+        /// Stop only the selected processes, leaving their dependents running.
+        #[serde(default, skip_serializing_if = "is_false")]
+        no_dependents: bool,
     },
     Restart {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         names: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        except: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+        /// Milliseconds between respawns; overrides each process's `restart_stagger`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stagger_ms: Option<u64>,
+    },
+    List {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filter: Option<ListFilter>,
     },
-    List,
     Kill,
     Reload {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         names: Option<Vec<String>>,
         #[serde(default, skip_serializing_if = "Option::is_none")]
+        except: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         path: Option<String>,
     },
     Info {
@@ -43,22 +79,141 @@ pub enum Request {
         name: String,
         signal: String,
     },
-    Save,
+    Save {
+        /// Also names the snapshot taken, replacing an older one with the same tag.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+    },
     Resurrect {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         path: Option<String>,
+        /// Restore this snapshot, by tag or id, instead of the dump file.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from: Option<String>,
+    },
+    /// The kept snapshots of saved state.
+    Snapshots,
+    /// The configs of a namespace's processes, as a pm3.toml would declare them.
+    Export {
+        /// The default namespace when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
     },
     Flush {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         names: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+        /// Also remove the log files of processes no longer in the table.
+        #[serde(default, skip_serializing_if = "is_false")]
+        all_files: bool,
+    },
+    /// Has the copiers of these processes' output, or of all of them and
+    /// the daemon's own log, reopen their files.
+    ReopenLogs {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        names: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+    },
+    /// Removes stopped, errored and finished processes from the table, and
+    /// log files no process in it or in the dump file writes to.
+    Prune {
+        /// Also remove the log files of the processes removed.
+        #[serde(default, skip_serializing_if = "is_false")]
+        logs: bool,
+        /// Only prune processes of this namespace, or of every one when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+    },
+    DaemonInfo,
+    /// Re-execs the daemon with the pm3 binary now installed at its path,
+    /// handing its processes over instead of stopping them.
+    Upgrade,
+    Wait {
+        names: Vec<String>,
+        status: ProcessStatus,
+        timeout_ms: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+    },
+    Suspend {
+        name: String,
+    },
+    Continue {
+        name: String,
+    },
+    Pause {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        names: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+    },
+    Resume {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        names: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+    },
+    Run {
+        configs: HashMap<String, ProcessConfig>,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        env: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
     },
     Log {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
         #[serde(default = "default_log_lines")]
         lines: usize,
         #[serde(default)]
         follow: bool,
+        /// Only lines logged since then, e.g. `"15m"` ago or
+        /// `"2024-05-01 12:00"`, going by their `log_date_format` timestamps.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        since: Option<String>,
+        /// Only lines logged until then, in the same forms as `since`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        until: Option<String>,
+        /// Only lines from this stream, or both when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stream: Option<LogStream>,
+    },
+    /// Turns the connection into a session carrying many tagged requests.
+    Session,
+    /// Streams process events until the client disconnects.
+    Events {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        names: Option<Vec<String>>,
+    },
+    /// Recorded resource samples of one process.
+    Stats {
+        name: String,
+        /// Only samples from the last this many seconds; all kept ones when absent.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        since_secs: Option<u64>,
+    },
+    /// Availability of one process, or every process, over trailing windows.
+    Report {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        windows_secs: Vec<u64>,
+    },
+    /// The processes running processes spawned, for these processes, groups
+    /// or patterns, or for all of them.
+    Tree {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    /// The last entries of the daemon's audit log.
+    History {
+        lines: usize,
     },
 }
 
@@ -74,6 +229,10 @@ pub enum Response {
     },
     ProcessList {
         processes: Vec<ProcessInfo>,
+        /// How far a restore from the dump file has got, while one runs, so
+        /// `pm3 list` can show it without asking separately.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        restoring: Option<RestoreProgress>,
     },
     ProcessDetail {
         info: Box<ProcessDetail>,
@@ -81,8 +240,182 @@ pub enum Response {
     LogLine {
         #[serde(default, skip_serializing_if = "Option::is_none")]
         name: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stream: Option<LogStream>,
+        /// When the daemon captured the line; absent for lines replayed
+        /// from log files.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timestamp: Option<String>,
         line: String,
     },
+    TaskFinished {
+        name: String,
+        status: ProcessStatus,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exit_code: Option<i32>,
+    },
+    DaemonInfo {
+        info: DaemonInfo,
+    },
+    Progress {
+        message: String,
+    },
+    /// Ends the responses to one request in a session.
+    Done,
+    Event(ProcessEvent),
+    Stats {
+        name: String,
+        /// Seconds between samples.
+        interval: u64,
+        samples: Vec<StatsSample>,
+    },
+    Report {
+        reports: Vec<AvailabilityReport>,
+    },
+    Snapshots {
+        snapshots: Vec<SnapshotInfo>,
+    },
+    Configs {
+        configs: HashMap<String, ProcessConfig>,
+    },
+    Trees {
+        trees: Vec<ProcessTree>,
+    },
+    History {
+        entries: Vec<AuditEntry>,
+    },
+}
+
+/// One reading of a process's resource use, as kept by the daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatsSample {
+    /// Unix timestamp in seconds.
+    pub time: i64,
+    /// Absent while the process isn't running.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+/// How available a process was over one window, as shown by `pm3 report`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AvailabilityReport {
+    pub name: String,
+    pub window_secs: u64,
+    /// Seconds the process was running.
+    pub uptime_secs: f64,
+    /// Seconds it should have been running but wasn't: crashed, waiting to
+    /// be restarted, or unhealthy. Time stopped on purpose counts as neither.
+    pub downtime_secs: f64,
+    /// Uptime as a percentage of uptime plus downtime; absent when the
+    /// process wasn't meant to run at all during the window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub availability: Option<f64>,
+    pub crashes: u32,
+    pub restarts: u32,
+    /// Mean seconds from going down to running again, over the outages that
+    /// ended within the window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mttr_secs: Option<f64>,
+    /// Unix timestamp in seconds of the latest crash in the window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_crash: Option<i64>,
+}
+
+/// A running process and what it spawned, as shown by `pm3 tree`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessTree {
+    pub name: String,
+    pub root: TreeNode,
+}
+
+/// One OS process in a [`ProcessTree`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeNode {
+    pub pid: u32,
+    /// Command line; empty when it couldn't be read.
+    #[serde(default)]
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+}
+
+/// A kept copy of the saved processes, as listed by `pm3 snapshots`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    /// When it was taken, as `YYYYMMDDTHHMMSSmmmZ` in UTC.
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Unix timestamp in seconds.
+    pub created: i64,
+    pub processes: usize,
+}
+
+/// Identifies the daemon answering on the socket, for `pm3 ping`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonInfo {
+    pub pid: u32,
+    pub version: String,
+    /// Seconds since the daemon started.
+    pub uptime: u64,
+    pub processes: usize,
+    pub data_dir: String,
+    /// Times this daemon has been upgraded in place since it started.
+    #[serde(default)]
+    pub upgrades: u32,
+}
+
+/// Progress of an in-flight restore from the dump file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RestoreProgress {
+    pub restored: usize,
+    pub total: usize,
+    #[serde(default)]
+    pub starting: Vec<String>,
+}
+
+/// Narrows `pm3 list` to processes matching every non-empty criterion.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ListFilter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status: Vec<ProcessStatus>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
+
+impl ListFilter {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_empty()
+            && self.group.is_empty()
+            && self.tag.is_empty()
+            && self.namespace.is_none()
+    }
+
+    pub fn matches(&self, name: &str, status: ProcessStatus, config: &ProcessConfig) -> bool {
+        let tags = config.tags.as_deref().unwrap_or_default();
+        self.namespace
+            .as_deref()
+            .is_none_or(|ns| crate::namespace::local_name(ns, name).is_some())
+            && (self.status.is_empty() || self.status.contains(&status))
+            && (self.group.is_empty()
+                || config
+                    .group
+                    .as_ref()
+                    .is_some_and(|g| self.group.contains(g)))
+            && (self.tag.is_empty() || tags.iter().any(|t| self.tag.contains(t)))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -93,6 +426,10 @@ pub enum ProcessStatus {
     Unhealthy,
     Stopped,
     Errored,
+    Flapping,
+    Succeeded,
+    Failed,
+    Suspended,
 }
 
 impl std::fmt::Display for ProcessStatus {
@@ -103,6 +440,29 @@ impl std::fmt::Display for ProcessStatus {
             ProcessStatus::Unhealthy => write!(f, "unhealthy"),
             ProcessStatus::Stopped => write!(f, "stopped"),
             ProcessStatus::Errored => write!(f, "errored"),
+            ProcessStatus::Flapping => write!(f, "flapping"),
+            ProcessStatus::Succeeded => write!(f, "succeeded"),
+            ProcessStatus::Failed => write!(f, "failed"),
+            ProcessStatus::Suspended => write!(f, "suspended"),
+        }
+    }
+}
+
+impl std::str::FromStr for ProcessStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "starting" => Ok(ProcessStatus::Starting),
+            "online" => Ok(ProcessStatus::Online),
+            "unhealthy" => Ok(ProcessStatus::Unhealthy),
+            "stopped" => Ok(ProcessStatus::Stopped),
+            "errored" => Ok(ProcessStatus::Errored),
+            "flapping" => Ok(ProcessStatus::Flapping),
+            "succeeded" => Ok(ProcessStatus::Succeeded),
+            "failed" => Ok(ProcessStatus::Failed),
+            "suspended" => Ok(ProcessStatus::Suspended),
+            other => Err(format!("unknown process status '{other}'")),
         }
     }
 }
@@ -122,7 +482,52 @@ pub struct ProcessInfo {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub memory_bytes: Option<u64>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceUsage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub paused: bool,
+}
+
+/// The latest `health_check` probe of a process.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub passed: bool,
+    /// Seconds since the probe ran.
+    pub checked_ago: u64,
+    pub latency_ms: u64,
+    /// The status code an HTTP check got back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    /// Why the probe failed, when it got no answer at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Probes failed in a row, 0 when this one passed.
+    #[serde(default)]
+    pub failures: u32,
+}
+
+/// What a running process holds besides CPU and memory, for tracking down
+/// leaks. Only read on Linux, from `/proc`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Open file descriptors, sockets included.
+    pub fds: u32,
+    pub threads: u32,
+    /// Open network and unix sockets.
+    pub sockets: u32,
+    /// Bytes read from and written to storage since the process started;
+    /// absent when `/proc/<pid>/io` can't be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub write_bytes: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -140,7 +545,13 @@ pub struct ProcessDetail {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub memory_bytes: Option<u64>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceUsage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub command: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cwd: Option<String>,
@@ -160,6 +571,10 @@ pub struct ProcessDetail {
     pub health_check: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub depends_on: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metrics: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub paused: bool,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -172,21 +587,94 @@ pub enum ProtocolError {
     Malformed(String),
 }
 
-pub fn encode_request(req: &Request) -> Result<Vec<u8>, ProtocolError> {
-    let mut buf = serde_json::to_vec(req)?;
+/// The first line a TCP client sends, ahead of its request. Local socket
+/// clients skip it; they are identified by their peer credentials instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Auth {
+    pub token: String,
+}
+
+pub fn encode_auth(auth: &Auth) -> Result<Vec<u8>, ProtocolError> {
+    let mut buf = serde_json::to_vec(auth)?;
     buf.push(b'\n');
     Ok(buf)
 }
 
+pub fn decode_auth(line: &str) -> Result<Auth, ProtocolError> {
+    Ok(serde_json::from_str(line.trim_end())?)
+}
+
+/// Version of the request/response format. Bump it whenever a request or
+/// response changes in a way an older peer can't parse, so mismatched CLI
+/// and daemon versions fail with a clear message instead of a serde error.
+/// Messages without a version predate versioning and count as 0.
+pub const PROTOCOL_VERSION: u32 = 9;
+
+/// The key carrying `PROTOCOL_VERSION` in every encoded message.
+const VERSION_KEY: &str = "protocol";
+
+/// Serializes a tagged message with `PROTOCOL_VERSION` appended as its last
+/// field. Peers that don't know the key ignore it.
+fn encode_versioned(message: &impl Serialize) -> Result<Vec<u8>, ProtocolError> {
+    let mut buf = serde_json::to_vec(message)?;
+    if buf.pop() != Some(b'}') {
+        return Err(ProtocolError::Malformed("expected a JSON object".into()));
+    }
+    buf.extend_from_slice(format!(",\"{VERSION_KEY}\":{PROTOCOL_VERSION}}}\n").as_bytes());
+    Ok(buf)
+}
+
+/// The protocol version an encoded message was sent with, or `None` if the
+/// line isn't a JSON object. Unversioned messages report 0.
+pub fn message_version(line: &str) -> Option<u32> {
+    let value: serde_json::Value = serde_json::from_str(line.trim_end()).ok()?;
+    let object = value.as_object()?;
+    Some(
+        object
+            .get(VERSION_KEY)
+            .and_then(|v| v.as_u64())
+            .and_then(|v| u32::try_from(v).ok())
+            .unwrap_or(0),
+    )
+}
+
+/// Explains a message that failed to parse when the peer speaks another
+/// protocol version. `peer` names the other side ("daemon" or "CLI").
+pub fn version_mismatch(peer: &str, peer_version: u32) -> Option<String> {
+    use std::cmp::Ordering;
+    let hint = match (peer, peer_version.cmp(&PROTOCOL_VERSION)) {
+        (_, Ordering::Equal) => return None,
+        ("daemon", Ordering::Less) => "restart it with `pm3 kill` to run the current version",
+        ("daemon", Ordering::Greater) => "upgrade this pm3 to match the daemon",
+        (_, Ordering::Less) => "upgrade that pm3 to match the daemon",
+        (_, Ordering::Greater) => "restart the daemon with `pm3 kill` to run the current version",
+    };
+    let relation = if peer_version < PROTOCOL_VERSION {
+        "older"
+    } else {
+        "newer"
+    };
+    let this = if peer == "daemon" {
+        "this CLI"
+    } else {
+        "the daemon"
+    };
+    Some(format!(
+        "{peer} is {relation} than {this} (protocol {peer_version} vs {PROTOCOL_VERSION}); {hint}"
+    ))
+}
+
+pub fn encode_request(req: &Request) -> Result<Vec<u8>, ProtocolError> {
+    encode_versioned(req)
+}
+
 pub fn decode_request(line: &str) -> Result<Request, ProtocolError> {
     let trimmed = line.trim_end();
     Ok(serde_json::from_str(trimmed)?)
 }
 
 pub fn encode_response(resp: &Response) -> Result<Vec<u8>, ProtocolError> {
-    let mut buf = serde_json::to_vec(resp)?;
-    buf.push(b'\n');
-    Ok(buf)
+    encode_versioned(resp)
 }
 
 pub fn decode_response(line: &str) -> Result<Response, ProtocolError> {
@@ -194,6 +682,42 @@ pub fn decode_response(line: &str) -> Result<Response, ProtocolError> {
     Ok(serde_json::from_str(trimmed)?)
 }
 
+/// Id of session messages the daemon sends unprompted, such as events.
+pub const PUSH_ID: u64 = 0;
+
+/// A message inside a session: the request or response plus the id that
+/// pairs them up. Clients number their requests from 1; every response to a
+/// request carries its id, and `Response::Done` marks the last of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tagged<T> {
+    pub id: u64,
+    #[serde(flatten)]
+    pub message: T,
+}
+
+pub fn encode_tagged_request(id: u64, req: &Request) -> Result<Vec<u8>, ProtocolError> {
+    encode_versioned(&Tagged { id, message: req })
+}
+
+pub fn decode_tagged_request(line: &str) -> Result<Tagged<Request>, ProtocolError> {
+    Ok(serde_json::from_str(line.trim_end())?)
+}
+
+pub fn encode_tagged_response(id: u64, resp: &Response) -> Result<Vec<u8>, ProtocolError> {
+    encode_versioned(&Tagged { id, message: resp })
+}
+
+pub fn decode_tagged_response(line: &str) -> Result<Tagged<Response>, ProtocolError> {
+    Ok(serde_json::from_str(line.trim_end())?)
+}
+
+/// The id of a session line, even one whose message doesn't parse, so the
+/// error can be routed back to the request that caused it.
+pub fn message_id(line: &str) -> Option<u64> {
+    let value: serde_json::Value = serde_json::from_str(line.trim_end()).ok()?;
+    value.get("id")?.as_u64()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,26 +746,73 @@ mod tests {
                 cwd: Some("/app".to_string()),
                 env: None,
                 env_file: None,
+                env_cmd: None,
                 readiness_check: None,
                 readiness_timeout: None,
                 health_check: None,
+                on_unhealthy: None,
+                unhealthy_threshold: None,
                 kill_timeout: None,
                 kill_signal: None,
                 max_restarts: None,
                 max_memory: None,
+                memory_check_interval: None,
+                memory_action: None,
+                memory_leak_window: None,
+                memory_leak_action: None,
                 min_uptime: None,
                 stop_exit_codes: None,
                 watch: None,
                 watch_ignore: None,
+                watch_extensions: None,
+                watch_use_gitignore: None,
+                watch_debounce: None,
+                watch_delay: None,
+                watch_poll: None,
+                watch_env_files: None,
+                watch_command: None,
+                watch_restart: None,
                 depends_on: None,
                 restart: None,
                 group: None,
                 pre_start: None,
                 post_stop: None,
                 cron_restart: None,
+                cron_start: None,
+                cron_stop: None,
+                cron_timezone: None,
+                tags: None,
+                ready_signal: None,
+                ready_delay: None,
+                drain_signal: None,
+                drain_timeout: None,
+                port: None,
+                increment_port: None,
+                instance_env: None,
+                optional_deps: None,
+                stop_dependents: None,
+                restart_stagger: None,
                 log_date_format: None,
+                log_combine: None,
+                merge_logs: None,
+                stdout_log: None,
+                stderr_log: None,
+                log_max_line_len: None,
+                log_rate_limit: None,
                 instances: None,
+                flap_threshold: None,
+                flap_window: None,
+                flap_cooldown: None,
+                conflict_check: None,
+                restart_delay: None,
+                restart_backoff: None,
+                max_restart_delay: None,
+                max_restarts_window: None,
+                kind: None,
+                metrics_endpoint: None,
+                metrics: None,
                 environments: HashMap::new(),
+                environment_overrides: HashMap::new(),
             },
         );
         let req = Request::Start {
@@ -250,6 +821,9 @@ mod tests {
             env: Some("production".to_string()),
             wait: false,
             path: Some("/usr/bin:/usr/local/bin".to_string()),
+            force: false,
+            namespace: None,
+            progress: false,
         };
         assert_eq!(roundtrip_request(&req), req);
 
@@ -259,6 +833,9 @@ mod tests {
             env: None,
             wait: true,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         };
         assert_eq!(roundtrip_request(&req_wait), req_wait);
     }
@@ -267,22 +844,132 @@ mod tests {
     fn test_request_stop_roundtrip() {
         let req = Request::Stop {
             names: Some(vec!["web".to_string(), "api".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        };
+        assert_eq!(roundtrip_request(&req), req);
+
+        let req = Request::Stop {
+            names: None,
+            except: Some(vec!["db".to_string()]),
+            namespace: None,
+            no_dependents: false,
         };
         assert_eq!(roundtrip_request(&req), req);
     }
 
     #[test]
     fn test_request_restart_roundtrip() {
-        let req = Request::Restart { names: None };
+        let req = Request::Restart {
+            names: None,
+            except: None,
+            namespace: None,
+            stagger_ms: None,
+        };
+        assert_eq!(roundtrip_request(&req), req);
+    }
+
+    #[test]
+    fn test_request_suspend_continue_roundtrip() {
+        let req = Request::Suspend {
+            name: "web".to_string(),
+        };
+        assert_eq!(roundtrip_request(&req), req);
+        let req = Request::Continue {
+            name: "web".to_string(),
+        };
+        assert_eq!(roundtrip_request(&req), req);
+    }
+
+    #[test]
+    fn test_request_pause_resume_roundtrip() {
+        let req = Request::Pause {
+            names: Some(vec!["web".to_string()]),
+            namespace: None,
+        };
+        assert_eq!(roundtrip_request(&req), req);
+        let req = Request::Resume {
+            names: None,
+            namespace: None,
+        };
         assert_eq!(roundtrip_request(&req), req);
     }
 
     #[test]
     fn test_request_list_roundtrip() {
-        let req = Request::List;
+        let req = Request::List { filter: None };
+        assert_eq!(roundtrip_request(&req), req);
+        assert_eq!(
+            String::from_utf8(encode_request(&req).unwrap()).unwrap(),
+            "{\"type\":\"list\",\"protocol\":9}\n"
+        );
+
+        let req = Request::List {
+            filter: Some(ListFilter {
+                status: vec![ProcessStatus::Errored],
+                group: vec!["backend".to_string()],
+                namespace: Some("shop".to_string()),
+                tag: vec!["critical".to_string()],
+            }),
+        };
         assert_eq!(roundtrip_request(&req), req);
     }
 
+    #[test]
+    fn test_list_filter_matches() {
+        let configs = crate::config::parse_config(
+            r#"
+[api]
+command = "api"
+group = "backend"
+tags = ["critical"]
+
+[ui]
+command = "ui"
+group = "frontend"
+
+[batch]
+command = "batch"
+"#,
+        )
+        .unwrap();
+        let filter = ListFilter {
+            status: vec![ProcessStatus::Errored, ProcessStatus::Failed],
+            group: vec!["backend".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.matches("api", ProcessStatus::Errored, &configs["api"]));
+        assert!(!filter.matches("api", ProcessStatus::Online, &configs["api"]));
+        assert!(!filter.matches("ui", ProcessStatus::Failed, &configs["ui"]));
+        assert!(!filter.matches("batch", ProcessStatus::Failed, &configs["batch"]));
+        assert!(ListFilter::default().matches("batch", ProcessStatus::Online, &configs["batch"]));
+
+        let filter = ListFilter {
+            tag: vec!["critical".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.matches("api", ProcessStatus::Online, &configs["api"]));
+        assert!(!filter.matches("ui", ProcessStatus::Online, &configs["ui"]));
+
+        let filter = ListFilter {
+            namespace: Some("shop".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches("shop/api", ProcessStatus::Online, &configs["api"]));
+        assert!(!filter.matches("api", ProcessStatus::Online, &configs["api"]));
+    }
+
+    #[test]
+    fn test_process_status_from_str() {
+        assert_eq!(
+            "errored".parse::<ProcessStatus>(),
+            Ok(ProcessStatus::Errored)
+        );
+        assert_eq!("Online".parse::<ProcessStatus>(), Ok(ProcessStatus::Online));
+        assert!("bogus".parse::<ProcessStatus>().is_err());
+    }
+
     #[test]
     fn test_request_kill_roundtrip() {
         let req = Request::Kill;
@@ -293,13 +980,17 @@ mod tests {
     fn test_request_reload_roundtrip() {
         let req = Request::Reload {
             names: Some(vec!["worker".to_string()]),
+            except: None,
             path: Some("/usr/bin".to_string()),
+            namespace: None,
         };
         assert_eq!(roundtrip_request(&req), req);
 
         let req_no_path = Request::Reload {
             names: None,
+            except: None,
             path: None,
+            namespace: None,
         };
         assert_eq!(roundtrip_request(&req_no_path), req_no_path);
     }
@@ -323,7 +1014,11 @@ mod tests {
 
     #[test]
     fn test_request_save_roundtrip() {
-        let req = Request::Save;
+        let req = Request::Save { tag: None };
+        assert_eq!(roundtrip_request(&req), req);
+        let req = Request::Save {
+            tag: Some("pre-deploy".to_string()),
+        };
         assert_eq!(roundtrip_request(&req), req);
     }
 
@@ -331,16 +1026,137 @@ mod tests {
     fn test_request_resurrect_roundtrip() {
         let req = Request::Resurrect {
             path: Some("/usr/bin:/home/user/.nix-profile/bin".to_string()),
+            from: Some("pre-deploy".to_string()),
         };
         assert_eq!(roundtrip_request(&req), req);
 
-        let req_no_path = Request::Resurrect { path: None };
+        let req_no_path = Request::Resurrect {
+            path: None,
+            from: None,
+        };
         assert_eq!(roundtrip_request(&req_no_path), req_no_path);
     }
 
+    #[test]
+    fn test_snapshots_roundtrip() {
+        assert_eq!(roundtrip_request(&Request::Snapshots), Request::Snapshots);
+        let resp = Response::Snapshots {
+            snapshots: vec![SnapshotInfo {
+                id: "20250101T000000000Z".to_string(),
+                tag: Some("pre-deploy".to_string()),
+                created: 1_735_689_600,
+                processes: 3,
+            }],
+        };
+        assert_eq!(roundtrip_response(&resp), resp);
+    }
+
+    #[test]
+    fn test_history_roundtrip() {
+        let req = Request::History { lines: 20 };
+        assert_eq!(roundtrip_request(&req), req);
+        let resp = Response::History {
+            entries: vec![AuditEntry {
+                time: "2025-01-01T00:00:00Z".to_string(),
+                uid: Some(1000),
+                user: Some("deploy".to_string()),
+                pid: Some(4242),
+                remote: None,
+                command: "restart web".to_string(),
+                outcome: "ok".to_string(),
+            }],
+        };
+        assert_eq!(roundtrip_response(&resp), resp);
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let req = Request::Export {
+            namespace: Some("shop".to_string()),
+        };
+        assert_eq!(roundtrip_request(&req), req);
+        let configs = crate::config::parse_config(
+            r#"
+[web]
+command = "node server.js"
+instances = 2
+"#,
+        )
+        .unwrap();
+        let resp = Response::Configs { configs };
+        assert_eq!(roundtrip_response(&resp), resp);
+    }
+
+    #[test]
+    fn test_upgrade_roundtrip() {
+        let req = Request::Upgrade;
+        assert_eq!(roundtrip_request(&req), req);
+        assert_eq!(
+            String::from_utf8(encode_request(&req).unwrap()).unwrap(),
+            "{\"type\":\"upgrade\",\"protocol\":9}\n"
+        );
+    }
+
     #[test]
     fn test_request_flush_roundtrip() {
-        let req = Request::Flush { names: None };
+        let req = Request::Flush {
+            names: None,
+            all_files: false,
+            namespace: None,
+        };
+        assert_eq!(roundtrip_request(&req), req);
+
+        let req = Request::Flush {
+            names: None,
+            all_files: true,
+            namespace: None,
+        };
+        assert_eq!(roundtrip_request(&req), req);
+    }
+
+    #[test]
+    fn test_request_reopen_logs_roundtrip() {
+        let req = Request::ReopenLogs {
+            names: Some(vec!["web".to_string()]),
+            namespace: None,
+        };
+        assert_eq!(roundtrip_request(&req), req);
+    }
+
+    #[test]
+    fn test_tree_roundtrip() {
+        let req = Request::Tree {
+            name: Some("web".to_string()),
+        };
+        assert_eq!(roundtrip_request(&req), req);
+
+        let resp = Response::Trees {
+            trees: vec![ProcessTree {
+                name: "web".to_string(),
+                root: TreeNode {
+                    pid: 10,
+                    command: "sh -c node server.js".to_string(),
+                    cpu_percent: Some(0.0),
+                    memory_bytes: Some(4096),
+                    children: vec![TreeNode {
+                        pid: 11,
+                        command: "node server.js".to_string(),
+                        cpu_percent: Some(12.5),
+                        memory_bytes: Some(80 * 1024 * 1024),
+                        children: Vec::new(),
+                    }],
+                },
+            }],
+        };
+        assert_eq!(roundtrip_response(&resp), resp);
+    }
+
+    #[test]
+    fn test_request_prune_roundtrip() {
+        let req = Request::Prune {
+            logs: true,
+            namespace: None,
+        };
         assert_eq!(roundtrip_request(&req), req);
     }
 
@@ -350,6 +1166,10 @@ mod tests {
             name: Some("web".to_string()),
             lines: 30,
             follow: true,
+            since: Some("15m".to_string()),
+            until: None,
+            stream: Some(LogStream::Stderr),
+            namespace: None,
         };
         assert_eq!(roundtrip_request(&req), req);
     }
@@ -385,7 +1205,12 @@ mod tests {
                     restarts: 2,
                     cpu_percent: Some(1.5),
                     memory_bytes: Some(52_428_800),
+                    resources: None,
+                    health: None,
                     group: Some("backend".to_string()),
+                    tags: vec!["critical".to_string()],
+                    metrics: BTreeMap::from([("queue_depth".to_string(), 12.0)]),
+                    paused: false,
                 },
                 ProcessInfo {
                     name: "worker".to_string(),
@@ -395,9 +1220,19 @@ mod tests {
                     restarts: 0,
                     cpu_percent: None,
                     memory_bytes: None,
+                    resources: None,
+                    health: None,
                     group: None,
+                    tags: Vec::new(),
+                    metrics: BTreeMap::new(),
+                    paused: true,
                 },
             ],
+            restoring: Some(RestoreProgress {
+                restored: 1,
+                total: 2,
+                starting: vec!["worker".to_string()],
+            }),
         };
         assert_eq!(roundtrip_response(&resp), resp);
     }
@@ -413,7 +1248,23 @@ mod tests {
                 restarts: 0,
                 cpu_percent: Some(2.3),
                 memory_bytes: Some(104_857_600),
+                resources: Some(ResourceUsage {
+                    fds: 42,
+                    threads: 8,
+                    sockets: 3,
+                    read_bytes: Some(4096),
+                    write_bytes: None,
+                }),
+                health: Some(HealthReport {
+                    passed: false,
+                    checked_ago: 2,
+                    latency_ms: 2001,
+                    http_status: Some(503),
+                    error: None,
+                    failures: 3,
+                }),
                 group: Some("backend".to_string()),
+                tags: vec!["payments".to_string()],
                 command: "node server.js".to_string(),
                 cwd: Some("/app".to_string()),
                 env: Some(HashMap::from([("PORT".to_string(), "3000".to_string())])),
@@ -424,6 +1275,8 @@ mod tests {
                 readiness_timeout: Some(180),
                 health_check: Some("http://localhost:3000/health".to_string()),
                 depends_on: Some(vec!["db".to_string()]),
+                metrics: BTreeMap::new(),
+                paused: false,
             }),
         };
         assert_eq!(roundtrip_response(&resp), resp);
@@ -433,17 +1286,160 @@ mod tests {
     fn test_response_log_line_roundtrip() {
         let resp = Response::LogLine {
             name: Some("web".to_string()),
+            stream: Some(LogStream::Stderr),
+            timestamp: Some("2026-01-02T03:04:05.678Z".to_string()),
             line: "Server started on port 3000".to_string(),
         };
         assert_eq!(roundtrip_response(&resp), resp);
 
         let resp_no_name = Response::LogLine {
             name: None,
+            stream: None,
+            timestamp: None,
             line: "some output".to_string(),
         };
         assert_eq!(roundtrip_response(&resp_no_name), resp_no_name);
     }
 
+    #[test]
+    fn test_streamed_messages_are_flat_json() {
+        let line = Response::LogLine {
+            name: Some("web".to_string()),
+            stream: Some(LogStream::Stdout),
+            timestamp: Some("2026-01-02T03:04:05.678Z".to_string()),
+            line: "ready".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&line).unwrap(),
+            serde_json::json!({
+                "type": "log_line",
+                "name": "web",
+                "stream": "stdout",
+                "timestamp": "2026-01-02T03:04:05.678Z",
+                "line": "ready",
+            })
+        );
+
+        let event = Response::Event(ProcessEvent {
+            name: "web".to_string(),
+            kind: crate::events::EventKind::StatusChanged,
+            status: ProcessStatus::Errored,
+            timestamp: "2026-01-02T03:04:05.678Z".to_string(),
+            detail: None,
+        });
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "type": "event",
+                "name": "web",
+                "kind": "status_changed",
+                "status": "errored",
+                "timestamp": "2026-01-02T03:04:05.678Z",
+            })
+        );
+        assert_eq!(roundtrip_response(&event), event);
+    }
+
+    #[test]
+    fn test_request_run_roundtrip() {
+        let req = Request::Run {
+            configs: HashMap::new(),
+            name: "migrate".to_string(),
+            env: Some("production".to_string()),
+            path: None,
+            namespace: Some("shop".to_string()),
+        };
+        assert_eq!(roundtrip_request(&req), req);
+    }
+
+    #[test]
+    fn test_response_task_finished_roundtrip() {
+        let resp = Response::TaskFinished {
+            name: "migrate".to_string(),
+            status: ProcessStatus::Failed,
+            exit_code: Some(3),
+        };
+        assert_eq!(roundtrip_response(&resp), resp);
+    }
+
+    #[test]
+    fn test_wait_roundtrip() {
+        let req = Request::Wait {
+            names: vec!["web".to_string()],
+            status: ProcessStatus::Stopped,
+            timeout_ms: 30_000,
+            namespace: Some("shop".to_string()),
+        };
+        assert_eq!(roundtrip_request(&req), req);
+    }
+
+    #[test]
+    fn test_stats_roundtrip() {
+        let req = Request::Stats {
+            name: "web".to_string(),
+            since_secs: Some(3600),
+        };
+        assert_eq!(roundtrip_request(&req), req);
+        let resp = Response::Stats {
+            name: "web".to_string(),
+            interval: 10,
+            samples: vec![
+                StatsSample {
+                    time: 1_700_000_000,
+                    cpu_percent: Some(12.5),
+                    memory_bytes: Some(1024),
+                    restarts: 1,
+                },
+                StatsSample {
+                    time: 1_700_000_010,
+                    cpu_percent: None,
+                    memory_bytes: None,
+                    restarts: 1,
+                },
+            ],
+        };
+        assert_eq!(roundtrip_response(&resp), resp);
+    }
+
+    #[test]
+    fn test_report_roundtrip() {
+        let req = Request::Report {
+            name: None,
+            windows_secs: vec![86_400, 604_800],
+        };
+        assert_eq!(roundtrip_request(&req), req);
+        let resp = Response::Report {
+            reports: vec![AvailabilityReport {
+                name: "web".to_string(),
+                window_secs: 86_400,
+                uptime_secs: 86_000.0,
+                downtime_secs: 400.0,
+                availability: Some(99.537),
+                crashes: 2,
+                restarts: 3,
+                mttr_secs: Some(200.0),
+                last_crash: Some(1_700_000_000),
+            }],
+        };
+        assert_eq!(roundtrip_response(&resp), resp);
+    }
+
+    #[test]
+    fn test_daemon_info_roundtrip() {
+        assert_eq!(roundtrip_request(&Request::DaemonInfo), Request::DaemonInfo);
+        let resp = Response::DaemonInfo {
+            info: DaemonInfo {
+                pid: 4242,
+                version: "0.1.6".to_string(),
+                uptime: 90,
+                processes: 3,
+                data_dir: "/tmp/pm3".to_string(),
+                upgrades: 1,
+            },
+        };
+        assert_eq!(roundtrip_response(&resp), resp);
+    }
+
     #[test]
     fn test_decode_invalid_json() {
         let result = decode_request("not json at all");
@@ -466,7 +1462,7 @@ mod tests {
 
     #[test]
     fn test_encode_appends_newline() {
-        let req = Request::List;
+        let req = Request::List { filter: None };
         let bytes = encode_request(&req).unwrap();
         assert_eq!(*bytes.last().unwrap(), b'\n');
 
@@ -482,6 +1478,84 @@ mod tests {
         assert_eq!(ProcessStatus::Unhealthy.to_string(), "unhealthy");
         assert_eq!(ProcessStatus::Stopped.to_string(), "stopped");
         assert_eq!(ProcessStatus::Errored.to_string(), "errored");
+        assert_eq!(ProcessStatus::Flapping.to_string(), "flapping");
+        assert_eq!(ProcessStatus::Succeeded.to_string(), "succeeded");
+        assert_eq!(ProcessStatus::Failed.to_string(), "failed");
+    }
+
+    #[test]
+    fn test_auth_roundtrip() {
+        let auth = Auth {
+            token: "s3cret".to_string(),
+        };
+        let bytes = encode_auth(&auth).unwrap();
+        assert_eq!(bytes, b"{\"token\":\"s3cret\"}\n");
+        let line = std::str::from_utf8(&bytes).unwrap();
+        assert_eq!(decode_auth(line).unwrap(), auth);
+        assert!(decode_auth(r#"{"type":"list"}"#).is_err());
+    }
+
+    #[test]
+    fn test_messages_carry_protocol_version() {
+        let bytes = encode_request(&Request::Kill).unwrap();
+        let line = std::str::from_utf8(&bytes).unwrap();
+        assert_eq!(message_version(line), Some(PROTOCOL_VERSION));
+        assert_eq!(decode_request(line).unwrap(), Request::Kill);
+
+        let bytes = encode_response(&Response::Success { message: None }).unwrap();
+        let line = std::str::from_utf8(&bytes).unwrap();
+        assert_eq!(message_version(line), Some(PROTOCOL_VERSION));
+        assert_eq!(
+            decode_response(line).unwrap(),
+            Response::Success { message: None }
+        );
+
+        // Messages from before versioning
+        assert_eq!(message_version(r#"{"type":"kill"}"#), Some(0));
+        assert_eq!(message_version("not json"), None);
+    }
+
+    #[test]
+    fn test_tagged_roundtrip() {
+        let bytes = encode_tagged_request(3, &Request::List { filter: None }).unwrap();
+        assert_eq!(bytes, b"{\"id\":3,\"type\":\"list\",\"protocol\":9}\n");
+        let line = std::str::from_utf8(&bytes).unwrap();
+        let tagged = decode_tagged_request(line).unwrap();
+        assert_eq!(tagged.id, 3);
+        assert_eq!(tagged.message, Request::List { filter: None });
+
+        let bytes = encode_tagged_response(3, &Response::Done).unwrap();
+        let line = std::str::from_utf8(&bytes).unwrap();
+        assert_eq!(
+            decode_tagged_response(line).unwrap(),
+            Tagged {
+                id: 3,
+                message: Response::Done
+            }
+        );
+    }
+
+    #[test]
+    fn test_message_id_of_unparseable_request() {
+        let line = r#"{"id":7,"type":"no_such_request"}"#;
+        assert!(decode_tagged_request(line).is_err());
+        assert_eq!(message_id(line), Some(7));
+        assert_eq!(message_id(r#"{"type":"list"}"#), None);
+        assert_eq!(message_id("not json"), None);
+    }
+
+    #[test]
+    fn test_version_mismatch_messages() {
+        assert_eq!(version_mismatch("daemon", PROTOCOL_VERSION), None);
+        let older = version_mismatch("daemon", 0).unwrap();
+        assert!(
+            older.starts_with("daemon is older than this CLI"),
+            "{older}"
+        );
+        assert!(older.contains("pm3 kill"), "{older}");
+        let newer = version_mismatch("CLI", PROTOCOL_VERSION + 1).unwrap();
+        assert!(newer.starts_with("CLI is newer than the daemon"), "{newer}");
+        assert!(newer.contains("pm3 kill"), "{newer}");
     }
 
     #[test]
@@ -0,0 +1,217 @@
+//! `[daemon] disk_quota`: caps how much the data dir may hold, so a process
+//! printing in a tight loop can't fill the disk with its logs. The data dirs
+//! of profiles, which other daemons own, don't count. Over the quota, rotated
+//! logs are removed, oldest first; if that isn't enough,
+//! [`Paths::over_quota_file`] exists and log copiers drop output instead of
+//! writing it, until flushing or pruning logs brings the dir back under.
+
+use crate::paths::Paths;
+use crate::profile::PROFILES_DIR;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+
+/// How often the data dir is measured against the quota.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Total size in bytes of the files under `dir`, not following symlinks.
+pub fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = entry.metadata()?;
+        if meta.is_dir() {
+            // A directory removed while being walked counts for nothing
+            total += dir_size(&entry.path()).unwrap_or(0);
+        } else if meta.is_file() {
+            total += meta.len();
+        }
+    }
+    Ok(total)
+}
+
+/// What this daemon's data dir holds, leaving out the profiles' data dirs.
+fn used_bytes(paths: &Paths) -> io::Result<u64> {
+    let profiles = paths.data_dir().join(PROFILES_DIR);
+    let mut total = dir_size(paths.data_dir())?;
+    if profiles.is_dir() {
+        total = total.saturating_sub(dir_size(&profiles).unwrap_or(0));
+    }
+    Ok(total)
+}
+
+/// The rotated logs (`web-out.log.1`, ...) under `dir`, with their size and
+/// when they were last written.
+fn rotated_logs(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+    let mut found = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let rotated = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.parse::<u32>().is_ok())
+                && path
+                    .with_extension("")
+                    .extension()
+                    .is_some_and(|ext| ext == "log");
+            if rotated {
+                let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                found.push((path, meta.len(), modified));
+            }
+        }
+    }
+    found
+}
+
+/// Removes rotated logs, oldest first, until `excess` bytes are freed or
+/// none are left. Returns how many bytes were freed.
+fn free_rotated(paths: &Paths, excess: u64) -> u64 {
+    let mut rotated = rotated_logs(&paths.log_dir());
+    rotated.sort_by_key(|(_, _, modified)| *modified);
+    let mut freed = 0;
+    for (path, size, _) in rotated {
+        if freed >= excess {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => freed += size,
+            Err(e) => tracing::warn!("failed to remove {}: {e}", path.display()),
+        }
+    }
+    freed
+}
+
+/// Marks the data dir as over `quota` or not, creating or removing the
+/// over-quota file, and logs when that changes. Rotated logs are removed
+/// first to get back under it.
+fn update(paths: &Paths, quota: u64) {
+    let flag = paths.over_quota_file();
+    let mut used = match used_bytes(paths) {
+        Ok(used) => used,
+        Err(e) => {
+            tracing::warn!("failed to measure the data dir for disk_quota: {e}");
+            return;
+        }
+    };
+    if used > quota {
+        let freed = free_rotated(paths, used - quota);
+        if freed > 0 {
+            tracing::info!("removed {freed} bytes of rotated logs to stay under disk_quota");
+            used = used.saturating_sub(freed);
+        }
+    }
+    let flagged = flag.exists();
+    if used > quota && !flagged {
+        tracing::warn!(
+            "data dir holds {used} bytes, over disk_quota of {quota}; dropping process output until logs are flushed or pruned"
+        );
+        if let Err(e) = std::fs::write(&flag, b"") {
+            tracing::warn!("failed to create {}: {e}", flag.display());
+        }
+    } else if used <= quota && flagged {
+        tracing::info!("data dir is back under disk_quota; logging process output again");
+        let _ = std::fs::remove_file(&flag);
+    }
+}
+
+/// With a `quota`, checks the data dir against it until shutdown. Without
+/// one, clears an over-quota mark a previous daemon may have left.
+pub fn spawn_quota_watcher(
+    quota: Option<u64>,
+    paths: Paths,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let Some(quota) = quota else {
+        let _ = std::fs::remove_file(paths.over_quota_file());
+        return;
+    };
+    tokio::spawn(async move {
+        loop {
+            let checked = paths.clone();
+            let _ = tokio::task::spawn_blocking(move || update(&checked, quota)).await;
+            tokio::select! {
+                _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dir_size_counts_nested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a"), [0; 100]).unwrap();
+        std::fs::create_dir(dir.path().join("logs")).unwrap();
+        std::fs::write(dir.path().join("logs/b"), [0; 50]).unwrap();
+        assert_eq!(dir_size(dir.path()).unwrap(), 150);
+    }
+
+    #[test]
+    fn test_update_flags_and_clears_over_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().to_path_buf());
+        std::fs::write(dir.path().join("big.log"), [0; 1000]).unwrap();
+
+        update(&paths, 500);
+        assert!(paths.over_quota_file().exists());
+
+        update(&paths, 5000);
+        assert!(!paths.over_quota_file().exists());
+    }
+
+    #[test]
+    fn test_update_skips_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().to_path_buf());
+        let profile = dir.path().join(PROFILES_DIR).join("work");
+        std::fs::create_dir_all(&profile).unwrap();
+        std::fs::write(profile.join("big.log"), [0; 1000]).unwrap();
+
+        update(&paths, 500);
+        assert!(!paths.over_quota_file().exists());
+    }
+
+    #[test]
+    fn test_update_removes_rotated_logs_oldest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().to_path_buf());
+        std::fs::create_dir_all(paths.log_dir()).unwrap();
+        let oldest = paths.rotated_stdout_log("web", 2);
+        let newer = paths.rotated_stdout_log("web", 1);
+        std::fs::write(&oldest, [0; 400]).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::write(&newer, [0; 400]).unwrap();
+        std::fs::write(paths.stdout_log("web"), [0; 100]).unwrap();
+
+        update(&paths, 600);
+        assert!(!oldest.exists());
+        assert!(newer.exists());
+        assert!(!paths.over_quota_file().exists());
+
+        // With nothing left to remove, output stays dropped
+        update(&paths, 50);
+        assert!(!newer.exists());
+        assert!(paths.over_quota_file().exists());
+    }
+}
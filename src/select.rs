@@ -0,0 +1,180 @@
+use regex::Regex;
+
+/// How a name given on the command line selects processes.
+///
+/// Plain names are matched exactly (and may still expand to cluster
+/// instances or groups). Names containing `*`, `?` or `[` are globs, and
+/// names wrapped in slashes (`/^web-\d+$/`, what `--regex` sends) are
+/// regular expressions. Process names can't contain `/`, so neither form
+/// is ambiguous.
+#[derive(Debug)]
+pub enum NamePattern {
+    Exact(String),
+    Glob(String),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        if name.len() >= 2 && name.starts_with('/') && name.ends_with('/') {
+            let expr = &name[1..name.len() - 1];
+            return Regex::new(expr)
+                .map(NamePattern::Regex)
+                .map_err(|e| format!("invalid regex '{expr}': {e}"));
+        }
+        if name.contains(['*', '?', '[']) {
+            return Ok(NamePattern::Glob(name.to_string()));
+        }
+        Ok(NamePattern::Exact(name.to_string()))
+    }
+
+    pub fn matches(&self, candidate: &str) -> bool {
+        match self {
+            NamePattern::Exact(name) => name == candidate,
+            NamePattern::Glob(pattern) => glob_match(pattern, candidate),
+            NamePattern::Regex(re) => re.is_match(candidate),
+        }
+    }
+}
+
+/// Wraps names so the daemon treats them as regular expressions.
+pub fn as_regex_names(names: Vec<String>) -> Vec<String> {
+    names.into_iter().map(|n| format!("/{n}/")).collect()
+}
+
+/// Shell-style glob matching: `*` matches any run of characters, `?` a
+/// single character, and `[abc]` / `[a-z]` / `[!abc]` a character class.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_at(&pattern, &text)
+}
+
+fn glob_match_at(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position to resume from after the most recent `*`
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    backtrack = Some((p, t));
+                    p += 1;
+                    continue;
+                }
+                '?' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                '[' => {
+                    if let Some((matched, next)) = match_class(&pattern[p..], text[t])
+                        && matched
+                    {
+                        p += next;
+                        t += 1;
+                        continue;
+                    }
+                }
+                c if c == text[t] => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        match backtrack {
+            Some((star_p, star_t)) => {
+                p = star_p + 1;
+                t = star_t + 1;
+                backtrack = Some((star_p, star_t + 1));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Matches `c` against the class at the start of `pattern`. Returns whether
+/// it matched and the class length, or `None` if the class is unterminated.
+fn match_class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while let Some(&ch) = pattern.get(i) {
+        if ch == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        if pattern.get(i + 1) == Some(&'-')
+            && let Some(&end) = pattern.get(i + 2)
+            && end != ']'
+        {
+            matched |= ch <= c && c <= end;
+            i += 3;
+        } else {
+            matched |= ch == c;
+            i += 1;
+        }
+        first = false;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_star_and_question() {
+        assert!(glob_match("web-*", "web-1"));
+        assert!(glob_match("web-*", "web-"));
+        assert!(!glob_match("web-*", "api-1"));
+        assert!(glob_match("worker:?", "worker:3"));
+        assert!(!glob_match("worker:?", "worker:12"));
+        assert!(glob_match("*-api-*", "eu-api-2"));
+        assert!(glob_match("*", "anything"));
+    }
+
+    #[test]
+    fn test_glob_character_classes() {
+        assert!(glob_match("worker:[0-2]", "worker:1"));
+        assert!(!glob_match("worker:[0-2]", "worker:5"));
+        assert!(glob_match("worker:[!0]", "worker:5"));
+        assert!(!glob_match("worker:[!0]", "worker:0"));
+        assert!(glob_match("[ab]pi", "api"));
+        assert!(!glob_match("[ab", "a"));
+    }
+
+    #[test]
+    fn test_pattern_parse() {
+        assert!(matches!(
+            NamePattern::parse("web").unwrap(),
+            NamePattern::Exact(_)
+        ));
+        assert!(matches!(
+            NamePattern::parse("web-*").unwrap(),
+            NamePattern::Glob(_)
+        ));
+        let re = NamePattern::parse(r"/^web-\d+$/").unwrap();
+        assert!(re.matches("web-12"));
+        assert!(!re.matches("web-x"));
+        assert!(NamePattern::parse("/(/").is_err());
+    }
+
+    #[test]
+    fn test_as_regex_names() {
+        assert_eq!(
+            as_regex_names(vec!["^web".to_string()]),
+            vec!["/^web/".to_string()]
+        );
+    }
+}
@@ -0,0 +1,972 @@
+use crate::config;
+use crate::paths::Paths;
+use crate::sys::PeerCredentials;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How many processes of one dependency level are spawned at once by default.
+pub const DEFAULT_SPAWN_CONCURRENCY: usize = 8;
+
+/// How many snapshots of saved state `pm3 save` keeps by default.
+pub const DEFAULT_KEEP_SNAPSHOTS: usize = 10;
+
+/// Env var names whose values `pm3 info` and the dump file hide by default.
+pub const DEFAULT_ENV_MASK: [&str; 5] = [
+    "*_KEY",
+    "*_TOKEN",
+    "*_SECRET",
+    "*PASSWORD*",
+    "*_CREDENTIALS",
+];
+
+/// Where the web dashboard listens when `web_ui` is on and `web_listen` isn't set.
+pub const DEFAULT_WEB_LISTEN: SocketAddr = SocketAddr::V4(std::net::SocketAddrV4::new(
+    std::net::Ipv4Addr::LOCALHOST,
+    7880,
+));
+
+/// Daemon-wide settings from `settings.toml` in the data dir, as opposed to
+/// the per-project process definitions in pm3.toml.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub daemon: DaemonSettings,
+    pub remote: RemoteSettings,
+    pub grpc: GrpcSettings,
+    pub metrics: MetricsSettings,
+    pub telemetry: TelemetrySettings,
+    pub history: HistorySettings,
+    /// `[[notify]]` tables, one per notification channel.
+    pub notify: Vec<NotifierSettings>,
+}
+
+/// The `[daemon]` section.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonSettings {
+    /// Maximum number of processes spawned in parallel within a dependency level.
+    pub spawn_concurrency: usize,
+    /// Least severe level written to `daemon.log`.
+    pub log_level: LogLevel,
+    /// Users, besides the daemon's own, allowed to connect. Empty with
+    /// `allowed_gids` also empty means anyone who can open the socket.
+    pub allowed_uids: Vec<u32>,
+    /// Groups whose members (by the client's primary gid) may connect.
+    pub allowed_gids: Vec<u32>,
+    /// Permission bits for the Unix socket, e.g. `0o660`.
+    pub socket_mode: Option<u32>,
+    /// Serve the web dashboard and its HTTP API.
+    pub web_ui: bool,
+    /// Address the web dashboard listens on.
+    pub web_listen: SocketAddr,
+    /// File holding the token dashboard clients must present. Required
+    /// when `web_listen` isn't a loopback address.
+    pub web_auth_token_file: Option<PathBuf>,
+    /// Rewrite the dump file whenever the process table changes, as if
+    /// `pm3 save` were run after every change.
+    pub autosave: bool,
+    /// Snapshots of saved state kept by `pm3 save`, oldest removed first.
+    /// Zero turns snapshots off.
+    pub keep_snapshots: usize,
+    /// Exit after this long, e.g. `"30m"`, with no processes running and no
+    /// client requests. Unset keeps the daemon running.
+    pub idle_timeout: Option<String>,
+    /// Most the data dir may hold, e.g. `"5G"`, not counting profiles. Over
+    /// it, rotated logs are removed, oldest first; if that isn't enough,
+    /// process output is dropped instead of logged until flushing or pruning
+    /// logs brings it back under. Unset means no quota.
+    pub disk_quota: Option<String>,
+    /// Globs of env var names, matched ignoring case, whose values are
+    /// hidden in `pm3 info` and, unless `mask_dump` is off, left out of the
+    /// dump file.
+    pub env_mask: Vec<String>,
+    /// Leave `env_mask` values out of the dump file and snapshots (default
+    /// true). Turning it off keeps them, so a resurrect restores them.
+    pub mask_dump: bool,
+}
+
+impl Default for DaemonSettings {
+    fn default() -> Self {
+        Self {
+            spawn_concurrency: DEFAULT_SPAWN_CONCURRENCY,
+            log_level: LogLevel::default(),
+            allowed_uids: Vec::new(),
+            allowed_gids: Vec::new(),
+            socket_mode: None,
+            web_ui: false,
+            web_listen: DEFAULT_WEB_LISTEN,
+            web_auth_token_file: None,
+            autosave: false,
+            keep_snapshots: DEFAULT_KEEP_SNAPSHOTS,
+            idle_timeout: None,
+            disk_quota: None,
+            env_mask: DEFAULT_ENV_MASK.map(String::from).to_vec(),
+            mask_dump: true,
+        }
+    }
+}
+
+impl DaemonSettings {
+    /// Whether a client connecting with `peer` credentials may send requests.
+    /// The daemon's own user is always allowed so it can't lock itself out.
+    pub fn authorizes(&self, peer: Option<PeerCredentials>, own_uid: Option<u32>) -> bool {
+        if self.allowed_uids.is_empty() && self.allowed_gids.is_empty() {
+            return true;
+        }
+        let Some(peer) = peer else {
+            return false;
+        };
+        Some(peer.uid) == own_uid
+            || self.allowed_uids.contains(&peer.uid)
+            || self.allowed_gids.contains(&peer.gid)
+    }
+
+    pub fn idle_timeout(&self) -> Result<Option<Duration>, SettingsError> {
+        self.idle_timeout
+            .as_deref()
+            .map(config::parse_duration)
+            .transpose()
+            .map_err(SettingsError::InvalidIdleTimeout)
+    }
+
+    pub fn disk_quota(&self) -> Result<Option<u64>, SettingsError> {
+        self.disk_quota
+            .as_deref()
+            .map(crate::memory::parse_memory_string)
+            .transpose()
+            .map_err(|e| SettingsError::InvalidDiskQuota(e.to_string()))
+    }
+
+    /// Whether the value of the env var `key` is hidden by `env_mask`.
+    pub fn masks_env(&self, key: &str) -> bool {
+        let key = key.to_ascii_uppercase();
+        self.env_mask
+            .iter()
+            .any(|pattern| crate::select::glob_match(&pattern.to_ascii_uppercase(), &key))
+    }
+
+    /// Reads the web dashboard's token.
+    pub fn load_web_token(&self) -> Result<String, SettingsError> {
+        let path = self
+            .web_auth_token_file
+            .as_ref()
+            .ok_or(SettingsError::MissingWebAuthToken)?;
+        read_token(path)
+    }
+}
+
+/// The `[remote]` section: an optional TCP listener for managing the daemon
+/// from another machine.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteSettings {
+    /// Address to accept remote clients on, e.g. `0.0.0.0:7878`.
+    pub listen: Option<SocketAddr>,
+    /// File holding the token remote clients must present.
+    pub auth_token_file: Option<PathBuf>,
+    /// PEM certificate chain served to remote clients; enables TLS.
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key for `tls_cert`.
+    pub tls_key: Option<PathBuf>,
+    /// PEM CA certificates that remote clients' certificates must chain to.
+    pub tls_client_ca: Option<PathBuf>,
+}
+
+impl RemoteSettings {
+    /// Checks that a listener would authenticate its clients, one way or
+    /// another, and that the TLS files come as a usable set.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.tls_cert.is_some() != self.tls_key.is_some() {
+            return Err(SettingsError::IncompleteTls(
+                "tls_cert and tls_key must be set together",
+            ));
+        }
+        if self.tls_client_ca.is_some() && self.tls_cert.is_none() {
+            return Err(SettingsError::IncompleteTls(
+                "tls_client_ca requires tls_cert and tls_key",
+            ));
+        }
+        if self.listen.is_some() && self.auth_token_file.is_none() && self.tls_client_ca.is_none() {
+            return Err(SettingsError::MissingAuthToken);
+        }
+        Ok(())
+    }
+
+    /// Reads the auth token. Surrounding whitespace is ignored so the file
+    /// can end with a newline.
+    pub fn load_token(&self) -> Result<String, SettingsError> {
+        let path = self
+            .auth_token_file
+            .as_ref()
+            .ok_or(SettingsError::MissingAuthToken)?;
+        read_token(path)
+    }
+}
+
+/// The `[grpc]` section: an optional gRPC server mirroring the line protocol,
+/// for tooling that prefers protobuf.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GrpcSettings {
+    /// Address to serve gRPC on, e.g. `127.0.0.1:7879`.
+    pub listen: Option<SocketAddr>,
+    /// File holding the bearer token gRPC clients must present.
+    pub auth_token_file: Option<PathBuf>,
+}
+
+impl GrpcSettings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.listen.is_some() && self.auth_token_file.is_none() {
+            return Err(SettingsError::MissingGrpcAuthToken);
+        }
+        Ok(())
+    }
+
+    pub fn load_token(&self) -> Result<String, SettingsError> {
+        let path = self
+            .auth_token_file
+            .as_ref()
+            .ok_or(SettingsError::MissingGrpcAuthToken)?;
+        read_token(path)
+    }
+}
+
+fn read_token(path: &Path) -> Result<String, SettingsError> {
+    let token = std::fs::read_to_string(path)
+        .map_err(|e| SettingsError::Io(format!("{}: {}", path.display(), e)))?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(SettingsError::EmptyAuthToken(path.display().to_string()));
+    }
+    Ok(token.to_string())
+}
+
+/// The `[metrics]` section: pushing process metrics to a statsd server.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsSettings {
+    /// `host:port` of a statsd or DogStatsD server; unset disables the push.
+    pub statsd: Option<String>,
+    /// Prepended to every metric name.
+    pub prefix: String,
+    /// How often gauges are pushed, e.g. `"10s"`.
+    pub interval: String,
+    /// Tag metrics with `#process:<name>` instead of putting the process
+    /// name in the metric name.
+    pub dogstatsd: bool,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            statsd: None,
+            prefix: "pm3".to_string(),
+            interval: "10s".to_string(),
+            dogstatsd: false,
+        }
+    }
+}
+
+impl MetricsSettings {
+    pub fn interval(&self) -> Result<Duration, SettingsError> {
+        config::parse_duration(&self.interval).map_err(SettingsError::InvalidMetricsInterval)
+    }
+}
+
+/// The `[telemetry]` section: exporting to an OpenTelemetry collector.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetrySettings {
+    /// Base URL of an OTLP/HTTP receiver, e.g. `http://localhost:4318`;
+    /// unset disables the export.
+    pub endpoint: Option<String>,
+    /// The `service.name` resource attribute.
+    pub service_name: String,
+    /// How often metrics and pending events are exported, e.g. `"10s"`.
+    pub interval: String,
+    /// Extra HTTP headers sent with every export, e.g. an API key.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for TelemetrySettings {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            service_name: "pm3".to_string(),
+            interval: "10s".to_string(),
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+impl TelemetrySettings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if let Some(endpoint) = &self.endpoint
+            && !endpoint.starts_with("http://")
+            && !endpoint.starts_with("https://")
+        {
+            return Err(SettingsError::InvalidTelemetryEndpoint(endpoint.clone()));
+        }
+        self.interval()?;
+        Ok(())
+    }
+
+    pub fn interval(&self) -> Result<Duration, SettingsError> {
+        config::parse_duration(&self.interval).map_err(SettingsError::InvalidTelemetryInterval)
+    }
+}
+
+/// The `[history]` section: the resource samples kept for `pm3 stats` and
+/// the TUI graphs.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistorySettings {
+    /// Record samples at all.
+    pub enabled: bool,
+    /// How often each process is sampled, e.g. `"10s"`.
+    pub interval: String,
+    /// How long samples are kept, e.g. `"12h"`.
+    pub retention: String,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: "10s".to_string(),
+            retention: "12h".to_string(),
+        }
+    }
+}
+
+impl HistorySettings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        self.interval()?;
+        self.retention()?;
+        Ok(())
+    }
+
+    pub fn interval(&self) -> Result<Duration, SettingsError> {
+        config::parse_duration(&self.interval).map_err(SettingsError::InvalidHistoryInterval)
+    }
+
+    pub fn retention(&self) -> Result<Duration, SettingsError> {
+        config::parse_duration(&self.retention).map_err(SettingsError::InvalidHistoryRetention)
+    }
+
+    /// How many samples of one process fit in the retention period.
+    pub fn capacity(&self) -> usize {
+        let interval = self.interval().unwrap_or(Duration::from_secs(10));
+        let retention = self
+            .retention()
+            .unwrap_or(Duration::from_secs(12 * 60 * 60));
+        (retention.as_millis() / interval.as_millis().max(1)).max(1) as usize
+    }
+}
+
+/// A `[[notify]]` table: where to send a message when a process needs
+/// attention, and for which processes and events.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifierSettings {
+    pub kind: NotifierKind,
+    /// Incoming webhook URL for Slack and Discord.
+    pub webhook_url: Option<String>,
+    /// The events to notify about; all of them by default.
+    pub on: Vec<NotifyEvent>,
+    /// Only notify about these processes; every process when empty.
+    pub processes: Vec<String>,
+    /// Minimum time between two messages about the same process and event.
+    pub rate_limit: String,
+    /// The message, with `{name}`, `{event}`, `{status}`, `{detail}`,
+    /// `{timestamp}`, `{host}` and `{suppressed}` filled in.
+    pub template: Option<String>,
+    /// Email subject, templated like `template`.
+    pub subject: Option<String>,
+    pub smtp_host: Option<String>,
+    /// Defaults to the usual port for `smtp_tls`.
+    pub smtp_port: Option<u16>,
+    pub smtp_tls: SmtpTls,
+    pub smtp_username: Option<String>,
+    /// File holding the SMTP password for `smtp_username`.
+    pub smtp_password_file: Option<PathBuf>,
+    pub from: Option<String>,
+    pub to: Vec<String>,
+}
+
+impl Default for NotifierSettings {
+    fn default() -> Self {
+        Self {
+            kind: NotifierKind::Slack,
+            webhook_url: None,
+            on: vec![
+                NotifyEvent::Errored,
+                NotifyEvent::RestartLimit,
+                NotifyEvent::MemoryLimit,
+            ],
+            processes: Vec::new(),
+            rate_limit: "5m".to_string(),
+            template: None,
+            subject: None,
+            smtp_host: None,
+            smtp_port: None,
+            smtp_tls: SmtpTls::Starttls,
+            smtp_username: None,
+            smtp_password_file: None,
+            from: None,
+            to: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    Slack,
+    Discord,
+    Email,
+}
+
+impl std::fmt::Display for NotifierKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            NotifierKind::Slack => "slack",
+            NotifierKind::Discord => "discord",
+            NotifierKind::Email => "email",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// What a notifier can be told about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    /// The process crashed and is now errored.
+    Errored,
+    /// The process crashed with its `max_restarts` used up.
+    RestartLimit,
+    /// The process went over `max_memory`.
+    MemoryLimit,
+    /// The process's memory is growing toward `max_memory`.
+    MemoryLeak,
+}
+
+impl std::fmt::Display for NotifyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            NotifyEvent::Errored => "errored",
+            NotifyEvent::RestartLimit => "restart_limit",
+            NotifyEvent::MemoryLimit => "memory_limit",
+            NotifyEvent::MemoryLeak => "memory_leak",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// How the SMTP connection is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Upgrade a plain connection, usually on port 587.
+    Starttls,
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// No encryption, for a relay on the same host or network.
+    None,
+}
+
+impl NotifierSettings {
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        let missing = |field| SettingsError::IncompleteNotifier(self.kind, field);
+        match self.kind {
+            NotifierKind::Slack | NotifierKind::Discord => {
+                let url = self.webhook_url.as_deref().ok_or(missing("webhook_url"))?;
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(SettingsError::InvalidWebhookUrl(url.to_string()));
+                }
+            }
+            NotifierKind::Email => {
+                self.smtp_host.as_ref().ok_or(missing("smtp_host"))?;
+                self.from.as_ref().ok_or(missing("from"))?;
+                if self.to.is_empty() {
+                    return Err(missing("to"));
+                }
+                if self.smtp_password_file.is_some() && self.smtp_username.is_none() {
+                    return Err(missing("smtp_username"));
+                }
+            }
+        }
+        self.rate_limit()?;
+        Ok(())
+    }
+
+    pub fn rate_limit(&self) -> Result<Duration, SettingsError> {
+        config::parse_duration(&self.rate_limit).map_err(SettingsError::InvalidNotifyRateLimit)
+    }
+
+    /// Reads the SMTP password, trimmed like the auth tokens.
+    pub fn load_smtp_password(&self) -> Result<Option<String>, SettingsError> {
+        self.smtp_password_file
+            .as_deref()
+            .map(read_token)
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum SettingsError {
+    #[error("{0}")]
+    Io(String),
+    #[error("invalid settings file: {0}")]
+    Parse(String),
+    #[error("spawn_concurrency must be at least 1")]
+    ZeroConcurrency,
+    #[error("socket_mode {0:#o} is not a valid permission mode")]
+    InvalidSocketMode(u32),
+    #[error("remote listen requires auth_token_file or tls_client_ca")]
+    MissingAuthToken,
+    #[error("grpc listen requires auth_token_file")]
+    MissingGrpcAuthToken,
+    #[error("web_ui requires web_auth_token_file")]
+    MissingWebAuthToken,
+    #[error("{0}")]
+    IncompleteTls(&'static str),
+    #[error("invalid idle_timeout: {0}")]
+    InvalidIdleTimeout(String),
+    #[error("invalid disk_quota: {0}")]
+    InvalidDiskQuota(String),
+    #[error("invalid metrics interval: {0}")]
+    InvalidMetricsInterval(String),
+    #[error("telemetry endpoint {0:?} must be an http:// or https:// URL")]
+    InvalidTelemetryEndpoint(String),
+    #[error("invalid telemetry interval: {0}")]
+    InvalidTelemetryInterval(String),
+    #[error("invalid history interval: {0}")]
+    InvalidHistoryInterval(String),
+    #[error("invalid history retention: {0}")]
+    InvalidHistoryRetention(String),
+    #[error("{0} notifier requires {1}")]
+    IncompleteNotifier(NotifierKind, &'static str),
+    #[error("webhook_url {0:?} must be an http:// or https:// URL")]
+    InvalidWebhookUrl(String),
+    #[error("invalid notify rate_limit: {0}")]
+    InvalidNotifyRateLimit(String),
+    #[error("auth token file {0} is empty")]
+    EmptyAuthToken(String),
+}
+
+/// Loads the daemon settings, falling back to defaults when the file is missing.
+pub fn load(paths: &Paths) -> Result<Settings, SettingsError> {
+    let path = paths.settings_file();
+    match std::fs::read_to_string(&path) {
+        Ok(content) => parse(&content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(e) => Err(SettingsError::Io(format!("{}: {}", path.display(), e))),
+    }
+}
+
+pub fn parse(content: &str) -> Result<Settings, SettingsError> {
+    let settings: Settings =
+        toml::from_str(content).map_err(|e| SettingsError::Parse(e.to_string()))?;
+    if settings.daemon.spawn_concurrency == 0 {
+        return Err(SettingsError::ZeroConcurrency);
+    }
+    if let Some(mode) = settings.daemon.socket_mode.filter(|m| *m > 0o777) {
+        return Err(SettingsError::InvalidSocketMode(mode));
+    }
+    let daemon = &settings.daemon;
+    // Any local user can reach even a loopback port, so without a token the
+    // dashboard would get around the socket's permissions and uid/gid check
+    if daemon.web_ui && daemon.web_auth_token_file.is_none() {
+        return Err(SettingsError::MissingWebAuthToken);
+    }
+    daemon.idle_timeout()?;
+    daemon.disk_quota()?;
+    settings.remote.validate()?;
+    settings.grpc.validate()?;
+    settings.metrics.interval()?;
+    settings.telemetry.validate()?;
+    settings.history.validate()?;
+    for notifier in &settings.notify {
+        notifier.validate()?;
+    }
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_when_empty() {
+        let settings = parse("").unwrap();
+        assert_eq!(settings, Settings::default());
+        assert_eq!(settings.daemon.spawn_concurrency, DEFAULT_SPAWN_CONCURRENCY);
+    }
+
+    #[test]
+    fn test_spawn_concurrency() {
+        let settings = parse("[daemon]\nspawn_concurrency = 2\n").unwrap();
+        assert_eq!(settings.daemon.spawn_concurrency, 2);
+        assert_eq!(
+            parse("[daemon]\nspawn_concurrency = 0\n"),
+            Err(SettingsError::ZeroConcurrency)
+        );
+    }
+
+    #[test]
+    fn test_log_level() {
+        assert_eq!(parse("").unwrap().daemon.log_level, LogLevel::Info);
+        let settings = parse("[daemon]\nlog_level = \"debug\"\n").unwrap();
+        assert_eq!(settings.daemon.log_level, LogLevel::Debug);
+        assert!(matches!(
+            parse("[daemon]\nlog_level = \"loud\"\n"),
+            Err(SettingsError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_env_mask() {
+        let daemon = parse("").unwrap().daemon;
+        assert!(daemon.masks_env("STRIPE_API_KEY"));
+        assert!(daemon.masks_env("github_token"));
+        assert!(daemon.masks_env("PASSWORD"));
+        assert!(daemon.masks_env("DB_PASSWORD_FILE"));
+        assert!(!daemon.masks_env("PORT"));
+        assert!(!daemon.masks_env("KEYBOARD_LAYOUT"));
+
+        let daemon = parse("[daemon]\nenv_mask = [\"DATABASE_URL\"]\n")
+            .unwrap()
+            .daemon;
+        assert!(daemon.masks_env("DATABASE_URL"));
+        assert!(!daemon.masks_env("API_KEY"));
+        assert!(daemon.mask_dump);
+        assert!(
+            !parse("[daemon]\nmask_dump = false\n")
+                .unwrap()
+                .daemon
+                .mask_dump
+        );
+        assert!(
+            !parse("[daemon]\nenv_mask = []\n")
+                .unwrap()
+                .daemon
+                .masks_env("API_KEY")
+        );
+    }
+
+    #[test]
+    fn test_socket_mode() {
+        assert_eq!(parse("").unwrap().daemon.socket_mode, None);
+        let settings = parse("[daemon]\nsocket_mode = 0o660\n").unwrap();
+        assert_eq!(settings.daemon.socket_mode, Some(0o660));
+        assert_eq!(
+            parse("[daemon]\nsocket_mode = 0o7777\n"),
+            Err(SettingsError::InvalidSocketMode(0o7777))
+        );
+    }
+
+    #[test]
+    fn test_authorizes_peers() {
+        let peer = |uid, gid| {
+            Some(PeerCredentials {
+                uid,
+                gid,
+                pid: None,
+            })
+        };
+        let open = DaemonSettings::default();
+        assert!(open.authorizes(peer(2000, 2000), Some(1000)));
+        assert!(open.authorizes(None, Some(1000)));
+
+        let settings = parse("[daemon]\nallowed_uids = [1001]\nallowed_gids = [50]\n").unwrap();
+        let daemon = settings.daemon;
+        assert!(daemon.authorizes(peer(1000, 1000), Some(1000)));
+        assert!(daemon.authorizes(peer(1001, 1001), Some(1000)));
+        assert!(daemon.authorizes(peer(1002, 50), Some(1000)));
+        assert!(!daemon.authorizes(peer(1002, 1002), Some(1000)));
+        assert!(!daemon.authorizes(None, Some(1000)));
+    }
+
+    #[test]
+    fn test_remote_requires_token() {
+        assert_eq!(parse("").unwrap().remote, RemoteSettings::default());
+        assert_eq!(
+            parse("[remote]\nlisten = \"0.0.0.0:7878\"\n"),
+            Err(SettingsError::MissingAuthToken)
+        );
+        assert!(matches!(
+            parse("[remote]\nlisten = \"not an address\"\n"),
+            Err(SettingsError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_remote_tls_settings() {
+        let settings = parse(
+            "[remote]\nlisten = \"0.0.0.0:7878\"\ntls_cert = \"cert.pem\"\ntls_key = \"key.pem\"\ntls_client_ca = \"ca.pem\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            settings.remote.tls_client_ca.as_deref(),
+            Some(std::path::Path::new("ca.pem"))
+        );
+        assert!(matches!(
+            parse("[remote]\ntls_cert = \"cert.pem\"\n"),
+            Err(SettingsError::IncompleteTls(_))
+        ));
+        assert!(matches!(
+            parse("[remote]\ntls_client_ca = \"ca.pem\"\n"),
+            Err(SettingsError::IncompleteTls(_))
+        ));
+        // TLS without client certificates still needs a token
+        assert_eq!(
+            parse(
+                "[remote]\nlisten = \"0.0.0.0:7878\"\ntls_cert = \"cert.pem\"\ntls_key = \"key.pem\"\n"
+            ),
+            Err(SettingsError::MissingAuthToken)
+        );
+    }
+
+    #[test]
+    fn test_load_token_trims() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "s3cret\n").unwrap();
+        let remote = parse(&format!(
+            "[remote]\nlisten = \"127.0.0.1:7878\"\nauth_token_file = {:?}\n",
+            path.display().to_string()
+        ))
+        .unwrap()
+        .remote;
+        assert_eq!(remote.load_token().unwrap(), "s3cret");
+
+        std::fs::write(&path, "  \n").unwrap();
+        assert!(matches!(
+            remote.load_token(),
+            Err(SettingsError::EmptyAuthToken(_))
+        ));
+    }
+
+    #[test]
+    fn test_web_ui() {
+        let daemon = parse("").unwrap().daemon;
+        assert!(!daemon.web_ui);
+        assert_eq!(daemon.web_listen, DEFAULT_WEB_LISTEN);
+
+        let daemon = parse(
+            "[daemon]\nweb_ui = true\nweb_listen = \"127.0.0.1:9000\"\nweb_auth_token_file = \"token\"\n",
+        )
+        .unwrap()
+        .daemon;
+        assert!(daemon.web_ui);
+        assert_eq!(daemon.web_listen.port(), 9000);
+        // Loopback or not, every local user could reach a token-less dashboard
+        for settings in [
+            "[daemon]\nweb_ui = true\n",
+            "[daemon]\nweb_ui = true\nweb_listen = \"0.0.0.0:7880\"\n",
+            "[daemon]\nweb_ui = true\nallowed_uids = [1001]\n",
+        ] {
+            assert_eq!(parse(settings), Err(SettingsError::MissingWebAuthToken));
+        }
+        assert!(
+            parse("[daemon]\nweb_ui = true\nweb_listen = \"0.0.0.0:7880\"\nweb_auth_token_file = \"token\"\n")
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_autosave() {
+        assert!(!parse("").unwrap().daemon.autosave);
+        assert!(
+            parse("[daemon]\nautosave = true\n")
+                .unwrap()
+                .daemon
+                .autosave
+        );
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        assert_eq!(parse("").unwrap().daemon.idle_timeout(), Ok(None));
+        assert_eq!(
+            parse("[daemon]\nidle_timeout = \"30m\"\n")
+                .unwrap()
+                .daemon
+                .idle_timeout(),
+            Ok(Some(Duration::from_secs(30 * 60)))
+        );
+        assert!(matches!(
+            parse("[daemon]\nidle_timeout = \"soon\"\n"),
+            Err(SettingsError::InvalidIdleTimeout(_))
+        ));
+    }
+
+    #[test]
+    fn test_disk_quota() {
+        assert_eq!(parse("").unwrap().daemon.disk_quota(), Ok(None));
+        assert_eq!(
+            parse("[daemon]\ndisk_quota = \"2G\"\n")
+                .unwrap()
+                .daemon
+                .disk_quota(),
+            Ok(Some(2 * 1024 * 1024 * 1024))
+        );
+        assert!(matches!(
+            parse("[daemon]\ndisk_quota = \"lots\"\n"),
+            Err(SettingsError::InvalidDiskQuota(_))
+        ));
+    }
+
+    #[test]
+    fn test_metrics_settings() {
+        let metrics = parse("").unwrap().metrics;
+        assert_eq!(metrics.statsd, None);
+        assert_eq!(metrics.interval().unwrap(), Duration::from_secs(10));
+
+        let metrics = parse(
+            "[metrics]\nstatsd = \"127.0.0.1:8125\"\ninterval = \"500ms\"\ndogstatsd = true\n",
+        )
+        .unwrap()
+        .metrics;
+        assert_eq!(metrics.statsd.as_deref(), Some("127.0.0.1:8125"));
+        assert_eq!(metrics.interval().unwrap(), Duration::from_millis(500));
+        assert!(metrics.dogstatsd);
+        assert!(matches!(
+            parse("[metrics]\ninterval = \"soon\"\n"),
+            Err(SettingsError::InvalidMetricsInterval(_))
+        ));
+    }
+
+    #[test]
+    fn test_telemetry_settings() {
+        let telemetry = parse("").unwrap().telemetry;
+        assert_eq!(telemetry.endpoint, None);
+        assert_eq!(telemetry.service_name, "pm3");
+
+        let telemetry = parse(
+            "[telemetry]\nendpoint = \"http://localhost:4318\"\nheaders = { x-api-key = \"k\" }\n",
+        )
+        .unwrap()
+        .telemetry;
+        assert_eq!(telemetry.endpoint.as_deref(), Some("http://localhost:4318"));
+        assert_eq!(telemetry.headers["x-api-key"], "k");
+        assert!(matches!(
+            parse("[telemetry]\nendpoint = \"localhost:4318\"\n"),
+            Err(SettingsError::InvalidTelemetryEndpoint(_))
+        ));
+    }
+
+    #[test]
+    fn test_history_settings() {
+        let history = parse("").unwrap().history;
+        assert!(history.enabled);
+        assert_eq!(history.capacity(), 12 * 60 * 6);
+
+        let history = parse("[history]\ninterval = \"30s\"\nretention = \"1d\"\n")
+            .unwrap()
+            .history;
+        assert_eq!(history.capacity(), 24 * 60 * 2);
+        assert!(matches!(
+            parse("[history]\nretention = \"forever\"\n"),
+            Err(SettingsError::InvalidHistoryRetention(_))
+        ));
+    }
+
+    #[test]
+    fn test_grpc_requires_token() {
+        assert_eq!(parse("").unwrap().grpc, GrpcSettings::default());
+        assert_eq!(
+            parse("[grpc]\nlisten = \"127.0.0.1:7879\"\n"),
+            Err(SettingsError::MissingGrpcAuthToken)
+        );
+        let settings =
+            parse("[grpc]\nlisten = \"127.0.0.1:7879\"\nauth_token_file = \"token\"\n").unwrap();
+        assert_eq!(
+            settings.grpc.listen,
+            Some("127.0.0.1:7879".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_unknown_key_rejected() {
+        assert!(matches!(
+            parse("[daemon]\nspawn_concurency = 2\n"),
+            Err(SettingsError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_missing_file_is_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().to_path_buf());
+        assert_eq!(load(&paths).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn test_notifiers() {
+        assert!(parse("").unwrap().notify.is_empty());
+        let settings = parse(
+            "[[notify]]\nkind = \"slack\"\nwebhook_url = \"https://hooks.slack.com/x\"\non = [\"errored\"]\nprocesses = [\"web\"]\n\n[[notify]]\nkind = \"email\"\nsmtp_host = \"mail.example.com\"\nfrom = \"pm3@example.com\"\nto = [\"ops@example.com\"]\n",
+        )
+        .unwrap();
+        assert_eq!(settings.notify.len(), 2);
+        assert_eq!(settings.notify[0].on, vec![NotifyEvent::Errored]);
+        assert_eq!(settings.notify[1].smtp_tls, SmtpTls::Starttls);
+        assert_eq!(
+            settings.notify[1].rate_limit().unwrap(),
+            Duration::from_secs(300)
+        );
+
+        assert_eq!(
+            parse("[[notify]]\nkind = \"discord\"\n"),
+            Err(SettingsError::IncompleteNotifier(
+                NotifierKind::Discord,
+                "webhook_url"
+            ))
+        );
+        assert_eq!(
+            parse("[[notify]]\nkind = \"email\"\nsmtp_host = \"h\"\nfrom = \"a@b\"\n"),
+            Err(SettingsError::IncompleteNotifier(NotifierKind::Email, "to"))
+        );
+        assert!(matches!(
+            parse(
+                "[[notify]]\nkind = \"slack\"\nwebhook_url = \"https://x\"\nrate_limit = \"often\"\n"
+            ),
+            Err(SettingsError::InvalidNotifyRateLimit(_))
+        ));
+        assert!(matches!(
+            parse(
+                "[[notify]]\nkind = \"slack\"\nwebhook_url = \"https://x\"\non = [\"stopped\"]\n"
+            ),
+            Err(SettingsError::Parse(_))
+        ));
+    }
+}
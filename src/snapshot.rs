@@ -0,0 +1,208 @@
+use crate::dump;
+use crate::paths::Paths;
+use crate::protocol::SnapshotInfo;
+use std::io;
+use std::path::PathBuf;
+
+/// Format of snapshot ids: when the snapshot was taken, in UTC, sortable.
+const ID_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// Longest tag accepted, so file names stay reasonable.
+const MAX_TAG_LEN: usize = 64;
+
+/// The snapshot id for `time`.
+pub fn id(time: chrono::DateTime<chrono::Utc>) -> String {
+    time.format(ID_FORMAT).to_string()
+}
+
+/// Tags become part of a file name, so only letters, digits, `-`, `_` and
+/// `.` are allowed, not leading with a `.`.
+pub fn validate_tag(tag: &str) -> Result<(), String> {
+    let valid = !tag.is_empty()
+        && tag.len() <= MAX_TAG_LEN
+        && !tag.starts_with('.')
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid snapshot tag '{tag}': use up to {MAX_TAG_LEN} letters, digits, '-', '_' or '.'"
+        ))
+    }
+}
+
+fn file_name(id: &str, tag: Option<&str>) -> String {
+    match tag {
+        Some(tag) => format!("{id}-{tag}.json"),
+        None => format!("{id}.json"),
+    }
+}
+
+/// The id and tag of a snapshot file, or `None` for anything else.
+fn parse_file_name(name: &str) -> Option<(String, Option<String>, i64)> {
+    let stem = name.strip_suffix(".json")?;
+    let id_len = id(chrono::DateTime::UNIX_EPOCH).len();
+    let id = stem.get(..id_len)?;
+    let created = chrono::NaiveDateTime::parse_from_str(id, ID_FORMAT)
+        .ok()?
+        .and_utc()
+        .timestamp();
+    let tag = match &stem[id_len..] {
+        "" => None,
+        rest => Some(rest.strip_prefix('-')?.to_string()),
+    };
+    Some((id.to_string(), tag, created))
+}
+
+/// Every kept snapshot with its file, oldest first.
+pub async fn list(paths: &Paths) -> io::Result<Vec<(SnapshotInfo, PathBuf)>> {
+    let mut dir = match tokio::fs::read_dir(paths.snapshots_dir()).await {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut snapshots = Vec::new();
+    while let Some(entry) = dir.next_entry().await? {
+        let Some((id, tag, created)) = entry.file_name().to_str().and_then(parse_file_name) else {
+            continue;
+        };
+        let path = entry.path();
+        // A snapshot that can't be read still shows up, as empty
+        let processes = tokio::fs::read_to_string(&path)
+            .await
+            .ok()
+            .and_then(|data| dump::parse(&data).ok())
+            .map_or(0, |(entries, _)| entries.len());
+        let info = SnapshotInfo {
+            id,
+            tag,
+            created,
+            processes,
+        };
+        snapshots.push((info, path));
+    }
+    snapshots.sort_by(|(a, _), (b, _)| a.id.cmp(&b.id));
+    Ok(snapshots)
+}
+
+/// The snapshot with tag `key`, or else with id `key`. The newest wins if
+/// several match.
+pub async fn find(paths: &Paths, key: &str) -> io::Result<Option<PathBuf>> {
+    let snapshots = list(paths).await?;
+    let by_tag = snapshots
+        .iter()
+        .rev()
+        .find(|(info, _)| info.tag.as_deref() == Some(key));
+    let by_id = || snapshots.iter().find(|(info, _)| info.id == key);
+    Ok(by_tag.or_else(by_id).map(|(_, path)| path.clone()))
+}
+
+/// Writes `json` as a new snapshot, replacing any with the same tag, then
+/// removes the oldest beyond `keep`.
+pub async fn write(
+    paths: &Paths,
+    json: &str,
+    tag: Option<&str>,
+    keep: usize,
+) -> io::Result<String> {
+    let dir = paths.snapshots_dir();
+    tokio::fs::create_dir_all(&dir).await?;
+    let mut existing = list(paths).await?;
+    if let Some(tag) = tag {
+        for (_, path) in existing.extract_if(.., |(info, _)| info.tag.as_deref() == Some(tag)) {
+            tokio::fs::remove_file(path).await?;
+        }
+    }
+
+    let mut id = id(chrono::Utc::now());
+    // Two saves within a millisecond would otherwise share a file
+    if existing.last().is_some_and(|(info, _)| info.id >= id) {
+        let (last, _) = existing.last().expect("checked above");
+        let next = chrono::NaiveDateTime::parse_from_str(&last.id, ID_FORMAT)
+            .map_err(io::Error::other)?
+            .and_utc()
+            + chrono::Duration::milliseconds(1);
+        id = self::id(next);
+    }
+    let path = dir.join(file_name(&id, tag));
+    let tmp = path.with_extension("json.tmp");
+    crate::dump::write_private(&tmp, json).await?;
+    tokio::fs::rename(&tmp, &path).await?;
+
+    let excess = (existing.len() + 1).saturating_sub(keep);
+    for (_, path) in existing.into_iter().take(excess) {
+        tokio::fs::remove_file(path).await?;
+    }
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_names() {
+        let time = chrono::DateTime::parse_from_rfc3339("2025-01-02T03:04:05.678Z")
+            .unwrap()
+            .to_utc();
+        let id = id(time);
+        assert_eq!(id, "20250102T030405678Z");
+        assert_eq!(
+            parse_file_name(&file_name(&id, Some("pre-deploy.v2"))),
+            Some((id.clone(), Some("pre-deploy.v2".to_string()), 1_735_787_045))
+        );
+        assert_eq!(
+            parse_file_name(&file_name(&id, None)),
+            Some((id, None, 1_735_787_045))
+        );
+        assert_eq!(parse_file_name("notes.json"), None);
+        assert_eq!(parse_file_name("20250102T030405678Zx.json"), None);
+    }
+
+    #[test]
+    fn test_validate_tag() {
+        assert!(validate_tag("pre-deploy_2.1").is_ok());
+        assert!(validate_tag("").is_err());
+        assert!(validate_tag(".hidden").is_err());
+        assert!(validate_tag("../escape").is_err());
+        assert!(validate_tag(&"x".repeat(MAX_TAG_LEN + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_replaces_tags_and_prunes() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().to_path_buf());
+        let entry = r#"{"name":"web","config":{"command":"sleep 1"}}"#;
+        let first = write(&paths, &format!("[{entry}]"), Some("good"), 3)
+            .await
+            .unwrap();
+        write(&paths, "[]", None, 3).await.unwrap();
+        let retagged = write(&paths, &format!("[{entry},{entry}]"), Some("good"), 3)
+            .await
+            .unwrap();
+        assert!(retagged > first);
+
+        let snapshots = list(&paths).await.unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1].0.tag.as_deref(), Some("good"));
+        assert_eq!(snapshots[1].0.processes, 2);
+        assert_eq!(
+            find(&paths, "good").await.unwrap(),
+            Some(snapshots[1].1.clone())
+        );
+        assert_eq!(
+            find(&paths, &snapshots[0].0.id).await.unwrap(),
+            Some(snapshots[0].1.clone())
+        );
+        assert_eq!(find(&paths, "bad").await.unwrap(), None);
+
+        for _ in 0..3 {
+            write(&paths, "[]", None, 3).await.unwrap();
+        }
+        let snapshots = list(&paths).await.unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert!(snapshots.iter().all(|(info, _)| info.tag.is_none()));
+    }
+}
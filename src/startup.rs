@@ -1,34 +1,212 @@
 use color_eyre::eyre::{WrapErr, bail};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// The service manager `pm3 startup` installs the daemon into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// A LaunchAgent on macOS.
+    Launchd,
+    /// A systemd user service.
+    Systemd,
+    /// An OpenRC init script, as on Alpine.
+    Openrc,
+    /// An LSB init script for SysV init.
+    Sysv,
+}
+
+impl std::str::FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "launchd" => Ok(Platform::Launchd),
+            "systemd" => Ok(Platform::Systemd),
+            "openrc" => Ok(Platform::Openrc),
+            "sysv" => Ok(Platform::Sysv),
+            other => Err(format!(
+                "unknown platform '{other}' (expected launchd, systemd, openrc or sysv)"
+            )),
+        }
+    }
+}
+
+impl Platform {
+    /// The service manager this machine boots with.
+    pub fn detect() -> color_eyre::Result<Self> {
+        if cfg!(target_os = "macos") {
+            return Ok(Platform::Launchd);
+        }
+        if !cfg!(target_os = "linux") {
+            bail!("startup/unstartup is not supported on this platform");
+        }
+        if Path::new("/run/systemd/system").is_dir() {
+            Ok(Platform::Systemd)
+        } else if Path::new("/run/openrc").is_dir() || Path::new("/sbin/openrc-run").exists() {
+            Ok(Platform::Openrc)
+        } else if Path::new(INIT_D).is_dir() {
+            Ok(Platform::Sysv)
+        } else {
+            bail!("could not detect the init system; pass --platform");
+        }
+    }
+
+    fn service_file(self) -> color_eyre::Result<PathBuf> {
+        let user_dir = |relative: &str| match dirs::home_dir() {
+            Some(home) => Ok(home.join(relative)),
+            None => bail!("could not determine home directory"),
+        };
+        match self {
+            Platform::Launchd => user_dir("Library/LaunchAgents/com.pm3.daemon.plist"),
+            Platform::Systemd => user_dir(".config/systemd/user/pm3.service"),
+            Platform::Openrc | Platform::Sysv => Ok(Path::new(INIT_D).join("pm3")),
+        }
+    }
+
+    fn service_content(self, exe_path: &str) -> color_eyre::Result<String> {
+        Ok(match self {
+            Platform::Launchd => generate_launchd_plist(exe_path),
+            Platform::Systemd => generate_systemd_unit(exe_path),
+            Platform::Openrc => {
+                let (user, home) = service_account()?;
+                generate_openrc_script(exe_path, &user, &home.to_string_lossy())
+            }
+            Platform::Sysv => {
+                let (user, home) = service_account()?;
+                generate_sysv_script(exe_path, &user, &home.to_string_lossy())
+            }
+        })
+    }
 
-pub fn install() -> color_eyre::Result<()> {
+    fn post_install(self, path: &Path) {
+        match self {
+            Platform::Launchd => {
+                // launchd opens the log files but doesn't create their directory
+                let log_dir = launchd_log_dir();
+                if let Err(e) = std::fs::create_dir_all(&log_dir) {
+                    eprintln!("warning: could not create {}: {e}", log_dir.display());
+                }
+                if run_tool("launchctl", &["load", &path.to_string_lossy()]) {
+                    eprintln!("Service loaded via launchctl");
+                }
+            }
+            Platform::Systemd => {
+                run_tool("systemctl", &["--user", "daemon-reload"]);
+                if run_tool("systemctl", &["--user", "enable", "pm3"]) {
+                    eprintln!("Service enabled via systemctl");
+                }
+            }
+            Platform::Openrc => {
+                if run_tool("rc-update", &["add", "pm3", "default"]) {
+                    eprintln!(
+                        "Service added to the default runlevel; start it with `rc-service pm3 start`"
+                    );
+                }
+            }
+            Platform::Sysv => {
+                let enabled = if has_tool("update-rc.d") {
+                    run_tool("update-rc.d", &["pm3", "defaults"])
+                } else if has_tool("chkconfig") {
+                    run_tool("chkconfig", &["--add", "pm3"])
+                } else {
+                    eprintln!(
+                        "warning: neither update-rc.d nor chkconfig found; link {} into your runlevels by hand",
+                        path.display()
+                    );
+                    false
+                };
+                if enabled {
+                    eprintln!("Service enabled; start it with `{} start`", path.display());
+                }
+            }
+        }
+    }
+
+    fn pre_uninstall(self, path: &Path) {
+        match self {
+            Platform::Launchd => {
+                if run_tool("launchctl", &["unload", &path.to_string_lossy()]) {
+                    eprintln!("Service unloaded via launchctl");
+                }
+            }
+            Platform::Systemd => {
+                if run_tool("systemctl", &["--user", "disable", "pm3"]) {
+                    eprintln!("Service disabled via systemctl");
+                }
+                run_tool("systemctl", &["--user", "daemon-reload"]);
+            }
+            Platform::Openrc => {
+                if run_tool("rc-update", &["del", "pm3", "default"]) {
+                    eprintln!("Service removed from the default runlevel");
+                }
+            }
+            Platform::Sysv => {
+                if has_tool("update-rc.d") {
+                    run_tool("update-rc.d", &["-f", "pm3", "remove"]);
+                } else if has_tool("chkconfig") {
+                    run_tool("chkconfig", &["--del", "pm3"]);
+                }
+            }
+        }
+    }
+}
+
+/// Where OpenRC and SysV init scripts live.
+const INIT_D: &str = "/etc/init.d";
+
+/// Installs the boot service for `platform`, or the detected one.
+pub fn install(platform: Option<Platform>) -> color_eyre::Result<()> {
+    let platform = match platform {
+        Some(platform) => platform,
+        None => Platform::detect()?,
+    };
     let exe = std::env::current_exe().wrap_err("could not determine pm3 executable path")?;
     let exe_path = exe.to_string_lossy();
-    let path = service_file_path()?;
+    let path = platform.service_file()?;
 
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)
             .wrap_err_with(|| format!("could not create directory {}", parent.display()))?;
     }
+    // Unload a service from an earlier install so the new definition applies
+    if path.exists() {
+        platform.pre_uninstall(&path);
+    }
 
-    let content = generate_service_content(&exe_path);
-    std::fs::write(&path, content)
-        .wrap_err_with(|| format!("could not write service file {}", path.display()))?;
+    let content = platform.service_content(&exe_path)?;
+    std::fs::write(&path, content).wrap_err_with(|| {
+        let hint = if path.starts_with(INIT_D) {
+            " (init scripts need root, try sudo)"
+        } else {
+            ""
+        };
+        format!("could not write service file {}{hint}", path.display())
+    })?;
+    #[cfg(unix)]
+    if path.starts_with(INIT_D) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .wrap_err_with(|| format!("could not make {} executable", path.display()))?;
+    }
 
     eprintln!("Service installed: {}", path.display());
-    post_install(&path);
+    platform.post_install(&path);
     Ok(())
 }
 
-pub fn uninstall() -> color_eyre::Result<()> {
-    let path = service_file_path()?;
+/// Removes the boot service for `platform`, or the detected one.
+pub fn uninstall(platform: Option<Platform>) -> color_eyre::Result<()> {
+    let platform = match platform {
+        Some(platform) => platform,
+        None => Platform::detect()?,
+    };
+    let path = platform.service_file()?;
 
     if !path.exists() {
         eprintln!("No service file found at {}", path.display());
         return Ok(());
     }
 
-    pre_uninstall(&path);
+    platform.pre_uninstall(&path);
     std::fs::remove_file(&path)
         .wrap_err_with(|| format!("could not remove service file {}", path.display()))?;
 
@@ -36,118 +214,60 @@ pub fn uninstall() -> color_eyre::Result<()> {
     Ok(())
 }
 
-#[cfg(target_os = "macos")]
-fn service_file_path() -> color_eyre::Result<PathBuf> {
-    let Some(home) = dirs::home_dir() else {
-        bail!("could not determine home directory");
+/// The account a system-wide init script runs the daemon as, and its home:
+/// whoever ran `sudo pm3 startup`, else the current user.
+fn service_account() -> color_eyre::Result<(String, PathBuf)> {
+    let user = std::env::var("SUDO_USER")
+        .ok()
+        .filter(|user| !user.is_empty())
+        .or_else(|| crate::sys::current_uid().and_then(crate::sys::user_name));
+    let Some(user) = user else {
+        bail!("could not determine which user to run the daemon as");
     };
-    Ok(home.join("Library/LaunchAgents/com.pm3.daemon.plist"))
-}
-
-#[cfg(target_os = "linux")]
-fn service_file_path() -> color_eyre::Result<PathBuf> {
-    let Some(home) = dirs::home_dir() else {
-        bail!("could not determine home directory");
+    let Some(home) = crate::sys::user_home(&user) else {
+        bail!("could not determine the home directory of {user}");
     };
-    Ok(home.join(".config/systemd/user/pm3.service"))
-}
-
-#[cfg(not(any(target_os = "macos", target_os = "linux")))]
-fn service_file_path() -> color_eyre::Result<PathBuf> {
-    bail!("startup/unstartup is not supported on this platform");
-}
-
-#[cfg(target_os = "macos")]
-fn generate_service_content(exe_path: &str) -> String {
-    generate_launchd_plist(exe_path)
-}
-
-#[cfg(target_os = "linux")]
-fn generate_service_content(exe_path: &str) -> String {
-    generate_systemd_unit(exe_path)
-}
-
-#[cfg(not(any(target_os = "macos", target_os = "linux")))]
-fn generate_service_content(_exe_path: &str) -> String {
-    String::new()
+    Ok((user, home))
 }
 
-#[cfg(target_os = "macos")]
-fn post_install(path: &std::path::Path) {
-    let status = std::process::Command::new("launchctl")
-        .args(["load", &path.to_string_lossy()])
-        .status();
-    match status {
-        Ok(s) if s.success() => eprintln!("Service loaded via launchctl"),
-        Ok(s) => eprintln!("warning: launchctl load exited with {s}"),
-        Err(e) => eprintln!("warning: could not run launchctl load: {e}"),
-    }
+fn has_tool(program: &str) -> bool {
+    std::env::split_paths(&std::env::var_os("PATH").unwrap_or_default())
+        .chain([PathBuf::from("/sbin"), PathBuf::from("/usr/sbin")])
+        .any(|dir| dir.join(program).is_file())
 }
 
-#[cfg(target_os = "macos")]
-fn pre_uninstall(path: &std::path::Path) {
-    let status = std::process::Command::new("launchctl")
-        .args(["unload", &path.to_string_lossy()])
-        .status();
-    match status {
-        Ok(s) if s.success() => eprintln!("Service unloaded via launchctl"),
-        Ok(s) => eprintln!("warning: launchctl unload exited with {s}"),
-        Err(e) => eprintln!("warning: could not run launchctl unload: {e}"),
+/// Runs a service manager command, warning rather than failing when it
+/// doesn't succeed. Returns whether it did.
+fn run_tool(program: &str, args: &[&str]) -> bool {
+    match std::process::Command::new(program).args(args).status() {
+        Ok(s) if s.success() => true,
+        Ok(s) => {
+            eprintln!("warning: {program} {} exited with {s}", args.join(" "));
+            false
+        }
+        Err(e) => {
+            eprintln!("warning: could not run {program} {}: {e}", args.join(" "));
+            false
+        }
     }
 }
 
-#[cfg(target_os = "linux")]
-fn post_install(_path: &std::path::Path) {
-    let reload = std::process::Command::new("systemctl")
-        .args(["--user", "daemon-reload"])
-        .status();
-    match reload {
-        Ok(s) if s.success() => {}
-        Ok(s) => eprintln!("warning: systemctl daemon-reload exited with {s}"),
-        Err(e) => eprintln!("warning: could not run systemctl daemon-reload: {e}"),
-    }
-
-    let enable = std::process::Command::new("systemctl")
-        .args(["--user", "enable", "pm3"])
-        .status();
-    match enable {
-        Ok(s) if s.success() => eprintln!("Service enabled via systemctl"),
-        Ok(s) => eprintln!("warning: systemctl enable exited with {s}"),
-        Err(e) => eprintln!("warning: could not run systemctl enable: {e}"),
-    }
+/// Where the LaunchAgent sends the daemon's stdout and stderr.
+fn launchd_log_dir() -> PathBuf {
+    dirs::data_dir()
+        .map(|d| d.join("pm3").join("logs"))
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
 }
 
-#[cfg(target_os = "linux")]
-fn pre_uninstall(_path: &std::path::Path) {
-    let disable = std::process::Command::new("systemctl")
-        .args(["--user", "disable", "pm3"])
-        .status();
-    match disable {
-        Ok(s) if s.success() => eprintln!("Service disabled via systemctl"),
-        Ok(s) => eprintln!("warning: systemctl disable exited with {s}"),
-        Err(e) => eprintln!("warning: could not run systemctl disable: {e}"),
-    }
-
-    let reload = std::process::Command::new("systemctl")
-        .args(["--user", "daemon-reload"])
-        .status();
-    match reload {
-        Ok(s) if s.success() => {}
-        Ok(s) => eprintln!("warning: systemctl daemon-reload exited with {s}"),
-        Err(e) => eprintln!("warning: could not run systemctl daemon-reload: {e}"),
-    }
+/// Escapes `&`, `<` and `>` for a plist `<string>`.
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
 
-#[cfg(not(any(target_os = "macos", target_os = "linux")))]
-fn post_install(_path: &std::path::Path) {}
-
-#[cfg(not(any(target_os = "macos", target_os = "linux")))]
-fn pre_uninstall(_path: &std::path::Path) {}
-
 pub fn generate_launchd_plist(exe_path: &str) -> String {
-    let log_dir = dirs::data_dir()
-        .map(|d| d.join("pm3").join("logs"))
-        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    let log_dir = launchd_log_dir();
     let stdout_log = log_dir.join("daemon-out.log");
     let stderr_log = log_dir.join("daemon-err.log");
 
@@ -160,13 +280,19 @@ pub fn generate_launchd_plist(exe_path: &str) -> String {
     <string>com.pm3.daemon</string>
     <key>ProgramArguments</key>
     <array>
-        <string>{exe_path}</string>
-        <string>--daemon</string>
+        <string>{exe}</string>
+        <string>daemon</string>
+        <string>start</string>
+        <string>--foreground</string>
+        <string>--resurrect</string>
     </array>
     <key>RunAtLoad</key>
     <true/>
     <key>KeepAlive</key>
-    <true/>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
     <key>StandardOutPath</key>
     <string>{stdout}</string>
     <key>StandardErrorPath</key>
@@ -174,8 +300,9 @@ pub fn generate_launchd_plist(exe_path: &str) -> String {
 </dict>
 </plist>
 "#,
-        stdout = stdout_log.display(),
-        stderr = stderr_log.display(),
+        exe = xml_escape(exe_path),
+        stdout = xml_escape(&stdout_log.to_string_lossy()),
+        stderr = xml_escape(&stderr_log.to_string_lossy()),
     )
 }
 
@@ -187,7 +314,8 @@ After=network.target
 
 [Service]
 Type=simple
-ExecStart={exe_path} --daemon
+ExecStart={exe_path} daemon start --foreground --resurrect
+ExecStop={exe_path} save --if-running
 Restart=on-failure
 
 [Install]
@@ -196,6 +324,89 @@ WantedBy=default.target
     )
 }
 
+/// An OpenRC service that runs the daemon as `user`. OpenRC backgrounds it
+/// and tracks its pid; stopping saves the process list first.
+pub fn generate_openrc_script(exe_path: &str, user: &str, home: &str) -> String {
+    format!(
+        r#"#!/sbin/openrc-run
+
+name="pm3"
+description="pm3 process manager daemon"
+command="{exe_path}"
+command_args="daemon start --foreground --resurrect"
+command_user="{user}"
+command_background=true
+pidfile="/run/pm3.pid"
+
+export HOME="{home}"
+
+depend() {{
+    need net
+}}
+
+stop_pre() {{
+    su -s /bin/sh -c "\"$command\" save --if-running" "$command_user" || true
+}}
+"#
+    )
+}
+
+/// An LSB init script for SysV init that runs the daemon as `user`. pm3
+/// detaches the daemon and tracks it in its own data dir, so the script
+/// needs no pid file of its own.
+pub fn generate_sysv_script(exe_path: &str, user: &str, home: &str) -> String {
+    format!(
+        r#"#!/bin/sh
+### BEGIN INIT INFO
+# Provides:          pm3
+# Required-Start:    $remote_fs $network
+# Required-Stop:     $remote_fs $network
+# Default-Start:     2 3 4 5
+# Default-Stop:      0 1 6
+# Short-Description: pm3 process manager daemon
+### END INIT INFO
+# chkconfig: 2345 90 10
+# description: pm3 process manager daemon
+
+PM3="{exe_path}"
+PM3_USER="{user}"
+export HOME="{home}"
+
+run_as() {{
+    if [ "$(id -un)" = "$PM3_USER" ]; then
+        sh -c "$1"
+    else
+        su -s /bin/sh -c "$1" "$PM3_USER"
+    fi
+}}
+
+start() {{
+    echo "Starting pm3"
+    run_as "\"$PM3\" daemon start --resurrect"
+}}
+
+stop() {{
+    echo "Stopping pm3"
+    run_as "\"$PM3\" save --if-running"
+    run_as "\"$PM3\" daemon stop"
+}}
+
+case "$1" in
+    start) start ;;
+    stop) stop ;;
+    restart) stop; start ;;
+    status)
+        run_as "\"$PM3\" daemon status"
+        ;;
+    *)
+        echo "Usage: $0 {{start|stop|restart|status}}" >&2
+        exit 2
+        ;;
+esac
+"#
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -215,7 +426,9 @@ mod tests {
     #[test]
     fn test_plist_contains_daemon_arg() {
         let plist = generate_launchd_plist("/usr/local/bin/pm3");
-        assert!(plist.contains("--daemon"));
+        assert!(plist.contains("<string>daemon</string>\n        <string>start</string>"));
+        assert!(plist.contains("<string>--foreground</string>"));
+        assert!(plist.contains("<string>--resurrect</string>"));
     }
 
     #[test]
@@ -229,6 +442,14 @@ mod tests {
     fn test_plist_contains_keep_alive() {
         let plist = generate_launchd_plist("/usr/local/bin/pm3");
         assert!(plist.contains("<key>KeepAlive</key>"));
+        // Restarted after a crash, like Restart=on-failure, but not after `pm3 kill`
+        assert!(plist.contains("<key>SuccessfulExit</key>\n        <false/>"));
+    }
+
+    #[test]
+    fn test_plist_escapes_paths() {
+        let plist = generate_launchd_plist("/Users/a&b/<bin>/pm3");
+        assert!(plist.contains("<string>/Users/a&amp;b/&lt;bin&gt;/pm3</string>"));
     }
 
     #[test]
@@ -253,7 +474,15 @@ mod tests {
     #[test]
     fn test_systemd_contains_exec_start() {
         let unit = generate_systemd_unit("/usr/local/bin/pm3");
-        assert!(unit.contains("ExecStart=/usr/local/bin/pm3 --daemon"));
+        assert!(
+            unit.contains("ExecStart=/usr/local/bin/pm3 daemon start --foreground --resurrect\n")
+        );
+    }
+
+    #[test]
+    fn test_systemd_saves_on_stop() {
+        let unit = generate_systemd_unit("/usr/local/bin/pm3");
+        assert!(unit.contains("ExecStop=/usr/local/bin/pm3 save --if-running\n"));
     }
 
     #[test]
@@ -282,27 +511,71 @@ mod tests {
         assert!(unit.contains("WantedBy=default.target"));
     }
 
-    #[cfg(target_os = "macos")]
     #[test]
-    fn test_service_file_path_macos() {
-        let path = service_file_path().unwrap();
+    fn test_openrc_runs_daemon_as_user() {
+        let script = generate_openrc_script("/usr/local/bin/pm3", "alice", "/home/alice");
+        assert!(script.starts_with("#!/sbin/openrc-run\n"));
+        assert!(script.contains("command=\"/usr/local/bin/pm3\"\n"));
+        assert!(script.contains("command_args=\"daemon start --foreground --resurrect\"\n"));
+        assert!(script.contains("command_user=\"alice\"\n"));
+        assert!(script.contains("export HOME=\"/home/alice\"\n"));
+    }
+
+    #[test]
+    fn test_openrc_saves_on_stop() {
+        let script = generate_openrc_script("/usr/local/bin/pm3", "alice", "/home/alice");
+        assert!(script.contains("stop_pre() {"));
+        assert!(script.contains("save --if-running"));
+    }
+
+    #[test]
+    fn test_sysv_has_lsb_header() {
+        let script = generate_sysv_script("/usr/local/bin/pm3", "alice", "/home/alice");
+        assert!(script.starts_with("#!/bin/sh\n### BEGIN INIT INFO\n"));
+        assert!(script.contains("# Provides:          pm3\n"));
+        assert!(script.contains("### END INIT INFO\n"));
+        assert!(script.contains("# chkconfig: 2345 90 10\n"));
+    }
+
+    #[test]
+    fn test_sysv_runs_daemon_as_user() {
+        let script = generate_sysv_script("/usr/local/bin/pm3", "alice", "/home/alice");
+        assert!(script.contains("PM3=\"/usr/local/bin/pm3\"\n"));
+        assert!(script.contains("PM3_USER=\"alice\"\n"));
+        assert!(script.contains("export HOME=\"/home/alice\"\n"));
+        assert!(script.contains("daemon start --resurrect"));
+        assert!(script.contains("daemon stop"));
+        assert!(script.contains("save --if-running"));
+        assert!(script.contains("Usage: $0 {start|stop|restart|status}"));
+    }
+
+    #[test]
+    fn test_platform_from_str() {
+        assert_eq!("launchd".parse(), Ok(Platform::Launchd));
+        assert_eq!("systemd".parse(), Ok(Platform::Systemd));
+        assert_eq!("openrc".parse(), Ok(Platform::Openrc));
+        assert_eq!("sysv".parse(), Ok(Platform::Sysv));
         assert!(
-            path.to_string_lossy()
-                .contains("Library/LaunchAgents/com.pm3.daemon.plist"),
-            "expected macOS plist path, got: {}",
-            path.display()
+            "upstart"
+                .parse::<Platform>()
+                .unwrap_err()
+                .contains("unknown platform")
         );
     }
 
-    #[cfg(target_os = "linux")]
     #[test]
-    fn test_service_file_path_linux() {
-        let path = service_file_path().unwrap();
-        assert!(
-            path.to_string_lossy()
-                .contains(".config/systemd/user/pm3.service"),
-            "expected Linux systemd path, got: {}",
-            path.display()
+    fn test_service_file_paths() {
+        let plist = Platform::Launchd.service_file().unwrap();
+        assert!(plist.ends_with("Library/LaunchAgents/com.pm3.daemon.plist"));
+        let unit = Platform::Systemd.service_file().unwrap();
+        assert!(unit.ends_with(".config/systemd/user/pm3.service"));
+        assert_eq!(
+            Platform::Openrc.service_file().unwrap(),
+            Path::new("/etc/init.d/pm3")
+        );
+        assert_eq!(
+            Platform::Sysv.service_file().unwrap(),
+            Path::new("/etc/init.d/pm3")
         );
     }
 }
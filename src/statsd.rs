@@ -0,0 +1,285 @@
+use crate::events::ProcessEvent;
+use crate::memory::StatsCache;
+use crate::metrics::format_metric;
+use crate::process::ProcessTable;
+use crate::protocol::{ProcessInfo, ProcessStatus};
+use crate::settings::MetricsSettings;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::{RwLock, broadcast, watch};
+
+/// Packets are kept under a typical MTU so they aren't fragmented.
+const MAX_PACKET_SIZE: usize = 1432;
+
+/// Builds statsd lines, naming metrics in either plain statsd or DogStatsD
+/// style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsdFormat {
+    pub prefix: String,
+    pub dogstatsd: bool,
+}
+
+impl StatsdFormat {
+    pub fn gauge(&self, process: &str, metric: &str, value: f64) -> String {
+        self.line(process, metric, &format_metric(value), "g")
+    }
+
+    pub fn counter(&self, process: &str, metric: &str, count: u64) -> String {
+        self.line(process, metric, &count.to_string(), "c")
+    }
+
+    fn line(&self, process: &str, metric: &str, value: &str, kind: &str) -> String {
+        let process = sanitize(process);
+        let metric = sanitize(metric);
+        let prefix = match self.prefix.as_str() {
+            "" => String::new(),
+            prefix => format!("{}.", sanitize(prefix)),
+        };
+        if self.dogstatsd {
+            format!("{prefix}process.{metric}:{value}|{kind}|#process:{process}")
+        } else {
+            format!("{prefix}{process}.{metric}:{value}|{kind}")
+        }
+    }
+
+    /// The gauges describing one process at this moment.
+    pub fn gauges(&self, info: &ProcessInfo) -> Vec<String> {
+        let mut lines = vec![
+            self.gauge(&info.name, "restarts", info.restarts as f64),
+            self.gauge(
+                &info.name,
+                "online",
+                if info.status == ProcessStatus::Online {
+                    1.0
+                } else {
+                    0.0
+                },
+            ),
+        ];
+        if let Some(cpu) = info.cpu_percent {
+            lines.push(self.gauge(&info.name, "cpu_percent", cpu));
+        }
+        if let Some(memory) = info.memory_bytes {
+            lines.push(self.gauge(&info.name, "memory_bytes", memory as f64));
+        }
+        if info.pid.is_some()
+            && let Some(uptime) = info.uptime
+        {
+            lines.push(self.gauge(&info.name, "uptime_seconds", uptime as f64));
+        }
+        for (name, value) in &info.metrics {
+            lines.push(self.gauge(&info.name, &format!("metric.{name}"), *value));
+        }
+        lines
+    }
+}
+
+/// Replaces characters that statsd treats as separators (`:`, `|`, `@`, `#`,
+/// `,`) or that don't belong in a metric name, such as the `/` of namespaced
+/// processes and the `:` of cluster instances.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Joins lines into as few packets as fit under `MAX_PACKET_SIZE`.
+pub fn packets(lines: &[String]) -> Vec<String> {
+    let mut packets = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        if !current.is_empty() && current.len() + 1 + line.len() > MAX_PACKET_SIZE {
+            packets.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        packets.push(current);
+    }
+    packets
+}
+
+/// Pushes process gauges every `interval`, along with counts of the
+/// lifecycle events seen since the last push, until the daemon shuts down.
+pub fn spawn_statsd_reporter(
+    settings: MetricsSettings,
+    processes: Arc<RwLock<ProcessTable>>,
+    stats_cache: Arc<RwLock<StatsCache>>,
+    mut events: broadcast::Receiver<ProcessEvent>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let Some(target) = settings.statsd.clone() else {
+        return;
+    };
+    let interval = settings.interval().unwrap_or(Duration::from_secs(10));
+    let format = StatsdFormat {
+        prefix: settings.prefix,
+        dogstatsd: settings.dogstatsd,
+    };
+    tokio::spawn(async move {
+        let mut socket = None;
+        let mut counts: BTreeMap<(String, String), u64> = BTreeMap::new();
+        let mut tick = tokio::time::interval(interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {}
+                event = events.recv() => {
+                    match event {
+                        Ok(event) => {
+                            *counts.entry((event.name, event.kind.to_string())).or_insert(0) += 1;
+                        }
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            tracing::warn!("statsd reporter fell behind, {n} events not counted");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    }
+                    continue;
+                }
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                    continue;
+                }
+            }
+
+            if socket.is_none() {
+                // Resolved on every attempt so a statsd host that comes up
+                // later, or moves, is picked up
+                match connect(&target).await {
+                    Ok(connected) => socket = Some(connected),
+                    Err(e) => {
+                        tracing::warn!("statsd {target}: {e}");
+                        continue;
+                    }
+                }
+            }
+
+            let mut lines: Vec<String> = {
+                let table = processes.read().await;
+                let stats = stats_cache.read().await;
+                table
+                    .values()
+                    .flat_map(|managed| format.gauges(&managed.to_process_info(&stats)))
+                    .collect()
+            };
+            for ((name, kind), count) in std::mem::take(&mut counts) {
+                lines.push(format.counter(&name, &format!("event.{kind}"), count));
+            }
+            let Some(connected) = socket.as_ref() else {
+                continue;
+            };
+            for packet in packets(&lines) {
+                if let Err(e) = connected.send(packet.as_bytes()).await {
+                    tracing::warn!("statsd {target}: {e}");
+                    socket = None;
+                    break;
+                }
+            }
+        }
+    });
+}
+
+async fn connect(target: &str) -> std::io::Result<UdpSocket> {
+    let addr = tokio::net::lookup_host(target)
+        .await?
+        .next()
+        .ok_or_else(|| std::io::Error::other("address did not resolve"))?;
+    let local: SocketAddr = match addr {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(addr).await?;
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str) -> ProcessInfo {
+        ProcessInfo {
+            name: name.to_string(),
+            pid: Some(42),
+            status: ProcessStatus::Online,
+            uptime: Some(90),
+            restarts: 2,
+            cpu_percent: Some(12.5),
+            memory_bytes: Some(1024),
+            resources: None,
+            health: None,
+            group: None,
+            tags: Vec::new(),
+            metrics: BTreeMap::from([("queue_depth".to_string(), 7.0)]),
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn test_plain_statsd_lines() {
+        let format = StatsdFormat {
+            prefix: "pm3".to_string(),
+            dogstatsd: false,
+        };
+        assert_eq!(
+            format.gauges(&info("web")),
+            vec![
+                "pm3.web.restarts:2|g",
+                "pm3.web.online:1|g",
+                "pm3.web.cpu_percent:12.50|g",
+                "pm3.web.memory_bytes:1024|g",
+                "pm3.web.uptime_seconds:90|g",
+                "pm3.web.metric.queue_depth:7|g",
+            ]
+        );
+        assert_eq!(
+            format.counter("staging/web:1", "event.restarted", 3),
+            "pm3.staging_web_1.event.restarted:3|c"
+        );
+    }
+
+    #[test]
+    fn test_dogstatsd_lines() {
+        let format = StatsdFormat {
+            prefix: String::new(),
+            dogstatsd: true,
+        };
+        let mut stopped = info("web:0");
+        stopped.pid = None;
+        stopped.status = ProcessStatus::Stopped;
+        stopped.cpu_percent = None;
+        stopped.memory_bytes = None;
+        stopped.metrics.clear();
+        assert_eq!(
+            format.gauges(&stopped),
+            vec![
+                "process.restarts:2|g|#process:web_0",
+                "process.online:0|g|#process:web_0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_packets_split_at_size() {
+        let line = "x".repeat(600);
+        let lines = vec![line.clone(), line.clone(), line.clone()];
+        let split = packets(&lines);
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[0], format!("{line}\n{line}"));
+        assert!(packets(&[]).is_empty());
+    }
+}
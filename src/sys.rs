@@ -78,6 +78,12 @@ mod platform {
         let master: OwnedFd = pty.master;
         let slave: OwnedFd = pty.slave;
 
+        // Kept from other processes the daemon spawns meanwhile, above all
+        // log relays, which would otherwise hold the slave open and never
+        // see the process's output end
+        set_inheritable(master.as_fd(), false)?;
+        set_inheritable(slave.as_fd(), false)?;
+
         // Set master fd to non-blocking for async I/O
         nix::fcntl::fcntl(
             master.as_fd(),
@@ -135,14 +141,86 @@ mod platform {
         send_signal(pid, Signal::SIGKILL)
     }
 
+    // -- Suspend / resume --
+
+    /// `pid` followed by its descendants, as `/proc` lists them right now.
+    #[cfg(target_os = "linux")]
+    fn live_process_tree(pid: u32) -> Vec<u32> {
+        let mut children: std::collections::HashMap<u32, Vec<u32>> = Default::default();
+        if let Ok(entries) = std::fs::read_dir("/proc") {
+            for entry in entries.flatten() {
+                let Some(child) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                    continue;
+                };
+                let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else {
+                    continue;
+                };
+                // Fields after the parenthesised command name: state, ppid, ...
+                let ppid = stat
+                    .rsplit_once(')')
+                    .and_then(|(_, rest)| rest.split_whitespace().nth(1))
+                    .and_then(|p| p.parse::<u32>().ok());
+                if let Some(ppid) = ppid.filter(|&ppid| ppid != child) {
+                    children.entry(ppid).or_default().push(child);
+                }
+            }
+        }
+        process_tree(pid, &children)
+    }
+
+    /// Without `/proc` there is no cheap way to walk the tree, so only the
+    /// process itself is signalled.
+    #[cfg(not(target_os = "linux"))]
+    fn live_process_tree(pid: u32) -> Vec<u32> {
+        vec![pid]
+    }
+
+    /// Freezes a process and its descendants with SIGSTOP.
+    pub fn suspend_process_tree(pid: u32) -> io::Result<()> {
+        send_signal(pid, Signal::SIGSTOP)?;
+        for child in live_process_tree(pid).into_iter().skip(1) {
+            let _ = send_signal(child, Signal::SIGSTOP);
+        }
+        Ok(())
+    }
+
+    /// Thaws a process tree frozen by `suspend_process_tree`, children first.
+    pub fn resume_process_tree(pid: u32) -> io::Result<()> {
+        for child in live_process_tree(pid).into_iter().skip(1).rev() {
+            let _ = send_signal(child, Signal::SIGCONT);
+        }
+        send_signal(pid, Signal::SIGCONT)
+    }
+
     // -- IPC (async) --
 
-    pub async fn ipc_bind(paths: &Paths) -> io::Result<tokio::net::UnixListener> {
+    /// Binds the daemon socket. With a `mode`, it is bound in a directory
+    /// only the daemon's user can enter and given the mode there before it
+    /// is moved into place, so it is never reachable with the umask's.
+    pub async fn ipc_bind(
+        paths: &Paths,
+        mode: Option<u32>,
+    ) -> io::Result<tokio::net::UnixListener> {
         let socket_path = paths.socket_file();
         if socket_path.exists() {
             tokio::fs::remove_file(&socket_path).await?;
         }
-        tokio::net::UnixListener::bind(&socket_path)
+        let Some(mode) = mode else {
+            return tokio::net::UnixListener::bind(&socket_path);
+        };
+
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        let dir = socket_path.with_file_name(format!(".bind-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+        let bound = dir.join("pm3.sock");
+        let result = tokio::net::UnixListener::bind(&bound).and_then(|listener| {
+            std::fs::set_permissions(&bound, std::fs::Permissions::from_mode(mode))?;
+            std::fs::rename(&bound, &socket_path)?;
+            Ok(listener)
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+        result
     }
 
     pub async fn ipc_cleanup(paths: &Paths) {
@@ -159,10 +237,134 @@ mod platform {
         std::os::unix::net::UnixStream::connect(paths.socket_file())
     }
 
+    // -- IPC (async, client) --
+
+    pub async fn ipc_connect_async(paths: &Paths) -> io::Result<tokio::net::UnixStream> {
+        tokio::net::UnixStream::connect(paths.socket_file()).await
+    }
+
+    // -- Peer credentials --
+
+    /// Who is on the other end of a client connection, via SO_PEERCRED.
+    pub fn peer_credentials(stream: &tokio::net::UnixStream) -> Option<PeerCredentials> {
+        let cred = stream.peer_cred().ok()?;
+        Some(PeerCredentials {
+            uid: cred.uid(),
+            gid: cred.gid(),
+            pid: cred.pid().and_then(|pid| u32::try_from(pid).ok()),
+        })
+    }
+
+    pub fn current_uid() -> Option<u32> {
+        Some(nix::unistd::getuid().as_raw())
+    }
+
+    /// Applies `mode` to the daemon socket so other users can (or can't) open it.
+    pub fn user_name(uid: u32) -> Option<String> {
+        nix::unistd::User::from_uid(nix::unistd::Uid::from_raw(uid))
+            .ok()
+            .flatten()
+            .map(|user| user.name)
+    }
+
+    pub fn user_home(name: &str) -> Option<std::path::PathBuf> {
+        nix::unistd::User::from_name(name)
+            .ok()
+            .flatten()
+            .map(|user| user.dir)
+    }
+
+    // -- Daemon upgrade --
+
+    impl AsFd for PtyReader {
+        fn as_fd(&self) -> BorrowedFd<'_> {
+            self.inner.get_ref().as_fd()
+        }
+    }
+
+    /// Wraps a PTY master or pipe handed over by the previous daemon, already
+    /// non-blocking, so a log copier can read it.
+    pub fn fd_reader(fd: OwnedFd) -> io::Result<PtyReader> {
+        Ok(PtyReader {
+            inner: AsyncFd::new(fd)?,
+        })
+    }
+
+    /// Whether `fd` stays open across an exec, as the fds handed to the
+    /// upgraded daemon must.
+    pub fn set_inheritable(fd: BorrowedFd<'_>, inheritable: bool) -> io::Result<()> {
+        use nix::fcntl::{FcntlArg, FdFlag, fcntl};
+        let flags = match inheritable {
+            true => FdFlag::empty(),
+            false => FdFlag::FD_CLOEXEC,
+        };
+        fcntl(fd, FcntlArg::F_SETFD(flags))
+            .map(drop)
+            .map_err(io::Error::from)
+    }
+
+    /// Takes ownership of an fd inherited across exec, no longer inheritable
+    /// so processes this daemon spawns don't get it too.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be an open fd that nothing else in this process owns.
+    pub unsafe fn adopt_fd(raw: i32) -> io::Result<OwnedFd> {
+        use std::os::fd::FromRawFd;
+        // SAFETY: upheld by the caller
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+        set_inheritable(fd.as_fd(), false)?;
+        Ok(fd)
+    }
+
+    /// Has `cmd`'s process start with each fd open at its target number.
+    pub fn pass_fds<const N: usize>(cmd: &mut tokio::process::Command, fds: [(OwnedFd, i32); N]) {
+        // SAFETY: fcntl and dup2 are async-signal-safe, and the fds are only
+        // touched in the child, which owns its copies of them
+        unsafe {
+            cmd.pre_exec(move || {
+                // Moved clear of the targets first, so no fd is overwritten
+                // before it has been passed on
+                let mut moved = [0; N];
+                for (slot, (fd, _)) in moved.iter_mut().zip(&fds) {
+                    *slot = nix::fcntl::fcntl(fd, nix::fcntl::FcntlArg::F_DUPFD_CLOEXEC(10))?;
+                }
+                for (raw, (_, target)) in moved.into_iter().zip(&fds) {
+                    let fd = BorrowedFd::borrow_raw(raw);
+                    // The target stays open for the exec, so it isn't dropped
+                    std::mem::forget(nix::unistd::dup2_raw(fd, *target)?);
+                }
+                Ok(())
+            });
+        }
+    }
+
+    /// Collects `pid` if it is a child of this daemon that has exited. After
+    /// an upgrade the processes the old daemon spawned are still its
+    /// children, and until reaped they linger as zombies `is_pid_alive`
+    /// counts as alive.
+    pub fn reap(pid: u32) -> Reaped {
+        use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+        let Ok(pid) = to_pid(pid) else {
+            return Reaped::NotChild;
+        };
+        match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::Exited(_, code)) => Reaped::Exited(Some(code)),
+            Ok(WaitStatus::Signaled(..)) => Reaped::Exited(None),
+            Ok(_) => Reaped::Running,
+            Err(_) => Reaped::NotChild,
+        }
+    }
+
     // -- Daemon spawn helper --
 
     pub fn configure_daemon_cmd(cmd: &mut std::process::Command) {
-        cmd.process_group(0);
+        // A session of its own detaches the daemon from the terminal, so
+        // closing it doesn't send the daemon SIGHUP
+        // SAFETY: setsid is async-signal-safe and touches no parent state
+        unsafe {
+            cmd.pre_exec(|| nix::unistd::setsid().map(drop).map_err(io::Error::from));
+        }
     }
 
     // -- Signal shutdown (async) --
@@ -179,6 +381,26 @@ mod platform {
         }
     }
 
+    // -- Log reopening --
+
+    /// Calls `reopen` each time this process gets SIGUSR1, which asks pm3
+    /// to reopen its log files, as logrotate does once it moved them aside.
+    pub fn on_reopen_signal(reopen: impl Fn() + Send + 'static) -> io::Result<()> {
+        let mut usr1 =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined1())?;
+        tokio::spawn(async move {
+            while usr1.recv().await.is_some() {
+                reopen();
+            }
+        });
+        Ok(())
+    }
+
+    /// Asks the pm3 process `pid`, like a log relay, to reopen its log files.
+    pub fn signal_reopen(pid: u32) -> io::Result<()> {
+        send_signal(pid, Signal::SIGUSR1)
+    }
+
     // -- Hook shell --
 
     pub fn hook_command(hook: &str) -> tokio::process::Command {
@@ -262,6 +484,20 @@ mod platform {
         terminate_process(pid)
     }
 
+    pub fn suspend_process_tree(_pid: u32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "suspending processes is not supported on Windows",
+        ))
+    }
+
+    pub fn resume_process_tree(_pid: u32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "suspending processes is not supported on Windows",
+        ))
+    }
+
     fn terminate_process(pid: u32) -> io::Result<()> {
         use windows_sys::Win32::Foundation::CloseHandle;
         use windows_sys::Win32::System::Threading::{
@@ -284,7 +520,10 @@ mod platform {
 
     // -- IPC (async) --
 
-    pub async fn ipc_bind(paths: &Paths) -> io::Result<tokio::net::TcpListener> {
+    pub async fn ipc_bind(
+        paths: &Paths,
+        _mode: Option<u32>,
+    ) -> io::Result<tokio::net::TcpListener> {
         let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
         let port = listener.local_addr()?.port();
         tokio::fs::write(paths.port_file(), port.to_string()).await?;
@@ -310,6 +549,43 @@ mod platform {
         std::net::TcpStream::connect(("127.0.0.1", port))
     }
 
+    // -- IPC (async, client) --
+
+    pub async fn ipc_connect_async(paths: &Paths) -> io::Result<tokio::net::TcpStream> {
+        let port_str = tokio::fs::read_to_string(paths.port_file()).await?;
+        let port: u16 = port_str
+            .trim()
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        tokio::net::TcpStream::connect(("127.0.0.1", port)).await
+    }
+
+    // -- Peer credentials --
+
+    pub fn peer_credentials(_stream: &tokio::net::TcpStream) -> Option<PeerCredentials> {
+        None
+    }
+
+    pub fn user_name(_uid: u32) -> Option<String> {
+        None
+    }
+
+    pub fn user_home(_name: &str) -> Option<std::path::PathBuf> {
+        None
+    }
+
+    pub fn current_uid() -> Option<u32> {
+        None
+    }
+
+    /// The TCP fallback has no socket file to restrict.
+    // -- Daemon upgrade --
+
+    /// Windows daemons don't upgrade in place, so they never adopt children.
+    pub fn reap(_pid: u32) -> Reaped {
+        Reaped::NotChild
+    }
+
     // -- Daemon spawn helper --
 
     pub fn configure_daemon_cmd(cmd: &mut std::process::Command) {
@@ -324,6 +600,18 @@ mod platform {
         tokio::signal::ctrl_c().await.ok();
     }
 
+    // -- Log reopening --
+
+    /// There is no SIGUSR1 here; `pm3 reopen-logs` is the only way to ask.
+    pub fn on_reopen_signal(_reopen: impl Fn() + Send + 'static) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Log relays only run on unix, so there is never one to ask.
+    pub fn signal_reopen(_pid: u32) -> io::Result<()> {
+        Ok(())
+    }
+
     // -- Hook shell --
 
     pub fn hook_command(hook: &str) -> tokio::process::Command {
@@ -361,6 +649,42 @@ pub type SyncIpcStream = std::os::unix::net::UnixStream;
 #[cfg(windows)]
 pub type SyncIpcStream = std::net::TcpStream;
 
+/// The uid, gid and pid of a connected client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<u32>,
+}
+
+/// What `reap` found for a pid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reaped {
+    /// Not a child of this daemon; poll `is_pid_alive` instead.
+    NotChild,
+    Running,
+    /// Exited, with its exit code unless a signal killed it.
+    Exited(Option<i32>),
+}
+
+/// `pid` followed by its descendants, parents before children, walking
+/// `children` (each pid's direct children). Every process is listed once,
+/// even if a reused pid makes the parent links loop.
+pub fn process_tree(pid: u32, children: &std::collections::HashMap<u32, Vec<u32>>) -> Vec<u32> {
+    let mut tree = vec![pid];
+    let mut seen = std::collections::HashSet::from([pid]);
+    let mut i = 0;
+    while i < tree.len() {
+        for &kid in children.get(&tree[i]).into_iter().flatten() {
+            if seen.insert(kid) {
+                tree.push(kid);
+            }
+        }
+        i += 1;
+    }
+    tree
+}
+
 // Helper to accept from an IpcListener returning an IpcStream
 pub async fn ipc_accept(listener: &IpcListener) -> io::Result<IpcStream> {
     let (stream, _addr) = listener.accept().await?;
@@ -506,4 +830,64 @@ mod tests {
             "child should see fd 1 as a terminal, got: {output:?}"
         );
     }
+
+    #[test]
+    fn test_process_tree_lists_parents_before_children_once() {
+        let children = std::collections::HashMap::from([
+            (1, vec![2, 3]),
+            (2, vec![4]),
+            (4, vec![1]),
+            (9, vec![10]),
+        ]);
+        assert_eq!(process_tree(1, &children), vec![1, 2, 3, 4]);
+        assert_eq!(process_tree(5, &children), vec![5]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_suspend_and_resume_process_tree() {
+        let state = |pid: u32| {
+            let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).unwrap();
+            let (_, rest) = stat.rsplit_once(')').unwrap();
+            rest.split_whitespace().next().unwrap().to_string()
+        };
+
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+
+        suspend_process_tree(pid).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(state(pid), "T");
+
+        resume_process_tree(pid).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_ne!(state(pid), "T");
+
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    #[test]
+    #[allow(clippy::zombie_processes)] // waited for by `reap`, which is under test
+    fn test_reap_collects_exited_children() {
+        let child = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let reaped = loop {
+            match reap(pid) {
+                Reaped::Running if std::time::Instant::now() < deadline => {
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                }
+                reaped => break reaped,
+            }
+        };
+        assert_eq!(reaped, Reaped::Exited(Some(3)));
+        assert_eq!(reap(pid), Reaped::NotChild);
+    }
 }
@@ -0,0 +1,362 @@
+use crate::events::ProcessEvent;
+use crate::memory::StatsCache;
+use crate::process::ProcessTable;
+use crate::protocol::{ProcessInfo, ProcessStatus};
+use crate::settings::TelemetrySettings;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{RwLock, broadcast, watch};
+
+pub const OTLP_EXPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Events kept for the next export while the collector is unreachable; the
+/// oldest are dropped beyond this.
+const MAX_PENDING_EVENTS: usize = 1000;
+
+/// Resource attribute carrying the process name.
+const PROCESS_NAME_ATTRIBUTE: &str = "pm3.process.name";
+
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// One resource per process, so backends can group by process.
+fn resource(service_name: &str, process: &str) -> Value {
+    json!({
+        "attributes": [
+            string_attribute("service.name", service_name),
+            string_attribute(PROCESS_NAME_ATTRIBUTE, process),
+        ]
+    })
+}
+
+fn scope() -> Value {
+    json!({ "name": "pm3", "version": env!("CARGO_PKG_VERSION") })
+}
+
+fn gauge(name: &str, unit: &str, time: u64, value: Value) -> Value {
+    let mut point = json!({ "timeUnixNano": time.to_string() });
+    // OTLP/JSON encodes 64-bit integers as strings
+    match value {
+        Value::Number(n) if n.is_u64() => point["asInt"] = Value::String(n.to_string()),
+        other => point["asDouble"] = other,
+    }
+    json!({ "name": name, "unit": unit, "gauge": { "dataPoints": [point] } })
+}
+
+fn process_metrics(info: &ProcessInfo, time: u64) -> Vec<Value> {
+    let online = u64::from(info.status == ProcessStatus::Online);
+    let mut metrics = vec![
+        gauge("pm3.process.online", "1", time, json!(online)),
+        gauge(
+            "pm3.process.restarts",
+            "{restart}",
+            time,
+            json!(info.restarts),
+        ),
+    ];
+    if let Some(cpu) = info.cpu_percent {
+        metrics.push(gauge(
+            "process.cpu.utilization",
+            "1",
+            time,
+            json!(cpu / 100.0),
+        ));
+    }
+    if let Some(memory) = info.memory_bytes {
+        metrics.push(gauge("process.memory.usage", "By", time, json!(memory)));
+    }
+    if info.pid.is_some()
+        && let Some(uptime) = info.uptime
+    {
+        metrics.push(gauge("process.uptime", "s", time, json!(uptime as f64)));
+    }
+    for (name, value) in &info.metrics {
+        metrics.push(gauge(
+            &format!("pm3.process.metric.{name}"),
+            "1",
+            time,
+            json!(value),
+        ));
+    }
+    metrics
+}
+
+/// An OTLP `ExportMetricsServiceRequest` with the current gauges of every
+/// process.
+pub fn metrics_payload(service_name: &str, processes: &[ProcessInfo], time: u64) -> Value {
+    let resource_metrics: Vec<Value> = processes
+        .iter()
+        .map(|info| {
+            json!({
+                "resource": resource(service_name, &info.name),
+                "scopeMetrics": [{ "scope": scope(), "metrics": process_metrics(info, time) }],
+            })
+        })
+        .collect();
+    json!({ "resourceMetrics": resource_metrics })
+}
+
+/// OTLP severity: errors for crashes, warnings for failing health checks.
+fn severity(status: ProcessStatus) -> (u8, &'static str) {
+    match status {
+        ProcessStatus::Errored | ProcessStatus::Failed | ProcessStatus::Flapping => (17, "ERROR"),
+        ProcessStatus::Unhealthy => (13, "WARN"),
+        _ => (9, "INFO"),
+    }
+}
+
+fn log_record(event: &ProcessEvent, observed: u64) -> Value {
+    let time = chrono::DateTime::parse_from_rfc3339(&event.timestamp)
+        .ok()
+        .and_then(|t| t.timestamp_nanos_opt())
+        .map_or(observed, |nanos| nanos as u64);
+    let (severity_number, severity_text) = severity(event.status);
+    let body = match &event.detail {
+        Some(detail) => format!("{} {}: {detail}", event.name, event.kind),
+        None => format!("{} {}", event.name, event.kind),
+    };
+    let mut attributes = vec![
+        string_attribute("event.name", &format!("pm3.process.{}", event.kind)),
+        string_attribute("pm3.process.status", &event.status.to_string()),
+    ];
+    if let Some(detail) = &event.detail {
+        attributes.push(string_attribute("pm3.event.detail", detail));
+    }
+    json!({
+        "timeUnixNano": time.to_string(),
+        "observedTimeUnixNano": observed.to_string(),
+        "severityNumber": severity_number,
+        "severityText": severity_text,
+        "body": { "stringValue": body },
+        "attributes": attributes,
+    })
+}
+
+/// An OTLP `ExportLogsServiceRequest` with one log record per event, grouped
+/// under each process's resource.
+pub fn logs_payload(service_name: &str, events: &[ProcessEvent], observed: u64) -> Value {
+    let mut by_process: BTreeMap<&str, Vec<Value>> = BTreeMap::new();
+    for event in events {
+        by_process
+            .entry(&event.name)
+            .or_default()
+            .push(log_record(event, observed));
+    }
+    let resource_logs: Vec<Value> = by_process
+        .into_iter()
+        .map(|(name, records)| {
+            json!({
+                "resource": resource(service_name, name),
+                "scopeLogs": [{ "scope": scope(), "logRecords": records }],
+            })
+        })
+        .collect();
+    json!({ "resourceLogs": resource_logs })
+}
+
+fn now_nanos() -> u64 {
+    chrono::Utc::now()
+        .timestamp_nanos_opt()
+        .map_or(0, |nanos| nanos as u64)
+}
+
+struct Exporter {
+    client: reqwest::Client,
+    endpoint: String,
+    headers: BTreeMap<String, String>,
+    /// Whether the last export failed, so failures are logged once rather
+    /// than on every interval.
+    failing: bool,
+}
+
+impl Exporter {
+    async fn post(&mut self, path: &str, payload: &Value) -> bool {
+        let url = format!("{}{path}", self.endpoint.trim_end_matches('/'));
+        let mut request = self
+            .client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload.to_string());
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => None,
+            Ok(response) => Some(format!("HTTP {}", response.status())),
+            Err(e) => Some(e.to_string()),
+        };
+        match error {
+            None => {
+                if self.failing {
+                    tracing::info!("telemetry export to {url} recovered");
+                }
+                self.failing = false;
+                true
+            }
+            Some(e) => {
+                if !self.failing {
+                    tracing::warn!("telemetry export to {url} failed: {e}");
+                }
+                self.failing = true;
+                false
+            }
+        }
+    }
+}
+
+/// Exports process gauges as OTLP metrics and lifecycle events as OTLP logs
+/// every `interval`, until the daemon shuts down. Events that fail to export
+/// are retried with the next batch.
+pub fn spawn_telemetry_exporter(
+    settings: TelemetrySettings,
+    processes: Arc<RwLock<ProcessTable>>,
+    stats_cache: Arc<RwLock<StatsCache>>,
+    mut events: broadcast::Receiver<ProcessEvent>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let Some(endpoint) = settings.endpoint.clone() else {
+        return;
+    };
+    let interval = settings.interval().unwrap_or(Duration::from_secs(10));
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder()
+            .timeout(OTLP_EXPORT_TIMEOUT)
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!("failed to build HTTP client for telemetry: {e}");
+                return;
+            }
+        };
+        let mut exporter = Exporter {
+            client,
+            endpoint,
+            headers: settings.headers,
+            failing: false,
+        };
+        let mut pending: VecDeque<ProcessEvent> = VecDeque::new();
+        let mut tick = tokio::time::interval(interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {}
+                event = events.recv() => {
+                    match event {
+                        Ok(event) => {
+                            if pending.len() == MAX_PENDING_EVENTS {
+                                pending.pop_front();
+                            }
+                            pending.push_back(event);
+                        }
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            tracing::warn!("telemetry exporter fell behind, {n} events dropped");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    }
+                    continue;
+                }
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                    continue;
+                }
+            }
+
+            let infos: Vec<ProcessInfo> = {
+                let table = processes.read().await;
+                let stats = stats_cache.read().await;
+                table
+                    .values()
+                    .map(|managed| managed.to_process_info(&stats))
+                    .collect()
+            };
+            let now = now_nanos();
+            if !infos.is_empty() {
+                let payload = metrics_payload(&settings.service_name, &infos, now);
+                exporter.post("/v1/metrics", &payload).await;
+            }
+            if !pending.is_empty() {
+                let batch: Vec<ProcessEvent> = pending.iter().cloned().collect();
+                let payload = logs_payload(&settings.service_name, &batch, now);
+                if exporter.post("/v1/logs", &payload).await {
+                    pending.clear();
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+
+    fn info() -> ProcessInfo {
+        ProcessInfo {
+            name: "web".to_string(),
+            pid: Some(42),
+            status: ProcessStatus::Online,
+            uptime: Some(90),
+            restarts: 2,
+            cpu_percent: Some(50.0),
+            memory_bytes: Some(1024),
+            resources: None,
+            health: None,
+            group: None,
+            tags: Vec::new(),
+            metrics: BTreeMap::new(),
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn test_metrics_payload() {
+        let payload = metrics_payload("api", &[info()], 7);
+        let resource = &payload["resourceMetrics"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][1],
+            json!({ "key": "pm3.process.name", "value": { "stringValue": "web" } })
+        );
+        let metrics = resource["scopeMetrics"][0]["metrics"].as_array().unwrap();
+        let point = |name: &str| {
+            metrics
+                .iter()
+                .find(|m| m["name"] == name)
+                .map(|m| m["gauge"]["dataPoints"][0].clone())
+                .unwrap()
+        };
+        assert_eq!(point("pm3.process.online")["asInt"], "1");
+        assert_eq!(point("pm3.process.restarts")["asInt"], "2");
+        assert_eq!(point("process.cpu.utilization")["asDouble"], 0.5);
+        assert_eq!(point("process.memory.usage")["asInt"], "1024");
+        assert_eq!(point("process.uptime")["timeUnixNano"], "7");
+    }
+
+    #[test]
+    fn test_logs_payload() {
+        let event = ProcessEvent {
+            name: "web".to_string(),
+            kind: EventKind::StatusChanged,
+            status: ProcessStatus::Errored,
+            timestamp: "2025-01-01T00:00:00.000Z".to_string(),
+            detail: Some("online -> errored (exit code 1)".to_string()),
+        };
+        let payload = logs_payload("pm3", &[event], 1);
+        let record = &payload["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["severityText"], "ERROR");
+        assert_eq!(record["timeUnixNano"], "1735689600000000000");
+        assert_eq!(
+            record["body"]["stringValue"],
+            "web status_changed: online -> errored (exit code 1)"
+        );
+        assert_eq!(
+            record["attributes"][0]["value"]["stringValue"],
+            "pm3.process.status_changed"
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use crate::memory::StatsCache;
+use crate::paths::Paths;
+use crate::process::ProcessTable;
+use crate::protocol::StatsSample;
+use crate::settings::HistorySettings;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{RwLock, watch};
+
+/// The resource samples of every process, oldest first, kept for the
+/// retention period. Each process's samples are mirrored to
+/// `stats/<name>.jsonl` so they survive a daemon restart.
+#[derive(Debug)]
+pub struct History {
+    samples: HashMap<String, VecDeque<StatsSample>>,
+    /// Lines in each process's file, which is compacted once it holds
+    /// twice what is kept.
+    file_lines: HashMap<String, usize>,
+    capacity: usize,
+    retention_secs: i64,
+}
+
+impl History {
+    pub fn new(capacity: usize, retention: Duration) -> Self {
+        Self {
+            samples: HashMap::new(),
+            file_lines: HashMap::new(),
+            capacity,
+            retention_secs: retention.as_secs() as i64,
+        }
+    }
+
+    pub fn from_settings(settings: &HistorySettings) -> Self {
+        let retention = settings
+            .retention()
+            .unwrap_or(Duration::from_secs(12 * 60 * 60));
+        Self::new(settings.capacity(), retention)
+    }
+
+    pub fn push(&mut self, name: &str, sample: StatsSample) {
+        let samples = self.samples.entry(name.to_string()).or_default();
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// The samples of `name` taken at or after `since`, or `None` when none
+    /// were ever recorded.
+    pub fn since(&self, name: &str, since: Option<i64>) -> Option<Vec<StatsSample>> {
+        let samples = self.samples.get(name)?;
+        Some(
+            samples
+                .iter()
+                .filter(|s| since.is_none_or(|since| s.time >= since))
+                .copied()
+                .collect(),
+        )
+    }
+
+    /// Drops samples older than the retention period, returning the
+    /// processes left without any, whose files can go.
+    pub fn prune(&mut self, now: i64) -> Vec<String> {
+        let cutoff = now - self.retention_secs;
+        let mut emptied = Vec::new();
+        self.samples.retain(|name, samples| {
+            while samples.front().is_some_and(|s| s.time < cutoff) {
+                samples.pop_front();
+            }
+            if samples.is_empty() {
+                emptied.push(name.clone());
+            }
+            !samples.is_empty()
+        });
+        for name in &emptied {
+            self.file_lines.remove(name);
+        }
+        emptied
+    }
+
+    /// Reads back the samples written by an earlier daemon. Lines that don't
+    /// parse are skipped.
+    pub fn load(&mut self, paths: &Paths, now: i64) -> io::Result<()> {
+        let dir = paths.stats_dir();
+        if !dir.exists() {
+            return Ok(());
+        }
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                // A namespace: its processes are `<namespace>/<name>`
+                let Some(namespace) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                for entry in std::fs::read_dir(&path)? {
+                    let file = entry?.path();
+                    if let Some(name) = process_name(&file) {
+                        self.load_file(&format!("{namespace}/{name}"), &file, now)?;
+                    }
+                }
+            } else if let Some(name) = process_name(&path) {
+                self.load_file(&name, &path, now)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_file(&mut self, name: &str, path: &Path, now: i64) -> io::Result<()> {
+        let content = std::fs::read_to_string(path)?;
+        let cutoff = now - self.retention_secs;
+        let mut lines = 0;
+        for line in content.lines() {
+            lines += 1;
+            if let Ok(sample) = serde_json::from_str::<StatsSample>(line)
+                && sample.time >= cutoff
+            {
+                self.push(name, sample);
+            }
+        }
+        self.file_lines.insert(name.to_string(), lines);
+        Ok(())
+    }
+
+    /// Counts a line appended to `name`'s file, returning the samples to
+    /// rewrite it with when it has grown past twice the capacity.
+    fn appended(&mut self, name: &str) -> Option<Vec<StatsSample>> {
+        let lines = self.file_lines.entry(name.to_string()).or_insert(0);
+        *lines += 1;
+        if *lines <= self.capacity * 2 {
+            return None;
+        }
+        let samples: Vec<StatsSample> = self.samples.get(name)?.iter().copied().collect();
+        *lines = samples.len();
+        Some(samples)
+    }
+}
+
+fn process_name(path: &Path) -> Option<String> {
+    if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+        return None;
+    }
+    path.file_stem()?.to_str().map(str::to_string)
+}
+
+async fn append(paths: &Paths, name: &str, sample: &StatsSample) -> io::Result<()> {
+    let path = paths.stats_file(name);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut line = serde_json::to_string(sample).map_err(io::Error::other)?;
+    line.push('\n');
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await
+}
+
+/// Replaces `name`'s file with just `samples`, writing a temporary file
+/// first so a crash midway doesn't lose what was there.
+async fn rewrite(paths: &Paths, name: &str, samples: &[StatsSample]) -> io::Result<()> {
+    let path = paths.stats_file(name);
+    let mut content = String::new();
+    for sample in samples {
+        content.push_str(&serde_json::to_string(sample).map_err(io::Error::other)?);
+        content.push('\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    tokio::fs::write(&tmp, content).await?;
+    tokio::fs::rename(&tmp, &path).await
+}
+
+/// Records a sample of every process each `interval`, until the daemon
+/// shuts down. Processes that aren't running are sampled too, without CPU
+/// and memory, so a crash shows up as a gap.
+pub fn spawn_sampler(
+    settings: HistorySettings,
+    history: Arc<RwLock<History>>,
+    processes: Arc<RwLock<ProcessTable>>,
+    stats_cache: Arc<RwLock<StatsCache>>,
+    paths: Paths,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    if !settings.enabled {
+        return;
+    }
+    let interval = settings.interval().unwrap_or(Duration::from_secs(10));
+    tokio::spawn(async move {
+        let now = chrono::Utc::now().timestamp();
+        if let Err(e) = history.write().await.load(&paths, now) {
+            tracing::warn!("failed to load stats history: {e}");
+        }
+
+        let mut tick = tokio::time::interval(interval);
+        tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        // Whether the last write failed, so failures are logged once
+        let mut failing = false;
+        loop {
+            tokio::select! {
+                _ = tick.tick() => {}
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        return;
+                    }
+                    continue;
+                }
+            }
+
+            let now = chrono::Utc::now().timestamp();
+            let samples: Vec<(String, StatsSample)> = {
+                let table = processes.read().await;
+                let stats = stats_cache.read().await;
+                table
+                    .iter()
+                    .map(|(name, managed)| {
+                        let info = managed.to_process_info(&stats);
+                        let sample = StatsSample {
+                            time: now,
+                            cpu_percent: info.cpu_percent,
+                            memory_bytes: info.memory_bytes,
+                            restarts: info.restarts,
+                        };
+                        (name.clone(), sample)
+                    })
+                    .collect()
+            };
+
+            let (rewrites, expired) = {
+                let mut history = history.write().await;
+                let mut rewrites = Vec::new();
+                for (name, sample) in &samples {
+                    history.push(name, *sample);
+                    if let Some(kept) = history.appended(name) {
+                        rewrites.push((name.clone(), kept));
+                    }
+                }
+                (rewrites, history.prune(now))
+            };
+
+            let mut result = Ok(());
+            for (name, sample) in &samples {
+                result = result.and(append(&paths, name, sample).await);
+            }
+            for (name, kept) in &rewrites {
+                result = result.and(rewrite(&paths, name, kept).await);
+            }
+            for name in &expired {
+                let _ = tokio::fs::remove_file(paths.stats_file(name)).await;
+            }
+            match result {
+                Ok(()) => failing = false,
+                Err(e) if !failing => {
+                    tracing::warn!("failed to record stats history: {e}");
+                    failing = true;
+                }
+                Err(_) => {}
+            }
+        }
+    });
+}
+
+/// Splits `samples` into `buckets` equal spans of time and reduces each to
+/// the largest value `value` picks from its samples, for drawing graphs
+/// narrower than the history. Spans without a value are `None`.
+pub fn downsample(
+    samples: &[StatsSample],
+    buckets: usize,
+    value: impl Fn(&StatsSample) -> Option<f64>,
+) -> Vec<Option<f64>> {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return Vec::new();
+    };
+    let buckets = buckets.min(samples.len()).max(1);
+    let span = (last.time - first.time).max(1) as f64;
+    let mut values = vec![None; buckets];
+    for sample in samples {
+        let Some(v) = value(sample) else {
+            continue;
+        };
+        let offset = (sample.time - first.time) as f64 / span;
+        let bucket = ((offset * buckets as f64) as usize).min(buckets - 1);
+        values[bucket] = Some(values[bucket].map_or(v, |max: f64| max.max(v)));
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: i64, memory: Option<u64>) -> StatsSample {
+        StatsSample {
+            time,
+            cpu_percent: memory.map(|_| 1.0),
+            memory_bytes: memory,
+            restarts: 0,
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_capacity() {
+        let mut history = History::new(3, Duration::from_secs(3600));
+        for t in 0..5 {
+            history.push("web", sample(t, Some(100)));
+        }
+        let times: Vec<i64> = history
+            .since("web", None)
+            .unwrap()
+            .iter()
+            .map(|s| s.time)
+            .collect();
+        assert_eq!(times, vec![2, 3, 4]);
+        assert_eq!(history.since("web", Some(4)).unwrap().len(), 1);
+        assert_eq!(history.since("api", None), None);
+    }
+
+    #[test]
+    fn test_prune_drops_expired_samples() {
+        let mut history = History::new(10, Duration::from_secs(60));
+        history.push("web", sample(0, Some(100)));
+        history.push("web", sample(100, Some(100)));
+        history.push("gone", sample(10, None));
+        assert_eq!(history.prune(120), vec!["gone".to_string()]);
+        assert_eq!(history.since("web", None).unwrap().len(), 1);
+        assert_eq!(history.since("gone", None), None);
+    }
+
+    #[tokio::test]
+    async fn test_persists_and_compacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = Paths::with_base(dir.path().to_path_buf());
+        let mut history = History::new(2, Duration::from_secs(3600));
+        for t in 0..5 {
+            let s = sample(1000 + t, Some(t as u64));
+            history.push("shop/web", s);
+            append(&paths, "shop/web", &s).await.unwrap();
+            if let Some(kept) = history.appended("shop/web") {
+                rewrite(&paths, "shop/web", &kept).await.unwrap();
+            }
+        }
+        // Compacted on the fifth line, back to the two kept samples
+        let lines = std::fs::read_to_string(paths.stats_file("shop/web")).unwrap();
+        assert_eq!(lines.lines().count(), 2);
+
+        let mut loaded = History::new(2, Duration::from_secs(3600));
+        loaded.load(&paths, 1010).unwrap();
+        assert_eq!(
+            loaded.since("shop/web", None).unwrap(),
+            vec![sample(1003, Some(3)), sample(1004, Some(4))]
+        );
+        // Nothing within the retention period is left an hour later
+        let mut later = History::new(2, Duration::from_secs(3600));
+        later.load(&paths, 1004 + 3601).unwrap();
+        assert_eq!(later.since("shop/web", None), None);
+    }
+
+    #[test]
+    fn test_downsample_takes_bucket_maximum() {
+        let samples: Vec<StatsSample> = (0..8)
+            .map(|t| sample(t, (t != 5).then_some(t as u64 * 10)))
+            .collect();
+        let memory = |s: &StatsSample| s.memory_bytes.map(|m| m as f64);
+        assert_eq!(
+            downsample(&samples, 4, memory),
+            vec![Some(10.0), Some(30.0), Some(40.0), Some(70.0)]
+        );
+        assert_eq!(downsample(&samples[..1], 4, memory), vec![Some(0.0)]);
+        assert!(downsample(&[], 4, memory).is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! `pm3 tree`: the processes a managed process spawned, down to its
+//! grandchildren and beyond, each with its own CPU and memory use.
+
+use crate::protocol::TreeNode;
+use std::collections::HashMap;
+
+/// One line of `ps` output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PsEntry {
+    pub pid: u32,
+    pub ppid: u32,
+    pub cpu_percent: f64,
+    pub memory_bytes: u64,
+    pub command: String,
+}
+
+/// Parses `ps -o pid=,ppid=,%cpu=,rss=,args=` output. Lines that don't
+/// parse, like a process exiting mid-listing, are skipped.
+pub fn parse_ps(output: &str) -> Vec<PsEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut rest = line.trim_start();
+            let mut field = || {
+                let (value, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                rest = tail.trim_start();
+                value
+            };
+            let pid = field().parse().ok()?;
+            let ppid = field().parse().ok()?;
+            let cpu_percent = field().parse().ok()?;
+            let rss_kb: u64 = field().parse().ok()?;
+            Some(PsEntry {
+                pid,
+                ppid,
+                cpu_percent,
+                memory_bytes: rss_kb * 1024,
+                command: rest.trim_end().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Every process on the system, or `None` where `ps` isn't available.
+#[cfg(unix)]
+pub async fn read_process_table() -> Option<Vec<PsEntry>> {
+    let output = tokio::process::Command::new("ps")
+        .args(["-e", "-o", "pid=,ppid=,%cpu=,rss=,args="])
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_ps(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(windows)]
+pub async fn read_process_table() -> Option<Vec<PsEntry>> {
+    None
+}
+
+/// The tree rooted at `pid`, children ordered by pid. A root missing from
+/// `entries` is still returned, without stats, so the caller always gets the
+/// process it asked about.
+pub fn build(pid: u32, entries: &[PsEntry]) -> TreeNode {
+    let by_pid: HashMap<u32, &PsEntry> = entries.iter().map(|e| (e.pid, e)).collect();
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for entry in entries {
+        if entry.pid != entry.ppid {
+            children.entry(entry.ppid).or_default().push(entry.pid);
+        }
+    }
+    // Parents come before children, so going backwards every node's
+    // children are built by the time it is reached.
+    let mut built: HashMap<u32, Vec<TreeNode>> = HashMap::new();
+    let mut root = None;
+    for pid_in_tree in crate::sys::process_tree(pid, &children).into_iter().rev() {
+        let entry = by_pid.get(&pid_in_tree);
+        let mut kids = built.remove(&pid_in_tree).unwrap_or_default();
+        kids.sort_unstable_by_key(|kid| kid.pid);
+        let node = TreeNode {
+            pid: pid_in_tree,
+            command: entry.map(|e| e.command.clone()).unwrap_or_default(),
+            cpu_percent: entry.map(|e| e.cpu_percent),
+            memory_bytes: entry.map(|e| e.memory_bytes),
+            children: kids,
+        };
+        match entry {
+            Some(e) if pid_in_tree != pid => built.entry(e.ppid).or_default().push(node),
+            _ => root = Some(node),
+        }
+    }
+    root.expect("the walk always starts at the root")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pid: u32, ppid: u32, command: &str) -> PsEntry {
+        PsEntry {
+            pid,
+            ppid,
+            cpu_percent: 1.5,
+            memory_bytes: 4096,
+            command: command.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_ps() {
+        let output = "    1     0  0.0  1234 /sbin/init splash\n\
+                      4242     1 12.5 20480 sh -c node  server.js\n\
+                      garbage\n";
+        assert_eq!(
+            parse_ps(output),
+            vec![
+                PsEntry {
+                    pid: 1,
+                    ppid: 0,
+                    cpu_percent: 0.0,
+                    memory_bytes: 1234 * 1024,
+                    command: "/sbin/init splash".to_string(),
+                },
+                PsEntry {
+                    pid: 4242,
+                    ppid: 1,
+                    cpu_percent: 12.5,
+                    memory_bytes: 20480 * 1024,
+                    command: "sh -c node  server.js".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_walks_descendants() {
+        let entries = vec![
+            entry(1, 0, "init"),
+            entry(10, 1, "sh -c app"),
+            entry(12, 10, "worker b"),
+            entry(11, 10, "worker a"),
+            entry(20, 11, "helper"),
+            entry(30, 1, "unrelated"),
+        ];
+        let tree = build(10, &entries);
+        assert_eq!(tree.command, "sh -c app");
+        assert_eq!(tree.memory_bytes, Some(4096));
+        let kids: Vec<u32> = tree.children.iter().map(|c| c.pid).collect();
+        assert_eq!(kids, vec![11, 12]);
+        assert_eq!(tree.children[0].children[0].command, "helper");
+        assert!(tree.children[1].children.is_empty());
+    }
+
+    #[test]
+    fn test_build_missing_root() {
+        let tree = build(99, &[entry(1, 0, "init")]);
+        assert_eq!(tree.pid, 99);
+        assert_eq!(tree.cpu_percent, None);
+        assert!(tree.children.is_empty());
+    }
+}
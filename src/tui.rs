@@ -1,8 +1,10 @@
 use crate::client;
 use crate::config;
 use crate::log;
+use crate::metrics;
 use crate::paths::Paths;
-use crate::protocol::{ProcessInfo, ProcessStatus, Request, Response};
+use crate::protocol::{ProcessInfo, ProcessStatus, Request, Response, StatsSample};
+use crate::timeseries;
 use color_eyre::eyre::Context;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{
@@ -14,14 +16,30 @@ use ratatui::layout::{Alignment, Constraint, Direction, Layout};
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{
-    Block, BorderType, Borders, Cell, Padding, Paragraph, Row, Table, TableState,
+    Block, BorderType, Borders, Cell, Padding, Paragraph, Row, Sparkline, Table, TableState,
 };
 use ratatui::{Frame, Terminal};
+use std::collections::{HashSet, VecDeque};
 use std::io;
 use std::time::{Duration, Instant};
 
 const TICK_RATE: Duration = Duration::from_millis(1000);
 
+/// How often the screen is redrawn while the log pane is streaming.
+const LOG_PANE_REFRESH: Duration = Duration::from_millis(100);
+/// Recent lines the log pane starts with.
+const LOG_PANE_LINES: usize = 100;
+/// Lines the log pane keeps for scrolling back.
+const LOG_PANE_SCROLLBACK: usize = 2000;
+/// Lines the full-screen log viewer shows.
+const LOG_VIEWER_LINES: usize = 200;
+/// The log pane sits beside the table from this width, below it otherwise.
+const LOG_PANE_SIDE_BY_SIDE_WIDTH: u16 = 120;
+/// How far back the CPU and memory graphs above the log pane reach.
+const GRAPH_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// The graphs are left out when the log pane is shorter than this.
+const GRAPH_MIN_PANE_HEIGHT: u16 = 8;
+
 // ── Color palette (cyan / green / red) ───────────────────────────────
 const BG_BASE: Color = Color::Reset;
 const BG_SURFACE: Color = Color::Reset;
@@ -58,6 +76,7 @@ enum View {
         scroll_offset: u16,
         auto_scroll: bool,
         stream: LogSource,
+        logs: log::LogFiles,
     },
 }
 
@@ -72,9 +91,14 @@ pub fn run(paths: &Paths) -> color_eyre::Result<()> {
     let mut last_tick = Instant::now();
 
     loop {
+        app.sync_log_pane(paths);
+        app.receive_logs();
         terminal.draw(|f| ui(f, &mut app))?;
 
-        let timeout = TICK_RATE.saturating_sub(last_tick.elapsed());
+        let mut timeout = TICK_RATE.saturating_sub(last_tick.elapsed());
+        if app.log_pane.is_some() {
+            timeout = timeout.min(LOG_PANE_REFRESH);
+        }
         if event::poll(timeout).context("failed to poll terminal events")?
             && let Event::Key(key) = event::read().context("failed to read terminal event")?
             && key.kind == KeyEventKind::Press
@@ -85,7 +109,8 @@ pub fn run(paths: &Paths) -> color_eyre::Result<()> {
 
         if last_tick.elapsed() >= TICK_RATE {
             app.refresh(paths);
-            app.refresh_logs(paths);
+            app.refresh_logs();
+            app.refresh_graphs(paths);
             last_tick = Instant::now();
         }
     }
@@ -100,6 +125,14 @@ fn handle_key_event(app: &mut App, code: KeyCode, modifiers: KeyModifiers, paths
         return true;
     }
 
+    if app.pending.is_some() {
+        match code {
+            KeyCode::Char('y') | KeyCode::Enter => app.confirm_pending(paths),
+            _ => app.cancel_pending(),
+        }
+        return false;
+    }
+
     match app.view {
         View::ProcessList => handle_process_list_key(app, code, paths),
         View::LogViewer { .. } => handle_log_viewer_key(app, code, paths),
@@ -107,19 +140,54 @@ fn handle_key_event(app: &mut App, code: KeyCode, modifiers: KeyModifiers, paths
 }
 
 fn handle_process_list_key(app: &mut App, code: KeyCode, paths: &Paths) -> bool {
+    if app.searching {
+        app.search_input(code);
+        return false;
+    }
+
     match code {
+        KeyCode::Esc if app.is_filtered() => app.clear_filters(),
         KeyCode::Char('q') | KeyCode::Esc => return true,
         KeyCode::Down | KeyCode::Char('j') => app.next(),
         KeyCode::Up | KeyCode::Char('k') => app.previous(),
         KeyCode::Home => app.first(),
         KeyCode::End => app.last(),
+        KeyCode::Enter if matches!(app.selected_entry(), Some(ListEntry::Group { .. })) => {
+            app.toggle_collapsed()
+        }
         KeyCode::Enter => app.open_log_viewer(paths),
-        KeyCode::Char('s') => app.start_selected(paths),
-        KeyCode::Char('x') => app.stop_selected(paths),
-        KeyCode::Char('r') => app.restart_selected(paths),
-        KeyCode::Char('S') => app.start_all(paths),
-        KeyCode::Char('X') => app.stop_all(paths),
-        KeyCode::Char('R') => app.restart_all(paths),
+        KeyCode::Char('/') => app.start_search(),
+        KeyCode::Char('t') => app.cycle_status_filter(),
+        KeyCode::Char(' ') => app.toggle_collapsed(),
+        KeyCode::Left => app.set_collapsed(true),
+        KeyCode::Right => app.set_collapsed(false),
+        KeyCode::Char('s') => app.act_on_selected(Action::Start, paths),
+        KeyCode::Char('x') => app.act_on_selected(Action::Stop, paths),
+        KeyCode::Char('r') => app.act_on_selected(Action::Restart, paths),
+        KeyCode::Char('f') => app.act_on_selected(Action::Flush, paths),
+        KeyCode::Char('S') => app.request_action(Action::Start, Target::All, paths),
+        KeyCode::Char('X') => app.request_action(Action::Stop, Target::All, paths),
+        KeyCode::Char('R') => app.request_action(Action::Restart, Target::All, paths),
+        KeyCode::Char('F') => app.request_action(Action::Flush, Target::All, paths),
+        KeyCode::Char('g') => app.toggle_scope(),
+        KeyCode::Char('l') => app.toggle_log_pane(),
+        KeyCode::Char('p') => {
+            if let Some(pane) = &mut app.log_pane {
+                pane.toggle_pause();
+            }
+        }
+        KeyCode::PageUp => {
+            let page = app.log_pane_height.max(1) as usize;
+            if let Some(pane) = &mut app.log_pane {
+                pane.scroll_up(page);
+            }
+        }
+        KeyCode::PageDown => {
+            let page = app.log_pane_height.max(1) as usize;
+            if let Some(pane) = &mut app.log_pane {
+                pane.scroll_down(page);
+            }
+        }
         _ => {}
     }
     false
@@ -134,25 +202,11 @@ fn handle_log_viewer_key(app: &mut App, code: KeyCode, paths: &Paths) -> bool {
         KeyCode::PageDown => app.log_page_down(),
         KeyCode::Home | KeyCode::Char('g') => app.log_scroll_to_top(),
         KeyCode::End | KeyCode::Char('G') => app.log_scroll_to_bottom(),
-        KeyCode::Tab => app.toggle_log_stream(paths),
-        KeyCode::Char('s') => {
-            let name = app.viewed_process_name().map(|s| s.to_string());
-            if let Some(name) = name {
-                app.start_named(&name, paths);
-            }
-        }
-        KeyCode::Char('x') => {
-            let name = app.viewed_process_name().map(|s| s.to_string());
-            if let Some(name) = name {
-                app.stop_named(&name, paths);
-            }
-        }
-        KeyCode::Char('r') => {
-            let name = app.viewed_process_name().map(|s| s.to_string());
-            if let Some(name) = name {
-                app.restart_named(&name, paths);
-            }
-        }
+        KeyCode::Tab => app.toggle_log_stream(),
+        KeyCode::Char('s') => app.act_on_selected(Action::Start, paths),
+        KeyCode::Char('x') => app.act_on_selected(Action::Stop, paths),
+        KeyCode::Char('r') => app.act_on_selected(Action::Restart, paths),
+        KeyCode::Char('f') => app.act_on_selected(Action::Flush, paths),
         _ => {}
     }
     false
@@ -212,19 +266,42 @@ fn ui(f: &mut Frame, app: &mut App) {
         .split(f.area());
 
     render_header(f, app, layout[0]);
+    if app.pending.is_some() {
+        render_confirmation_footer(f, app, layout[4]);
+    }
 
     let spacer = Paragraph::new("").style(Style::default().bg(BG_BASE));
     f.render_widget(spacer.clone(), layout[1]);
     f.render_widget(spacer, layout[3]);
 
     match &app.view {
+        View::ProcessList if app.log_pane.is_some() => {
+            let direction = if terminal_width >= LOG_PANE_SIDE_BY_SIDE_WIDTH {
+                Direction::Horizontal
+            } else {
+                Direction::Vertical
+            };
+            let split = Layout::default()
+                .direction(direction)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(layout[2]);
+            render_table(f, app, split[0], split[0].width);
+            render_log_pane(f, app, split[1]);
+            if app.pending.is_none() {
+                render_process_list_footer(f, app, layout[4]);
+            }
+        }
         View::ProcessList => {
             render_table(f, app, layout[2], terminal_width);
-            render_process_list_footer(f, app, layout[4]);
+            if app.pending.is_none() {
+                render_process_list_footer(f, app, layout[4]);
+            }
         }
         View::LogViewer { .. } => {
             render_log_viewer(f, app, layout[2]);
-            render_log_viewer_footer(f, &*app, layout[4]);
+            if app.pending.is_none() {
+                render_log_viewer_footer(f, &*app, layout[4]);
+            }
         }
     }
 }
@@ -295,6 +372,10 @@ fn render_header(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
         Span::styled("errored: ", label),
         Span::styled(counts.errored.to_string(), val(STATUS_RED)),
         Span::styled("   ", label),
+        status_dot(ProcessStatus::Flapping),
+        Span::styled("flapping: ", label),
+        Span::styled(counts.flapping.to_string(), val(STATUS_MAGENTA)),
+        Span::styled("   ", label),
         Span::styled("total: ", label),
         Span::styled(
             app.processes.len().to_string(),
@@ -314,6 +395,10 @@ fn status_dot(status: ProcessStatus) -> Span<'static> {
         ProcessStatus::Unhealthy => STATUS_MAGENTA,
         ProcessStatus::Stopped => STATUS_GRAY,
         ProcessStatus::Errored => STATUS_RED,
+        ProcessStatus::Flapping => STATUS_MAGENTA,
+        ProcessStatus::Succeeded => STATUS_GRAY,
+        ProcessStatus::Failed => STATUS_RED,
+        ProcessStatus::Suspended => STATUS_GRAY,
     };
     Span::styled("● ", Style::default().fg(color))
 }
@@ -324,8 +409,9 @@ fn render_table(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect, termi
     // Determine column layout based on terminal width
     let is_wide = terminal_width >= 100;
     let is_medium = terminal_width >= 70;
+    let show_metrics = is_wide && app.processes.iter().any(|p| !p.metrics.is_empty());
 
-    let header_cells: Vec<Cell> = if is_wide {
+    let mut header_cells: Vec<Cell> = if is_wide {
         vec![
             Cell::from(Line::from("NAME")),
             Cell::from(Line::from("PID").alignment(Alignment::Right)),
@@ -351,10 +437,51 @@ fn render_table(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect, termi
         ]
     };
 
+    if show_metrics {
+        header_cells.push(Cell::from(Line::from("METRICS")));
+    }
+
+    let column_count = header_cells.len();
     let header =
         Row::new(header_cells).style(Style::default().fg(ACCENT).add_modifier(Modifier::BOLD));
 
-    let rows = app.processes.iter().enumerate().map(|(idx, p)| {
+    let rows = app.entries.iter().enumerate().map(|(idx, entry)| {
+        let is_selected = selected_idx == Some(idx);
+        let row_bg = if is_selected {
+            BG_HIGHLIGHT
+        } else if idx % 2 == 0 {
+            BG_BASE
+        } else {
+            BG_SURFACE
+        };
+        let row_fg = if is_selected { FG_BRIGHT } else { FG_TEXT };
+
+        let (p, grouped) = match entry {
+            ListEntry::Group {
+                name,
+                members,
+                collapsed,
+            } => {
+                let online = members
+                    .iter()
+                    .filter(|&&i| app.processes[i].status == ProcessStatus::Online)
+                    .count();
+                let marker = if *collapsed { "▸" } else { "▾" };
+                let status_column = if is_medium { 2 } else { 1 };
+                let mut cells = vec![Cell::from(""); column_count];
+                cells[0] = Cell::from(Line::from(Span::styled(
+                    format!("{marker} {name}"),
+                    Style::default().fg(ACCENT).add_modifier(Modifier::BOLD),
+                )));
+                cells[status_column] = Cell::from(Line::from(Span::styled(
+                    format!("{online}/{} online", members.len()),
+                    Style::default().fg(FG_MUTED),
+                )));
+                return Row::new(cells).style(Style::default().bg(row_bg).fg(row_fg));
+            }
+            ListEntry::Process { index, grouped } => (&app.processes[*index], *grouped),
+        };
+
         let pid = p
             .pid
             .map(|id| id.to_string())
@@ -370,28 +497,23 @@ fn render_table(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect, termi
             Style::default().fg(FG_TEXT)
         };
 
-        let is_selected = selected_idx == Some(idx);
-        let row_bg = if is_selected {
-            BG_HIGHLIGHT
-        } else if idx % 2 == 0 {
-            BG_BASE
-        } else {
-            BG_SURFACE
-        };
-        let row_fg = if is_selected { FG_BRIGHT } else { FG_TEXT };
-
+        let indent = if grouped { "  " } else { "" };
         let name_display = if is_selected {
-            format!("▸ {}", p.name)
+            format!("{indent}▸ {}", p.name)
         } else {
-            format!("  {}", p.name)
+            format!("{indent}  {}", p.name)
         };
 
-        let status_line = Line::from(vec![
+        let mut status_spans = vec![
             status_dot(p.status),
             Span::styled(p.status.to_string(), status_style(p.status)),
-        ]);
+        ];
+        if p.paused {
+            status_spans.push(Span::styled(" paused", Style::default().fg(FG_DIM)));
+        }
+        let status_line = Line::from(status_spans);
 
-        let cells: Vec<Cell> = if is_wide {
+        let mut cells: Vec<Cell> = if is_wide {
             vec![
                 Cell::from(Line::from(Span::styled(
                     name_display,
@@ -430,10 +552,20 @@ fn render_table(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect, termi
             ]
         };
 
+        if show_metrics {
+            let metrics_text = p
+                .metrics
+                .iter()
+                .map(|(k, v)| format!("{k}={}", metrics::format_metric(*v)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            cells.push(Cell::from(Line::from(metrics_text)));
+        }
+
         Row::new(cells).style(Style::default().bg(row_bg).fg(row_fg))
     });
 
-    let widths: Vec<Constraint> = if is_wide {
+    let mut widths: Vec<Constraint> = if is_wide {
         vec![
             Constraint::Percentage(25),
             Constraint::Length(8),
@@ -459,7 +591,34 @@ fn render_table(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect, termi
         ]
     };
 
-    let title = format!(" Processes ({}) ", app.processes.len());
+    if show_metrics {
+        widths.push(Constraint::Min(12));
+    }
+
+    let shown = app
+        .entries
+        .iter()
+        .map(|entry| match entry {
+            ListEntry::Group {
+                members,
+                collapsed: true,
+                ..
+            } => members.len(),
+            ListEntry::Group { .. } => 0,
+            ListEntry::Process { .. } => 1,
+        })
+        .sum::<usize>();
+    let mut title = if app.is_filtered() {
+        format!(" Processes ({shown}/{}) ", app.processes.len())
+    } else {
+        format!(" Processes ({}) ", app.processes.len())
+    };
+    if app.status_filter != StatusFilter::All {
+        title.push_str(&format!("─ status: {} ", app.status_filter.label()));
+    }
+    if !app.search.is_empty() {
+        title.push_str(&format!("─ /{} ", app.search));
+    }
     let table = Table::new(rows, widths)
         .header(header)
         .block(
@@ -486,6 +645,28 @@ fn render_process_list_footer(f: &mut Frame, app: &App, area: ratatui::layout::R
         .add_modifier(Modifier::BOLD);
     let label_style = Style::default().fg(FG_DIM);
 
+    if app.searching {
+        let spans = vec![
+            Span::styled(" ", label_style),
+            Span::styled(
+                format!("/{}", app.search),
+                Style::default().fg(FG_BRIGHT).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled("█", Style::default().fg(ACCENT)),
+            Span::styled("  ", label_style),
+            Span::styled(" ⏎ ", key_style),
+            Span::styled(" keep ", label_style),
+            Span::styled(" Esc ", key_style),
+            Span::styled(" clear ", label_style),
+            Span::styled(" ↑↓ ", key_style),
+            Span::styled(" move ", label_style),
+        ];
+        let footer =
+            Paragraph::new(Text::from(Line::from(spans))).style(Style::default().bg(BG_BASE));
+        f.render_widget(footer, area);
+        return;
+    }
+
     let mut spans = vec![
         Span::styled(" ", label_style),
         Span::styled(" q ", key_style),
@@ -494,19 +675,51 @@ fn render_process_list_footer(f: &mut Frame, app: &App, area: ratatui::layout::R
         Span::styled(" move ", label_style),
         Span::styled(" ⏎ ", key_style),
         Span::styled(" logs ", label_style),
+        Span::styled(" / ", key_style),
+        Span::styled(" search ", label_style),
+        Span::styled(" t ", key_style),
+        Span::styled(
+            format!(" status: {} ", app.status_filter.label()),
+            label_style,
+        ),
+        Span::styled(" ␣ ", key_style),
+        Span::styled(" collapse ", label_style),
+        Span::styled(" l ", key_style),
+        Span::styled(" log pane ", label_style),
+    ];
+    if app.is_filtered() {
+        spans.extend([
+            Span::styled(" Esc ", key_style),
+            Span::styled(" clear filters ", label_style),
+        ]);
+    }
+    if app.log_pane.is_some() {
+        spans.extend([
+            Span::styled(" p ", key_style),
+            Span::styled(" pause ", label_style),
+            Span::styled(" PgUp/Dn ", key_style),
+            Span::styled(" scroll ", label_style),
+        ]);
+    }
+    let scope = if app.group_scope {
+        " scope: group "
+    } else {
+        " scope: process "
+    };
+    spans.extend([
+        Span::styled(" g ", key_style),
+        Span::styled(scope, label_style),
         Span::styled(" s ", key_style),
         Span::styled(" start ", label_style),
         Span::styled(" x ", key_style),
         Span::styled(" stop ", label_style),
         Span::styled(" r ", key_style),
         Span::styled(" restart ", label_style),
-        Span::styled(" S ", key_style),
-        Span::styled(" start all ", label_style),
-        Span::styled(" X ", key_style),
-        Span::styled(" stop all ", label_style),
-        Span::styled(" R ", key_style),
-        Span::styled(" restart all ", label_style),
-    ];
+        Span::styled(" f ", key_style),
+        Span::styled(" flush ", label_style),
+        Span::styled(" S/X/R/F ", key_style),
+        Span::styled(" all ", label_style),
+    ]);
 
     append_status_spans(&mut spans, app);
 
@@ -540,6 +753,8 @@ fn render_log_viewer_footer(f: &mut Frame, app: &App, area: ratatui::layout::Rec
         Span::styled(" stop ", label_style),
         Span::styled(" r ", key_style),
         Span::styled(" restart ", label_style),
+        Span::styled(" f ", key_style),
+        Span::styled(" flush ", label_style),
     ];
 
     append_status_spans(&mut spans, app);
@@ -549,6 +764,34 @@ fn render_log_viewer_footer(f: &mut Frame, app: &App, area: ratatui::layout::Rec
     f.render_widget(footer, area);
 }
 
+fn render_confirmation_footer(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let Some((action, target)) = &app.pending else {
+        return;
+    };
+    let key_style = Style::default()
+        .fg(KEY_FG)
+        .bg(KEY_BG)
+        .add_modifier(Modifier::BOLD);
+    let label_style = Style::default().fg(FG_DIM);
+    let spans = vec![
+        Span::styled(" ", label_style),
+        Span::styled(
+            format!(" {} {target}? ", action.verb()),
+            Style::default()
+                .fg(FG_BRIGHT)
+                .bg(STATUS_RED)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled("  ", label_style),
+        Span::styled(" y ", key_style),
+        Span::styled(" confirm ", label_style),
+        Span::styled(" any other key ", key_style),
+        Span::styled(" cancel ", label_style),
+    ];
+    let footer = Paragraph::new(Text::from(Line::from(spans))).style(Style::default().bg(BG_BASE));
+    f.render_widget(footer, area);
+}
+
 fn append_status_spans<'a>(spans: &mut Vec<Span<'a>>, app: &'a App) {
     let label_style = Style::default().fg(FG_DIM);
 
@@ -583,6 +826,7 @@ fn render_log_viewer(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect)
             scroll_offset,
             auto_scroll,
             stream,
+            ..
         } => (process_name, lines, *scroll_offset, *auto_scroll, stream),
         _ => return,
     };
@@ -684,6 +928,124 @@ fn render_log_viewer(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect)
     f.render_widget(text, inner);
 }
 
+fn render_log_pane(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let Some(pane) = &app.log_pane else {
+        return;
+    };
+
+    let state = if pane.paused {
+        format!("paused, {} new", pane.held.len())
+    } else if pane.scroll_offset > 0 {
+        format!("{} lines up", pane.scroll_offset)
+    } else {
+        "following".to_string()
+    };
+    let title = Line::from(vec![
+        Span::styled(
+            format!(" {} ", pane.process_name),
+            Style::default().fg(FG_TEXT).add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(format!("({state}) "), Style::default().fg(FG_DIM)),
+    ]);
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(ACCENT_DIM))
+        .padding(Padding::horizontal(1));
+
+    let mut inner = block.inner(area);
+    f.render_widget(block, area);
+    if !pane.stats.is_empty() && inner.height >= GRAPH_MIN_PANE_HEIGHT {
+        let split = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)])
+            .split(inner);
+        render_graphs(f, &pane.stats, split[0]);
+        inner = split[1];
+    }
+    app.log_pane_height = inner.height;
+
+    if pane.lines.is_empty() {
+        let (message, style) = match &pane.error {
+            Some(err) => (err.clone(), Style::default().fg(STATUS_RED)),
+            None => ("No output yet".to_string(), Style::default().fg(FG_DIM)),
+        };
+        let text = Paragraph::new(Text::from(Line::from(Span::styled(message, style))))
+            .alignment(Alignment::Center);
+        f.render_widget(text, inner);
+        return;
+    }
+
+    let display_lines: Vec<Line> = pane
+        .visible(inner.height as usize)
+        .map(|line| {
+            let style = if line.stderr {
+                Style::default().fg(STATUS_RED)
+            } else {
+                Style::default().fg(FG_TEXT)
+            };
+            Line::from(Span::styled(line.text.as_str(), style))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(Text::from(display_lines)), inner);
+}
+
+/// One-line CPU and memory sparklines over `GRAPH_WINDOW`, each with its
+/// latest value, above a blank separator line.
+fn render_graphs(f: &mut Frame, samples: &[StatsSample], area: ratatui::layout::Rect) {
+    let label_width = 5;
+    let value_width = 9;
+    let width = area.width.saturating_sub(label_width + value_width) as usize;
+    let latest = samples.last();
+    let rows = [
+        (
+            "cpu",
+            // Tenths of a percent, since sparklines take integers
+            timeseries::downsample(samples, width, |s| s.cpu_percent.map(|c| c * 10.0)),
+            format_cpu(latest.and_then(|s| s.cpu_percent)),
+            STATUS_GREEN,
+        ),
+        (
+            "mem",
+            timeseries::downsample(samples, width, |s| s.memory_bytes.map(|m| m as f64)),
+            format_memory(latest.and_then(|s| s.memory_bytes)),
+            ACCENT,
+        ),
+    ];
+    for (i, (label, values, latest, color)) in rows.into_iter().enumerate() {
+        let row = ratatui::layout::Rect {
+            y: area.y + i as u16,
+            height: 1,
+            ..area
+        };
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(label_width),
+                Constraint::Min(1),
+                Constraint::Length(value_width),
+            ])
+            .split(row);
+        f.render_widget(
+            Paragraph::new(Span::styled(label, Style::default().fg(FG_DIM))),
+            columns[0],
+        );
+        let data: Vec<Option<u64>> = values.iter().map(|v| v.map(|v| v as u64)).collect();
+        f.render_widget(
+            Sparkline::default()
+                .data(&data)
+                .style(Style::default().fg(color)),
+            columns[1],
+        );
+        f.render_widget(
+            Paragraph::new(Line::from(latest).alignment(Alignment::Right))
+                .style(Style::default().fg(FG_TEXT)),
+            columns[2],
+        );
+    }
+}
+
 fn status_style(status: ProcessStatus) -> Style {
     match status {
         ProcessStatus::Online => Style::default().fg(STATUS_GREEN),
@@ -691,6 +1053,10 @@ fn status_style(status: ProcessStatus) -> Style {
         ProcessStatus::Unhealthy => Style::default().fg(STATUS_MAGENTA),
         ProcessStatus::Stopped => Style::default().fg(STATUS_GRAY),
         ProcessStatus::Errored => Style::default().fg(STATUS_RED),
+        ProcessStatus::Flapping => Style::default().fg(STATUS_MAGENTA),
+        ProcessStatus::Succeeded => Style::default().fg(STATUS_GRAY),
+        ProcessStatus::Failed => Style::default().fg(STATUS_RED),
+        ProcessStatus::Suspended => Style::default().fg(STATUS_GRAY),
     }
 }
 
@@ -738,6 +1104,7 @@ struct StatusCounts {
     unhealthy: usize,
     stopped: usize,
     errored: usize,
+    flapping: usize,
 }
 
 fn status_counts(processes: &[ProcessInfo]) -> StatusCounts {
@@ -749,6 +1116,10 @@ fn status_counts(processes: &[ProcessInfo]) -> StatusCounts {
             ProcessStatus::Unhealthy => counts.unhealthy += 1,
             ProcessStatus::Stopped => counts.stopped += 1,
             ProcessStatus::Errored => counts.errored += 1,
+            ProcessStatus::Flapping => counts.flapping += 1,
+            ProcessStatus::Succeeded => counts.stopped += 1,
+            ProcessStatus::Failed => counts.errored += 1,
+            ProcessStatus::Suspended => counts.stopped += 1,
         }
     }
     counts
@@ -761,27 +1132,362 @@ fn load_configs() -> color_eyre::Result<std::collections::HashMap<String, config
 
 const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
 
+/// A row of the process table.
+#[derive(Debug, Clone, PartialEq)]
+enum ListEntry {
+    /// A group header with the indices of its listed members.
+    Group {
+        name: String,
+        members: Vec<usize>,
+        collapsed: bool,
+    },
+    /// A process, by index into `App::processes`.
+    Process { index: usize, grouped: bool },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum StatusFilter {
+    #[default]
+    All,
+    Running,
+    Stopped,
+    Problems,
+}
+
+impl StatusFilter {
+    fn next(self) -> Self {
+        match self {
+            StatusFilter::All => StatusFilter::Running,
+            StatusFilter::Running => StatusFilter::Stopped,
+            StatusFilter::Stopped => StatusFilter::Problems,
+            StatusFilter::Problems => StatusFilter::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            StatusFilter::All => "all",
+            StatusFilter::Running => "running",
+            StatusFilter::Stopped => "stopped",
+            StatusFilter::Problems => "problems",
+        }
+    }
+
+    fn matches(self, status: ProcessStatus) -> bool {
+        match self {
+            StatusFilter::All => true,
+            StatusFilter::Running => matches!(
+                status,
+                ProcessStatus::Online | ProcessStatus::Starting | ProcessStatus::Unhealthy
+            ),
+            StatusFilter::Stopped => matches!(
+                status,
+                ProcessStatus::Stopped | ProcessStatus::Succeeded | ProcessStatus::Suspended
+            ),
+            StatusFilter::Problems => matches!(
+                status,
+                ProcessStatus::Errored
+                    | ProcessStatus::Failed
+                    | ProcessStatus::Flapping
+                    | ProcessStatus::Unhealthy
+            ),
+        }
+    }
+}
+
+/// The group a process is listed under: its `group`, or for a cluster
+/// instance like `web:0`, the cluster name.
+fn group_key(process: &ProcessInfo) -> Option<String> {
+    process.group.clone().or_else(|| {
+        process
+            .name
+            .split_once(':')
+            .map(|(base, _)| base.to_string())
+    })
+}
+
+/// The table rows: processes that match `search` (by name or group,
+/// ignoring case) and `filter`, with each group's members listed under a
+/// header where the first of them would be. Groups are expanded while
+/// searching so matches are never hidden.
+fn build_entries(
+    processes: &[ProcessInfo],
+    search: &str,
+    filter: StatusFilter,
+    collapsed: &HashSet<String>,
+) -> Vec<ListEntry> {
+    let search = search.to_lowercase();
+    let shown: Vec<(usize, Option<String>)> = processes
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| filter.matches(p.status))
+        .map(|(index, p)| (index, group_key(p)))
+        .filter(|(index, group)| {
+            search.is_empty()
+                || processes[*index].name.to_lowercase().contains(&search)
+                || group
+                    .as_ref()
+                    .is_some_and(|g| g.to_lowercase().contains(&search))
+        })
+        .collect();
+
+    let mut entries = Vec::new();
+    let mut listed = HashSet::new();
+    for (index, group) in &shown {
+        let Some(group) = group else {
+            entries.push(ListEntry::Process {
+                index: *index,
+                grouped: false,
+            });
+            continue;
+        };
+        if !listed.insert(group.clone()) {
+            continue;
+        }
+        let members: Vec<usize> = shown
+            .iter()
+            .filter(|(_, g)| g.as_ref() == Some(group))
+            .map(|(index, _)| *index)
+            .collect();
+        let is_collapsed = search.is_empty() && collapsed.contains(group);
+        entries.push(ListEntry::Group {
+            name: group.clone(),
+            members: members.clone(),
+            collapsed: is_collapsed,
+        });
+        if !is_collapsed {
+            entries.extend(members.into_iter().map(|index| ListEntry::Process {
+                index,
+                grouped: true,
+            }));
+        }
+    }
+    entries
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Start,
+    Stop,
+    Restart,
+    Flush,
+}
+
+impl Action {
+    /// Stopping, restarting and flushing interrupt processes or lose logs,
+    /// so they are confirmed first.
+    fn needs_confirmation(self) -> bool {
+        !matches!(self, Action::Start)
+    }
+
+    fn verb(self) -> &'static str {
+        match self {
+            Action::Start => "start",
+            Action::Stop => "stop",
+            Action::Restart => "restart",
+            Action::Flush => "flush logs of",
+        }
+    }
+
+    fn past_tense(self) -> &'static str {
+        match self {
+            Action::Start => "started",
+            Action::Stop => "stopped",
+            Action::Restart => "restarted",
+            Action::Flush => "flushed logs of",
+        }
+    }
+}
+
+/// The processes an action is sent for.
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Process(String),
+    Group { name: String, members: Vec<String> },
+    All,
+}
+
+impl Target {
+    fn names(&self) -> Option<Vec<String>> {
+        match self {
+            Target::Process(name) => Some(vec![name.clone()]),
+            Target::Group { members, .. } => Some(members.clone()),
+            Target::All => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Process(name) => write!(f, "{name}"),
+            Target::Group { name, .. } => write!(f, "group {name}"),
+            Target::All => write!(f, "all processes"),
+        }
+    }
+}
+
+struct PaneLine {
+    stderr: bool,
+    text: String,
+}
+
+/// The live log of the selected process, shown alongside the table.
+struct LogPane {
+    process_name: String,
+    /// The followed log; dropping it ends the stream.
+    stream: Option<client::BackgroundStream>,
+    lines: VecDeque<PaneLine>,
+    /// Lines that arrived while paused, shown on resume.
+    held: Vec<PaneLine>,
+    /// How many lines up from the bottom the view is scrolled.
+    scroll_offset: usize,
+    paused: bool,
+    error: Option<String>,
+    /// Recorded resource samples for the graphs, oldest first.
+    stats: Vec<StatsSample>,
+}
+
+impl LogPane {
+    fn new(process_name: String) -> Self {
+        Self {
+            process_name,
+            stream: None,
+            lines: VecDeque::new(),
+            held: Vec::new(),
+            scroll_offset: 0,
+            paused: false,
+            error: None,
+            stats: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, line: PaneLine) {
+        if self.paused {
+            if self.held.len() == LOG_PANE_SCROLLBACK {
+                self.held.remove(0);
+            }
+            self.held.push(line);
+        } else {
+            self.append(line);
+        }
+    }
+
+    fn append(&mut self, line: PaneLine) {
+        self.lines.push_back(line);
+        // Keep a scrolled-back view still while new lines arrive
+        if self.scroll_offset > 0 {
+            self.scroll_offset += 1;
+        }
+        if self.lines.len() > LOG_PANE_SCROLLBACK {
+            self.lines.pop_front();
+            self.scroll_offset = self.scroll_offset.min(self.lines.len().saturating_sub(1));
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if !self.paused {
+            for line in std::mem::take(&mut self.held) {
+                self.append(line);
+            }
+        }
+    }
+
+    fn scroll_up(&mut self, amount: usize) {
+        let max_offset = self.lines.len().saturating_sub(1);
+        self.scroll_offset = (self.scroll_offset + amount).min(max_offset);
+    }
+
+    fn scroll_down(&mut self, amount: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(amount);
+    }
+
+    /// The lines that fit in `height`, bottom-pinned like the log viewer.
+    fn visible(&self, height: usize) -> impl Iterator<Item = &PaneLine> {
+        let end = self.lines.len().saturating_sub(self.scroll_offset);
+        let start = end.saturating_sub(height);
+        self.lines.range(start..end)
+    }
+
+    fn receive(&mut self) {
+        let Some(stream) = &self.stream else {
+            return;
+        };
+        for response in stream.drain() {
+            match response {
+                Response::LogLine { stream, line, .. } => self.push(PaneLine {
+                    stderr: matches!(stream, Some(log::LogStream::Stderr)),
+                    text: strip_ansi_escape_codes(&line),
+                }),
+                Response::Error { message } => self.error = Some(message),
+                _ => {}
+            }
+        }
+    }
+}
+
 struct App {
     processes: Vec<ProcessInfo>,
+    /// What the table shows, built from `processes` and the filters.
+    entries: Vec<ListEntry>,
     table_state: TableState,
+    search: String,
+    /// Keys go to the search box.
+    searching: bool,
+    status_filter: StatusFilter,
+    collapsed: HashSet<String>,
     last_error: Option<String>,
     status_message: Option<(String, bool, Instant)>, // (message, is_success, timestamp)
     view: View,
     last_visible_height: u16,
+    show_log_pane: bool,
+    log_pane: Option<LogPane>,
+    log_pane_height: u16,
+    /// Actions on the selection apply to its whole group.
+    group_scope: bool,
+    /// An action waiting for the user to confirm it.
+    pending: Option<(Action, Target)>,
+    /// One connection reused across polls and actions, reopened after errors.
+    session: Option<client::Session>,
 }
 
 impl App {
     fn new() -> Self {
         Self {
             processes: Vec::new(),
+            entries: Vec::new(),
             table_state: TableState::default(),
+            search: String::new(),
+            searching: false,
+            status_filter: StatusFilter::All,
+            collapsed: HashSet::new(),
             last_error: None,
             status_message: None,
             view: View::ProcessList,
             last_visible_height: 20,
+            show_log_pane: true,
+            log_pane: None,
+            log_pane_height: 10,
+            group_scope: false,
+            pending: None,
+            session: None,
         }
     }
 
+    fn send(&mut self, paths: &Paths, request: &Request) -> color_eyre::Result<Response> {
+        let session = match &mut self.session {
+            Some(session) => session,
+            None => self.session.insert(client::Session::open(paths)?),
+        };
+        let result = session.request(request);
+        if result.is_err() {
+            // The daemon may have restarted; the next request reconnects
+            self.session = None;
+        }
+        result
+    }
+
     fn set_status(&mut self, message: String, is_success: bool) {
         self.status_message = Some((message, is_success, Instant::now()));
     }
@@ -796,85 +1502,9 @@ impl App {
         })
     }
 
-    fn start_selected(&mut self, paths: &Paths) {
-        let name = match self.viewed_process_name() {
-            Some(n) => n.to_string(),
-            None => return,
-        };
-        self.start_named(&name, paths);
-    }
-
-    fn stop_selected(&mut self, paths: &Paths) {
-        let name = match self.viewed_process_name() {
-            Some(n) => n.to_string(),
-            None => return,
-        };
-        self.stop_named(&name, paths);
-    }
-
-    fn restart_selected(&mut self, paths: &Paths) {
-        let name = match self.viewed_process_name() {
-            Some(n) => n.to_string(),
-            None => return,
-        };
-        self.restart_named(&name, paths);
-    }
-
-    fn start_all(&mut self, paths: &Paths) {
-        let configs = match load_configs() {
-            Ok(c) => c,
-            Err(e) => {
-                self.set_status(format!("config error: {e}"), false);
-                return;
-            }
-        };
-        match client::send_request(
-            paths,
-            &Request::Start {
-                configs,
-                names: None,
-                env: None,
-                wait: false,
-                path: std::env::var("PATH").ok(),
-            },
-        ) {
-            Ok(Response::Success { .. }) => {
-                self.set_status("started all".to_string(), true);
-                self.refresh(paths);
-            }
-            Ok(Response::Error { message }) => self.set_status(message, false),
-            Ok(_) => self.set_status("unexpected response".to_string(), false),
-            Err(e) => self.set_status(e.to_string(), false),
-        }
-    }
-
-    fn stop_all(&mut self, paths: &Paths) {
-        match client::send_request(paths, &Request::Stop { names: None }) {
-            Ok(Response::Success { .. }) => {
-                self.set_status("stopped all".to_string(), true);
-                self.refresh(paths);
-            }
-            Ok(Response::Error { message }) => self.set_status(message, false),
-            Ok(_) => self.set_status("unexpected response".to_string(), false),
-            Err(e) => self.set_status(e.to_string(), false),
-        }
-    }
-
-    fn restart_all(&mut self, paths: &Paths) {
-        match client::send_request(paths, &Request::Restart { names: None }) {
-            Ok(Response::Success { .. }) => {
-                self.set_status("restarted all".to_string(), true);
-                self.refresh(paths);
-            }
-            Ok(Response::Error { message }) => self.set_status(message, false),
-            Ok(_) => self.set_status("unexpected response".to_string(), false),
-            Err(e) => self.set_status(e.to_string(), false),
-        }
-    }
-
     fn refresh(&mut self, paths: &Paths) {
-        match client::send_request(paths, &Request::List) {
-            Ok(Response::ProcessList { processes }) => {
+        match self.send(paths, &Request::List { filter: None }) {
+            Ok(Response::ProcessList { processes, .. }) => {
                 self.last_error = None;
                 self.set_processes(processes);
             }
@@ -888,16 +1518,28 @@ impl App {
     }
 
     fn set_processes(&mut self, processes: Vec<ProcessInfo>) {
-        let selected_name = self.selected_name().map(|name| name.to_string());
         self.processes = processes;
+        self.rebuild_entries();
+    }
 
-        if self.processes.is_empty() {
+    /// Recomputes the table rows after the processes, filters or collapsed
+    /// groups change, keeping the same row selected when it is still shown.
+    fn rebuild_entries(&mut self) {
+        let selected = self.selected_key();
+        self.entries = build_entries(
+            &self.processes,
+            &self.search,
+            self.status_filter,
+            &self.collapsed,
+        );
+
+        if self.entries.is_empty() {
             self.table_state.select(None);
             return;
         }
 
-        if let Some(name) = selected_name
-            && let Some(idx) = self.processes.iter().position(|p| p.name == name)
+        if let Some(key) = selected
+            && let Some(idx) = self.entries.iter().position(|e| self.entry_key(e) == key)
         {
             self.table_state.select(Some(idx));
             return;
@@ -906,20 +1548,39 @@ impl App {
         let idx = self
             .table_state
             .selected()
-            .filter(|&i| i < self.processes.len())
+            .map(|i| i.min(self.entries.len() - 1))
             .unwrap_or(0);
         self.table_state.select(Some(idx));
     }
 
-    fn selected_name(&self) -> Option<&str> {
+    /// Identifies a row across rebuilds: a process by name, a group header
+    /// by group name.
+    fn entry_key(&self, entry: &ListEntry) -> (bool, String) {
+        match entry {
+            ListEntry::Group { name, .. } => (true, name.clone()),
+            ListEntry::Process { index, .. } => (false, self.processes[*index].name.clone()),
+        }
+    }
+
+    fn selected_key(&self) -> Option<(bool, String)> {
+        self.selected_entry().map(|entry| self.entry_key(entry))
+    }
+
+    fn selected_entry(&self) -> Option<&ListEntry> {
         self.table_state
             .selected()
-            .and_then(|idx| self.processes.get(idx))
-            .map(|p| p.name.as_str())
+            .and_then(|idx| self.entries.get(idx))
+    }
+
+    fn selected_name(&self) -> Option<&str> {
+        match self.selected_entry()? {
+            ListEntry::Process { index, .. } => Some(self.processes[*index].name.as_str()),
+            ListEntry::Group { .. } => None,
+        }
     }
 
     fn next(&mut self) {
-        let len = self.processes.len();
+        let len = self.entries.len();
         if len == 0 {
             return;
         }
@@ -931,7 +1592,7 @@ impl App {
     }
 
     fn previous(&mut self) {
-        let len = self.processes.len();
+        let len = self.entries.len();
         if len == 0 {
             return;
         }
@@ -943,14 +1604,163 @@ impl App {
     }
 
     fn first(&mut self) {
-        if !self.processes.is_empty() {
+        if !self.entries.is_empty() {
             self.table_state.select(Some(0));
         }
     }
 
     fn last(&mut self) {
-        if !self.processes.is_empty() {
-            self.table_state.select(Some(self.processes.len() - 1));
+        if !self.entries.is_empty() {
+            self.table_state.select(Some(self.entries.len() - 1));
+        }
+    }
+
+    // ── Filtering and groups ────────────────────────────────────────
+
+    fn start_search(&mut self) {
+        self.searching = true;
+    }
+
+    fn search_input(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => self.search.push(c),
+            KeyCode::Backspace => {
+                self.search.pop();
+            }
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.searching = false;
+                self.search.clear();
+            }
+            KeyCode::Down => return self.next(),
+            KeyCode::Up => return self.previous(),
+            _ => return,
+        }
+        self.rebuild_entries();
+    }
+
+    fn cycle_status_filter(&mut self) {
+        self.status_filter = self.status_filter.next();
+        self.rebuild_entries();
+    }
+
+    /// Whether a search or status filter hides any processes.
+    fn is_filtered(&self) -> bool {
+        !self.search.is_empty() || self.status_filter != StatusFilter::All
+    }
+
+    fn clear_filters(&mut self) {
+        self.search.clear();
+        self.status_filter = StatusFilter::All;
+        self.rebuild_entries();
+    }
+
+    /// The group of the selected row: the header itself, or the group a
+    /// listed member belongs to.
+    fn selected_group(&self) -> Option<String> {
+        match self.selected_entry()? {
+            ListEntry::Group { name, .. } => Some(name.clone()),
+            ListEntry::Process {
+                index,
+                grouped: true,
+            } => group_key(&self.processes[*index]),
+            ListEntry::Process { .. } => None,
+        }
+    }
+
+    fn set_collapsed(&mut self, collapse: bool) {
+        let Some(group) = self.selected_group() else {
+            return;
+        };
+        if collapse {
+            self.collapsed.insert(group.clone());
+        } else {
+            self.collapsed.remove(&group);
+        }
+        self.rebuild_entries();
+        // Collapsing from a member moves the selection up to its header
+        if let Some(idx) = self
+            .entries
+            .iter()
+            .position(|e| matches!(e, ListEntry::Group { name, .. } if *name == group))
+            && collapse
+        {
+            self.table_state.select(Some(idx));
+        }
+    }
+
+    fn toggle_collapsed(&mut self) {
+        let collapse = self
+            .selected_group()
+            .is_some_and(|group| !self.collapsed.contains(&group));
+        self.set_collapsed(collapse);
+    }
+
+    // ── Log pane methods ────────────────────────────────────────────
+
+    fn toggle_log_pane(&mut self) {
+        self.show_log_pane = !self.show_log_pane;
+    }
+
+    /// Follows the selected process's log, switching streams when the
+    /// selection changes.
+    fn sync_log_pane(&mut self, paths: &Paths) {
+        let selected = self
+            .selected_name()
+            .filter(|_| self.show_log_pane)
+            .map(|name| name.to_string());
+        let Some(name) = selected else {
+            self.log_pane = None;
+            return;
+        };
+        if self
+            .log_pane
+            .as_ref()
+            .is_some_and(|pane| pane.process_name == name)
+        {
+            return;
+        }
+
+        let mut pane = LogPane::new(name.clone());
+        let request = Request::Log {
+            name: Some(name),
+            lines: LOG_PANE_LINES,
+            follow: true,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        };
+        match client::BackgroundStream::spawn(paths, &request) {
+            Ok(stream) => pane.stream = Some(stream),
+            Err(e) => pane.error = Some(e.to_string()),
+        }
+        self.log_pane = Some(pane);
+        self.refresh_graphs(paths);
+    }
+
+    /// Fetches the recorded samples of the log pane's process. The graphs
+    /// are left empty when the daemon keeps no history.
+    fn refresh_graphs(&mut self, paths: &Paths) {
+        let Some(name) = self.log_pane.as_ref().map(|p| p.process_name.clone()) else {
+            return;
+        };
+        let request = Request::Stats {
+            name,
+            since_secs: Some(GRAPH_WINDOW.as_secs()),
+        };
+        let samples = match self.send(paths, &request) {
+            Ok(Response::Stats { samples, .. }) => samples,
+            _ => Vec::new(),
+        };
+        if let Some(pane) = &mut self.log_pane {
+            pane.stats = samples;
+        }
+    }
+
+    fn receive_logs(&mut self) {
+        if let Some(pane) = &mut self.log_pane {
+            pane.receive();
         }
     }
 
@@ -968,21 +1778,51 @@ impl App {
             Some(n) => n.to_string(),
             None => return,
         };
+        let logs = self.log_files(paths, &name);
         self.view = View::LogViewer {
             process_name: name,
             lines: Vec::new(),
             scroll_offset: 0,
             auto_scroll: true,
             stream: LogSource::Stdout,
+            logs,
         };
-        self.refresh_logs(paths);
+        self.refresh_logs();
+    }
+
+    /// Where the daemon logs `name`'s output, which `log_combine` and
+    /// `merge_logs` change.
+    fn log_files(&mut self, paths: &Paths, name: &str) -> log::LogFiles {
+        let request = Request::Info {
+            name: name.to_string(),
+        };
+        let info = match self.send(paths, &request) {
+            Ok(Response::ProcessDetail { info }) => Some(info),
+            _ => None,
+        };
+        match info
+            .as_ref()
+            .and_then(|info| info.stdout_log.clone().zip(info.stderr_log.clone()))
+        {
+            Some((stdout, stderr)) => log::LogFiles {
+                stdout: stdout.into(),
+                stderr: stderr.into(),
+                // Lines of other instances are tagged with theirs
+                instance: Some(name.to_string()),
+            },
+            None => log::LogFiles {
+                stdout: paths.stdout_log(name),
+                stderr: paths.stderr_log(name),
+                instance: None,
+            },
+        }
     }
 
     fn close_log_viewer(&mut self) {
         self.view = View::ProcessList;
     }
 
-    fn toggle_log_stream(&mut self, paths: &Paths) {
+    fn toggle_log_stream(&mut self) {
         if let View::LogViewer {
             stream,
             lines,
@@ -999,23 +1839,37 @@ impl App {
             *scroll_offset = 0;
             *auto_scroll = true;
         }
-        self.refresh_logs(paths);
+        self.refresh_logs();
     }
 
-    fn refresh_logs(&mut self, paths: &Paths) {
+    fn refresh_logs(&mut self) {
         if let View::LogViewer {
-            process_name,
             lines,
             scroll_offset,
             auto_scroll,
             stream,
+            logs,
+            ..
         } = &mut self.view
         {
-            let path = match stream {
-                LogSource::Stdout => paths.stdout_log(process_name),
-                LogSource::Stderr => paths.stderr_log(process_name),
+            let stream = match stream {
+                LogSource::Stdout => log::LogStream::Stdout,
+                LogSource::Stderr => log::LogStream::Stderr,
             };
-            if let Ok(new_lines) = log::tail_file(&path, 200) {
+            let path = logs.path(&stream);
+            if logs.is_combined() {
+                // The other stream's lines are in between
+                if let Ok(all) = log::tail_file(path, LOG_PANE_SCROLLBACK) {
+                    let mut kept: Vec<String> = all
+                        .iter()
+                        .filter_map(|line| logs.untag(line))
+                        .filter(|(line_stream, _)| *line_stream == stream)
+                        .map(|(_, line)| strip_ansi_escape_codes(line))
+                        .collect();
+                    kept.drain(..kept.len().saturating_sub(LOG_VIEWER_LINES));
+                    *lines = kept;
+                }
+            } else if let Ok(new_lines) = log::tail_file(path, LOG_VIEWER_LINES) {
                 *lines = new_lines
                     .into_iter()
                     .map(|line| strip_ansi_escape_codes(&line))
@@ -1092,61 +1946,132 @@ impl App {
         }
     }
 
-    fn start_named(&mut self, name: &str, paths: &Paths) {
-        let configs = match load_configs() {
-            Ok(c) => c,
-            Err(e) => {
-                self.set_status(format!("config error: {e}"), false);
-                return;
-            }
+    // ── Actions ─────────────────────────────────────────────────────
+
+    fn toggle_scope(&mut self) {
+        self.group_scope = !self.group_scope;
+        let scope = if self.group_scope {
+            "actions apply to the selected group"
+        } else {
+            "actions apply to the selected process"
         };
-        match client::send_request(
-            paths,
-            &Request::Start {
-                configs,
-                names: Some(vec![name.to_string()]),
-                env: None,
-                wait: false,
-                path: std::env::var("PATH").ok(),
-            },
-        ) {
-            Ok(Response::Success { .. }) => {
-                self.set_status(format!("started {name}"), true);
-                self.refresh(paths);
-            }
-            Ok(Response::Error { message }) => self.set_status(message, false),
-            Ok(_) => self.set_status("unexpected response".to_string(), false),
-            Err(e) => self.set_status(e.to_string(), false),
+        self.set_status(scope.to_string(), true);
+    }
+
+    /// What an action on the selection affects: the viewed or selected
+    /// process, or in group scope every process in its group.
+    fn selected_target(&self) -> Result<Target, String> {
+        // A group header stands for the members listed under it
+        if matches!(self.view, View::ProcessList)
+            && let Some(ListEntry::Group { name, members, .. }) = self.selected_entry()
+        {
+            return Ok(Target::Group {
+                name: name.clone(),
+                members: members
+                    .iter()
+                    .map(|&i| self.processes[i].name.clone())
+                    .collect(),
+            });
         }
+        let Some(name) = self.viewed_process_name() else {
+            return Err("no process selected".to_string());
+        };
+        if !self.group_scope || matches!(self.view, View::LogViewer { .. }) {
+            return Ok(Target::Process(name.to_string()));
+        }
+        let group = self
+            .processes
+            .iter()
+            .find(|p| p.name == name)
+            .and_then(|p| p.group.clone())
+            .ok_or_else(|| format!("{name} is not in a group"))?;
+        let members = self
+            .processes
+            .iter()
+            .filter(|p| p.group.as_deref() == Some(group.as_str()))
+            .map(|p| p.name.clone())
+            .collect();
+        Ok(Target::Group {
+            name: group,
+            members,
+        })
     }
 
-    fn stop_named(&mut self, name: &str, paths: &Paths) {
-        match client::send_request(
-            paths,
-            &Request::Stop {
-                names: Some(vec![name.to_string()]),
-            },
-        ) {
-            Ok(Response::Success { .. }) => {
-                self.set_status(format!("stopped {name}"), true);
-                self.refresh(paths);
-            }
-            Ok(Response::Error { message }) => self.set_status(message, false),
-            Ok(_) => self.set_status("unexpected response".to_string(), false),
-            Err(e) => self.set_status(e.to_string(), false),
+    fn act_on_selected(&mut self, action: Action, paths: &Paths) {
+        match self.selected_target() {
+            Ok(target) => self.request_action(action, target, paths),
+            Err(message) => self.set_status(message, false),
+        }
+    }
+
+    /// Runs `action`, or asks first if it would stop processes or lose logs.
+    fn request_action(&mut self, action: Action, target: Target, paths: &Paths) {
+        if action.needs_confirmation() {
+            self.pending = Some((action, target));
+        } else {
+            self.run_action(action, target, paths);
         }
     }
 
-    fn restart_named(&mut self, name: &str, paths: &Paths) {
-        match client::send_request(
-            paths,
-            &Request::Restart {
-                names: Some(vec![name.to_string()]),
+    fn confirm_pending(&mut self, paths: &Paths) {
+        if let Some((action, target)) = self.pending.take() {
+            self.run_action(action, target, paths);
+        }
+    }
+
+    fn cancel_pending(&mut self) {
+        if self.pending.take().is_some() {
+            self.set_status("cancelled".to_string(), true);
+        }
+    }
+
+    fn run_action(&mut self, action: Action, target: Target, paths: &Paths) {
+        let names = target.names();
+        let request = match action {
+            Action::Start => {
+                let configs = match load_configs() {
+                    Ok(c) => c,
+                    Err(e) => {
+                        self.set_status(format!("config error: {e}"), false);
+                        return;
+                    }
+                };
+                Request::Start {
+                    configs,
+                    names,
+                    env: None,
+                    wait: false,
+                    path: std::env::var("PATH").ok(),
+                    force: false,
+                    namespace: None,
+                    progress: false,
+                }
+            }
+            Action::Stop => Request::Stop {
+                names,
+                except: None,
+                namespace: None,
+                no_dependents: false,
+            },
+            Action::Restart => Request::Restart {
+                names,
+                except: None,
+                namespace: None,
+                stagger_ms: None,
+            },
+            Action::Flush => Request::Flush {
+                names,
+                all_files: false,
+                namespace: None,
             },
-        ) {
+        };
+        match self.send(paths, &request) {
             Ok(Response::Success { .. }) => {
-                self.set_status(format!("restarted {name}"), true);
+                self.set_status(format!("{} {target}", action.past_tense()), true);
                 self.refresh(paths);
+                if action == Action::Flush {
+                    self.refresh_logs();
+                }
             }
             Ok(Response::Error { message }) => self.set_status(message, false),
             Ok(_) => self.set_status("unexpected response".to_string(), false),
@@ -1175,7 +2100,13 @@ impl Drop for TerminalGuard {
 
 #[cfg(test)]
 mod tests {
-    use super::strip_ansi_escape_codes;
+    use super::{
+        Action, App, LOG_PANE_SCROLLBACK, ListEntry, LogPane, PaneLine, StatusFilter, Target,
+        strip_ansi_escape_codes,
+    };
+    use crate::paths::Paths;
+    use crate::protocol::{ProcessInfo, ProcessStatus};
+    use crossterm::event::KeyCode;
 
     #[test]
     fn strips_csi_color_sequences() {
@@ -1188,4 +2119,221 @@ mod tests {
         let line = "plain log line";
         assert_eq!(strip_ansi_escape_codes(line), "plain log line");
     }
+
+    fn pane_with(count: usize) -> LogPane {
+        let mut pane = LogPane::new("web".to_string());
+        for i in 0..count {
+            pane.push(PaneLine {
+                stderr: false,
+                text: format!("line {i}"),
+            });
+        }
+        pane
+    }
+
+    fn visible_text(pane: &LogPane, height: usize) -> Vec<&str> {
+        pane.visible(height).map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn log_pane_scrollback_stays_put_as_lines_arrive() {
+        let mut pane = pane_with(5);
+        assert_eq!(visible_text(&pane, 2), vec!["line 3", "line 4"]);
+        pane.scroll_up(2);
+        assert_eq!(visible_text(&pane, 2), vec!["line 1", "line 2"]);
+        pane.push(PaneLine {
+            stderr: false,
+            text: "line 5".to_string(),
+        });
+        assert_eq!(visible_text(&pane, 2), vec!["line 1", "line 2"]);
+        pane.scroll_down(10);
+        assert_eq!(visible_text(&pane, 2), vec!["line 4", "line 5"]);
+
+        let pane = pane_with(LOG_PANE_SCROLLBACK + 10);
+        assert_eq!(pane.lines.len(), LOG_PANE_SCROLLBACK);
+        assert_eq!(pane.lines[0].text, "line 10");
+    }
+
+    #[test]
+    fn log_pane_holds_lines_while_paused() {
+        let mut pane = pane_with(2);
+        pane.toggle_pause();
+        pane.push(PaneLine {
+            stderr: true,
+            text: "held".to_string(),
+        });
+        assert_eq!(visible_text(&pane, 5), vec!["line 0", "line 1"]);
+        assert_eq!(pane.held.len(), 1);
+        pane.toggle_pause();
+        assert_eq!(visible_text(&pane, 5), vec!["line 0", "line 1", "held"]);
+        assert!(pane.held.is_empty());
+    }
+
+    fn info(name: &str, group: Option<&str>) -> ProcessInfo {
+        ProcessInfo {
+            name: name.to_string(),
+            pid: None,
+            status: ProcessStatus::Online,
+            uptime: None,
+            restarts: 0,
+            cpu_percent: None,
+            memory_bytes: None,
+            resources: None,
+            health: None,
+            group: group.map(str::to_string),
+            tags: Vec::new(),
+            metrics: Default::default(),
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn group_scope_targets_the_selected_group() {
+        let mut app = App::new();
+        app.set_processes(vec![
+            info("api", Some("backend")),
+            info("web", None),
+            info("worker", Some("backend")),
+        ]);
+        // Past the "backend" header
+        app.next();
+        assert_eq!(
+            app.selected_target(),
+            Ok(Target::Process("api".to_string()))
+        );
+        app.toggle_scope();
+        assert_eq!(
+            app.selected_target(),
+            Ok(Target::Group {
+                name: "backend".to_string(),
+                members: vec!["api".to_string(), "worker".to_string()],
+            })
+        );
+        app.last();
+        assert_eq!(
+            app.selected_target(),
+            Err("web is not in a group".to_string())
+        );
+    }
+
+    fn with_status(mut process: ProcessInfo, status: ProcessStatus) -> ProcessInfo {
+        process.status = status;
+        process
+    }
+
+    fn rows(app: &App) -> Vec<String> {
+        app.entries
+            .iter()
+            .map(|entry| match entry {
+                ListEntry::Group {
+                    name, collapsed, ..
+                } => format!("{}{name}", if *collapsed { "+" } else { "-" }),
+                ListEntry::Process { index, grouped } => {
+                    let indent = if *grouped { "  " } else { "" };
+                    format!("{indent}{}", app.processes[*index].name)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_and_clusters_list_under_headers() {
+        let mut app = App::new();
+        app.set_processes(vec![
+            info("api", Some("backend")),
+            info("web:0", None),
+            info("cron", None),
+            info("web:1", None),
+            info("worker", Some("backend")),
+        ]);
+        assert_eq!(
+            rows(&app),
+            vec![
+                "-backend", "  api", "  worker", "-web", "  web:0", "  web:1", "cron"
+            ]
+        );
+
+        // Collapsing from a member moves the selection to its header
+        app.next();
+        app.set_collapsed(true);
+        assert_eq!(app.table_state.selected(), Some(0));
+        assert_eq!(
+            rows(&app),
+            vec!["+backend", "-web", "  web:0", "  web:1", "cron"]
+        );
+        assert_eq!(
+            app.selected_target(),
+            Ok(Target::Group {
+                name: "backend".to_string(),
+                members: vec!["api".to_string(), "worker".to_string()],
+            })
+        );
+        assert_eq!(app.selected_name(), None);
+        app.toggle_collapsed();
+        assert_eq!(rows(&app)[0], "-backend");
+    }
+
+    #[test]
+    fn search_and_status_filter_narrow_the_list() {
+        let mut app = App::new();
+        app.set_processes(vec![
+            info("api", Some("backend")),
+            with_status(info("worker", Some("backend")), ProcessStatus::Errored),
+            with_status(info("cron", None), ProcessStatus::Stopped),
+            info("web:0", None),
+        ]);
+        app.collapsed.insert("backend".to_string());
+
+        app.start_search();
+        for c in "WOR".chars() {
+            app.search_input(KeyCode::Char(c));
+        }
+        // Groups open up while searching so matches are visible
+        assert_eq!(rows(&app), vec!["-backend", "  worker"]);
+        app.search_input(KeyCode::Enter);
+        assert!(!app.searching);
+        assert!(app.is_filtered());
+
+        app.search_input(KeyCode::Esc);
+        assert_eq!(rows(&app), vec!["+backend", "cron", "-web", "  web:0"]);
+
+        // Searching by group name lists all its members
+        app.search = "back".to_string();
+        app.rebuild_entries();
+        assert_eq!(rows(&app), vec!["-backend", "  api", "  worker"]);
+        app.clear_filters();
+
+        app.cycle_status_filter();
+        assert_eq!(app.status_filter, StatusFilter::Running);
+        assert_eq!(rows(&app), vec!["+backend", "-web", "  web:0"]);
+        app.cycle_status_filter();
+        assert_eq!(rows(&app), vec!["cron"]);
+        assert_eq!(app.selected_name(), Some("cron"));
+        app.cycle_status_filter();
+        assert_eq!(rows(&app), vec!["+backend"]);
+        assert_eq!(
+            app.selected_target(),
+            Ok(Target::Group {
+                name: "backend".to_string(),
+                members: vec!["worker".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn destructive_actions_wait_for_confirmation() {
+        let paths = Paths::with_base(std::env::temp_dir().join("pm3-tui-test"));
+        let mut app = App::new();
+        app.set_processes(vec![info("web", None)]);
+        for action in [Action::Stop, Action::Restart, Action::Flush] {
+            app.act_on_selected(action, &paths);
+            assert_eq!(
+                app.pending,
+                Some((action, Target::Process("web".to_string())))
+            );
+            app.cancel_pending();
+            assert_eq!(app.pending, None);
+        }
+        assert!(!Action::Start.needs_confirmation());
+    }
 }
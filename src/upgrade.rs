@@ -0,0 +1,385 @@
+//! `pm3 daemon upgrade`: the daemon re-execs itself with the pm3 binary now
+//! installed at its path. It keeps its pid, so a service manager keeps
+//! tracking it, and leaves its listening socket and its processes' output
+//! open across the exec, described in a handover file the new daemon reads
+//! to adopt them. No managed process is stopped.
+
+use crate::dump::DumpEntry;
+use crate::protocol::ProcessStatus;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::sync::Notify;
+
+/// Version of the handover format written by this pm3. The new binary must
+/// read at least this version, which the daemon checks before it execs.
+pub const HANDOVER_VERSION: u32 = 1;
+
+/// How long an upgrade waits for in-flight requests, including the upgrade
+/// request's own response, before the exec.
+#[cfg(unix)]
+const DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// One process as handed to the new daemon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandedOver {
+    #[serde(flatten)]
+    pub entry: DumpEntry,
+    pub status: ProcessStatus,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub paused: bool,
+    /// The status to restore on continue, while frozen with SIGSTOP.
+    #[serde(default)]
+    pub suspended: Option<ProcessStatus>,
+    #[serde(default)]
+    pub crashes: u32,
+    /// The PTY master the process writes its stdout to, left open.
+    #[serde(default)]
+    pub stdout_fd: Option<i32>,
+    /// The pipe the process writes its stderr to, left open.
+    #[serde(default)]
+    pub stderr_fd: Option<i32>,
+    /// The `pm3 log-relay` copying the process's output instead, still a
+    /// child of the daemon.
+    #[serde(default)]
+    pub log_relay: Option<u32>,
+    /// The pipe the relay sends the lines it copies on, left open.
+    #[serde(default)]
+    pub relay_feed_fd: Option<i32>,
+}
+
+/// Everything the new daemon takes over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handover {
+    pub version: u32,
+    /// The daemon's socket, still listening.
+    pub listener_fd: i32,
+    /// Seconds the daemon had been up, so its uptime carries on.
+    pub uptime: u64,
+    /// Upgrades so far, counting this one.
+    pub upgrades: u32,
+    pub processes: Vec<HandedOver>,
+}
+
+impl Handover {
+    /// Reads and removes the handover file at `path`.
+    pub fn take(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let _ = std::fs::remove_file(path);
+        let handover: Self = serde_json::from_str(&data)
+            .map_err(|e| format!("failed to parse {}: {e}", path.display()))?;
+        if handover.version > HANDOVER_VERSION {
+            return Err(format!(
+                "handover is format version {}, but this pm3 reads up to version {HANDOVER_VERSION}",
+                handover.version
+            ));
+        }
+        Ok(handover)
+    }
+}
+
+/// Whether this daemon can upgrade in place, and the signal that it should.
+#[derive(Debug, Default)]
+pub struct Upgrader {
+    /// The binary to re-exec, as it was found when the daemon started, since
+    /// replacing the file makes the running one's own path point nowhere.
+    /// Unset for daemons embedded in another program.
+    exe: Option<PathBuf>,
+    upgrades: u32,
+    requested: Notify,
+}
+
+impl Upgrader {
+    pub fn new(exe: PathBuf, upgrades: u32) -> Self {
+        Self {
+            exe: Some(exe),
+            upgrades,
+            requested: Notify::new(),
+        }
+    }
+
+    /// Times this daemon has been upgraded in place since it started.
+    pub fn upgrades(&self) -> u32 {
+        self.upgrades
+    }
+
+    /// Checks the binary at the daemon's path can take over from it,
+    /// returning the version of pm3 it runs.
+    pub async fn check(&self) -> Result<String, String> {
+        if cfg!(not(unix)) {
+            return Err("daemon upgrade is not supported on this platform".to_string());
+        }
+        let Some(exe) = &self.exe else {
+            return Err("this daemon is embedded in another program and can't upgrade".to_string());
+        };
+        let output = tokio::process::Command::new(exe)
+            .arg("--handover-version")
+            .stdin(std::process::Stdio::null())
+            .output()
+            .await
+            .map_err(|e| format!("failed to run {}: {e}", exe.display()))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let Some((reads, version)) = output
+            .status
+            .success()
+            .then(|| stdout.trim().split_once(' '))
+            .flatten()
+        else {
+            return Err(format!(
+                "{} can't take over from this daemon: it predates `pm3 daemon upgrade`",
+                exe.display()
+            ));
+        };
+        match reads.parse::<u32>() {
+            Ok(reads) if reads >= HANDOVER_VERSION => Ok(version.to_string()),
+            _ => Err(format!(
+                "{} (pm3 {version}) can't read this daemon's handover; restart the daemon instead",
+                exe.display()
+            )),
+        }
+    }
+
+    pub fn request(&self) {
+        self.requested.notify_one();
+    }
+
+    /// Resolves once an upgrade is requested.
+    pub async fn requested(&self) {
+        self.requested.notified().await;
+    }
+
+    #[cfg(unix)]
+    fn exe(&self) -> Option<&Path> {
+        self.exe.as_deref()
+    }
+}
+
+/// Hands the listener and every process over to the binary at the daemon's
+/// path, replacing this process. Only returns if that fails, with the error.
+#[cfg(unix)]
+pub async fn exec(
+    manager: &crate::manager::Manager,
+    listener: &crate::sys::IpcListener,
+) -> color_eyre::Report {
+    use std::os::fd::{AsFd, AsRawFd};
+
+    let deadline = tokio::time::Instant::now() + DRAIN_TIMEOUT;
+    while manager.activity().idle_for().is_none() && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    let upgrader = manager.upgrader();
+    let Some(exe) = upgrader.exe() else {
+        return color_eyre::eyre::eyre!("this daemon can't upgrade");
+    };
+    let paths = manager.paths();
+
+    // Held until the exec, so no process changes after it is written down
+    let processes = manager.processes();
+    let table = processes.write().await;
+    let mut inherited = vec![listener.as_fd()];
+    let mut handed_over = Vec::new();
+    for managed in table.values() {
+        let fds = &managed.output_fds;
+        let [stdout_fd, stderr_fd, relay_feed_fd] = [&fds.stdout, &fds.stderr, &fds.relay_feed]
+            .map(|fd| {
+                let fd = fd.as_ref().filter(|_| managed.pid.is_some())?.as_fd();
+                inherited.push(fd);
+                Some(fd.as_raw_fd())
+            });
+        handed_over.push(HandedOver {
+            entry: DumpEntry {
+                name: managed.name.clone(),
+                config: managed.config.clone(),
+                pid: managed.pid,
+                restarts: managed.restarts,
+            },
+            status: managed.status,
+            exit_code: managed.exit_code,
+            paused: managed.paused,
+            suspended: managed.suspended.map(|(status, _)| status),
+            crashes: managed.crashes,
+            stdout_fd,
+            stderr_fd,
+            log_relay: managed.log_relay.filter(|_| managed.pid.is_some()),
+            relay_feed_fd,
+        });
+    }
+    let handover = Handover {
+        version: HANDOVER_VERSION,
+        listener_fd: listener.as_raw_fd(),
+        uptime: manager.uptime().as_secs(),
+        upgrades: upgrader.upgrades() + 1,
+        processes: handed_over,
+    };
+
+    let path = paths.handover_file();
+    let err = match write_and_exec(&handover, &path, exe, &inherited) {
+        Ok(never) => match never {},
+        Err(e) => e,
+    };
+    for fd in inherited {
+        let _ = crate::sys::set_inheritable(fd, false);
+    }
+    let _ = std::fs::remove_file(&path);
+    err
+}
+
+/// Writes the handover to `path` and execs `exe` to take it over, with the
+/// fds it names left open.
+#[cfg(unix)]
+fn write_and_exec(
+    handover: &Handover,
+    path: &Path,
+    exe: &Path,
+    inherited: &[std::os::fd::BorrowedFd<'_>],
+) -> color_eyre::Result<std::convert::Infallible> {
+    use color_eyre::eyre::WrapErr;
+    use std::os::unix::process::CommandExt;
+
+    for fd in inherited {
+        crate::sys::set_inheritable(*fd, true)?;
+    }
+    let json = serde_json::to_string_pretty(handover)?;
+    std::fs::write(path, json).wrap_err_with(|| format!("failed to write {}", path.display()))?;
+
+    tracing::info!(
+        "upgrading: handing {} process(es) over to {}",
+        handover.processes.len(),
+        exe.display()
+    );
+    let err = std::process::Command::new(exe)
+        .arg("--handover")
+        .arg(path)
+        .args(exec_args(std::env::args_os().skip(1)))
+        .exec();
+    Err(err).wrap_err_with(|| format!("failed to exec {}", exe.display()))
+}
+
+/// The daemon's own arguments without the `--handover` it may have been
+/// started with by an earlier upgrade.
+#[cfg(unix)]
+fn exec_args(mut args: impl Iterator<Item = std::ffi::OsString>) -> Vec<std::ffi::OsString> {
+    let mut kept = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--handover" {
+            args.next();
+        } else if !arg.to_string_lossy().starts_with("--handover=") {
+            kept.push(arg);
+        }
+    }
+    kept
+}
+
+/// Takes over the socket the previous daemon left listening.
+///
+/// # Safety
+///
+/// Must be called once per handover, which must come from the daemon this
+/// process replaced.
+#[cfg(unix)]
+pub unsafe fn listener(handover: &Handover) -> std::io::Result<crate::sys::IpcListener> {
+    // SAFETY: upheld by the caller
+    let fd = unsafe { crate::sys::adopt_fd(handover.listener_fd)? };
+    let listener = std::os::unix::net::UnixListener::from(fd);
+    listener.set_nonblocking(true)?;
+    tokio::net::UnixListener::from_std(listener)
+}
+
+/// Takes over the output of a handed-over process.
+///
+/// # Safety
+///
+/// Must be called once per process, with a handover from the daemon this
+/// process replaced.
+#[cfg(unix)]
+pub unsafe fn output_fds(process: &HandedOver) -> crate::process::OutputFds {
+    // SAFETY: upheld by the caller
+    let adopt = |fd: Option<i32>| fd.and_then(|fd| unsafe { crate::sys::adopt_fd(fd) }.ok());
+    crate::process::OutputFds {
+        stdout: adopt(process.stdout_fd),
+        stderr: adopt(process.stderr_fd),
+        relay_feed: adopt(process.relay_feed_fd),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handover() -> Handover {
+        let configs = crate::config::parse_config("[web]\ncommand = \"sleep 1\"\n").unwrap();
+        Handover {
+            version: HANDOVER_VERSION,
+            listener_fd: 5,
+            uptime: 90,
+            upgrades: 2,
+            processes: vec![HandedOver {
+                entry: DumpEntry {
+                    name: "web".to_string(),
+                    config: configs["web"].clone(),
+                    pid: Some(42),
+                    restarts: 1,
+                },
+                status: ProcessStatus::Online,
+                exit_code: None,
+                paused: true,
+                suspended: None,
+                crashes: 3,
+                stdout_fd: Some(7),
+                stderr_fd: Some(8),
+                log_relay: None,
+                relay_feed_fd: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_take_reads_and_removes_the_handover() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("handover.json");
+        std::fs::write(&path, serde_json::to_string(&handover()).unwrap()).unwrap();
+        assert_eq!(Handover::take(&path).unwrap(), handover());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_take_rejects_newer_handovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("handover.json");
+        let mut newer = handover();
+        newer.version = HANDOVER_VERSION + 1;
+        std::fs::write(&path, serde_json::to_string(&newer).unwrap()).unwrap();
+        let err = Handover::take(&path).unwrap_err();
+        assert!(err.contains("reads up to version"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_args_drop_an_earlier_handover() {
+        let args = [
+            "--handover",
+            "/tmp/h.json",
+            "daemon",
+            "start",
+            "--handover=x",
+            "--foreground",
+        ]
+        .map(std::ffi::OsString::from);
+        assert_eq!(
+            exec_args(args.into_iter()),
+            ["daemon", "start", "--foreground"].map(std::ffi::OsString::from)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_embedded_daemons_cannot_upgrade() {
+        let err = Upgrader::default().check().await.unwrap_err();
+        assert!(
+            err.contains("can't upgrade") || err.contains("not supported"),
+            "{err}"
+        );
+    }
+}
@@ -1,17 +1,25 @@
 use crate::config::{ProcessConfig, Watch};
+use crate::gitignore::GitIgnore;
 use crate::paths::Paths;
 use crate::process::{self, ProcessTable};
 use crate::protocol::ProcessStatus;
+use crate::select::glob_match;
 use notify::{RecursiveMode, Watcher};
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{RwLock, watch};
+/// How long changes are collected before a restart, unless `watch_debounce`
+/// says otherwise.
 pub const DEBOUNCE_DURATION: Duration = Duration::from_millis(500);
+/// How often paths are scanned when filesystem notifications can't be set up
+/// and the process has no `watch_poll` interval.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// The directory to watch, or for `watch` globs the one they are relative to.
 pub fn resolve_watch_path(config: &ProcessConfig) -> Option<PathBuf> {
     match config.watch.as_ref()? {
         Watch::Enabled(false) => None,
-        Watch::Enabled(true) => {
+        Watch::Enabled(true) | Watch::Globs(_) => {
             let base = config.cwd.as_deref().unwrap_or(".");
             Some(PathBuf::from(base))
         }
@@ -25,6 +33,200 @@ pub fn resolve_watch_path(config: &ProcessConfig) -> Option<PathBuf> {
         }
     }
 }
+/// Splits a `watch` glob into the directory before its first wildcard, which
+/// is what gets watched, and the rest, which changed paths under it must
+/// match. A glob without wildcards names a file in the directory.
+fn split_glob(glob: &str) -> (&str, &str) {
+    let mut dir_end = None;
+    for (i, _) in glob.match_indices('/') {
+        let segment = &glob[dir_end.map_or(0, |end| end + 1)..i];
+        if segment.contains(['*', '?', '[']) {
+            break;
+        }
+        dir_end = Some(i);
+    }
+    match dir_end {
+        Some(0) => ("/", &glob[1..]),
+        Some(i) => (&glob[..i], &glob[i + 1..]),
+        None => ("", glob),
+    }
+}
+
+/// Matches `path` against a glob of `/`-separated segments, where a `**`
+/// segment matches any number of directories and others match one name.
+pub fn path_glob_match(pattern: &str, path: &Path) -> bool {
+    let pattern: Vec<&str> = pattern
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != ".")
+        .collect();
+    let names: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    segments_match(&pattern, &names)
+}
+
+fn segments_match(pattern: &[&str], names: &[String]) -> bool {
+    match pattern.split_first() {
+        None => names.is_empty(),
+        Some((&"**", rest)) => (0..=names.len()).any(|skip| segments_match(rest, &names[skip..])),
+        Some((segment, rest)) => names
+            .split_first()
+            .is_some_and(|(name, names)| glob_match(segment, name) && segments_match(rest, names)),
+    }
+}
+
+/// Decides which changed paths restart a process: files not ignored by
+/// `watch_ignore` or, with `watch_use_gitignore`, the project's ignore files,
+/// with one of the `watch_extensions` if set, and matching one of the
+/// `watch` globs if it has them. With `watch_env_files`, changes to the
+/// process's env files always count, even without `watch`.
+struct WatchFilter {
+    /// The watch path, `None` when only env files are watched.
+    base: Option<PathBuf>,
+    /// Each glob's directory and the pattern paths under it must match.
+    globs: Vec<(PathBuf, String)>,
+    extensions: Vec<String>,
+    ignore: Vec<String>,
+    gitignore: Option<GitIgnore>,
+    env_files: Vec<PathBuf>,
+}
+
+impl WatchFilter {
+    fn new(config: &ProcessConfig, watch_path: Option<&Path>) -> Self {
+        let base = watch_path.map(|p| p.canonicalize().unwrap_or_else(|_| p.to_path_buf()));
+        let globs = match (&config.watch, &base) {
+            (Some(Watch::Globs(globs)), Some(base)) => globs
+                .iter()
+                .map(|glob| {
+                    let (dir, pattern) = split_glob(glob);
+                    let dir = base.join(dir);
+                    let dir = dir.canonicalize().unwrap_or(dir);
+                    (dir, pattern.to_string())
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let extensions = config
+            .watch_extensions
+            .iter()
+            .flatten()
+            .map(|e| e.trim_start_matches('.').to_string())
+            .collect();
+        let gitignore = base
+            .as_deref()
+            .filter(|_| config.watch_use_gitignore == Some(true))
+            .map(GitIgnore::new);
+        let env_files = if config.watch_env_files == Some(true) {
+            config
+                .env_file_paths()
+                .iter()
+                .map(|path| canonicalize_file(path))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            base,
+            globs,
+            extensions,
+            ignore: config.watch_ignore.clone().unwrap_or_default(),
+            gitignore,
+            env_files,
+        }
+    }
+
+    /// The directories to watch recursively: the globs' directories, leaving
+    /// out ones inside another, or else the whole watch path.
+    fn roots(&self) -> Vec<PathBuf> {
+        let Some(base) = &self.base else {
+            return Vec::new();
+        };
+        if self.globs.is_empty() {
+            return vec![base.clone()];
+        }
+        let mut dirs: Vec<PathBuf> = self.globs.iter().map(|(dir, _)| dir.clone()).collect();
+        dirs.sort();
+        dirs.dedup();
+        let nested = |dir: &PathBuf| dirs.iter().any(|d| d != dir && dir.starts_with(d));
+        dirs.iter().filter(|dir| !nested(dir)).cloned().collect()
+    }
+
+    /// The directories of env files outside the roots, watched on their own
+    /// rather than the files, so editors that save by renaming are seen.
+    fn env_file_dirs(&self) -> Vec<PathBuf> {
+        let roots = self.roots();
+        let mut dirs: Vec<PathBuf> = self
+            .env_files
+            .iter()
+            .filter_map(|file| file.parent())
+            .filter(|dir| !roots.iter().any(|root| dir.starts_with(root)))
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+
+    fn is_relevant(&mut self, path: &Path) -> bool {
+        if self.env_files.iter().any(|file| file == path) {
+            return true;
+        }
+        let Some(base) = &self.base else {
+            return false;
+        };
+        if let Some(gitignore) = &mut self.gitignore
+            && GitIgnore::is_ignore_file(path)
+            && let Some(dir) = path.parent()
+        {
+            gitignore.forget(dir);
+        }
+        // On macOS, FSEvents fires events for parent directories when child
+        // files change, and those parent paths may not contain the ignored
+        // component
+        if path.is_dir() {
+            return false;
+        }
+        let relative = path.strip_prefix(base).unwrap_or(path);
+        if should_ignore(relative, &self.ignore) {
+            return false;
+        }
+        if let Some(gitignore) = &mut self.gitignore
+            && gitignore.is_ignored(path, false)
+        {
+            return false;
+        }
+        if !self.extensions.is_empty()
+            && !path
+                .extension()
+                .is_some_and(|ext| self.extensions.iter().any(|e| ext == e.as_str()))
+        {
+            return false;
+        }
+        self.globs.is_empty()
+            || self.globs.iter().any(|(dir, pattern)| {
+                path.strip_prefix(dir)
+                    .is_ok_and(|relative| path_glob_match(pattern, relative))
+            })
+    }
+}
+
+/// Canonicalizes the directory of `path`, which works before the file itself
+/// exists and matches the paths events report for it.
+fn canonicalize_file(path: &Path) -> PathBuf {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match (dir.canonicalize(), path.file_name()) {
+        (Ok(dir), Some(file)) => dir.join(file),
+        _ => path.to_path_buf(),
+    }
+}
+
 fn should_ignore(path: &std::path::Path, ignore_patterns: &[String]) -> bool {
     let path_str = path.to_string_lossy();
     for pattern in ignore_patterns {
@@ -43,6 +245,79 @@ fn should_ignore(path: &std::path::Path, ignore_patterns: &[String]) -> bool {
     }
     false
 }
+type EventSender = tokio::sync::mpsc::Sender<notify::Event>;
+
+fn event_handler(tx: &EventSender) -> impl notify::EventHandler {
+    let tx = tx.clone();
+    move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            let _ = tx.blocking_send(event);
+        }
+    }
+}
+
+/// The directories to watch and whether to watch inside their subdirectories.
+type Roots = [(PathBuf, RecursiveMode)];
+
+/// Watches `roots` with `watcher`, or `None` if none of them could be.
+fn watch_roots<W: Watcher + Send + 'static>(
+    name: &str,
+    roots: &Roots,
+    mut watcher: W,
+) -> Option<Box<dyn Watcher + Send>> {
+    let mut watching = false;
+    for (root, mode) in roots {
+        match watcher.watch(root, *mode) {
+            Ok(()) => watching = true,
+            Err(e) => tracing::error!(
+                "failed to watch path '{}' for '{}': {}",
+                root.display(),
+                name,
+                e
+            ),
+        }
+    }
+    watching.then(|| Box::new(watcher) as Box<dyn Watcher + Send>)
+}
+
+/// The platform's filesystem notifications (inotify, FSEvents, ...), or
+/// `None` if they can't be set up, e.g. when out of inotify watches.
+fn start_notify_watcher(
+    name: &str,
+    roots: &Roots,
+    tx: &EventSender,
+) -> Option<Box<dyn Watcher + Send>> {
+    match notify::recommended_watcher(event_handler(tx)) {
+        Ok(watcher) => watch_roots(name, roots, watcher),
+        Err(e) => {
+            tracing::error!("failed to create file watcher for '{}': {}", name, e);
+            None
+        }
+    }
+}
+
+/// `watch_poll`: compares modification times every `interval`, which works
+/// on filesystems that don't send notifications.
+fn start_poll_watcher(
+    name: &str,
+    roots: &Roots,
+    interval: Duration,
+    tx: &EventSender,
+) -> Option<Box<dyn Watcher + Send>> {
+    let config = notify::Config::default().with_poll_interval(interval);
+    match notify::PollWatcher::new(event_handler(tx), config) {
+        Ok(watcher) => watch_roots(name, roots, watcher),
+        Err(e) => {
+            tracing::error!(
+                "failed to create polling file watcher for '{}': {}",
+                name,
+                e
+            );
+            None
+        }
+    }
+}
+
 pub fn spawn_watcher(
     name: String,
     config: ProcessConfig,
@@ -50,38 +325,53 @@ pub fn spawn_watcher(
     paths: Paths,
     mut shutdown_rx: watch::Receiver<bool>,
 ) {
-    let Some(watch_path) = resolve_watch_path(&config) else {
+    let watch_path = resolve_watch_path(&config);
+    if watch_path.is_none() && config.watch_env_files != Some(true) {
         return;
-    };
+    }
 
-    let ignore_patterns: Vec<String> = config.watch_ignore.clone().unwrap_or_default();
+    let mut filter = WatchFilter::new(&config, watch_path.as_deref());
+    let duration = |value: &Option<String>| {
+        value
+            .as_deref()
+            .and_then(|v| crate::config::parse_duration(v).ok())
+    };
+    let debounce = duration(&config.watch_debounce).unwrap_or(DEBOUNCE_DURATION);
+    let delay = duration(&config.watch_delay);
+    let poll = duration(&config.watch_poll);
+    let watch_command = config.watch_command.clone();
+    let restart = config.watch_restart != Some(false);
 
     tokio::spawn(async move {
         // Create a channel for notify events
         let (tx, mut rx) = tokio::sync::mpsc::channel(256);
 
-        let event_tx = tx.clone();
-        let mut watcher = match notify::recommended_watcher(move |res: Result<notify::Event, _>| {
-            if let Ok(event) = res {
-                let _ = event_tx.blocking_send(event);
-            }
-        }) {
-            Ok(w) => w,
-            Err(e) => {
-                eprintln!("failed to create file watcher for '{}': {}", name, e);
-                return;
-            }
+        let roots: Vec<_> = filter
+            .roots()
+            .into_iter()
+            .map(|root| (root, RecursiveMode::Recursive))
+            .chain(
+                filter
+                    .env_file_dirs()
+                    .into_iter()
+                    .map(|dir| (dir, RecursiveMode::NonRecursive)),
+            )
+            .collect();
+        let watcher = match poll {
+            Some(interval) => start_poll_watcher(&name, &roots, interval, &tx),
+            None => start_notify_watcher(&name, &roots, &tx).or_else(|| {
+                tracing::warn!(
+                    "file notifications unavailable for '{}', scanning for changes every {:?} instead",
+                    name,
+                    POLL_INTERVAL
+                );
+                start_poll_watcher(&name, &roots, POLL_INTERVAL, &tx)
+            }),
         };
-
-        if let Err(e) = watcher.watch(&watch_path, RecursiveMode::Recursive) {
-            eprintln!(
-                "failed to watch path '{}' for '{}': {}",
-                watch_path.display(),
-                name,
-                e
-            );
+        // Dropping the watcher stops it, so it lives as long as this task
+        let Some(_watcher) = watcher else {
             return;
-        }
+        };
 
         loop {
             // Wait for first event or shutdown
@@ -98,18 +388,12 @@ pub fn spawn_watcher(
                 }
             };
 
-            // Check if the first event is relevant (not ignored).
-            // Skip directory paths — on macOS, FSEvents fires events for parent
-            // directories when child files change, and those parent paths may not
-            // contain the ignored component.
-            let mut has_relevant = first_event
-                .paths
-                .iter()
-                .any(|p| !p.is_dir() && !should_ignore(p, &ignore_patterns));
+            // Check if the first event is relevant
+            let mut has_relevant = first_event.paths.iter().any(|p| filter.is_relevant(p));
 
-            // Debounce: wait DEBOUNCE_DURATION, drain any further events
+            // Debounce: wait, then drain any further events
             tokio::select! {
-                _ = tokio::time::sleep(DEBOUNCE_DURATION) => {}
+                _ = tokio::time::sleep(debounce) => {}
                 _ = shutdown_rx.changed() => {
                     if *shutdown_rx.borrow() {
                         return;
@@ -120,12 +404,7 @@ pub fn spawn_watcher(
             // Drain buffered events during debounce
             while let Ok(event) = rx.try_recv() {
                 if !has_relevant {
-                    for path in &event.paths {
-                        if !path.is_dir() && !should_ignore(path, &ignore_patterns) {
-                            has_relevant = true;
-                            break;
-                        }
-                    }
+                    has_relevant = event.paths.iter().any(|p| filter.is_relevant(p));
                 }
             }
 
@@ -133,6 +412,16 @@ pub fn spawn_watcher(
                 continue;
             }
 
+            // watch_delay: give whatever made the change time to finish,
+            // absorbing the changes it makes meanwhile
+            if let Some(delay) = delay {
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = shutdown_rx.changed() => {}
+                }
+                while rx.try_recv().is_ok() {}
+            }
+
             // Check shutdown
             if *shutdown_rx.borrow() {
                 return;
@@ -142,6 +431,11 @@ pub fn spawn_watcher(
             {
                 let table = processes.read().await;
                 match table.get(&name) {
+                    Some(managed)
+                        if managed.paused || managed.status == ProcessStatus::Suspended =>
+                    {
+                        continue;
+                    }
                     Some(managed)
                         if managed.status == ProcessStatus::Online
                             || managed.status == ProcessStatus::Starting =>
@@ -152,7 +446,25 @@ pub fn spawn_watcher(
                 }
             }
 
-            eprintln!("file change detected for '{}', restarting", name);
+            if let Some(command) = &watch_command {
+                tracing::info!("file change detected for '{}', running '{}'", name, command);
+                let result = process::run_hook(command, &name, &config, &paths).await;
+                // Whatever the command wrote is its own doing, not a new change
+                while rx.try_recv().is_ok() {}
+                if let Err(e) = result {
+                    tracing::error!("watch_command for '{}' failed, not restarting: {}", name, e);
+                    continue;
+                }
+                if !restart {
+                    continue;
+                }
+                // The process may have been stopped while the command ran
+                if *shutdown_rx.borrow() {
+                    return;
+                }
+            }
+
+            tracing::info!("file change detected for '{}', restarting", name);
 
             // Graceful stop
             let (old_config, old_restarts) = {
@@ -172,7 +484,7 @@ pub fn spawn_watcher(
                 // Perform graceful stop inline
                 let _ = managed.graceful_stop().await;
                 if let Some(ref hook) = cfg.post_stop {
-                    let _ = process::run_hook(hook, &name, cfg.cwd.as_deref(), &paths).await;
+                    let _ = process::run_hook(hook, &name, &cfg, &paths).await;
                 }
 
                 (cfg, restarts)
@@ -193,7 +505,7 @@ pub fn spawn_watcher(
                     return;
                 }
                 Err(e) => {
-                    eprintln!("failed to restart '{}' after file change: {}", name, e);
+                    tracing::error!("failed to restart '{}' after file change: {}", name, e);
                     let mut table = processes.write().await;
                     if let Some(managed) = table.get_mut(&name) {
                         managed.status = ProcessStatus::Errored;
@@ -208,6 +520,7 @@ pub fn spawn_watcher(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::EnvFile;
     use std::collections::HashMap;
 
     fn base_config() -> ProcessConfig {
@@ -216,26 +529,73 @@ mod tests {
             cwd: None,
             env: None,
             env_file: None,
+            env_cmd: None,
             readiness_check: None,
             readiness_timeout: None,
             health_check: None,
+            on_unhealthy: None,
+            unhealthy_threshold: None,
             kill_timeout: None,
             kill_signal: None,
             max_restarts: None,
             max_memory: None,
+            memory_check_interval: None,
+            memory_action: None,
+            memory_leak_window: None,
+            memory_leak_action: None,
             min_uptime: None,
             stop_exit_codes: None,
             watch: None,
             watch_ignore: None,
+            watch_extensions: None,
+            watch_use_gitignore: None,
+            watch_debounce: None,
+            watch_delay: None,
+            watch_poll: None,
+            watch_env_files: None,
+            watch_command: None,
+            watch_restart: None,
             depends_on: None,
             restart: None,
             group: None,
             pre_start: None,
             post_stop: None,
             cron_restart: None,
+            cron_start: None,
+            cron_stop: None,
+            cron_timezone: None,
+            tags: None,
+            ready_signal: None,
+            ready_delay: None,
+            drain_signal: None,
+            drain_timeout: None,
+            port: None,
+            increment_port: None,
+            instance_env: None,
+            optional_deps: None,
+            stop_dependents: None,
+            restart_stagger: None,
             log_date_format: None,
+            log_combine: None,
+            merge_logs: None,
+            stdout_log: None,
+            stderr_log: None,
+            log_max_line_len: None,
+            log_rate_limit: None,
             instances: None,
+            flap_threshold: None,
+            flap_window: None,
+            flap_cooldown: None,
+            conflict_check: None,
+            restart_delay: None,
+            restart_backoff: None,
+            max_restart_delay: None,
+            max_restarts_window: None,
+            kind: None,
+            metrics_endpoint: None,
+            metrics: None,
             environments: HashMap::new(),
+            environment_overrides: HashMap::new(),
         }
     }
 
@@ -299,6 +659,126 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_watch_globs_from_cwd() {
+        let mut config = base_config();
+        config.watch = Some(Watch::Globs(vec!["src/**/*.rs".to_string()]));
+        config.cwd = Some("/app".to_string());
+        assert_eq!(resolve_watch_path(&config).unwrap(), PathBuf::from("/app"));
+    }
+
+    #[test]
+    fn test_split_glob_at_first_wildcard() {
+        assert_eq!(split_glob("src/**/*.rs"), ("src", "**/*.rs"));
+        assert_eq!(split_glob("config/*.toml"), ("config", "*.toml"));
+        assert_eq!(split_glob("a/b/Cargo.toml"), ("a/b", "Cargo.toml"));
+        assert_eq!(split_glob("*.rs"), ("", "*.rs"));
+        assert_eq!(split_glob("/etc/app/*.conf"), ("/etc/app", "*.conf"));
+        assert_eq!(split_glob("/*.conf"), ("/", "*.conf"));
+    }
+
+    #[test]
+    fn test_path_glob_match() {
+        let matches = |pattern, path| path_glob_match(pattern, Path::new(path));
+        assert!(matches("**/*.rs", "main.rs"));
+        assert!(matches("**/*.rs", "a/b/lib.rs"));
+        assert!(!matches("**/*.rs", "a/b/lib.rs.swp"));
+        assert!(matches("*.toml", "app.toml"));
+        assert!(!matches("*.toml", "nested/app.toml"));
+        assert!(matches("a/**/c/*.txt", "a/c/x.txt"));
+        assert!(matches("a/**/c/*.txt", "a/b/b/c/x.txt"));
+        assert!(!matches("a/**/c/*.txt", "a/b/x.txt"));
+    }
+
+    #[test]
+    fn test_filter_globs_and_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(base.join("src/net")).unwrap();
+        std::fs::create_dir_all(base.join("config")).unwrap();
+        let mut config = base_config();
+        config.watch = Some(Watch::Globs(vec![
+            "src/**/*.rs".to_string(),
+            "src/net/*.rs".to_string(),
+            "config/*".to_string(),
+        ]));
+        config.watch_extensions = Some(vec!["rs".to_string(), ".toml".to_string()]);
+        let mut filter = WatchFilter::new(&config, Some(&base));
+
+        assert_eq!(filter.roots(), vec![base.join("config"), base.join("src")]);
+        assert!(filter.is_relevant(&base.join("src/net/tcp.rs")));
+        assert!(filter.is_relevant(&base.join("config/app.toml")));
+        assert!(!filter.is_relevant(&base.join("config/app.json")));
+        assert!(!filter.is_relevant(&base.join("src/.main.rs.swp")));
+        assert!(!filter.is_relevant(&base.join("assets/logo.rs")));
+    }
+
+    #[test]
+    fn test_filter_extensions_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        let mut config = base_config();
+        config.watch = Some(Watch::Enabled(true));
+        config.watch_extensions = Some(vec!["py".to_string()]);
+        config.watch_ignore = Some(vec!["venv".to_string()]);
+        let mut filter = WatchFilter::new(&config, Some(&base));
+
+        assert_eq!(filter.roots(), vec![base.clone()]);
+        assert!(filter.is_relevant(&base.join("app/main.py")));
+        assert!(!filter.is_relevant(&base.join("app/main.py~")));
+        assert!(!filter.is_relevant(&base.join("venv/lib/site.py")));
+    }
+
+    #[test]
+    fn test_filter_uses_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        std::fs::write(base.join(".gitignore"), "dist/\n*.pyc\n").unwrap();
+        let mut config = base_config();
+        config.watch = Some(Watch::Enabled(true));
+        config.watch_use_gitignore = Some(true);
+        let mut filter = WatchFilter::new(&config, Some(&base));
+
+        assert!(filter.is_relevant(&base.join("app/main.py")));
+        assert!(!filter.is_relevant(&base.join("app/main.pyc")));
+        assert!(!filter.is_relevant(&base.join("dist/bundle.js")));
+
+        // Edits to the ignore file itself take effect
+        std::fs::write(base.join(".gitignore"), "dist/\n").unwrap();
+        filter.is_relevant(&base.join(".gitignore"));
+        assert!(filter.is_relevant(&base.join("app/main.pyc")));
+    }
+
+    #[test]
+    fn test_filter_env_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(base.join("src")).unwrap();
+        let mut config = base_config();
+        config.cwd = Some(base.to_string_lossy().to_string());
+        config.env_file = Some(EnvFile::Multiple(vec![
+            ".env".to_string(),
+            "src/.env.local".to_string(),
+        ]));
+        config.watch_env_files = Some(true);
+
+        // Only the env files, watched through their directories
+        let mut filter = WatchFilter::new(&config, None);
+        assert!(filter.roots().is_empty());
+        assert_eq!(filter.env_file_dirs(), vec![base.clone(), base.join("src")]);
+        assert!(filter.is_relevant(&base.join(".env")));
+        assert!(filter.is_relevant(&base.join("src/.env.local")));
+        assert!(!filter.is_relevant(&base.join("src/main.rs")));
+
+        // Env files count even when the watch filters would skip them
+        config.watch = Some(Watch::Path("src".to_string()));
+        config.watch_extensions = Some(vec!["rs".to_string()]);
+        let mut filter = WatchFilter::new(&config, Some(&base.join("src")));
+        assert_eq!(filter.env_file_dirs(), vec![base.clone()]);
+        assert!(filter.is_relevant(&base.join("src/.env.local")));
+        assert!(filter.is_relevant(&base.join("src/main.rs")));
+    }
+
     #[test]
     fn test_should_ignore_matching_component() {
         let path = std::path::Path::new("/app/node_modules/foo/bar.js");
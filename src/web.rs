@@ -0,0 +1,357 @@
+use crate::daemon::{self, Client};
+use crate::manager::Manager;
+use crate::protocol::{self, Request, Response};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, watch};
+
+/// The dashboard is a single page talking to the API below.
+const INDEX_HTML: &str = include_str!("../web/index.html");
+
+/// How many recent lines the log view starts with.
+const DASHBOARD_LOG_LINES: usize = 100;
+
+#[derive(Clone)]
+struct WebState {
+    manager: Manager,
+    shutdown_tx: watch::Sender<bool>,
+    token: Arc<str>,
+}
+
+impl WebState {
+    /// Runs `request` like one from a remote client, so it is audited the
+    /// same way.
+    fn spawn(&self, addr: SocketAddr, request: Request) -> mpsc::Receiver<Response> {
+        daemon::spawn_request(
+            request,
+            Client::Remote(addr),
+            self.shutdown_tx.clone(),
+            self.manager.clone(),
+        )
+    }
+
+    async fn call(&self, addr: SocketAddr, request: Request) -> Result<Response, ApiError> {
+        let response = daemon::run_request(
+            request,
+            Client::Remote(addr),
+            self.shutdown_tx.clone(),
+            self.manager.clone(),
+        )
+        .await;
+        match response {
+            Some(Response::Error { message }) => Err(ApiError::from_daemon(message)),
+            Some(response) => Ok(response),
+            None => Err(ApiError::internal("daemon sent no response")),
+        }
+    }
+}
+
+/// Serves the dashboard and its HTTP API on `listener` until the task is
+/// aborted. Every request must present `token`.
+pub fn spawn_server(
+    listener: TcpListener,
+    token: String,
+    manager: Manager,
+    shutdown_tx: watch::Sender<bool>,
+) -> tokio::task::JoinHandle<()> {
+    let state = WebState {
+        manager,
+        shutdown_tx,
+        token: Arc::from(token),
+    };
+    let app = router(state).into_make_service_with_connect_info::<SocketAddr>();
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!("web dashboard: {e}");
+        }
+    })
+}
+
+fn router(state: WebState) -> Router {
+    Router::new()
+        .route("/", get(index))
+        .route("/api/daemon", get(daemon_info))
+        .route("/api/processes", get(list))
+        .route("/api/processes/{name}", get(info))
+        .route("/api/processes/{name}/restart", post(restart))
+        .route("/api/processes/{name}/stop", post(stop))
+        .route("/api/processes/{name}/logs", get(process_logs))
+        .route("/api/events", get(events))
+        .route("/api/logs", get(logs))
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .with_state(state)
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Accepts the token as `Authorization: Bearer <token>`, or as `?token=` for
+/// browsers, which can't set headers on page loads and WebSockets. Every
+/// request needs it, loopback ones included, since any local user can reach
+/// the port.
+async fn authenticate(
+    State(state): State<WebState>,
+    request: axum::extract::Request,
+    next: Next,
+) -> axum::response::Response {
+    let token = &*state.token;
+    let query = Query::<TokenQuery>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(query)| query.token);
+    let given = bearer_token(request.headers()).or(query.as_deref());
+    if given.is_some_and(|given| daemon::tokens_match(given, token)) {
+        next.run(request).await
+    } else {
+        ApiError {
+            status: StatusCode::UNAUTHORIZED,
+            message: "authentication failed".to_string(),
+        }
+        .into_response()
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// An API error, sent as `{"error": "..."}`.
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn from_daemon(message: String) -> Self {
+        let status = if message.contains("not found") {
+            StatusCode::NOT_FOUND
+        } else {
+            StatusCode::CONFLICT
+        };
+        Self { status, message }
+    }
+
+    fn internal(message: &str) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: message.to_string(),
+        }
+    }
+
+    fn unexpected(response: &Response) -> Self {
+        Self::internal(&format!("unexpected response from daemon: {response:?}"))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        let body = serde_json::json!({ "error": self.message });
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ApiResult = Result<axum::response::Response, ApiError>;
+
+async fn index() -> Html<&'static str> {
+    Html(INDEX_HTML)
+}
+
+async fn daemon_info(
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> ApiResult {
+    match state.call(addr, Request::DaemonInfo).await? {
+        Response::DaemonInfo { info } => Ok(Json(info).into_response()),
+        other => Err(ApiError::unexpected(&other)),
+    }
+}
+
+async fn list(
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> ApiResult {
+    match state.call(addr, Request::List { filter: None }).await? {
+        Response::ProcessList { processes, .. } => Ok(Json(processes).into_response()),
+        other => Err(ApiError::unexpected(&other)),
+    }
+}
+
+async fn info(
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(name): Path<String>,
+) -> ApiResult {
+    match state.call(addr, Request::Info { name }).await? {
+        Response::ProcessDetail { info } => Ok(Json(info).into_response()),
+        other => Err(ApiError::unexpected(&other)),
+    }
+}
+
+async fn restart(
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(name): Path<String>,
+) -> ApiResult {
+    let request = Request::Restart {
+        names: Some(vec![name]),
+        except: None,
+        namespace: None,
+        stagger_ms: None,
+    };
+    command(&state, addr, request).await
+}
+
+async fn stop(
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(name): Path<String>,
+) -> ApiResult {
+    let request = Request::Stop {
+        names: Some(vec![name]),
+        except: None,
+        namespace: None,
+        no_dependents: false,
+    };
+    command(&state, addr, request).await
+}
+
+async fn command(state: &WebState, addr: SocketAddr, request: Request) -> ApiResult {
+    match state.call(addr, request).await? {
+        Response::Success { message } => {
+            Ok(Json(serde_json::json!({ "message": message })).into_response())
+        }
+        other => Err(ApiError::unexpected(&other)),
+    }
+}
+
+async fn events(
+    ws: WebSocketUpgrade,
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> impl IntoResponse {
+    let responses = state.spawn(addr, Request::Events { names: None });
+    ws.on_upgrade(move |socket| forward(socket, responses, None))
+}
+
+/// Recent lines of every process, then live ones as they are written.
+async fn logs(
+    ws: WebSocketUpgrade,
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+) -> impl IntoResponse {
+    let request = Request::Log {
+        name: None,
+        lines: DASHBOARD_LOG_LINES,
+        follow: true,
+        since: None,
+        until: None,
+        stream: None,
+        namespace: None,
+    };
+    let responses = state.spawn(addr, request);
+    ws.on_upgrade(move |socket| forward(socket, responses, None))
+}
+
+#[derive(Deserialize)]
+struct LogQuery {
+    #[serde(default = "protocol::default_log_lines")]
+    lines: usize,
+    #[serde(default)]
+    follow: bool,
+}
+
+/// The last `lines` lines of one process, then live ones with `follow=true`.
+/// An unknown process is refused before the upgrade, with a 404.
+async fn process_logs(
+    ws: WebSocketUpgrade,
+    State(state): State<WebState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(name): Path<String>,
+    Query(query): Query<LogQuery>,
+) -> ApiResult {
+    state
+        .call(addr, Request::Info { name: name.clone() })
+        .await?;
+    let request = Request::Log {
+        name: Some(name.clone()),
+        lines: query.lines,
+        follow: query.follow,
+        since: None,
+        until: None,
+        stream: None,
+        namespace: None,
+    };
+    let responses = state.spawn(addr, request);
+    Ok(ws
+        .on_upgrade(move |socket| forward(socket, responses, Some(name)))
+        .into_response())
+}
+
+/// Sends each response as a JSON text message, in the same shape as the
+/// CLI's `--json` output, until either side is done. Dropping `responses`
+/// when the browser goes away stops the request. Log lines of a
+/// single-process stream are labelled with `name`, like `pm3 log --json`.
+async fn forward(
+    mut socket: WebSocket,
+    mut responses: mpsc::Receiver<Response>,
+    name: Option<String>,
+) {
+    loop {
+        tokio::select! {
+            response = responses.recv() => {
+                let Some(mut response) = response else {
+                    break;
+                };
+                if let Response::LogLine { name: line_name @ None, .. } = &mut response {
+                    *line_name = name.clone();
+                }
+                let Ok(text) = serde_json::to_string(&response) else {
+                    continue;
+                };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => {
+                if matches!(message, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    return;
+                }
+            }
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("s3cret"));
+    }
+
+    #[test]
+    fn test_daemon_errors_map_to_status_codes() {
+        let status = |message: &str| ApiError::from_daemon(message.to_string()).status;
+        assert_eq!(status("process not found: web"), StatusCode::NOT_FOUND);
+        assert_eq!(status("web is already stopped"), StatusCode::CONFLICT);
+    }
+}
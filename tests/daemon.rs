@@ -1,14 +1,19 @@
-use pm3::config::{self, EnvFile, ProcessConfig, RestartPolicy, Watch};
-use pm3::daemon;
+use pm3::client::{ClientError, DaemonClient};
+use pm3::config::{
+    self, EnvFile, ProcessConfig, ReadySignal, RestartPolicy, UnhealthyAction, Watch,
+};
+use pm3::daemon::{self, Daemon, DaemonHandle};
+use pm3::events::EventKind;
 use pm3::log::LOG_ROTATION_SIZE;
 use pm3::paths::Paths;
-use pm3::protocol::{self, ProcessStatus, Request, Response};
+use pm3::protocol::{self, ListFilter, ProcessInfo, ProcessStatus, Request, Response};
 use regex::Regex;
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
 use std::os::unix::net::UnixStream;
 use std::time::{Duration, Instant};
 use tempfile::TempDir;
+use tokio_stream::StreamExt;
 
 fn test_config(command: &str) -> ProcessConfig {
     ProcessConfig {
@@ -16,26 +21,73 @@ fn test_config(command: &str) -> ProcessConfig {
         cwd: None,
         env: None,
         env_file: None,
+        env_cmd: None,
         readiness_check: None,
         readiness_timeout: None,
         health_check: None,
+        on_unhealthy: None,
+        unhealthy_threshold: None,
         kill_timeout: None,
         kill_signal: None,
         max_restarts: None,
         max_memory: None,
+        memory_check_interval: None,
+        memory_action: None,
+        memory_leak_window: None,
+        memory_leak_action: None,
         min_uptime: None,
         stop_exit_codes: None,
         watch: None,
         watch_ignore: None,
+        watch_extensions: None,
+        watch_use_gitignore: None,
+        watch_debounce: None,
+        watch_delay: None,
+        watch_poll: None,
+        watch_env_files: None,
+        watch_command: None,
+        watch_restart: None,
         depends_on: None,
         restart: None,
         group: None,
         pre_start: None,
         post_stop: None,
         cron_restart: None,
+        cron_start: None,
+        cron_stop: None,
+        cron_timezone: None,
+        tags: None,
+        ready_signal: None,
+        ready_delay: None,
+        drain_signal: None,
+        drain_timeout: None,
+        port: None,
+        increment_port: None,
+        instance_env: None,
+        optional_deps: None,
+        stop_dependents: None,
+        restart_stagger: None,
         log_date_format: None,
+        log_combine: None,
+        merge_logs: None,
+        stdout_log: None,
+        stderr_log: None,
+        log_max_line_len: None,
+        log_rate_limit: None,
         instances: None,
+        flap_threshold: None,
+        flap_window: None,
+        flap_cooldown: None,
+        conflict_check: None,
+        restart_delay: None,
+        restart_backoff: None,
+        max_restart_delay: None,
+        max_restarts_window: None,
+        kind: None,
+        metrics_endpoint: None,
+        metrics: None,
         environments: HashMap::new(),
+        environment_overrides: HashMap::new(),
     }
 }
 
@@ -50,21 +102,12 @@ fn test_config_with_kill(
     config
 }
 
-async fn start_test_daemon(paths: &Paths) -> tokio::task::JoinHandle<color_eyre::Result<()>> {
-    let p = paths.clone();
-    let handle = tokio::spawn(async move { daemon::run(p).await });
-
-    // Wait for socket file to appear
-    let socket = paths.socket_file();
-    for _ in 0..50 {
-        if socket.exists() {
-            break;
-        }
-        tokio::time::sleep(Duration::from_millis(50)).await;
-    }
-    assert!(socket.exists(), "daemon socket was not created");
-
-    handle
+async fn start_test_daemon(paths: &Paths) -> DaemonHandle {
+    Daemon::builder()
+        .paths(paths.clone())
+        .spawn()
+        .await
+        .expect("daemon failed to start")
 }
 
 fn send_raw_request_sync(paths: &Paths, request: &Request) -> Response {
@@ -134,6 +177,641 @@ async fn test_daemon_creates_pid_and_socket() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_embedded_daemon_spawn_and_shutdown() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let daemon = Daemon::builder()
+        .paths(paths.clone())
+        .spawn()
+        .await
+        .unwrap();
+    assert_eq!(daemon.paths().data_dir(), paths.data_dir());
+    assert!(daemon.client().list().await.unwrap().is_empty());
+
+    // A second daemon on the same data dir reports why it couldn't start
+    let err = Daemon::builder()
+        .paths(paths.clone())
+        .spawn()
+        .await
+        .err()
+        .expect("second daemon should fail");
+    assert!(err.to_string().contains("already running"), "{err}");
+
+    daemon.shutdown().await.unwrap();
+    assert!(!paths.pid_file().exists(), "PID file should be cleaned up");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_grpc_mirrors_requests() {
+    use pm3::grpc::proto::{self, process_manager_client::ProcessManagerClient};
+
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let port = free_port();
+    let token_file = dir.path().join("grpc-token");
+    std::fs::write(&token_file, "s3cret\n").unwrap();
+    std::fs::write(
+        paths.settings_file(),
+        format!(
+            "[grpc]\nlisten = \"127.0.0.1:{port}\"\nauth_token_file = {:?}\n",
+            token_file.display().to_string()
+        ),
+    )
+    .unwrap();
+    let handle = start_test_daemon(&paths).await;
+
+    let mut client = ProcessManagerClient::connect(format!("http://127.0.0.1:{port}"))
+        .await
+        .unwrap();
+    let err = client
+        .list(proto::ListRequest::default())
+        .await
+        .expect_err("calls without a token should be rejected");
+    assert_eq!(err.code(), tonic::Code::Unauthenticated);
+
+    fn authed<T>(message: T) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer s3cret".parse().unwrap());
+        request
+    }
+    client
+        .start(authed(proto::StartRequest {
+            config_toml: "[web]\ncommand = \"sleep 30\"\n".to_string(),
+            wait: true,
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+
+    let processes = client
+        .list(authed(proto::ListRequest::default()))
+        .await
+        .unwrap()
+        .into_inner()
+        .processes;
+    assert_eq!(processes.len(), 1);
+    assert_eq!(processes[0].name, "web");
+    assert_eq!(processes[0].status(), proto::ProcessStatus::Online);
+
+    let err = client
+        .info(authed(proto::InfoRequest {
+            name: "nope".to_string(),
+        }))
+        .await
+        .expect_err("unknown process");
+    assert_eq!(err.code(), tonic::Code::NotFound);
+
+    let mut events = client
+        .events(authed(proto::EventsRequest::default()))
+        .await
+        .unwrap()
+        .into_inner();
+    // Give the 100ms event watcher time to see the start first
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    client
+        .stop(authed(proto::StopRequest {
+            names: vec!["web".to_string()],
+            ..Default::default()
+        }))
+        .await
+        .unwrap();
+    let event = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let event = events.message().await.unwrap().expect("event stream ended");
+            if event.kind() == proto::EventKind::StatusChanged {
+                return event;
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for a status change");
+    assert_eq!(event.name, "web");
+    assert_eq!(event.status(), proto::ProcessStatus::Stopped);
+
+    handle.shutdown().await.unwrap();
+}
+
+/// A port that was free a moment ago, for listeners configured by address.
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_web_dashboard_api() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let port = free_port();
+    let token_file = dir.path().join("web-token");
+    std::fs::write(&token_file, "s3cret\n").unwrap();
+    std::fs::write(
+        paths.settings_file(),
+        format!(
+            "[daemon]\nweb_ui = true\nweb_listen = \"127.0.0.1:{port}\"\nweb_auth_token_file = {:?}\n",
+            token_file.display().to_string()
+        ),
+    )
+    .unwrap();
+    let handle = start_test_daemon(&paths).await;
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 30"));
+    handle
+        .client()
+        .start(configs, None)
+        .await
+        .expect("start should succeed");
+
+    let http = reqwest::Client::new();
+    let url = |path: &str| format!("http://127.0.0.1:{port}{path}");
+    let response = http.get(url("/api/processes")).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let page = http.get(url("/?token=s3cret")).send().await.unwrap();
+    assert!(page.status().is_success());
+    assert!(page.text().await.unwrap().contains("/api/processes"));
+
+    let response = http
+        .get(url("/api/processes"))
+        .bearer_auth("s3cret")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let processes: serde_json::Value =
+        serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert_eq!(processes[0]["name"], "web");
+
+    let response = http
+        .post(url("/api/processes/nope/restart"))
+        .bearer_auth("s3cret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    let body: serde_json::Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(body["error"].as_str().unwrap().contains("nope"), "{body}");
+
+    let response = http
+        .post(url("/api/processes/web/stop"))
+        .bearer_auth("s3cret")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let list = handle.client().list().await.unwrap();
+    assert_eq!(list[0].status, ProcessStatus::Stopped);
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_web_dashboard_refuses_local_requests_without_token() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let port = free_port();
+    let token_file = dir.path().join("web-token");
+    std::fs::write(&token_file, "s3cret\n").unwrap();
+    std::fs::write(
+        paths.settings_file(),
+        format!(
+            "[daemon]\nweb_ui = true\nweb_listen = \"127.0.0.1:{port}\"\nallowed_uids = [0]\nweb_auth_token_file = {:?}\n",
+            token_file.display().to_string()
+        ),
+    )
+    .unwrap();
+    let handle = start_test_daemon(&paths).await;
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 30"));
+    handle
+        .client()
+        .start(configs, None)
+        .await
+        .expect("start should succeed");
+
+    // A plain local client, like curl from another account, or a page
+    // claiming to be the dashboard itself, gets nowhere without the token
+    let http = reqwest::Client::new();
+    let url = |path: &str| format!("http://127.0.0.1:{port}{path}");
+    let response = http
+        .post(url("/api/processes/web/stop"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let response = http
+        .post(url("/api/processes/web/stop"))
+        .header("Origin", format!("http://127.0.0.1:{port}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let response = http.get(url("/api/logs")).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let list = handle.client().list().await.unwrap();
+    assert_eq!(list[0].status, ProcessStatus::Online);
+
+    let response = http
+        .post(url("/api/processes/web/stop"))
+        .bearer_auth("s3cret")
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let list = handle.client().list().await.unwrap();
+    assert_eq!(list[0].status, ProcessStatus::Stopped);
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_web_process_logs_websocket() {
+    use tokio_tungstenite::tungstenite::{self, Message};
+
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let port = free_port();
+    let token_file = dir.path().join("web-token");
+    std::fs::write(&token_file, "s3cret\n").unwrap();
+    std::fs::write(
+        paths.settings_file(),
+        format!(
+            "[daemon]\nweb_ui = true\nweb_listen = \"127.0.0.1:{port}\"\nweb_auth_token_file = {:?}\n",
+            token_file.display().to_string()
+        ),
+    )
+    .unwrap();
+    let handle = start_test_daemon(&paths).await;
+    let mut configs = HashMap::new();
+    configs.insert(
+        "web".to_string(),
+        test_config("sh -c 'echo first; sleep 1; echo second; sleep 30'"),
+    );
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let url = |path: &str| {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        format!("ws://127.0.0.1:{port}{path}{separator}token=s3cret")
+    };
+    match tokio_tungstenite::connect_async(url("/api/processes/nope/logs")).await {
+        Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 404),
+        other => panic!("expected a 404, got {other:?}"),
+    }
+
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(url("/api/processes/web/logs?follow=true"))
+            .await
+            .unwrap();
+    let mut lines = Vec::new();
+    while lines.len() < 2 {
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("timed out waiting for a log line")
+            .expect("socket closed")
+            .unwrap();
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let line: serde_json::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(line["type"], "log_line");
+        assert_eq!(line["name"], "web");
+        lines.push(line["line"].as_str().unwrap().to_string());
+    }
+    assert_eq!(lines, vec!["first", "second"]);
+
+    // Without follow the socket closes after the history
+    let (mut socket, _) = tokio_tungstenite::connect_async(url("/api/processes/web/logs?lines=1"))
+        .await
+        .unwrap();
+    let mut messages = Vec::new();
+    while let Some(Ok(message)) = socket.next().await {
+        if let Message::Text(text) = message {
+            messages.push(text.to_string());
+        }
+    }
+    assert_eq!(messages.len(), 1, "{messages:?}");
+    assert!(messages[0].contains("second"));
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_statsd_push() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let statsd = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    std::fs::write(
+        paths.settings_file(),
+        format!(
+            "[metrics]\nstatsd = \"{}\"\ninterval = \"200ms\"\n",
+            statsd.local_addr().unwrap()
+        ),
+    )
+    .unwrap();
+    let handle = start_test_daemon(&paths).await;
+    let client = handle.client();
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 30"));
+    client.start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    client.restart(Some(vec!["web".to_string()])).await.unwrap();
+
+    let mut received = String::new();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !(received.contains("pm3.web.online:1|g")
+        && received.contains("pm3.web.event.restarted:1|c"))
+    {
+        let mut buf = [0u8; 2048];
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let n = tokio::time::timeout(remaining, statsd.recv(&mut buf))
+            .await
+            .unwrap_or_else(|_| panic!("timed out; received:\n{received}"))
+            .unwrap();
+        received.push_str(std::str::from_utf8(&buf[..n]).unwrap());
+        received.push('\n');
+    }
+    assert!(received.contains("pm3.web.restarts:"), "{received}");
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_telemetry_exports_otlp() {
+    // A stand-in OTLP/HTTP collector that hands over each request body
+    let (tx, mut exports) = tokio::sync::mpsc::unbounded_channel::<(String, serde_json::Value)>();
+    let collector = axum::Router::new().route(
+        "/v1/{signal}",
+        axum::routing::post(
+            move |axum::extract::Path(signal): axum::extract::Path<String>,
+                  headers: axum::http::HeaderMap,
+                  body: String| async move {
+                assert_eq!(headers["x-api-key"], "k");
+                let _ = tx.send((signal, serde_json::from_str(&body).unwrap()));
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, collector).await });
+
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::write(
+        paths.settings_file(),
+        format!(
+            "[telemetry]\nendpoint = \"{endpoint}\"\ninterval = \"200ms\"\nheaders = {{ x-api-key = \"k\" }}\n"
+        ),
+    )
+    .unwrap();
+    let handle = start_test_daemon(&paths).await;
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 30"));
+    handle.client().start(configs, None).await.unwrap();
+
+    let (mut metrics, mut logs) = (None, None);
+    while metrics.is_none() || logs.is_none() {
+        let (signal, body) = tokio::time::timeout(Duration::from_secs(5), exports.recv())
+            .await
+            .expect("timed out waiting for an export")
+            .unwrap();
+        match signal.as_str() {
+            "metrics" => metrics = Some(body),
+            "logs" => logs = Some(body),
+            other => panic!("unexpected signal {other}"),
+        }
+    }
+    let metrics = metrics.unwrap();
+    let resource = &metrics["resourceMetrics"][0]["resource"]["attributes"];
+    assert_eq!(resource[1]["key"], "pm3.process.name");
+    assert_eq!(resource[1]["value"]["stringValue"], "web");
+    let logs = logs.unwrap();
+    let record = &logs["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+    assert_eq!(record["body"]["stringValue"], "web added");
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_stats_history_is_recorded() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::write(paths.settings_file(), "[history]\ninterval = \"200ms\"\n").unwrap();
+    let handle = start_test_daemon(&paths).await;
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 30"));
+    handle.client().start(configs, None).await.unwrap();
+
+    let samples = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let samples = handle.client().stats("web", None).await.unwrap();
+            if samples.iter().any(|s| s.memory_bytes.is_some()) {
+                break samples;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await
+    .expect("timed out waiting for a sample of a running process");
+    assert!(samples.iter().all(|s| s.restarts == 0));
+    assert!(paths.stats_file("web").exists());
+    assert!(
+        handle
+            .client()
+            .stats("web", Some(Duration::from_secs(3600)))
+            .await
+            .unwrap()
+            .len()
+            >= samples.len()
+    );
+
+    let response = send_raw_request(
+        &paths,
+        &Request::Stats {
+            name: "nope".to_string(),
+            since_secs: None,
+        },
+    )
+    .await;
+    assert_eq!(
+        response,
+        Response::Error {
+            message: "process not found: nope".to_string()
+        }
+    );
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_report_counts_crashes_and_downtime() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+    let mut configs = HashMap::new();
+    let mut crashy = test_config("sh -c 'sleep 0.5; exit 1'");
+    crashy.max_restarts = Some(1);
+    configs.insert("crashy".to_string(), crashy);
+    configs.insert("steady".to_string(), test_config("sleep 30"));
+    handle.client().start(configs, None).await.unwrap();
+
+    let window = [Duration::from_secs(3600)];
+    let reports = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let reports = handle.client().report(None, &window).await.unwrap();
+            if reports[0].crashes >= 2 {
+                break reports;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await
+    .expect("timed out waiting for crashes to be reported");
+    assert_eq!(reports.len(), 2);
+    let (crashy, steady) = (&reports[0], &reports[1]);
+    assert_eq!(crashy.name, "crashy");
+    assert_eq!(crashy.window_secs, 3600);
+    assert!(crashy.restarts >= 1);
+    assert!(crashy.downtime_secs > 0.0);
+    assert!(crashy.availability.unwrap() < 100.0);
+    assert!(crashy.last_crash.is_some());
+    assert_eq!(steady.name, "steady");
+    assert_eq!(steady.crashes, 0);
+    assert_eq!(steady.downtime_secs, 0.0);
+    assert!(paths.availability_log().exists());
+
+    let response = send_raw_request(
+        &paths,
+        &Request::Report {
+            name: Some("nope".to_string()),
+            windows_secs: vec![3600],
+        },
+    )
+    .await;
+    assert_eq!(
+        response,
+        Response::Error {
+            message: "process not found: nope".to_string()
+        }
+    );
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_notifiers_post_to_webhooks() {
+    // Stand-in Slack and Discord webhooks that hand over each message
+    let (tx, mut messages) = tokio::sync::mpsc::unbounded_channel::<(String, serde_json::Value)>();
+    let hooks = axum::Router::new().route(
+        "/{service}",
+        axum::routing::post(
+            move |axum::extract::Path(service): axum::extract::Path<String>, body: String| async move {
+                let _ = tx.send((service, serde_json::from_str(&body).unwrap()));
+            },
+        ),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, hooks).await });
+
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::write(
+        paths.settings_file(),
+        format!(
+            "[[notify]]\nkind = \"slack\"\nwebhook_url = \"{base}/slack\"\non = [\"restart_limit\"]\nprocesses = [\"crashy\"]\ntemplate = \"{{name}} {{event}}: {{detail}}\"\n\n\
+             [[notify]]\nkind = \"discord\"\nwebhook_url = \"{base}/discord\"\non = [\"errored\"]\ntemplate = \"{{name}} is {{status}}\"\n"
+        ),
+    )
+    .unwrap();
+    let handle = start_test_daemon(&paths).await;
+    let mut configs = HashMap::new();
+    for name in ["crashy", "other"] {
+        let mut config = test_config("sh -c 'sleep 0.3; exit 1'");
+        config.max_restarts = Some(1);
+        configs.insert(name.to_string(), config);
+    }
+    handle.client().start(configs, None).await.unwrap();
+
+    let mut received = Vec::new();
+    while received.len() < 3 {
+        let message = tokio::time::timeout(Duration::from_secs(5), messages.recv())
+            .await
+            .unwrap_or_else(|_| panic!("timed out; received: {received:?}"))
+            .unwrap();
+        received.push(message);
+    }
+    received.sort_by_key(|(service, body)| (service.clone(), body.to_string()));
+    assert_eq!(
+        received,
+        vec![
+            (
+                "discord".to_string(),
+                serde_json::json!({ "content": "crashy is errored" })
+            ),
+            (
+                "discord".to_string(),
+                serde_json::json!({ "content": "other is errored" })
+            ),
+            (
+                "slack".to_string(),
+                serde_json::json!({ "text": "crashy restart_limit: gave up after 1 restarts" })
+            ),
+        ]
+    );
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_background_stream_follows_logs() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+    let mut configs = HashMap::new();
+    configs.insert(
+        "web".to_string(),
+        test_config("sh -c 'echo one; sleep 0.5; echo two; sleep 30'"),
+    );
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let lines = tokio::task::spawn_blocking(move || {
+        let request = Request::Log {
+            name: Some("web".to_string()),
+            lines: 10,
+            follow: true,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        };
+        let stream = pm3::client::BackgroundStream::spawn(&paths, &request).unwrap();
+        let mut lines = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !lines.contains(&"two".to_string()) && Instant::now() < deadline {
+            for response in stream.drain() {
+                if let Response::LogLine { line, .. } = response {
+                    lines.push(line);
+                }
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        lines
+    })
+    .await
+    .unwrap();
+    assert_eq!(lines, vec!["one", "two"]);
+
+    handle.shutdown().await.unwrap();
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn test_client_sends_request_gets_response() {
     let dir = TempDir::new().unwrap();
@@ -141,9 +819,9 @@ async fn test_client_sends_request_gets_response() {
 
     let handle = start_test_daemon(&paths).await;
 
-    let response = send_raw_request(&paths, &Request::List).await;
+    let response = send_raw_request(&paths, &Request::List { filter: None }).await;
     assert!(
-        matches!(&response, Response::ProcessList { processes } if processes.is_empty()),
+        matches!(&response, Response::ProcessList { processes, .. } if processes.is_empty()),
         "expected empty process list, got: {response:?}"
     );
 
@@ -159,9 +837,9 @@ async fn test_daemon_handles_multiple_sequential_connections() {
     let handle = start_test_daemon(&paths).await;
 
     for i in 0..5 {
-        let response = send_raw_request(&paths, &Request::List).await;
+        let response = send_raw_request(&paths, &Request::List { filter: None }).await;
         assert!(
-            matches!(&response, Response::ProcessList { processes } if processes.is_empty()),
+            matches!(&response, Response::ProcessList { processes, .. } if processes.is_empty()),
             "request {i}: expected empty process list, got: {response:?}"
         );
     }
@@ -209,6 +887,9 @@ async fn test_spawn_process_tracks_pid() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -218,9 +899,9 @@ async fn test_spawn_process_tracks_pid() {
     );
 
     // List and verify the process appears
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             let info = &processes[0];
             assert_eq!(info.name, "sleeper");
@@ -265,6 +946,9 @@ async fn test_spawn_with_cwd() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -305,6 +989,9 @@ async fn test_log_capture_stdout() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -347,6 +1034,9 @@ async fn test_log_capture_stderr() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -390,6 +1080,9 @@ async fn test_stdout_uses_pty_child_sees_tty() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -435,6 +1128,9 @@ async fn test_stderr_still_piped_not_tty() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -477,6 +1173,9 @@ async fn test_log_directory_created() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -521,6 +1220,9 @@ command = "sleep 999"
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -529,9 +1231,9 @@ command = "sleep 999"
         "expected Success, got: {start_resp:?}"
     );
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             assert_eq!(processes[0].name, "sleeper");
             assert_eq!(processes[0].status, pm3::protocol::ProcessStatus::Online);
@@ -573,6 +1275,9 @@ command = "sleep 999"
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -581,9 +1286,9 @@ command = "sleep 999"
         "expected Success, got: {start_resp:?}"
     );
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 2);
             let mut names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
             names.sort();
@@ -626,6 +1331,9 @@ command = "sleep 999"
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -634,9 +1342,9 @@ command = "sleep 999"
         "expected Success, got: {start_resp:?}"
     );
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             assert_eq!(processes[0].name, "web");
         }
@@ -664,9 +1372,9 @@ async fn test_list_empty_returns_no_processes() {
 
     let handle = start_test_daemon(&paths).await;
 
-    let response = send_raw_request(&paths, &Request::List).await;
+    let response = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &response {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert!(processes.is_empty(), "expected empty list");
         }
         other => panic!("expected ProcessList, got: {other:?}"),
@@ -696,6 +1404,9 @@ async fn test_list_process_info_fields() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -706,9 +1417,9 @@ async fn test_list_process_info_fields() {
 
     tokio::time::sleep(Duration::from_millis(100)).await;
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             let info = &processes[0];
             assert_eq!(info.name, "worker");
@@ -749,6 +1460,9 @@ async fn test_list_multiple_processes_all_fields() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -759,9 +1473,9 @@ async fn test_list_multiple_processes_all_fields() {
 
     tokio::time::sleep(Duration::from_millis(100)).await;
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 2);
 
             let mut sorted: Vec<_> = processes.iter().collect();
@@ -807,14 +1521,17 @@ async fn test_stop_process_handles_sigterm() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
     // Get PID from list
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     let pid = match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             let info = &processes[0];
             assert_eq!(info.status, ProcessStatus::Online);
@@ -828,6 +1545,9 @@ async fn test_stop_process_handles_sigterm() {
         &paths,
         &Request::Stop {
             names: Some(vec!["sleeper".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
         },
     )
     .await;
@@ -844,9 +1564,9 @@ async fn test_stop_process_handles_sigterm() {
     );
 
     // Verify status is Stopped
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             assert_eq!(processes[0].status, ProcessStatus::Stopped);
         }
@@ -883,6 +1603,9 @@ async fn test_stop_process_ignores_sigterm_gets_sigkill() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -891,9 +1614,9 @@ async fn test_stop_process_ignores_sigterm_gets_sigkill() {
     tokio::time::sleep(Duration::from_millis(300)).await;
 
     // Get PID
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     let pid = match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             assert_eq!(processes[0].status, ProcessStatus::Online);
             processes[0].pid.unwrap()
@@ -908,6 +1631,9 @@ async fn test_stop_process_ignores_sigterm_gets_sigkill() {
         &paths,
         &Request::Stop {
             names: Some(vec!["stubborn".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
         },
     )
     .await;
@@ -964,6 +1690,9 @@ async fn test_stop_custom_kill_signal_sigint() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -976,6 +1705,9 @@ async fn test_stop_custom_kill_signal_sigint() {
         &paths,
         &Request::Stop {
             names: Some(vec!["sigint-handler".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
         },
     )
     .await;
@@ -1014,6 +1746,9 @@ async fn test_restart_preserves_process_config() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -1023,9 +1758,9 @@ async fn test_restart_preserves_process_config() {
     );
 
     // Get PID before restart
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     let old_pid = match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             assert_eq!(processes[0].status, ProcessStatus::Online);
             processes[0].pid.unwrap()
@@ -1038,6 +1773,9 @@ async fn test_restart_preserves_process_config() {
         &paths,
         &Request::Restart {
             names: Some(vec!["worker".to_string()]),
+            except: None,
+            namespace: None,
+            stagger_ms: None,
         },
     )
     .await;
@@ -1047,9 +1785,9 @@ async fn test_restart_preserves_process_config() {
     );
 
     // Verify: online, new PID, restarts == 1, group preserved
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
+        Response::ProcessList { processes, .. } => {
             assert_eq!(processes.len(), 1);
             let info = &processes[0];
             assert_eq!(info.name, "worker");
@@ -1071,64 +1809,49 @@ async fn test_restart_preserves_process_config() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_start_nonexistent_name_returns_error() {
+async fn test_start_conflict_check_refuses_unless_forced() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
-    let config_path = dir.path().join("pm3.toml");
-    std::fs::write(
-        &config_path,
-        r#"
-[web]
-command = "sleep 999"
-"#,
-    )
-    .unwrap();
+    let handle = start_test_daemon(&paths).await;
 
-    let configs = config::load_config(&config_path).unwrap();
-
-    let handle = start_test_daemon(&paths).await;
+    let mut config = test_config("sleep 999");
+    config.conflict_check = Some("echo 1".to_string());
+    let mut configs = HashMap::new();
+    configs.insert("legacy".to_string(), config);
 
     let resp = send_raw_request(
         &paths,
         &Request::Start {
-            configs,
-            names: Some(vec!["nonexistent".to_string()]),
+            configs: configs.clone(),
+            names: None,
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
     match &resp {
         Response::Error { message } => {
             assert!(
-                message.contains("not found"),
-                "error should mention 'not found', got: {message}"
+                message.contains("refusing to start 'legacy'"),
+                "unexpected error: {message}"
             );
+            assert!(message.contains("--force"), "unexpected error: {message}");
         }
         other => panic!("expected Error, got: {other:?}"),
     }
 
-    send_raw_request(&paths, &Request::Kill).await;
-    let _ = handle.await;
-}
-
-// ── Item 14: Log command ────────────────────────────────────────────
-
-#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_shows_stdout_lines() {
-    let dir = TempDir::new().unwrap();
-    let paths = Paths::with_base(dir.path().to_path_buf());
-
-    let handle = start_test_daemon(&paths).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => assert!(processes.is_empty()),
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
 
-    let mut configs = HashMap::new();
-    configs.insert(
-        "echoer".to_string(),
-        test_config("sh -c 'echo line1; echo line2; echo line3'"),
-    );
-    send_raw_request(
+    let resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -1136,239 +1859,511 @@ async fn test_log_shows_stdout_lines() {
             env: None,
             wait: false,
             path: None,
+            force: true,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
+    assert!(
+        matches!(resp, Response::Success { .. }),
+        "expected Success, got: {resp:?}"
+    );
 
-    // Wait for process to finish and logs to be written
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
 
-    let responses = send_streaming_request(
-        &paths,
-        &Request::Log {
-            name: Some("echoer".to_string()),
-            lines: 15,
-            follow: false,
-        },
-    )
-    .await;
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_conflict_check_ignores_children_of_managed_processes() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
 
-    let log_lines: Vec<&str> = responses
-        .iter()
-        .filter_map(|r| match r {
-            Response::LogLine { line, .. } => Some(line.as_str()),
-            _ => None,
-        })
-        .collect();
+    let handle = start_test_daemon(&paths).await;
 
-    assert!(
-        log_lines.iter().any(|l| l.contains("line1")),
-        "should contain line1, got: {log_lines:?}"
-    );
-    assert!(
-        log_lines.iter().any(|l| l.contains("line2")),
-        "should contain line2, got: {log_lines:?}"
-    );
-    assert!(
-        log_lines.iter().any(|l| l.contains("line3")),
-        "should contain line3, got: {log_lines:?}"
-    );
+    // The shell stays as pm3's child; the sleep it runs is a grandchild.
+    // The duration is unique to this run so other runs' sleeps don't match.
+    let marker = format!("sleep 31{}", std::process::id());
+    let wrapped = test_config(&format!("sh -c '{marker}; true'"));
+    let mut config = test_config("sleep 999");
+    config.conflict_check = Some(format!("pgrep -x -f '{marker}'"));
+    for (name, config) in [("wrapped", wrapped), ("checked", config)] {
+        let resp = send_raw_request(
+            &paths,
+            &Request::Start {
+                configs: HashMap::from([(name.to_string(), config)]),
+                names: None,
+                env: None,
+                wait: false,
+                path: None,
+                force: false,
+                namespace: None,
+                progress: false,
+            },
+        )
+        .await;
+        assert!(
+            matches!(resp, Response::Success { .. }),
+            "expected Success for '{name}', got: {resp:?}"
+        );
+    }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
+    // The orphaned grandchild outlives the daemon
+    let _ = std::process::Command::new("pkill")
+        .args(["-x", "-f", &marker])
+        .status();
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_lines_param_limits_output() {
+async fn test_hanging_conflict_check_times_out_without_blocking() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
+    let mut config = test_config("sleep 999");
+    config.conflict_check = Some("sleep 60".to_string());
     let mut configs = HashMap::new();
-    configs.insert(
-        "counter".to_string(),
-        test_config("sh -c 'for i in 1 2 3 4 5 6 7 8 9 10; do echo line$i; done'"),
-    );
-    send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
+    configs.insert("legacy".to_string(), config);
+    let start_paths = paths.clone();
+    let start = tokio::spawn(async move {
+        send_raw_request(
+            &start_paths,
+            &Request::Start {
+                configs,
+                names: None,
+                env: None,
+                wait: false,
+                path: None,
+                force: false,
+                namespace: None,
+                progress: false,
+            },
+        )
+        .await
+    });
 
+    // Other requests are answered while the check runs
     tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let responses = send_streaming_request(
-        &paths,
-        &Request::Log {
-            name: Some("counter".to_string()),
-            lines: 5,
-            follow: false,
-        },
+    let list = tokio::time::timeout(
+        Duration::from_secs(2),
+        send_raw_request(&paths, &Request::List { filter: None }),
     )
-    .await;
-
-    let log_lines: Vec<&str> = responses
-        .iter()
-        .filter_map(|r| match r {
-            Response::LogLine { line, .. } => Some(line.as_str()),
-            _ => None,
-        })
-        .collect();
+    .await
+    .expect("list should not wait for the conflict check");
+    assert!(matches!(list, Response::ProcessList { .. }), "{list:?}");
 
-    assert_eq!(
-        log_lines.len(),
-        5,
-        "should show exactly 5 lines, got: {log_lines:?}"
-    );
-    // Should be the last 5 lines (line6..line10)
-    assert!(
-        log_lines.iter().any(|l| l.contains("line10")),
-        "should contain line10, got: {log_lines:?}"
-    );
+    match start.await.unwrap() {
+        Response::Error { message } => {
+            assert!(message.contains("timed out"), "unexpected error: {message}")
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_no_name_interleaves_all_processes() {
+async fn test_except_keeps_excluded_dependents() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
+    let mut web = test_config("sleep 999");
+    web.depends_on = Some(vec!["db".to_string()]);
     let mut configs = HashMap::new();
-    configs.insert(
-        "alpha".to_string(),
-        test_config("sh -c 'echo alpha_output'"),
-    );
-    configs.insert("beta".to_string(), test_config("sh -c 'echo beta_output'"));
+    configs.insert("db".to_string(), test_config("sleep 999"));
+    configs.insert("web".to_string(), web);
     send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
             env: None,
-            wait: false,
+            wait: true,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
+    let pids = || async {
+        match send_raw_request(&paths, &Request::List { filter: None }).await {
+            Response::ProcessList { processes, .. } => processes
+                .into_iter()
+                .map(|p| (p.name, (p.pid, p.status)))
+                .collect::<HashMap<_, _>>(),
+            other => panic!("expected ProcessList, got: {other:?}"),
+        }
+    };
+    let before = pids().await;
 
-    tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let responses = send_streaming_request(
+    let resp = send_raw_request(
         &paths,
-        &Request::Log {
-            name: None,
-            lines: 15,
-            follow: false,
+        &Request::Restart {
+            names: None,
+            except: Some(vec!["web".to_string()]),
+            namespace: None,
+            stagger_ms: None,
         },
     )
     .await;
+    assert!(matches!(resp, Response::Success { .. }), "{resp:?}");
+    let after = pids().await;
+    assert_ne!(after["db"].0, before["db"].0, "db should be restarted");
+    assert_eq!(after["web"], before["web"], "web is excluded");
 
-    let log_lines: Vec<(&Option<String>, &str)> = responses
-        .iter()
-        .filter_map(|r| match r {
-            Response::LogLine { name, line } => Some((name, line.as_str())),
-            _ => None,
-        })
-        .collect();
-
-    // Should have lines from both processes
-    let has_alpha = log_lines.iter().any(|(_, l)| l.contains("alpha_output"));
-    let has_beta = log_lines.iter().any(|(_, l)| l.contains("beta_output"));
-    assert!(has_alpha, "should have alpha output, got: {log_lines:?}");
-    assert!(has_beta, "should have beta output, got: {log_lines:?}");
-
-    // All lines should have process name prefix when multiple processes
-    for (name, _) in &log_lines {
-        assert!(
-            name.is_some(),
-            "all lines should have process name when showing multiple processes"
-        );
-    }
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["db".to_string()]),
+            except: Some(vec!["web".to_string()]),
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "{resp:?}");
+    let after_stop = pids().await;
+    assert_eq!(after_stop["db"].1, ProcessStatus::Stopped);
+    assert_eq!(after_stop["web"], before["web"], "web is excluded");
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_single_process_no_name_prefix() {
-    let dir = TempDir::new().unwrap();
-    let paths = Paths::with_base(dir.path().to_path_buf());
-
-    let handle = start_test_daemon(&paths).await;
+async fn wait_for_status(paths: &Paths, name: &str, status: ProcessStatus) -> ProcessInfo {
+    for _ in 0..60 {
+        if let Response::ProcessList { processes, .. } =
+            send_raw_request(paths, &Request::List { filter: None }).await
+            && let Some(info) = processes.into_iter().find(|p| p.name == name)
+            && info.status == status
+        {
+            return info;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    panic!("'{name}' never became {status}");
+}
 
+async fn start_health_checked(
+    paths: &Paths,
+    port: u16,
+    on_unhealthy: UnhealthyAction,
+    configure: impl FnOnce(&mut ProcessConfig),
+) {
+    let mut config = test_config("sleep 999");
+    config.health_check = Some(format!("tcp://127.0.0.1:{port}"));
+    config.on_unhealthy = Some(on_unhealthy);
+    config.unhealthy_threshold = Some(1);
+    configure(&mut config);
     let mut configs = HashMap::new();
-    configs.insert("solo".to_string(), test_config("sh -c 'echo solo_output'"));
-    send_raw_request(
-        &paths,
+    configs.insert("web".to_string(), config);
+    let resp = send_raw_request(
+        paths,
         &Request::Start {
             configs,
             names: None,
             env: None,
-            wait: false,
+            wait: true,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+}
 
-    tokio::time::sleep(Duration::from_millis(500)).await;
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_on_unhealthy_none_marks_unhealthy_and_recovers() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = start_test_daemon(&paths).await;
 
-    let responses = send_streaming_request(
-        &paths,
-        &Request::Log {
-            name: Some("solo".to_string()),
-            lines: 15,
-            follow: false,
-        },
-    )
-    .await;
+    start_health_checked(&paths, port, UnhealthyAction::None, |_| {}).await;
+    let pid = wait_for_status(&paths, "web", ProcessStatus::Online)
+        .await
+        .pid;
+
+    drop(listener);
+    let info = wait_for_status(&paths, "web", ProcessStatus::Unhealthy).await;
+    assert_eq!(info.pid, pid, "none leaves the process running");
+    let health = info.health.expect("the latest probe is recorded");
+    assert!(!health.passed);
+    assert!(health.failures > 0);
+    assert!(health.error.is_some());
+
+    let _listener = std::net::TcpListener::bind(("127.0.0.1", port)).unwrap();
+    let info = wait_for_status(&paths, "web", ProcessStatus::Online).await;
+    assert_eq!(info.pid, pid);
+    let health = info.health.unwrap();
+    assert!(health.passed);
+    assert_eq!(health.failures, 0);
 
-    let log_lines: Vec<(&Option<String>, &str)> = responses
-        .iter()
-        .filter_map(|r| match r {
-            Response::LogLine { name, line } => Some((name, line.as_str())),
-            _ => None,
-        })
-        .collect();
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
 
-    // Single process: no name prefix
-    for (name, _) in &log_lines {
-        assert!(name.is_none(), "single process should NOT have name prefix");
-    }
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_on_unhealthy_stop() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = start_test_daemon(&paths).await;
+
+    start_health_checked(&paths, port, UnhealthyAction::Stop, |_| {}).await;
+    drop(listener);
+    let info = wait_for_status(&paths, "web", ProcessStatus::Stopped).await;
+    assert_eq!(info.pid, None);
+    assert_eq!(info.restarts, 0);
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_nonexistent_process_returns_error() {
+async fn test_on_unhealthy_restart_ignores_restart_policy() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
     let handle = start_test_daemon(&paths).await;
 
-    let responses = send_streaming_request(
-        &paths,
-        &Request::Log {
-            name: Some("nope".to_string()),
-            lines: 15,
-            follow: false,
-        },
-    )
+    start_health_checked(&paths, port, UnhealthyAction::Restart, |config| {
+        config.restart = Some(RestartPolicy::Never);
+        config.restart_delay = Some(100);
+    })
     .await;
+    let pid = wait_for_status(&paths, "web", ProcessStatus::Online)
+        .await
+        .pid;
+    drop(listener);
 
-    assert_eq!(responses.len(), 1, "should get one error response");
-    match &responses[0] {
+    let mut restarted = None;
+    for _ in 0..40 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        if let Response::ProcessList { processes, .. } =
+            send_raw_request(&paths, &Request::List { filter: None }).await
+            && let Some(info) = processes.into_iter().find(|p| p.name == "web")
+            && info.restarts == 1
+            && info.pid.is_some()
+        {
+            restarted = Some(info);
+            break;
+        }
+    }
+    let info = restarted.expect("web should be restarted once");
+    assert_ne!(info.pid, pid);
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_on_unhealthy_restart_within_max_restarts() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = start_test_daemon(&paths).await;
+
+    start_health_checked(&paths, port, UnhealthyAction::Restart, |config| {
+        config.max_restarts = Some(0);
+    })
+    .await;
+    drop(listener);
+    let info = wait_for_status(&paths, "web", ProcessStatus::Errored).await;
+    assert_eq!(info.pid, None);
+    assert_eq!(info.restarts, 0);
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_reload_with_health_check_keeps_monitoring_promoted_process() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut config = test_config("sleep 999");
+    config.health_check = Some(format!("tcp://127.0.0.1:{port}"));
+    config.restart = Some(RestartPolicy::OnFailure);
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), config);
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Reload {
+            names: Some(vec!["web".to_string()]),
+            except: None,
+            path: None,
+            namespace: None,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    let promoted = match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1, "temp reload entry should be gone");
+            assert_eq!(processes[0].name, "web");
+            assert_eq!(processes[0].status, ProcessStatus::Online);
+            processes[0].pid.unwrap()
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    };
+
+    // Crash the promoted process; it must still be restarted under its final name
+    nix::sys::signal::kill(
+        nix::unistd::Pid::from_raw(promoted as i32),
+        nix::sys::signal::Signal::SIGKILL,
+    )
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.name, "web");
+            assert!(info.pid.is_some(), "promoted process should be restarted");
+            assert_ne!(info.pid, Some(promoted));
+            assert!(info.restarts >= 1);
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_reload_keeps_health_record_and_checker_across_promotion() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = start_test_daemon(&paths).await;
+
+    start_health_checked(&paths, port, UnhealthyAction::None, |_| {}).await;
+    let old_pid = wait_for_status(&paths, "web", ProcessStatus::Online)
+        .await
+        .pid;
+
+    let reload = {
+        let paths = paths.clone();
+        tokio::spawn(async move {
+            send_raw_request(
+                &paths,
+                &Request::Reload {
+                    names: Some(vec!["web".to_string()]),
+                    except: None,
+                    path: None,
+                    namespace: None,
+                },
+            )
+            .await
+        })
+    };
+    // Watch the final name through the whole swap
+    while !reload.is_finished() {
+        match send_raw_request(
+            &paths,
+            &Request::Info {
+                name: "web".to_string(),
+            },
+        )
+        .await
+        {
+            Response::ProcessDetail { info } => {
+                assert_eq!(info.status, ProcessStatus::Online, "during the swap");
+                let health = info.health.expect("health record during the swap");
+                assert!(health.passed);
+            }
+            other => panic!("expected ProcessDetail, got: {other:?}"),
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let resp = reload.await.unwrap();
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    let info = wait_for_status(&paths, "web", ProcessStatus::Online).await;
+    assert_ne!(info.pid, old_pid);
+    assert!(info.health.is_some_and(|h| h.passed));
+
+    // The liveness checker probes the promoted process under its final name
+    drop(listener);
+    let info = wait_for_status(&paths, "web", ProcessStatus::Unhealthy).await;
+    assert!(info.health.is_some_and(|h| !h.passed && h.failures > 0));
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_start_nonexistent_name_returns_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let config_path = dir.path().join("pm3.toml");
+    std::fs::write(
+        &config_path,
+        r#"
+[web]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+
+    let configs = config::load_config(&config_path).unwrap();
+
+    let handle = start_test_daemon(&paths).await;
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: Some(vec!["nonexistent".to_string()]),
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    match &resp {
         Response::Error { message } => {
             assert!(
                 message.contains("not found"),
@@ -1382,18 +2377,19 @@ async fn test_log_nonexistent_process_returns_error() {
     let _ = handle.await;
 }
 
+// ── Item 14: Log command ────────────────────────────────────────────
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_follow_streams_new_lines() {
+async fn test_log_shows_stdout_lines() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    // Start a process that writes lines slowly
     let mut configs = HashMap::new();
     configs.insert(
-        "slow".to_string(),
-        test_config("sh -c 'echo initial; sleep 0.3; echo follow1; sleep 0.3; echo follow2'"),
+        "echoer".to_string(),
+        test_config("sh -c 'echo line1; echo line2; echo line3'"),
     );
     send_raw_request(
         &paths,
@@ -1403,44 +2399,29 @@ async fn test_log_follow_streams_new_lines() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    // Wait for the initial line to be written
-    tokio::time::sleep(Duration::from_millis(200)).await;
-
-    // Start a follow request in a background task with a timeout
-    let paths_clone = paths.clone();
-    let follow_handle = tokio::task::spawn_blocking(move || {
-        let mut stream = UnixStream::connect(paths_clone.socket_file()).unwrap();
-        stream
-            .set_read_timeout(Some(Duration::from_secs(3)))
-            .unwrap();
+    // Wait for process to finish and logs to be written
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-        let request = Request::Log {
-            name: Some("slow".to_string()),
+    let responses = send_streaming_request(
+        &paths,
+        &Request::Log {
+            name: Some("echoer".to_string()),
             lines: 15,
-            follow: true,
-        };
-        let encoded = protocol::encode_request(&request).unwrap();
-        stream.write_all(&encoded).unwrap();
-        stream.shutdown(std::net::Shutdown::Write).unwrap();
-
-        let reader = BufReader::new(stream);
-        let mut responses = Vec::new();
-        for line_result in reader.lines() {
-            match line_result {
-                Ok(line) if !line.is_empty() => {
-                    responses.push(protocol::decode_response(&line).unwrap());
-                }
-                _ => break, // timeout or EOF
-            }
-        }
-        responses
-    });
-
-    let responses = follow_handle.await.unwrap();
+            follow: false,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        },
+    )
+    .await;
 
     let log_lines: Vec<&str> = responses
         .iter()
@@ -1450,28 +2431,25 @@ async fn test_log_follow_streams_new_lines() {
         })
         .collect();
 
-    // Should have received the initial line and then the follow lines
     assert!(
-        log_lines.iter().any(|l| l.contains("initial")),
-        "should contain 'initial', got: {log_lines:?}"
+        log_lines.iter().any(|l| l.contains("line1")),
+        "should contain line1, got: {log_lines:?}"
     );
     assert!(
-        log_lines.iter().any(|l| l.contains("follow1")),
-        "should contain 'follow1', got: {log_lines:?}"
+        log_lines.iter().any(|l| l.contains("line2")),
+        "should contain line2, got: {log_lines:?}"
     );
     assert!(
-        log_lines.iter().any(|l| l.contains("follow2")),
-        "should contain 'follow2', got: {log_lines:?}"
+        log_lines.iter().any(|l| l.contains("line3")),
+        "should contain line3, got: {log_lines:?}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ── Item 15: Flush command ──────────────────────────────────────────
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_flush_empties_log_file() {
+async fn test_log_lines_param_limits_output() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
@@ -1479,8 +2457,8 @@ async fn test_flush_empties_log_file() {
 
     let mut configs = HashMap::new();
     configs.insert(
-        "echoer".to_string(),
-        test_config("sh -c 'echo flush_test_output; echo flush_err >&2'"),
+        "counter".to_string(),
+        test_config("sh -c 'for i in 1 2 3 4 5 6 7 8 9 10; do echo line$i; done'"),
     );
     send_raw_request(
         &paths,
@@ -1490,49 +2468,46 @@ async fn test_flush_empties_log_file() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // Verify logs have content
-    let stdout_log = paths.stdout_log("echoer");
-    let stderr_log = paths.stderr_log("echoer");
-    assert!(stdout_log.exists(), "stdout log should exist");
-    assert!(stderr_log.exists(), "stderr log should exist");
-    assert!(
-        !std::fs::read_to_string(&stdout_log).unwrap().is_empty(),
-        "stdout log should have content before flush"
-    );
-    assert!(
-        !std::fs::read_to_string(&stderr_log).unwrap().is_empty(),
-        "stderr log should have content before flush"
-    );
-
-    // Flush by name
-    let resp = send_raw_request(
+    let responses = send_streaming_request(
         &paths,
-        &Request::Flush {
-            names: Some(vec!["echoer".to_string()]),
+        &Request::Log {
+            name: Some("counter".to_string()),
+            lines: 5,
+            follow: false,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
         },
     )
     .await;
-    assert!(
-        matches!(&resp, Response::Success { .. }),
-        "expected Success, got: {resp:?}"
-    );
 
-    // Verify log files exist but are empty
-    assert!(stdout_log.exists(), "stdout log should still exist");
-    assert!(stderr_log.exists(), "stderr log should still exist");
-    assert!(
-        std::fs::read_to_string(&stdout_log).unwrap().is_empty(),
-        "stdout log should be empty after flush"
+    let log_lines: Vec<&str> = responses
+        .iter()
+        .filter_map(|r| match r {
+            Response::LogLine { line, .. } => Some(line.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        log_lines.len(),
+        5,
+        "should show exactly 5 lines, got: {log_lines:?}"
     );
+    // Should be the last 5 lines (line6..line10)
     assert!(
-        std::fs::read_to_string(&stderr_log).unwrap().is_empty(),
-        "stderr log should be empty after flush"
+        log_lines.iter().any(|l| l.contains("line10")),
+        "should contain line10, got: {log_lines:?}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -1540,7 +2515,7 @@ async fn test_flush_empties_log_file() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_flush_all_empties_all_logs() {
+async fn test_log_no_name_interleaves_all_processes() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
@@ -1560,36 +2535,48 @@ async fn test_flush_all_empties_all_logs() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // Verify logs have content
-    for name in &["alpha", "beta"] {
-        let stdout_log = paths.stdout_log(name);
-        assert!(stdout_log.exists(), "{name} stdout log should exist");
-        assert!(
-            !std::fs::read_to_string(&stdout_log).unwrap().is_empty(),
-            "{name} stdout log should have content before flush"
-        );
-    }
+    let responses = send_streaming_request(
+        &paths,
+        &Request::Log {
+            name: None,
+            lines: 15,
+            follow: false,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        },
+    )
+    .await;
 
-    // Flush all (no names)
-    let resp = send_raw_request(&paths, &Request::Flush { names: None }).await;
-    assert!(
-        matches!(&resp, Response::Success { .. }),
-        "expected Success, got: {resp:?}"
-    );
+    let log_lines: Vec<(&Option<String>, &str)> = responses
+        .iter()
+        .filter_map(|r| match r {
+            Response::LogLine { name, line, .. } => Some((name, line.as_str())),
+            _ => None,
+        })
+        .collect();
 
-    // Verify all log files are empty
-    for name in &["alpha", "beta"] {
-        let stdout_log = paths.stdout_log(name);
-        assert!(stdout_log.exists(), "{name} stdout log should still exist");
+    // Should have lines from both processes
+    let has_alpha = log_lines.iter().any(|(_, l)| l.contains("alpha_output"));
+    let has_beta = log_lines.iter().any(|(_, l)| l.contains("beta_output"));
+    assert!(has_alpha, "should have alpha output, got: {log_lines:?}");
+    assert!(has_beta, "should have beta output, got: {log_lines:?}");
+
+    // All lines should have process name prefix when multiple processes
+    for (name, _) in &log_lines {
         assert!(
-            std::fs::read_to_string(&stdout_log).unwrap().is_empty(),
-            "{name} stdout log should be empty after flush"
+            name.is_some(),
+            "all lines should have process name when showing multiple processes"
         );
     }
 
@@ -1598,15 +2585,14 @@ async fn test_flush_all_empties_all_logs() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_flush_deletes_rotated_files() {
+async fn test_log_single_process_no_name_prefix() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    // Start a process so it exists in the process table
     let mut configs = HashMap::new();
-    configs.insert("worker".to_string(), test_config("sleep 999"));
+    configs.insert("solo".to_string(), test_config("sh -c 'echo solo_output'"));
     send_raw_request(
         &paths,
         &Request::Start {
@@ -1615,56 +2601,40 @@ async fn test_flush_deletes_rotated_files() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(300)).await;
-
-    // Create rotated log files manually
-    std::fs::create_dir_all(paths.log_dir()).unwrap();
-    for i in 1..=3 {
-        let rotated_stdout = paths.rotated_stdout_log("worker", i);
-        let rotated_stderr = paths.rotated_stderr_log("worker", i);
-        std::fs::write(&rotated_stdout, format!("rotated stdout {i}")).unwrap();
-        std::fs::write(&rotated_stderr, format!("rotated stderr {i}")).unwrap();
-    }
-
-    // Verify rotated files exist
-    for i in 1..=3 {
-        assert!(
-            paths.rotated_stdout_log("worker", i).exists(),
-            "rotated stdout.{i} should exist before flush"
-        );
-        assert!(
-            paths.rotated_stderr_log("worker", i).exists(),
-            "rotated stderr.{i} should exist before flush"
-        );
-    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // Flush
-    let resp = send_raw_request(
+    let responses = send_streaming_request(
         &paths,
-        &Request::Flush {
-            names: Some(vec!["worker".to_string()]),
+        &Request::Log {
+            name: Some("solo".to_string()),
+            lines: 15,
+            follow: false,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
         },
     )
     .await;
-    assert!(
-        matches!(&resp, Response::Success { .. }),
-        "expected Success, got: {resp:?}"
-    );
 
-    // Verify rotated files are deleted
-    for i in 1..=3 {
-        assert!(
-            !paths.rotated_stdout_log("worker", i).exists(),
-            "rotated stdout.{i} should be deleted after flush"
-        );
-        assert!(
-            !paths.rotated_stderr_log("worker", i).exists(),
-            "rotated stderr.{i} should be deleted after flush"
-        );
+    let log_lines: Vec<(&Option<String>, &str)> = responses
+        .iter()
+        .filter_map(|r| match r {
+            Response::LogLine { name, line, .. } => Some((name, line.as_str())),
+            _ => None,
+        })
+        .collect();
+
+    // Single process: no name prefix
+    for (name, _) in &log_lines {
+        assert!(name.is_none(), "single process should NOT have name prefix");
     }
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -1672,20 +2642,28 @@ async fn test_flush_deletes_rotated_files() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_flush_nonexistent_process_returns_error() {
+async fn test_log_nonexistent_process_returns_error() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let resp = send_raw_request(
+    let responses = send_streaming_request(
         &paths,
-        &Request::Flush {
-            names: Some(vec!["nope".to_string()]),
+        &Request::Log {
+            name: Some("nope".to_string()),
+            lines: 15,
+            follow: false,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
         },
     )
     .await;
-    match &resp {
+
+    assert_eq!(responses.len(), 1, "should get one error response");
+    match &responses[0] {
         Response::Error { message } => {
             assert!(
                 message.contains("not found"),
@@ -1699,21 +2677,20 @@ async fn test_flush_nonexistent_process_returns_error() {
     let _ = handle.await;
 }
 
-// ── Item 16: Log timestamp tests ────────────────────────────────────
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_timestamp_prefix() {
+async fn test_log_follow_streams_new_lines() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo line1; echo line2; echo line3'");
-    config.log_date_format = Some("%Y-%m-%d %H:%M:%S".to_string());
-
+    // Start a process that writes lines slowly
     let mut configs = HashMap::new();
-    configs.insert("ts-echo".to_string(), config);
-    let start_resp = send_raw_request(
+    configs.insert(
+        "slow".to_string(),
+        test_config("sh -c 'echo initial; sleep 0.3; echo follow1; sleep 0.3; echo follow2'"),
+    );
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -1721,172 +2698,181 @@ async fn test_log_timestamp_prefix() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
-    );
 
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    // Wait for the initial line to be written
+    tokio::time::sleep(Duration::from_millis(200)).await;
 
-    let stdout_log = paths.stdout_log("ts-echo");
-    assert!(stdout_log.exists(), "stdout log file should exist");
+    // Start a follow request in a background task with a timeout
+    let paths_clone = paths.clone();
+    let follow_handle = tokio::task::spawn_blocking(move || {
+        let mut stream = UnixStream::connect(paths_clone.socket_file()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(3)))
+            .unwrap();
 
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
-    let re = Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2} \| .+$").unwrap();
-    let lines: Vec<&str> = content.lines().collect();
-    assert_eq!(lines.len(), 3, "should have 3 lines, got: {lines:?}");
-    for line in &lines {
-        assert!(
-            re.is_match(line),
-            "line did not match timestamp pattern: {line}"
-        );
-    }
-    // Verify original content is preserved after the separator
-    assert!(content.contains("line1"), "content should contain 'line1'");
-    assert!(content.contains("line2"), "content should contain 'line2'");
-    assert!(content.contains("line3"), "content should contain 'line3'");
+        let request = Request::Log {
+            name: Some("slow".to_string()),
+            lines: 15,
+            follow: true,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        };
+        let encoded = protocol::encode_request(&request).unwrap();
+        stream.write_all(&encoded).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
 
-    send_raw_request(&paths, &Request::Kill).await;
-    let _ = handle.await;
-}
+        let reader = BufReader::new(stream);
+        let mut responses = Vec::new();
+        for line_result in reader.lines() {
+            match line_result {
+                Ok(line) if !line.is_empty() => {
+                    responses.push(protocol::decode_response(&line).unwrap());
+                }
+                _ => break, // timeout or EOF
+            }
+        }
+        responses
+    });
 
-#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_no_timestamp_without_format() {
-    let dir = TempDir::new().unwrap();
-    let paths = Paths::with_base(dir.path().to_path_buf());
+    let responses = follow_handle.await.unwrap();
 
-    let handle = start_test_daemon(&paths).await;
+    let log_lines: Vec<&str> = responses
+        .iter()
+        .filter_map(|r| match r {
+            Response::LogLine { line, .. } => Some(line.as_str()),
+            _ => None,
+        })
+        .collect();
 
-    // Default test_config has log_date_format = None
-    let mut configs = HashMap::new();
-    configs.insert(
-        "no-ts".to_string(),
-        test_config("sh -c 'echo plain1; echo plain2'"),
+    // Should have received the initial line and then the follow lines
+    assert!(
+        log_lines.iter().any(|l| l.contains("initial")),
+        "should contain 'initial', got: {log_lines:?}"
     );
-    let start_resp = send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
     assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
+        log_lines.iter().any(|l| l.contains("follow1")),
+        "should contain 'follow1', got: {log_lines:?}"
+    );
+    assert!(
+        log_lines.iter().any(|l| l.contains("follow2")),
+        "should contain 'follow2', got: {log_lines:?}"
     );
-
-    tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let stdout_log = paths.stdout_log("no-ts");
-    assert!(stdout_log.exists(), "stdout log file should exist");
-
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
-    let lines: Vec<&str> = content.lines().collect();
-    assert_eq!(lines.len(), 2, "should have 2 lines, got: {lines:?}");
-    assert_eq!(lines[0], "plain1");
-    assert_eq!(lines[1], "plain2");
-    for line in &lines {
-        assert!(
-            !line.contains(" | "),
-            "line should not contain ' | ' separator: {line}"
-        );
-    }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_timestamp_stderr() {
+async fn test_log_interleaves_streams_by_timestamp() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo err1 >&2; echo err2 >&2'");
-    config.log_date_format = Some("%Y-%m-%d %H:%M:%S".to_string());
-
+    let mut config = test_config(
+        "sh -c 'echo one; sleep 0.2; echo two >&2; sleep 0.2; echo three; sleep 0.2; echo four >&2; sleep 30'",
+    );
+    config.log_date_format = Some("%H:%M:%S%.3f".to_string());
     let mut configs = HashMap::new();
-    configs.insert("ts-err".to_string(), config);
-    let start_resp = send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
-    assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
-    );
-
-    tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let stderr_log = paths.stderr_log("ts-err");
-    assert!(stderr_log.exists(), "stderr log file should exist");
+    configs.insert("chatty".to_string(), config);
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(1200)).await;
+
+    let mut client = UnixStream::connect(paths.socket_file()).unwrap();
+    let request = Request::Log {
+        name: Some("chatty".to_string()),
+        lines: 10,
+        follow: false,
+        since: None,
+        until: None,
+        stream: None,
+        namespace: None,
+    };
+    client
+        .write_all(&protocol::encode_request(&request).unwrap())
+        .unwrap();
+    client.shutdown(std::net::Shutdown::Write).unwrap();
+    let lines: Vec<(Option<pm3::log::LogStream>, String)> = BufReader::new(client)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| match protocol::decode_response(&line).unwrap() {
+            Response::LogLine { stream, line, .. } => Some((stream, line)),
+            _ => None,
+        })
+        .collect();
 
-    let content = std::fs::read_to_string(&stderr_log).unwrap();
-    let re = Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2} \| .+$").unwrap();
-    let lines: Vec<&str> = content.lines().collect();
-    assert_eq!(lines.len(), 2, "should have 2 lines, got: {lines:?}");
-    for line in &lines {
-        assert!(
-            re.is_match(line),
-            "stderr line did not match timestamp pattern: {line}"
-        );
-    }
-    assert!(content.contains("err1"), "content should contain 'err1'");
-    assert!(content.contains("err2"), "content should contain 'err2'");
+    let messages: Vec<&str> = lines
+        .iter()
+        .map(|(_, line)| line.rsplit(" | ").next().unwrap())
+        .collect();
+    assert_eq!(messages, vec!["one", "two", "three", "four"]);
+    assert_eq!(lines[1].0, Some(pm3::log::LogStream::Stderr));
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ── Item 17: Log rotation ───────────────────────────────────────────
+/// Sends a non-following `Request::Log` for `name` and collects the lines.
+fn read_logs(paths: &Paths, name: &str) -> Vec<(Option<pm3::log::LogStream>, String)> {
+    let mut client = UnixStream::connect(paths.socket_file()).unwrap();
+    let request = Request::Log {
+        name: Some(name.to_string()),
+        lines: 10,
+        follow: false,
+        since: None,
+        until: None,
+        stream: None,
+        namespace: None,
+    };
+    client
+        .write_all(&protocol::encode_request(&request).unwrap())
+        .unwrap();
+    client.shutdown(std::net::Shutdown::Write).unwrap();
+    BufReader::new(client)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| match protocol::decode_response(&line).unwrap() {
+            Response::LogLine { stream, line, .. } => Some((stream, line)),
+            _ => None,
+        })
+        .collect()
+}
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_rotation_creates_rotated_file() {
+async fn test_log_combine_writes_one_tagged_file() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    // Generate 12,000 lines × 1000 bytes = 12MB (> 10MB threshold)
+    let mut config = test_config("sh -c 'echo one; sleep 0.2; echo two >&2; sleep 30'");
+    config.log_combine = Some(true);
     let mut configs = HashMap::new();
-    configs.insert(
-        "biglog".to_string(),
-        test_config("sh -c 'yes \"$(printf \"%0999d\" 0)\" | head -n 12000'"),
-    );
-    send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
+    configs.insert("both".to_string(), config);
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(800)).await;
 
-    // Wait for the log copier to flush
-    tokio::time::sleep(Duration::from_secs(5)).await;
+    let content = std::fs::read_to_string(paths.combined_log("both")).unwrap();
+    assert_eq!(
+        content.lines().collect::<Vec<_>>(),
+        ["[out] one", "[err] two"]
+    );
+    assert!(!paths.stdout_log("both").exists());
+    assert!(!paths.stderr_log("both").exists());
 
-    let rotated = paths.rotated_stdout_log("biglog", 1);
-    assert!(
-        rotated.exists(),
-        "rotated stdout log .1 should exist after writing >10MB"
+    use pm3::log::LogStream;
+    assert_eq!(
+        read_logs(&paths, "both"),
+        vec![
+            (Some(LogStream::Stdout), "one".to_string()),
+            (Some(LogStream::Stderr), "two".to_string()),
+        ]
     );
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -1894,128 +2880,147 @@ async fn test_log_rotation_creates_rotated_file() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_log_rotation_only_keeps_three_files() {
+async fn test_log_rate_limit_suppresses_lines_with_an_event() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let socket = paths.socket_file();
+    let events = tokio::task::spawn_blocking(move || {
+        let mut stream = UnixStream::connect(socket).unwrap();
+        stream
+            .write_all(&protocol::encode_request(&Request::Events { names: None }).unwrap())
+            .unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        for line in BufReader::new(stream).lines() {
+            if let Response::Event(event) = protocol::decode_response(&line.unwrap()).unwrap()
+                && event.kind == EventKind::LogsSuppressed
+            {
+                return event;
+            }
+        }
+        panic!("no logs_suppressed event");
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
 
+    let mut config =
+        test_config("sh -c 'for i in 1 2 3 4 5 6 7 8; do echo line$i; done; sleep 30'");
+    config.log_rate_limit = Some("3/300ms".to_string());
+    let mut configs = HashMap::new();
+    configs.insert("noisy".to_string(), config);
+    handle.client().start(configs, None).await.unwrap();
+
+    let event = events.await.unwrap();
+    assert_eq!(event.name, "noisy");
+    assert_eq!(event.detail.as_deref(), Some("5 log lines suppressed"));
+    let content = std::fs::read_to_string(paths.stdout_log("noisy")).unwrap();
+    assert_eq!(
+        content.lines().collect::<Vec<_>>(),
+        [
+            "line1",
+            "line2",
+            "line3",
+            "[pm3: 5 lines suppressed (log_rate_limit)]"
+        ]
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_reopen_logs_after_logs_are_moved() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
     let handle = start_test_daemon(&paths).await;
 
-    // 1. Start "rotator" with a quick command that exits immediately
     let mut configs = HashMap::new();
     configs.insert(
-        "rotator".to_string(),
-        test_config("sh -c 'echo setup_done'"),
+        "ticker".to_string(),
+        test_config("sh -c 'while true; do echo tick; sleep 0.1; done'"),
     );
-    send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
-
-    // 2. Wait for log copier to finish
+    handle.client().start(configs, None).await.unwrap();
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // 3. Stop "rotator" (may already be stopped)
-    let _ = send_raw_request(
+    let moved = paths.rotated_stdout_log("ticker", 1);
+    std::fs::rename(paths.stdout_log("ticker"), &moved).unwrap();
+    let response = send_raw_request(
         &paths,
-        &Request::Stop {
-            names: Some(vec!["rotator".to_string()]),
+        &Request::ReopenLogs {
+            names: Some(vec!["tick*".to_string()]),
+            namespace: None,
         },
     )
     .await;
+    assert!(matches!(response, Response::Success { .. }), "{response:?}");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let moved_len = std::fs::metadata(&moved).unwrap().len();
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // 4. Wait for stop to settle
-    tokio::time::sleep(Duration::from_millis(300)).await;
-
-    // 5. Seed: overwrite stdout log with exactly LOG_ROTATION_SIZE bytes of padding
-    let stdout_log = paths.stdout_log("rotator");
-    std::fs::write(&stdout_log, vec![b'X'; LOG_ROTATION_SIZE as usize]).unwrap();
-
-    // 6. Seed: create .1, .2, .3 manually
-    for i in 1..=3 {
-        std::fs::write(
-            paths.rotated_stdout_log("rotator", i),
-            format!("old-rotated-{i}"),
-        )
-        .unwrap();
-    }
+    let content = std::fs::read_to_string(paths.stdout_log("ticker")).unwrap();
+    assert!(content.contains("tick"), "{content:?}");
+    assert_eq!(std::fs::metadata(&moved).unwrap().len(), moved_len);
 
-    // 7. Restart "rotator" — log copier opens file, sees 10MB, first line triggers rotation
-    let restart_resp = send_raw_request(
+    let response = send_raw_request(
         &paths,
-        &Request::Restart {
-            names: Some(vec!["rotator".to_string()]),
+        &Request::ReopenLogs {
+            names: Some(vec!["nope".to_string()]),
+            namespace: None,
         },
     )
     .await;
-    assert!(
-        matches!(&restart_resp, Response::Success { .. }),
-        "expected Success, got: {restart_resp:?}"
-    );
-
-    // 8. Wait for rotation to happen
-    tokio::time::sleep(Duration::from_secs(2)).await;
-
-    // 9. Verify .1, .2, .3 exist; .4 does NOT
-    for i in 1..=3 {
-        assert!(
-            paths.rotated_stdout_log("rotator", i).exists(),
-            "rotated stdout log .{i} should exist"
-        );
-    }
-    assert!(
-        !paths.rotated_stdout_log("rotator", 4).exists(),
-        "rotated stdout log .4 should NOT exist (max keep is 3)"
-    );
+    assert!(matches!(response, Response::Error { .. }), "{response:?}");
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ── Item 18: Restart policy ─────────────────────────────────────────
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_restart_policy_never_does_not_restart() {
+async fn test_custom_log_paths_are_created_and_read() {
     let dir = TempDir::new().unwrap();
-    let paths = Paths::with_base(dir.path().to_path_buf());
-
+    let paths = Paths::with_base(dir.path().join("pm3"));
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'exit 1'");
-    config.restart = Some(RestartPolicy::Never);
-
+    let mut config = test_config("sh -c 'echo one; sleep 0.2; echo two >&2; sleep 30'");
+    config.cwd = Some(dir.path().to_string_lossy().into_owned());
+    config.stdout_log = Some("volume/web/out.log".to_string());
     let mut configs = HashMap::new();
-    configs.insert("never-restart".to_string(), config);
-    send_raw_request(
+    configs.insert("web".to_string(), config);
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(800)).await;
+
+    let stdout_log = dir.path().join("volume/web/out.log");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    assert_eq!(content.lines().collect::<Vec<_>>(), ["one"]);
+    assert!(!paths.stdout_log("web").exists());
+    assert!(paths.stderr_log("web").exists());
+
+    use pm3::log::LogStream;
+    assert_eq!(
+        read_logs(&paths, "web"),
+        vec![
+            (Some(LogStream::Stdout), "one".to_string()),
+            (Some(LogStream::Stderr), "two".to_string()),
+        ]
+    );
+    match send_raw_request(
         &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
+        &Request::Info {
+            name: "web".to_string(),
         },
     )
-    .await;
-
-    tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Errored);
-            assert_eq!(info.restarts, 0);
-            assert!(info.pid.is_none(), "pid should be None after exit");
+    .await
+    {
+        Response::ProcessDetail { info } => {
+            assert_eq!(
+                info.stdout_log,
+                Some(stdout_log.to_string_lossy().into_owned())
+            );
         }
-        other => panic!("expected ProcessList, got: {other:?}"),
+        other => panic!("expected ProcessDetail, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -2023,239 +3028,357 @@ async fn test_restart_policy_never_does_not_restart() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_restart_policy_always_restarts_on_clean_exit() {
+async fn test_merge_logs_shares_the_cluster_log() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'exit 0'");
-    config.restart = Some(RestartPolicy::Always);
-    config.max_restarts = Some(2);
-
+    let mut config = test_config("sh -c 'echo \"hi from $PM3_INSTANCE_ID\"; sleep 30'");
+    config.instances = Some(2);
+    config.merge_logs = Some(true);
     let mut configs = HashMap::new();
-    configs.insert("always-restart".to_string(), config);
-    send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
+    configs.insert("web".to_string(), config);
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(800)).await;
 
-    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let content = std::fs::read_to_string(paths.combined_log("web")).unwrap();
+    let mut lines: Vec<&str> = content.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec!["[web:0 out] hi from 0", "[web:1 out] hi from 1"]
+    );
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Stopped);
-            assert_eq!(info.restarts, 2);
-            assert!(info.pid.is_none(), "pid should be None after final exit");
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
-    }
+    // Each instance's log holds its own lines
+    assert_eq!(
+        read_logs(&paths, "web:1"),
+        vec![(Some(pm3::log::LogStream::Stdout), "hi from 1".to_string())]
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_restart_policy_on_failure_exit_zero_not_restarted() {
+async fn test_log_follows_group_members() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'exit 0'");
-    config.restart = Some(RestartPolicy::OnFailure);
-
     let mut configs = HashMap::new();
-    configs.insert("on-failure-clean".to_string(), config);
-    send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
-
-    tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Stopped);
-            assert_eq!(info.restarts, 0);
-            assert!(info.pid.is_none(), "pid should be None after exit");
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
+    for name in ["api", "worker"] {
+        let mut config = test_config(&format!("sh -c 'sleep 0.8; echo from-{name}; sleep 30'"));
+        config.group = Some("backend".to_string());
+        configs.insert(name.to_string(), config);
     }
-
+    configs.insert(
+        "web".to_string(),
+        test_config("sh -c 'sleep 0.8; echo from-web; sleep 30'"),
+    );
+    handle.client().start(configs, None).await.unwrap();
+
+    let paths_clone = paths.clone();
+    let follow = tokio::task::spawn_blocking(move || {
+        let mut stream = UnixStream::connect(paths_clone.socket_file()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let request = Request::Log {
+            name: Some("backend".to_string()),
+            lines: 10,
+            follow: true,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        };
+        stream
+            .write_all(&protocol::encode_request(&request).unwrap())
+            .unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut lines = Vec::new();
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if let Response::LogLine { name, line, .. } = protocol::decode_response(&line).unwrap()
+            {
+                lines.push((name, line));
+            }
+        }
+        lines
+    });
+
+    let mut lines = follow.await.unwrap();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            (Some("api".to_string()), "from-api".to_string()),
+            (Some("worker".to_string()), "from-worker".to_string()),
+        ]
+    );
+
+    let response = send_raw_request(
+        &paths,
+        &Request::Log {
+            name: Some("nope".to_string()),
+            lines: 10,
+            follow: false,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&response, Response::Error { message } if message.contains("not found")),
+        "{response:?}"
+    );
+
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_restart_policy_on_failure_exit_nonzero_restarts() {
+async fn test_log_selects_one_stream() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'exit 1'");
-    config.restart = Some(RestartPolicy::OnFailure);
-    config.max_restarts = Some(2);
-
     let mut configs = HashMap::new();
-    configs.insert("on-failure-err".to_string(), config);
-    send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
+    configs.insert(
+        "both".to_string(),
+        test_config(
+            "sh -c 'echo out1; echo err1 >&2; sleep 0.8; echo out2; echo err2 >&2; sleep 30'",
+        ),
+    );
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(400)).await;
 
-    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let paths_clone = paths.clone();
+    let follow = tokio::task::spawn_blocking(move || {
+        let mut stream = UnixStream::connect(paths_clone.socket_file()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let request = Request::Log {
+            name: Some("both".to_string()),
+            lines: 10,
+            follow: true,
+            since: None,
+            until: None,
+            stream: Some(pm3::log::LogStream::Stderr),
+            namespace: None,
+        };
+        stream
+            .write_all(&protocol::encode_request(&request).unwrap())
+            .unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Errored);
-            assert_eq!(info.restarts, 2);
-            assert!(info.pid.is_none(), "pid should be None after final exit");
+        let mut lines = Vec::new();
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if let Response::LogLine { stream, line, .. } =
+                protocol::decode_response(&line).unwrap()
+            {
+                lines.push((stream, line));
+            }
         }
-        other => panic!("expected ProcessList, got: {other:?}"),
-    }
+        lines
+    });
+
+    let stderr = Some(pm3::log::LogStream::Stderr);
+    assert_eq!(
+        follow.await.unwrap(),
+        vec![
+            (stderr.clone(), "err1".to_string()),
+            (stderr, "err2".to_string())
+        ]
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_restart_policy_stop_exit_codes_prevents_restart() {
+async fn test_log_since_until_filter_by_timestamp() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'exit 42'");
-    config.restart = Some(RestartPolicy::OnFailure);
-    config.stop_exit_codes = Some(vec![42]);
-
+    let mut timed = test_config("sh -c 'echo hello; sleep 30'");
+    timed.log_date_format = Some("%Y-%m-%d %H:%M:%S".to_string());
     let mut configs = HashMap::new();
-    configs.insert("stop-code".to_string(), config);
-    send_raw_request(
+    configs.insert("timed".to_string(), timed);
+    configs.insert("plain".to_string(), test_config("sleep 30"));
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let log = |name: &str, since: Option<&str>, until: Option<&str>| {
+        let request = Request::Log {
+            name: Some(name.to_string()),
+            lines: 15,
+            follow: false,
+            since: since.map(str::to_string),
+            until: until.map(str::to_string),
+            stream: None,
+            namespace: None,
+        };
+        let paths = paths.clone();
+        async move {
+            let mut client = tokio::net::UnixStream::connect(paths.socket_file())
+                .await
+                .unwrap();
+            tokio::io::AsyncWriteExt::write_all(
+                &mut client,
+                &protocol::encode_request(&request).unwrap(),
+            )
+            .await
+            .unwrap();
+            let mut out = String::new();
+            tokio::io::AsyncReadExt::read_to_string(&mut client, &mut out)
+                .await
+                .unwrap();
+            out.lines()
+                .map(|line| protocol::decode_response(line).unwrap())
+                .collect::<Vec<_>>()
+        }
+    };
+    let lines = |responses: Vec<Response>| {
+        responses
+            .into_iter()
+            .filter_map(|r| match r {
+                Response::LogLine { line, .. } => Some(line),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let recent = lines(log("timed", Some("1h"), None).await);
+    assert_eq!(recent.len(), 1);
+    assert!(recent[0].ends_with(" | hello"), "{recent:?}");
+    assert!(lines(log("timed", None, Some("1h")).await).is_empty());
+    assert!(matches!(
+        log("plain", Some("1h"), None).await.as_slice(),
+        [Response::Error { .. }]
+    ));
+    assert!(matches!(
+        log("timed", Some("last week"), None).await.as_slice(),
+        [Response::Error { .. }]
+    ));
+    // Following never reaches an end, so an upper bound can't be honoured
+    let response = send_raw_request(
         &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
+        &Request::Log {
+            name: Some("timed".to_string()),
+            lines: 15,
+            follow: true,
+            since: None,
+            until: Some("1h".to_string()),
+            stream: None,
+            namespace: None,
         },
     )
     .await;
-
-    tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Errored);
-            assert_eq!(info.restarts, 0);
-            assert!(info.pid.is_none(), "pid should be None after exit");
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
-    }
+    assert!(matches!(response, Response::Error { .. }), "{response:?}");
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ── Item 19: Auto-restart ───────────────────────────────────────────
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_auto_restart_recovers_after_crash() {
+async fn test_log_follow_continues_across_restart() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let marker = dir.path().join("marker");
-    let cmd = format!(
-        "sh -c 'MARKER={}; if [ ! -f $MARKER ]; then touch $MARKER; exit 1; fi; sleep 999'",
-        marker.display()
+    let mut configs = HashMap::new();
+    configs.insert(
+        "phoenix".to_string(),
+        test_config("sh -c 'while :; do echo \"pid $$\"; sleep 0.1; done'"),
     );
-    let mut config = test_config(&cmd);
-    config.restart = Some(RestartPolicy::OnFailure);
-    config.max_restarts = Some(5);
+    handle.client().start(configs, None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
 
-    let mut configs = HashMap::new();
-    configs.insert("crash-once".to_string(), config);
+    let paths_clone = paths.clone();
+    let follow_handle = tokio::task::spawn_blocking(move || {
+        let mut stream = UnixStream::connect(paths_clone.socket_file()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(3)))
+            .unwrap();
+        let request = Request::Log {
+            name: Some("phoenix".to_string()),
+            lines: 0,
+            follow: true,
+            since: None,
+            until: None,
+            stream: None,
+            namespace: None,
+        };
+        stream
+            .write_all(&protocol::encode_request(&request).unwrap())
+            .unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        // Read until a line from a second pid shows up
+        let mut pids = Vec::new();
+        for line in BufReader::new(stream).lines() {
+            match line {
+                Ok(line) if !line.is_empty() => {
+                    if let Response::LogLine { line, .. } =
+                        protocol::decode_response(&line).unwrap()
+                        && !pids.contains(&line)
+                    {
+                        pids.push(line);
+                        if pids.len() == 2 {
+                            break;
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+        pids
+    });
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
     send_raw_request(
         &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
+        &Request::Restart {
+            names: Some(vec!["phoenix".to_string()]),
+            except: None,
+            namespace: None,
+            stagger_ms: None,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(1000)).await;
-
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Online);
-            assert_eq!(info.restarts, 1);
-            assert!(info.pid.is_some(), "pid should be present after recovery");
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
-    }
+    let pids = follow_handle.await.unwrap();
+    assert_eq!(
+        pids.len(),
+        2,
+        "the restarted process's output should reach the follower, got: {pids:?}"
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
+// ── Item 15: Flush command ──────────────────────────────────────────
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_auto_restart_stops_after_max_restarts() {
+async fn test_flush_empties_log_file() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'exit 1'");
-    config.restart = Some(RestartPolicy::OnFailure);
-    config.max_restarts = Some(3);
-
     let mut configs = HashMap::new();
-    configs.insert("max-restart".to_string(), config);
+    configs.insert(
+        "echoer".to_string(),
+        test_config("sh -c 'echo flush_test_output; echo flush_err >&2'"),
+    );
     send_raw_request(
         &paths,
         &Request::Start {
@@ -2264,95 +3387,139 @@ async fn test_auto_restart_stops_after_max_restarts() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(2000)).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Errored);
-            assert_eq!(info.restarts, 3);
-            assert!(info.pid.is_none(), "pid should be None after max restarts");
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
-    }
+    // Verify logs have content
+    let stdout_log = paths.stdout_log("echoer");
+    let stderr_log = paths.stderr_log("echoer");
+    assert!(stdout_log.exists(), "stdout log should exist");
+    assert!(stderr_log.exists(), "stderr log should exist");
+    assert!(
+        !std::fs::read_to_string(&stdout_log).unwrap().is_empty(),
+        "stdout log should have content before flush"
+    );
+    assert!(
+        !std::fs::read_to_string(&stderr_log).unwrap().is_empty(),
+        "stderr log should have content before flush"
+    );
+
+    // Flush by name
+    let resp = send_raw_request(
+        &paths,
+        &Request::Flush {
+            names: Some(vec!["echoer".to_string()]),
+            all_files: false,
+            namespace: None,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&resp, Response::Success { .. }),
+        "expected Success, got: {resp:?}"
+    );
+
+    // Verify log files exist but are empty
+    assert!(stdout_log.exists(), "stdout log should still exist");
+    assert!(stderr_log.exists(), "stderr log should still exist");
+    assert!(
+        std::fs::read_to_string(&stdout_log).unwrap().is_empty(),
+        "stdout log should be empty after flush"
+    );
+    assert!(
+        std::fs::read_to_string(&stderr_log).unwrap().is_empty(),
+        "stderr log should be empty after flush"
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_auto_restart_list_shows_restart_count() {
+async fn test_flush_all_empties_all_logs() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let stable_config = test_config("sleep 999");
-
-    let mut crasher_config = test_config("sh -c 'exit 1'");
-    crasher_config.restart = Some(RestartPolicy::OnFailure);
-    crasher_config.max_restarts = Some(2);
-
     let mut configs = HashMap::new();
-    configs.insert("stable".to_string(), stable_config);
-    configs.insert("crasher".to_string(), crasher_config);
-    send_raw_request(
-        &paths,
-        &Request::Start {
+    configs.insert(
+        "alpha".to_string(),
+        test_config("sh -c 'echo alpha_output'"),
+    );
+    configs.insert("beta".to_string(), test_config("sh -c 'echo beta_output'"));
+    send_raw_request(
+        &paths,
+        &Request::Start {
             configs,
             names: None,
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(1500)).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 2);
-            let stable = processes.iter().find(|p| p.name == "stable").unwrap();
-            let crasher = processes.iter().find(|p| p.name == "crasher").unwrap();
+    // Verify logs have content
+    for name in &["alpha", "beta"] {
+        let stdout_log = paths.stdout_log(name);
+        assert!(stdout_log.exists(), "{name} stdout log should exist");
+        assert!(
+            !std::fs::read_to_string(&stdout_log).unwrap().is_empty(),
+            "{name} stdout log should have content before flush"
+        );
+    }
 
-            assert_eq!(stable.status, ProcessStatus::Online);
-            assert_eq!(stable.restarts, 0);
+    // Flush all (no names)
+    let resp = send_raw_request(
+        &paths,
+        &Request::Flush {
+            names: None,
+            all_files: false,
+            namespace: None,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&resp, Response::Success { .. }),
+        "expected Success, got: {resp:?}"
+    );
 
-            assert_eq!(crasher.status, ProcessStatus::Errored);
-            assert_eq!(crasher.restarts, 2);
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
+    // Verify all log files are empty
+    for name in &["alpha", "beta"] {
+        let stdout_log = paths.stdout_log(name);
+        assert!(stdout_log.exists(), "{name} stdout log should still exist");
+        assert!(
+            std::fs::read_to_string(&stdout_log).unwrap().is_empty(),
+            "{name} stdout log should be empty after flush"
+        );
     }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ── Item 20: Exponential backoff ────────────────────────────────────
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_exponential_backoff_increases_delay_between_restarts() {
+async fn test_flush_deletes_rotated_files() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'exit 1'");
-    config.restart = Some(RestartPolicy::OnFailure);
-    config.max_restarts = Some(3);
-
+    // Start a process so it exists in the process table
     let mut configs = HashMap::new();
-    configs.insert("backoff-test".to_string(), config);
-
-    let start = Instant::now();
+    configs.insert("worker".to_string(), test_config("sleep 999"));
     send_raw_request(
         &paths,
         &Request::Start {
@@ -2361,95 +3528,91 @@ async fn test_exponential_backoff_increases_delay_between_restarts() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    // Wait for all 3 restarts to exhaust.
-    // Backoff: 100ms (count=0) + 200ms (count=1) + 400ms (count=2) = 700ms minimum
-    tokio::time::sleep(Duration::from_millis(2000)).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
 
-    let elapsed = start.elapsed();
+    // Create rotated log files manually
+    std::fs::create_dir_all(paths.log_dir()).unwrap();
+    for i in 1..=3 {
+        let rotated_stdout = paths.rotated_stdout_log("worker", i);
+        let rotated_stderr = paths.rotated_stderr_log("worker", i);
+        std::fs::write(&rotated_stdout, format!("rotated stdout {i}")).unwrap();
+        std::fs::write(&rotated_stderr, format!("rotated stderr {i}")).unwrap();
+    }
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Errored);
-            assert_eq!(info.restarts, 3);
-            // Total backoff must be at least 700ms (100 + 200 + 400)
-            assert!(
-                elapsed >= Duration::from_millis(700),
-                "expected at least 700ms of backoff, but only {elapsed:?} elapsed"
-            );
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
+    // Verify rotated files exist
+    for i in 1..=3 {
+        assert!(
+            paths.rotated_stdout_log("worker", i).exists(),
+            "rotated stdout.{i} should exist before flush"
+        );
+        assert!(
+            paths.rotated_stderr_log("worker", i).exists(),
+            "rotated stderr.{i} should exist before flush"
+        );
+    }
+
+    // Flush
+    let resp = send_raw_request(
+        &paths,
+        &Request::Flush {
+            names: Some(vec!["worker".to_string()]),
+            all_files: false,
+            namespace: None,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&resp, Response::Success { .. }),
+        "expected Success, got: {resp:?}"
+    );
+
+    // Verify rotated files are deleted
+    for i in 1..=3 {
+        assert!(
+            !paths.rotated_stdout_log("worker", i).exists(),
+            "rotated stdout.{i} should be deleted after flush"
+        );
+        assert!(
+            !paths.rotated_stderr_log("worker", i).exists(),
+            "rotated stderr.{i} should be deleted after flush"
+        );
     }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ── Item 21: min_uptime ─────────────────────────────────────────────
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_min_uptime_stable_run_resets_restart_count() {
+async fn test_flush_nonexistent_process_returns_error() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    // Marker-file counter: first two runs crash instantly, third runs past
-    // min_uptime then crashes, fourth stays up.
-    let counter = dir.path().join("counter");
-    let cmd = format!(
-        concat!(
-            "sh -c '",
-            "C=$(cat {} 2>/dev/null || echo 0); ",
-            "echo $((C + 1)) > {}; ",
-            "if [ $C -lt 2 ]; then exit 1; fi; ",
-            "if [ $C -eq 2 ]; then sleep 0.5; exit 1; fi; ",
-            "sleep 999",
-            "'"
-        ),
-        counter.display(),
-        counter.display()
-    );
-    let mut config = test_config(&cmd);
-    config.restart = Some(RestartPolicy::OnFailure);
-    config.max_restarts = Some(2);
-    config.min_uptime = Some(200);
-
-    let mut configs = HashMap::new();
-    configs.insert("min-uptime-reset".to_string(), config);
-    send_raw_request(
+    let resp = send_raw_request(
         &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
+        &Request::Flush {
+            names: Some(vec!["nope".to_string()]),
+            all_files: false,
+            namespace: None,
         },
     )
     .await;
-
-    // Two quick crashes (100+200ms backoff) + one 500ms run + 100ms backoff + spawn
-    tokio::time::sleep(Duration::from_millis(2000)).await;
-
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Online);
-            // Without min_uptime reset restarts would hit max (2) and stop.
-            // The reset allows the fourth run to succeed with restarts = 1.
-            assert_eq!(info.restarts, 1);
-            assert!(info.pid.is_some(), "pid should be present");
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("not found"),
+                "error should mention 'not found', got: {message}"
+            );
         }
-        other => panic!("expected ProcessList, got: {other:?}"),
+        other => panic!("expected Error, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -2457,19 +3620,20 @@ async fn test_min_uptime_stable_run_resets_restart_count() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_min_uptime_quick_crash_increments_restart_count() {
+async fn test_flush_group_and_orphaned_files() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'exit 1'");
-    config.restart = Some(RestartPolicy::OnFailure);
-    config.max_restarts = Some(3);
-    config.min_uptime = Some(5000); // 5s — process never lives this long
-
     let mut configs = HashMap::new();
-    configs.insert("quick-crash".to_string(), config);
+    let mut worker = test_config("sh -c 'echo from_worker; sleep 60'");
+    worker.group = Some("workers".to_string());
+    configs.insert("worker".to_string(), worker);
+    configs.insert(
+        "web".to_string(),
+        test_config("sh -c 'echo from_web; sleep 60'"),
+    );
     send_raw_request(
         &paths,
         &Request::Start {
@@ -2478,45 +3642,95 @@ async fn test_min_uptime_quick_crash_increments_restart_count() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(2000)).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1);
-            let info = &processes[0];
-            assert_eq!(info.status, ProcessStatus::Errored);
-            assert_eq!(info.restarts, 3);
-            assert!(info.pid.is_none(), "pid should be None after max restarts");
+    let resp = send_raw_request(
+        &paths,
+        &Request::Flush {
+            names: Some(vec!["workers".to_string()]),
+            all_files: false,
+            namespace: None,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&resp, Response::Success { .. }),
+        "expected Success, got: {resp:?}"
+    );
+    assert!(
+        std::fs::read_to_string(paths.stdout_log("worker"))
+            .unwrap()
+            .is_empty()
+    );
+    assert!(
+        !std::fs::read_to_string(paths.stdout_log("web"))
+            .unwrap()
+            .is_empty(),
+        "processes outside the group should keep their logs"
+    );
+
+    // Logs of a process that was deleted stay behind until --all-files
+    let orphan = paths.stdout_log("deleted");
+    let rotated_orphan = paths.rotated_stdout_log("deleted", 1);
+    std::fs::write(&orphan, "old output").unwrap();
+    std::fs::write(&rotated_orphan, "older output").unwrap();
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Flush {
+            names: None,
+            all_files: true,
+            namespace: None,
+        },
+    )
+    .await;
+    match &resp {
+        Response::Success { message } => {
+            let message = message.as_deref().unwrap_or_default();
+            assert!(
+                message.contains("removed 2 orphaned log files"),
+                "got: {message}"
+            );
         }
-        other => panic!("expected ProcessList, got: {other:?}"),
+        other => panic!("expected Success, got: {other:?}"),
     }
+    assert!(!orphan.exists(), "orphaned log should be removed");
+    assert!(
+        !rotated_orphan.exists(),
+        "orphaned rotation should be removed"
+    );
+    assert!(paths.stdout_log("web").exists(), "live logs are kept");
+    assert!(
+        std::fs::read_to_string(paths.stdout_log("web"))
+            .unwrap()
+            .is_empty()
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// Environment variables (step 22)
-// ---------------------------------------------------------------------------
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_env_vars_passed_to_child() {
+async fn test_prune_removes_stopped_and_errored_processes() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo $FOO'");
-    config.env = Some(HashMap::from([("FOO".to_string(), "bar".to_string())]));
-
+    let mut crasher = test_config("sh -c 'echo crashing; exit 1'");
+    crasher.restart = Some(RestartPolicy::Never);
     let mut configs = HashMap::new();
-    configs.insert("env-test".to_string(), config);
-    let start_resp = send_raw_request(
+    configs.insert("crasher".to_string(), crasher);
+    configs.insert("idle".to_string(), test_config("sleep 60"));
+    configs.insert("web".to_string(), test_config("sleep 60"));
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -2524,22 +3738,67 @@ async fn test_env_vars_passed_to_child() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["idle".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
         },
     )
     .await;
     assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
+        wait_for_statuses(
+            &paths,
+            &[
+                ("crasher", ProcessStatus::Errored),
+                ("idle", ProcessStatus::Stopped),
+            ],
+            20,
+        )
+        .await
     );
+    let orphan = paths.stdout_log("deleted");
+    std::fs::write(&orphan, "old output").unwrap();
 
-    tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let stdout_log = paths.stdout_log("env-test");
-    assert!(stdout_log.exists(), "stdout log file should exist");
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    let resp = send_raw_request(
+        &paths,
+        &Request::Prune {
+            logs: true,
+            namespace: None,
+        },
+    )
+    .await;
+    match &resp {
+        Response::Success { message } => {
+            assert_eq!(
+                message.as_deref(),
+                Some("pruned: crasher, idle; removed 5 log files")
+            );
+        }
+        other => panic!("expected Success, got: {other:?}"),
+    }
+
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(names, vec!["web"]);
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+    assert!(!orphan.exists(), "orphaned log should be removed");
+    assert!(!paths.stdout_log("crasher").exists());
+    assert!(!paths.stderr_log("idle").exists());
     assert!(
-        content.contains("bar"),
-        "stdout log should contain 'bar', got: {content}"
+        paths.stdout_log("web").exists(),
+        "running processes keep logs"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -2547,69 +3806,106 @@ async fn test_env_vars_passed_to_child() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_multiple_env_vars_passed_correctly() {
+async fn test_prune_stays_in_namespace_and_keeps_saved_logs() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo A=$A B=$B'");
-    config.env = Some(HashMap::from([
-        ("A".to_string(), "1".to_string()),
-        ("B".to_string(), "2".to_string()),
-    ]));
+    // Saved before a daemon restart, and not resurrected yet
+    let saved = pm3::dump::DumpEntry {
+        name: "saved".to_string(),
+        config: test_config("sleep 60"),
+        pid: None,
+        restarts: 0,
+    };
+    std::fs::write(paths.dump_file(), pm3::dump::to_json(&[saved]).unwrap()).unwrap();
+    std::fs::create_dir_all(paths.log_dir()).unwrap();
+    std::fs::write(paths.stdout_log("saved"), "old output").unwrap();
+
+    let mut job = test_config("true");
+    job.kind = Some(config::ProcessKind::Task);
+    for (namespace, name, config) in [
+        ("shop", "job", job),
+        ("blog", "idle", test_config("sh -c 'exit 1'")),
+    ] {
+        let mut config = config;
+        config.restart = Some(RestartPolicy::Never);
+        send_raw_request(
+            &paths,
+            &Request::Start {
+                configs: HashMap::from([(name.to_string(), config)]),
+                names: None,
+                env: None,
+                wait: false,
+                path: None,
+                force: false,
+                namespace: Some(namespace.to_string()),
+                progress: false,
+            },
+        )
+        .await;
+    }
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("shop/job", ProcessStatus::Succeeded),
+                ("blog/idle", ProcessStatus::Errored),
+            ],
+            20,
+        )
+        .await
+    );
 
-    let mut configs = HashMap::new();
-    configs.insert("multi-env".to_string(), config);
-    let start_resp = send_raw_request(
+    let resp = send_raw_request(
         &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
+        &Request::Prune {
+            logs: false,
+            namespace: Some("shop".to_string()),
         },
     )
     .await;
-    assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
-    );
-
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    match &resp {
+        Response::Success { message } => {
+            assert_eq!(
+                message.as_deref(),
+                Some("pruned: shop/job; removed 0 log files")
+            );
+        }
+        other => panic!("expected Success, got: {other:?}"),
+    }
 
-    let stdout_log = paths.stdout_log("multi-env");
-    assert!(stdout_log.exists(), "stdout log file should exist");
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
-    assert!(
-        content.contains("A=1"),
-        "stdout log should contain 'A=1', got: {content}"
-    );
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(names, vec!["blog/idle"]);
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
     assert!(
-        content.contains("B=2"),
-        "stdout log should contain 'B=2', got: {content}"
+        paths.stdout_log("saved").exists(),
+        "logs of saved processes are kept for resurrect"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
+// ── Item 16: Log timestamp tests ────────────────────────────────────
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_env_vars_dont_leak_between_processes() {
+async fn test_log_timestamp_prefix() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config_with_env = test_config("sh -c 'echo SECRET=$SECRET'");
-    config_with_env.env = Some(HashMap::from([("SECRET".to_string(), "xyz".to_string())]));
-
-    let config_without_env = test_config("sh -c 'echo SECRET=$SECRET'");
+    let mut config = test_config("sh -c 'echo line1; echo line2; echo line3'");
+    config.log_date_format = Some("%Y-%m-%d %H:%M:%S".to_string());
 
     let mut configs = HashMap::new();
-    configs.insert("with-secret".to_string(), config_with_env);
-    configs.insert("without-secret".to_string(), config_without_env);
+    configs.insert("ts-echo".to_string(), config);
     let start_resp = send_raw_request(
         &paths,
         &Request::Start {
@@ -2618,6 +3914,9 @@ async fn test_env_vars_dont_leak_between_processes() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -2628,50 +3927,41 @@ async fn test_env_vars_dont_leak_between_processes() {
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let with_log = paths.stdout_log("with-secret");
-    assert!(with_log.exists(), "with-secret stdout log should exist");
-    let with_content = std::fs::read_to_string(&with_log).unwrap();
-    assert!(
-        with_content.contains("SECRET=xyz"),
-        "with-secret log should contain 'SECRET=xyz', got: {with_content}"
-    );
+    let stdout_log = paths.stdout_log("ts-echo");
+    assert!(stdout_log.exists(), "stdout log file should exist");
 
-    let without_log = paths.stdout_log("without-secret");
-    assert!(
-        without_log.exists(),
-        "without-secret stdout log should exist"
-    );
-    let without_content = std::fs::read_to_string(&without_log).unwrap();
-    assert!(
-        !without_content.contains("xyz"),
-        "without-secret log should NOT contain 'xyz', got: {without_content}"
-    );
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    let re = Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2} \| .+$").unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 3, "should have 3 lines, got: {lines:?}");
+    for line in &lines {
+        assert!(
+            re.is_match(line),
+            "line did not match timestamp pattern: {line}"
+        );
+    }
+    // Verify original content is preserved after the separator
+    assert!(content.contains("line1"), "content should contain 'line1'");
+    assert!(content.contains("line2"), "content should contain 'line2'");
+    assert!(content.contains("line3"), "content should contain 'line3'");
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// Env file support (step 23)
-// ---------------------------------------------------------------------------
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_env_file_values_available_in_child() {
+async fn test_log_no_timestamp_without_format() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
-    let workdir = dir.path().join("workdir");
-    std::fs::create_dir_all(&workdir).unwrap();
-    std::fs::write(workdir.join(".env"), "MY_VAR=from_env_file\n").unwrap();
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo $MY_VAR'");
-    config.cwd = Some(workdir.to_str().unwrap().to_string());
-    config.env_file = Some(EnvFile::Single(".env".to_string()));
-
+    // Default test_config has log_date_format = None
     let mut configs = HashMap::new();
-    configs.insert("env-file-test".to_string(), config);
+    configs.insert(
+        "no-ts".to_string(),
+        test_config("sh -c 'echo plain1; echo plain2'"),
+    );
     let start_resp = send_raw_request(
         &paths,
         &Request::Start {
@@ -2680,6 +3970,9 @@ async fn test_env_file_values_available_in_child() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -2690,39 +3983,37 @@ async fn test_env_file_values_available_in_child() {
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let stdout_log = paths.stdout_log("env-file-test");
+    let stdout_log = paths.stdout_log("no-ts");
     assert!(stdout_log.exists(), "stdout log file should exist");
+
     let content = std::fs::read_to_string(&stdout_log).unwrap();
-    assert!(
-        content.contains("from_env_file"),
-        "stdout should contain 'from_env_file', got: {content}"
-    );
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 2, "should have 2 lines, got: {lines:?}");
+    assert_eq!(lines[0], "plain1");
+    assert_eq!(lines[1], "plain2");
+    for line in &lines {
+        assert!(
+            !line.contains(" | "),
+            "line should not contain ' | ' separator: {line}"
+        );
+    }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_inline_env_overrides_env_file() {
+async fn test_log_timestamp_stderr() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
-    let workdir = dir.path().join("workdir");
-    std::fs::create_dir_all(&workdir).unwrap();
-    std::fs::write(workdir.join(".env"), "MY_VAR=from_file\n").unwrap();
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo $MY_VAR'");
-    config.cwd = Some(workdir.to_str().unwrap().to_string());
-    config.env_file = Some(EnvFile::Single(".env".to_string()));
-    config.env = Some(HashMap::from([(
-        "MY_VAR".to_string(),
-        "from_inline".to_string(),
-    )]));
+    let mut config = test_config("sh -c 'echo err1 >&2; echo err2 >&2'");
+    config.log_date_format = Some("%Y-%m-%d %H:%M:%S".to_string());
 
     let mut configs = HashMap::new();
-    configs.insert("env-override".to_string(), config);
+    configs.insert("ts-err".to_string(), config);
     let start_resp = send_raw_request(
         &paths,
         &Request::Start {
@@ -2731,6 +4022,9 @@ async fn test_inline_env_overrides_env_file() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
@@ -2741,39 +4035,42 @@ async fn test_inline_env_overrides_env_file() {
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let stdout_log = paths.stdout_log("env-override");
-    assert!(stdout_log.exists(), "stdout log file should exist");
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
-    assert!(
-        content.contains("from_inline"),
-        "inline env should override env_file, got: {content}"
-    );
-    assert!(
-        !content.contains("from_file"),
-        "env_file value should NOT appear, got: {content}"
-    );
+    let stderr_log = paths.stderr_log("ts-err");
+    assert!(stderr_log.exists(), "stderr log file should exist");
+
+    let content = std::fs::read_to_string(&stderr_log).unwrap();
+    let re = Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2} \| .+$").unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines.len(), 2, "should have 2 lines, got: {lines:?}");
+    for line in &lines {
+        assert!(
+            re.is_match(line),
+            "stderr line did not match timestamp pattern: {line}"
+        );
+    }
+    assert!(content.contains("err1"), "content should contain 'err1'");
+    assert!(content.contains("err2"), "content should contain 'err2'");
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
+// ── Item 17: Log rotation ───────────────────────────────────────────
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_missing_env_file_returns_error() {
+async fn test_log_rotation_creates_rotated_file() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
-    let workdir = dir.path().join("workdir");
-    std::fs::create_dir_all(&workdir).unwrap();
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sleep 999");
-    config.cwd = Some(workdir.to_str().unwrap().to_string());
-    config.env_file = Some(EnvFile::Single("nonexistent.env".to_string()));
-
+    // Generate 12,000 lines × 1000 bytes = 12MB (> 10MB threshold)
     let mut configs = HashMap::new();
-    configs.insert("missing-env-file".to_string(), config);
-    let resp = send_raw_request(
+    configs.insert(
+        "biglog".to_string(),
+        test_config("sh -c 'yes \"$(printf \"%0999d\" 0)\" | head -n 12000'"),
+    );
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -2781,45 +4078,40 @@ async fn test_missing_env_file_returns_error() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("env file"),
-                "error should mention 'env file', got: {message}"
-            );
-        }
-        other => panic!("expected Error, got: {other:?}"),
-    }
+
+    // Wait for the log copier to flush
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    let rotated = paths.rotated_stdout_log("biglog", 1);
+    assert!(
+        rotated.exists(),
+        "rotated stdout log .1 should exist after writing >10MB"
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_env_file_array_loads_multiple_files() {
+async fn test_log_rotation_only_keeps_three_files() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
-    let workdir = dir.path().join("workdir");
-    std::fs::create_dir_all(&workdir).unwrap();
-    std::fs::write(workdir.join(".env"), "VAR_A=alpha\n").unwrap();
-    std::fs::write(workdir.join(".env.local"), "VAR_B=beta\n").unwrap();
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo A=$VAR_A B=$VAR_B'");
-    config.cwd = Some(workdir.to_str().unwrap().to_string());
-    config.env_file = Some(EnvFile::Multiple(vec![
-        ".env".to_string(),
-        ".env.local".to_string(),
-    ]));
-
+    // 1. Start "rotator" with a quick command that exits immediately
     let mut configs = HashMap::new();
-    configs.insert("multi-env-file".to_string(), config);
-    let start_resp = send_raw_request(
+    configs.insert(
+        "rotator".to_string(),
+        test_config("sh -c 'echo setup_done'"),
+    );
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -2827,189 +4119,230 @@ async fn test_env_file_array_loads_multiple_files() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
-    );
 
+    // 2. Wait for log copier to finish
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let stdout_log = paths.stdout_log("multi-env-file");
-    assert!(stdout_log.exists(), "stdout log file should exist");
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    // 3. Stop "rotator" (may already be stopped)
+    let _ = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["rotator".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+
+    // 4. Wait for stop to settle
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // 5. Seed: overwrite stdout log with exactly LOG_ROTATION_SIZE bytes of padding
+    let stdout_log = paths.stdout_log("rotator");
+    std::fs::write(&stdout_log, vec![b'X'; LOG_ROTATION_SIZE as usize]).unwrap();
+
+    // 6. Seed: create .1, .2, .3 manually
+    for i in 1..=3 {
+        std::fs::write(
+            paths.rotated_stdout_log("rotator", i),
+            format!("old-rotated-{i}"),
+        )
+        .unwrap();
+    }
+
+    // 7. Restart "rotator" — log copier opens file, sees 10MB, first line triggers rotation
+    let restart_resp = send_raw_request(
+        &paths,
+        &Request::Restart {
+            names: Some(vec!["rotator".to_string()]),
+            except: None,
+            namespace: None,
+            stagger_ms: None,
+        },
+    )
+    .await;
     assert!(
-        content.contains("A=alpha"),
-        "stdout should contain 'A=alpha', got: {content}"
+        matches!(&restart_resp, Response::Success { .. }),
+        "expected Success, got: {restart_resp:?}"
     );
+
+    // 8. Wait for rotation to happen
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    // 9. Verify .1, .2, .3 exist; .4 does NOT
+    for i in 1..=3 {
+        assert!(
+            paths.rotated_stdout_log("rotator", i).exists(),
+            "rotated stdout log .{i} should exist"
+        );
+    }
     assert!(
-        content.contains("B=beta"),
-        "stdout should contain 'B=beta', got: {content}"
+        !paths.rotated_stdout_log("rotator", 4).exists(),
+        "rotated stdout log .4 should NOT exist (max keep is 3)"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// Per-environment config (step 24)
-// ---------------------------------------------------------------------------
+// ── Item 18: Restart policy ─────────────────────────────────────────
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_env_start_merges_env_production() {
+async fn test_restart_policy_never_does_not_restart() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo BASE=$BASE PROD_VAR=$PROD_VAR'");
-    config.env = Some(HashMap::from([(
-        "BASE".to_string(),
-        "base_val".to_string(),
-    )]));
-    config.environments.insert(
-        "production".to_string(),
-        HashMap::from([("PROD_VAR".to_string(), "prod_val".to_string())]),
-    );
+    let mut config = test_config("sh -c 'exit 1'");
+    config.restart = Some(RestartPolicy::Never);
 
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), config);
-    let start_resp = send_raw_request(
+    configs.insert("never-restart".to_string(), config);
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
-            env: Some("production".to_string()),
+            env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
-    );
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let stdout_log = paths.stdout_log("web");
-    assert!(stdout_log.exists(), "stdout log file should exist");
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
-    assert!(
-        content.contains("BASE=base_val"),
-        "should contain 'BASE=base_val', got: {content}"
-    );
-    assert!(
-        content.contains("PROD_VAR=prod_val"),
-        "should contain 'PROD_VAR=prod_val', got: {content}"
-    );
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Errored);
+            assert_eq!(info.restarts, 0);
+            assert!(info.pid.is_none(), "pid should be None after exit");
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_env_production_overrides_base() {
+async fn test_restart_policy_always_restarts_on_clean_exit() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sh -c 'echo MY_VAR=$MY_VAR'");
-    config.env = Some(HashMap::from([("MY_VAR".to_string(), "base".to_string())]));
-    config.environments.insert(
-        "production".to_string(),
-        HashMap::from([("MY_VAR".to_string(), "prod".to_string())]),
-    );
+    let mut config = test_config("sh -c 'exit 0'");
+    config.restart = Some(RestartPolicy::Always);
+    config.max_restarts = Some(2);
 
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), config);
-    let start_resp = send_raw_request(
+    configs.insert("always-restart".to_string(), config);
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
-            env: Some("production".to_string()),
+            env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
-    );
 
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
 
-    let stdout_log = paths.stdout_log("web");
-    assert!(stdout_log.exists(), "stdout log file should exist");
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
-    assert!(
-        content.contains("MY_VAR=prod"),
-        "production env should override base, got: {content}"
-    );
-    assert!(
-        !content.contains("MY_VAR=base"),
-        "base value should NOT appear, got: {content}"
-    );
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Stopped);
+            assert_eq!(info.restarts, 2);
+            assert!(info.pid.is_none(), "pid should be None after final exit");
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_env_unknown_name_errors() {
+async fn test_restart_policy_on_failure_exit_zero_not_restarted() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
+    let mut config = test_config("sh -c 'exit 0'");
+    config.restart = Some(RestartPolicy::OnFailure);
+
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), test_config("sleep 999"));
-    let resp = send_raw_request(
+    configs.insert("on-failure-clean".to_string(), config);
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
-            env: Some("nonexistent".to_string()),
+            env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("nonexistent"),
-                "error should mention the environment name, got: {message}"
-            );
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Stopped);
+            assert_eq!(info.restarts, 0);
+            assert!(info.pid.is_none(), "pid should be None after exit");
         }
-        other => panic!("expected Error, got: {other:?}"),
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ── Step 25: Info command ───────────────────────────────────────────
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_info_returns_process_detail() {
+async fn test_restart_policy_on_failure_exit_nonzero_restarts() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sleep 999");
-    config.cwd = Some("/tmp".to_string());
-    config.env = Some(HashMap::from([("MY_VAR".to_string(), "hello".to_string())]));
-    config.group = Some("backend".to_string());
+    let mut config = test_config("sh -c 'exit 1'");
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.max_restarts = Some(2);
 
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), config);
+    configs.insert("on-failure-err".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
@@ -3018,35 +4351,25 @@ async fn test_info_returns_process_detail() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(200)).await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
 
-    let resp = send_raw_request(
-        &paths,
-        &Request::Info {
-            name: "web".to_string(),
-        },
-    )
-    .await;
-    match &resp {
-        Response::ProcessDetail { info } => {
-            assert_eq!(info.name, "web");
-            assert_eq!(info.status, ProcessStatus::Online);
-            assert!(info.pid.is_some(), "should have a PID");
-            assert_eq!(info.command, "sleep 999");
-            assert_eq!(info.cwd.as_deref(), Some("/tmp"));
-            assert_eq!(info.group.as_deref(), Some("backend"));
-            assert!(info.uptime.is_some(), "should have uptime");
-            assert_eq!(info.restarts, 0);
-            let env = info.env.as_ref().unwrap();
-            assert_eq!(env.get("MY_VAR").unwrap(), "hello");
-            assert!(info.stdout_log.is_some(), "should have stdout log path");
-            assert!(info.stderr_log.is_some(), "should have stderr log path");
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Errored);
+            assert_eq!(info.restarts, 2);
+            assert!(info.pid.is_none(), "pid should be None after final exit");
         }
-        other => panic!("expected ProcessDetail, got: {other:?}"),
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -3054,54 +4377,72 @@ async fn test_info_returns_process_detail() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_info_nonexistent_returns_error() {
+async fn test_restart_policy_stop_exit_codes_prevents_restart() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let resp = send_raw_request(
+    let mut config = test_config("sh -c 'exit 42'");
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.stop_exit_codes = Some(vec![42]);
+
+    let mut configs = HashMap::new();
+    configs.insert("stop-code".to_string(), config);
+    send_raw_request(
         &paths,
-        &Request::Info {
-            name: "nonexistent".to_string(),
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("not found"),
-                "error should contain 'not found', got: {message}"
-            );
-        }
-        other => panic!("expected Error, got: {other:?}"),
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Errored);
+            assert_eq!(info.restarts, 0);
+            assert!(info.pid.is_none(), "pid should be None after exit");
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// Process dependency tests
-// ---------------------------------------------------------------------------
+// ── Item 19: Auto-restart ───────────────────────────────────────────
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_dependency_start_order() {
+async fn test_auto_restart_recovers_after_crash() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    // db has no deps, web depends on db
-    let db_config = test_config("sleep 999");
-    let mut web_config = test_config("sleep 999");
-    web_config.depends_on = Some(vec!["db".to_string()]);
+    let marker = dir.path().join("marker");
+    let cmd = format!(
+        "sh -c 'MARKER={}; if [ ! -f $MARKER ]; then touch $MARKER; exit 1; fi; sleep 999'",
+        marker.display()
+    );
+    let mut config = test_config(&cmd);
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.max_restarts = Some(5);
 
     let mut configs = HashMap::new();
-    configs.insert("db".to_string(), db_config);
-    configs.insert("web".to_string(), web_config);
-
-    let resp = send_raw_request(
+    configs.insert("crash-once".to_string(), config);
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3109,30 +4450,23 @@ async fn test_dependency_start_order() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    assert!(
-        matches!(&resp, Response::Success { .. }),
-        "expected Success, got: {resp:?}"
-    );
-
-    tokio::time::sleep(Duration::from_millis(200)).await;
+    tokio::time::sleep(Duration::from_millis(1000)).await;
 
-    // Both should be online
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 2);
-            for p in processes {
-                assert_eq!(
-                    p.status,
-                    ProcessStatus::Online,
-                    "process '{}' should be online",
-                    p.name
-                );
-            }
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Online);
+            assert_eq!(info.restarts, 1);
+            assert!(info.pid.is_some(), "pid should be present after recovery");
         }
         other => panic!("expected ProcessList, got: {other:?}"),
     }
@@ -3142,20 +4476,18 @@ async fn test_dependency_start_order() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_dependency_stop_order() {
+async fn test_auto_restart_stops_after_max_restarts() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let db_config = test_config("sleep 999");
-    let mut web_config = test_config("sleep 999");
-    web_config.depends_on = Some(vec!["db".to_string()]);
+    let mut config = test_config("sh -c 'exit 1'");
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.max_restarts = Some(3);
 
     let mut configs = HashMap::new();
-    configs.insert("db".to_string(), db_config);
-    configs.insert("web".to_string(), web_config);
-
+    configs.insert("max-restart".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
@@ -3164,44 +4496,23 @@ async fn test_dependency_stop_order() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(200)).await;
-
-    // Stop db — should cascade to web (web stopped first as dependent)
-    let stop_resp = send_raw_request(
-        &paths,
-        &Request::Stop {
-            names: Some(vec!["db".to_string()]),
-        },
-    )
-    .await;
-
-    match &stop_resp {
-        Response::Success { message } => {
-            let msg = message.as_deref().unwrap_or("");
-            assert!(
-                msg.contains("web") && msg.contains("db"),
-                "stop should include both web and db, got: {msg}"
-            );
-        }
-        other => panic!("expected Success, got: {other:?}"),
-    }
+    tokio::time::sleep(Duration::from_millis(2000)).await;
 
-    // Both should be stopped
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
-            for p in processes {
-                assert_eq!(
-                    p.status,
-                    ProcessStatus::Stopped,
-                    "process '{}' should be stopped",
-                    p.name
-                );
-            }
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Errored);
+            assert_eq!(info.restarts, 3);
+            assert!(info.pid.is_none(), "pid should be None after max restarts");
         }
         other => panic!("expected ProcessList, got: {other:?}"),
     }
@@ -3211,23 +4522,22 @@ async fn test_dependency_stop_order() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_circular_dependency_error() {
+async fn test_max_restarts_window_only_counts_recent_crashes() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut a_config = test_config("sleep 999");
-    a_config.depends_on = Some(vec!["b".to_string()]);
-
-    let mut b_config = test_config("sleep 999");
-    b_config.depends_on = Some(vec!["a".to_string()]);
+    let mut config = test_config("sh -c 'echo run; exit 1'");
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.max_restarts = Some(2);
+    config.max_restarts_window = Some("1s".to_string());
+    config.restart_backoff = Some(config::RestartBackoff::Fixed);
+    config.restart_delay = Some(700);
 
     let mut configs = HashMap::new();
-    configs.insert("a".to_string(), a_config);
-    configs.insert("b".to_string(), b_config);
-
-    let resp = send_raw_request(
+    configs.insert("bursty".to_string(), config);
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3235,18 +4545,28 @@ async fn test_circular_dependency_error() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("circular"),
-                "error should mention circular, got: {message}"
-            );
+    // Without the window, the budget of 2 would allow only 3 runs in total
+    tokio::time::sleep(Duration::from_millis(3000)).await;
+
+    let content = std::fs::read_to_string(paths.stdout_log("bursty")).unwrap();
+    let runs = content.lines().filter(|l| l.contains("run")).count();
+    assert!(runs >= 4, "expected restarts to continue, got {runs} runs");
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            // the budget counts the window, the reported count every restart
+            let restarts = processes[0].restarts;
+            assert!(restarts >= 3, "restarts should count them all: {restarts}");
         }
-        other => panic!("expected Error, got: {other:?}"),
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -3254,19 +4574,20 @@ async fn test_circular_dependency_error() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_missing_dependency_error() {
+async fn test_task_reports_failed_and_is_not_restarted_or_saved() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut web_config = test_config("sleep 999");
-    web_config.depends_on = Some(vec!["nonexistent".to_string()]);
+    let mut task = test_config("sh -c 'sleep 0.2; exit 2'");
+    task.kind = Some(config::ProcessKind::Task);
+    task.restart = Some(RestartPolicy::Always);
 
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), web_config);
-
-    let resp = send_raw_request(
+    configs.insert("job".to_string(), task);
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3274,84 +4595,92 @@ async fn test_missing_dependency_error() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("nonexistent"),
-                "error should mention the missing dep, got: {message}"
-            );
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            let job = processes.iter().find(|p| p.name == "job").unwrap();
+            assert_eq!(job.status, ProcessStatus::Failed);
+            assert_eq!(job.restarts, 0, "tasks must not be restarted");
+            assert!(job.pid.is_none());
         }
-        other => panic!("expected Error, got: {other:?}"),
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
+    send_raw_request(&paths, &Request::Save { tag: None }).await;
+    let dump: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(paths.dump_file()).unwrap()).unwrap();
+    let names: Vec<&str> = dump["processes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["web"], "tasks should be excluded from the dump");
+
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// Process groups (step 28)
-// ---------------------------------------------------------------------------
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_group_start_by_group_name() {
+async fn test_metrics_endpoint_scraped_into_list() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
-    let handle = start_test_daemon(&paths).await;
-
-    let mut api_config = test_config("sleep 999");
-    api_config.group = Some("backend".to_string());
-
-    let mut worker_config = test_config("sleep 999");
-    worker_config.group = Some("backend".to_string());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else {
+                return;
+            };
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await;
+            let body = "# TYPE queue_depth gauge\nqueue_depth{queue=\"a\"} 4\nqueue_depth{queue=\"b\"} 8\n";
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = socket.write_all(resp.as_bytes()).await;
+        }
+    });
 
-    let frontend_config = test_config("sleep 999");
+    let handle = start_test_daemon(&paths).await;
 
+    let mut config = test_config("sleep 999");
+    config.metrics_endpoint = Some(format!("http://127.0.0.1:{port}/metrics"));
+    config.metrics = Some(vec!["queue_depth".to_string()]);
     let mut configs = HashMap::new();
-    configs.insert("api".to_string(), api_config);
-    configs.insert("worker".to_string(), worker_config);
-    configs.insert("frontend".to_string(), frontend_config);
-
-    // Start only the "backend" group
-    let resp = send_raw_request(
+    configs.insert("worker".to_string(), config);
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
-            names: Some(vec!["backend".to_string()]),
+            names: None,
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    match &resp {
-        Response::Success { message } => {
-            let msg = message.as_deref().unwrap_or("");
-            assert!(
-                msg.contains("api") && msg.contains("worker"),
-                "should start both backend processes, got: {msg}"
-            );
-            assert!(
-                !msg.contains("frontend"),
-                "should NOT start frontend, got: {msg}"
-            );
-        }
-        other => panic!("expected Success, got: {other:?}"),
-    }
+    tokio::time::sleep(Duration::from_millis(1000)).await;
 
-    // List should only have the two backend processes
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 2, "should have 2 processes started");
-            let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
-            assert!(names.contains(&"api"), "api should be running");
-            assert!(names.contains(&"worker"), "worker should be running");
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes[0].metrics.get("queue_depth"), Some(&12.0));
         }
         other => panic!("expected ProcessList, got: {other:?}"),
     }
@@ -3361,26 +4690,21 @@ async fn test_group_start_by_group_name() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_group_stop_by_group_name() {
+async fn test_flapping_process_is_quarantined() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let mut api_config = test_config("sleep 999");
-    api_config.group = Some("backend".to_string());
-
-    let mut worker_config = test_config("sleep 999");
-    worker_config.group = Some("backend".to_string());
-
-    let frontend_config = test_config("sleep 999");
+    let mut config = test_config("sh -c 'exit 1'");
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.max_restarts = Some(10);
+    config.flap_threshold = Some(2);
+    config.flap_window = Some(10_000);
+    config.flap_cooldown = Some(60_000);
 
     let mut configs = HashMap::new();
-    configs.insert("api".to_string(), api_config);
-    configs.insert("worker".to_string(), worker_config);
-    configs.insert("frontend".to_string(), frontend_config);
-
-    // Start all
+    configs.insert("flapper".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
@@ -3389,106 +4713,97 @@ async fn test_group_stop_by_group_name() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(200)).await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
 
-    // Stop only the "backend" group
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Flapping);
+            assert!(info.pid.is_none(), "pid should be None while flapping");
+            assert!(info.restarts < 10, "restarts should pause while flapping");
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    // A manual stop clears the quarantine
     let stop_resp = send_raw_request(
         &paths,
         &Request::Stop {
-            names: Some(vec!["backend".to_string()]),
+            names: Some(vec!["flapper".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
         },
     )
     .await;
+    assert!(matches!(stop_resp, Response::Success { .. }));
 
-    match &stop_resp {
-        Response::Success { message } => {
-            let msg = message.as_deref().unwrap_or("");
-            assert!(
-                msg.contains("api") && msg.contains("worker"),
-                "should stop both backend processes, got: {msg}"
-            );
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes[0].status, ProcessStatus::Stopped);
         }
-        other => panic!("expected Success, got: {other:?}"),
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
-    // Verify: api and worker stopped, frontend still online
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            let api = processes.iter().find(|p| p.name == "api").unwrap();
-            assert_eq!(api.status, ProcessStatus::Stopped, "api should be stopped");
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
 
-            let worker = processes.iter().find(|p| p.name == "worker").unwrap();
-            assert_eq!(
-                worker.status,
-                ProcessStatus::Stopped,
-                "worker should be stopped"
-            );
-
-            let frontend = processes.iter().find(|p| p.name == "frontend").unwrap();
-            assert_eq!(
-                frontend.status,
-                ProcessStatus::Online,
-                "frontend should still be online"
-            );
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
-    }
-
-    send_raw_request(&paths, &Request::Kill).await;
-    let _ = handle.await;
-}
-
-#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_process_name_takes_priority_over_group() {
-    let dir = TempDir::new().unwrap();
-    let paths = Paths::with_base(dir.path().to_path_buf());
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_auto_restart_list_shows_restart_count() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    // Process named "backend" and another process with group = "backend"
-    let backend_process = test_config("sleep 999");
+    let stable_config = test_config("sleep 999");
 
-    let mut grouped = test_config("sleep 999");
-    grouped.group = Some("backend".to_string());
+    let mut crasher_config = test_config("sh -c 'exit 1'");
+    crasher_config.restart = Some(RestartPolicy::OnFailure);
+    crasher_config.max_restarts = Some(2);
 
     let mut configs = HashMap::new();
-    configs.insert("backend".to_string(), backend_process);
-    configs.insert("api".to_string(), grouped);
-
-    // Start "backend" — should start only the process named "backend",
-    // NOT the "api" process that has group = "backend"
-    let resp = send_raw_request(
+    configs.insert("stable".to_string(), stable_config);
+    configs.insert("crasher".to_string(), crasher_config);
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
-            names: Some(vec!["backend".to_string()]),
+            names: None,
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    match &resp {
-        Response::Success { message } => {
-            let msg = message.as_deref().unwrap_or("");
-            assert!(msg.contains("backend"), "should start backend, got: {msg}");
-            assert!(!msg.contains("api"), "should NOT start api, got: {msg}");
-        }
-        other => panic!("expected Success, got: {other:?}"),
-    }
+    tokio::time::sleep(Duration::from_millis(1500)).await;
 
-    // Only the "backend" process should be running
-    let list_resp = send_raw_request(&paths, &Request::List).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
     match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(processes.len(), 1, "should have 1 process started");
-            assert_eq!(processes[0].name, "backend");
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 2);
+            let stable = processes.iter().find(|p| p.name == "stable").unwrap();
+            let crasher = processes.iter().find(|p| p.name == "crasher").unwrap();
+
+            assert_eq!(stable.status, ProcessStatus::Online);
+            assert_eq!(stable.restarts, 0);
+
+            assert_eq!(crasher.status, ProcessStatus::Errored);
+            assert_eq!(crasher.restarts, 2);
         }
         other => panic!("expected ProcessList, got: {other:?}"),
     }
@@ -3497,63 +4812,96 @@ async fn test_process_name_takes_priority_over_group() {
     let _ = handle.await;
 }
 
+// ── Item 20: Exponential backoff ────────────────────────────────────
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_group_not_found_returns_error() {
+async fn test_exponential_backoff_increases_delay_between_restarts() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
+    let mut config = test_config("sh -c 'exit 1'");
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.max_restarts = Some(3);
+
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), test_config("sleep 999"));
+    configs.insert("backoff-test".to_string(), config);
 
-    let resp = send_raw_request(
+    let start = Instant::now();
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
-            names: Some(vec!["nonexistent-group".to_string()]),
+            names: None,
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    match &resp {
-        Response::Error { message } => {
+    // Wait for all 3 restarts to exhaust.
+    // Backoff: 100ms (count=0) + 200ms (count=1) + 400ms (count=2) = 700ms minimum
+    tokio::time::sleep(Duration::from_millis(2000)).await;
+
+    let elapsed = start.elapsed();
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Errored);
+            assert_eq!(info.restarts, 3);
+            // Total backoff must be at least 700ms (100 + 200 + 400)
             assert!(
-                message.contains("not found"),
-                "error should contain 'not found', got: {message}"
+                elapsed >= Duration::from_millis(700),
+                "expected at least 700ms of backoff, but only {elapsed:?} elapsed"
             );
         }
-        other => panic!("expected Error, got: {other:?}"),
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// Signal command (step 29)
-// ---------------------------------------------------------------------------
+// ── Item 21: min_uptime ─────────────────────────────────────────────
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_signal_sends_to_process() {
+async fn test_min_uptime_stable_run_resets_restart_count() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let marker = dir.path().join("sigusr1_received");
+    // Marker-file counter: first two runs crash instantly, third runs past
+    // min_uptime then crashes, fourth stays up.
+    let counter = dir.path().join("counter");
     let cmd = format!(
-        "sh -c 'trap \"touch {}\" USR1; while true; do sleep 0.1; done'",
-        marker.display()
+        concat!(
+            "sh -c '",
+            "C=$(cat {} 2>/dev/null || echo 0); ",
+            "echo $((C + 1)) > {}; ",
+            "if [ $C -lt 2 ]; then exit 1; fi; ",
+            "if [ $C -eq 2 ]; then sleep 0.5; exit 1; fi; ",
+            "sleep 999",
+            "'"
+        ),
+        counter.display(),
+        counter.display()
     );
     let mut config = test_config(&cmd);
-    config.restart = Some(RestartPolicy::Never);
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.max_restarts = Some(2);
+    config.min_uptime = Some(200);
 
     let mut configs = HashMap::new();
-    configs.insert("trapper".to_string(), config);
+    configs.insert("min-uptime-reset".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
@@ -3562,78 +4910,98 @@ async fn test_signal_sends_to_process() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    // Two quick crashes (100+200ms backoff) + one 500ms run + 100ms backoff + spawn
+    tokio::time::sleep(Duration::from_millis(2000)).await;
 
-    let resp = send_raw_request(
-        &paths,
-        &Request::Signal {
-            name: "trapper".to_string(),
-            signal: "SIGUSR1".to_string(),
-        },
-    )
-    .await;
-    match &resp {
-        Response::Success { message } => {
-            let msg = message.as_deref().unwrap_or("");
-            assert!(msg.contains("sent"), "should contain 'sent', got: {msg}");
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Online);
+            // Without min_uptime reset restarts would hit max (2) and stop.
+            // The reset allows the fourth run to succeed with restarts = 1.
+            assert_eq!(info.restarts, 1);
+            assert!(info.pid.is_some(), "pid should be present");
         }
-        other => panic!("expected Success, got: {other:?}"),
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
-    // Wait for trap handler to execute
-    tokio::time::sleep(Duration::from_millis(500)).await;
-    assert!(
-        marker.exists(),
-        "marker file should exist after SIGUSR1 trap fired"
-    );
-
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_signal_nonexistent_process_returns_error() {
+async fn test_min_uptime_quick_crash_increments_restart_count() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
-    let resp = send_raw_request(
+    let mut config = test_config("sh -c 'exit 1'");
+    config.restart = Some(RestartPolicy::OnFailure);
+    config.max_restarts = Some(3);
+    config.min_uptime = Some(5000); // 5s — process never lives this long
+
+    let mut configs = HashMap::new();
+    configs.insert("quick-crash".to_string(), config);
+    send_raw_request(
         &paths,
-        &Request::Signal {
-            name: "nonexistent".to_string(),
-            signal: "SIGHUP".to_string(),
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("not found"),
-                "error should contain 'not found', got: {message}"
-            );
+
+    tokio::time::sleep(Duration::from_millis(2000)).await;
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            let info = &processes[0];
+            assert_eq!(info.status, ProcessStatus::Errored);
+            assert_eq!(info.restarts, 3);
+            assert!(info.pid.is_none(), "pid should be None after max restarts");
         }
-        other => panic!("expected Error, got: {other:?}"),
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
+// ---------------------------------------------------------------------------
+// Environment variables (step 22)
+// ---------------------------------------------------------------------------
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_signal_invalid_signal_returns_error() {
+async fn test_env_vars_passed_to_child() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
+    let mut config = test_config("sh -c 'echo $FOO'");
+    config.env = Some(HashMap::from([("FOO".to_string(), "bar".to_string())]));
+
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), test_config("sleep 999"));
-    send_raw_request(
+    configs.insert("env-test".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3641,44 +5009,47 @@ async fn test_signal_invalid_signal_returns_error() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
-    tokio::time::sleep(Duration::from_millis(200)).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let resp = send_raw_request(
-        &paths,
-        &Request::Signal {
-            name: "web".to_string(),
-            signal: "SIGFAKE".to_string(),
-        },
-    )
-    .await;
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("invalid signal"),
-                "error should contain 'invalid signal', got: {message}"
-            );
-        }
-        other => panic!("expected Error, got: {other:?}"),
-    }
+    let stdout_log = paths.stdout_log("env-test");
+    assert!(stdout_log.exists(), "stdout log file should exist");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    assert!(
+        content.contains("bar"),
+        "stdout log should contain 'bar', got: {content}"
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_signal_stopped_process_returns_error() {
+async fn test_multiple_env_vars_passed_correctly() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
 
     let handle = start_test_daemon(&paths).await;
 
+    let mut config = test_config("sh -c 'echo A=$A B=$B'");
+    config.env = Some(HashMap::from([
+        ("A".to_string(), "1".to_string()),
+        ("B".to_string(), "2".to_string()),
+    ]));
+
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), test_config("sleep 999"));
-    send_raw_request(
+    configs.insert("multi-env".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3686,62 +5057,51 @@ async fn test_signal_stopped_process_returns_error() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
-    tokio::time::sleep(Duration::from_millis(200)).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // Stop the process first
-    send_raw_request(
-        &paths,
-        &Request::Stop {
-            names: Some(vec!["web".to_string()]),
-        },
-    )
-    .await;
-
-    tokio::time::sleep(Duration::from_millis(200)).await;
-
-    let resp = send_raw_request(
-        &paths,
-        &Request::Signal {
-            name: "web".to_string(),
-            signal: "SIGUSR1".to_string(),
-        },
-    )
-    .await;
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("not running"),
-                "error should contain 'not running', got: {message}"
-            );
-        }
-        other => panic!("expected Error, got: {other:?}"),
-    }
+    let stdout_log = paths.stdout_log("multi-env");
+    assert!(stdout_log.exists(), "stdout log file should exist");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    assert!(
+        content.contains("A=1"),
+        "stdout log should contain 'A=1', got: {content}"
+    );
+    assert!(
+        content.contains("B=2"),
+        "stdout log should contain 'B=2', got: {content}"
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// Lifecycle hooks (PRD item 30)
-// ---------------------------------------------------------------------------
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_pre_start_runs_before_process() {
+async fn test_env_vars_dont_leak_between_processes() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sleep 999");
-    config.pre_start = Some("echo pre_start_marker".to_string());
+    let mut config_with_env = test_config("sh -c 'echo SECRET=$SECRET'");
+    config_with_env.env = Some(HashMap::from([("SECRET".to_string(), "xyz".to_string())]));
 
-    let mut configs = HashMap::new();
-    configs.insert("hooky".to_string(), config);
+    let config_without_env = test_config("sh -c 'echo SECRET=$SECRET'");
 
-    let resp = send_raw_request(
+    let mut configs = HashMap::new();
+    configs.insert("with-secret".to_string(), config_with_env);
+    configs.insert("without-secret".to_string(), config_without_env);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3749,37 +5109,64 @@ async fn test_pre_start_runs_before_process() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(matches!(resp, Response::Success { .. }));
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
-    tokio::time::sleep(Duration::from_millis(300)).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
-    let stdout_log = paths.stdout_log("hooky");
-    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    let with_log = paths.stdout_log("with-secret");
+    assert!(with_log.exists(), "with-secret stdout log should exist");
+    let with_content = std::fs::read_to_string(&with_log).unwrap();
     assert!(
-        content.contains("pre_start_marker"),
-        "stdout log should contain pre_start_marker, got: {content}"
+        with_content.contains("SECRET=xyz"),
+        "with-secret log should contain 'SECRET=xyz', got: {with_content}"
+    );
+
+    let without_log = paths.stdout_log("without-secret");
+    assert!(
+        without_log.exists(),
+        "without-secret stdout log should exist"
+    );
+    let without_content = std::fs::read_to_string(&without_log).unwrap();
+    assert!(
+        !without_content.contains("xyz"),
+        "without-secret log should NOT contain 'xyz', got: {without_content}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
+// ---------------------------------------------------------------------------
+// Env file support (step 23)
+// ---------------------------------------------------------------------------
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_pre_start_failure_prevents_start() {
+async fn test_env_file_values_available_in_child() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let workdir = dir.path().join("workdir");
+    std::fs::create_dir_all(&workdir).unwrap();
+    std::fs::write(workdir.join(".env"), "MY_VAR=from_env_file\n").unwrap();
+
     let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sleep 999");
-    config.pre_start = Some("exit 1".to_string());
+    let mut config = test_config("sh -c 'echo $MY_VAR'");
+    config.cwd = Some(workdir.to_str().unwrap().to_string());
+    config.env_file = Some(EnvFile::Single(".env".to_string()));
 
     let mut configs = HashMap::new();
-    configs.insert("failhook".to_string(), config);
-
-    let resp = send_raw_request(
+    configs.insert("env-file-test".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3787,49 +5174,53 @@ async fn test_pre_start_failure_prevents_start() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    match &resp {
-        Response::Error { message } => {
-            assert!(
-                message.contains("hook failed"),
-                "error should mention hook failed, got: {message}"
-            );
-        }
-        other => panic!("expected Error, got: {other:?}"),
-    }
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
-    // Verify process is NOT in the list
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match list_resp {
-        Response::ProcessList { processes } => {
-            assert!(
-                !processes.iter().any(|p| p.name == "failhook"),
-                "failhook should not be in process list"
-            );
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
-    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let stdout_log = paths.stdout_log("env-file-test");
+    assert!(stdout_log.exists(), "stdout log file should exist");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    assert!(
+        content.contains("from_env_file"),
+        "stdout should contain 'from_env_file', got: {content}"
+    );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_post_stop_runs_after_stop() {
+async fn test_inline_env_overrides_env_file() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let workdir = dir.path().join("workdir");
+    std::fs::create_dir_all(&workdir).unwrap();
+    std::fs::write(workdir.join(".env"), "MY_VAR=from_file\n").unwrap();
+
     let handle = start_test_daemon(&paths).await;
 
-    let marker_path = dir.path().join("post_stop_marker");
-    let mut config = test_config("sleep 999");
-    config.post_stop = Some(format!("touch {}", marker_path.display()));
+    let mut config = test_config("sh -c 'echo $MY_VAR'");
+    config.cwd = Some(workdir.to_str().unwrap().to_string());
+    config.env_file = Some(EnvFile::Single(".env".to_string()));
+    config.env = Some(HashMap::from([(
+        "MY_VAR".to_string(),
+        "from_inline".to_string(),
+    )]));
 
     let mut configs = HashMap::new();
-    configs.insert("stophook".to_string(), config);
-
-    let resp = send_raw_request(
+    configs.insert("env-override".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3837,29 +5228,29 @@ async fn test_post_stop_runs_after_stop() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(matches!(resp, Response::Success { .. }));
-
-    tokio::time::sleep(Duration::from_millis(200)).await;
-
-    // Stop the process
-    let stop_resp = send_raw_request(
-        &paths,
-        &Request::Stop {
-            names: Some(vec!["stophook".to_string()]),
-        },
-    )
-    .await;
-    assert!(matches!(stop_resp, Response::Success { .. }));
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
-    tokio::time::sleep(Duration::from_millis(300)).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
 
+    let stdout_log = paths.stdout_log("env-override");
+    assert!(stdout_log.exists(), "stdout log file should exist");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
     assert!(
-        marker_path.exists(),
-        "post_stop marker file should exist at {}",
-        marker_path.display()
+        content.contains("from_inline"),
+        "inline env should override env_file, got: {content}"
+    );
+    assert!(
+        !content.contains("from_file"),
+        "env_file value should NOT appear, got: {content}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -3867,19 +5258,21 @@ async fn test_post_stop_runs_after_stop() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_restart_runs_post_stop_then_pre_start() {
+async fn test_missing_env_file_returns_error() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let workdir = dir.path().join("workdir");
+    std::fs::create_dir_all(&workdir).unwrap();
+
     let handle = start_test_daemon(&paths).await;
 
-    let order_file = dir.path().join("hook_order.txt");
     let mut config = test_config("sleep 999");
-    config.post_stop = Some(format!("echo post_stop >> {}", order_file.display()));
-    config.pre_start = Some(format!("echo pre_start >> {}", order_file.display()));
+    config.cwd = Some(workdir.to_str().unwrap().to_string());
+    config.env_file = Some(EnvFile::Single("nonexistent.env".to_string()));
 
     let mut configs = HashMap::new();
-    configs.insert("orderhook".to_string(), config);
-
+    configs.insert("missing-env-file".to_string(), config);
     let resp = send_raw_request(
         &paths,
         &Request::Start {
@@ -3888,59 +5281,46 @@ async fn test_restart_runs_post_stop_then_pre_start() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(matches!(resp, Response::Success { .. }));
-
-    tokio::time::sleep(Duration::from_millis(200)).await;
-
-    // Clear the order file (pre_start from initial start wrote to it)
-    std::fs::write(&order_file, "").unwrap();
-
-    // Restart the process
-    let restart_resp = send_raw_request(
-        &paths,
-        &Request::Restart {
-            names: Some(vec!["orderhook".to_string()]),
-        },
-    )
-    .await;
-    assert!(matches!(restart_resp, Response::Success { .. }));
-
-    tokio::time::sleep(Duration::from_millis(500)).await;
-
-    let content = std::fs::read_to_string(&order_file).unwrap();
-    let lines: Vec<&str> = content.lines().collect();
-    assert_eq!(
-        lines,
-        vec!["post_stop", "pre_start"],
-        "hooks should run in order: post_stop then pre_start, got: {:?}",
-        lines
-    );
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("env file"),
+                "error should mention 'env file', got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_max_memory_restart_triggers() {
+async fn test_env_cmd_output_available_in_child() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
     let handle = start_test_daemon(&paths).await;
 
-    // Node script that allocates ~30MB of filled buffers (RSS ~65MB)
-    let mut config = test_config(
-        r#"node -e "const bufs = []; for (let i = 0; i < 30; i++) { const b = Buffer.alloc(1024*1024); b.fill(0x42); bufs.push(b); } setInterval(() => {}, 1000);""#,
-    );
-    config.max_memory = Some("50M".to_string());
-    // Don't let restart policy interfere; memory restarts are maintenance ops
-    config.restart = Some(RestartPolicy::Never);
+    let mut config = test_config("sh -c 'echo \"SECRET=[$SECRET]\"'");
+    config.env = Some(HashMap::from([(
+        "VAULT_PATH".to_string(),
+        "db".to_string(),
+    )]));
+    config.env_cmd = Some(HashMap::from([(
+        "SECRET".to_string(),
+        "echo \"s3cret-$VAULT_PATH\"".to_string(),
+    )]));
 
     let mut configs = HashMap::new();
-    configs.insert("memhog".to_string(), config);
-
-    let resp = send_raw_request(
+    configs.insert("env-cmd".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -3948,41 +5328,23 @@ async fn test_max_memory_restart_triggers() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(matches!(resp, Response::Success { .. }));
-
-    // Capture original PID
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
+
     tokio::time::sleep(Duration::from_millis(500)).await;
-    let orig_pid = {
-        let list_resp = send_raw_request(&paths, &Request::List).await;
-        match list_resp {
-            Response::ProcessList { processes } => {
-                let p = processes.iter().find(|p| p.name == "memhog").unwrap();
-                p.pid.unwrap()
-            }
-            _ => panic!("expected process list"),
-        }
-    };
 
-    // Wait for memory monitor to detect + restart (up to 15s)
-    let mut restarted = false;
-    for _ in 0..30 {
-        tokio::time::sleep(Duration::from_millis(500)).await;
-        let list_resp = send_raw_request(&paths, &Request::List).await;
-        if let Response::ProcessList { processes } = list_resp {
-            let p = processes.iter().find(|p| p.name == "memhog").unwrap();
-            if p.restarts >= 1 && p.pid.is_some() && p.pid.unwrap() != orig_pid {
-                restarted = true;
-                assert!(p.status == ProcessStatus::Online || p.status == ProcessStatus::Starting);
-                break;
-            }
-        }
-    }
+    let content = std::fs::read_to_string(paths.stdout_log("env-cmd")).unwrap();
     assert!(
-        restarted,
-        "process should have been restarted due to memory limit"
+        content.contains("SECRET=[s3cret-db]"),
+        "env_cmd output without its newline should be set, got: {content}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -3990,17 +5352,20 @@ async fn test_max_memory_restart_triggers() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_max_memory_no_restart_when_under_limit() {
+async fn test_failing_env_cmd_returns_error() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
     let handle = start_test_daemon(&paths).await;
 
     let mut config = test_config("sleep 999");
-    config.max_memory = Some("500M".to_string());
+    config.env_cmd = Some(HashMap::from([(
+        "SECRET".to_string(),
+        "echo 'permission denied' >&2; exit 2".to_string(),
+    )]));
 
     let mut configs = HashMap::new();
-    configs.insert("lowmem".to_string(), config);
-
+    configs.insert("env-cmd".to_string(), config);
     let resp = send_raw_request(
         &paths,
         &Request::Start {
@@ -4009,22 +5374,21 @@ async fn test_max_memory_no_restart_when_under_limit() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(matches!(resp, Response::Success { .. }));
-
-    // Wait ~12s (two check intervals)
-    tokio::time::sleep(Duration::from_secs(12)).await;
-
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match list_resp {
-        Response::ProcessList { processes } => {
-            let p = processes.iter().find(|p| p.name == "lowmem").unwrap();
-            assert_eq!(p.restarts, 0, "process should not have been restarted");
-            assert_eq!(p.status, ProcessStatus::Online);
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("env_cmd failed: SECRET")
+                    && message.contains("exited with code 2: permission denied"),
+                "error should name the variable and the failure, got: {message}"
+            );
         }
-        _ => panic!("expected process list"),
+        other => panic!("expected Error, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -4032,70 +5396,51 @@ async fn test_max_memory_no_restart_when_under_limit() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_watch_restarts_on_file_change() {
+async fn test_slow_env_cmd_does_not_block_list() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-    let handle = start_test_daemon(&paths).await;
 
-    // Create a watched directory with a file
-    let watch_dir = dir.path().join("watched_src");
-    std::fs::create_dir_all(&watch_dir).unwrap();
-    std::fs::write(watch_dir.join("app.txt"), "initial").unwrap();
+    let handle = start_test_daemon(&paths).await;
 
     let mut config = test_config("sleep 999");
-    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
-    config.restart = Some(RestartPolicy::Never);
-
+    config.env_cmd = Some(HashMap::from([(
+        "SECRET".to_string(),
+        "sleep 3; echo s3cret".to_string(),
+    )]));
     let mut configs = HashMap::new();
-    configs.insert("watchme".to_string(), config);
-
-    let resp = send_raw_request(
-        &paths,
-        &Request::Start {
-            configs,
-            names: None,
-            env: None,
-            wait: false,
-            path: None,
-        },
-    )
-    .await;
-    assert!(matches!(resp, Response::Success { .. }));
-
-    // Capture original PID
-    tokio::time::sleep(Duration::from_millis(500)).await;
-    let orig_pid = {
-        let list_resp = send_raw_request(&paths, &Request::List).await;
-        match list_resp {
-            Response::ProcessList { processes } => {
-                let p = processes.iter().find(|p| p.name == "watchme").unwrap();
-                p.pid.unwrap()
-            }
-            _ => panic!("expected process list"),
-        }
-    };
+    configs.insert("slow-secret".to_string(), config);
+    let start_paths = paths.clone();
+    let start = tokio::spawn(async move {
+        send_raw_request(
+            &start_paths,
+            &Request::Start {
+                configs,
+                names: None,
+                env: None,
+                wait: false,
+                path: None,
+                force: false,
+                namespace: None,
+                progress: false,
+            },
+        )
+        .await
+    });
 
-    // Modify a file in the watched directory
+    // Other requests are answered while the env_cmd runs
     tokio::time::sleep(Duration::from_millis(500)).await;
-    std::fs::write(watch_dir.join("app.txt"), "changed").unwrap();
+    let list = tokio::time::timeout(
+        Duration::from_secs(1),
+        send_raw_request(&paths, &Request::List { filter: None }),
+    )
+    .await
+    .expect("list should not wait for the env_cmd");
+    assert!(matches!(list, Response::ProcessList { .. }), "{list:?}");
 
-    // Wait for watcher to detect + debounce + restart (up to 10s)
-    let mut restarted = false;
-    for _ in 0..20 {
-        tokio::time::sleep(Duration::from_millis(500)).await;
-        let list_resp = send_raw_request(&paths, &Request::List).await;
-        if let Response::ProcessList { processes } = list_resp {
-            let p = processes.iter().find(|p| p.name == "watchme").unwrap();
-            if p.restarts >= 1 && p.pid.is_some() && p.pid.unwrap() != orig_pid {
-                restarted = true;
-                assert!(p.status == ProcessStatus::Online || p.status == ProcessStatus::Starting);
-                break;
-            }
-        }
-    }
+    let resp = start.await.unwrap();
     assert!(
-        restarted,
-        "process should have been restarted after file change"
+        matches!(resp, Response::Success { .. }),
+        "expected Success, got: {resp:?}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -4103,23 +5448,27 @@ async fn test_watch_restarts_on_file_change() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_watch_debounce_rapid_changes_trigger_one_restart() {
+async fn test_env_file_array_loads_multiple_files() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-    let handle = start_test_daemon(&paths).await;
 
-    let watch_dir = dir.path().join("debounce_src");
-    std::fs::create_dir_all(&watch_dir).unwrap();
-    std::fs::write(watch_dir.join("file.txt"), "v0").unwrap();
+    let workdir = dir.path().join("workdir");
+    std::fs::create_dir_all(&workdir).unwrap();
+    std::fs::write(workdir.join(".env"), "VAR_A=alpha\n").unwrap();
+    std::fs::write(workdir.join(".env.local"), "VAR_B=beta\n").unwrap();
 
-    let mut config = test_config("sleep 999");
-    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
-    config.restart = Some(RestartPolicy::Never);
+    let handle = start_test_daemon(&paths).await;
 
-    let mut configs = HashMap::new();
-    configs.insert("debounce".to_string(), config);
+    let mut config = test_config("sh -c 'echo A=$VAR_A B=$VAR_B'");
+    config.cwd = Some(workdir.to_str().unwrap().to_string());
+    config.env_file = Some(EnvFile::Multiple(vec![
+        ".env".to_string(),
+        ".env.local".to_string(),
+    ]));
 
-    let resp = send_raw_request(
+    let mut configs = HashMap::new();
+    configs.insert("multi-env-file".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
@@ -4127,109 +5476,89 @@ async fn test_watch_debounce_rapid_changes_trigger_one_restart() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(matches!(resp, Response::Success { .. }));
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // Rapid-fire multiple changes within debounce window
-    for i in 1..=5 {
-        std::fs::write(watch_dir.join("file.txt"), format!("v{i}")).unwrap();
-        tokio::time::sleep(Duration::from_millis(50)).await;
-    }
-
-    // Wait for restart
-    let mut final_restarts = 0;
-    for _ in 0..20 {
-        tokio::time::sleep(Duration::from_millis(500)).await;
-        let list_resp = send_raw_request(&paths, &Request::List).await;
-        if let Response::ProcessList { processes } = list_resp {
-            let p = processes.iter().find(|p| p.name == "debounce").unwrap();
-            final_restarts = p.restarts;
-            if final_restarts >= 1 {
-                break;
-            }
-        }
-    }
-
-    // Should have restarted exactly once (debounced), not 5 times
-    assert_eq!(
-        final_restarts, 1,
-        "rapid file changes should debounce into a single restart, got {} restarts",
-        final_restarts
+    let stdout_log = paths.stdout_log("multi-env-file");
+    assert!(stdout_log.exists(), "stdout log file should exist");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    assert!(
+        content.contains("A=alpha"),
+        "stdout should contain 'A=alpha', got: {content}"
+    );
+    assert!(
+        content.contains("B=beta"),
+        "stdout should contain 'B=beta', got: {content}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
+// ---------------------------------------------------------------------------
+// Per-environment config (step 24)
+// ---------------------------------------------------------------------------
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_watch_true_watches_cwd() {
+async fn test_env_start_merges_env_production() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-    let handle = start_test_daemon(&paths).await;
 
-    // Create a cwd directory
-    let cwd_dir = dir.path().join("app_cwd");
-    std::fs::create_dir_all(&cwd_dir).unwrap();
-    std::fs::write(cwd_dir.join("data.txt"), "initial").unwrap();
+    let handle = start_test_daemon(&paths).await;
 
-    let mut config = test_config("sleep 999");
-    config.watch = Some(Watch::Enabled(true));
-    config.cwd = Some(cwd_dir.to_string_lossy().to_string());
-    config.restart = Some(RestartPolicy::Never);
+    let mut config = test_config("sh -c 'echo BASE=$BASE PROD_VAR=$PROD_VAR'");
+    config.env = Some(HashMap::from([(
+        "BASE".to_string(),
+        "base_val".to_string(),
+    )]));
+    config.environments.insert(
+        "production".to_string(),
+        HashMap::from([("PROD_VAR".to_string(), "prod_val".to_string())]),
+    );
 
     let mut configs = HashMap::new();
-    configs.insert("cwdwatch".to_string(), config);
-
-    let resp = send_raw_request(
+    configs.insert("web".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
-            env: None,
+            env: Some("production".to_string()),
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(matches!(resp, Response::Success { .. }));
-
-    // Capture original PID
-    tokio::time::sleep(Duration::from_millis(500)).await;
-    let orig_pid = {
-        let list_resp = send_raw_request(&paths, &Request::List).await;
-        match list_resp {
-            Response::ProcessList { processes } => {
-                let p = processes.iter().find(|p| p.name == "cwdwatch").unwrap();
-                p.pid.unwrap()
-            }
-            _ => panic!("expected process list"),
-        }
-    };
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
-    // Modify a file in the cwd directory
     tokio::time::sleep(Duration::from_millis(500)).await;
-    std::fs::write(cwd_dir.join("data.txt"), "modified").unwrap();
 
-    // Wait for watcher to restart
-    let mut restarted = false;
-    for _ in 0..20 {
-        tokio::time::sleep(Duration::from_millis(500)).await;
-        let list_resp = send_raw_request(&paths, &Request::List).await;
-        if let Response::ProcessList { processes } = list_resp {
-            let p = processes.iter().find(|p| p.name == "cwdwatch").unwrap();
-            if p.restarts >= 1 && p.pid.is_some() && p.pid.unwrap() != orig_pid {
-                restarted = true;
-                break;
-            }
-        }
-    }
+    let stdout_log = paths.stdout_log("web");
+    assert!(stdout_log.exists(), "stdout log file should exist");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
     assert!(
-        restarted,
-        "process should have been restarted when file in cwd changed"
+        content.contains("BASE=base_val"),
+        "should contain 'BASE=base_val', got: {content}"
+    );
+    assert!(
+        content.contains("PROD_VAR=prod_val"),
+        "should contain 'PROD_VAR=prod_val', got: {content}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -4237,165 +5566,158 @@ async fn test_watch_true_watches_cwd() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_watch_ignore_excludes_directories() {
+async fn test_env_production_overrides_base() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
     let handle = start_test_daemon(&paths).await;
 
-    // Create watched directory with an ignored subdirectory and a non-ignored one
-    let watch_dir = dir.path().join("ignore_src");
-    let ignored_dir = watch_dir.join("node_modules");
-    let src_dir = watch_dir.join("src");
-    std::fs::create_dir_all(&ignored_dir).unwrap();
-    std::fs::create_dir_all(&src_dir).unwrap();
-    std::fs::write(ignored_dir.join("dep.js"), "initial").unwrap();
-    std::fs::write(src_dir.join("app.js"), "initial").unwrap();
-
-    let mut config = test_config("sleep 999");
-    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
-    config.watch_ignore = Some(vec!["node_modules".to_string()]);
-    config.restart = Some(RestartPolicy::Never);
+    let mut config = test_config("sh -c 'echo MY_VAR=$MY_VAR'");
+    config.env = Some(HashMap::from([("MY_VAR".to_string(), "base".to_string())]));
+    config.environments.insert(
+        "production".to_string(),
+        HashMap::from([("MY_VAR".to_string(), "prod".to_string())]),
+    );
 
     let mut configs = HashMap::new();
-    configs.insert("ignoreme".to_string(), config);
-
-    let resp = send_raw_request(
+    configs.insert("web".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
-            env: None,
+            env: Some("production".to_string()),
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(matches!(resp, Response::Success { .. }));
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // Modify a file in the IGNORED directory — should NOT trigger restart
-    std::fs::write(ignored_dir.join("dep.js"), "changed").unwrap();
-
-    // Wait long enough for watcher to process (debounce + margin)
-    tokio::time::sleep(Duration::from_secs(3)).await;
-
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match list_resp {
-        Response::ProcessList { processes } => {
-            let p = processes.iter().find(|p| p.name == "ignoreme").unwrap();
-            assert_eq!(
-                p.restarts, 0,
-                "changes in ignored directory should NOT trigger restart"
-            );
-            assert_eq!(p.status, ProcessStatus::Online);
-        }
-        _ => panic!("expected process list"),
-    }
-
-    // Now modify a file in the NON-ignored directory — should trigger restart
-    std::fs::write(src_dir.join("app.js"), "changed").unwrap();
-
-    let mut restarted = false;
-    for _ in 0..20 {
-        tokio::time::sleep(Duration::from_millis(500)).await;
-        let list_resp = send_raw_request(&paths, &Request::List).await;
-        if let Response::ProcessList { processes } = list_resp {
-            let p = processes.iter().find(|p| p.name == "ignoreme").unwrap();
-            if p.restarts >= 1 {
-                restarted = true;
-                break;
-            }
-        }
-    }
+    let stdout_log = paths.stdout_log("web");
+    assert!(stdout_log.exists(), "stdout log file should exist");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
     assert!(
-        restarted,
-        "changes in non-ignored directory should trigger restart"
+        content.contains("MY_VAR=prod"),
+        "production env should override base, got: {content}"
+    );
+    assert!(
+        !content.contains("MY_VAR=base"),
+        "base value should NOT appear, got: {content}"
     );
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// Cron restart tests
-// ---------------------------------------------------------------------------
-
-#[tokio::test]
-async fn test_cron_restart_triggers() {
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_env_overrides_instances() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
     let handle = start_test_daemon(&paths).await;
 
-    // Use a 6-field cron expression (with seconds): fire every 3 seconds
     let mut config = test_config("sleep 999");
-    config.cron_restart = Some("*/3 * * * * *".to_string());
+    config.environment_overrides.insert(
+        "production".to_string(),
+        toml::from_str("instances = 3").unwrap(),
+    );
 
     let mut configs = HashMap::new();
-    configs.insert("cronproc".to_string(), config);
-
-    send_raw_request(
+    configs.insert("web".to_string(), config);
+    let start_resp = send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
-            env: None,
+            env: Some("production".to_string()),
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
 
-    // Get initial PID
-    let initial_pid: Option<u32>;
-    loop {
-        let resp = send_raw_request(&paths, &Request::List).await;
-        if let Response::ProcessList { processes } = resp
-            && let Some(pid) = processes
-                .iter()
-                .find(|p| p.name == "cronproc")
-                .and_then(|p| p.pid)
-        {
-            initial_pid = Some(pid);
-            break;
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            let mut names: Vec<_> = processes.iter().map(|p| p.name.as_str()).collect();
+            names.sort();
+            assert_eq!(names, vec!["web:0", "web:1", "web:2"]);
         }
-        tokio::time::sleep(Duration::from_millis(100)).await;
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
-    // Wait for cron to trigger a restart (up to 10 seconds)
-    let mut restarted = false;
-    for _ in 0..40 {
-        tokio::time::sleep(Duration::from_millis(250)).await;
-        let resp = send_raw_request(&paths, &Request::List).await;
-        if let Response::ProcessList { processes } = resp
-            && let Some(p) = processes.iter().find(|p| p.name == "cronproc")
-            && p.restarts >= 1
-            && p.pid != initial_pid
-            && p.status == ProcessStatus::Online
-        {
-            restarted = true;
-            break;
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_env_unknown_name_errors() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: Some("nonexistent".to_string()),
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("nonexistent"),
+                "error should mention the environment name, got: {message}"
+            );
         }
+        other => panic!("expected Error, got: {other:?}"),
     }
-    assert!(restarted, "cron_restart should trigger a restart");
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-#[tokio::test]
-async fn test_cron_restart_no_trigger_with_long_interval() {
+// ── Step 25: Info command ───────────────────────────────────────────
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_info_returns_process_detail() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
     let handle = start_test_daemon(&paths).await;
 
-    // Use a cron expression that won't fire during the test (daily at 3am)
     let mut config = test_config("sleep 999");
-    config.cron_restart = Some("0 3 * * *".to_string());
+    config.cwd = Some("/tmp".to_string());
+    config.env = Some(HashMap::from([("MY_VAR".to_string(), "hello".to_string())]));
+    config.group = Some("backend".to_string());
 
     let mut configs = HashMap::new();
-    configs.insert("longcron".to_string(), config);
-
+    configs.insert("web".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
@@ -4404,42 +5726,59 @@ async fn test_cron_restart_no_trigger_with_long_interval() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    // Wait a few seconds and verify no restart happened
-    tokio::time::sleep(Duration::from_secs(5)).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
 
-    let resp = send_raw_request(&paths, &Request::List).await;
-    match resp {
-        Response::ProcessList { processes } => {
-            let p = processes.iter().find(|p| p.name == "longcron").unwrap();
-            assert_eq!(p.restarts, 0, "no cron restart should have triggered");
-            assert_eq!(p.status, ProcessStatus::Online);
+    let resp = send_raw_request(
+        &paths,
+        &Request::Info {
+            name: "web".to_string(),
+        },
+    )
+    .await;
+    match &resp {
+        Response::ProcessDetail { info } => {
+            assert_eq!(info.name, "web");
+            assert_eq!(info.status, ProcessStatus::Online);
+            assert!(info.pid.is_some(), "should have a PID");
+            assert_eq!(info.command, "sleep 999");
+            assert_eq!(info.cwd.as_deref(), Some("/tmp"));
+            assert_eq!(info.group.as_deref(), Some("backend"));
+            assert!(info.uptime.is_some(), "should have uptime");
+            assert_eq!(info.restarts, 0);
+            let env = info.env.as_ref().unwrap();
+            assert_eq!(env.get("MY_VAR").unwrap(), "hello");
+            assert!(info.stdout_log.is_some(), "should have stdout log path");
+            assert!(info.stderr_log.is_some(), "should have stderr log path");
         }
-        _ => panic!("expected process list"),
+        other => panic!("expected ProcessDetail, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-// ---------------------------------------------------------------------------
-// State persistence (save / resurrect) tests
-// ---------------------------------------------------------------------------
-
-#[tokio::test]
-async fn test_save_writes_dump_file() {
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_env_mask_hides_secrets_in_info_and_dump() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+
     let handle = start_test_daemon(&paths).await;
 
-    // Start two processes
+    let mut config = test_config("sh -c 'echo API_KEY=$API_KEY; sleep 999'");
+    config.env = Some(HashMap::from([
+        ("PORT".to_string(), "3000".to_string()),
+        ("API_KEY".to_string(), "abc123".to_string()),
+        ("db_password".to_string(), "hunter2".to_string()),
+    ]));
     let mut configs = HashMap::new();
-    configs.insert("alpha".to_string(), test_config("sleep 999"));
-    configs.insert("beta".to_string(), test_config("sleep 888"));
-
+    configs.insert("web".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
@@ -4448,286 +5787,4664 @@ async fn test_save_writes_dump_file() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
     tokio::time::sleep(Duration::from_millis(500)).await;
 
-    // Save
-    let resp = send_raw_request(&paths, &Request::Save).await;
-    match resp {
-        Response::Success { message } => {
-            assert!(message.unwrap().contains("2 process(es)"));
+    let content = std::fs::read_to_string(paths.stdout_log("web")).unwrap();
+    assert!(content.contains("API_KEY=abc123"), "got: {content}");
+
+    match send_raw_request(
+        &paths,
+        &Request::Info {
+            name: "web".to_string(),
+        },
+    )
+    .await
+    {
+        Response::ProcessDetail { info } => {
+            let env = info.env.as_ref().unwrap();
+            assert_eq!(env["PORT"], "3000");
+            assert_eq!(env["API_KEY"], pm3::dump::MASKED);
+            assert_eq!(env["db_password"], pm3::dump::MASKED);
         }
-        _ => panic!("expected success, got {:?}", resp),
+        other => panic!("expected ProcessDetail, got: {other:?}"),
     }
 
-    // Verify dump file exists and is valid JSON
-    let dump_path = paths.dump_file();
-    assert!(dump_path.exists(), "dump.json should exist");
-    let data = std::fs::read_to_string(&dump_path).unwrap();
-    let entries: serde_json::Value = serde_json::from_str(&data).unwrap();
-    assert!(entries.is_array());
-    assert_eq!(entries.as_array().unwrap().len(), 2);
+    send_raw_request(&paths, &Request::Save { tag: None }).await;
+    let dump = std::fs::read_to_string(paths.dump_file()).unwrap();
+    assert!(
+        !dump.contains("abc123") && !dump.contains("hunter2"),
+        "{dump}"
+    );
+    assert!(dump.contains("3000"), "{dump}");
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-#[tokio::test]
-async fn test_resurrect_restores_processes() {
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_mask_dump_off_keeps_secrets_in_private_dump() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::write(paths.settings_file(), "[daemon]\nmask_dump = false\n").unwrap();
 
-    // Phase 1: start daemon, start processes, save, kill daemon
-    {
-        let handle = start_test_daemon(&paths).await;
+    let handle = start_test_daemon(&paths).await;
 
-        let mut configs = HashMap::new();
-        configs.insert("web".to_string(), test_config("sleep 999"));
-        configs.insert("worker".to_string(), test_config("sleep 888"));
+    let mut config = test_config("sh -c 'echo API_KEY=$API_KEY; sleep 999'");
+    config.env = Some(HashMap::from([
+        ("PORT".to_string(), "3000".to_string()),
+        ("API_KEY".to_string(), "abc123".to_string()),
+    ]));
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), config);
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
 
-        send_raw_request(
-            &paths,
+    // the dump keeps the real values so a resurrect restores them, and only
+    // the daemon's user can read it
+    send_raw_request(&paths, &Request::Save { tag: None }).await;
+    let dump = std::fs::read_to_string(paths.dump_file()).unwrap();
+    assert!(dump.contains("abc123"), "{dump}");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(paths.dump_file())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_info_nonexistent_returns_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Info {
+            name: "nonexistent".to_string(),
+        },
+    )
+    .await;
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("not found"),
+                "error should contain 'not found', got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_tree_shows_spawned_children() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert(
+        "wrapper".to_string(),
+        test_config("sh -c 'sleep 61 & sleep 62 & wait'"),
+    );
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let pid = match send_raw_request(
+        &paths,
+        &Request::Info {
+            name: "wrapper".to_string(),
+        },
+    )
+    .await
+    {
+        Response::ProcessDetail { info } => info.pid.expect("wrapper should be running"),
+        other => panic!("expected ProcessDetail, got: {other:?}"),
+    };
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Tree {
+            name: Some("wrapper".to_string()),
+        },
+    )
+    .await;
+    match &resp {
+        Response::Trees { trees } => {
+            assert_eq!(trees.len(), 1);
+            let root = &trees[0].root;
+            assert_eq!(root.pid, pid);
+            assert!(root.memory_bytes.is_some());
+            let mut commands: Vec<&str> =
+                root.children.iter().map(|c| c.command.as_str()).collect();
+            commands.sort();
+            assert_eq!(commands, vec!["sleep 61", "sleep 62"]);
+        }
+        other => panic!("expected Trees, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_history_is_answered_by_the_daemon() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["web".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+
+    let resp = send_raw_request(&paths, &Request::History { lines: 1 }).await;
+    match &resp {
+        Response::History { entries } => {
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].command, "stop web");
+        }
+        other => panic!("expected History, got: {other:?}"),
+    }
+
+    // reading the history isn't itself recorded
+    let resp = send_raw_request(&paths, &Request::History { lines: 10 }).await;
+    match &resp {
+        Response::History { entries } => {
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[1].command, "stop web");
+        }
+        other => panic!("expected History, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+// ---------------------------------------------------------------------------
+// Process dependency tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_dependency_start_order() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    // db has no deps, web depends on db
+    let db_config = test_config("sleep 999");
+    let mut web_config = test_config("sleep 999");
+    web_config.depends_on = Some(vec!["db".to_string()]);
+
+    let mut configs = HashMap::new();
+    configs.insert("db".to_string(), db_config);
+    configs.insert("web".to_string(), web_config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    assert!(
+        matches!(&resp, Response::Success { .. }),
+        "expected Success, got: {resp:?}"
+    );
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Both should be online
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 2);
+            for p in processes {
+                assert_eq!(
+                    p.status,
+                    ProcessStatus::Online,
+                    "process '{}' should be online",
+                    p.name
+                );
+            }
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+async fn start_four_slow_spawns(paths: &Paths) -> Duration {
+    let mut configs = HashMap::new();
+    for name in ["a", "b", "c", "d"] {
+        let mut config = test_config("sleep 999");
+        config.pre_start = Some("sleep 0.5".to_string());
+        configs.insert(name.to_string(), config);
+    }
+    let started = std::time::Instant::now();
+    let resp = send_raw_request(
+        paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "{resp:?}");
+    started.elapsed()
+}
+
+#[tokio::test]
+async fn test_start_spawns_level_concurrently() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    // Four 0.5s pre_start hooks in one level finish together
+    let elapsed = start_four_slow_spawns(&paths).await;
+    assert!(
+        elapsed < Duration::from_millis(1500),
+        "start took {elapsed:?}"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_spawn_concurrency_setting_caps_parallel_spawns() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::write(paths.settings_file(), "[daemon]\nspawn_concurrency = 1\n").unwrap();
+    let handle = start_test_daemon(&paths).await;
+
+    let elapsed = start_four_slow_spawns(&paths).await;
+    assert!(
+        elapsed >= Duration::from_millis(2000),
+        "start took {elapsed:?}"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_socket_mode_and_allowed_uids() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let other_uid = nix::unistd::getuid().as_raw() + 1;
+    std::fs::write(
+        paths.settings_file(),
+        format!("[daemon]\nsocket_mode = 0o600\nallowed_uids = [{other_uid}]\n"),
+    )
+    .unwrap();
+    let handle = start_test_daemon(&paths).await;
+
+    let mode = std::fs::metadata(paths.socket_file())
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+    // It was bound elsewhere and moved into place, leaving nothing behind
+    let parent = paths.socket_file().parent().unwrap().to_path_buf();
+    let leftovers: Vec<_> = std::fs::read_dir(parent)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(".bind-"))
+        .collect();
+    assert!(leftovers.is_empty(), "left behind: {leftovers:?}");
+
+    // The daemon's own user is always allowed, even when not listed
+    let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    assert!(
+        matches!(resp, Response::ProcessList { .. }),
+        "expected ProcessList, got {resp:?}"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_unparseable_request_explains_version_mismatch() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let send_line = |line: String| {
+        let socket = paths.socket_file();
+        tokio::task::spawn_blocking(move || {
+            let mut stream = UnixStream::connect(socket).unwrap();
+            stream.write_all(line.as_bytes()).unwrap();
+            stream.shutdown(std::net::Shutdown::Write).unwrap();
+            let mut response = String::new();
+            BufReader::new(stream).read_line(&mut response).unwrap();
+            protocol::decode_response(&response).unwrap()
+        })
+    };
+
+    let newer = format!(
+        "{{\"type\":\"teleport\",\"protocol\":{}}}\n",
+        protocol::PROTOCOL_VERSION + 1
+    );
+    match send_line(newer).await.unwrap() {
+        Response::Error { message } => {
+            assert!(
+                message.starts_with("CLI is newer than the daemon"),
+                "{message}"
+            );
+        }
+        other => panic!("expected Error, got {other:?}"),
+    }
+
+    let current = format!(
+        "{{\"type\":\"teleport\",\"protocol\":{}}}\n",
+        protocol::PROTOCOL_VERSION
+    );
+    match send_line(current).await.unwrap() {
+        Response::Error { message } => {
+            assert!(message.starts_with("invalid request:"), "{message}");
+        }
+        other => panic!("expected Error, got {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_session_multiplexes_tagged_requests() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    let socket = paths.socket_file();
+    let received = tokio::task::spawn_blocking(move || {
+        let mut stream = UnixStream::connect(socket).unwrap();
+        stream
+            .write_all(&protocol::encode_request(&Request::Session).unwrap())
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(
+            matches!(
+                protocol::decode_response(&line).unwrap(),
+                Response::Success { .. }
+            ),
+            "{line}"
+        );
+
+        // A slow request first: the quick one behind it must not wait for it
+        let slow = Request::Wait {
+            names: vec!["web".to_string()],
+            status: ProcessStatus::Stopped,
+            timeout_ms: 500,
+            namespace: None,
+        };
+        stream
+            .write_all(&protocol::encode_tagged_request(1, &slow).unwrap())
+            .unwrap();
+        stream
+            .write_all(
+                &protocol::encode_tagged_request(2, &Request::List { filter: None }).unwrap(),
+            )
+            .unwrap();
+        stream
+            .write_all(b"{\"id\":3,\"type\":\"teleport\"}\n")
+            .unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        reader
+            .lines()
+            .map(|line| protocol::decode_tagged_response(&line.unwrap()).unwrap())
+            .map(|tagged| (tagged.id, tagged.message))
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap();
+
+    let position = |id, done: bool| {
+        received
+            .iter()
+            .position(|(i, r)| *i == id && (*r == Response::Done) == done)
+            .unwrap_or_else(|| panic!("no response for {id} (done: {done}) in {received:?}"))
+    };
+    assert!(matches!(
+        received[position(2, false)].1,
+        Response::ProcessList { .. }
+    ));
+    assert!(matches!(
+        received[position(1, false)].1,
+        Response::Error { .. }
+    ));
+    assert!(matches!(
+        received[position(3, false)].1,
+        Response::Error { .. }
+    ));
+    assert!(position(2, true) < position(1, false), "{received:?}");
+    assert!(position(1, false) < position(1, true));
+    assert_eq!(received.len(), 6, "{received:?}");
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_events_stream_status_changes() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let socket = paths.socket_file();
+    let events = tokio::task::spawn_blocking(move || {
+        let mut stream = UnixStream::connect(socket).unwrap();
+        stream
+            .write_all(&protocol::encode_request(&Request::Events { names: None }).unwrap())
+            .unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut events = Vec::new();
+        for line in BufReader::new(stream).lines() {
+            let Response::Event(event) = protocol::decode_response(&line.unwrap()).unwrap() else {
+                continue;
+            };
+            let stopped = event.status == ProcessStatus::Stopped;
+            events.push(event);
+            if stopped {
+                break;
+            }
+        }
+        events
+    });
+    // Let the subscription land before anything happens
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["web".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+
+    let events = events.await.unwrap();
+    let kinds: Vec<_> = events.iter().map(|e| (e.name.as_str(), e.kind)).collect();
+    assert_eq!(
+        kinds,
+        vec![("web", EventKind::Added), ("web", EventKind::StatusChanged)],
+        "{events:?}"
+    );
+    assert_eq!(events[0].status, ProcessStatus::Online);
+    assert!(events[1].timestamp.ends_with('Z'));
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_daemon_client_controls_daemon() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+    let client = DaemonClient::new(paths.clone());
+
+    let mut configs = HashMap::new();
+    configs.insert(
+        "web".to_string(),
+        test_config("sh -c 'echo hello_from_client; sleep 999'"),
+    );
+    client.start(configs, None).await.unwrap();
+
+    let processes = client.list().await.unwrap();
+    assert_eq!(processes.len(), 1);
+    assert_eq!(processes[0].name, "web");
+    assert_eq!(client.info("web").await.unwrap().name, "web");
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let mut logs = client.tail_logs(Some("web"), 10, false).await.unwrap();
+    let mut lines = Vec::new();
+    while let Some(response) = logs.next().await.unwrap() {
+        if let Response::LogLine { line, .. } = response {
+            lines.push(line);
+        }
+    }
+    assert!(
+        lines.iter().any(|l| l.contains("hello_from_client")),
+        "{lines:?}"
+    );
+
+    match client.info("missing").await {
+        Err(ClientError::Daemon(message)) => assert!(message.contains("missing"), "{message}"),
+        other => panic!("expected a daemon error, got {other:?}"),
+    }
+
+    client.stop(None).await.unwrap();
+    assert_eq!(
+        client.list().await.unwrap()[0].status,
+        ProcessStatus::Stopped
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_restart_stagger_spaces_out_respawns() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    for name in ["a", "b", "c"] {
+        configs.insert(name.to_string(), test_config("sleep 999"));
+    }
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    let started = std::time::Instant::now();
+    let resp = send_raw_request(
+        &paths,
+        &Request::Restart {
+            names: None,
+            except: None,
+            namespace: None,
+            stagger_ms: Some(400),
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "{resp:?}");
+    // Two gaps between three respawns
+    assert!(
+        started.elapsed() >= Duration::from_millis(800),
+        "restart took {:?}",
+        started.elapsed()
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_stop_without_dependents() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut cache = test_config("sleep 999");
+    cache.stop_dependents = Some(false);
+    let mut web = test_config("sleep 999");
+    web.depends_on = Some(vec!["db".to_string(), "cache".to_string()]);
+
+    let mut configs = HashMap::new();
+    configs.insert("db".to_string(), test_config("sleep 999"));
+    configs.insert("cache".to_string(), cache);
+    configs.insert("web".to_string(), web);
+
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    // --no-dependents: only db stops
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["db".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: true,
+        },
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Success {
+            message: Some("stopped: db".to_string())
+        }
+    );
+
+    // stop_dependents = false on cache: only cache stops
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["cache".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert_eq!(
+        resp,
+        Response::Success {
+            message: Some("stopped: cache".to_string())
+        }
+    );
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Online)], 4).await);
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_dependency_stop_order() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let db_config = test_config("sleep 999");
+    let mut web_config = test_config("sleep 999");
+    web_config.depends_on = Some(vec!["db".to_string()]);
+
+    let mut configs = HashMap::new();
+    configs.insert("db".to_string(), db_config);
+    configs.insert("web".to_string(), web_config);
+
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Stop db — should cascade to web (web stopped first as dependent)
+    let stop_resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["db".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+
+    match &stop_resp {
+        Response::Success { message } => {
+            let msg = message.as_deref().unwrap_or("");
+            assert!(
+                msg.contains("web") && msg.contains("db"),
+                "stop should include both web and db, got: {msg}"
+            );
+        }
+        other => panic!("expected Success, got: {other:?}"),
+    }
+
+    // Both should be stopped
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            for p in processes {
+                assert_eq!(
+                    p.status,
+                    ProcessStatus::Stopped,
+                    "process '{}' should be stopped",
+                    p.name
+                );
+            }
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_circular_dependency_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut a_config = test_config("sleep 999");
+    a_config.depends_on = Some(vec!["b".to_string()]);
+
+    let mut b_config = test_config("sleep 999");
+    b_config.depends_on = Some(vec!["a".to_string()]);
+
+    let mut configs = HashMap::new();
+    configs.insert("a".to_string(), a_config);
+    configs.insert("b".to_string(), b_config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("circular"),
+                "error should mention circular, got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_missing_dependency_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut web_config = test_config("sleep 999");
+    web_config.depends_on = Some(vec!["nonexistent".to_string()]);
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), web_config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("nonexistent"),
+                "error should mention the missing dep, got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+// ---------------------------------------------------------------------------
+// Process groups (step 28)
+// ---------------------------------------------------------------------------
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_group_start_by_group_name() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut api_config = test_config("sleep 999");
+    api_config.group = Some("backend".to_string());
+
+    let mut worker_config = test_config("sleep 999");
+    worker_config.group = Some("backend".to_string());
+
+    let frontend_config = test_config("sleep 999");
+
+    let mut configs = HashMap::new();
+    configs.insert("api".to_string(), api_config);
+    configs.insert("worker".to_string(), worker_config);
+    configs.insert("frontend".to_string(), frontend_config);
+
+    // Start only the "backend" group
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: Some(vec!["backend".to_string()]),
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    match &resp {
+        Response::Success { message } => {
+            let msg = message.as_deref().unwrap_or("");
+            assert!(
+                msg.contains("api") && msg.contains("worker"),
+                "should start both backend processes, got: {msg}"
+            );
+            assert!(
+                !msg.contains("frontend"),
+                "should NOT start frontend, got: {msg}"
+            );
+        }
+        other => panic!("expected Success, got: {other:?}"),
+    }
+
+    // List should only have the two backend processes
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 2, "should have 2 processes started");
+            let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+            assert!(names.contains(&"api"), "api should be running");
+            assert!(names.contains(&"worker"), "worker should be running");
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_group_stop_by_group_name() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut api_config = test_config("sleep 999");
+    api_config.group = Some("backend".to_string());
+
+    let mut worker_config = test_config("sleep 999");
+    worker_config.group = Some("backend".to_string());
+
+    let frontend_config = test_config("sleep 999");
+
+    let mut configs = HashMap::new();
+    configs.insert("api".to_string(), api_config);
+    configs.insert("worker".to_string(), worker_config);
+    configs.insert("frontend".to_string(), frontend_config);
+
+    // Start all
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Stop only the "backend" group
+    let stop_resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["backend".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+
+    match &stop_resp {
+        Response::Success { message } => {
+            let msg = message.as_deref().unwrap_or("");
+            assert!(
+                msg.contains("api") && msg.contains("worker"),
+                "should stop both backend processes, got: {msg}"
+            );
+        }
+        other => panic!("expected Success, got: {other:?}"),
+    }
+
+    // Verify: api and worker stopped, frontend still online
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            let api = processes.iter().find(|p| p.name == "api").unwrap();
+            assert_eq!(api.status, ProcessStatus::Stopped, "api should be stopped");
+
+            let worker = processes.iter().find(|p| p.name == "worker").unwrap();
+            assert_eq!(
+                worker.status,
+                ProcessStatus::Stopped,
+                "worker should be stopped"
+            );
+
+            let frontend = processes.iter().find(|p| p.name == "frontend").unwrap();
+            assert_eq!(
+                frontend.status,
+                ProcessStatus::Online,
+                "frontend should still be online"
+            );
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_process_name_takes_priority_over_group() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    // Process named "backend" and another process with group = "backend"
+    let backend_process = test_config("sleep 999");
+
+    let mut grouped = test_config("sleep 999");
+    grouped.group = Some("backend".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("backend".to_string(), backend_process);
+    configs.insert("api".to_string(), grouped);
+
+    // Start "backend" — should start only the process named "backend",
+    // NOT the "api" process that has group = "backend"
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: Some(vec!["backend".to_string()]),
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    match &resp {
+        Response::Success { message } => {
+            let msg = message.as_deref().unwrap_or("");
+            assert!(msg.contains("backend"), "should start backend, got: {msg}");
+            assert!(!msg.contains("api"), "should NOT start api, got: {msg}");
+        }
+        other => panic!("expected Success, got: {other:?}"),
+    }
+
+    // Only the "backend" process should be running
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1, "should have 1 process started");
+            assert_eq!(processes[0].name, "backend");
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_group_not_found_returns_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: Some(vec!["nonexistent-group".to_string()]),
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("not found"),
+                "error should contain 'not found', got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+// ---------------------------------------------------------------------------
+// Signal command (step 29)
+// ---------------------------------------------------------------------------
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_signal_sends_to_process() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let marker = dir.path().join("sigusr1_received");
+    let cmd = format!(
+        "sh -c 'trap \"touch {}\" USR1; while true; do sleep 0.1; done'",
+        marker.display()
+    );
+    let mut config = test_config(&cmd);
+    config.restart = Some(RestartPolicy::Never);
+
+    let mut configs = HashMap::new();
+    configs.insert("trapper".to_string(), config);
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Signal {
+            name: "trapper".to_string(),
+            signal: "SIGUSR1".to_string(),
+        },
+    )
+    .await;
+    match &resp {
+        Response::Success { message } => {
+            let msg = message.as_deref().unwrap_or("");
+            assert!(msg.contains("sent"), "should contain 'sent', got: {msg}");
+        }
+        other => panic!("expected Success, got: {other:?}"),
+    }
+
+    // Wait for trap handler to execute
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(
+        marker.exists(),
+        "marker file should exist after SIGUSR1 trap fired"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_signal_nonexistent_process_returns_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Signal {
+            name: "nonexistent".to_string(),
+            signal: "SIGHUP".to_string(),
+        },
+    )
+    .await;
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("not found"),
+                "error should contain 'not found', got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_signal_invalid_signal_returns_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Signal {
+            name: "web".to_string(),
+            signal: "SIGFAKE".to_string(),
+        },
+    )
+    .await;
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("invalid signal"),
+                "error should contain 'invalid signal', got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_signal_stopped_process_returns_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Stop the process first
+    send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["web".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Signal {
+            name: "web".to_string(),
+            signal: "SIGUSR1".to_string(),
+        },
+    )
+    .await;
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("not running"),
+                "error should contain 'not running', got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+// ---------------------------------------------------------------------------
+// Lifecycle hooks (PRD item 30)
+// ---------------------------------------------------------------------------
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_pre_start_runs_before_process() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut config = test_config("sleep 999");
+    config.pre_start = Some("echo pre_start_marker".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("hooky".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let stdout_log = paths.stdout_log("hooky");
+    let content = std::fs::read_to_string(&stdout_log).unwrap();
+    assert!(
+        content.contains("pre_start_marker"),
+        "stdout log should contain pre_start_marker, got: {content}"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_pre_start_failure_prevents_start() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut config = test_config("sleep 999");
+    config.pre_start = Some("exit 1".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("failhook".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    match &resp {
+        Response::Error { message } => {
+            assert!(
+                message.contains("hook failed"),
+                "error should mention hook failed, got: {message}"
+            );
+        }
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    // Verify process is NOT in the list
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert!(
+                !processes.iter().any(|p| p.name == "failhook"),
+                "failhook should not be in process list"
+            );
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_post_stop_runs_after_stop() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let marker_path = dir.path().join("post_stop_marker");
+    let mut config = test_config("sleep 999");
+    config.post_stop = Some(format!("touch {}", marker_path.display()));
+
+    let mut configs = HashMap::new();
+    configs.insert("stophook".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Stop the process
+    let stop_resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["stophook".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(stop_resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert!(
+        marker_path.exists(),
+        "post_stop marker file should exist at {}",
+        marker_path.display()
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_restart_runs_post_stop_then_pre_start() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let order_file = dir.path().join("hook_order.txt");
+    let mut config = test_config("sleep 999");
+    config.post_stop = Some(format!("echo post_stop >> {}", order_file.display()));
+    config.pre_start = Some(format!("echo pre_start >> {}", order_file.display()));
+
+    let mut configs = HashMap::new();
+    configs.insert("orderhook".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // Clear the order file (pre_start from initial start wrote to it)
+    std::fs::write(&order_file, "").unwrap();
+
+    // Restart the process
+    let restart_resp = send_raw_request(
+        &paths,
+        &Request::Restart {
+            names: Some(vec!["orderhook".to_string()]),
+            except: None,
+            namespace: None,
+            stagger_ms: None,
+        },
+    )
+    .await;
+    assert!(matches!(restart_resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let content = std::fs::read_to_string(&order_file).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(
+        lines,
+        vec!["post_stop", "pre_start"],
+        "hooks should run in order: post_stop then pre_start, got: {:?}",
+        lines
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_max_memory_restart_triggers() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    // Node script that allocates ~30MB of filled buffers (RSS ~65MB)
+    let mut config = test_config(
+        r#"node -e "const bufs = []; for (let i = 0; i < 30; i++) { const b = Buffer.alloc(1024*1024); b.fill(0x42); bufs.push(b); } setInterval(() => {}, 1000);""#,
+    );
+    config.max_memory = Some("50M".to_string());
+    // Don't let restart policy interfere; memory restarts are maintenance ops
+    config.restart = Some(RestartPolicy::Never);
+
+    let mut configs = HashMap::new();
+    configs.insert("memhog".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    // Capture original PID
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let orig_pid = {
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        match list_resp {
+            Response::ProcessList { processes, .. } => {
+                let p = processes.iter().find(|p| p.name == "memhog").unwrap();
+                p.pid.unwrap()
+            }
+            _ => panic!("expected process list"),
+        }
+    };
+
+    // Wait for memory monitor to detect + restart (up to 15s)
+    let mut restarted = false;
+    for _ in 0..30 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = list_resp {
+            let p = processes.iter().find(|p| p.name == "memhog").unwrap();
+            if p.restarts >= 1 && p.pid.is_some() && p.pid.unwrap() != orig_pid {
+                restarted = true;
+                assert!(p.status == ProcessStatus::Online || p.status == ProcessStatus::Starting);
+                break;
+            }
+        }
+    }
+    assert!(
+        restarted,
+        "process should have been restarted due to memory limit"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_max_memory_stop_action() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut config = test_config("sleep 999");
+    config.max_memory = Some("1K".to_string());
+    config.memory_check_interval = Some("200ms".to_string());
+    config.memory_action = Some("stop".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("tiny".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    let mut stopped = false;
+    for _ in 0..30 {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = list_resp {
+            let p = processes.iter().find(|p| p.name == "tiny").unwrap();
+            if p.status == ProcessStatus::Stopped {
+                stopped = true;
+                assert_eq!(p.pid, None);
+                assert_eq!(p.restarts, 0);
+                break;
+            }
+        }
+    }
+    assert!(stopped, "process should have been stopped over max_memory");
+
+    // Stopped for good, not restarted
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    let Response::ProcessList { processes, .. } = list_resp else {
+        panic!("expected process list");
+    };
+    assert_eq!(processes[0].status, ProcessStatus::Stopped);
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_max_memory_no_restart_when_under_limit() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut config = test_config("sleep 999");
+    config.max_memory = Some("500M".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("lowmem".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    // Wait ~12s (two check intervals)
+    tokio::time::sleep(Duration::from_secs(12)).await;
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match list_resp {
+        Response::ProcessList { processes, .. } => {
+            let p = processes.iter().find(|p| p.name == "lowmem").unwrap();
+            assert_eq!(p.restarts, 0, "process should not have been restarted");
+            assert_eq!(p.status, ProcessStatus::Online);
+        }
+        _ => panic!("expected process list"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_restarts_on_file_change() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    // Create a watched directory with a file
+    let watch_dir = dir.path().join("watched_src");
+    std::fs::create_dir_all(&watch_dir).unwrap();
+    std::fs::write(watch_dir.join("app.txt"), "initial").unwrap();
+
+    let mut config = test_config("sleep 999");
+    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
+    config.restart = Some(RestartPolicy::Never);
+
+    let mut configs = HashMap::new();
+    configs.insert("watchme".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    // Capture original PID
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let orig_pid = {
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        match list_resp {
+            Response::ProcessList { processes, .. } => {
+                let p = processes.iter().find(|p| p.name == "watchme").unwrap();
+                p.pid.unwrap()
+            }
+            _ => panic!("expected process list"),
+        }
+    };
+
+    // Modify a file in the watched directory
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    std::fs::write(watch_dir.join("app.txt"), "changed").unwrap();
+
+    // Wait for watcher to detect + debounce + restart (up to 10s)
+    let mut restarted = false;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = list_resp {
+            let p = processes.iter().find(|p| p.name == "watchme").unwrap();
+            if p.restarts >= 1 && p.pid.is_some() && p.pid.unwrap() != orig_pid {
+                restarted = true;
+                assert!(p.status == ProcessStatus::Online || p.status == ProcessStatus::Starting);
+                break;
+            }
+        }
+    }
+    assert!(
+        restarted,
+        "process should have been restarted after file change"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_debounce_rapid_changes_trigger_one_restart() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let watch_dir = dir.path().join("debounce_src");
+    std::fs::create_dir_all(&watch_dir).unwrap();
+    std::fs::write(watch_dir.join("file.txt"), "v0").unwrap();
+
+    let mut config = test_config("sleep 999");
+    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
+    config.restart = Some(RestartPolicy::Never);
+
+    let mut configs = HashMap::new();
+    configs.insert("debounce".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Rapid-fire multiple changes within debounce window
+    for i in 1..=5 {
+        std::fs::write(watch_dir.join("file.txt"), format!("v{i}")).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    // Wait for restart
+    let mut final_restarts = 0;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = list_resp {
+            let p = processes.iter().find(|p| p.name == "debounce").unwrap();
+            final_restarts = p.restarts;
+            if final_restarts >= 1 {
+                break;
+            }
+        }
+    }
+
+    // Should have restarted exactly once (debounced), not 5 times
+    assert_eq!(
+        final_restarts, 1,
+        "rapid file changes should debounce into a single restart, got {} restarts",
+        final_restarts
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_delay_absorbs_changes_after_debounce() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let watch_dir = dir.path().join("delay_src");
+    std::fs::create_dir_all(&watch_dir).unwrap();
+    std::fs::write(watch_dir.join("file.txt"), "v0").unwrap();
+
+    let mut config = test_config("sleep 999");
+    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
+    config.restart = Some(RestartPolicy::Never);
+    config.watch_debounce = Some("200ms".to_string());
+    config.watch_delay = Some("1500ms".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("delayed".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Changes spread well past the debounce, but within the delay
+    for i in 1..=3 {
+        std::fs::write(watch_dir.join("file.txt"), format!("v{i}")).unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+    }
+
+    let restarts = || async {
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        let Response::ProcessList { processes, .. } = list_resp else {
+            panic!("expected ProcessList");
+        };
+        processes
+            .iter()
+            .find(|p| p.name == "delayed")
+            .unwrap()
+            .restarts
+    };
+    // Still inside the delay: nothing restarted yet
+    assert_eq!(restarts().await, 0);
+
+    let mut final_restarts = 0;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        final_restarts = restarts().await;
+        if final_restarts >= 1 {
+            break;
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    assert_eq!(
+        restarts().await,
+        1,
+        "changes during watch_delay should fold into one restart, got {final_restarts} first"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_poll_restarts_on_file_change() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let watch_dir = dir.path().join("poll_src");
+    std::fs::create_dir_all(&watch_dir).unwrap();
+    std::fs::write(watch_dir.join("app.txt"), "v0").unwrap();
+
+    let mut config = test_config("sleep 999");
+    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
+    config.watch_poll = Some("200ms".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("polled".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    // Nothing changed yet: the first scan must not count as a change
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    let restarts = || async {
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        let Response::ProcessList { processes, .. } = list_resp else {
+            panic!("expected ProcessList");
+        };
+        processes
+            .iter()
+            .find(|p| p.name == "polled")
+            .unwrap()
+            .restarts
+    };
+    assert_eq!(restarts().await, 0);
+
+    std::fs::write(watch_dir.join("app.txt"), "changed").unwrap();
+
+    let mut restarted = false;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        if restarts().await >= 1 {
+            restarted = true;
+            break;
+        }
+    }
+    assert!(restarted, "a polled change should restart the process");
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_env_files_restarts_with_new_env() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let app_dir = dir.path().join("env_app");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    std::fs::write(app_dir.join(".env"), "GREETING=hello\n").unwrap();
+
+    let mut config = test_config("sh -c 'echo \"greeting=$GREETING\"; sleep 999'");
+    config.cwd = Some(app_dir.to_string_lossy().to_string());
+    config.env_file = Some(EnvFile::Single(".env".to_string()));
+    config.watch_env_files = Some(true);
+
+    let mut configs = HashMap::new();
+    configs.insert("envy".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    std::fs::write(app_dir.join(".env"), "GREETING=goodbye\n").unwrap();
+
+    let log = paths.stdout_log("envy");
+    let mut reloaded = false;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        if std::fs::read_to_string(&log).is_ok_and(|out| out.contains("greeting=goodbye")) {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(
+        reloaded,
+        "an env file change should restart with the new env"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_command_runs_instead_of_restart() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let watch_dir = dir.path().join("cmd_src");
+    std::fs::create_dir_all(&watch_dir).unwrap();
+    let marker = dir.path().join("built");
+
+    let mut config = test_config("sleep 999");
+    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
+    config.watch_command = Some(format!("echo built >> {}", marker.display()));
+    config.watch_restart = Some(false);
+
+    let mut configs = HashMap::new();
+    configs.insert("builder".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    std::fs::write(watch_dir.join("main.c"), "int main;").unwrap();
+
+    let mut ran = false;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        if marker.exists() {
+            ran = true;
+            break;
+        }
+    }
+    assert!(ran, "a change should run watch_command");
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    let Response::ProcessList { processes, .. } = list_resp else {
+        panic!("expected ProcessList");
+    };
+    let builder = processes.iter().find(|p| p.name == "builder").unwrap();
+    assert_eq!(builder.restarts, 0);
+    assert_eq!(builder.status, ProcessStatus::Online);
+
+    // The watcher keeps going after the command
+    std::fs::remove_file(&marker).unwrap();
+    std::fs::write(watch_dir.join("main.c"), "int main();").unwrap();
+    let mut ran_again = false;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        if marker.exists() {
+            ran_again = true;
+            break;
+        }
+    }
+    assert!(ran_again, "later changes should run watch_command again");
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_true_watches_cwd() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    // Create a cwd directory
+    let cwd_dir = dir.path().join("app_cwd");
+    std::fs::create_dir_all(&cwd_dir).unwrap();
+    std::fs::write(cwd_dir.join("data.txt"), "initial").unwrap();
+
+    let mut config = test_config("sleep 999");
+    config.watch = Some(Watch::Enabled(true));
+    config.cwd = Some(cwd_dir.to_string_lossy().to_string());
+    config.restart = Some(RestartPolicy::Never);
+
+    let mut configs = HashMap::new();
+    configs.insert("cwdwatch".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    // Capture original PID
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let orig_pid = {
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        match list_resp {
+            Response::ProcessList { processes, .. } => {
+                let p = processes.iter().find(|p| p.name == "cwdwatch").unwrap();
+                p.pid.unwrap()
+            }
+            _ => panic!("expected process list"),
+        }
+    };
+
+    // Modify a file in the cwd directory
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    std::fs::write(cwd_dir.join("data.txt"), "modified").unwrap();
+
+    // Wait for watcher to restart
+    let mut restarted = false;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = list_resp {
+            let p = processes.iter().find(|p| p.name == "cwdwatch").unwrap();
+            if p.restarts >= 1 && p.pid.is_some() && p.pid.unwrap() != orig_pid {
+                restarted = true;
+                break;
+            }
+        }
+    }
+    assert!(
+        restarted,
+        "process should have been restarted when file in cwd changed"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_ignore_excludes_directories() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    // Create watched directory with an ignored subdirectory and a non-ignored one
+    let watch_dir = dir.path().join("ignore_src");
+    let ignored_dir = watch_dir.join("node_modules");
+    let src_dir = watch_dir.join("src");
+    std::fs::create_dir_all(&ignored_dir).unwrap();
+    std::fs::create_dir_all(&src_dir).unwrap();
+    std::fs::write(ignored_dir.join("dep.js"), "initial").unwrap();
+    std::fs::write(src_dir.join("app.js"), "initial").unwrap();
+
+    let mut config = test_config("sleep 999");
+    config.watch = Some(Watch::Path(watch_dir.to_string_lossy().to_string()));
+    config.watch_ignore = Some(vec!["node_modules".to_string()]);
+    config.restart = Some(RestartPolicy::Never);
+
+    let mut configs = HashMap::new();
+    configs.insert("ignoreme".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Modify a file in the IGNORED directory — should NOT trigger restart
+    std::fs::write(ignored_dir.join("dep.js"), "changed").unwrap();
+
+    // Wait long enough for watcher to process (debounce + margin)
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match list_resp {
+        Response::ProcessList { processes, .. } => {
+            let p = processes.iter().find(|p| p.name == "ignoreme").unwrap();
+            assert_eq!(
+                p.restarts, 0,
+                "changes in ignored directory should NOT trigger restart"
+            );
+            assert_eq!(p.status, ProcessStatus::Online);
+        }
+        _ => panic!("expected process list"),
+    }
+
+    // Now modify a file in the NON-ignored directory — should trigger restart
+    std::fs::write(src_dir.join("app.js"), "changed").unwrap();
+
+    let mut restarted = false;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = list_resp {
+            let p = processes.iter().find(|p| p.name == "ignoreme").unwrap();
+            if p.restarts >= 1 {
+                restarted = true;
+                break;
+            }
+        }
+    }
+    assert!(
+        restarted,
+        "changes in non-ignored directory should trigger restart"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_watch_globs_only_restart_on_matching_files() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let app_dir = dir.path().join("glob_app");
+    let src_dir = app_dir.join("src").join("net");
+    let assets_dir = app_dir.join("assets");
+    std::fs::create_dir_all(&src_dir).unwrap();
+    std::fs::create_dir_all(&assets_dir).unwrap();
+
+    let mut config = test_config("sleep 999");
+    config.cwd = Some(app_dir.to_string_lossy().to_string());
+    config.watch = Some(Watch::Globs(vec![
+        "src/**/*.rs".to_string(),
+        "*.toml".to_string(),
+    ]));
+    config.restart = Some(RestartPolicy::Never);
+
+    let mut configs = HashMap::new();
+    configs.insert("globbed".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }));
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Editor swap files and unrelated assets don't match
+    std::fs::write(src_dir.join(".tcp.rs.swp"), "swap").unwrap();
+    std::fs::write(assets_dir.join("logo.svg"), "<svg/>").unwrap();
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match list_resp {
+        Response::ProcessList { processes, .. } => {
+            let p = processes.iter().find(|p| p.name == "globbed").unwrap();
+            assert_eq!(p.restarts, 0, "unmatched files should NOT trigger restart");
+        }
+        _ => panic!("expected process list"),
+    }
+
+    std::fs::write(src_dir.join("tcp.rs"), "fn main() {}").unwrap();
+
+    let mut restarted = false;
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = list_resp {
+            let p = processes.iter().find(|p| p.name == "globbed").unwrap();
+            if p.restarts >= 1 {
+                restarted = true;
+                break;
+            }
+        }
+    }
+    assert!(restarted, "a file matching a glob should trigger restart");
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+// ---------------------------------------------------------------------------
+// Cron restart tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn test_cron_restart_triggers() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    // Use a 6-field cron expression (with seconds): fire every 3 seconds
+    let mut config = test_config("sleep 999");
+    config.cron_restart = Some("*/3 * * * * *".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("cronproc".to_string(), config);
+
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    // Get initial PID
+    let initial_pid: Option<u32>;
+    loop {
+        let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = resp
+            && let Some(pid) = processes
+                .iter()
+                .find(|p| p.name == "cronproc")
+                .and_then(|p| p.pid)
+        {
+            initial_pid = Some(pid);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Wait for cron to trigger a restart (up to 10 seconds)
+    let mut restarted = false;
+    for _ in 0..40 {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        if let Response::ProcessList { processes, .. } = resp
+            && let Some(p) = processes.iter().find(|p| p.name == "cronproc")
+            && p.restarts >= 1
+            && p.pid != initial_pid
+            && p.status == ProcessStatus::Online
+        {
+            restarted = true;
+            break;
+        }
+    }
+    assert!(restarted, "cron_restart should trigger a restart");
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_cron_restart_no_trigger_with_long_interval() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    // Use a cron expression that won't fire during the test (daily at 3am)
+    let mut config = test_config("sleep 999");
+    config.cron_restart = Some("0 3 * * *".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("longcron".to_string(), config);
+
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    // Wait a few seconds and verify no restart happened
+    tokio::time::sleep(Duration::from_secs(5)).await;
+
+    let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match resp {
+        Response::ProcessList { processes, .. } => {
+            let p = processes.iter().find(|p| p.name == "longcron").unwrap();
+            assert_eq!(p.restarts, 0, "no cron restart should have triggered");
+            assert_eq!(p.status, ProcessStatus::Online);
+        }
+        _ => panic!("expected process list"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+async fn wait_for_statuses(
+    paths: &Paths,
+    expected: &[(&str, ProcessStatus)],
+    attempts: usize,
+) -> bool {
+    for _ in 0..attempts {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        if let Response::ProcessList { processes, .. } =
+            send_raw_request(paths, &Request::List { filter: None }).await
+            && expected.iter().all(|(name, status)| {
+                processes
+                    .iter()
+                    .any(|p| p.name == *name && p.status == *status)
+            })
+        {
+            return true;
+        }
+    }
+    false
+}
+
+#[tokio::test]
+async fn test_cron_stop_stops_dependents() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut db = test_config("sleep 999");
+    db.cron_stop = Some("*/3 * * * * *".to_string());
+    let mut web = test_config("sleep 999");
+    web.depends_on = Some(vec!["db".to_string()]);
+
+    let mut configs = HashMap::new();
+    configs.insert("db".to_string(), db);
+    configs.insert("web".to_string(), web);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("db", ProcessStatus::Stopped),
+                ("web", ProcessStatus::Stopped)
+            ],
+            40
+        )
+        .await,
+        "cron_stop should stop the process and its dependents"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_cron_start_starts_stopped_process_with_deps() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let db = test_config("sleep 999");
+    let mut web = test_config("sleep 999");
+    web.depends_on = Some(vec!["db".to_string()]);
+    web.cron_start = Some("*/3 * * * * *".to_string());
+
+    let mut configs = HashMap::new();
+    configs.insert("db".to_string(), db);
+    configs.insert("web".to_string(), web);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    // Stopping db cascades to web
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["db".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("db", ProcessStatus::Online),
+                ("web", ProcessStatus::Online)
+            ],
+            40
+        )
+        .await,
+        "cron_start should bring back the process and its dependency"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+// ---------------------------------------------------------------------------
+// State persistence (save / resurrect) tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn test_save_writes_dump_file() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    // Start two processes
+    let mut configs = HashMap::new();
+    configs.insert("alpha".to_string(), test_config("sleep 999"));
+    configs.insert("beta".to_string(), test_config("sleep 888"));
+
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Save
+    let resp = send_raw_request(&paths, &Request::Save { tag: None }).await;
+    match resp {
+        Response::Success { message } => {
+            assert!(message.unwrap().contains("2 process(es)"));
+        }
+        _ => panic!("expected success, got {:?}", resp),
+    }
+
+    // Verify dump file exists and is valid JSON
+    let dump_path = paths.dump_file();
+    assert!(dump_path.exists(), "dump.json should exist");
+    let data = std::fs::read_to_string(&dump_path).unwrap();
+    let dump: serde_json::Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(dump["version"], pm3::dump::DUMP_VERSION);
+    assert_eq!(dump["processes"].as_array().unwrap().len(), 2);
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_autosave_rewrites_dump_on_changes() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::write(paths.settings_file(), "[daemon]\nautosave = true\n").unwrap();
+    let handle = start_test_daemon(&paths).await;
+
+    let saved_names = || -> Option<Vec<String>> {
+        let data = std::fs::read_to_string(paths.dump_file()).ok()?;
+        let dump: serde_json::Value = serde_json::from_str(&data).ok()?;
+        let mut names: Vec<String> = dump["processes"]
+            .as_array()?
+            .iter()
+            .map(|e| e["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        Some(names)
+    };
+    let wait_for = |expected: Vec<&'static str>| async move {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while saved_names() != Some(expected.iter().map(|n| n.to_string()).collect()) {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("dump never held {expected:?}; has {:?}", saved_names()));
+    };
+
+    let mut configs = HashMap::new();
+    configs.insert("alpha".to_string(), test_config("sleep 999"));
+    configs.insert("beta".to_string(), test_config("sleep 888"));
+    handle.client().start(configs, None).await.unwrap();
+    wait_for(vec!["alpha", "beta"]).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("gamma".to_string(), test_config("sleep 777"));
+    handle.client().start(configs, None).await.unwrap();
+    wait_for(vec!["alpha", "beta", "gamma"]).await;
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_idle_timeout_exits_once_nothing_is_running() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::write(paths.settings_file(), "[daemon]\nidle_timeout = \"1s\"\n").unwrap();
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("sleeper".to_string(), test_config("sleep 999"));
+    handle.client().start(configs, None).await.unwrap();
+    wait_for_statuses(&paths, &[("sleeper", ProcessStatus::Online)], 20).await;
+
+    // A running process keeps the daemon up past the timeout
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let stop_resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["sleeper".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&stop_resp, Response::Success { .. }),
+        "expected Success, got: {stop_resp:?}"
+    );
+
+    tokio::time::timeout(Duration::from_secs(10), handle)
+        .await
+        .expect("idle daemon should exit")
+        .unwrap();
+    assert!(!paths.socket_file().exists());
+}
+
+#[tokio::test]
+async fn test_resurrect_from_snapshot_replaces_stopped_processes() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+    let client = handle.client();
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    configs.insert("worker".to_string(), test_config("sleep 888"));
+    client.start(configs, None).await.unwrap();
+
+    let resp = client
+        .request(&Request::Save {
+            tag: Some("good".to_string()),
+        })
+        .await
+        .unwrap();
+    match resp {
+        Response::Success { message } => {
+            assert!(
+                message
+                    .unwrap()
+                    .contains("2 process(es) to dump file and snapshot good")
+            );
+        }
+        _ => panic!("expected success, got {resp:?}"),
+    }
+
+    let snapshots = match client.request(&Request::Snapshots).await.unwrap() {
+        Response::Snapshots { snapshots } => snapshots,
+        other => panic!("expected Snapshots, got {other:?}"),
+    };
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].tag.as_deref(), Some("good"));
+    assert_eq!(snapshots[0].processes, 2);
+    let file = std::fs::read_dir(paths.snapshots_dir())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let dump: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(file).unwrap()).unwrap();
+    assert!(
+        dump["processes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|e| e["pid"].is_null())
+    );
+
+    client.stop(Some(vec!["web".to_string()])).await.unwrap();
+
+    let err = client
+        .request(&Request::Resurrect {
+            path: None,
+            from: Some("bad".to_string()),
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, ClientError::Daemon(message) if message == "snapshot not found: bad"),
+        "got {err:?}"
+    );
+
+    // Only the stopped process comes back; the running one is left alone
+    let resp = client
+        .request(&Request::Resurrect {
+            path: None,
+            from: Some("good".to_string()),
+        })
+        .await
+        .unwrap();
+    match resp {
+        Response::Success { message } => assert_eq!(message.unwrap(), "resurrected: web"),
+        _ => panic!("expected success, got {resp:?}"),
+    }
+    let processes = client.list().await.unwrap();
+    assert_eq!(processes.len(), 2);
+    assert!(
+        processes.iter().all(|p| p.status == ProcessStatus::Online),
+        "{processes:?}"
+    );
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_resurrect_restores_processes() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    // Phase 1: start daemon, start processes, save, kill daemon
+    {
+        let handle = start_test_daemon(&paths).await;
+
+        let mut configs = HashMap::new();
+        configs.insert("web".to_string(), test_config("sleep 999"));
+        configs.insert("worker".to_string(), test_config("sleep 888"));
+
+        send_raw_request(
+            &paths,
+            &Request::Start {
+                configs,
+                names: None,
+                env: None,
+                wait: false,
+                path: None,
+                force: false,
+                namespace: None,
+                progress: false,
+            },
+        )
+        .await;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        // Save state
+        send_raw_request(&paths, &Request::Save { tag: None }).await;
+
+        // Kill daemon (this also kills the processes)
+        send_raw_request(&paths, &Request::Kill).await;
+        let _ = handle.await;
+    }
+
+    // Small delay between daemon instances
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Phase 2: start a new daemon — auto-restore brings processes back
+    {
+        let handle = start_test_daemon(&paths).await;
+
+        // Wait for auto-restore to bring processes online
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        // Verify both processes were auto-restored and are running
+        let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        match resp {
+            Response::ProcessList { processes, .. } => {
+                assert_eq!(processes.len(), 2);
+                for p in &processes {
+                    assert_eq!(p.status, ProcessStatus::Online);
+                    assert!(p.pid.is_some());
+                }
+            }
+            _ => panic!("expected process list"),
+        }
+
+        // Explicit resurrect should report everything already running
+        let resp = send_raw_request(
+            &paths,
+            &Request::Resurrect {
+                path: None,
+                from: None,
+            },
+        )
+        .await;
+        match resp {
+            Response::Success { message } => {
+                let msg = message.unwrap();
+                assert!(
+                    msg.contains("already running"),
+                    "should say already running: {}",
+                    msg
+                );
+            }
+            _ => panic!("expected success, got {:?}", resp),
+        }
+
+        send_raw_request(&paths, &Request::Kill).await;
+        let _ = handle.await;
+    }
+}
+
+#[tokio::test]
+async fn test_resurrect_marks_dead_processes_as_restarted() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    // Phase 1: start a process, save, kill daemon
+    {
+        let handle = start_test_daemon(&paths).await;
+
+        let mut configs = HashMap::new();
+        configs.insert("myproc".to_string(), test_config("sleep 999"));
+
+        send_raw_request(
+            &paths,
+            &Request::Start {
+                configs,
+                names: None,
+                env: None,
+                wait: false,
+                path: None,
+                force: false,
+                namespace: None,
+                progress: false,
+            },
+        )
+        .await;
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        // Save state
+        send_raw_request(&paths, &Request::Save { tag: None }).await;
+
+        // Kill daemon (processes get killed too)
+        send_raw_request(&paths, &Request::Kill).await;
+        let _ = handle.await;
+    }
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Phase 2: auto-restore brings back the process with a new PID
+    {
+        let handle = start_test_daemon(&paths).await;
+
+        // Wait for auto-restore to bring the process online
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        // Process should be online with a new PID
+        let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+        match resp {
+            Response::ProcessList { processes, .. } => {
+                assert_eq!(processes.len(), 1);
+                let p = &processes[0];
+                assert_eq!(p.name, "myproc");
+                assert_eq!(p.status, ProcessStatus::Online);
+                assert!(p.pid.is_some());
+            }
+            _ => panic!("expected process list"),
+        }
+
+        send_raw_request(&paths, &Request::Kill).await;
+        let _ = handle.await;
+    }
+}
+
+#[tokio::test]
+async fn test_daemon_with_resurrect_restores_saved_processes() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::create_dir_all(paths.data_dir()).unwrap();
+    let dump = serde_json::json!({
+        "version": pm3::dump::DUMP_VERSION,
+        "processes": [{ "name": "web", "config": test_config("sleep 999") }],
+    });
+    std::fs::write(paths.dump_file(), dump.to_string()).unwrap();
+
+    let handle = Daemon::builder()
+        .paths(paths.clone())
+        .resurrect(true)
+        .spawn()
+        .await
+        .unwrap();
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Online)], 20).await);
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_resurrect_rejects_newer_dump_format() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let dump = serde_json::json!({
+        "version": pm3::dump::DUMP_VERSION + 1,
+        "processes": [{ "name": "web", "config": test_config("sleep 999") }],
+    });
+    std::fs::write(paths.dump_file(), dump.to_string()).unwrap();
+
+    let err = handle
+        .client()
+        .request(&Request::Resurrect {
+            path: None,
+            from: None,
+        })
+        .await
+        .unwrap_err();
+    match err {
+        ClientError::Daemon(message) => {
+            assert!(message.contains("upgrade pm3"), "{message}")
+        }
+        other => panic!("expected daemon error, got: {other:?}"),
+    }
+    assert!(handle.client().list().await.unwrap().is_empty());
+
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_list_reports_auto_restore_progress() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    std::fs::create_dir_all(paths.data_dir()).unwrap();
+
+    // "db" never becomes healthy, so restore stalls before starting "web"
+    let mut db = test_config("sleep 999");
+    db.health_check = Some("tcp://127.0.0.1:1".to_string());
+    let mut web = test_config("sleep 999");
+    web.depends_on = Some(vec!["db".to_string()]);
+    let dump = serde_json::json!([
+        { "name": "db", "config": db, "pid": null, "restarts": 0 },
+        { "name": "web", "config": web, "pid": null, "restarts": 0 },
+    ]);
+    std::fs::write(paths.dump_file(), dump.to_string()).unwrap();
+
+    let handle = start_test_daemon(&paths).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match resp {
+        Response::ProcessList {
+            restoring: Some(progress),
+            ..
+        } => {
+            assert_eq!(progress.restored, 0);
+            assert_eq!(progress.total, 2);
+            assert_eq!(progress.starting, vec!["db".to_string()]);
+        }
+        other => panic!("expected restore progress in the list, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_list_reports_no_restore_without_dump() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    assert!(
+        matches!(
+            resp,
+            Response::ProcessList {
+                restoring: None,
+                ..
+            }
+        ),
+        "{resp:?}"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_daemon_info_identifies_daemon() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let resp = send_raw_request(&paths, &Request::DaemonInfo).await;
+    match resp {
+        Response::DaemonInfo { info } => {
+            assert_eq!(info.pid, std::process::id());
+            assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+            assert_eq!(info.processes, 0);
+            assert_eq!(info.data_dir, dir.path().display().to_string());
+        }
+        other => panic!("expected DaemonInfo, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_resurrect_no_dump_file_returns_error() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Resurrect {
+            path: None,
+            from: None,
+        },
+    )
+    .await;
+    match resp {
+        Response::Error { message } => {
+            assert!(message.contains("no dump file"), "got: {}", message);
+        }
+        _ => panic!("expected error, got {:?}", resp),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[test]
+fn test_dump_serialization_roundtrip() {
+    use serde_json;
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+    struct DumpEntry {
+        name: String,
+        config: ProcessConfig,
+        pid: Option<u32>,
+        restarts: u32,
+    }
+
+    let config = test_config("sleep 999");
+    let entry = DumpEntry {
+        name: "myproc".to_string(),
+        config: config.clone(),
+        pid: Some(12345),
+        restarts: 3,
+    };
+
+    let entries = vec![entry.clone()];
+    let json = serde_json::to_string_pretty(&entries).unwrap();
+    let restored: Vec<DumpEntry> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(restored.len(), 1);
+    assert_eq!(restored[0].name, "myproc");
+    assert_eq!(restored[0].pid, Some(12345));
+    assert_eq!(restored[0].restarts, 3);
+    assert_eq!(restored[0].config.command, "sleep 999");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_cluster_mode_starts_multiple_instances() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut web = test_config("sleep 999");
+    web.instances = Some(3);
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), web);
+    let start_resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&start_resp, Response::Success { .. }),
+        "expected Success, got: {start_resp:?}"
+    );
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(
+                processes.len(),
+                3,
+                "expected 3 instances, got: {processes:?}"
+            );
+            let mut names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+            names.sort();
+            assert_eq!(names, vec!["web:0", "web:1", "web:2"]);
+            for p in processes {
+                assert!(p.pid.is_some(), "PID should be set for {}", p.name);
+                assert_eq!(p.status, ProcessStatus::Online);
+                assert_eq!(p.group.as_deref(), Some("web"));
+            }
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_cluster_mode_stop_single_instance() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut web = test_config("sleep 999");
+    web.instances = Some(3);
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), web);
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // Stop just web:1
+    let stop_resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["web:1".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&stop_resp, Response::Success { .. }),
+        "expected Success, got: {stop_resp:?}"
+    );
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            let stopped: Vec<_> = processes.iter().filter(|p| p.name == "web:1").collect();
+            assert_eq!(stopped.len(), 1);
+            assert_eq!(stopped[0].status, ProcessStatus::Stopped);
+
+            let running: Vec<_> = processes
+                .iter()
+                .filter(|p| p.status == ProcessStatus::Online)
+                .collect();
+            assert_eq!(running.len(), 2);
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_cluster_mode_stop_by_logical_name() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let mut web = test_config("sleep 999");
+    web.instances = Some(3);
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), web);
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // Stop all instances via logical name "web" (resolved via group)
+    let stop_resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["web".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(
+        matches!(&stop_resp, Response::Success { .. }),
+        "expected Success, got: {stop_resp:?}"
+    );
+
+    let list_resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    match &list_resp {
+        Response::ProcessList { processes, .. } => {
+            for p in processes {
+                assert_eq!(
+                    p.status,
+                    ProcessStatus::Stopped,
+                    "expected all stopped, {} is {:?}",
+                    p.name,
+                    p.status
+                );
+            }
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_cluster_mode_env_injection() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+
+    let handle = start_test_daemon(&paths).await;
+
+    let output_dir = dir.path().join("output");
+    std::fs::create_dir_all(&output_dir).unwrap();
+
+    let mut web = test_config(&format!(
+        "sh -c 'echo $PM3_INSTANCE_ID $PM3_INSTANCE_COUNT > {}/instance_$PM3_INSTANCE_ID.txt; sleep 999'",
+        output_dir.display()
+    ));
+    web.instances = Some(3);
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), web);
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    for i in 0..3 {
+        let file = output_dir.join(format!("instance_{}.txt", i));
+        assert!(file.exists(), "output file for instance {} should exist", i);
+        let content = std::fs::read_to_string(&file).unwrap();
+        let trimmed = content.trim();
+        assert_eq!(
+            trimmed,
+            format!("{} 3", i),
+            "instance {} should have ID={} COUNT=3, got: {}",
+            i,
+            i,
+            trimmed
+        );
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+// ---------------------------------------------------------------------------
+// Pause / resume monitoring
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn test_paused_process_is_not_auto_restarted() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut config = test_config("sleep 999");
+    config.restart = Some(RestartPolicy::Always);
+    let mut configs = HashMap::new();
+    configs.insert("svc".to_string(), config);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Pause {
+            names: Some(vec!["svc".to_string()]),
+            namespace: None,
+        },
+    )
+    .await;
+    match resp {
+        Response::Success { message } => assert_eq!(message.as_deref(), Some("paused: svc")),
+        other => panic!("expected success, got: {other:?}"),
+    }
+
+    send_raw_request(
+        &paths,
+        &Request::Signal {
+            name: "svc".to_string(),
+            signal: "SIGKILL".to_string(),
+        },
+    )
+    .await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            let p = processes.iter().find(|p| p.name == "svc").unwrap();
+            assert!(p.paused);
+            assert_eq!(p.pid, None, "paused process should stay down");
+            assert_eq!(p.restarts, 0);
+        }
+        other => panic!("expected process list, got: {other:?}"),
+    }
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Resume {
+            names: None,
+            namespace: None,
+        },
+    )
+    .await;
+    match resp {
+        Response::Success { message } => assert_eq!(message.as_deref(), Some("resumed: svc")),
+        other => panic!("expected success, got: {other:?}"),
+    }
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            assert!(!processes.iter().find(|p| p.name == "svc").unwrap().paused);
+        }
+        other => panic!("expected process list, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_suspend_and_continue_process() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("frozen".to_string(), test_config("sleep 999"));
+    let resp = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    let status_and_uptime = |resp: Response| match resp {
+        Response::ProcessList { processes, .. } => {
+            let p = processes.iter().find(|p| p.name == "frozen").unwrap();
+            (p.status, p.uptime)
+        }
+        other => panic!("expected process list, got: {other:?}"),
+    };
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Suspend {
+            name: "frozen".to_string(),
+        },
+    )
+    .await;
+    match resp {
+        Response::Success { message } => assert_eq!(message.as_deref(), Some("suspended: frozen")),
+        other => panic!("expected success, got: {other:?}"),
+    }
+
+    let (status, frozen_uptime) =
+        status_and_uptime(send_raw_request(&paths, &Request::List { filter: None }).await);
+    assert_eq!(status, ProcessStatus::Suspended);
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let (_, later_uptime) =
+        status_and_uptime(send_raw_request(&paths, &Request::List { filter: None }).await);
+    assert_eq!(
+        frozen_uptime, later_uptime,
+        "uptime should not advance while suspended"
+    );
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Continue {
+            name: "frozen".to_string(),
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    let (status, _) =
+        status_and_uptime(send_raw_request(&paths, &Request::List { filter: None }).await);
+    assert_eq!(status, ProcessStatus::Online);
+
+    // A suspended process can still be stopped promptly
+    send_raw_request(
+        &paths,
+        &Request::Suspend {
+            name: "frozen".to_string(),
+        },
+    )
+    .await;
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["frozen".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    let (status, _) =
+        status_and_uptime(send_raw_request(&paths, &Request::List { filter: None }).await);
+    assert_eq!(status, ProcessStatus::Stopped);
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_suspend_partly_stopped_cluster_signals_nothing() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut config = test_config("sleep 999");
+    config.instances = Some(2);
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), config);
+    handle.client().start(configs, None).await.unwrap();
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("web:0", ProcessStatus::Online),
+                ("web:1", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["web:1".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Suspend {
+            name: "web".to_string(),
+        },
+    )
+    .await;
+    match resp {
+        Response::Error { message } => assert!(message.contains("web:1"), "got: {message}"),
+        other => panic!("expected error, got: {other:?}"),
+    }
+
+    // The running instance was left alone
+    let resp = send_raw_request(&paths, &Request::List { filter: None }).await;
+    let Response::ProcessList { processes, .. } = resp else {
+        panic!("expected process list, got: {resp:?}");
+    };
+    let web0 = processes.iter().find(|p| p.name == "web:0").unwrap();
+    assert_eq!(web0.status, ProcessStatus::Online);
+    let pid = web0.pid.unwrap();
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).unwrap_or_default();
+    assert!(
+        !stat.contains(") T "),
+        "web:0 should not be stopped: {stat}"
+    );
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_stop_by_glob_and_regex_pattern() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    for name in ["web-1", "web-2", "api"] {
+        configs.insert(name.to_string(), test_config("sleep 999"));
+    }
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("web-1", ProcessStatus::Online),
+                ("web-2", ProcessStatus::Online),
+                ("api", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["web-*".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("web-1", ProcessStatus::Stopped),
+                ("web-2", ProcessStatus::Stopped),
+                ("api", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["/^db/".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    match resp {
+        Response::Error { message } => assert!(message.contains("no processes match"), "{message}"),
+        other => panic!("expected error, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_stop_except_skips_excluded_processes() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    for name in ["web-1", "web-2", "db"] {
+        configs.insert(name.to_string(), test_config("sleep 999"));
+    }
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("web-1", ProcessStatus::Online),
+                ("web-2", ProcessStatus::Online),
+                ("db", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+
+    // Exclusion is applied after pattern expansion
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: None,
+            except: Some(vec!["db".to_string(), "web-[2]".to_string()]),
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    match resp {
+        Response::Success { message } => assert_eq!(message.as_deref(), Some("stopped: web-1")),
+        other => panic!("expected success, got: {other:?}"),
+    }
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("web-1", ProcessStatus::Stopped),
+                ("web-2", ProcessStatus::Online),
+                ("db", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+
+    // Unknown exclusions are rejected rather than silently ignored
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: None,
+            except: Some(vec!["dbb".to_string()]),
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Error { .. }), "got: {resp:?}");
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_list_filter_by_status_and_group() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    for (name, group) in [("web", "backend"), ("api", "backend"), ("ui", "frontend")] {
+        let mut config = test_config("sleep 999");
+        config.group = Some(group.to_string());
+        configs.insert(name.to_string(), config);
+    }
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("web", ProcessStatus::Online),
+                ("api", ProcessStatus::Online),
+                ("ui", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+    send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["api".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+
+    let list_names = |resp: Response| match resp {
+        Response::ProcessList { processes, .. } => {
+            let mut names: Vec<String> = processes.into_iter().map(|p| p.name).collect();
+            names.sort();
+            names
+        }
+        other => panic!("expected process list, got: {other:?}"),
+    };
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::List {
+            filter: Some(ListFilter {
+                group: vec!["backend".to_string()],
+                ..Default::default()
+            }),
+        },
+    )
+    .await;
+    assert_eq!(list_names(resp), vec!["api", "web"]);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::List {
+            filter: Some(ListFilter {
+                status: vec![ProcessStatus::Online],
+                group: vec!["backend".to_string()],
+                ..Default::default()
+            }),
+        },
+    )
+    .await;
+    assert_eq!(list_names(resp), vec!["web"]);
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::List {
+            filter: Some(ListFilter {
+                status: vec![ProcessStatus::Errored],
+                ..Default::default()
+            }),
+        },
+    )
+    .await;
+    assert!(list_names(resp).is_empty());
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_restart_by_tag_and_list_shows_tags() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    for (name, tags) in [
+        ("payments", vec!["critical", "payments"]),
+        ("db", vec!["critical"]),
+        ("web", vec![]),
+    ] {
+        let mut config = test_config("sleep 999");
+        if !tags.is_empty() {
+            config.tags = Some(tags.into_iter().map(String::from).collect());
+        }
+        configs.insert(name.to_string(), config);
+    }
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("payments", ProcessStatus::Online),
+                ("db", ProcessStatus::Online),
+                ("web", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["critical".to_string()]),
+            except: None,
+            namespace: None,
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("payments", ProcessStatus::Stopped),
+                ("db", ProcessStatus::Stopped),
+                ("web", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::List {
+            filter: Some(ListFilter {
+                tag: vec!["payments".to_string()],
+                ..Default::default()
+            }),
+        },
+    )
+    .await;
+    match resp {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            assert_eq!(processes[0].name, "payments");
+            assert_eq!(processes[0].tags, vec!["critical", "payments"]);
+        }
+        other => panic!("expected process list, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_namespaces_isolate_processes_with_same_name() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    for namespace in [None, Some("shop"), Some("blog")] {
+        let mut db = test_config("sleep 999");
+        db.group = Some("storage".to_string());
+        let mut web = test_config("sleep 999");
+        web.depends_on = Some(vec!["db".to_string()]);
+        let resp = send_raw_request(
+            &paths,
+            &Request::Start {
+                configs: HashMap::from([("web".to_string(), web), ("db".to_string(), db)]),
+                names: None,
+                env: None,
+                wait: false,
+                path: None,
+                force: false,
+                namespace: namespace.map(String::from),
+                progress: false,
+            },
+        )
+        .await;
+        assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    }
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("web", ProcessStatus::Online),
+                ("shop/web", ProcessStatus::Online),
+                ("shop/db", ProcessStatus::Online),
+                ("blog/web", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+    assert!(paths.stdout_log("shop/web").exists());
+
+    // Stopping everything in one namespace leaves the others alone
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: None,
+            except: None,
+            namespace: Some("shop".to_string()),
+            no_dependents: false,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    assert!(
+        wait_for_statuses(
+            &paths,
+            &[
+                ("shop/web", ProcessStatus::Stopped),
+                ("shop/db", ProcessStatus::Stopped),
+                ("web", ProcessStatus::Online),
+                ("blog/web", ProcessStatus::Online),
+            ],
+            20,
+        )
+        .await
+    );
+
+    // Names and groups resolve within the namespace
+    let resp = send_raw_request(
+        &paths,
+        &Request::Stop {
+            names: Some(vec!["storage".to_string()]),
+            except: None,
+            namespace: Some("blog".to_string()),
+            no_dependents: false,
+        },
+    )
+    .await;
+    match resp {
+        Response::Success { message } => {
+            assert_eq!(message.as_deref(), Some("stopped: blog/web, blog/db"))
+        }
+        other => panic!("expected success, got: {other:?}"),
+    }
+
+    let resp = send_raw_request(
+        &paths,
+        &Request::List {
+            filter: Some(ListFilter {
+                namespace: Some("default".to_string()),
+                ..Default::default()
+            }),
+        },
+    )
+    .await;
+    match resp {
+        Response::ProcessList { processes, .. } => {
+            let mut names: Vec<String> = processes.into_iter().map(|p| p.name).collect();
+            names.sort();
+            assert_eq!(names, vec!["db", "web"]);
+        }
+        other => panic!("expected process list, got: {other:?}"),
+    }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_pause_and_flush_without_names_stay_in_namespace() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    for namespace in ["shop", "blog"] {
+        let resp = send_raw_request(
+            &paths,
             &Request::Start {
-                configs,
+                configs: HashMap::from([("web".to_string(), test_config("sleep 999"))]),
                 names: None,
                 env: None,
                 wait: false,
                 path: None,
+                force: false,
+                namespace: Some(namespace.to_string()),
+                progress: false,
             },
         )
         .await;
+        assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    }
 
-        tokio::time::sleep(Duration::from_millis(500)).await;
+    let resp = send_raw_request(
+        &paths,
+        &Request::Pause {
+            names: None,
+            namespace: Some("shop".to_string()),
+        },
+    )
+    .await;
+    match resp {
+        Response::Success { message } => {
+            assert_eq!(message.as_deref(), Some("paused: shop/web"))
+        }
+        other => panic!("expected success, got: {other:?}"),
+    }
 
-        // Save state
-        send_raw_request(&paths, &Request::Save).await;
+    let resp = send_raw_request(
+        &paths,
+        &Request::Flush {
+            names: None,
+            all_files: false,
+            namespace: Some("blog".to_string()),
+        },
+    )
+    .await;
+    match resp {
+        Response::Success { message } => {
+            assert_eq!(message.as_deref(), Some("flushed logs: blog/web"))
+        }
+        other => panic!("expected success, got: {other:?}"),
+    }
 
-        // Kill daemon (this also kills the processes)
-        send_raw_request(&paths, &Request::Kill).await;
-        let _ = handle.await;
+    // Without a namespace, as with --all-namespaces, every one is affected
+    let resp = send_raw_request(
+        &paths,
+        &Request::Resume {
+            names: None,
+            namespace: None,
+        },
+    )
+    .await;
+    match resp {
+        Response::Success { message } => {
+            assert_eq!(message.as_deref(), Some("resumed: shop/web"))
+        }
+        other => panic!("expected success, got: {other:?}"),
     }
 
-    // Small delay between daemon instances
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
 
-    // Phase 2: start a new daemon — auto-restore brings processes back
-    {
-        let handle = start_test_daemon(&paths).await;
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_run_streams_every_line_of_each_run() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
 
-        // Wait for auto-restore to bring processes online
-        tokio::time::sleep(Duration::from_millis(500)).await;
+    // Output written the moment it starts is still caught, run after run
+    let mut seed = test_config("sh -c 'echo first; echo >&2 second'");
+    seed.kind = Some(config::ProcessKind::Task);
+    for _ in 0..2 {
+        let responses = send_streaming_request(
+            &paths,
+            &Request::Run {
+                configs: HashMap::from([("seed".to_string(), seed.clone())]),
+                name: "seed".to_string(),
+                env: None,
+                path: None,
+                namespace: None,
+            },
+        )
+        .await;
+        let mut lines: Vec<_> = responses
+            .iter()
+            .filter_map(|resp| match resp {
+                Response::LogLine { stream, line, .. } => Some((stream.clone(), line.as_str())),
+                _ => None,
+            })
+            .collect();
+        lines.sort_by_key(|(_, line)| *line);
+        assert_eq!(
+            lines,
+            vec![
+                (Some(pm3::log::LogStream::Stdout), "first"),
+                (Some(pm3::log::LogStream::Stderr), "second"),
+            ]
+        );
+        assert!(
+            matches!(
+                responses.last(),
+                Some(Response::TaskFinished {
+                    status: ProcessStatus::Succeeded,
+                    ..
+                })
+            ),
+            "got: {responses:?}"
+        );
+    }
 
-        // Verify both processes were auto-restored and are running
-        let resp = send_raw_request(&paths, &Request::List).await;
-        match resp {
-            Response::ProcessList { processes } => {
-                assert_eq!(processes.len(), 2);
-                for p in &processes {
-                    assert_eq!(p.status, ProcessStatus::Online);
-                    assert!(p.pid.is_some());
-                }
-            }
-            _ => panic!("expected process list"),
-        }
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
 
-        // Explicit resurrect should report everything already running
-        let resp = send_raw_request(&paths, &Request::Resurrect { path: None }).await;
-        match resp {
-            Response::Success { message } => {
-                let msg = message.unwrap();
-                assert!(
-                    msg.contains("already running"),
-                    "should say already running: {}",
-                    msg
-                );
-            }
-            _ => panic!("expected success, got {:?}", resp),
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_run_registers_task_in_namespace() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut seed = test_config("echo seeded");
+    seed.kind = Some(config::ProcessKind::Task);
+    let responses = send_streaming_request(
+        &paths,
+        &Request::Run {
+            configs: HashMap::from([("seed".to_string(), seed)]),
+            name: "seed".to_string(),
+            env: None,
+            path: None,
+            namespace: Some("shop".to_string()),
+        },
+    )
+    .await;
+    match responses.last() {
+        Some(Response::TaskFinished { name, status, .. }) => {
+            assert_eq!(name, "shop/seed");
+            assert_eq!(*status, ProcessStatus::Succeeded);
         }
+        other => panic!("expected TaskFinished, got: {other:?}"),
+    }
+    assert!(
+        responses
+            .iter()
+            .any(|resp| matches!(resp, Response::LogLine { line, .. } if line.contains("seeded")))
+    );
 
-        send_raw_request(&paths, &Request::Kill).await;
-        let _ = handle.await;
+    let resp = send_raw_request(
+        &paths,
+        &Request::List {
+            filter: Some(ListFilter {
+                namespace: Some("shop".to_string()),
+                ..Default::default()
+            }),
+        },
+    )
+    .await;
+    match resp {
+        Response::ProcessList { processes, .. } => {
+            let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(names, vec!["shop/seed"]);
+        }
+        other => panic!("expected process list, got: {other:?}"),
     }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
 }
 
 #[tokio::test]
-async fn test_resurrect_marks_dead_processes_as_restarted() {
+async fn test_export_returns_namespace_configs() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+    let client = handle.client();
 
-    // Phase 1: start a process, save, kill daemon
-    {
-        let handle = start_test_daemon(&paths).await;
-
-        let mut configs = HashMap::new();
-        configs.insert("myproc".to_string(), test_config("sleep 999"));
-
-        send_raw_request(
-            &paths,
-            &Request::Start {
-                configs,
+    let mut web = test_config("sleep 999");
+    web.instances = Some(2);
+    web.depends_on = Some(vec!["db".to_string()]);
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), web.clone());
+    configs.insert("db".to_string(), test_config("sleep 888"));
+    for namespace in ["shop", "default"] {
+        let resp = client
+            .request(&Request::Start {
+                configs: configs.clone(),
                 names: None,
                 env: None,
                 wait: false,
-                path: None,
-            },
-        )
-        .await;
+                path: Some("/usr/bin:/bin".to_string()),
+                force: false,
+                namespace: Some(namespace.to_string()),
+                progress: false,
+            })
+            .await
+            .unwrap();
+        assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    }
 
-        tokio::time::sleep(Duration::from_millis(500)).await;
+    let resp = client
+        .request(&Request::Export {
+            namespace: Some("shop".to_string()),
+        })
+        .await
+        .unwrap();
+    let exported = match resp {
+        Response::Configs { configs } => configs,
+        other => panic!("expected configs, got: {other:?}"),
+    };
+    // Instances fold back into one entry, without the env pm3 injected
+    assert_eq!(exported, configs);
 
-        // Save state
-        send_raw_request(&paths, &Request::Save).await;
+    let rendered = config::to_toml(&exported, Some("shop")).unwrap();
+    assert_eq!(config::parse_config(&rendered).unwrap(), configs);
+    assert!(rendered.starts_with("namespace = \"shop\"\n"), "{rendered}");
 
-        // Kill daemon (processes get killed too)
-        send_raw_request(&paths, &Request::Kill).await;
-        let _ = handle.await;
+    handle.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_wait_blocks_until_status_reached() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), test_config("sleep 999"));
+    send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: false,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+
+    let wait = |status, timeout_ms| Request::Wait {
+        names: vec!["web".to_string()],
+        status,
+        timeout_ms,
+        namespace: None,
+    };
+
+    let resp = send_raw_request(&paths, &wait(ProcessStatus::Online, 10_000)).await;
+    match resp {
+        Response::Success { message } => assert_eq!(message.as_deref(), Some("online: web")),
+        other => panic!("expected success, got: {other:?}"),
     }
 
-    tokio::time::sleep(Duration::from_millis(500)).await;
+    let resp = send_raw_request(&paths, &wait(ProcessStatus::Stopped, 500)).await;
+    match resp {
+        Response::Error { message } => {
+            assert!(message.contains("timeout"), "got: {message}");
+            assert!(message.contains("web: online"), "got: {message}");
+        }
+        other => panic!("expected timeout error, got: {other:?}"),
+    }
 
-    // Phase 2: auto-restore brings back the process with a new PID
-    {
-        let handle = start_test_daemon(&paths).await;
+    let resp = send_raw_request(
+        &paths,
+        &Request::Wait {
+            names: vec!["missing".to_string()],
+            status: ProcessStatus::Online,
+            timeout_ms: 500,
+            namespace: None,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Error { .. }), "got: {resp:?}");
 
-        // Wait for auto-restore to bring the process online
-        tokio::time::sleep(Duration::from_millis(500)).await;
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
 
-        // Process should be online with a new PID
-        let resp = send_raw_request(&paths, &Request::List).await;
-        match resp {
-            Response::ProcessList { processes } => {
-                assert_eq!(processes.len(), 1);
-                let p = &processes[0];
-                assert_eq!(p.name, "myproc");
-                assert_eq!(p.status, ProcessStatus::Online);
-                assert!(p.pid.is_some());
-            }
-            _ => panic!("expected process list"),
+#[tokio::test]
+async fn test_start_wait_streams_progress_per_level() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut web = test_config("sleep 999");
+    web.depends_on = Some(vec!["db".to_string()]);
+    let mut configs = HashMap::new();
+    configs.insert("db".to_string(), test_config("sleep 999"));
+    configs.insert("web".to_string(), web);
+
+    let responses = send_streaming_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: true,
+        },
+    )
+    .await;
+    let progress: Vec<&str> = responses
+        .iter()
+        .filter_map(|r| match r {
+            Response::Progress { message } => Some(message.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        progress,
+        vec!["starting db…", "online: db", "starting web…", "online: web"]
+    );
+    match responses.last() {
+        Some(Response::Success { message }) => {
+            assert_eq!(message.as_deref(), Some("started: db, web"))
         }
-
-        send_raw_request(&paths, &Request::Kill).await;
-        let _ = handle.await;
+        other => panic!("expected final success, got: {other:?}"),
     }
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
 }
 
 #[tokio::test]
-async fn test_resurrect_no_dump_file_returns_error() {
+async fn test_start_continues_past_failed_optional_dependency() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
     let handle = start_test_daemon(&paths).await;
 
-    let resp = send_raw_request(&paths, &Request::Resurrect { path: None }).await;
-    match resp {
-        Response::Error { message } => {
-            assert!(message.contains("no dump file"), "got: {}", message);
+    let mut cache = test_config("sh -c 'exit 1'");
+    cache.restart = Some(RestartPolicy::Never);
+    let mut web = test_config("sleep 999");
+    web.depends_on = Some(vec!["cache".to_string()]);
+    web.optional_deps = Some(vec!["cache".to_string()]);
+    let mut configs = HashMap::new();
+    configs.insert("cache".to_string(), cache);
+    configs.insert("web".to_string(), web);
+
+    let response = send_raw_request(
+        &paths,
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
+        },
+    )
+    .await;
+    match response {
+        Response::Success {
+            message: Some(message),
+        } => {
+            assert!(message.starts_with("started: cache, web"), "{message}");
+            assert!(
+                message.contains("warning: dependency 'cache' failed"),
+                "{message}"
+            );
         }
-        _ => panic!("expected error, got {:?}", resp),
+        other => panic!("expected success with warning, got: {other:?}"),
     }
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Online)], 8).await);
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-#[test]
-fn test_dump_serialization_roundtrip() {
-    use serde_json;
-
-    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
-    struct DumpEntry {
-        name: String,
-        config: ProcessConfig,
-        pid: Option<u32>,
-        restarts: u32,
-    }
-
-    let config = test_config("sleep 999");
-    let entry = DumpEntry {
-        name: "myproc".to_string(),
-        config: config.clone(),
-        pid: Some(12345),
-        restarts: 3,
-    };
-
-    let entries = vec![entry.clone()];
-    let json = serde_json::to_string_pretty(&entries).unwrap();
-    let restored: Vec<DumpEntry> = serde_json::from_str(&json).unwrap();
-
-    assert_eq!(restored.len(), 1);
-    assert_eq!(restored[0].name, "myproc");
-    assert_eq!(restored[0].pid, Some(12345));
-    assert_eq!(restored[0].restarts, 3);
-    assert_eq!(restored[0].config.command, "sleep 999");
-}
-
-#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_cluster_mode_starts_multiple_instances() {
+#[tokio::test]
+async fn test_restart_and_resurrect_continue_past_failed_optional_dependency() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
+    let mut cache = test_config("sh -c 'exit 1'");
+    cache.restart = Some(RestartPolicy::Never);
     let mut web = test_config("sleep 999");
-    web.instances = Some(3);
-
+    web.depends_on = Some(vec!["cache".to_string()]);
+    web.optional_deps = Some(vec!["cache".to_string()]);
     let mut configs = HashMap::new();
+    configs.insert("cache".to_string(), cache);
     configs.insert("web".to_string(), web);
-    let start_resp = send_raw_request(
+    send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
             env: None,
-            wait: false,
+            wait: true,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(
-        matches!(&start_resp, Response::Success { .. }),
-        "expected Success, got: {start_resp:?}"
-    );
-
-    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Online)], 8).await);
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            assert_eq!(
-                processes.len(),
-                3,
-                "expected 3 instances, got: {processes:?}"
+    let response = send_raw_request(
+        &paths,
+        &Request::Restart {
+            names: Some(vec!["cache".to_string()]),
+            except: None,
+            namespace: None,
+            stagger_ms: None,
+        },
+    )
+    .await;
+    match response {
+        Response::Success {
+            message: Some(message),
+        } => {
+            assert!(message.starts_with("restarted: cache, web"), "{message}");
+            assert!(
+                message.contains("warning: dependency 'cache' failed"),
+                "{message}"
             );
-            let mut names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
-            names.sort();
-            assert_eq!(names, vec!["web:0", "web:1", "web:2"]);
-            for p in processes {
-                assert!(p.pid.is_some(), "PID should be set for {}", p.name);
-                assert_eq!(p.status, ProcessStatus::Online);
-                assert_eq!(p.group.as_deref(), Some("web"));
-            }
         }
-        other => panic!("expected ProcessList, got: {other:?}"),
+        other => panic!("expected success with warning, got: {other:?}"),
     }
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Online)], 8).await);
+
+    send_raw_request(&paths, &Request::Save { tag: None }).await;
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+
+    // The new daemon restores web even though cache fails again
+    let handle = start_test_daemon(&paths).await;
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Online)], 8).await);
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
-#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_cluster_mode_stop_single_instance() {
+#[tokio::test]
+async fn test_ready_signal_ipc_waits_for_process_to_report_ready() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut web = test_config("sleep 999");
-    web.instances = Some(3);
-
+    // Reports ready only once the test creates the `go` file
+    let mut config = test_config(
+        r#"sh -c 'while [ ! -f go ]; do sleep 0.1; done; echo ready > "$PM3_READY_PATH"; sleep 999'"#,
+    );
+    config.cwd = Some(dir.path().display().to_string());
+    config.ready_signal = Some(ReadySignal::Ipc);
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), web);
+    configs.insert("web".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
@@ -4736,96 +10453,120 @@ async fn test_cluster_mode_stop_single_instance() {
             env: None,
             wait: false,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
 
-    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Starting)], 4).await);
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Starting)], 1).await);
 
-    // Stop just web:1
-    let stop_resp = send_raw_request(
+    std::fs::write(dir.path().join("go"), "").unwrap();
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Online)], 20).await);
+
+    send_raw_request(&paths, &Request::Kill).await;
+    let _ = handle.await;
+}
+
+#[tokio::test]
+async fn test_ready_signal_delay_goes_online_after_delay() {
+    let dir = TempDir::new().unwrap();
+    let paths = Paths::with_base(dir.path().to_path_buf());
+    let handle = start_test_daemon(&paths).await;
+
+    let mut config = test_config("sleep 999");
+    config.ready_signal = Some(ReadySignal::Delay);
+    config.ready_delay = Some(1500);
+    let mut configs = HashMap::new();
+    configs.insert("web".to_string(), config);
+    let started = Instant::now();
+    let resp = send_raw_request(
         &paths,
-        &Request::Stop {
-            names: Some(vec!["web:1".to_string()]),
+        &Request::Start {
+            configs,
+            names: None,
+            env: None,
+            wait: true,
+            path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
-    assert!(
-        matches!(&stop_resp, Response::Success { .. }),
-        "expected Success, got: {stop_resp:?}"
-    );
-
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            let stopped: Vec<_> = processes.iter().filter(|p| p.name == "web:1").collect();
-            assert_eq!(stopped.len(), 1);
-            assert_eq!(stopped[0].status, ProcessStatus::Stopped);
-
-            let running: Vec<_> = processes
-                .iter()
-                .filter(|p| p.status == ProcessStatus::Online)
-                .collect();
-            assert_eq!(running.len(), 2);
-        }
-        other => panic!("expected ProcessList, got: {other:?}"),
-    }
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    assert!(started.elapsed() >= Duration::from_millis(1500));
+    assert!(wait_for_statuses(&paths, &[("web", ProcessStatus::Online)], 1).await);
 
     send_raw_request(&paths, &Request::Kill).await;
     let _ = handle.await;
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_cluster_mode_stop_by_logical_name() {
+async fn test_reload_with_ready_delay_keeps_old_instance_during_warmup() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let mut web = test_config("sleep 999");
-    web.instances = Some(3);
-
+    let mut config = test_config("sleep 999");
+    config.ready_signal = Some(ReadySignal::Delay);
+    config.ready_delay = Some(1500);
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), web);
+    configs.insert("web".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
             env: None,
-            wait: false,
+            wait: true,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
+    let old_pid = match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => processes[0].pid.unwrap(),
+        other => panic!("expected ProcessList, got: {other:?}"),
+    };
 
-    tokio::time::sleep(Duration::from_millis(300)).await;
+    let reload_paths = paths.clone();
+    let reload = tokio::spawn(async move {
+        send_raw_request(
+            &reload_paths,
+            &Request::Reload {
+                names: Some(vec!["web".to_string()]),
+                except: None,
+                path: None,
+                namespace: None,
+            },
+        )
+        .await
+    });
 
-    // Stop all instances via logical name "web" (resolved via group)
-    let stop_resp = send_raw_request(
-        &paths,
-        &Request::Stop {
-            names: Some(vec!["web".to_string()]),
-        },
-    )
-    .await;
-    assert!(
-        matches!(&stop_resp, Response::Success { .. }),
-        "expected Success, got: {stop_resp:?}"
-    );
+    // Mid warm-up the old instance is still the one serving
+    tokio::time::sleep(Duration::from_millis(700)).await;
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            let web = processes.iter().find(|p| p.name == "web").unwrap();
+            assert_eq!(web.pid, Some(old_pid));
+            assert_eq!(web.status, ProcessStatus::Online);
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
 
-    let list_resp = send_raw_request(&paths, &Request::List).await;
-    match &list_resp {
-        Response::ProcessList { processes } => {
-            for p in processes {
-                assert_eq!(
-                    p.status,
-                    ProcessStatus::Stopped,
-                    "expected all stopped, {} is {:?}",
-                    p.name,
-                    p.status
-                );
-            }
+    let resp = reload.await.unwrap();
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            assert_ne!(processes[0].pid, Some(old_pid));
+            assert_eq!(processes[0].status, ProcessStatus::Online);
         }
         other => panic!("expected ProcessList, got: {other:?}"),
     }
@@ -4835,50 +10576,68 @@ async fn test_cluster_mode_stop_by_logical_name() {
 }
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
-async fn test_cluster_mode_env_injection() {
+async fn test_reload_sends_drain_signal_before_stopping_old_instance() {
     let dir = TempDir::new().unwrap();
     let paths = Paths::with_base(dir.path().to_path_buf());
-
     let handle = start_test_daemon(&paths).await;
 
-    let output_dir = dir.path().join("output");
-    std::fs::create_dir_all(&output_dir).unwrap();
-
-    let mut web = test_config(&format!(
-        "sh -c 'echo $PM3_INSTANCE_ID $PM3_INSTANCE_COUNT > {}/instance_$PM3_INSTANCE_ID.txt; sleep 999'",
-        output_dir.display()
-    ));
-    web.instances = Some(3);
-
+    // Records the drain and exits on its own, well inside drain_timeout
+    let mut config = test_config(
+        r#"sh -c 'trap "echo $$ >> drained; exit 0" USR2; while true; do sleep 0.1; done'"#,
+    );
+    config.cwd = Some(dir.path().display().to_string());
+    config.ready_signal = Some(ReadySignal::Delay);
+    config.ready_delay = Some(300);
+    config.drain_signal = Some("SIGUSR2".to_string());
+    config.drain_timeout = Some(10_000);
     let mut configs = HashMap::new();
-    configs.insert("web".to_string(), web);
+    configs.insert("web".to_string(), config);
     send_raw_request(
         &paths,
         &Request::Start {
             configs,
             names: None,
             env: None,
-            wait: false,
+            wait: true,
             path: None,
+            force: false,
+            namespace: None,
+            progress: false,
         },
     )
     .await;
+    let old_pid = match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => processes[0].pid.unwrap(),
+        other => panic!("expected ProcessList, got: {other:?}"),
+    };
 
-    tokio::time::sleep(Duration::from_millis(1000)).await;
+    let started = Instant::now();
+    let resp = send_raw_request(
+        &paths,
+        &Request::Reload {
+            names: Some(vec!["web".to_string()]),
+            except: None,
+            path: None,
+            namespace: None,
+        },
+    )
+    .await;
+    assert!(matches!(resp, Response::Success { .. }), "got: {resp:?}");
+    assert!(
+        started.elapsed() < Duration::from_secs(5),
+        "reload should not wait out drain_timeout once the old instance exits"
+    );
 
-    for i in 0..3 {
-        let file = output_dir.join(format!("instance_{}.txt", i));
-        assert!(file.exists(), "output file for instance {} should exist", i);
-        let content = std::fs::read_to_string(&file).unwrap();
-        let trimmed = content.trim();
-        assert_eq!(
-            trimmed,
-            format!("{} 3", i),
-            "instance {} should have ID={} COUNT=3, got: {}",
-            i,
-            i,
-            trimmed
-        );
+    let drained = std::fs::read_to_string(dir.path().join("drained")).unwrap();
+    assert_eq!(drained.trim(), old_pid.to_string());
+    match send_raw_request(&paths, &Request::List { filter: None }).await {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            assert_ne!(processes[0].pid, Some(old_pid));
+            assert_eq!(processes[0].status, ProcessStatus::Online);
+            assert_eq!(processes[0].restarts, 0);
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
     }
 
     send_raw_request(&paths, &Request::Kill).await;
@@ -11,6 +11,9 @@ fn pm3(data_dir: &Path, work_dir: &Path) -> Command {
     let mut cmd: Command = cargo_bin_cmd!("pm3");
     cmd.env("PM3_DATA_DIR", data_dir);
     cmd.current_dir(work_dir);
+    // Keep bare process names rather than ones qualified with the namespace
+    // the temp dir's name would give
+    cmd.args(["--namespace", "default"]);
     cmd.timeout(Duration::from_secs(30));
     cmd
 }
@@ -31,7 +34,7 @@ fn get_process_list(data_dir: &Path, work_dir: &Path) -> Vec<ProcessInfo> {
         .output()
         .unwrap();
     match parse_json_response(&output) {
-        Response::ProcessList { processes } => processes,
+        Response::ProcessList { processes, .. } => processes,
         other => panic!("expected ProcessList, got: {other:?}"),
     }
 }
@@ -656,6 +659,478 @@ command = "sleep 999"
     kill_daemon(&data_dir, work_dir);
 }
 
+#[test]
+fn test_e2e_namespace_defaults_to_project_directory() {
+    let dir = TempDir::new().unwrap();
+    let data_dir = dir.path().join("data");
+    let shop = dir.path().join("shop");
+    std::fs::create_dir(&shop).unwrap();
+    std::fs::write(
+        shop.join("pm3.toml"),
+        r#"
+[web]
+command = "sh -c 'echo hello_from_shop; sleep 999'"
+"#,
+    )
+    .unwrap();
+
+    let project = |args: &[&str]| {
+        let mut cmd: Command = cargo_bin_cmd!("pm3");
+        cmd.env("PM3_DATA_DIR", &data_dir)
+            .current_dir(&shop)
+            .timeout(Duration::from_secs(30))
+            .args(args);
+        cmd
+    };
+
+    project(&["start", "--wait"]).assert().success();
+    let output = project(&["--json", "list"]).output().unwrap();
+    let resp: Response = serde_json::from_slice(&output.stdout).unwrap();
+    match resp {
+        Response::ProcessList { processes, .. } => {
+            let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(names, vec!["shop/web"]);
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    // Commands taking one process resolve the bare name in the namespace
+    project(&["info", "web"]).assert().success();
+    std::thread::sleep(Duration::from_millis(300));
+    project(&["log", "web", "--lines", "5"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello_from_shop"));
+
+    kill_daemon(&data_dir, &shop);
+}
+
+#[test]
+fn test_e2e_list_custom_columns_and_sort() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[web]
+command = "sleep 999"
+
+[api]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+
+    let output = pm3(&data_dir, work_dir)
+        .args(["list", "--sort", "name", "--columns", "name,status"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("status"), "stdout: {stdout}");
+    assert!(!stdout.contains("restarts"), "stdout: {stdout}");
+    assert!(
+        stdout.find("api").unwrap() < stdout.find("web").unwrap(),
+        "api should sort before web: {stdout}"
+    );
+    assert!(stdout.contains("2 processes: 2 online"), "stdout: {stdout}");
+
+    let output = pm3(&data_dir, work_dir)
+        .args(["list", "--group-by", "group"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("total"), "stdout: {stdout}");
+    assert!(!stdout.contains("api"), "stdout: {stdout}");
+
+    let output = pm3(&data_dir, work_dir)
+        .args(["--json", "list", "--sort", "name"])
+        .output()
+        .unwrap();
+    match parse_json_response(&output) {
+        Response::ProcessList { processes, .. } => {
+            let names: Vec<&str> = processes.iter().map(|p| p.name.as_str()).collect();
+            assert_eq!(names, vec!["api", "web"]);
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    kill_daemon(&data_dir, work_dir);
+}
+
+#[test]
+fn test_e2e_profiles_run_isolated_daemons() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[web]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+
+    pm3(&data_dir, work_dir)
+        .args(["--profile", "work", "start"])
+        .assert()
+        .success();
+
+    let output = pm3(&data_dir, work_dir)
+        .args(["--profile", "work", "--json", "list"])
+        .output()
+        .unwrap();
+    match parse_json_response(&output) {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            assert_eq!(processes[0].name, "web");
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+    assert!(
+        get_process_list(&data_dir, work_dir).is_empty(),
+        "default daemon should not see the work profile's processes"
+    );
+
+    let output = pm3(&data_dir, work_dir)
+        .args(["--json", "daemons"])
+        .output()
+        .unwrap();
+    let daemons: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let work = daemons
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["profile"] == "work")
+        .expect("work profile should be listed");
+    assert_eq!(work["running"], true);
+
+    let _ = pm3(&data_dir, work_dir)
+        .args(["--profile", "work", "kill"])
+        .output();
+    kill_daemon(&data_dir, work_dir);
+}
+
+#[test]
+fn test_e2e_daemon_logs_show_daemon_events() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[web]
+command = "sleep 999"
+cron_restart = "not a cron expression"
+"#,
+    )
+    .unwrap();
+
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+    std::thread::sleep(Duration::from_millis(300));
+
+    pm3(&data_dir, work_dir)
+        .arg("daemon-logs")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("INFO daemon started"))
+        .stdout(predicate::str::contains(
+            "WARN invalid cron_restart for 'web'",
+        ));
+
+    kill_daemon(&data_dir, work_dir);
+}
+
+#[test]
+fn test_e2e_history_records_operations() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[web]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+    pm3(&data_dir, work_dir)
+        .args(["stop", "web"])
+        .assert()
+        .success();
+    pm3(&data_dir, work_dir)
+        .args(["restart", "nope"])
+        .output()
+        .unwrap();
+
+    let output = pm3(&data_dir, work_dir)
+        .args(["--json", "history"])
+        .output()
+        .unwrap();
+    let entries: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let commands: Vec<&str> = entries
+        .iter()
+        .map(|e| e["command"].as_str().unwrap())
+        .collect();
+    // list requests from the auto-list after start/stop are not recorded
+    assert_eq!(commands, vec!["start all", "stop web", "restart nope"]);
+    assert_eq!(entries[1]["outcome"], "ok");
+    assert!(
+        entries[2]["outcome"]
+            .as_str()
+            .unwrap()
+            .starts_with("error: ")
+    );
+    #[cfg(unix)]
+    assert_eq!(
+        entries[0]["uid"].as_u64(),
+        Some(u64::from(nix::unistd::getuid().as_raw()))
+    );
+
+    pm3(&data_dir, work_dir)
+        .arg("history")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("stop web"));
+
+    kill_daemon(&data_dir, work_dir);
+}
+
+#[test]
+fn test_e2e_remote_client_over_tcp() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+    let client_dir = dir.path().join("client");
+    std::fs::create_dir_all(&data_dir).unwrap();
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let token_file = dir.path().join("token");
+    std::fs::write(&token_file, "s3cret\n").unwrap();
+    std::fs::write(
+        data_dir.join("settings.toml"),
+        format!(
+            "[remote]\nlisten = \"127.0.0.1:{port}\"\nauth_token_file = {:?}\n",
+            token_file.display().to_string()
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[web]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+
+    // A client with its own data dir reaches the daemon through PM3_REMOTE
+    let output = pm3(&client_dir, work_dir)
+        .env("PM3_REMOTE", format!("127.0.0.1:{port}"))
+        .env("PM3_AUTH_TOKEN", "s3cret")
+        .args(["--json", "list"])
+        .output()
+        .unwrap();
+    match parse_json_response(&output) {
+        Response::ProcessList { processes, .. } => {
+            assert_eq!(processes.len(), 1);
+            assert_eq!(processes[0].name, "web");
+        }
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+    assert!(
+        !client_dir.join("pm3.pid").exists(),
+        "remote client should not spawn a local daemon"
+    );
+
+    let output = pm3(&client_dir, work_dir)
+        .env("PM3_REMOTE", format!("127.0.0.1:{port}"))
+        .env("PM3_AUTH_TOKEN", "wrong")
+        .args(["--json", "list"])
+        .output()
+        .unwrap();
+    match parse_json_response(&output) {
+        Response::Error { message } => assert_eq!(message, "authentication failed"),
+        other => panic!("expected Error, got: {other:?}"),
+    }
+
+    // A client that connects and never sends its token is hung up on
+    let mut silent = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    silent
+        .set_read_timeout(Some(std::time::Duration::from_secs(15)))
+        .unwrap();
+    let started = std::time::Instant::now();
+    let mut buf = Vec::new();
+    let read = std::io::Read::read_to_end(&mut silent, &mut buf);
+    assert!(matches!(read, Ok(0)), "{read:?}");
+    assert!(started.elapsed() < std::time::Duration::from_secs(15));
+
+    kill_daemon(&data_dir, work_dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_e2e_host_runs_requests_through_ssh() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let server_dir = dir.path().join("server");
+    let client_dir = dir.path().join("client");
+
+    // Stand-in for ssh: drop `-T -- <host>` and have a shell run the
+    // command against the "server" data dir, as sshd would on the remote
+    // machine.
+    let fake_ssh = dir.path().join("fake-ssh");
+    std::fs::write(
+        &fake_ssh,
+        format!(
+            "#!/bin/sh\nshift 3\nPM3_DATA_DIR={:?} exec sh -c \"$*\"\n",
+            server_dir.display().to_string()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&fake_ssh, std::fs::Permissions::from_mode(0o755)).unwrap();
+    // A space in the remote path only survives the remote shell if quoted
+    let bin_dir = dir.path().join("remote bin");
+    std::fs::create_dir_all(&bin_dir).unwrap();
+    let pm3_bin = bin_dir.join("pm3");
+    std::os::unix::fs::symlink(assert_cmd::cargo::cargo_bin!("pm3"), &pm3_bin).unwrap();
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[web]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+
+    let over_ssh = |args: &[&str]| {
+        let mut cmd = pm3(&client_dir, work_dir);
+        cmd.env("PM3_SSH", &fake_ssh)
+            .env("PM3_SSH_BIN", &pm3_bin)
+            .args(["--host", "deploy@server"])
+            .args(args);
+        cmd
+    };
+
+    over_ssh(&["start"]).assert().success();
+    let output = over_ssh(&["--json", "list"]).output().unwrap();
+    match parse_json_response(&output) {
+        Response::ProcessList { processes, .. } => assert_eq!(processes[0].name, "web"),
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    // The daemon runs on the "server" side only
+    assert!(server_dir.join("pm3.pid").exists());
+    assert!(!client_dir.join("pm3.pid").exists());
+
+    kill_daemon(&server_dir, work_dir);
+}
+
+/// Writes a CA plus server (for 127.0.0.1) and client certificates signed by
+/// it into `dir`, as PEM files named `<ca|server|client>.{pem,key}`.
+fn write_test_certs(dir: &Path) {
+    use rcgen::{BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, KeyPair};
+
+    let ca_key = KeyPair::generate().unwrap();
+    let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+    std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+
+    for (name, san, usage) in [
+        ("server", "127.0.0.1", ExtendedKeyUsagePurpose::ServerAuth),
+        ("client", "laptop", ExtendedKeyUsagePurpose::ClientAuth),
+    ] {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec![san.to_string()]).unwrap();
+        params.extended_key_usages = vec![usage];
+        let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+        std::fs::write(dir.join(format!("{name}.pem")), cert.pem()).unwrap();
+        std::fs::write(dir.join(format!("{name}.key")), key.serialize_pem()).unwrap();
+    }
+}
+
+#[test]
+fn test_e2e_remote_client_over_tls_with_client_cert() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+    let client_dir = dir.path().join("client");
+    std::fs::create_dir_all(&data_dir).unwrap();
+    write_test_certs(work_dir);
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let file = |name: &str| format!("{:?}", work_dir.join(name).display().to_string());
+    std::fs::write(
+        data_dir.join("settings.toml"),
+        format!(
+            "[remote]\nlisten = \"127.0.0.1:{port}\"\ntls_cert = {}\ntls_key = {}\ntls_client_ca = {}\n",
+            file("server.pem"),
+            file("server.key"),
+            file("ca.pem"),
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[web]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+
+    let remote = |cmd: &mut Command| {
+        cmd.env("PM3_REMOTE", format!("127.0.0.1:{port}"))
+            .env("PM3_REMOTE_CA", work_dir.join("ca.pem"));
+    };
+
+    let mut cmd = pm3(&client_dir, work_dir);
+    remote(&mut cmd);
+    let output = cmd
+        .env("PM3_REMOTE_CERT", work_dir.join("client.pem"))
+        .env("PM3_REMOTE_KEY", work_dir.join("client.key"))
+        .args(["--json", "list"])
+        .output()
+        .unwrap();
+    match parse_json_response(&output) {
+        Response::ProcessList { processes, .. } => assert_eq!(processes[0].name, "web"),
+        other => panic!("expected ProcessList, got: {other:?}"),
+    }
+
+    // Without a client certificate the handshake is refused, token or not
+    let mut cmd = pm3(&client_dir, work_dir);
+    remote(&mut cmd);
+    cmd.env("PM3_AUTH_TOKEN", "anything")
+        .args(["--json", "list"])
+        .assert()
+        .failure();
+
+    kill_daemon(&data_dir, work_dir);
+}
+
 #[test]
 fn test_e2e_list_no_processes_shows_message() {
     let dir = TempDir::new().unwrap();
@@ -778,10 +1253,37 @@ command = "sleep 999"
     kill_daemon(&data_dir, work_dir);
 }
 
-// ── Item 14: Log command ────────────────────────────────────────────
-
+// ── Item 14: Log command ────────────────────────────────────────────
+
+#[test]
+fn test_e2e_log_shows_output() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[echoer]
+command = "sh -c 'echo hello_from_echoer'"
+"#,
+    )
+    .unwrap();
+
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+    std::thread::sleep(Duration::from_millis(500));
+
+    pm3(&data_dir, work_dir)
+        .args(["log", "echoer"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello_from_echoer"));
+
+    kill_daemon(&data_dir, work_dir);
+}
+
 #[test]
-fn test_e2e_log_shows_output() {
+fn test_e2e_log_json_is_ndjson() {
     let dir = TempDir::new().unwrap();
     let work_dir = dir.path();
     let data_dir = dir.path().join("data");
@@ -790,7 +1292,7 @@ fn test_e2e_log_shows_output() {
         work_dir.join("pm3.toml"),
         r#"
 [echoer]
-command = "sh -c 'echo hello_from_echoer'"
+command = "sh -c 'echo to_stdout; echo to_stderr >&2'"
 "#,
     )
     .unwrap();
@@ -798,11 +1300,27 @@ command = "sh -c 'echo hello_from_echoer'"
     pm3(&data_dir, work_dir).arg("start").assert().success();
     std::thread::sleep(Duration::from_millis(500));
 
-    pm3(&data_dir, work_dir)
-        .args(["log", "echoer"])
-        .assert()
-        .success()
-        .stdout(predicate::str::contains("hello_from_echoer"));
+    let output = pm3(&data_dir, work_dir)
+        .args(["--json", "log", "echoer"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap_or_else(|e| panic!("{l}: {e}")))
+        .collect();
+    let stream_of = |text: &str| {
+        records
+            .iter()
+            .find(|r| r["line"].as_str().is_some_and(|l| l.contains(text)))
+            .unwrap_or_else(|| panic!("no record for {text} in {stdout}"))
+    };
+    for (text, stream) in [("to_stdout", "stdout"), ("to_stderr", "stderr")] {
+        let record = stream_of(text);
+        assert_eq!(record["type"], "log_line");
+        assert_eq!(record["name"], "echoer");
+        assert_eq!(record["stream"], stream);
+    }
 
     kill_daemon(&data_dir, work_dir);
 }
@@ -922,6 +1440,52 @@ command = "sh -c 'echo web_flush_output'"
     kill_daemon(&data_dir, work_dir);
 }
 
+#[test]
+fn test_e2e_sigusr1_reopens_moved_logs() {
+    let dir = TempDir::new().unwrap();
+    let data_dir = dir.path().join("data");
+    let work_dir = dir.path();
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[worker]
+command = "sh -c 'while true; do echo tick; sleep 0.1; done'"
+"#,
+    )
+    .unwrap();
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+    wait_until_online(&data_dir, work_dir, "worker", 10);
+
+    // What logrotate does: move the logs aside, then signal the daemon
+    let stdout_log = data_dir.join("logs").join("worker-out.log");
+    let daemon_log = data_dir.join("daemon.log");
+    std::fs::rename(&stdout_log, data_dir.join("logs").join("worker-out.log.1")).unwrap();
+    std::fs::rename(&daemon_log, data_dir.join("daemon.log.1")).unwrap();
+    let daemon_pid: i32 = std::fs::read_to_string(data_dir.join("pm3.pid"))
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    nix::sys::signal::kill(
+        nix::unistd::Pid::from_raw(daemon_pid),
+        nix::sys::signal::Signal::SIGUSR1,
+    )
+    .unwrap();
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while !std::fs::read_to_string(&stdout_log).is_ok_and(|log| log.contains("tick")) {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "output was not logged to the reopened log"
+        );
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert!(daemon_log.exists(), "daemon.log should be reopened");
+
+    kill_daemon(&data_dir, work_dir);
+}
+
 #[test]
 fn test_e2e_flush_all() {
     let dir = TempDir::new().unwrap();
@@ -1355,6 +1919,49 @@ command = "sleep 999"
     kill_daemon(&data_dir, work_dir);
 }
 
+#[test]
+fn test_e2e_status_exit_codes() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[web]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+    wait_until_online(&data_dir, work_dir, "web", 5);
+
+    pm3(&data_dir, work_dir)
+        .args(["status", "web"])
+        .assert()
+        .code(0)
+        .stdout("online\n");
+
+    pm3(&data_dir, work_dir)
+        .args(["stop", "web"])
+        .assert()
+        .success();
+    pm3(&data_dir, work_dir)
+        .args(["status", "web"])
+        .assert()
+        .code(1)
+        .stdout("stopped\n");
+
+    pm3(&data_dir, work_dir)
+        .args(["status", "nonexistent"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("not found"));
+
+    kill_daemon(&data_dir, work_dir);
+}
+
 // ---------------------------------------------------------------------------
 // Process dependency E2E tests
 // ---------------------------------------------------------------------------
@@ -1529,6 +2136,258 @@ command = "sleep 999"
 // Save & resurrect E2E tests
 // ---------------------------------------------------------------------------
 
+#[test]
+fn test_e2e_save_if_running_does_not_start_daemon() {
+    let dir = TempDir::new().unwrap();
+    let data_dir = dir.path().join("data");
+    let work_dir = dir.path();
+
+    pm3(&data_dir, work_dir)
+        .args(["save", "--if-running"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("daemon is not running"));
+
+    assert!(
+        !data_dir.join("pm3.pid").exists(),
+        "save --if-running should not start a daemon"
+    );
+    assert!(!data_dir.join("dump.json").exists());
+}
+
+#[test]
+fn test_e2e_daemon_start_status_stop() {
+    let dir = TempDir::new().unwrap();
+    let data_dir = dir.path().join("data");
+    let work_dir = dir.path();
+
+    pm3(&data_dir, work_dir)
+        .args(["daemon", "status"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("daemon is not running"));
+
+    pm3(&data_dir, work_dir)
+        .args(["daemon", "start"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("daemon started"));
+    pm3(&data_dir, work_dir)
+        .args(["daemon", "start"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("daemon is already running"));
+
+    pm3(&data_dir, work_dir)
+        .args(["daemon", "status"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("daemon is running"));
+
+    pm3(&data_dir, work_dir)
+        .args(["daemon", "stop"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("daemon stopped"));
+    assert!(
+        !data_dir.join("pm3.pid").exists(),
+        "daemon stop should wait for the daemon to exit"
+    );
+
+    pm3(&data_dir, work_dir)
+        .args(["daemon", "stop"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("daemon is not running"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_e2e_daemon_upgrade_keeps_processes_running() {
+    let dir = TempDir::new().unwrap();
+    let data_dir = dir.path().join("data");
+    let work_dir = dir.path();
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[worker]
+command = "sh -c 'echo before_upgrade; sleep 3; echo after_upgrade; sleep 60'"
+"#,
+    )
+    .unwrap();
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+    wait_until_online(&data_dir, work_dir, "worker", 10);
+    let worker_pid = find_process_pid(&get_process_list(&data_dir, work_dir), "worker");
+    let daemon_pid = std::fs::read_to_string(data_dir.join("pm3.pid")).unwrap();
+
+    pm3(&data_dir, work_dir)
+        .args(["daemon", "upgrade"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("daemon upgraded"));
+
+    assert_eq!(
+        std::fs::read_to_string(data_dir.join("pm3.pid")).unwrap(),
+        daemon_pid,
+        "the upgraded daemon should keep its pid"
+    );
+    let processes = get_process_list(&data_dir, work_dir);
+    assert_eq!(find_process_pid(&processes, "worker"), worker_pid);
+    assert_eq!(processes[0].status, ProcessStatus::Online);
+    let output = pm3(&data_dir, work_dir)
+        .args(["--json", "ping"])
+        .output()
+        .unwrap();
+    match parse_json_response(&output) {
+        Response::DaemonInfo { info } => assert_eq!(info.upgrades, 1),
+        other => panic!("expected DaemonInfo, got: {other:?}"),
+    }
+
+    // Output written after the handover still reaches the log
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let output = pm3(&data_dir, work_dir)
+            .args(["log", "worker"])
+            .output()
+            .unwrap();
+        if String::from_utf8_lossy(&output.stdout).contains("after_upgrade") {
+            break;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "output after the upgrade was not logged"
+        );
+        std::thread::sleep(Duration::from_millis(200));
+    }
+
+    // The adopted process is still reaped once stopped
+    pm3(&data_dir, work_dir)
+        .args(["stop", "worker"])
+        .assert()
+        .success();
+    #[cfg(target_os = "linux")]
+    assert!(
+        !Path::new(&format!("/proc/{worker_pid}")).exists(),
+        "the stopped worker should not be left a zombie"
+    );
+
+    kill_daemon(&data_dir, work_dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_e2e_restored_survivors_keep_logging_after_daemon_crash() {
+    let dir = TempDir::new().unwrap();
+    let data_dir = dir.path().join("data");
+    let work_dir = dir.path();
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[worker]
+command = "sh -c 'i=0; while true; do i=$((i+1)); echo tick_$i; sleep 0.1; done'"
+"#,
+    )
+    .unwrap();
+    pm3(&data_dir, work_dir).arg("start").assert().success();
+    wait_until_online(&data_dir, work_dir, "worker", 10);
+    let worker_pid = find_process_pid(&get_process_list(&data_dir, work_dir), "worker");
+    pm3(&data_dir, work_dir).arg("save").assert().success();
+
+    let daemon_pid: i32 = std::fs::read_to_string(data_dir.join("pm3.pid"))
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    let daemon_pid = nix::unistd::Pid::from_raw(daemon_pid);
+    nix::sys::signal::kill(daemon_pid, nix::sys::signal::Signal::SIGKILL).unwrap();
+    // Until it is reaped the killed daemon still counts as running
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while nix::sys::signal::kill(daemon_pid, None).is_ok() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "daemon was not reaped"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    std::thread::sleep(Duration::from_millis(300));
+    let ticks = || {
+        std::fs::read_to_string(data_dir.join("logs").join("worker-out.log"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("tick_")?.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0)
+    };
+    let at_crash = ticks();
+    assert!(
+        at_crash > 0,
+        "the worker should have logged before the crash"
+    );
+
+    // The next command starts a daemon, which adopts the still-running worker
+    wait_until_online(&data_dir, work_dir, "worker", 10);
+    assert_eq!(
+        find_process_pid(&get_process_list(&data_dir, work_dir), "worker"),
+        worker_pid,
+        "the worker should be adopted, not restarted"
+    );
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let output = pm3(&data_dir, work_dir)
+            .args(["log", "worker", "--lines", "5"])
+            .output()
+            .unwrap();
+        let logged = String::from_utf8_lossy(&output.stdout);
+        if logged.contains(&format!("tick_{}", at_crash + 20)) {
+            break;
+        }
+        assert!(
+            std::time::Instant::now() < deadline,
+            "output after the crash was not logged; last tick {}, at crash {at_crash}",
+            ticks()
+        );
+        std::thread::sleep(Duration::from_millis(200));
+    }
+
+    kill_daemon(&data_dir, work_dir);
+}
+
+#[test]
+fn test_e2e_no_autostart_fails_instead_of_starting_daemon() {
+    let dir = TempDir::new().unwrap();
+    let data_dir = dir.path().join("data");
+    let work_dir = dir.path();
+
+    pm3(&data_dir, work_dir)
+        .env("PM3_NO_AUTOSTART", "1")
+        .arg("list")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("daemon is not running"));
+    pm3(&data_dir, work_dir)
+        .args(["list", "--no-autostart"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("autostart is off"));
+    assert!(
+        !data_dir.join("pm3.pid").exists(),
+        "no daemon should have been started"
+    );
+
+    // --autostart wins over the environment
+    pm3(&data_dir, work_dir)
+        .env("PM3_NO_AUTOSTART", "1")
+        .args(["list", "--autostart"])
+        .assert()
+        .success();
+    assert!(data_dir.join("pm3.pid").exists());
+
+    kill_daemon(&data_dir, work_dir);
+}
+
 #[test]
 fn test_e2e_save_creates_snapshot_file() {
     let dir = TempDir::new().unwrap();
@@ -1562,8 +2421,8 @@ command = "sleep 888"
     assert!(dump_path.exists(), "dump.json should exist after save");
 
     let data = std::fs::read_to_string(&dump_path).unwrap();
-    let entries: serde_json::Value = serde_json::from_str(&data).unwrap();
-    let arr = entries.as_array().unwrap();
+    let dump: serde_json::Value = serde_json::from_str(&data).unwrap();
+    let arr = dump["processes"].as_array().unwrap();
     assert_eq!(arr.len(), 2, "dump should contain 2 processes");
 
     let names: Vec<&str> = arr.iter().map(|e| e["name"].as_str().unwrap()).collect();
@@ -1773,6 +2632,56 @@ command = "sh -c 'exit 1'"
     kill_daemon(&data_dir, work_dir);
 }
 
+#[test]
+fn test_e2e_run_task_streams_output_and_exit_code() {
+    let dir = TempDir::new().unwrap();
+    let work_dir = dir.path();
+    let data_dir = dir.path().join("data");
+
+    std::fs::write(
+        work_dir.join("pm3.toml"),
+        r#"
+[migrate]
+command = "sh -c 'sleep 0.2; echo migrating; exit 3'"
+kind = "task"
+
+[seed]
+command = "echo seeded"
+kind = "task"
+
+[web]
+command = "sleep 999"
+"#,
+    )
+    .unwrap();
+
+    pm3(&data_dir, work_dir)
+        .args(["run", "migrate"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("migrating"));
+
+    pm3(&data_dir, work_dir)
+        .args(["run", "seed"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("seeded"))
+        .stdout(predicate::str::contains("task 'seed' succeeded"));
+
+    pm3(&data_dir, work_dir)
+        .args(["run", "web"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not a task"));
+
+    let processes = get_process_list(&data_dir, work_dir);
+    let status = |name: &str| processes.iter().find(|p| p.name == name).unwrap().status;
+    assert_eq!(status("migrate"), ProcessStatus::Failed);
+    assert_eq!(status("seed"), ProcessStatus::Succeeded);
+
+    kill_daemon(&data_dir, work_dir);
+}
+
 // ---------------------------------------------------------------------------
 // Init command E2E tests
 // ---------------------------------------------------------------------------
@@ -1901,6 +2810,19 @@ fn expected_service_path() -> std::path::PathBuf {
         .join(".config/systemd/user/pm3.service")
 }
 
+/// Runs `pm3 startup` or `pm3 unstartup` against the per-user service, so
+/// the tests never touch `/etc/init.d` on hosts without systemd.
+fn pm3_startup(command: &str) -> Command {
+    let platform = if cfg!(target_os = "macos") {
+        "launchd"
+    } else {
+        "systemd"
+    };
+    let mut cmd = pm3_standalone();
+    cmd.args([command, "--platform", platform]);
+    cmd
+}
+
 #[test]
 fn test_e2e_startup_creates_service_file() {
     let path = expected_service_path();
@@ -1908,8 +2830,7 @@ fn test_e2e_startup_creates_service_file() {
     // Clean up from any prior runs
     let _ = std::fs::remove_file(&path);
 
-    pm3_standalone()
-        .arg("startup")
+    pm3_startup("startup")
         .assert()
         .success()
         .stderr(predicate::str::contains("Service installed"));
@@ -1922,8 +2843,8 @@ fn test_e2e_startup_creates_service_file() {
 
     let content = std::fs::read_to_string(&path).unwrap();
     assert!(
-        content.contains("--daemon"),
-        "service file should contain --daemon arg"
+        content.contains("--foreground"),
+        "service file should run the daemon in the foreground"
     );
 
     #[cfg(target_os = "macos")]
@@ -1959,11 +2880,10 @@ fn test_e2e_unstartup_removes_service_file() {
 
     // Ensure the file exists first
     let _ = std::fs::remove_file(&path);
-    pm3_standalone().arg("startup").assert().success();
+    pm3_startup("startup").assert().success();
     assert!(path.exists(), "service file should exist before unstartup");
 
-    pm3_standalone()
-        .arg("unstartup")
+    pm3_startup("unstartup")
         .assert()
         .success()
         .stderr(predicate::str::contains("Service removed"));
@@ -1981,8 +2901,7 @@ fn test_e2e_unstartup_no_file_prints_message() {
     // Make sure no service file exists
     let _ = std::fs::remove_file(&path);
 
-    pm3_standalone()
-        .arg("unstartup")
+    pm3_startup("unstartup")
         .assert()
         .success()
         .stderr(predicate::str::contains("No service file found"));
@@ -1994,8 +2913,8 @@ fn test_e2e_startup_idempotent() {
     let _ = std::fs::remove_file(&path);
 
     // Run startup twice — second should overwrite without error
-    pm3_standalone().arg("startup").assert().success();
-    pm3_standalone().arg("startup").assert().success();
+    pm3_startup("startup").assert().success();
+    pm3_startup("startup").assert().success();
 
     assert!(
         path.exists(),